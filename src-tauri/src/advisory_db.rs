@@ -0,0 +1,557 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use semver::{BuildMetadata, Comparator, Op, Prerelease, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::security_scanner::{DependencyVulnerability, VulnerabilityResult, VulnerabilitySeverity};
+
+/// Days after which `AdvisoryDbInfo` reports the local database as stale
+pub const ADVISORY_DB_MAX_AGE_DAYS: i64 = 7;
+
+/// How long downloading the advisory feed may take
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Package registry an advisory or locked package belongs to, named as in OSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Ecosystem {
+    #[serde(rename = "crates.io")]
+    Cargo,
+    #[serde(rename = "npm")]
+    Npm,
+    #[serde(rename = "PyPI")]
+    PyPI,
+}
+
+impl Ecosystem {
+    /// Package names as the registry compares them; PyPI ignores case and `-`/`_`/`.` runs
+    fn normalize(self, name: &str) -> String {
+        match self {
+            Ecosystem::PyPI => name
+                .to_lowercase()
+                .split(['-', '_', '.'])
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-"),
+            Ecosystem::Cargo | Ecosystem::Npm => name.to_string(),
+        }
+    }
+}
+
+/// A package version pinned by a lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    pub version: String,
+}
+
+/// Registry packages in a `Cargo.lock`; path and git dependencies have no advisories
+pub fn parse_cargo_lock(text: &str) -> Result<Vec<LockedPackage>> {
+    #[derive(Deserialize)]
+    struct Lock {
+        #[serde(default)]
+        package: Vec<Package>,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        name: String,
+        version: String,
+        source: Option<String>,
+    }
+
+    let lock: Lock = toml::from_str(text).context("Invalid Cargo.lock")?;
+    Ok(lock
+        .package
+        .into_iter()
+        .filter(|package| package.source.as_deref().is_some_and(|source| source.starts_with("registry+")))
+        .map(|package| LockedPackage { ecosystem: Ecosystem::Cargo, name: package.name, version: package.version })
+        .collect())
+}
+
+/// Installed packages in a `package-lock.json`, from the `packages` map (lockfile v2/v3)
+/// or the nested `dependencies` tree (v1)
+pub fn parse_package_lock(text: &str) -> Result<Vec<LockedPackage>> {
+    let lock: serde_json::Value = serde_json::from_str(text).context("Invalid package-lock.json")?;
+    let mut found = BTreeSet::new();
+
+    if let Some(packages) = lock.get("packages").and_then(|packages| packages.as_object()) {
+        for (path, entry) in packages {
+            // "" is the project itself; links point at workspace folders
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if entry.get("link").and_then(|link| link.as_bool()) == Some(true) {
+                continue;
+            }
+            if let Some(version) = entry.get("version").and_then(|version| version.as_str()) {
+                found.insert(LockedPackage { ecosystem: Ecosystem::Npm, name: name.to_string(), version: version.to_string() });
+            }
+        }
+    } else if let Some(dependencies) = lock.get("dependencies") {
+        collect_v1_dependencies(dependencies, &mut found);
+    }
+    Ok(found.into_iter().collect())
+}
+
+fn collect_v1_dependencies(dependencies: &serde_json::Value, found: &mut BTreeSet<LockedPackage>) {
+    let Some(dependencies) = dependencies.as_object() else {
+        return;
+    };
+    for (name, entry) in dependencies {
+        if let Some(version) = entry.get("version").and_then(|version| version.as_str()) {
+            found.insert(LockedPackage { ecosystem: Ecosystem::Npm, name: name.clone(), version: version.to_string() });
+        }
+        if let Some(nested) = entry.get("dependencies") {
+            collect_v1_dependencies(nested, found);
+        }
+    }
+}
+
+/// `name==version` pins in a `requirements.txt`. Unpinned requirements are skipped
+/// because the installed version cannot be known from the file.
+pub fn parse_requirements(text: &str) -> Vec<LockedPackage> {
+    // Hash-pinned requirements continue onto following lines with a trailing backslash
+    let joined = text.replace("\\\r\n", " ").replace("\\\n", " ");
+    joined
+        .lines()
+        .filter_map(|line| {
+            let line = match line.find(" #") {
+                Some(comment) => &line[..comment],
+                None => line,
+            };
+            // Per-requirement options such as `--hash=sha256:...` follow the requirement
+            let line = match line.find(" --") {
+                Some(options) => &line[..options],
+                None => line,
+            };
+            // Environment markers follow a semicolon
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+                return None;
+            }
+            let (name, version) = line.split_once("==")?;
+            let name = name.split('[').next().unwrap_or_default().trim();
+            let version = version.trim_start_matches('=').trim();
+            if name.is_empty() || version.is_empty() || version.contains('*') {
+                return None;
+            }
+            Some(LockedPackage { ecosystem: Ecosystem::PyPI, name: name.to_string(), version: version.to_string() })
+        })
+        .collect()
+}
+
+/// One advisory in the RustSec style: every version not matched by `patched` or
+/// `unaffected` is vulnerable, so an advisory with neither affects all versions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Advisory {
+    /// Advisory id, e.g. RUSTSEC-2021-0001 or GHSA-xxxx-xxxx-xxxx
+    pub id: String,
+    pub package: String,
+    pub ecosystem: Ecosystem,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Other ids for the same issue, including CVEs
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub severity: Option<VulnerabilitySeverity>,
+    #[serde(default)]
+    pub cvss_score: Option<f32>,
+    /// Semver requirements for fixed versions, e.g. ">= 1.2.3"
+    #[serde(default)]
+    pub patched: Vec<String>,
+    /// Semver requirements for versions that never had the issue
+    #[serde(default)]
+    pub unaffected: Vec<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl Advisory {
+    pub fn cve_id(&self) -> Option<&str> {
+        self.aliases.iter().map(String::as_str).find(|alias| alias.starts_with("CVE-"))
+    }
+
+    pub fn affects(&self, version: &Version) -> bool {
+        let matches_any = |reqs: &[String]| reqs.iter().filter_map(|req| VersionReq::parse(req).ok()).any(|req| req_contains(&req, version));
+        !matches_any(&self.patched) && !matches_any(&self.unaffected)
+    }
+
+    /// The lowest patched version above `version`
+    pub fn fixed_version(&self, version: &Version) -> Option<Version> {
+        self.patched
+            .iter()
+            .filter_map(|req| VersionReq::parse(req).ok())
+            .flat_map(|req| req.comparators)
+            .filter(|cmp| matches!(cmp.op, Op::GreaterEq | Op::Exact | Op::Caret | Op::Tilde | Op::Wildcard))
+            .map(|cmp| lower_bound(&cmp))
+            .filter(|fixed| fixed > version)
+            .min()
+    }
+
+    /// Human-readable vulnerable range, e.g. "< 1.2.3" or ">= 0.5.0, < 1.2.3"
+    pub fn vulnerable_range(&self) -> String {
+        let single = |reqs: &[String], op: Op| -> Option<Version> {
+            let [req] = reqs else { return None };
+            let req = VersionReq::parse(req).ok()?;
+            let [cmp] = req.comparators.as_slice() else { return None };
+            (cmp.op == op).then(|| lower_bound(cmp))
+        };
+        if self.patched.is_empty() && self.unaffected.is_empty() {
+            return "*".to_string();
+        }
+        match (single(&self.patched, Op::GreaterEq), self.unaffected.is_empty(), single(&self.unaffected, Op::Less)) {
+            (Some(fixed), true, _) => format!("< {}", fixed),
+            (Some(fixed), false, Some(introduced)) => format!(">= {}, < {}", introduced, fixed),
+            _ => {
+                let excluded: Vec<&str> = self.patched.iter().chain(&self.unaffected).map(String::as_str).collect();
+                format!("all versions except {}", excluded.join(" | "))
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        for req in self.patched.iter().chain(&self.unaffected) {
+            VersionReq::parse(req).map_err(|e| anyhow!("Advisory {} has an invalid version range '{}': {}", self.id, req, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a locked version. PyPI versions such as "2.0" or "1.0rc1" are read leniently as
+/// "2.0.0" and "1.0.0-rc1"; build metadata is dropped because it has no precedence.
+pub fn parse_version(ecosystem: Ecosystem, text: &str) -> Option<Version> {
+    let text = text.trim().trim_start_matches(['v', '=']);
+    let mut version = match Version::parse(text) {
+        Ok(version) => version,
+        Err(_) if ecosystem == Ecosystem::PyPI => parse_pep440(text)?,
+        Err(_) => return None,
+    };
+    version.build = BuildMetadata::EMPTY;
+    Some(version)
+}
+
+fn parse_pep440(text: &str) -> Option<Version> {
+    let release_end = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (release, suffix) = text.split_at(release_end);
+    let mut parts = release.trim_end_matches('.').split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let suffix = suffix.trim_start_matches(['.', '-', '_']).to_lowercase();
+    let mut version = Version::new(major, minor, patch);
+    // Post-releases follow their release; only pre-releases and dev builds sort before it
+    if !suffix.is_empty() && !suffix.starts_with("post") {
+        version.pre = Prerelease::new(&suffix.replace(['_', '-'], ".")).ok()?;
+    }
+    Some(version)
+}
+
+/// Whether `req` covers `version` by precedence alone. Unlike `VersionReq::matches`,
+/// a pre-release such as 2.0.0-alpha satisfies ">= 1.2.3", which is what a patched
+/// range means; a pre-release of the fixed version itself still sorts below it.
+fn req_contains(req: &VersionReq, version: &Version) -> bool {
+    req.comparators.iter().all(|cmp| comparator_contains(cmp, version))
+}
+
+fn comparator_contains(cmp: &Comparator, version: &Version) -> bool {
+    let lower = lower_bound(cmp);
+    let exact = cmp.patch.is_some();
+    match cmp.op {
+        Op::Exact | Op::Wildcard if exact => *version == lower,
+        Op::Exact | Op::Wildcard => lower <= *version && *version < partial_upper(cmp),
+        Op::Greater if exact => *version > lower,
+        Op::Greater => *version >= partial_upper(cmp),
+        Op::GreaterEq => *version >= lower,
+        Op::Less => *version < lower,
+        Op::LessEq if exact => *version <= lower,
+        Op::LessEq => *version < partial_upper(cmp),
+        Op::Tilde => {
+            let upper = match cmp.minor {
+                Some(minor) => first_of(cmp.major, minor + 1, 0),
+                None => first_of(cmp.major + 1, 0, 0),
+            };
+            lower <= *version && *version < upper
+        }
+        Op::Caret => {
+            let upper = match (cmp.major, cmp.minor, cmp.patch) {
+                (major, _, _) if major > 0 => first_of(major + 1, 0, 0),
+                (_, None, _) => first_of(1, 0, 0),
+                (_, Some(minor), _) if minor > 0 => first_of(0, minor + 1, 0),
+                (_, Some(_), None) => first_of(0, 1, 0),
+                (_, Some(_), Some(patch)) => first_of(0, 0, patch + 1),
+            };
+            lower <= *version && *version < upper
+        }
+        _ => false,
+    }
+}
+
+fn lower_bound(cmp: &Comparator) -> Version {
+    Version {
+        major: cmp.major,
+        minor: cmp.minor.unwrap_or(0),
+        patch: cmp.patch.unwrap_or(0),
+        pre: cmp.pre.clone(),
+        build: BuildMetadata::EMPTY,
+    }
+}
+
+/// First version past a partial comparator such as "1.2" or "1"
+fn partial_upper(cmp: &Comparator) -> Version {
+    match cmp.minor {
+        Some(minor) => first_of(cmp.major, minor + 1, 0),
+        None => first_of(cmp.major + 1, 0, 0),
+    }
+}
+
+/// The earliest possible version in a release line, below all of its pre-releases
+fn first_of(major: u64, minor: u64, patch: u64) -> Version {
+    Version { pre: Prerelease::new("0").expect("valid pre-release"), ..Version::new(major, minor, patch) }
+}
+
+/// The locally cached advisories
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdvisoryDatabase {
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Where the advisories were last fetched from
+    pub source: Option<String>,
+    pub advisories: Vec<Advisory>,
+}
+
+/// Age and size of the local advisory database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdvisoryDbInfo {
+    pub path: Option<String>,
+    pub source: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub age_seconds: Option<i64>,
+    pub advisory_count: usize,
+    /// Never updated, or older than `ADVISORY_DB_MAX_AGE_DAYS`
+    pub stale: bool,
+}
+
+/// An advisory feed: either a bare list or a database document
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AdvisoryFeed {
+    List(Vec<Advisory>),
+    Database(AdvisoryDatabase),
+}
+
+impl AdvisoryDatabase {
+    /// The database at `path`, or an empty one if it has not been downloaded yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).with_context(|| format!("Invalid advisory database {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write beside the old copy so a failed write never leaves a truncated database
+        let partial = path.with_extension("json.partial");
+        tokio::fs::write(&partial, serde_json::to_vec(self)?).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    /// Read advisories from an http(s) URL or a local file, rejecting the whole feed
+    /// if any advisory has a range that does not parse
+    pub async fn fetch(source: &str) -> Result<Self> {
+        let body = if source.starts_with("http://") || source.starts_with("https://") {
+            let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+            client.get(source).send().await?.error_for_status()?.text().await?
+        } else {
+            let path = source.strip_prefix("file://").unwrap_or(source);
+            tokio::fs::read_to_string(path).await.with_context(|| format!("Failed to read advisory feed {}", path))?
+        };
+        let advisories = match serde_json::from_str(&body).context("Invalid advisory feed")? {
+            AdvisoryFeed::List(advisories) => advisories,
+            AdvisoryFeed::Database(database) => database.advisories,
+        };
+        for advisory in &advisories {
+            advisory.validate()?;
+        }
+        Ok(Self { updated_at: Some(Utc::now()), source: Some(source.to_string()), advisories })
+    }
+
+    pub fn info(&self, path: Option<&PathBuf>) -> AdvisoryDbInfo {
+        let age_seconds = self.updated_at.map(|updated| (Utc::now() - updated).num_seconds());
+        AdvisoryDbInfo {
+            path: path.map(|path| path.to_string_lossy().to_string()),
+            source: self.source.clone(),
+            updated_at: self.updated_at,
+            age_seconds,
+            advisory_count: self.advisories.len(),
+            stale: age_seconds.is_none_or(|age| age > ADVISORY_DB_MAX_AGE_DAYS * 24 * 60 * 60),
+        }
+    }
+
+    /// Vulnerable packages among `packages`, found in `lockfile`
+    pub fn check(&self, packages: &[LockedPackage], lockfile: &str) -> Vec<DependencyVulnerability> {
+        let mut vulnerable = Vec::new();
+        for package in packages {
+            let Some(version) = parse_version(package.ecosystem, &package.version) else {
+                tracing::debug!("Skipping {} {}: unrecognized version", package.name, package.version);
+                continue;
+            };
+            let name = package.ecosystem.normalize(&package.name);
+            for advisory in &self.advisories {
+                if advisory.ecosystem != package.ecosystem || advisory.ecosystem.normalize(&advisory.package) != name || !advisory.affects(&version) {
+                    continue;
+                }
+                vulnerable.push(dependency_vulnerability(advisory, package, &version, lockfile));
+            }
+        }
+        vulnerable
+    }
+}
+
+fn dependency_vulnerability(advisory: &Advisory, package: &LockedPackage, version: &Version, lockfile: &str) -> DependencyVulnerability {
+    let fixed_version = advisory.fixed_version(version).map(|fixed| fixed.to_string());
+    let remediation = match &fixed_version {
+        Some(fixed) => format!("Upgrade {} to {} or later", package.name, fixed),
+        None => format!("No patched version of {} is available; consider replacing it", package.name),
+    };
+    let mut description = format!("{} {} is affected by {}", package.name, package.version, advisory.id);
+    if !advisory.description.is_empty() {
+        description = format!("{}: {}", description, advisory.description);
+    }
+    if let Some(url) = &advisory.url {
+        description = format!("{} ({})", description, url);
+    }
+    DependencyVulnerability {
+        package_name: package.name.clone(),
+        current_version: package.version.clone(),
+        vulnerable_version_range: advisory.vulnerable_range(),
+        fixed_version,
+        vulnerability: VulnerabilityResult {
+            id: uuid::Uuid::new_v4().to_string(),
+            severity: advisory.severity.clone().unwrap_or(VulnerabilitySeverity::Medium),
+            title: format!("{}: {}", advisory.id, advisory.title),
+            description,
+            affected_files: vec![lockfile.to_string()],
+            cve_id: advisory.cve_id().map(str::to_string),
+            cvss_score: advisory.cvss_score,
+            remediation: Some(remediation),
+            detected_at: Utc::now(),
+            rule_id: Some(advisory.id.clone()),
+            line: None,
+            matched_text: Some(format!("{} {}", package.name, package.version)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(patched: &[&str], unaffected: &[&str]) -> Advisory {
+        Advisory {
+            id: "RUSTSEC-2023-0001".to_string(),
+            package: "tokio".to_string(),
+            ecosystem: Ecosystem::Cargo,
+            title: "reject_remote_clients configuration corruption".to_string(),
+            description: String::new(),
+            aliases: vec!["GHSA-7rrj-xr53-82p7".to_string(), "CVE-2023-22466".to_string()],
+            severity: Some(VulnerabilitySeverity::High),
+            cvss_score: None,
+            patched: patched.iter().map(|req| req.to_string()).collect(),
+            unaffected: unaffected.iter().map(|req| req.to_string()).collect(),
+            url: None,
+        }
+    }
+
+    fn version(text: &str) -> Version {
+        parse_version(Ecosystem::Cargo, text).unwrap()
+    }
+
+    #[test]
+    fn test_version_ranges_follow_precedence() {
+        let advisory = advisory(&[">= 1.18.4, < 1.19.0", "~1.20.3", ">= 1.23.1"], &["< 1.7.0"]);
+        for vulnerable in ["1.7.0", "1.18.3", "1.19.5", "1.20.2", "1.21.0", "1.23.0", "1.23.1-rc.1", "1.18.4-beta"] {
+            assert!(advisory.affects(&version(vulnerable)), "{} should be vulnerable", vulnerable);
+        }
+        for safe in ["1.6.9", "1.18.4", "1.18.9", "1.20.3", "1.20.9", "1.23.1", "2.0.0-alpha.1", "1.23.1+build.5"] {
+            assert!(!advisory.affects(&version(safe)), "{} should be safe", safe);
+        }
+        assert_eq!(advisory.fixed_version(&version("1.19.5")), Some(version("1.20.3")));
+        assert_eq!(advisory.fixed_version(&version("1.23.0")), Some(version("1.23.1")));
+
+        let simple = self::advisory(&[">= 1.2.3"], &["< 0.5"]);
+        assert_eq!(simple.vulnerable_range(), ">= 0.5.0, < 1.2.3");
+        assert_eq!(self::advisory(&[">= 1.2.3"], &[]).vulnerable_range(), "< 1.2.3");
+        assert_eq!(self::advisory(&[], &[]).vulnerable_range(), "*");
+        assert!(self::advisory(&[], &[]).affects(&version("0.0.1")));
+        let caret = self::advisory(&["^0.2.3"], &[]);
+        assert!(!caret.affects(&version("0.2.9")));
+        assert!(caret.affects(&version("0.2.2")));
+        assert!(caret.affects(&version("0.3.0")));
+
+        assert_eq!(parse_version(Ecosystem::PyPI, "2.0"), Some(version("2.0.0")));
+        assert_eq!(parse_version(Ecosystem::PyPI, "1.0rc1"), Some(version("1.0.0-rc1")));
+        assert_eq!(parse_version(Ecosystem::PyPI, "2.25.1.post1"), Some(version("2.25.1")));
+        assert_eq!(parse_version(Ecosystem::PyPI, "1.2.3.4"), None);
+    }
+
+    #[test]
+    fn test_lockfile_flags_vulnerable_version_but_not_patched_one() {
+        let cargo_lock = r#"
+version = 3
+
+[[package]]
+name = "nexus"
+version = "0.1.0"
+
+[[package]]
+name = "tokio"
+version = "1.18.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "tokio"
+version = "1.18.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        let database = AdvisoryDatabase { advisories: vec![advisory(&[">= 1.18.4, < 1.19.0", ">= 1.23.1"], &[])], ..AdvisoryDatabase::default() };
+        let packages = parse_cargo_lock(cargo_lock).unwrap();
+        assert_eq!(packages.len(), 2);
+
+        let found = database.check(&packages, "Cargo.lock");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].current_version, "1.18.3");
+        assert_eq!(found[0].fixed_version.as_deref(), Some("1.18.4"));
+        assert_eq!(found[0].vulnerability.cve_id.as_deref(), Some("CVE-2023-22466"));
+        assert_eq!(found[0].vulnerability.rule_id.as_deref(), Some("RUSTSEC-2023-0001"));
+
+        let package_lock = r#"{"lockfileVersion": 3, "packages": {
+            "": {"name": "web"},
+            "node_modules/lodash": {"version": "4.17.20"},
+            "node_modules/a/node_modules/@scope/lodash": {"version": "1.0.0"},
+            "packages/ui": {"version": "0.0.1", "link": true}
+        }}"#;
+        let npm = parse_package_lock(package_lock).unwrap();
+        assert_eq!(npm.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["@scope/lodash", "lodash"]);
+
+        let requirements = "requests[socks]==2.19.1 ; python_version > '3'\nDjango>=3.2\n-r base.txt\nPyYAML==5.4 # pinned\n\
+            urllib3==1.26.4 \\\n    --hash=sha256:aaaa \\\n    --hash=sha256:bbbb\nidna==2.10 --hash=sha256:cccc\n";
+        let python = parse_requirements(requirements);
+        assert_eq!(
+            python.iter().map(|p| (p.name.as_str(), p.version.as_str())).collect::<Vec<_>>(),
+            vec![("requests", "2.19.1"), ("PyYAML", "5.4"), ("urllib3", "1.26.4"), ("idna", "2.10")]
+        );
+        let pyyaml = Advisory { package: "pyyaml".to_string(), ecosystem: Ecosystem::PyPI, ..advisory(&[">= 5.4"], &[]) };
+        assert!(AdvisoryDatabase { advisories: vec![pyyaml], ..AdvisoryDatabase::default() }.check(&python, "requirements.txt").is_empty());
+    }
+}
@@ -1,12 +1,23 @@
 use anyhow::{Context, Result};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
-use std::sync::Arc;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, RwLock};
+use tokio_util::sync::CancellationToken;
 
 use crate::ai_optimized::{OptimizedAIService, AIRequest, RequestPriority};
+use crate::cache::{Cache, CacheConfig, CacheMetrics};
 use crate::local_recall::LocalRecallClient;
+use crate::redaction::{RedactionConfig, Redactor};
+use crate::secret_store::{is_secret_ref, SecretStore};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AIConfig {
@@ -15,6 +26,155 @@ pub struct AIConfig {
     pub timeout_seconds: u64,
     pub temperature: f32,
     pub max_tokens: u32,
+    /// Consecutive failures before the AI circuit breaker opens
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub circuit_failure_threshold: u32,
+    /// How long the breaker stays open before letting a probe request through
+    #[serde(default = "default_circuit_cooldown_seconds")]
+    pub circuit_cooldown_seconds: u64,
+    /// Attempts per Ollama HTTP call, including the first; all share `timeout_seconds`
+    #[serde(default = "default_retry_max_attempts")]
+    pub retry_max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    #[serde(default = "default_retry_backoff_base_ms")]
+    pub retry_backoff_base_ms: u64,
+    #[serde(default)]
+    pub cache: ResponseCacheConfig,
+    /// Processes listed in the terminal context sent with AI requests
+    #[serde(default = "default_context_process_limit")]
+    pub context_process_limit: usize,
+    #[serde(default)]
+    pub queue_weights: QueueWeightsConfig,
+    #[serde(default)]
+    pub pool: ConnectionPoolConfig,
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Bearer token for OpenAI-compatible endpoints, as a `secret://` reference
+    #[serde(default, skip_serializing_if = "Option::is_none", serialize_with = "crate::secret_store::serialize_secret_ref")]
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProvider {
+    /// `POST {url}/api/embeddings`, one text per call
+    #[default]
+    Ollama,
+    /// `POST {url}/v1/embeddings` with a batch of texts; sends `ai.api_key` if set, resolved through the secret store
+    OpenAI,
+}
+
+/// The `[ai.embeddings]` section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingsConfig {
+    pub provider: EmbeddingProvider,
+    /// Defaults to `ollama_url`
+    pub url: Option<String>,
+    pub model: String,
+    /// Texts per request for OpenAI, concurrent requests for Ollama; provider default if unset
+    pub batch_size: Option<usize>,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            provider: EmbeddingProvider::Ollama,
+            url: None,
+            model: "nomic-embed-text".to_string(),
+            batch_size: None,
+        }
+    }
+}
+
+impl EmbeddingsConfig {
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(match self.provider {
+            EmbeddingProvider::Ollama => 8,
+            EmbeddingProvider::OpenAI => 256,
+        })
+    }
+}
+
+/// The `[ai.pool]` section: bounds for the adaptively sized pool of concurrent AI requests
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionPoolConfig {
+    pub min_connections: usize,
+    pub max_connections: usize,
+    /// Average latency above which the pool stops growing and shrinks instead
+    pub target_latency_ms: u64,
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            min_connections: 2,
+            max_connections: 10,
+            target_latency_ms: 10_000,
+        }
+    }
+}
+
+/// The `[ai.cache]` section: how many replies to keep and for how long
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseCacheConfig {
+    pub enabled: bool,
+    pub max_entries: usize,
+    pub ttl_seconds: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: 256,
+            ttl_seconds: 3600,
+        }
+    }
+}
+
+/// The `[ai.queue_weights]` section: relative share of dispatch slots per request priority
+/// while several priorities have requests waiting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueWeightsConfig {
+    pub critical: u32,
+    pub high: u32,
+    pub normal: u32,
+    pub low: u32,
+    pub background: u32,
+}
+
+impl Default for QueueWeightsConfig {
+    fn default() -> Self {
+        Self {
+            critical: 16,
+            high: 8,
+            normal: 4,
+            low: 2,
+            background: 1,
+        }
+    }
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_cooldown_seconds() -> u64 {
+    30
+}
+
+fn default_context_process_limit() -> usize {
+    15
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_backoff_base_ms() -> u64 {
+    250
 }
 
 impl Default for AIConfig {
@@ -38,6 +198,17 @@ impl Default for AIConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(4096),
+            circuit_failure_threshold: default_circuit_failure_threshold(),
+            circuit_cooldown_seconds: default_circuit_cooldown_seconds(),
+            retry_max_attempts: default_retry_max_attempts(),
+            retry_backoff_base_ms: default_retry_backoff_base_ms(),
+            cache: ResponseCacheConfig::default(),
+            context_process_limit: default_context_process_limit(),
+            queue_weights: QueueWeightsConfig::default(),
+            pool: ConnectionPoolConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            redaction: RedactionConfig::default(),
+            api_key: None,
         }
     }
 }
@@ -60,6 +231,144 @@ struct OllamaOptions {
 struct OllamaResponse {
     response: String,
     done: bool,
+    /// Tokens in the prompt; omitted by Ollama when the prompt was served from its KV cache
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+}
+
+impl OllamaResponse {
+    fn usage(&self) -> Option<TokenUsage> {
+        if self.prompt_eval_count.is_none() && self.eval_count.is_none() {
+            return None;
+        }
+        Some(TokenUsage {
+            prompt_tokens: self.prompt_eval_count.unwrap_or(0),
+            completion_tokens: self.eval_count.unwrap_or(0),
+        })
+    }
+}
+
+/// Prompt and completion token counts reported by the model provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Chat reply together with the model that produced it and what it cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub content: String,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Latencies kept for the p95, so it reflects recent behaviour rather than all time
+const STATS_LATENCY_WINDOW: usize = 1000;
+
+/// Request counters and latencies for every model call the AI service makes, saved across restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestStats {
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    pub total_latency_ms: f64,
+    pub requests_by_model: HashMap<String, u64>,
+    /// Most recent latencies, oldest first
+    pub recent_latencies_ms: VecDeque<f64>,
+}
+
+impl RequestStats {
+    pub fn record(&mut self, model: &str, latency: Duration, success: bool) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        self.total_requests += 1;
+        if success {
+            self.successful_requests += 1;
+        } else {
+            self.failed_requests += 1;
+        }
+        self.total_latency_ms += latency_ms;
+        *self.requests_by_model.entry(model.to_string()).or_insert(0) += 1;
+        self.recent_latencies_ms.push_back(latency_ms);
+        if self.recent_latencies_ms.len() > STATS_LATENCY_WINDOW {
+            self.recent_latencies_ms.pop_front();
+        }
+    }
+
+    /// Counters without queue information, which only the request processor knows
+    pub fn summary(&self) -> AIServiceStats {
+        let mut recent: Vec<f64> = self.recent_latencies_ms.iter().copied().collect();
+        recent.sort_by(f64::total_cmp);
+        let p95_latency_ms = match recent.len() {
+            0 => 0.0,
+            n => recent[(0.95 * (n - 1) as f64) as usize],
+        };
+        AIServiceStats {
+            total_requests: self.total_requests,
+            successful_requests: self.successful_requests,
+            failed_requests: self.failed_requests,
+            average_latency_ms: if self.total_requests == 0 { 0.0 } else { self.total_latency_ms / self.total_requests as f64 },
+            p95_latency_ms,
+            requests_by_model: self.requests_by_model.clone(),
+            pending_requests: 0,
+            queue_depths: HashMap::new(),
+        }
+    }
+
+    /// Saved stats, or empty ones if none were saved yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).with_context(|| format!("Invalid AI stats in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)?;
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, json).with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// AI service statistics as reported to the frontend
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AIServiceStats {
+    pub total_requests: u64,
+    pub successful_requests: u64,
+    pub failed_requests: u64,
+    pub average_latency_ms: f64,
+    /// Over the most recent requests
+    pub p95_latency_ms: f64,
+    pub requests_by_model: HashMap<String, u64>,
+    pub pending_requests: usize,
+    /// Queued requests per priority
+    pub queue_depths: HashMap<String, usize>,
+}
+
+/// Per-call options for [`AIService::chat_with_options`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChatOptions {
+    /// The reply is streamed to the caller, so there is no complete response to cache
+    pub streaming: bool,
+    /// The caller wants a fresh answer each time (e.g. "suggest another name")
+    pub nondeterministic: bool,
+}
+
+impl ChatOptions {
+    fn cacheable(&self) -> bool {
+        !self.streaming && !self.nondeterministic
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -67,9 +376,278 @@ pub struct AIService {
     pub client: Client,
     pub config: AIConfig,
     pub optimized_service: Option<Arc<OptimizedAIService>>,
+    /// Replies keyed by a hash of (model, prompt, context)
+    pub response_cache: Arc<Mutex<Cache<String, ChatResponse>>>,
+    /// Shared with services that call models on this one's behalf, and kept across reloads
+    pub stats: Arc<Mutex<RequestStats>>,
+    /// Model calls in flight, shared by clones so a restart sees all of them
+    pub gate: Arc<RequestGate>,
+    /// Masks secrets in every prompt before it is sent
+    pub redactor: Arc<Redactor>,
+    /// Resolves `secret://` references in the config, such as `api_key`
+    pub secret_store: Option<Arc<SecretStore>>,
+}
+
+/// Error returned for calls made while the service is being replaced
+pub const RESTARTING_ERROR: &str = "AI service is restarting";
+/// Error returned for calls cut short by a restart
+pub const CANCELLED_BY_RESTART_ERROR: &str = "AI request cancelled by service restart";
+
+/// What happened to outstanding requests when a service was replaced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrainSummary {
+    /// In flight at the restart and allowed to finish
+    pub drained: usize,
+    /// Still in flight when the drain timed out
+    pub cancelled: usize,
+    /// Queued but never dispatched, answered with an error instead
+    pub flushed: usize,
+}
+
+impl std::ops::Add for DrainSummary {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            drained: self.drained + other.drained,
+            cancelled: self.cancelled + other.cancelled,
+            flushed: self.flushed + other.flushed,
+        }
+    }
+}
+
+/// Admission control for model calls, so a restart can stop new ones and wait for the rest
+#[derive(Debug, Default)]
+pub struct RequestGate {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    drained: AtomicUsize,
+    cancelled: AtomicUsize,
+    idle: Notify,
+    /// Replaced on `reopen`, since a token stays cancelled once fired
+    cancel: Mutex<CancellationToken>,
+}
+
+/// Held for the duration of one model call
+struct RequestPermit<'a> {
+    gate: &'a RequestGate,
+    cancelled: bool,
+}
+
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        let gate = self.gate;
+        if self.cancelled {
+            gate.cancelled.fetch_add(1, Ordering::SeqCst);
+        } else if gate.is_closed() {
+            gate.drained.fetch_add(1, Ordering::SeqCst);
+        }
+        if gate.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            gate.idle.notify_waiters();
+        }
+    }
+}
+
+impl RequestGate {
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    fn enter(&self) -> Result<RequestPermit<'_>> {
+        if self.is_closed() {
+            return Err(anyhow::anyhow!(RESTARTING_ERROR));
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        // A drain that started since the check above has already counted what it waits for
+        if self.is_closed() {
+            if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+                self.idle.notify_waiters();
+            }
+            return Err(anyhow::anyhow!(RESTARTING_ERROR));
+        }
+        Ok(RequestPermit { gate: self, cancelled: false })
+    }
+
+    /// Run `call` as an admitted request, abandoning it if the drain it outlives cancels it
+    async fn run<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        let mut permit = self.enter()?;
+        let cancel = self.cancel_token();
+        tokio::select! {
+            result = call => result,
+            _ = cancel.cancelled() => {
+                permit.cancelled = true;
+                Err(anyhow::anyhow!(CANCELLED_BY_RESTART_ERROR))
+            }
+        }
+    }
+
+    fn cancel_token(&self) -> CancellationToken {
+        self.cancel.lock().map(|token| token.clone()).unwrap_or_default()
+    }
+
+    /// Stop admitting requests; those already admitted carry on
+    pub fn close(&self) {
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            self.drained.store(0, Ordering::SeqCst);
+            self.cancelled.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Stop admitting requests, give those in flight `timeout` to finish, then cancel the rest
+    pub async fn drain(&self, timeout: Duration) -> DrainSummary {
+        self.close();
+        if tokio::time::timeout(timeout, self.wait_idle()).await.is_err() {
+            warn!("{} AI request(s) still running after {:?}; cancelling", self.in_flight(), timeout);
+            self.cancel_token().cancel();
+            self.wait_idle().await;
+        }
+        DrainSummary {
+            drained: self.drained.load(Ordering::SeqCst),
+            cancelled: self.cancelled.load(Ordering::SeqCst),
+            flushed: 0,
+        }
+    }
+
+    /// Admit requests again, after a restart that could not go through
+    pub fn reopen(&self) {
+        if let Ok(mut cancel) = self.cancel.lock() {
+            *cancel = CancellationToken::new();
+        }
+        self.closed.store(false, Ordering::SeqCst);
+    }
+
+    async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            tokio::pin!(idle);
+            // Register before checking, so a permit dropped in between still wakes us
+            idle.as_mut().enable();
+            if self.in_flight() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+/// How long a restart waits for the service lock once requests are drained. Only callers that
+/// hold the lock without making model calls can still be in the way.
+pub const SWAP_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Replace the service in `slot` without abandoning requests mid-flight: stop admitting new ones,
+/// wait up to `drain_timeout` for those in flight, cancel what remains, then swap. Readers are
+/// never blocked on a write lock while their own model call is pending.
+pub async fn replace_service(slot: &RwLock<AIService>, replacement: AIService, drain_timeout: Duration) -> Result<DrainSummary> {
+    // Clone the gate out so no lock is held while waiting for requests that hold read guards
+    let gate = slot.read().await.gate.clone();
+    let summary = gate.drain(drain_timeout).await;
+
+    match tokio::time::timeout(SWAP_LOCK_TIMEOUT, slot.write()).await {
+        Ok(mut service) => {
+            *service = replacement.with_stats(service.stats.clone());
+            info!(
+                "AI service replaced: {} request(s) drained, {} cancelled",
+                summary.drained, summary.cancelled
+            );
+            Ok(summary)
+        }
+        Err(_) => {
+            gate.reopen();
+            Err(anyhow::anyhow!("AI service is still in use after draining requests; restart abandoned"))
+        }
+    }
+}
+
+/// Hash the inputs that determine a reply, so prompts of any size make a fixed-size key
+fn response_cache_key(model: &str, prompt: &str, context: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    for part in [model, prompt, context.unwrap_or_default()] {
+        hasher.update(part.len().to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher.update([context.is_some() as u8]);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Delay before retry number `retry` (1-based): `base * 2^(retry-1)`, with the upper half
+/// scaled by `jitter` in `0.0..=1.0` so clients that failed together don't retry together
+fn backoff_delay(base: Duration, retry: u32, jitter: f64) -> Duration {
+    let exponential = base.saturating_mul(1 << retry.saturating_sub(1).min(16));
+    exponential / 2 + exponential.mul_f64(jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+fn random_jitter() -> f64 {
+    let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Why an attempt failed, and whether trying again could help
+enum AttemptError {
+    Retryable(anyhow::Error),
+    Fatal(anyhow::Error),
+}
+
+async fn attempt(request: RequestBuilder, timeout: Duration) -> std::result::Result<Response, AttemptError> {
+    match request.timeout(timeout).send().await {
+        Ok(response) if response.status().is_success() => Ok(response),
+        Ok(response) => {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown HTTP error".to_string());
+            let error = anyhow::anyhow!("Ollama HTTP error {}: {}", status, error_text);
+            if status.is_server_error() {
+                Err(AttemptError::Retryable(error))
+            } else {
+                Err(AttemptError::Fatal(error))
+            }
+        }
+        Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => {
+            Err(AttemptError::Retryable(anyhow::anyhow!("Network error connecting to Ollama: {}", e)))
+        }
+        Err(e) => Err(AttemptError::Fatal(anyhow::anyhow!("Network error connecting to Ollama: {}", e))),
+    }
 }
 
 impl AIService {
+    /// Send an Ollama request, retrying timeouts, connection errors and 5xx responses with
+    /// exponential backoff. Every attempt and delay comes out of one `timeout_seconds` budget.
+    async fn send_with_retry(&self, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_seconds);
+        let max_attempts = self.config.retry_max_attempts.max(1);
+        let base = Duration::from_millis(self.config.retry_backoff_base_ms);
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let error = match attempt(build(), remaining).await {
+                Ok(response) => return Ok(response),
+                Err(AttemptError::Fatal(e)) => return Err(e),
+                Err(AttemptError::Retryable(e)) => e,
+            };
+
+            let delay = backoff_delay(base, attempts, random_jitter());
+            let out_of_time = deadline.saturating_duration_since(Instant::now()) <= delay;
+            if attempts >= max_attempts || out_of_time {
+                error!("Ollama request failed after {} attempt(s): {}", attempts, error);
+                return Err(anyhow::anyhow!("Ollama request failed after {} attempt(s): {}", attempts, error));
+            }
+
+            warn!("Ollama request attempt {} failed, retrying in {:?}: {}", attempts, delay, error);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// An empty reply cache sized from `config.cache`
+    pub fn new_response_cache(config: &AIConfig) -> Arc<Mutex<Cache<String, ChatResponse>>> {
+        let cache_config = CacheConfig::new(config.cache.max_entries)
+            .with_ttl(Duration::from_secs(config.cache.ttl_seconds));
+        Arc::new(Mutex::new(Cache::new(cache_config)))
+    }
+
     pub async fn new(config: &AIConfig) -> Result<Self> {
         let client = Client::builder()
             .timeout(Duration::from_secs(config.timeout_seconds))
@@ -77,8 +655,9 @@ impl AIService {
             .context("Failed to create HTTP client")?;
 
         // Initialize optimized AI service
+        let stats = Arc::new(Mutex::new(RequestStats::default()));
         let optimized_service = match OptimizedAIService::new(config).await {
-            Ok(service) => Some(Arc::new(service)),
+            Ok(service) => Some(Arc::new(service.with_request_stats(stats.clone()))),
             Err(e) => {
                 debug!("Failed to initialize OptimizedAIService: {}", e);
                 None
@@ -89,6 +668,11 @@ impl AIService {
             client,
             config: config.clone(),
             optimized_service,
+            response_cache: Self::new_response_cache(config),
+            stats,
+            gate: Arc::default(),
+            redactor: Arc::new(Redactor::new(&config.redaction)),
+            secret_store: None,
         };
 
         // Auto-initialize Ollama service if needed
@@ -102,25 +686,24 @@ impl AIService {
 
     async fn test_connection(&self) -> Result<()> {
         let url = format!("{}/api/tags", self.config.ollama_url);
-        let response = self.client.get(&url).send().await
+        self.send_with_retry(|| self.client.get(&url)).await
             .context("Failed to connect to Ollama")?;
 
-        if response.status().is_success() {
-            info!("Successfully connected to Ollama at {}", self.config.ollama_url);
-            Ok(())
-        } else {
-            error!("Failed to connect to Ollama: {}", response.status());
-            Err(anyhow::anyhow!("Ollama connection failed: {}", response.status()))
-        }
+        info!("Successfully connected to Ollama at {}", self.config.ollama_url);
+        Ok(())
     }
 
     async fn generate(&self, prompt: &str, model: Option<&str>) -> Result<String> {
+        Ok(self.generate_with_usage(prompt, model).await?.content)
+    }
+
+    async fn generate_with_usage(&self, prompt: &str, model: Option<&str>) -> Result<ChatResponse> {
         let model = model.unwrap_or(&self.config.default_model);
         let url = format!("{}/api/generate", self.config.ollama_url);
         
         let request = OllamaRequest {
             model: model.to_string(),
-            prompt: prompt.to_string(),
+            prompt: self.redactor.redact_logged(prompt, "AI prompt"),
             stream: false,
             options: OllamaOptions {
                 temperature: self.config.temperature,
@@ -131,42 +714,124 @@ impl AIService {
         debug!("Sending request to Ollama: {:?}", request);
 
         info!("Sending request to Ollama model '{}' with timeout {}s", model, self.config.timeout_seconds);
-        
-        let response = match self.client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await {
-            Ok(resp) => resp,
-            Err(e) => {
-                error!("Failed to send request to Ollama: {}", e);
-                return Err(anyhow::anyhow!("Network error connecting to Ollama: {}", e));
-            }
-        };
 
-        info!("Received response from Ollama with status: {}", response.status());
+        // Only admitted calls are counted; one rejected during a restart never reached the model
+        let ollama_response = self.gate.run(async {
+            let started = Instant::now();
+            let response = self.send_generate(&url, &request).await;
+            self.record_request(model, started, response.is_ok());
+            response
+        }).await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown HTTP error".to_string());
-            error!("Ollama HTTP request failed with status {}: {}", status, error_text);
-            return Err(anyhow::anyhow!("Ollama HTTP error {}: {}", status, error_text));
-        }
+        info!("Successfully received response from Ollama model '{}': {} characters", model, ollama_response.response.len());
+        debug!("Ollama response content: {:?}", ollama_response);
+        Ok(ChatResponse {
+            usage: ollama_response.usage(),
+            content: ollama_response.response,
+            model: model.to_string(),
+        })
+    }
+
+    async fn send_generate(&self, url: &str, request: &OllamaRequest) -> Result<OllamaResponse> {
+        let response = self.send_with_retry(|| self.client.post(url).json(request)).await?;
+
+        info!("Received response from Ollama with status: {}", response.status());
 
-        let ollama_response: OllamaResponse = match response.json().await {
-            Ok(resp) => resp,
+        match response.json().await {
+            Ok(resp) => Ok(resp),
             Err(e) => {
                 error!("Failed to parse Ollama JSON response: {}", e);
-                return Err(anyhow::anyhow!("Invalid JSON response from Ollama: {}", e));
+                Err(anyhow::anyhow!("Invalid JSON response from Ollama: {}", e))
             }
-        };
+        }
+    }
 
-        info!("Successfully received response from Ollama model '{}': {} characters", model, ollama_response.response.len());
-        debug!("Ollama response content: {:?}", ollama_response);
-        Ok(ollama_response.response)
+    fn record_request(&self, model: &str, started: Instant, success: bool) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.record(model, started.elapsed(), success);
+        }
+    }
+
+    /// Counters for every model call made so far, including earlier runs if stats were loaded
+    pub fn service_stats(&self) -> AIServiceStats {
+        self.stats.lock().map(|stats| stats.summary()).unwrap_or_default()
+    }
+
+    pub fn reset_stats(&self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            *stats = RequestStats::default();
+        }
+    }
+
+    /// Count into `stats` instead, e.g. to keep the previous service's stats across a reload
+    pub fn with_stats(mut self, stats: Arc<Mutex<RequestStats>>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn with_secret_store(mut self, store: Arc<SecretStore>) -> Self {
+        self.secret_store = Some(store);
+        self
+    }
+
+    /// Continue counting from stats saved at `path`
+    pub fn load_stats(&self, path: &Path) -> Result<()> {
+        let loaded = RequestStats::load(path)?;
+        if let Ok(mut stats) = self.stats.lock() {
+            *stats = loaded;
+        }
+        Ok(())
     }
 
     pub async fn chat(&self, message: &str, context: Option<&str>) -> Result<String> {
+        Ok(self.chat_with_usage(message, context).await?.content)
+    }
+
+    /// Like `chat`, but also reports the model used and the provider's token counts
+    pub async fn chat_with_usage(&self, message: &str, context: Option<&str>) -> Result<ChatResponse> {
+        self.chat_with_options(message, context, ChatOptions::default()).await
+    }
+
+    /// Chat, answering repeated identical prompts from the reply cache when `options` allow it
+    pub async fn chat_with_options(&self, message: &str, context: Option<&str>, options: ChatOptions) -> Result<ChatResponse> {
+        let cache_key = (options.cacheable() && self.config.cache.enabled)
+            .then(|| response_cache_key(&self.config.default_model, message, context));
+        if let Some(cached) = cache_key.as_ref().and_then(|key| self.cached_response(key)) {
+            return Ok(cached);
+        }
+
+        let reply = self.chat_uncached(message, context).await?;
+        if let Some(key) = cache_key {
+            self.cache_response(key, &reply);
+        }
+        Ok(reply)
+    }
+
+    /// A cached reply costs no tokens, so its usage is cleared to keep accounting honest
+    fn cached_response(&self, key: &str) -> Option<ChatResponse> {
+        let cached = self.response_cache.lock().ok()?.get(&key.to_string())?;
+        debug!("AI response cache hit for {}", key);
+        Some(ChatResponse { usage: None, ..cached })
+    }
+
+    fn cache_response(&self, key: String, reply: &ChatResponse) {
+        if let Ok(mut cache) = self.response_cache.lock() {
+            cache.insert(key, reply.clone());
+        }
+    }
+
+    /// Hit/miss counters for the reply cache
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        self.response_cache.lock().map(|cache| cache.metrics()).unwrap_or_default()
+    }
+
+    pub fn clear_cache(&self) {
+        if let Ok(mut cache) = self.response_cache.lock() {
+            cache.clear();
+        }
+    }
+
+    async fn chat_uncached(&self, message: &str, context: Option<&str>) -> Result<ChatResponse> {
         // Use optimized AI service if available
         if let Some(ref optimized_service) = self.optimized_service {
             let ai_request = AIRequest::new(message.to_string())
@@ -180,7 +845,13 @@ impl AIService {
             };
             
             match optimized_service.chat_async(&ai_request.prompt, ai_request.context.as_deref()).await {
-                Ok(response) => return Ok(response.content),
+                Ok(response) => {
+                    return Ok(ChatResponse {
+                        content: response.content,
+                        model: response.model_used,
+                        usage: response.usage,
+                    })
+                }
                 Err(e) => {
                     debug!("OptimizedAIService failed, falling back to standard AI: {}", e);
                 }
@@ -191,7 +862,7 @@ impl AIService {
         let contextual_prompt = self.build_contextual_prompt(message, context).await?;
         
         // Generate response using AI model
-        self.generate(&contextual_prompt, None).await
+        self.generate_with_usage(&contextual_prompt, None).await
     }
     
     /// Build a context-aware prompt that incorporates RAG results, system context, and conversation history
@@ -269,7 +940,7 @@ impl AIService {
     }
     
     /// Enhanced chat with memory and learning capabilities
-    pub async fn chat_with_memory(&self, message: &str, conversation_id: &str, context: Option<&str>) -> Result<String> {
+    pub async fn chat_with_memory(&self, message: &str, conversation_id: &str, context: Option<&str>) -> Result<ChatResponse> {
         // Build conversation history prompt
         let mut conversation_prompt = format!(
             "Conversation ID: {}\nPrevious context and memory would be loaded here.\n\n",
@@ -280,11 +951,11 @@ impl AIService {
         conversation_prompt.push_str(&self.build_contextual_prompt(message, context).await?);
         
         // Generate response
-        let response = self.generate(&conversation_prompt, None).await?;
+        let response = self.generate_with_usage(&conversation_prompt, None).await?;
         
         // Store conversation in RAG system for future context
         let recall_client = LocalRecallClient::default();
-        let messages = vec![("user", message), ("assistant", response.as_str())];
+        let messages = vec![("user", message), ("assistant", response.content.as_str())];
         let _ = recall_client.index_conversation(&messages, context).await;
         
         Ok(response)
@@ -314,7 +985,17 @@ impl AIService {
             command, error_output
         );
 
-        self.generate(&prompt, None).await
+        let cache_key = self.config.cache.enabled
+            .then(|| response_cache_key(&self.config.default_model, &prompt, None));
+        if let Some(cached) = cache_key.as_ref().and_then(|key| self.cached_response(key)) {
+            return Ok(cached.content);
+        }
+
+        let reply = self.generate_with_usage(&prompt, None).await?;
+        if let Some(key) = cache_key {
+            self.cache_response(key, &reply);
+        }
+        Ok(reply.content)
     }
 
     pub async fn generate_code(&self, description: &str, language: &str) -> Result<String> {
@@ -326,10 +1007,16 @@ impl AIService {
         self.generate(&prompt, None).await
     }
 
-    pub async fn generate_commit_message(&self, diff: &str) -> Result<String> {
+    /// `lfs_assets` describes changed Git LFS files, whose pointer text is left out of `diff`
+    pub async fn generate_commit_message(&self, diff: &str, lfs_assets: &[String]) -> Result<String> {
+        let assets = if lfs_assets.is_empty() {
+            String::new()
+        } else {
+            format!("\n\nLarge binary assets changed (Git LFS):\n- {}", lfs_assets.join("\n- "))
+        };
         let prompt = format!(
-            "Generate a concise, descriptive git commit message for these changes:\n\n{}\n\nFollow conventional commit format (type: description). Be specific but concise:",
-            diff
+            "Generate a concise, descriptive git commit message for these changes:\n\n{}{}\n\nFollow conventional commit format (type: description). Be specific but concise:",
+            diff, assets
         );
 
         self.generate(&prompt, None).await
@@ -360,6 +1047,21 @@ impl AIService {
         self.generate(&prompt, Some("codellama:7b")).await
     }
 
+    /// Like `suggest_improvements`, but asks for a JSON array of line-anchored replacements
+    pub async fn suggest_improvements_structured(&self, code: &str, language: &str) -> Result<String> {
+        let numbered: String = code
+            .lines()
+            .enumerate()
+            .map(|(i, line)| format!("{:>4} | {}\n", i + 1, line))
+            .collect();
+        let prompt = format!(
+            "Review this {} code and suggest improvements. Lines are numbered for reference:\n\n{}\n\nReply only with a JSON array. Each element must have:\n- start_line and end_line: the 1-based inclusive range to replace\n- original: the exact current text of those lines, without line numbers\n- replacement: the new text for those lines\n- description: why the change helps",
+            language, numbered
+        );
+
+        self.generate(&prompt, Some("codellama:7b")).await
+    }
+
     pub async fn explain_concept(&self, concept: &str, context: &str) -> Result<String> {
         let prompt = format!(
             "Explain the concept '{}' in the context of '{}':\n\nProvide:\n1. A clear definition\n2. How it relates to the context\n3. Practical examples\n4. Common use cases or applications",
@@ -371,13 +1073,6 @@ impl AIService {
 
     pub async fn get_available_models(&self) -> Result<Vec<String>> {
         let url = format!("{}/api/tags", self.config.ollama_url);
-        
-        let response = self.client.get(&url).send().await
-            .context("Failed to fetch available models")?;
-
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to fetch models: {}", response.status()));
-        }
 
         #[derive(Deserialize)]
         struct ModelsResponse {
@@ -389,12 +1084,128 @@ impl AIService {
             name: String,
         }
 
-        let models_response: ModelsResponse = response.json().await
-            .context("Failed to parse models response")?;
+        let models_response: ModelsResponse = self.gate.run(async {
+            let response = self.send_with_retry(|| self.client.get(&url)).await
+                .context("Failed to fetch available models")?;
+            response.json().await.context("Failed to parse models response")
+        }).await?;
 
         Ok(models_response.models.into_iter().map(|m| m.name).collect())
     }
 
+    /// Embedding vectors for `texts`, in input order, all of the same dimension
+    pub async fn embed(&self, texts: Vec<String>, model: Option<String>) -> Result<Vec<Vec<f32>>> {
+        let config = &self.config.embeddings;
+        let model = model.unwrap_or_else(|| config.model.clone());
+        let base_url = config.url.as_deref().unwrap_or(&self.config.ollama_url).trim_end_matches('/');
+
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(config.batch_size().max(1)) {
+            let vectors = self.gate.run(async {
+                match config.provider {
+                    EmbeddingProvider::Ollama => {
+                        let url = format!("{}/api/embeddings", base_url);
+                        futures::future::try_join_all(batch.iter().map(|text| self.ollama_embedding(&url, &model, text))).await
+                    }
+                    EmbeddingProvider::OpenAI => {
+                        self.openai_embeddings(&format!("{}/v1/embeddings", base_url), &model, batch).await
+                    }
+                }
+            })
+            .await?;
+            if vectors.len() != batch.len() {
+                return Err(anyhow::anyhow!(
+                    "Embedding provider returned {} vectors for {} texts",
+                    vectors.len(),
+                    batch.len()
+                ));
+            }
+            embeddings.extend(vectors);
+        }
+
+        if let Some(dimensions) = embeddings.first().map(Vec::len) {
+            if let Some((index, vector)) = embeddings.iter().enumerate().find(|(_, v)| v.is_empty() || v.len() != dimensions) {
+                return Err(anyhow::anyhow!(
+                    "Embedding model '{}' returned a {}-dimensional vector for text {}, expected {}",
+                    model,
+                    vector.len(),
+                    index,
+                    dimensions
+                ));
+            }
+        }
+        Ok(embeddings)
+    }
+
+    async fn ollama_embedding(&self, url: &str, model: &str, text: &str) -> Result<Vec<f32>> {
+        #[derive(Deserialize)]
+        struct EmbeddingResponse {
+            embedding: Vec<f32>,
+        }
+
+        let body = serde_json::json!({ "model": model, "prompt": text });
+        let started = Instant::now();
+        let result: Result<EmbeddingResponse> = async {
+            let response = self.send_with_retry(|| self.client.post(url).json(&body)).await
+                .context("Failed to generate embedding")?;
+            response.json().await.context("Failed to parse embedding response")
+        }
+        .await;
+        self.record_request(model, started, result.is_ok());
+        Ok(result?.embedding)
+    }
+
+    /// `ai.api_key` in plain text, looked up in the secret store if it is a reference
+    fn api_key(&self) -> Result<Option<String>> {
+        let Some(value) = self.config.api_key.as_deref() else {
+            return Ok(None);
+        };
+        if !is_secret_ref(value) {
+            return Ok(Some(value.to_string()));
+        }
+        let store = self
+            .secret_store
+            .as_ref()
+            .context("ai.api_key is a secret reference but no secret store is available")?;
+        let key = store.resolve(value)?.with_context(|| format!("{} is not in the secret store", value))?;
+        Ok(Some(key))
+    }
+
+    async fn openai_embeddings(&self, url: &str, model: &str, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct EmbeddingsResponse {
+            data: Vec<EmbeddingData>,
+        }
+
+        #[derive(Deserialize)]
+        struct EmbeddingData {
+            index: usize,
+            embedding: Vec<f32>,
+        }
+
+        let body = serde_json::json!({ "model": model, "input": texts });
+        let api_key = self.api_key()?;
+        let started = Instant::now();
+        let result: Result<EmbeddingsResponse> = async {
+            let response = self.send_with_retry(|| {
+                let request = self.client.post(url).json(&body);
+                match &api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            }).await
+                .context("Failed to generate embeddings")?;
+            response.json().await.context("Failed to parse embeddings response")
+        }
+        .await;
+        self.record_request(model, started, result.is_ok());
+        let mut response = result?;
+
+        // Entries carry their input position and aren't guaranteed to arrive in order
+        response.data.sort_by_key(|data| data.index);
+        Ok(response.data.into_iter().map(|data| data.embedding).collect())
+    }
+
     /// System diagnostic and repair capabilities
     pub async fn diagnose_system_issue(&self, issue_description: &str, system_info: &str) -> Result<String> {
         let prompt = format!(
@@ -944,15 +1755,6 @@ impl AIService {
         }
     }
     
-    /// Get service statistics and performance metrics
-    pub async fn get_service_stats(&self) -> Result<String> {
-        if let Some(optimized) = &self.optimized_service {
-            Ok(optimized.get_stats().await)
-        } else {
-            Ok("Optimized service not available".to_string())
-        }
-    }
-    
     /// Clear completed requests from the optimized service
     pub async fn clear_completed_requests(&self) -> Result<()> {
         if let Some(optimized) = &self.optimized_service {
@@ -982,8 +1784,443 @@ impl Default for AIService {
         
         Self {
             client,
+            response_cache: Self::new_response_cache(&config),
             config,
             optimized_service: None, // Can't create OptimizedAIService without async context
+            stats: Arc::default(),
+            gate: Arc::default(),
+            redactor: Arc::default(),
+            secret_store: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use crate::secret_store::tests::MockKeyring;
+
+    /// Answer every `/api/generate` call with the same reply, counting the calls
+    async fn mock_ollama() -> (String, Arc<AtomicUsize>) {
+        mock_ollama_failing(0, 503).await
+    }
+
+    /// Like `mock_ollama`, but the first `failures` calls get `status` instead of a reply
+    async fn mock_ollama_failing(failures: usize, status: u16) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    // Read headers plus the JSON body; the body always ends with '}'
+                    while !request.ends_with(b"}") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    let (status, body) = if n <= failures {
+                        (status, format!(r#"{{"error": "failure {}"}}"#, n))
+                    } else {
+                        (200, format!(r#"{{"response": "reply {}", "done": true, "prompt_eval_count": 10, "eval_count": 5}}"#, n))
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (url, calls)
+    }
+
+    /// `/api/generate` that takes `delay` to answer
+    async fn mock_ollama_slow(delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    while !request.ends_with(b"}") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    tokio::time::sleep(delay).await;
+                    let body = r#"{"response": "slow reply", "done": true}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+
+    /// Start a chat on the service in `slot` that holds its read guard throughout, as commands do
+    async fn chat_in_flight(slot: &Arc<RwLock<AIService>>) -> tokio::task::JoinHandle<Result<String>> {
+        let slot = slot.clone();
+        let gate = slot.read().await.gate.clone();
+        let handle = tokio::spawn(async move {
+            let service = slot.read().await;
+            service.chat("slow question", None).await
+        });
+        while gate.in_flight() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        handle
+    }
+
+    #[tokio::test]
+    async fn test_restart_waits_for_in_flight_request() {
+        let slot = Arc::new(RwLock::new(service_for(mock_ollama_slow(Duration::from_millis(300)).await)));
+        let old_service = slot.read().await.clone();
+        let in_flight = chat_in_flight(&slot).await;
+
+        let (url, calls) = mock_ollama().await;
+        let restart = tokio::spawn({
+            let slot = slot.clone();
+            async move { replace_service(&slot, service_for(url), Duration::from_secs(5)).await }
+        });
+        while !old_service.gate.is_closed() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let rejected = old_service.chat("new question", None).await.unwrap_err();
+        assert_eq!(rejected.to_string(), RESTARTING_ERROR);
+
+        let summary = tokio::time::timeout(Duration::from_secs(5), restart).await.unwrap().unwrap().unwrap();
+        assert_eq!(summary, DrainSummary { drained: 1, cancelled: 0, flushed: 0 });
+        assert_eq!(in_flight.await.unwrap().unwrap(), "slow reply");
+
+        // The replacement admits requests and keeps counting into the same stats
+        slot.read().await.chat("after restart", None).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(slot.read().await.service_stats().total_requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_restart_cancels_request_past_drain_timeout() {
+        let slot = Arc::new(RwLock::new(service_for(mock_ollama_slow(Duration::from_secs(30)).await)));
+        let in_flight = chat_in_flight(&slot).await;
+
+        let (url, _) = mock_ollama().await;
+        let summary = tokio::time::timeout(
+            Duration::from_secs(5),
+            replace_service(&slot, service_for(url), Duration::from_millis(100)),
+        )
+        .await
+        .expect("restart deadlocked")
+        .unwrap();
+        assert_eq!(summary, DrainSummary { drained: 0, cancelled: 1, flushed: 0 });
+
+        let error = in_flight.await.unwrap().unwrap_err();
+        assert_eq!(error.to_string(), CANCELLED_BY_RESTART_ERROR);
+        slot.read().await.chat("after restart", None).await.unwrap();
+    }
+
+    /// Embeddings endpoint for both providers. A text's vector is `[len, first byte]`, or
+    /// three-dimensional for "odd one out"; OpenAI batches come back in reverse order and are
+    /// refused without `Bearer sk-embed`.
+    async fn mock_embeddings() -> (String, Arc<Mutex<Vec<usize>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let recorded = batches.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    while !request.ends_with(b"}") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request).to_string();
+                    let (head, body) = request.split_once("\r\n\r\n").unwrap();
+                    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+                    let vector = |text: &str| {
+                        if text == "odd one out" {
+                            vec![1.0, 2.0, 3.0]
+                        } else {
+                            vec![text.len() as f32, text.as_bytes()[0] as f32]
+                        }
+                    };
+
+                    let authorized = head.lines().any(|line| line.eq_ignore_ascii_case("authorization: Bearer sk-embed"));
+                    if head.starts_with("POST /v1/embeddings") && !authorized {
+                        let _ = stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await;
+                        return;
+                    }
+
+                    let body = if head.starts_with("POST /v1/embeddings") {
+                        let inputs: Vec<String> = serde_json::from_value(body["input"].clone()).unwrap();
+                        recorded.lock().unwrap().push(inputs.len());
+                        let data: Vec<serde_json::Value> = inputs
+                            .iter()
+                            .enumerate()
+                            .rev()
+                            .map(|(index, text)| serde_json::json!({ "index": index, "embedding": vector(text) }))
+                            .collect();
+                        serde_json::json!({ "data": data })
+                    } else {
+                        recorded.lock().unwrap().push(1);
+                        serde_json::json!({ "embedding": vector(body["prompt"].as_str().unwrap()) })
+                    }
+                    .to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (url, batches)
+    }
+
+    fn embedding_service(url: String, provider: EmbeddingProvider, batch_size: usize) -> AIService {
+        let mut service = service_for(url);
+        service.config.embeddings = EmbeddingsConfig {
+            provider,
+            batch_size: Some(batch_size),
+            ..EmbeddingsConfig::default()
+        };
+        service
+    }
+
+    #[tokio::test]
+    async fn test_openai_embeddings_are_batched_in_input_order() {
+        let (url, batches) = mock_embeddings().await;
+        let store = SecretStore::with_backend(Box::new(MockKeyring::default()));
+        let mut service = embedding_service(url, EmbeddingProvider::OpenAI, 2);
+        service.config.api_key = Some(store.store("ai/api_key", "sk-embed").unwrap());
+        let service = service.with_secret_store(Arc::new(store));
+        let texts: Vec<String> = ["a", "bb", "ccc", "dddd", "eeeee"].iter().map(|t| t.to_string()).collect();
+
+        let vectors = service.embed(texts, None).await.unwrap();
+        assert_eq!(*batches.lock().unwrap(), vec![2, 2, 1]);
+        let lengths: Vec<f32> = vectors.iter().map(|v| v[0]).collect();
+        assert_eq!(lengths, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert!(service.embed(Vec::new(), None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ollama_embeddings_keep_order_and_check_dimensions() {
+        let (url, batches) = mock_embeddings().await;
+        let service = embedding_service(url, EmbeddingProvider::Ollama, 3);
+        let texts: Vec<String> = ["xyz", "a", "hello"].iter().map(|t| t.to_string()).collect();
+
+        let vectors = service.embed(texts, Some("all-minilm".to_string())).await.unwrap();
+        assert_eq!(vectors, vec![vec![3.0, b'x' as f32], vec![1.0, b'a' as f32], vec![5.0, b'h' as f32]]);
+        assert_eq!(batches.lock().unwrap().len(), 3);
+
+        let mixed: Vec<String> = ["fine", "odd one out"].iter().map(|t| t.to_string()).collect();
+        let error = service.embed(mixed, None).await.unwrap_err().to_string();
+        assert!(error.contains("3-dimensional vector for text 1, expected 2"), "{}", error);
+    }
+
+    fn service_for(url: String) -> AIService {
+        let config = AIConfig {
+            ollama_url: url,
+            default_model: "llama3:8b".to_string(),
+            retry_backoff_base_ms: 10,
+            ..AIConfig::default()
+        };
+        AIService { config: config.clone(), response_cache: AIService::new_response_cache(&config), ..AIService::default() }
+    }
+
+    #[tokio::test]
+    async fn test_identical_prompts_are_served_from_cache() {
+        let (url, calls) = mock_ollama().await;
+        let service = service_for(url);
+
+        let first = service.chat_with_usage("explain ENOSPC", Some("cwd: /tmp")).await.unwrap();
+        let second = service.chat_with_usage("explain ENOSPC", Some("cwd: /tmp")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second.content, first.content);
+        assert!(first.usage.is_some());
+        assert_eq!(second.usage, None);
+
+        // A different context is a different prompt
+        service.chat("explain ENOSPC", Some("cwd: /home")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let first = service.explain_error("No space left on device", "cp a b").await.unwrap();
+        let second = service.explain_error("No space left on device", "cp a b").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(first, second);
+
+        let metrics = service.cache_metrics();
+        assert_eq!((metrics.hits, metrics.misses), (2, 3));
+
+        service.clear_cache();
+        service.explain_error("No space left on device", "cp a b").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_nondeterministic_prompts_skip_cache() {
+        let (url, calls) = mock_ollama().await;
+        let service = service_for(url);
+        let options = ChatOptions { nondeterministic: true, ..ChatOptions::default() };
+
+        let first = service.chat_with_options("suggest a branch name", None, options).await.unwrap();
+        let second = service.chat_with_options("suggest a branch name", None, options).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_ne!(first.content, second.content);
+        assert!(service.response_cache.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let (url, calls) = mock_ollama_failing(2, 503).await;
+        let service = service_for(url);
+
+        let reply = service.explain_error("Connection reset", "curl host").await.unwrap();
+        assert_eq!(reply, "reply 3");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_final_error_reports_attempts() {
+        let (url, calls) = mock_ollama_failing(usize::MAX, 502).await;
+        let service = service_for(url);
+
+        let error = service.explain_error("Connection reset", "curl host").await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(error.to_string().starts_with("Ollama request failed after 3 attempt(s): Ollama HTTP error 502"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let (url, calls) = mock_ollama_failing(usize::MAX, 404).await;
+        let service = service_for(url);
+
+        let error = service.explain_error("Connection reset", "curl host").await.unwrap_err();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(error.to_string().contains("404"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_the_request_timeout() {
+        let (url, calls) = mock_ollama_failing(usize::MAX, 503).await;
+        let mut service = service_for(url);
+        service.config.timeout_seconds = 1;
+        service.config.retry_max_attempts = 100;
+        service.config.retry_backoff_base_ms = 200;
+
+        let started = Instant::now();
+        assert!(service.explain_error("Connection reset", "curl host").await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(calls.load(Ordering::SeqCst) < 5);
+    }
+
+    #[test]
+    fn test_backoff_doubles_with_bounded_jitter() {
+        let base = Duration::from_millis(100);
+        assert_eq!(backoff_delay(base, 1, 0.0), Duration::from_millis(50));
+        assert_eq!(backoff_delay(base, 1, 1.0), Duration::from_millis(100));
+        assert_eq!(backoff_delay(base, 3, 1.0), Duration::from_millis(400));
+        assert!((0.0..1.0).contains(&random_jitter()));
+    }
+
+    #[test]
+    fn test_ollama_token_counts_are_parsed() {
+        let body = r#"{
+            "model": "llama3:8b",
+            "created_at": "2024-05-01T12:00:00Z",
+            "response": "Use `ls -la`.",
+            "done": true,
+            "total_duration": 5043500667,
+            "prompt_eval_count": 26,
+            "eval_count": 290
+        }"#;
+        let response: OllamaResponse = serde_json::from_str(body).unwrap();
+        let usage = response.usage().unwrap();
+        assert_eq!(usage, TokenUsage { prompt_tokens: 26, completion_tokens: 290 });
+        assert_eq!(usage.total(), 316);
+
+        // Cached prompts omit prompt_eval_count
+        let cached: OllamaResponse = serde_json::from_str(r#"{"response": "ok", "done": true, "eval_count": 3}"#).unwrap();
+        assert_eq!(cached.usage(), Some(TokenUsage { prompt_tokens: 0, completion_tokens: 3 }));
+        let bare: OllamaResponse = serde_json::from_str(r#"{"response": "ok", "done": true}"#).unwrap();
+        assert_eq!(bare.usage(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_every_model_call() {
+        let (url, calls) = mock_ollama_failing(2, 400).await;
+        let service = service_for(url);
+
+        // Client errors aren't retried, so the first two calls fail outright
+        assert!(service.explain_error("disk full", "cp a b").await.is_err());
+        assert!(service.generate("hello", Some("mistral:7b")).await.is_err());
+        service.explain_error("disk full", "cp a b").await.unwrap();
+        service.generate("hello", Some("mistral:7b")).await.unwrap();
+        service.generate_code("a fizzbuzz", "rust").await.unwrap();
+        // Served from the reply cache, so no model call is counted
+        service.explain_error("disk full", "cp a b").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        let stats = service.service_stats();
+        assert_eq!((stats.total_requests, stats.successful_requests, stats.failed_requests), (5, 3, 2));
+        assert_eq!(stats.requests_by_model["llama3:8b"], 3);
+        assert_eq!(stats.requests_by_model["mistral:7b"], 2);
+        assert!(stats.average_latency_ms > 0.0);
+        assert!(stats.p95_latency_ms > 0.0);
+
+        service.reset_stats();
+        assert_eq!(service.service_stats(), AIServiceStats::default());
+    }
+
+    #[test]
+    fn test_stats_percentiles_and_persistence() {
+        let mut stats = RequestStats::default();
+        for ms in 1..=100 {
+            stats.record("llama3:8b", Duration::from_millis(ms), ms % 10 != 0);
+        }
+        let summary = stats.summary();
+        assert_eq!((summary.total_requests, summary.successful_requests, summary.failed_requests), (100, 90, 10));
+        assert!((summary.average_latency_ms - 50.5).abs() < 1e-9);
+        assert!((summary.p95_latency_ms - 95.0).abs() < 1e-9);
+
+        // Saved stats carry over to a new service
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ai_stats.json");
+        stats.save(&path).unwrap();
+        let service = AIService::default();
+        service.load_stats(&path).unwrap();
+        assert_eq!(service.service_stats(), summary);
+        service.load_stats(&dir.path().join("missing.json")).unwrap();
+        assert_eq!(service.service_stats().total_requests, 0);
+
+        // Only the most recent latencies feed the p95
+        for _ in 0..STATS_LATENCY_WINDOW {
+            stats.record("llama3:8b", Duration::from_millis(7), true);
         }
+        assert_eq!(stats.recent_latencies_ms.len(), STATS_LATENCY_WINDOW);
+        assert!((stats.summary().p95_latency_ms - 7.0).abs() < 1e-9);
     }
 }
@@ -6,11 +6,25 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, Mutex, RwLock, Semaphore};
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info};
 use uuid::Uuid;
 use std::hash::Hash;
 
-use crate::ai::{AIConfig, AIService};
+use crate::ai::{
+    AIConfig, AIService, ConnectionPoolConfig, DrainSummary, QueueWeightsConfig, RequestStats, TokenUsage, RESTARTING_ERROR,
+    SWAP_LOCK_TIMEOUT,
+};
+use crate::cache::{Cache, CacheConfig, CacheMetrics};
+use crate::redaction::Redactor;
+
+/// How long a cached AI response stays valid
+const RESPONSE_CACHE_TTL: Duration = Duration::from_secs(300);
+const RESPONSE_CACHE_MAX_ENTRIES: usize = 1000;
+const RESPONSE_CACHE_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Error carried by the response sent for a cancelled request
+pub const CANCELLED_ERROR: &str = "cancelled";
 
 /// Request priority levels for AI service
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -22,6 +36,17 @@ pub enum RequestPriority {
     Background = 4, // Non-urgent operations
 }
 
+impl RequestPriority {
+    /// Highest priority first
+    pub const ALL: [RequestPriority; 5] = [
+        RequestPriority::Critical,
+        RequestPriority::High,
+        RequestPriority::Normal,
+        RequestPriority::Low,
+        RequestPriority::Background,
+    ];
+}
+
 /// AI request structure with priority and metadata
 #[derive(Debug, Clone)]
 pub struct AIRequest {
@@ -34,6 +59,8 @@ pub struct AIRequest {
     pub context: Option<String>,
     pub retry_count: u32,
     pub max_retries: u32,
+    /// Fired by `cancel_request`; aborts the HTTP call if the request is already dispatched
+    pub cancel_token: CancellationToken,
 }
 
 impl AIRequest {
@@ -48,6 +75,7 @@ impl AIRequest {
             context: None,
             retry_count: 0,
             max_retries: 3,
+            cancel_token: CancellationToken::new(),
         }
     }
     
@@ -68,6 +96,7 @@ impl AIRequest {
             context: None,
             retry_count: 0,
             max_retries: 3,
+            cancel_token: CancellationToken::new(),
         }
     }
     
@@ -82,6 +111,7 @@ impl AIRequest {
             context: None,
             retry_count: 0,
             max_retries: 3,
+            cancel_token: CancellationToken::new(),
         }
     }
 
@@ -123,10 +153,256 @@ pub struct AIResponse {
     pub model_used: String,
     pub processing_time: Duration,
     pub tokens_used: Option<u32>,
+    pub usage: Option<TokenUsage>,
     pub success: bool,
     pub error: Option<String>,
 }
 
+impl AIResponse {
+    fn rejected(request_id: String, reason: &str) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            request_id,
+            content: String::new(),
+            model_used: reason.to_string(),
+            processing_time: Duration::default(),
+            tokens_used: None,
+            usage: None,
+            success: false,
+            error: Some(reason.to_string()),
+        }
+    }
+
+    fn cancelled(request_id: String) -> Self {
+        Self::rejected(request_id, CANCELLED_ERROR)
+    }
+
+    fn circuit_open(request_id: String) -> Self {
+        Self::rejected(request_id, CIRCUIT_OPEN_ERROR)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.error.as_deref() == Some(CANCELLED_ERROR)
+    }
+}
+
+/// Error carried by responses rejected while the circuit breaker is open
+pub const CIRCUIT_OPEN_ERROR: &str = "circuit_open";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Stops sending requests to a failing AI backend.
+///
+/// Opens after `failure_threshold` consecutive failures; once `cooldown` has passed it
+/// half-opens and lets one probe through, closing again if the probe succeeds.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            consecutive_failures: 0,
+            opened_at: None,
+            probe_in_flight: false,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent now; in half-open state only the first caller gets through
+    pub fn try_acquire(&mut self) -> bool {
+        match self.state() {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.probe_in_flight => false,
+            CircuitState::HalfOpen => {
+                self.probe_in_flight = true;
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.probe_in_flight = false;
+    }
+
+    pub fn record_failure(&mut self) {
+        if self.probe_in_flight {
+            self.probe_in_flight = false;
+            self.opened_at = Some(Instant::now());
+            return;
+        }
+
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= self.failure_threshold {
+            self.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Release an acquired slot without a verdict, e.g. when the request was cancelled
+    pub fn release(&mut self) {
+        self.probe_in_flight = false;
+    }
+}
+
+/// A queued request together with the channel its response goes to
+#[derive(Debug)]
+struct QueuedRequest {
+    request: AIRequest,
+    response_sender: mpsc::Sender<AIResponse>,
+}
+
+/// Smooth weighted round-robin over the priorities that have requests waiting. Each
+/// priority gets a share of dispatches proportional to its weight, so a flood of Critical
+/// requests can't starve Low ones, while a priority with nothing queued gives up its share.
+#[derive(Debug)]
+struct FairScheduler {
+    weights: QueueWeightsConfig,
+    credit: HashMap<RequestPriority, i64>,
+}
+
+impl FairScheduler {
+    fn new(weights: QueueWeightsConfig) -> Self {
+        Self {
+            weights,
+            credit: HashMap::new(),
+        }
+    }
+
+    fn weight(&self, priority: RequestPriority) -> i64 {
+        let weight = match priority {
+            RequestPriority::Critical => self.weights.critical,
+            RequestPriority::High => self.weights.high,
+            RequestPriority::Normal => self.weights.normal,
+            RequestPriority::Low => self.weights.low,
+            RequestPriority::Background => self.weights.background,
+        };
+        i64::from(weight.max(1))
+    }
+
+    /// Choose which of the `ready` priorities dispatches next
+    fn pick(&mut self, ready: &[RequestPriority]) -> Option<RequestPriority> {
+        // Idle priorities don't bank credit for later bursts
+        self.credit.retain(|priority, _| ready.contains(priority));
+
+        let mut total = 0;
+        for &priority in ready {
+            let weight = self.weight(priority);
+            *self.credit.entry(priority).or_insert(0) += weight;
+            total += weight;
+        }
+        // Ties go to the higher priority
+        let chosen = ready
+            .iter()
+            .copied()
+            .max_by_key(|priority| (self.credit[priority], std::cmp::Reverse(*priority)))?;
+        *self.credit.entry(chosen).or_insert(0) -= total;
+        Some(chosen)
+    }
+}
+
+/// Weight of the newest response in the latency moving average
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+/// AIMD sizing for the number of concurrent requests: grow by one while requests queue up
+/// and latency is acceptable, halve when requests fail or latency goes over target
+#[derive(Debug)]
+struct AdaptivePoolSize {
+    config: ConnectionPoolConfig,
+    size: usize,
+    latency_ewma_ms: Option<f64>,
+}
+
+impl AdaptivePoolSize {
+    fn new(config: ConnectionPoolConfig) -> Self {
+        let max = config.max_connections.max(1);
+        let config = ConnectionPoolConfig {
+            min_connections: config.min_connections.clamp(1, max),
+            max_connections: max,
+            ..config
+        };
+        Self {
+            size: config.min_connections,
+            config,
+            latency_ewma_ms: None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn latency_ewma_ms(&self) -> f64 {
+        self.latency_ewma_ms.unwrap_or(0.0)
+    }
+
+    fn record_success(&mut self, latency: Duration, pending: usize) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let ewma = match self.latency_ewma_ms {
+            Some(ewma) => ewma + LATENCY_EWMA_ALPHA * (latency_ms - ewma),
+            None => latency_ms,
+        };
+        self.latency_ewma_ms = Some(ewma);
+
+        if ewma > self.config.target_latency_ms as f64 {
+            self.shrink();
+        } else if pending > 0 && self.size < self.config.max_connections {
+            self.size += 1;
+            debug!("AI pool grew to {} (latency {:.0}ms, {} pending)", self.size, ewma, pending);
+        }
+    }
+
+    /// A failed or timed-out request
+    fn record_failure(&mut self) {
+        self.shrink();
+    }
+
+    fn shrink(&mut self) {
+        let size = (self.size / 2).max(self.config.min_connections);
+        if size < self.size {
+            debug!("AI pool shrank from {} to {}", self.size, size);
+        }
+        self.size = size;
+    }
+
+    fn reset(&mut self) {
+        self.size = self.config.min_connections;
+        self.latency_ewma_ms = None;
+    }
+}
+
 /// Connection pool statistics
 #[derive(Debug, Clone, Serialize)]
 pub struct PoolStats {
@@ -135,8 +411,16 @@ pub struct PoolStats {
     pub pending_requests: usize,
     pub processed_requests: u64,
     pub failed_requests: u64,
+    pub cancelled_requests: u64,
     pub average_response_time: f64,
     pub queue_by_priority: HashMap<String, usize>,
+    /// Requests handed to the processor per priority, to confirm fair scheduling
+    pub dispatched_by_priority: HashMap<String, u64>,
+    /// Concurrent requests currently allowed by adaptive sizing
+    pub pool_size: usize,
+    /// Moving average of recent response times
+    pub latency_ewma_ms: f64,
+    pub circuit_state: CircuitState,
 }
 
 /// HTTP client pool for managing connections
@@ -206,9 +490,14 @@ pub struct OptimizedAIService {
     base_service: AIService,
     client_pool: Arc<HttpClientPool>,
     request_queue: Arc<Mutex<VecDeque<AIRequest>>>,
-    priority_queues: Arc<Mutex<HashMap<RequestPriority, VecDeque<AIRequest>>>>,
-    response_cache: Arc<RwLock<HashMap<String, (AIResponse, Instant)>>>,
+    priority_queues: Arc<Mutex<HashMap<RequestPriority, VecDeque<QueuedRequest>>>>,
+    scheduler: Arc<Mutex<FairScheduler>>,
+    /// Cancellation tokens of dispatched requests, by request id
+    in_flight: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    circuit_breaker: Arc<Mutex<CircuitBreaker>>,
+    response_cache: Arc<Mutex<Cache<String, AIResponse>>>,
     request_semaphore: Arc<Semaphore>,
+    pool_size: Arc<Mutex<AdaptivePoolSize>>,
     stats: Arc<RwLock<PoolStats>>,
     response_times: Arc<Mutex<VecDeque<Duration>>>,
     shutdown_sender: Option<mpsc::Sender<()>>,
@@ -232,16 +521,21 @@ impl OptimizedAIService {
             client,
             config: config.clone(),
             optimized_service: None, // Don't create circular reference
+            response_cache: AIService::new_response_cache(config),
+            stats: Arc::default(),
+            gate: Arc::default(),
+            redactor: Arc::new(Redactor::new(&config.redaction)),
+            secret_store: None,
         };
         
-        let max_connections = 10; // Configurable connection pool size
+        let pool_size = AdaptivePoolSize::new(config.pool.clone());
+        let max_connections = pool_size.config.max_connections;
         let client_pool = Arc::new(HttpClientPool::new(config, max_connections)?);
         
-        let mut priority_queues = HashMap::new();
-        priority_queues.insert(RequestPriority::Critical, VecDeque::new());
-        priority_queues.insert(RequestPriority::High, VecDeque::new());
-        priority_queues.insert(RequestPriority::Normal, VecDeque::new());
-        priority_queues.insert(RequestPriority::Background, VecDeque::new());
+        let priority_queues: HashMap<RequestPriority, VecDeque<QueuedRequest>> = RequestPriority::ALL
+            .into_iter()
+            .map(|priority| (priority, VecDeque::new()))
+            .collect();
 
         let initial_stats = PoolStats {
             active_connections: 0,
@@ -249,11 +543,19 @@ impl OptimizedAIService {
             pending_requests: 0,
             processed_requests: 0,
             failed_requests: 0,
+            cancelled_requests: 0,
             average_response_time: 0.0,
             queue_by_priority: priority_queues
                 .keys()
                 .map(|p| (format!("{:?}", p), 0))
                 .collect(),
+            dispatched_by_priority: priority_queues
+                .keys()
+                .map(|p| (format!("{:?}", p), 0))
+                .collect(),
+            pool_size: pool_size.size(),
+            latency_ewma_ms: 0.0,
+            circuit_state: CircuitState::Closed,
         };
 
         let (shutdown_sender, _shutdown_receiver) = mpsc::channel(1);
@@ -263,8 +565,22 @@ impl OptimizedAIService {
             client_pool: client_pool.clone(),
             request_queue: Arc::new(Mutex::new(VecDeque::new())),
             priority_queues: Arc::new(Mutex::new(priority_queues)),
-            response_cache: Arc::new(RwLock::new(HashMap::new())),
+            scheduler: Arc::new(Mutex::new(FairScheduler::new(config.queue_weights.clone()))),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            circuit_breaker: Arc::new(Mutex::new(CircuitBreaker::new(
+                config.circuit_failure_threshold,
+                Duration::from_secs(config.circuit_cooldown_seconds),
+            ))),
+            response_cache: Arc::new(Mutex::new(
+                Cache::new(
+                    CacheConfig::new(RESPONSE_CACHE_MAX_ENTRIES)
+                        .with_max_bytes(RESPONSE_CACHE_MAX_BYTES)
+                        .with_ttl(RESPONSE_CACHE_TTL),
+                )
+                .with_weigher(|response: &AIResponse| response.content.len()),
+            )),
             request_semaphore: Arc::new(Semaphore::new(max_connections)),
+            pool_size: Arc::new(Mutex::new(pool_size)),
             stats: Arc::new(RwLock::new(initial_stats)),
             response_times: Arc::new(Mutex::new(VecDeque::new())),
             shutdown_sender: Some(shutdown_sender),
@@ -275,17 +591,36 @@ impl OptimizedAIService {
         Ok(service)
     }
 
+    /// Count this service's model calls in `stats`, e.g. those of the `AIService` that reports them.
+    /// Call before `start_background_tasks`.
+    pub fn with_request_stats(mut self, stats: Arc<std::sync::Mutex<RequestStats>>) -> Self {
+        self.base_service.stats = stats;
+        self
+    }
+
     /// Submit a request to the AI service (returns response receiver)
     pub async fn submit_request_async(&self, request: AIRequest) -> Result<mpsc::Receiver<AIResponse>> {
-        // Check cache first
+        // Check cache first; a cached answer costs no tokens
         if let Some(cached) = self.get_cached_response(&request).await {
             let (tx, rx) = mpsc::channel(1);
-            let _ = tx.send(cached).await;
+            let _ = tx.send(AIResponse { tokens_used: None, usage: None, ..cached }).await;
             return Ok(rx);
         }
 
-        // Add to appropriate priority queue
         let (tx, rx) = mpsc::channel(1);
+
+        if self.base_service.gate.is_closed() {
+            let _ = tx.send(AIResponse::rejected(request.id, RESTARTING_ERROR)).await;
+            return Ok(rx);
+        }
+
+        // Fail fast instead of queueing behind a backend that is known to be down
+        if self.circuit_breaker.lock().await.state() == CircuitState::Open {
+            let _ = tx.send(AIResponse::circuit_open(request.id)).await;
+            return Ok(rx);
+        }
+
+        // Add to appropriate priority queue
         self.enqueue_request(request, tx).await?;
         
         Ok(rx)
@@ -293,6 +628,10 @@ impl OptimizedAIService {
 
     /// Quick API for chat functionality
     pub async fn chat_async(&self, message: &str, context: Option<&str>) -> Result<AIResponse> {
+        if self.background_tasks.is_empty() {
+            return Err(anyhow::anyhow!("Request processor is not running"));
+        }
+
         let request = AIRequest::simple(message.to_string())
             .with_priority(RequestPriority::High)
             .with_context(context.unwrap_or_default().to_string());
@@ -311,15 +650,17 @@ impl OptimizedAIService {
     async fn start_request_processor(&self, mut shutdown_receiver: mpsc::Receiver<()>) -> tokio::task::JoinHandle<()> {
         let client_pool = self.client_pool.clone();
         let priority_queues = self.priority_queues.clone();
+        let scheduler = self.scheduler.clone();
         let response_cache = self.response_cache.clone();
         let stats = self.stats.clone();
         let response_times = self.response_times.clone();
         let request_semaphore = self.request_semaphore.clone();
         let base_service = self.base_service.clone();
+        let in_flight = self.in_flight.clone();
+        let circuit_breaker = self.circuit_breaker.clone();
+        let pool_size = self.pool_size.clone();
 
         tokio::spawn(async move {
-            let request_handlers: Arc<Mutex<HashMap<String, (AIRequest, mpsc::Sender<AIResponse>)>>> = Arc::new(Mutex::new(HashMap::new()));
-            
             loop {
                 tokio::select! {
                     _ = shutdown_receiver.recv() => {
@@ -327,96 +668,185 @@ impl OptimizedAIService {
                         break;
                     }
                     _ = tokio::time::sleep(Duration::from_millis(10)) => {
-                        if let Some((request, response_sender)) = Self::get_next_request(&priority_queues).await {
-                            let permit = match request_semaphore.clone().try_acquire_owned() {
-                                Ok(permit) => permit,
-                                Err(_) => continue, // No available slots
-                            };
+                        // Take a slot before dequeuing so a busy pool never drops a request
+                        let permit = match request_semaphore.clone().try_acquire_owned() {
+                            Ok(permit) => permit,
+                            Err(_) => continue, // No available slots
+                        };
+                        if in_flight.lock().await.len() >= pool_size.lock().await.size() {
+                            continue; // Adaptive limit reached
+                        }
+                        let Some(QueuedRequest { request, response_sender }) =
+                            Self::get_next_request(&priority_queues, &scheduler, &in_flight, &stats).await
+                        else {
+                            continue;
+                        };
+
+                        let request_id = request.id.clone();
+                        if !circuit_breaker.lock().await.try_acquire() {
+                            in_flight.lock().await.remove(&request_id);
+                            let _ = response_sender.send(AIResponse::circuit_open(request_id)).await;
+                            continue;
+                        }
+                        let cache_key = Self::generate_cache_key(&request);
+
+                        // Process request in background
+                        let client_pool_clone = client_pool.clone();
+                        let response_cache_clone = response_cache.clone();
+                        let stats_clone = stats.clone();
+                        let response_times_clone = response_times.clone();
+                        let base_service_clone = base_service.clone();
+                        let in_flight_clone = in_flight.clone();
+                        let circuit_breaker_clone = circuit_breaker.clone();
+                        let pool_size_clone = pool_size.clone();
+                        let priority_queues_clone = priority_queues.clone();
+
+                        tokio::spawn(async move {
+                            let _permit = permit; // Keep permit alive
+                            let result = Self::process_single_request(
+                                request,
+                                client_pool_clone,
+                                base_service_clone,
+                            ).await;
+                            in_flight_clone.lock().await.remove(&request_id);
 
-                            let request_id = request.id.clone();
                             {
-                                let mut handlers = request_handlers.lock().await;
-                                handlers.insert(request_id.clone(), (request.clone(), response_sender));
+                                let mut breaker = circuit_breaker_clone.lock().await;
+                                match &result {
+                                    Ok(response) if response.is_cancelled() => breaker.release(),
+                                    Ok(response) if response.success => breaker.record_success(),
+                                    _ => breaker.record_failure(),
+                                }
                             }
 
-                            // Process request in background
-                            let client_pool_clone = client_pool.clone();
-                            let response_cache_clone = response_cache.clone();
-                            let stats_clone = stats.clone();
-                            let response_times_clone = response_times.clone();
-                            let base_service_clone = base_service.clone();
-                            let request_handlers_clone = request_handlers.clone();
-
-                            tokio::spawn(async move {
-                                let _permit = permit; // Keep permit alive
-                                let result = Self::process_single_request(
-                                    request,
-                                    client_pool_clone,
-                                    base_service_clone,
-                                ).await;
-
-                                match result {
-                                    Ok(response) => {
-                                        // Cache successful responses
-                                        Self::cache_response(&response_cache_clone, &response).await;
-                                        
-                                        // Update stats
-                                        Self::update_stats(&stats_clone, &response_times_clone, &response).await;
-                                        
-                                        // Send response
-                                        if let Some((_, sender)) = {
-                                            let mut handlers = request_handlers_clone.lock().await;
-                                            handlers.remove(&request_id)
-                                        } {
-                                            let _ = sender.send(response).await;
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!("Request processing failed: {}", e);
-                                        Self::update_failed_stats(&stats_clone).await;
-                                        
-                                        if let Some((_, sender)) = {
-                                            let mut handlers = request_handlers_clone.lock().await;
-                                            handlers.remove(&request_id)
-                                        } {
-                                            let error_response = AIResponse {
-                                                id: Uuid::new_v4().to_string(),
-                                                request_id,
-                                                content: String::new(),
-                                                model_used: "error".to_string(),
-                                                processing_time: Duration::default(),
-                                                tokens_used: None,
-                                                success: false,
-                                                error: Some(e.to_string()),
-                                            };
-                                            let _ = sender.send(error_response).await;
-                                        }
+                            match &result {
+                                Ok(response) if response.is_cancelled() => {}
+                                Ok(response) if response.success => {
+                                    let pending: usize = priority_queues_clone.lock().await.values().map(VecDeque::len).sum();
+                                    pool_size_clone.lock().await.record_success(response.processing_time, pending);
+                                }
+                                _ => pool_size_clone.lock().await.record_failure(),
+                            }
+
+                            match result {
+                                Ok(response) if response.is_cancelled() => {
+                                    Self::update_cancelled_stats(&stats_clone).await;
+                                    let _ = response_sender.send(response).await;
+                                }
+                                Ok(response) => {
+                                    // Cache successful responses
+                                    if response.success {
+                                        response_cache_clone.lock().await.insert(cache_key, response.clone());
                                     }
+
+                                    // Update stats
+                                    Self::update_stats(&stats_clone, &response_times_clone, &response).await;
+
+                                    let _ = response_sender.send(response).await;
                                 }
-                            });
-                        }
+                                Err(e) => {
+                                    error!("Request processing failed: {}", e);
+                                    Self::update_failed_stats(&stats_clone).await;
+
+                                    let error_response = AIResponse {
+                                        id: Uuid::new_v4().to_string(),
+                                        request_id,
+                                        content: String::new(),
+                                        model_used: "error".to_string(),
+                                        processing_time: Duration::default(),
+                                        tokens_used: None,
+                                        usage: None,
+                                        success: false,
+                                        error: Some(e.to_string()),
+                                    };
+                                    let _ = response_sender.send(error_response).await;
+                                }
+                            }
+                        });
                     }
                 }
             }
         })
     }
 
+    /// Pop the next request chosen by the fair scheduler and mark it in flight while the queues
+    /// are still locked, so `cancel_request` always finds it in one place or the other
     async fn get_next_request(
-        priority_queues: &Arc<Mutex<HashMap<RequestPriority, VecDeque<AIRequest>>>>
-    ) -> Option<(AIRequest, mpsc::Sender<AIResponse>)> {
+        priority_queues: &Arc<Mutex<HashMap<RequestPriority, VecDeque<QueuedRequest>>>>,
+        scheduler: &Arc<Mutex<FairScheduler>>,
+        in_flight: &Arc<Mutex<HashMap<String, CancellationToken>>>,
+        stats: &Arc<RwLock<PoolStats>>,
+    ) -> Option<QueuedRequest> {
         let mut queues = priority_queues.lock().await;
-        
-        // Process in priority order
-        for priority in [RequestPriority::Critical, RequestPriority::High, RequestPriority::Normal, RequestPriority::Background] {
-            if let Some(queue) = queues.get_mut(&priority) {
-                if let Some(request) = queue.pop_front() {
-                    // Create response channel
-                    let (tx, _rx) = mpsc::channel(1);
-                    return Some((request, tx));
-                }
+
+        let ready: Vec<RequestPriority> = RequestPriority::ALL
+            .into_iter()
+            .filter(|priority| queues.get(priority).is_some_and(|queue| !queue.is_empty()))
+            .collect();
+        let priority = scheduler.lock().await.pick(&ready)?;
+        let queued = queues.get_mut(&priority)?.pop_front()?;
+
+        in_flight
+            .lock()
+            .await
+            .insert(queued.request.id.clone(), queued.request.cancel_token.clone());
+        *stats
+            .write()
+            .await
+            .dispatched_by_priority
+            .entry(format!("{:?}", priority))
+            .or_insert(0) += 1;
+        Some(queued)
+    }
+
+    /// Cancel a queued or in-flight request; its submitter receives a `cancelled` error response
+    pub async fn cancel_request(&self, request_id: &str) -> bool {
+        let mut queues = self.priority_queues.lock().await;
+
+        for queue in queues.values_mut() {
+            if let Some(position) = queue.iter().position(|queued| queued.request.id == request_id) {
+                let queued = queue.remove(position).expect("position is in bounds");
+                drop(queues);
+
+                self.request_queue.lock().await.retain(|request| request.id != request_id);
+                Self::update_cancelled_stats(&self.stats).await;
+                let _ = queued.response_sender.try_send(AIResponse::cancelled(request_id.to_string()));
+                return true;
             }
         }
-        None
+
+        // Dispatched requests send their own cancelled response once the HTTP call is aborted
+        match self.in_flight.lock().await.get(request_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Quiesce before the service is replaced: reject new submissions, answer every queued
+    /// request with a restarting error, then drain dispatched ones as `AIService` does
+    pub async fn drain(&self, timeout: Duration) -> DrainSummary {
+        let gate = self.base_service.gate.clone();
+        gate.close();
+
+        let flushed: Vec<QueuedRequest> = self
+            .priority_queues
+            .lock()
+            .await
+            .values_mut()
+            .flat_map(|queue| queue.drain(..))
+            .collect();
+        self.request_queue.lock().await.clear();
+        for queued in &flushed {
+            let _ = queued.response_sender.try_send(AIResponse::rejected(queued.request.id.clone(), RESTARTING_ERROR));
+        }
+        if !flushed.is_empty() {
+            info!("Flushed {} queued AI request(s) before restart", flushed.len());
+        }
+
+        DrainSummary { flushed: flushed.len(), ..gate.drain(timeout).await }
     }
 
     async fn process_single_request(
@@ -429,24 +859,33 @@ impl OptimizedAIService {
         // Get HTTP client from pool
         let (client_index, _client) = client_pool.get_client().await?;
         
-        let response = match base_service.chat(&request.prompt, request.context.as_deref()).await {
-            Ok(content) => AIResponse {
+        // Dropping the chat future on cancellation aborts the underlying HTTP request
+        let result = tokio::select! {
+            _ = request.cancel_token.cancelled() => None,
+            result = base_service.chat_with_usage(&request.prompt, request.context.as_deref()) => Some(result),
+        };
+
+        let response = match result {
+            None => AIResponse::cancelled(request.id),
+            Some(Ok(reply)) => AIResponse {
                 id: Uuid::new_v4().to_string(),
                 request_id: request.id,
-                content,
-                model_used: request.model.unwrap_or_else(|| "default".to_string()),
+                content: reply.content,
+                model_used: reply.model,
                 processing_time: start_time.elapsed(),
-                tokens_used: None, // Would need to implement token counting
+                tokens_used: reply.usage.map(|usage| usage.total()),
+                usage: reply.usage,
                 success: true,
                 error: None,
             },
-            Err(e) => AIResponse {
+            Some(Err(e)) => AIResponse {
                 id: Uuid::new_v4().to_string(),
                 request_id: request.id,
                 content: String::new(),
                 model_used: request.model.unwrap_or_else(|| "error".to_string()),
                 processing_time: start_time.elapsed(),
                 tokens_used: None,
+                usage: None,
                 success: false,
                 error: Some(e.to_string()),
             },
@@ -458,7 +897,7 @@ impl OptimizedAIService {
         Ok(response)
     }
 
-    async fn enqueue_request(&self, request: AIRequest, _response_sender: mpsc::Sender<AIResponse>) -> Result<()> {
+    async fn enqueue_request(&self, request: AIRequest, response_sender: mpsc::Sender<AIResponse>) -> Result<()> {
         // Add to main request queue for tracking
         {
             let mut main_queue = self.request_queue.lock().await;
@@ -468,8 +907,7 @@ impl OptimizedAIService {
         // Add to priority queue for processing
         let mut queues = self.priority_queues.lock().await;
         if let Some(queue) = queues.get_mut(&request.priority) {
-            queue.push_back(request);
-            // Store response sender - in real implementation, would use a better approach
+            queue.push_back(QueuedRequest { request, response_sender });
             Ok(())
         } else {
             Err(anyhow::anyhow!("Invalid request priority"))
@@ -477,47 +915,16 @@ impl OptimizedAIService {
     }
 
     async fn get_cached_response(&self, request: &AIRequest) -> Option<AIResponse> {
-        let cache = self.response_cache.read().await;
-        let cache_key = self.generate_cache_key(request);
-        
-        if let Some((response, cached_at)) = cache.get(&cache_key) {
-            // Check if cache is still valid (5 minutes)
-            if cached_at.elapsed() < Duration::from_secs(300) {
-                Some(response.clone())
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+        let cache_key = Self::generate_cache_key(request);
+        self.response_cache.lock().await.get(&cache_key)
     }
 
-    async fn cache_response(cache: &Arc<RwLock<HashMap<String, (AIResponse, Instant)>>>, response: &AIResponse) {
-        let mut cache = cache.write().await;
-        let cache_key = response.request_id.clone(); // Simplified cache key
-        cache.insert(cache_key, (response.clone(), Instant::now()));
-        
-        // Prevent cache from growing too large
-        if cache.len() > 1000 {
-            // Remove oldest 20% of entries
-            let to_remove: Vec<String> = cache
-                .iter()
-                .map(|(k, (_, t))| (k.clone(), *t))
-                .collect::<Vec<_>>()
-                .into_iter()
-                .min_by_key(|(_, t)| *t)
-                .into_iter()
-                .take(200)
-                .map(|(k, _)| k)
-                .collect();
-            
-            for key in to_remove {
-                cache.remove(&key);
-            }
-        }
+    /// Hit/miss counters for the response cache
+    pub async fn cache_metrics(&self) -> CacheMetrics {
+        self.response_cache.lock().await.metrics()
     }
 
-    fn generate_cache_key(&self, request: &AIRequest) -> String {
+    fn generate_cache_key(request: &AIRequest) -> String {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
         
@@ -555,6 +962,10 @@ impl OptimizedAIService {
         stats.failed_requests += 1;
     }
 
+    async fn update_cancelled_stats(stats: &Arc<RwLock<PoolStats>>) {
+        stats.write().await.cancelled_requests += 1;
+    }
+
     /// Start cache cleanup background task
     async fn start_cache_cleanup(&self) -> tokio::task::JoinHandle<()> {
         let cache = self.response_cache.clone();
@@ -565,11 +976,8 @@ impl OptimizedAIService {
             loop {
                 interval.tick().await;
                 
-                let mut cache = cache.write().await;
-                let now = Instant::now();
-                
-                // Remove expired entries (older than 10 minutes)
-                cache.retain(|_, (_, cached_at)| now.duration_since(*cached_at) < Duration::from_secs(600));
+                let mut cache = cache.lock().await;
+                cache.purge_expired();
                 
                 debug!("Cache cleanup completed. Entries remaining: {}", cache.len());
             }
@@ -608,16 +1016,22 @@ impl OptimizedAIService {
 
     /// Get current service statistics as PoolStats struct
     pub async fn get_pool_stats(&self) -> PoolStats {
-        self.stats.read().await.clone()
+        let mut stats = self.stats.read().await.clone();
+        stats.circuit_state = self.circuit_state().await;
+        let pool_size = self.pool_size.lock().await;
+        stats.pool_size = pool_size.size();
+        stats.latency_ewma_ms = pool_size.latency_ewma_ms();
+        stats
+    }
+
+    pub async fn circuit_state(&self) -> CircuitState {
+        self.circuit_breaker.lock().await.state()
     }
 
     /// Force cleanup of cache and queues
     pub async fn force_cleanup(&self) {
         // Clear cache
-        {
-            let mut cache = self.response_cache.write().await;
-            cache.clear();
-        }
+        self.response_cache.lock().await.clear();
         
         // Clear old response times
         {
@@ -626,6 +1040,9 @@ impl OptimizedAIService {
                 times.truncate(10);
             }
         }
+
+        // Start sizing over from the configured minimum
+        self.pool_size.lock().await.reset();
         
         info!("Forced cleanup completed");
     }
@@ -646,12 +1063,13 @@ impl OptimizedAIService {
     pub async fn get_stats(&self) -> String {
         let stats = self.stats.read().await;
         format!(
-            "Active: {}, Idle: {}, Pending: {}, Processed: {}, Failed: {}, Avg Time: {:.2}ms",
+            "Active: {}, Idle: {}, Pending: {}, Processed: {}, Failed: {}, Cancelled: {}, Avg Time: {:.2}ms",
             stats.active_connections,
             stats.idle_connections,
             stats.pending_requests,
             stats.processed_requests,
             stats.failed_requests,
+            stats.cancelled_requests,
             stats.average_response_time
         )
     }
@@ -661,6 +1079,25 @@ impl OptimizedAIService {
         self.force_cleanup().await;
     }
     
+    /// Replace the service in `slot` after draining it with `drain`; see `ai::replace_service`
+    pub async fn replace(slot: &RwLock<OptimizedAIService>, replacement: OptimizedAIService, drain_timeout: Duration) -> Result<DrainSummary> {
+        let (summary, gate) = {
+            let service = slot.read().await;
+            (service.drain(drain_timeout).await, service.base_service.gate.clone())
+        };
+        match timeout(SWAP_LOCK_TIMEOUT, slot.write()).await {
+            Ok(mut service) => {
+                // Dropping the old service stops its request processor
+                *service = replacement;
+                Ok(summary)
+            }
+            Err(_) => {
+                gate.reopen();
+                Err(anyhow::anyhow!("Optimized AI service is still in use after draining requests; restart abandoned"))
+            }
+        }
+    }
+
     /// Start background tasks (should be called after initialization)
     pub async fn start_background_tasks(&mut self) -> Result<()> {
         if !self.background_tasks.is_empty() {
@@ -703,3 +1140,228 @@ impl Drop for OptimizedAIService {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_flushes_queue_and_rejects_new_requests() {
+        // No request processor, so submissions stay queued
+        let service = OptimizedAIService::new_with_config(&AIConfig::default()).await.unwrap();
+        let mut first = service.submit_request_async(AIRequest::simple("first".to_string())).await.unwrap();
+        let mut second = service
+            .submit_request_async(AIRequest::simple("second".to_string()).with_priority(RequestPriority::Low))
+            .await
+            .unwrap();
+
+        let summary = service.drain(Duration::from_millis(100)).await;
+        assert_eq!(summary, DrainSummary { drained: 0, cancelled: 0, flushed: 2 });
+        for receiver in [&mut first, &mut second] {
+            assert_eq!(receiver.recv().await.unwrap().error.as_deref(), Some(RESTARTING_ERROR));
+        }
+        assert!(service.priority_queues.lock().await.values().all(VecDeque::is_empty));
+
+        let mut late = service.submit_request_async(AIRequest::simple("late".to_string())).await.unwrap();
+        assert_eq!(late.recv().await.unwrap().error.as_deref(), Some(RESTARTING_ERROR));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_queued_request() {
+        let service = OptimizedAIService::new_with_config(&AIConfig::default()).await.unwrap();
+
+        let request = AIRequest::simple("summarize the build log".to_string())
+            .with_priority(RequestPriority::Background);
+        let request_id = request.id.clone();
+        let mut rx = service.submit_request(request).await.unwrap();
+
+        assert!(service.cancel_request(&request_id).await);
+
+        let response = rx.recv().await.unwrap();
+        assert_eq!(response.request_id, request_id);
+        assert!(!response.success);
+        assert!(response.is_cancelled());
+
+        let stats = service.get_pool_stats().await;
+        assert_eq!(stats.cancelled_requests, 1);
+        assert!(service.priority_queues.lock().await.values().all(|queue| queue.is_empty()));
+        assert!(!service.cancel_request(&request_id).await);
+    }
+
+    #[test]
+    fn test_scheduler_shares_follow_weights() {
+        let mut scheduler = FairScheduler::new(QueueWeightsConfig::default());
+        let ready = [RequestPriority::Critical, RequestPriority::Low];
+        let picks: Vec<RequestPriority> = (0..18).filter_map(|_| scheduler.pick(&ready)).collect();
+        assert_eq!(picks.iter().filter(|p| **p == RequestPriority::Low).count(), 2);
+
+        // With nothing else waiting, Critical gets every slot
+        assert!((0..10).all(|_| scheduler.pick(&[RequestPriority::Critical]) == Some(RequestPriority::Critical)));
+        assert_eq!(scheduler.pick(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_low_priority_dispatches_under_critical_flood() {
+        let service = OptimizedAIService::new_with_config(&AIConfig::default()).await.unwrap();
+        let mut receivers = Vec::new();
+        let submit = |priority| AIRequest::simple("work".to_string()).with_priority(priority);
+
+        for _ in 0..50 {
+            let (tx, rx) = mpsc::channel(1);
+            service.enqueue_request(submit(RequestPriority::Critical), tx).await.unwrap();
+            receivers.push(rx);
+        }
+        let mut low_ids = Vec::new();
+        for _ in 0..3 {
+            let request = submit(RequestPriority::Low);
+            low_ids.push(request.id.clone());
+            let (tx, rx) = mpsc::channel(1);
+            service.enqueue_request(request, tx).await.unwrap();
+            receivers.push(rx);
+        }
+
+        // Critical work keeps arriving as fast as it's dispatched
+        let mut low_cycles = Vec::new();
+        for cycle in 1..=60 {
+            let queued = OptimizedAIService::get_next_request(
+                &service.priority_queues,
+                &service.scheduler,
+                &service.in_flight,
+                &service.stats,
+            )
+            .await
+            .unwrap();
+            if low_ids.contains(&queued.request.id) {
+                low_cycles.push(cycle);
+            }
+            let (tx, rx) = mpsc::channel(1);
+            service.enqueue_request(submit(RequestPriority::Critical), tx).await.unwrap();
+            receivers.push(rx);
+        }
+
+        // Weights 16:2 give Low one slot in every 9
+        assert_eq!(low_cycles.len(), 3, "{:?}", low_cycles);
+        assert!(low_cycles.iter().enumerate().all(|(i, cycle)| *cycle <= (i + 1) * 9), "{:?}", low_cycles);
+
+        let stats = service.get_pool_stats().await;
+        assert_eq!(stats.dispatched_by_priority["Low"], 3);
+        assert_eq!(stats.dispatched_by_priority["Critical"], 57);
+    }
+
+    fn pool_config() -> ConnectionPoolConfig {
+        ConnectionPoolConfig {
+            min_connections: 2,
+            max_connections: 6,
+            target_latency_ms: 1000,
+        }
+    }
+
+    #[test]
+    fn test_pool_grows_under_load_up_to_max() {
+        let mut pool = AdaptivePoolSize::new(pool_config());
+        assert_eq!(pool.size(), 2);
+
+        // Nothing waiting: no reason to grow
+        pool.record_success(Duration::from_millis(200), 0);
+        assert_eq!(pool.size(), 2);
+
+        for _ in 0..20 {
+            pool.record_success(Duration::from_millis(200), 50);
+            assert!(pool.size() <= 6);
+        }
+        assert_eq!(pool.size(), 6);
+        assert!((pool.latency_ewma_ms() - 200.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pool_shrinks_on_latency_spike_and_failures() {
+        let mut pool = AdaptivePoolSize::new(pool_config());
+        for _ in 0..4 {
+            pool.record_success(Duration::from_millis(100), 10);
+        }
+        assert_eq!(pool.size(), 6);
+
+        // One slow reply pulls the average over target
+        pool.record_success(Duration::from_millis(8000), 10);
+        assert!(pool.latency_ewma_ms() > 1000.0);
+        assert_eq!(pool.size(), 3);
+        for _ in 0..5 {
+            pool.record_success(Duration::from_millis(8000), 10);
+            assert!(pool.size() >= 2);
+        }
+        assert_eq!(pool.size(), 2);
+
+        pool.reset();
+        for _ in 0..2 {
+            pool.record_success(Duration::from_millis(100), 10);
+        }
+        assert_eq!(pool.size(), 4);
+        pool.record_failure();
+        assert_eq!(pool.size(), 2);
+        pool.record_failure();
+        assert_eq!(pool.size(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pool_stats_report_size_and_cleanup_resets_to_min() {
+        let config = AIConfig {
+            pool: ConnectionPoolConfig { min_connections: 3, ..pool_config() },
+            ..AIConfig::default()
+        };
+        let service = OptimizedAIService::new_with_config(&config).await.unwrap();
+        assert_eq!(service.get_pool_stats().await.pool_size, 3);
+
+        service.pool_size.lock().await.record_success(Duration::from_millis(100), 5);
+        let stats = service.get_pool_stats().await;
+        assert_eq!(stats.pool_size, 4);
+        assert!((stats.latency_ewma_ms - 100.0).abs() < 1e-6);
+
+        service.force_cleanup().await;
+        assert_eq!(service.get_pool_stats().await.pool_size, 3);
+    }
+
+    #[test]
+    fn test_circuit_breaker_trips_and_recovers() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_millis(50));
+
+        for _ in 0..2 {
+            assert!(breaker.try_acquire());
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire());
+
+        // A failed probe re-opens for another cooldown
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // A successful probe closes it
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.try_acquire());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire());
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_rejects_new_requests() {
+        let config = AIConfig {
+            circuit_failure_threshold: 1,
+            ..AIConfig::default()
+        };
+        let service = OptimizedAIService::new_with_config(&config).await.unwrap();
+        service.circuit_breaker.lock().await.record_failure();
+
+        let mut rx = service.submit_request(AIRequest::simple("ping".to_string())).await.unwrap();
+        let response = rx.recv().await.unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error.as_deref(), Some(CIRCUIT_OPEN_ERROR));
+        assert_eq!(service.get_pool_stats().await.circuit_state, CircuitState::Open);
+        assert!(service.priority_queues.lock().await.values().all(|queue| queue.is_empty()));
+    }
+}
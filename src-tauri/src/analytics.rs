@@ -1,7 +1,16 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use chrono::{DateTime, Utc, Duration};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use chrono::{DateTime, Datelike, Utc, Duration};
+
+use crate::cache::CacheMetrics;
+use crate::quantile_sketch::QuantileSketch;
+
+/// Relative accuracy of the percentile sketches kept for timer and histogram series
+const SKETCH_RELATIVE_ACCURACY: f64 = 0.01;
+/// Up to this many points in range, percentiles are computed exactly
+const EXACT_PERCENTILE_LIMIT: usize = 2000;
 
 // Missing types expected by main.rs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,6 +70,75 @@ pub struct AnalyticsEngine {
     reports: HashMap<String, AnalyticsReport>,
     performance_profiles: HashMap<String, PerformanceProfile>,
     optimization_suggestions: Vec<OptimizationSuggestion>,
+    anomaly_config: AnomalyConfig,
+    /// Hour (seconds since epoch / 3600) -> sketch, for timer and histogram series
+    sketches: HashMap<String, BTreeMap<i64, QuantileSketch>>,
+    /// User-defined metrics, keyed by name
+    custom_metrics: BTreeMap<String, MetricDefinition>,
+    definitions_path: Option<PathBuf>,
+}
+
+/// Metric recording AI token consumption, tagged with `model` and `kind` (`prompt` or `completion`)
+pub const AI_TOKENS_METRIC: &str = "ai_tokens";
+
+/// Token consumption per model over a time range
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsageSummary {
+    pub models: Vec<ModelTokenUsage>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelTokenUsage {
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+/// A percentile together with how far it may be from the exact value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PercentileEstimate {
+    pub value: f64,
+    /// Maximum absolute difference from the exact percentile
+    pub error_bound: f64,
+    pub exact: bool,
+    pub sample_count: usize,
+}
+
+/// Tuning for rolling z-score anomaly detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyConfig {
+    /// Absolute z-score above which a point is anomalous
+    pub z_threshold: f64,
+    /// Number of preceding points the mean and deviation are computed over
+    pub window_size: usize,
+    /// Smallest usable window; shorter histories are skipped
+    pub min_window_size: usize,
+    /// How many of the latest points are scored
+    pub recent_points: usize,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            z_threshold: 3.0,
+            window_size: 50,
+            min_window_size: 10,
+            recent_points: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ScoredPoint {
+    value: f64,
+    mean: f64,
+    z_score: f64,
+    window_size: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,7 +150,7 @@ pub struct MetricSeries {
     pub retention_days: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MetricType {
     Counter,
     Gauge,
@@ -88,7 +166,7 @@ pub struct DataPoint {
     pub tags: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AggregationType {
     Sum,
     Average,
@@ -98,6 +176,32 @@ pub enum AggregationType {
     Percentile(f64),
 }
 
+/// Longest retention a custom metric may ask for
+const MAX_RETENTION_DAYS: u32 = 3650;
+
+/// Configuration of a user-defined metric series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDefinition {
+    pub name: String,
+    pub metric_type: MetricType,
+    pub aggregation: AggregationType,
+    pub retention_days: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A registered series as listed to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricInfo {
+    pub name: String,
+    pub metric_type: MetricType,
+    pub aggregation: AggregationType,
+    pub retention_days: u32,
+    pub custom: bool,
+    pub data_points: usize,
+    pub last_value: Option<f64>,
+    pub last_recorded: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Insight {
     pub id: String,
@@ -110,6 +214,9 @@ pub struct Insight {
     pub time_range: TimeRange,
     pub created_at: DateTime<Utc>,
     pub actions: Vec<RecommendedAction>,
+    /// Detector-specific details, e.g. the z-score behind an anomaly
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -217,6 +324,36 @@ pub enum ChartType {
     Heatmap,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "text/html",
+            ReportFormat::Pdf => "application/pdf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedReport {
+    pub file_name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReportSummary {
     pub total_metrics: u32,
@@ -328,6 +465,60 @@ pub enum PatternType {
     EventDriven,
 }
 
+/// A prioritized step in an action plan; related insights and suggestions collapse into one item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionPlanItem {
+    pub area: String,
+    pub title: String,
+    pub description: String,
+    pub impact: f64,
+    pub complexity: ComplexityLevel,
+    pub priority_score: f64,
+    pub steps: Vec<String>,
+    pub related: Vec<String>,
+    pub source_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionPlan {
+    pub items: Vec<ActionPlanItem>,
+    pub ai_enriched: bool,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl ActionPlan {
+    /// Prompt asking the AI for concrete steps per plan area, answered as a JSON object
+    pub fn enrichment_prompt(&self) -> String {
+        let mut prompt = String::from(
+            "Suggest concrete, actionable steps for each item of this terminal performance action plan. \
+             Reply only with a JSON object mapping each area to an array of steps.\n\n",
+        );
+        for item in &self.items {
+            prompt.push_str(&format!("- {}: {} ({})\n", item.area, item.title, item.description));
+        }
+        prompt
+    }
+
+    /// Replace rule-based steps with those from an AI response; returns false if nothing applied
+    pub fn apply_ai_steps(&mut self, response: &str) -> bool {
+        let json = match (response.find('{'), response.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &response[start..=end],
+            _ => return false,
+        };
+        let Ok(steps_by_area) = serde_json::from_str::<HashMap<String, Vec<String>>>(json) else {
+            return false;
+        };
+
+        for item in &mut self.items {
+            if let Some(steps) = steps_by_area.get(&item.area).filter(|steps| !steps.is_empty()) {
+                item.steps = steps.clone();
+                self.ai_enriched = true;
+            }
+        }
+        self.ai_enriched
+    }
+}
+
 #[allow(dead_code)]
 impl AnalyticsEngine {
     pub fn new() -> Self {
@@ -337,7 +528,153 @@ impl AnalyticsEngine {
             reports: HashMap::new(),
             performance_profiles: HashMap::new(),
             optimization_suggestions: Vec::new(),
+            anomaly_config: AnomalyConfig::default(),
+            sketches: HashMap::new(),
+            custom_metrics: BTreeMap::new(),
+            definitions_path: None,
+        }
+    }
+
+    /// Keep custom metric definitions in `path`, registering any it already holds
+    pub fn load_metric_definitions(&mut self, path: PathBuf) -> Result<()> {
+        self.definitions_path = Some(path.clone());
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let definitions: Vec<MetricDefinition> = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        for definition in definitions {
+            self.register_definition(definition);
+        }
+        Ok(())
+    }
+
+    /// Register a custom metric, or update the aggregation and retention of an existing one
+    pub fn define_metric(
+        &mut self,
+        name: &str,
+        metric_type: MetricType,
+        aggregation: AggregationType,
+        retention_days: u32,
+    ) -> Result<MetricDefinition> {
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-')) {
+            return Err(anyhow!("Invalid metric name '{}': use letters, digits, '_', '.', ':' or '-'", name));
+        }
+        if retention_days == 0 || retention_days > MAX_RETENTION_DAYS {
+            return Err(anyhow!("Retention must be between 1 and {} days", MAX_RETENTION_DAYS));
+        }
+        if let AggregationType::Percentile(p) = aggregation {
+            if !(0.0..=100.0).contains(&p) {
+                return Err(anyhow!("Percentile aggregation must be between 0 and 100, got {}", p));
+            }
         }
+
+        let created_at = match self.custom_metrics.get(name) {
+            Some(existing) if existing.metric_type != metric_type => {
+                return Err(anyhow!(
+                    "Metric '{}' is already defined as {:?} and cannot become {:?}",
+                    name,
+                    existing.metric_type,
+                    metric_type
+                ));
+            }
+            Some(existing) => existing.created_at,
+            None if self.metrics.contains_key(name) => {
+                return Err(anyhow!("Metric '{}' is a built-in metric", name));
+            }
+            None => Utc::now(),
+        };
+
+        let definition = MetricDefinition { name: name.to_string(), metric_type, aggregation, retention_days, created_at };
+        let previous = self.custom_metrics.get(name).cloned();
+        self.register_definition(definition.clone());
+        if let Err(e) = self.save_metric_definitions() {
+            // Keep memory and disk in agreement
+            match previous {
+                Some(previous) => self.register_definition(previous),
+                None => {
+                    self.custom_metrics.remove(name);
+                    self.metrics.remove(name);
+                }
+            }
+            return Err(e);
+        }
+        Ok(definition)
+    }
+
+    /// Record a value for a custom metric, checking it suits the metric's type
+    pub fn record_custom(&mut self, name: &str, value: f64, tags: HashMap<String, String>) -> Result<()> {
+        let definition = self
+            .custom_metrics
+            .get(name)
+            .ok_or_else(|| anyhow!("Metric '{}' is not defined; define it before recording values", name))?;
+        if !value.is_finite() {
+            return Err(anyhow!("Metric '{}' only accepts finite values", name));
+        }
+        match definition.metric_type {
+            // Counters record increments, so a drop would be a gauge-style reset
+            MetricType::Counter if value < 0.0 => {
+                return Err(anyhow!("Counter '{}' cannot decrease; record a gauge for values that go down", name));
+            }
+            MetricType::Timer | MetricType::Rate if value < 0.0 => {
+                return Err(anyhow!("{:?} '{}' cannot be negative", definition.metric_type, name));
+            }
+            _ => {}
+        }
+
+        let metric_type = definition.metric_type.clone();
+        let data_point = DataPoint { timestamp: Utc::now(), value, tags };
+        self.record_point(name.to_string(), metric_type, data_point);
+        Ok(())
+    }
+
+    /// Every series the engine knows about, built-in and custom, sorted by name
+    pub fn list_metrics(&self) -> Vec<MetricInfo> {
+        let mut metrics: Vec<MetricInfo> = self
+            .metrics
+            .values()
+            .map(|series| {
+                let last = series.data_points.last();
+                MetricInfo {
+                    name: series.name.clone(),
+                    metric_type: series.metric_type.clone(),
+                    aggregation: series.aggregation.clone(),
+                    retention_days: series.retention_days,
+                    custom: self.custom_metrics.contains_key(&series.name),
+                    data_points: series.data_points.len(),
+                    last_value: last.map(|dp| dp.value),
+                    last_recorded: last.map(|dp| dp.timestamp),
+                }
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        metrics
+    }
+
+    fn register_definition(&mut self, definition: MetricDefinition) {
+        let series = self.metrics.entry(definition.name.clone()).or_insert_with(|| MetricSeries {
+            name: definition.name.clone(),
+            metric_type: definition.metric_type.clone(),
+            data_points: Vec::new(),
+            aggregation: definition.aggregation.clone(),
+            retention_days: definition.retention_days,
+        });
+        series.aggregation = definition.aggregation.clone();
+        series.retention_days = definition.retention_days;
+        self.custom_metrics.insert(definition.name.clone(), definition);
+    }
+
+    fn save_metric_definitions(&self) -> Result<()> {
+        let Some(path) = &self.definitions_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let definitions: Vec<&MetricDefinition> = self.custom_metrics.values().collect();
+        std::fs::write(path, serde_json::to_string_pretty(&definitions)?)?;
+        Ok(())
     }
 
     pub fn record_metric(&mut self, name: String, value: f64, tags: HashMap<String, String>) {
@@ -346,28 +683,112 @@ impl AnalyticsEngine {
             value,
             tags,
         };
+        self.record_point(name, MetricType::Gauge, data_point);
+    }
+
+    /// Record a duration; timer series keep incremental percentile sketches
+    pub fn record_timer(&mut self, name: String, value: f64, tags: HashMap<String, String>) {
+        let data_point = DataPoint {
+            timestamp: Utc::now(),
+            value,
+            tags,
+        };
+        self.record_point(name, MetricType::Timer, data_point);
+    }
 
-        if let Some(series) = self.metrics.get_mut(&name) {
-            series.data_points.push(data_point);
-            
-            // Apply retention policy
-            let cutoff = Utc::now() - Duration::days(series.retention_days as i64);
+    /// Append a point, creating the series with `metric_type` if it does not exist yet
+    fn record_point(&mut self, name: String, metric_type: MetricType, data_point: DataPoint) {
+        let series = self.metrics.entry(name.clone()).or_insert_with(|| MetricSeries {
+            name,
+            metric_type,
+            data_points: Vec::new(),
+            aggregation: AggregationType::Average,
+            retention_days: 30,
+        });
+        let cutoff = Utc::now() - Duration::days(series.retention_days as i64);
+
+        if is_sketched(&series.metric_type) {
+            let hours = self.sketches.entry(series.name.clone()).or_default();
+            hours
+                .entry(sketch_hour(data_point.timestamp))
+                .or_insert_with(|| QuantileSketch::new(SKETCH_RELATIVE_ACCURACY))
+                .add(data_point.value);
+            while let Some(oldest) = hours.first_entry() {
+                if *oldest.key() >= sketch_hour(cutoff) {
+                    break;
+                }
+                oldest.remove();
+            }
+        }
+
+        series.data_points.push(data_point);
+
+        // Apply retention policy; points are chronological, so only the oldest needs checking
+        if series.data_points.first().is_some_and(|dp| dp.timestamp <= cutoff) {
             series.data_points.retain(|dp| dp.timestamp > cutoff);
-        } else {
-            // Create new metric series
-            let series = MetricSeries {
-                name: name.clone(),
-                metric_type: MetricType::Gauge,
-                data_points: vec![data_point],
-                aggregation: AggregationType::Average,
-                retention_days: 30,
-            };
-            self.metrics.insert(name, series);
         }
     }
 
+    /// Record a cache's hit/miss counters, tagged with the cache name
+    pub fn record_cache_metrics(&mut self, cache_name: &str, metrics: &CacheMetrics) {
+        let mut tags = HashMap::new();
+        tags.insert("cache".to_string(), cache_name.to_string());
+
+        self.record_metric("cache_hits".to_string(), metrics.hits as f64, tags.clone());
+        self.record_metric("cache_misses".to_string(), metrics.misses as f64, tags.clone());
+        self.record_metric("cache_evictions".to_string(), metrics.evictions as f64, tags.clone());
+        self.record_metric("cache_entries".to_string(), metrics.entries as f64, tags.clone());
+        self.record_metric("cache_hit_rate".to_string(), metrics.hit_rate(), tags);
+    }
+
+    /// Record the tokens one AI request consumed
+    pub fn record_token_usage(&mut self, model: &str, prompt_tokens: u32, completion_tokens: u32) {
+        for (kind, tokens) in [("prompt", prompt_tokens), ("completion", completion_tokens)] {
+            let mut tags = HashMap::new();
+            tags.insert("model".to_string(), model.to_string());
+            tags.insert("kind".to_string(), kind.to_string());
+            self.record_metric(AI_TOKENS_METRIC.to_string(), tokens as f64, tags);
+        }
+    }
+
+    /// Prompt and completion tokens per model, busiest model first
+    pub fn get_token_usage(&self, time_range: Option<TimeRange>) -> TokenUsageSummary {
+        let mut summary = TokenUsageSummary::default();
+        let Some(series) = self.metrics.get(AI_TOKENS_METRIC) else {
+            return summary;
+        };
+
+        let mut by_model: BTreeMap<&str, ModelTokenUsage> = BTreeMap::new();
+        for point in points_in_range(&series.data_points, time_range.as_ref()) {
+            let model = point.tags.get("model").map_or("unknown", String::as_str);
+            let usage = by_model.entry(model).or_insert_with(|| ModelTokenUsage { model: model.to_string(), ..Default::default() });
+            let tokens = point.value as u64;
+            match point.tags.get("kind").map(String::as_str) {
+                Some("prompt") => {
+                    usage.requests += 1;
+                    usage.prompt_tokens += tokens;
+                }
+                _ => usage.completion_tokens += tokens,
+            }
+            usage.total_tokens += tokens;
+        }
+
+        summary.models = by_model.into_values().collect();
+        summary.models.sort_by_key(|usage| std::cmp::Reverse(usage.total_tokens));
+        summary.prompt_tokens = summary.models.iter().map(|usage| usage.prompt_tokens).sum();
+        summary.completion_tokens = summary.models.iter().map(|usage| usage.completion_tokens).sum();
+        summary.total_tokens = summary.prompt_tokens + summary.completion_tokens;
+        summary
+    }
+
     pub fn get_metric_value(&self, name: &str, time_range: Option<TimeRange>) -> Option<f64> {
         if let Some(series) = self.metrics.get(name) {
+            if let AggregationType::Percentile(p) = series.aggregation {
+                if is_sketched(&series.metric_type) {
+                    return self.get_percentile(name, p, time_range).map(|estimate| estimate.value);
+                }
+            }
+
             let data_points = if let Some(range) = time_range {
                 series.data_points.iter()
                     .filter(|dp| dp.timestamp >= range.start && dp.timestamp <= range.end)
@@ -409,6 +830,61 @@ impl AnalyticsEngine {
         }
     }
 
+    /// Percentile `p` (0-100) of a series.
+    ///
+    /// Exact for small samples; for large timer and histogram series the hours wholly inside
+    /// the range come from precomputed sketches and only the partial hours at either edge are
+    /// read point by point.
+    pub fn get_percentile(&self, name: &str, p: f64, time_range: Option<TimeRange>) -> Option<PercentileEstimate> {
+        let series = self.metrics.get(name)?;
+        let points = points_in_range(&series.data_points, time_range.as_ref());
+        if points.is_empty() {
+            return None;
+        }
+
+        let hours = match self.sketches.get(name) {
+            Some(hours) if points.len() > EXACT_PERCENTILE_LIMIT => hours,
+            _ => {
+                let mut values: Vec<f64> = points.iter().map(|dp| dp.value).collect();
+                let index = (p / 100.0 * (values.len() - 1) as f64) as usize;
+                let (_, value, _) = values.select_nth_unstable_by(index, f64::total_cmp);
+                return Some(PercentileEstimate {
+                    value: *value,
+                    error_bound: 0.0,
+                    exact: true,
+                    sample_count: points.len(),
+                });
+            }
+        };
+
+        let start = points[0].timestamp;
+        let end = points[points.len() - 1].timestamp;
+        let start_is_aligned = start.timestamp().rem_euclid(3600) == 0 && start.timestamp_subsec_nanos() == 0;
+        let first_full = sketch_hour(start) + if start_is_aligned { 0 } else { 1 };
+        let last_full = end.timestamp().div_euclid(3600) - 1;
+
+        let mut sketch = QuantileSketch::new(SKETCH_RELATIVE_ACCURACY);
+        if first_full <= last_full {
+            for hour in hours.range(first_full..=last_full).map(|(_, sketch)| sketch) {
+                sketch.merge(hour);
+            }
+        }
+        let full_start = points.partition_point(|dp| sketch_hour(dp.timestamp) < first_full);
+        let full_end = points.partition_point(|dp| sketch_hour(dp.timestamp) <= last_full).max(full_start);
+        for dp in points[..full_start].iter().chain(&points[full_end..]) {
+            sketch.add(dp.value);
+        }
+
+        let value = sketch.quantile(p / 100.0)?;
+        Some(PercentileEstimate {
+            value,
+            // |estimate - exact| <= accuracy * |exact| <= accuracy * |estimate| / (1 - accuracy)
+            error_bound: value.abs() * sketch.relative_accuracy() / (1.0 - sketch.relative_accuracy()),
+            exact: false,
+            sample_count: sketch.count() as usize,
+        })
+    }
+
     pub fn analyze_performance(&mut self) -> Vec<Insight> {
         let mut insights = Vec::new();
         
@@ -440,6 +916,7 @@ impl AnalyticsEngine {
                             },
                         }
                     ],
+                    tags: HashMap::new(),
                 });
             }
         }
@@ -472,6 +949,7 @@ impl AnalyticsEngine {
                             },
                         }
                     ],
+                    tags: HashMap::new(),
                 });
             }
         }
@@ -504,6 +982,7 @@ impl AnalyticsEngine {
                             },
                         }
                     ],
+                    tags: HashMap::new(),
                 });
             }
         }
@@ -512,76 +991,80 @@ impl AnalyticsEngine {
         insights
     }
 
+    /// Flag recent points whose rolling z-score exceeds the configured threshold.
+    ///
+    /// Each of the last `recent_points` values is scored against the mean and standard
+    /// deviation of the `window_size` points before it; at most one insight is reported
+    /// per metric, for its most extreme point.
     pub fn detect_anomalies(&mut self) -> Vec<Insight> {
+        let config = self.anomaly_config.clone();
         let mut anomalies = Vec::new();
 
-        for (metric_name, series) in &self.metrics {
-            if series.data_points.len() < 10 {
-                continue; // Need sufficient data for anomaly detection
-            }
-
-            let recent_values: Vec<f64> = series.data_points.iter()
-                .rev()
-                .take(10)
-                .map(|dp| dp.value)
-                .collect();
-
-            let historical_values: Vec<f64> = series.data_points.iter()
-                .rev()
-                .skip(10)
-                .take(50)
-                .map(|dp| dp.value)
-                .collect();
+        let mut names: Vec<&String> = self.metrics.keys().collect();
+        names.sort();
 
-            if historical_values.is_empty() {
+        for metric_name in names {
+            let values: Vec<f64> = self.metrics[metric_name].data_points.iter().map(|dp| dp.value).collect();
+            let Some(anomaly) = strongest_anomaly(&values, &config) else {
                 continue;
-            }
-
-            let historical_avg = historical_values.iter().sum::<f64>() / historical_values.len() as f64;
-            let recent_avg = recent_values.iter().sum::<f64>() / recent_values.len() as f64;
-
-            let deviation = ((recent_avg - historical_avg) / historical_avg).abs();
-
-            if deviation > 0.5 { // 50% deviation threshold
-                let severity = if deviation > 1.0 {
-                    InsightSeverity::High
-                } else {
-                    InsightSeverity::Medium
-                };
+            };
 
-                anomalies.push(Insight {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    insight_type: InsightType::Anomaly,
-                    title: format!("Anomaly Detected in {}", metric_name),
-                    description: format!("Metric {} shows {:.1}% deviation from baseline", metric_name, deviation * 100.0),
-                    severity,
-                    confidence: 0.7,
-                    metric_names: vec![metric_name.clone()],
-                    time_range: TimeRange {
-                        start: Utc::now() - Duration::minutes(30),
-                        end: Utc::now(),
-                    },
-                    created_at: Utc::now(),
-                    actions: vec![
-                        RecommendedAction {
-                            action_type: ActionType::Investigation,
-                            description: "Investigate the cause of this anomaly".to_string(),
-                            priority: 1,
-                            estimated_impact: EstimatedImpact {
-                                performance_improvement: None,
-                                resource_savings: None,
-                                user_experience_improvement: None,
-                            },
-                        }
-                    ],
-                });
-            }
+            let severity = if anomaly.z_score.abs() >= config.z_threshold * 2.0 {
+                InsightSeverity::High
+            } else {
+                InsightSeverity::Medium
+            };
+            let direction = if anomaly.z_score > 0.0 { "above" } else { "below" };
+
+            let mut tags = HashMap::new();
+            tags.insert("z_score".to_string(), format!("{:.2}", anomaly.z_score));
+            tags.insert("window_size".to_string(), anomaly.window_size.to_string());
+
+            anomalies.push(Insight {
+                id: uuid::Uuid::new_v4().to_string(),
+                insight_type: InsightType::Anomaly,
+                title: format!("Anomaly Detected in {}", metric_name),
+                description: format!(
+                    "Metric {} reached {:.2}, {:.1} standard deviations {} the mean of the previous {} points ({:.2})",
+                    metric_name,
+                    anomaly.value,
+                    anomaly.z_score.abs(),
+                    direction,
+                    anomaly.window_size,
+                    anomaly.mean
+                ),
+                severity,
+                confidence: (anomaly.z_score.abs() / (config.z_threshold * 2.0)).clamp(0.5, 0.99),
+                metric_names: vec![metric_name.clone()],
+                time_range: TimeRange {
+                    start: Utc::now() - Duration::minutes(30),
+                    end: Utc::now(),
+                },
+                created_at: Utc::now(),
+                actions: vec![
+                    RecommendedAction {
+                        action_type: ActionType::Investigation,
+                        description: "Investigate the cause of this anomaly".to_string(),
+                        priority: 1,
+                        estimated_impact: EstimatedImpact {
+                            performance_improvement: None,
+                            resource_savings: None,
+                            user_experience_improvement: None,
+                        },
+                    }
+                ],
+                tags,
+            });
         }
 
         self.insights.extend(anomalies.clone());
         anomalies
     }
 
+    pub fn set_anomaly_config(&mut self, config: AnomalyConfig) {
+        self.anomaly_config = config;
+    }
+
     pub fn generate_optimization_suggestions(&mut self) -> Vec<OptimizationSuggestion> {
         let mut suggestions = Vec::new();
 
@@ -887,17 +1370,70 @@ impl AnalyticsEngine {
         Ok(self.optimization_suggestions.clone())
     }
 
+    /// Rule-based action plan from current insights and suggestions, ranked by impact over effort
+    pub fn get_action_plan(&self) -> ActionPlan {
+        let mut groups: Vec<ActionPlanItem> = Vec::new();
+
+        let candidates = self
+            .insights
+            .iter()
+            .map(insight_plan_item)
+            .chain(self.optimization_suggestions.iter().map(suggestion_plan_item));
+
+        for candidate in candidates {
+            match groups.iter_mut().find(|g| g.area == candidate.area) {
+                Some(group) => merge_plan_item(group, candidate),
+                None => groups.push(candidate),
+            }
+        }
+
+        groups.sort_by(|a, b| {
+            b.priority_score
+                .total_cmp(&a.priority_score)
+                .then(b.impact.total_cmp(&a.impact))
+        });
+
+        ActionPlan {
+            items: groups,
+            ai_enriched: false,
+            generated_at: Utc::now(),
+        }
+    }
+
+    /// Render every metric series in the Prometheus text exposition format
+    pub fn export_prometheus(&self) -> String {
+        let mut names: Vec<&String> = self.metrics.keys().collect();
+        names.sort();
+
+        let mut emitted = HashSet::new();
+        let mut output = String::new();
+        for name in names {
+            let metric_name = prometheus_metric_name(name);
+            // Two series that sanitize to the same name would form an invalid family
+            if emitted.insert(metric_name.clone()) {
+                write_prometheus_series(&mut output, &metric_name, &self.metrics[name]);
+            }
+        }
+        output
+    }
+
     pub fn get_report(&self, report_id: &str) -> Option<&AnalyticsReport> {
         self.reports.get(report_id)
     }
 
+    /// Render a generated report as a self-contained HTML page with inline SVG charts
+    pub fn export_report_html(&self, report_id: &str) -> Result<String> {
+        let report = self.get_report(report_id).ok_or_else(|| anyhow!("Report not found: {}", report_id))?;
+        Ok(render_report_html(report))
+    }
+
     pub fn list_reports(&self) -> Vec<&AnalyticsReport> {
         self.reports.values().collect()
     }
 
     // Methods expected by main.rs
     pub async fn get_performance_metrics(&self, time_range: &str) -> Result<PerformanceMetrics> {
-        let range = self.parse_time_range(time_range)?;
+        let range = parse_time_range(time_range)?;
         
         let cpu_usage = self.get_metric_value("cpu_usage", Some(range.clone())).unwrap_or(0.0);
         let memory_usage = self.get_metric_value("memory_usage", Some(range.clone())).unwrap_or(0.0);
@@ -924,7 +1460,7 @@ impl AnalyticsEngine {
     }
 
     pub async fn get_usage_statistics(&self, period: &str) -> Result<UsageStatistics> {
-        let range = self.parse_time_range(period)?;
+        let range = parse_time_range(period)?;
         
         // Calculate usage statistics from recorded metrics
         let total_commands = self.get_metric_value("command_count", Some(range.clone())).unwrap_or(0.0) as u64;
@@ -983,7 +1519,7 @@ impl AnalyticsEngine {
         tags.insert("success".to_string(), success.to_string());
         
         // Record execution time
-        self.record_metric("command_execution_time".to_string(), execution_time as f64, tags.clone());
+        self.record_timer("command_execution_time".to_string(), execution_time as f64, tags.clone());
         
         // Record command count
         self.record_metric("command_count".to_string(), 1.0, tags.clone());
@@ -1061,28 +1597,730 @@ impl AnalyticsEngine {
         Ok(patterns)
     }
 
+}
 
-    fn parse_time_range(&self, time_range_str: &str) -> Result<TimeRange> {
-        let end = Utc::now();
-        let start = match time_range_str {
-            "1h" | "hour" => end - Duration::hours(1),
-            "24h" | "day" => end - Duration::days(1),
-            "7d" | "week" => end - Duration::days(7),
-            "30d" | "month" => end - Duration::days(30),
-            "90d" | "quarter" => end - Duration::days(90),
-            "365d" | "year" => end - Duration::days(365),
-            _ => {
-                // Try to parse as duration in hours
-                if let Ok(hours) = time_range_str.parse::<i64>() {
-                    end - Duration::hours(hours)
-                } else {
-                    return Err(anyhow!("Invalid time range format: {}", time_range_str));
+/// Parse a user-supplied time range ending now.
+///
+/// Accepts relative spans (`90m`, `24h`, `7d`, `2w`, or a bare number of hours),
+/// absolute ranges (`2024-01-01..2024-02-01`, dates or RFC 3339 timestamps) and
+/// named presets (`today`, `yesterday`, `this_week`, `this_month`, `hour`, `day`, ...).
+pub fn parse_time_range(input: &str) -> Result<TimeRange> {
+    parse_time_range_at(input, Utc::now())
+}
+
+fn parse_time_range_at(input: &str, now: DateTime<Utc>) -> Result<TimeRange> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(anyhow!("Time range is empty"));
+    }
+
+    if let Some((start, end)) = trimmed.split_once("..") {
+        let start = parse_range_bound(start, "start")?;
+        let end = parse_range_bound(end, "end")?;
+        if end <= start {
+            return Err(anyhow!("Invalid time range '{}': end must be after start", trimmed));
+        }
+        return Ok(TimeRange { start, end });
+    }
+
+    if let Some(range) = preset_time_range(&trimmed.to_lowercase(), now) {
+        return Ok(range);
+    }
+
+    let duration = parse_relative_duration(trimmed)?;
+    Ok(TimeRange { start: now - duration, end: now })
+}
+
+fn preset_time_range(name: &str, now: DateTime<Utc>) -> Option<TimeRange> {
+    let midnight = now.date_naive().and_hms_opt(0, 0, 0)?.and_utc();
+    let (start, end) = match name {
+        "hour" => (now - Duration::hours(1), now),
+        "day" => (now - Duration::days(1), now),
+        "week" => (now - Duration::days(7), now),
+        "month" => (now - Duration::days(30), now),
+        "quarter" => (now - Duration::days(90), now),
+        "year" => (now - Duration::days(365), now),
+        "today" => (midnight, now),
+        "yesterday" => (midnight - Duration::days(1), midnight),
+        "this_week" => {
+            let days_since_monday = now.weekday().num_days_from_monday() as i64;
+            (midnight - Duration::days(days_since_monday), now)
+        }
+        "this_month" => (midnight - Duration::days(now.day0() as i64), now),
+        _ => return None,
+    };
+    Some(TimeRange { start, end })
+}
+
+fn parse_relative_duration(input: &str) -> Result<Duration> {
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    if amount.is_empty() {
+        return Err(anyhow!(
+            "Invalid time range '{}': expected a span like 24h or 7d, a range like 2024-01-01..2024-02-01, or a preset like today",
+            input
+        ));
+    }
+
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow!("Invalid time range '{}': amount is too large", input))?;
+    if amount == 0 {
+        return Err(anyhow!("Invalid time range '{}': amount must be greater than zero", input));
+    }
+
+    let duration = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" | "" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => {
+            return Err(anyhow!(
+                "Invalid time range '{}': unknown unit '{}' (expected s, m, h, d or w)",
+                input,
+                unit
+            ))
+        }
+    };
+    duration.ok_or_else(|| anyhow!("Invalid time range '{}': amount is too large", input))
+}
+
+/// One side of an absolute range: a `YYYY-MM-DD` date (midnight UTC) or an RFC 3339 timestamp
+fn parse_range_bound(value: &str, which: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+        .ok_or_else(|| anyhow!("Invalid range {} '{}': expected YYYY-MM-DD or an RFC 3339 timestamp", which, value))
+}
+
+/// Relative effort of a complexity level, used as the divisor when ranking
+fn complexity_effort(complexity: &ComplexityLevel) -> f64 {
+    match complexity {
+        ComplexityLevel::Low => 1.0,
+        ComplexityLevel::Medium => 2.0,
+        ComplexityLevel::High => 3.0,
+        ComplexityLevel::Expert => 4.0,
+    }
+}
+
+fn action_complexity(action_type: &ActionType) -> ComplexityLevel {
+    match action_type {
+        ActionType::Configuration | ActionType::Monitoring => ComplexityLevel::Low,
+        ActionType::Optimization | ActionType::Investigation => ComplexityLevel::Medium,
+        ActionType::Scaling => ComplexityLevel::High,
+    }
+}
+
+/// Area a metric belongs to, so insights group with matching optimization categories
+fn metric_area(metric_name: &str) -> String {
+    let name = metric_name.to_lowercase();
+    let area = if name.contains("cpu") {
+        "cpu"
+    } else if name.contains("memory") {
+        "memory"
+    } else if name.contains("network") || name.contains("latency") {
+        "network"
+    } else if name.contains("disk") || name.contains("storage") {
+        "storage"
+    } else if name.contains("execution_time") || name.contains("response_time") {
+        "performance"
+    } else {
+        return name;
+    };
+    area.to_string()
+}
+
+fn category_area(category: &OptimizationCategory) -> String {
+    match category {
+        OptimizationCategory::Performance => "performance",
+        OptimizationCategory::Memory => "memory",
+        OptimizationCategory::CPU => "cpu",
+        OptimizationCategory::Network => "network",
+        OptimizationCategory::Storage => "storage",
+        OptimizationCategory::Configuration => "configuration",
+        OptimizationCategory::Architecture => "architecture",
+    }
+    .to_string()
+}
+
+/// Impact on the 0-10 scale of `OptimizationSuggestion::impact_rating`
+fn insight_impact(insight: &Insight) -> f64 {
+    let estimated = insight
+        .actions
+        .iter()
+        .map(|action| {
+            let impact = &action.estimated_impact;
+            [impact.performance_improvement, impact.resource_savings, impact.user_experience_improvement]
+                .iter()
+                .flatten()
+                .sum::<f64>()
+                / 10.0
+        })
+        .fold(0.0, f64::max);
+    let by_severity = match insight.severity {
+        InsightSeverity::Critical => 10.0,
+        InsightSeverity::High => 7.5,
+        InsightSeverity::Medium => 5.0,
+        InsightSeverity::Low => 2.5,
+        InsightSeverity::Info => 1.0,
+    };
+    estimated.max(by_severity).min(10.0) * insight.confidence.clamp(0.0, 1.0)
+}
+
+fn insight_plan_item(insight: &Insight) -> ActionPlanItem {
+    let area = insight
+        .metric_names
+        .first()
+        .map(|name| metric_area(name))
+        .unwrap_or_else(|| format!("{:?}", insight.insight_type).to_lowercase());
+    let complexity = insight
+        .actions
+        .iter()
+        .min_by_key(|action| action.priority)
+        .map(|action| action_complexity(&action.action_type))
+        .unwrap_or(ComplexityLevel::Medium);
+    let impact = insight_impact(insight);
+
+    ActionPlanItem {
+        area,
+        title: insight.title.clone(),
+        description: insight.description.clone(),
+        impact,
+        priority_score: impact / complexity_effort(&complexity),
+        complexity,
+        steps: insight.actions.iter().map(|action| action.description.clone()).collect(),
+        related: Vec::new(),
+        source_ids: vec![insight.id.clone()],
+    }
+}
+
+fn suggestion_plan_item(suggestion: &OptimizationSuggestion) -> ActionPlanItem {
+    let impact = suggestion.impact_rating.clamp(0.0, 10.0);
+    ActionPlanItem {
+        area: category_area(&suggestion.category),
+        title: suggestion.title.clone(),
+        description: suggestion.description.clone(),
+        impact,
+        priority_score: impact / complexity_effort(&suggestion.complexity),
+        complexity: suggestion.complexity.clone(),
+        steps: suggestion.implementation_steps.clone(),
+        related: Vec::new(),
+        source_ids: vec![suggestion.id.clone()],
+    }
+}
+
+/// Fold `other` into `group`; the higher-priority item leads, steps and titles are deduplicated
+fn merge_plan_item(group: &mut ActionPlanItem, mut other: ActionPlanItem) {
+    if other.priority_score > group.priority_score {
+        std::mem::swap(group, &mut other);
+    }
+
+    for title in std::iter::once(other.title).chain(other.related) {
+        if title != group.title && !group.related.contains(&title) {
+            group.related.push(title);
+        }
+    }
+    for step in other.steps {
+        if !group.steps.contains(&step) {
+            group.steps.push(step);
+        }
+    }
+    group.source_ids.extend(other.source_ids);
+}
+
+fn is_sketched(metric_type: &MetricType) -> bool {
+    matches!(metric_type, MetricType::Histogram | MetricType::Timer)
+}
+
+fn sketch_hour(timestamp: DateTime<Utc>) -> i64 {
+    timestamp.timestamp().div_euclid(3600)
+}
+
+/// The chronological slice of `points` inside `range`, inclusive on both ends
+fn points_in_range<'a>(points: &'a [DataPoint], range: Option<&TimeRange>) -> &'a [DataPoint] {
+    let Some(range) = range else {
+        return points;
+    };
+    let start = points.partition_point(|dp| dp.timestamp < range.start);
+    let end = points.partition_point(|dp| dp.timestamp <= range.end).max(start);
+    &points[start..end]
+}
+
+/// The recent point with the largest rolling z-score beyond the threshold, if any
+fn strongest_anomaly(values: &[f64], config: &AnomalyConfig) -> Option<ScoredPoint> {
+    let first_scored = values
+        .len()
+        .saturating_sub(config.recent_points)
+        .max(config.min_window_size.max(2));
+
+    (first_scored..values.len())
+        .filter_map(|i| {
+            let window = &values[i.saturating_sub(config.window_size)..i];
+            let n = window.len() as f64;
+            let mean = window.iter().sum::<f64>() / n;
+            let std_dev = (window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+            // A flat baseline makes any wiggle look infinitely unlikely
+            if std_dev <= 1e-9 * mean.abs().max(1.0) {
+                return None;
+            }
+
+            let z_score = (values[i] - mean) / std_dev;
+            (z_score.abs() > config.z_threshold).then_some(ScoredPoint {
+                value: values[i],
+                mean,
+                z_score,
+                window_size: window.len(),
+            })
+        })
+        .max_by(|a, b| a.z_score.abs().total_cmp(&b.z_score.abs()))
+}
+
+/// Most recent data points per label set used for summary quantiles
+const PROMETHEUS_SUMMARY_WINDOW: usize = 1000;
+
+fn write_prometheus_series(output: &mut String, name: &str, series: &MetricSeries) {
+    // Data points are chronological, so each label set's values stay in order
+    let mut by_labels: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for point in &series.data_points {
+        by_labels.entry(prometheus_labels(&point.tags)).or_default().push(point.value);
+    }
+    if by_labels.is_empty() {
+        return;
+    }
+
+    let kind = match series.metric_type {
+        MetricType::Counter => "counter",
+        MetricType::Gauge | MetricType::Rate => "gauge",
+        MetricType::Histogram | MetricType::Timer => "summary",
+    };
+    output.push_str(&format!("# TYPE {} {}\n", name, kind));
+
+    for (labels, values) in &by_labels {
+        match series.metric_type {
+            MetricType::Counter => push_prometheus_sample(output, name, labels, values.iter().sum()),
+            MetricType::Gauge | MetricType::Rate => {
+                push_prometheus_sample(output, name, labels, values[values.len() - 1])
+            }
+            MetricType::Histogram | MetricType::Timer => {
+                let mut recent = values[values.len().saturating_sub(PROMETHEUS_SUMMARY_WINDOW)..].to_vec();
+                recent.sort_by(f64::total_cmp);
+                for quantile in [0.5, 0.9, 0.99] {
+                    let rank = ((quantile * recent.len() as f64).ceil() as usize).clamp(1, recent.len());
+                    let quantile_label = format!("quantile=\"{}\"", quantile);
+                    let quantile_labels = if labels.is_empty() {
+                        quantile_label
+                    } else {
+                        format!("{},{}", labels, quantile_label)
+                    };
+                    push_prometheus_sample(output, name, &quantile_labels, recent[rank - 1]);
+                }
+                push_prometheus_sample(output, &format!("{}_sum", name), labels, values.iter().sum());
+                push_prometheus_sample(output, &format!("{}_count", name), labels, values.len() as f64);
+            }
+        }
+    }
+}
+
+fn push_prometheus_sample(output: &mut String, name: &str, labels: &str, value: f64) {
+    let value = if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    };
+
+    if labels.is_empty() {
+        output.push_str(&format!("{} {}\n", name, value));
+    } else {
+        output.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+    }
+}
+
+/// Tags as a sorted, escaped Prometheus label list without the surrounding braces
+fn prometheus_labels(tags: &HashMap<String, String>) -> String {
+    let labels: BTreeMap<String, String> = tags
+        .iter()
+        .map(|(key, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            (prometheus_label_name(key), value)
+        })
+        .collect();
+
+    labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Map a metric name onto `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn prometheus_metric_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Map a tag key onto `[a-zA-Z_][a-zA-Z0-9_]*`, avoiding the reserved `__` prefix
+fn prometheus_label_name(key: &str) -> String {
+    let mut sanitized: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) || sanitized.starts_with("__") {
+        sanitized.insert_str(0, "tag_");
+    }
+    sanitized
+}
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 240.0;
+const CHART_MARGIN: f64 = 40.0;
+const DEFAULT_CHART_COLOR: &str = "#4C6EF5";
+const PDF_CONVERSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+const REPORT_STYLE: &str = "body{font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;color:#1f2933;margin:2rem auto;max-width:760px}\
+h1{margin-bottom:.25rem}.meta{color:#616e7c;margin-top:0}section{margin:2rem 0;page-break-inside:avoid}\
+table{border-collapse:collapse;width:100%}th,td{border:1px solid #cbd2d9;padding:.35rem .6rem;text-align:left}\
+th{background:#f0f4f8}.metrics{display:flex;flex-wrap:wrap;gap:.75rem}\
+.metric{border:1px solid #cbd2d9;border-radius:6px;padding:.6rem .9rem;min-width:140px}\
+.metric .label{color:#616e7c;font-size:.8rem}.metric .value{font-size:1.4rem;font-weight:600}\
+svg text{font-size:11px;fill:#52606d}";
+
+fn render_report_html(report: &AnalyticsReport) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", escape_html(&report.name), REPORT_STYLE));
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(&report.name)));
+    html.push_str(&format!(
+        "<p class=\"meta\">Generated {} &middot; {} to {}</p>\n",
+        report.generated_at.format("%Y-%m-%d %H:%M UTC"),
+        report.time_range.start.format("%Y-%m-%d %H:%M"),
+        report.time_range.end.format("%Y-%m-%d %H:%M"),
+    ));
+
+    html.push_str("<section>\n<h2>Summary</h2>\n<div class=\"metrics\">\n");
+    push_metric_card(&mut html, "Metrics tracked", &report.summary.total_metrics.to_string());
+    push_metric_card(&mut html, "Insights found", &report.summary.insights_found.to_string());
+    if let Some(score) = report.summary.performance_score {
+        push_metric_card(&mut html, "Performance score", &format_report_number(score));
+    }
+    html.push_str("</div>\n");
+    if !report.summary.key_findings.is_empty() {
+        html.push_str("<ul>\n");
+        for finding in &report.summary.key_findings {
+            html.push_str(&format!("<li>{}</li>\n", escape_html(finding)));
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</section>\n");
+
+    for section in &report.sections {
+        html.push_str(&format!("<section>\n<h2>{}</h2>\n", escape_html(&section.title)));
+        render_section_body(&mut html, section);
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_section_body(html: &mut String, section: &ReportSection) {
+    match (&section.content_type, &section.data) {
+        (ContentType::Text, serde_json::Value::String(text)) => {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(text)));
+        }
+        (ContentType::Metrics, serde_json::Value::Object(metrics)) => {
+            html.push_str("<div class=\"metrics\">\n");
+            for (name, value) in metrics {
+                push_metric_card(html, &humanize_key(name), &json_cell(value));
+            }
+            html.push_str("</div>\n");
+        }
+        (ContentType::Chart, data) => {
+            // Only line, bar and area charts have an SVG rendering; anything else shows its data
+            let chart = section
+                .visualization
+                .as_ref()
+                .and_then(|visualization| render_svg_chart(visualization, &chart_points(data, visualization)));
+            match chart {
+                Some(svg) => html.push_str(&svg),
+                None => push_json_table(html, data),
+            }
+        }
+        (_, data) => push_json_table(html, data),
+    }
+}
+
+fn push_metric_card(html: &mut String, label: &str, value: &str) {
+    html.push_str(&format!(
+        "<div class=\"metric\"><div class=\"label\">{}</div><div class=\"value\">{}</div></div>\n",
+        escape_html(label),
+        escape_html(value)
+    ));
+}
+
+/// Labelled values to plot, read from either a list of records or a name-to-number map
+fn chart_points(data: &serde_json::Value, visualization: &VisualizationConfig) -> Vec<(String, f64)> {
+    match data {
+        serde_json::Value::Array(rows) => rows
+            .iter()
+            .filter_map(|row| {
+                let label = row.get(&visualization.x_axis).or_else(|| row.get("timestamp"))?;
+                let value = row.get(&visualization.y_axis).or_else(|| row.get("value"))?.as_f64()?;
+                let label = match label {
+                    serde_json::Value::String(text) => DateTime::parse_from_rfc3339(text)
+                        .map(|time| time.format("%H:%M").to_string())
+                        .unwrap_or_else(|_| text.clone()),
+                    other => other.to_string(),
+                };
+                Some((label, value))
+            })
+            .collect(),
+        serde_json::Value::Object(map) => {
+            let mut points: Vec<(String, f64)> =
+                map.iter().filter_map(|(key, value)| Some((key.clone(), value.as_f64()?))).collect();
+            points.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            points
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn render_svg_chart(visualization: &VisualizationConfig, points: &[(String, f64)]) -> Option<String> {
+    if points.is_empty() || !matches!(visualization.chart_type, ChartType::Line | ChartType::Bar | ChartType::Area) {
+        return None;
+    }
+
+    let color = escape_html(visualization.colors.first().map(String::as_str).unwrap_or(DEFAULT_CHART_COLOR));
+    let max = points.iter().map(|(_, value)| *value).fold(0.0_f64, f64::max);
+    let scale = if max > 0.0 { max } else { 1.0 };
+    let plot_width = CHART_WIDTH - 2.0 * CHART_MARGIN;
+    let plot_height = CHART_HEIGHT - 2.0 * CHART_MARGIN;
+    let baseline = CHART_HEIGHT - CHART_MARGIN;
+    let y_of = |value: f64| baseline - (value.max(0.0) / scale) * plot_height;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"chart chart-{}\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" role=\"img\">\n",
+        format!("{:?}", visualization.chart_type).to_lowercase(),
+        CHART_WIDTH,
+        CHART_HEIGHT,
+        CHART_WIDTH,
+        CHART_HEIGHT
+    );
+    if let Some(series) = visualization.series.first() {
+        svg.push_str(&format!("<title>{}</title>\n", escape_html(series)));
+    }
+    svg.push_str(&format!(
+        "<line class=\"axis\" x1=\"{m}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"#9aa5b1\"/>\n<line class=\"axis\" x1=\"{m}\" y1=\"{m}\" x2=\"{m}\" y2=\"{b}\" stroke=\"#9aa5b1\"/>\n",
+        m = CHART_MARGIN,
+        b = baseline,
+        r = CHART_WIDTH - CHART_MARGIN
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n<text x=\"{}\" y=\"{}\" text-anchor=\"end\">0</text>\n",
+        CHART_MARGIN - 4.0,
+        CHART_MARGIN + 4.0,
+        format_report_number(max),
+        CHART_MARGIN - 4.0,
+        baseline
+    ));
+
+    if let ChartType::Bar = visualization.chart_type {
+        let slot = plot_width / points.len() as f64;
+        let bar_width = slot * 0.7;
+        for (index, (label, value)) in points.iter().enumerate() {
+            let x = CHART_MARGIN + slot * index as f64 + (slot - bar_width) / 2.0;
+            let y = y_of(*value);
+            svg.push_str(&format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{}: {}</title></rect>\n",
+                x,
+                y,
+                bar_width,
+                baseline - y,
+                color,
+                escape_html(label),
+                format_report_number(*value)
+            ));
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                x + bar_width / 2.0,
+                baseline + 14.0,
+                escape_html(label)
+            ));
+        }
+    } else {
+        let step = if points.len() > 1 { plot_width / (points.len() - 1) as f64 } else { 0.0 };
+        let coordinates: Vec<(f64, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(index, (_, value))| (CHART_MARGIN + step * index as f64, y_of(*value)))
+            .collect();
+        let polyline = coordinates.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect::<Vec<_>>().join(" ");
+
+        if let ChartType::Area = visualization.chart_type {
+            let (first_x, _) = coordinates[0];
+            let (last_x, _) = coordinates[coordinates.len() - 1];
+            svg.push_str(&format!(
+                "<polygon class=\"area\" points=\"{:.1},{:.1} {} {:.1},{:.1}\" fill=\"{}\" fill-opacity=\"0.3\"/>\n",
+                first_x, baseline, polyline, last_x, baseline, color
+            ));
+        }
+        svg.push_str(&format!(
+            "<polyline class=\"line\" points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+            polyline, color
+        ));
+        for (label, x) in [(&points[0].0, coordinates[0].0), (&points[points.len() - 1].0, coordinates[coordinates.len() - 1].0)] {
+            svg.push_str(&format!("<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n", x, baseline + 14.0, escape_html(label)));
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    Some(svg)
+}
+
+/// Tabulate arbitrary section data: records become rows, maps become name/value pairs
+fn push_json_table(html: &mut String, data: &serde_json::Value) {
+    html.push_str("<table>\n");
+    match data {
+        serde_json::Value::Array(rows) if !rows.is_empty() && rows.iter().all(|row| row.is_object()) => {
+            let mut columns: Vec<&String> = Vec::new();
+            for row in rows.iter().filter_map(|row| row.as_object()) {
+                for key in row.keys() {
+                    if !columns.contains(&key) {
+                        columns.push(key);
+                    }
+                }
+            }
+            html.push_str("<tr>");
+            for column in &columns {
+                html.push_str(&format!("<th>{}</th>", escape_html(&humanize_key(column))));
+            }
+            html.push_str("</tr>\n");
+            for row in rows {
+                html.push_str("<tr>");
+                for column in &columns {
+                    let cell = row.get(column.as_str()).map(json_cell).unwrap_or_default();
+                    html.push_str(&format!("<td>{}</td>", escape_html(&cell)));
                 }
+                html.push_str("</tr>\n");
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                html.push_str(&format!("<tr><td>{}</td></tr>\n", escape_html(&json_cell(value))));
             }
+        }
+        serde_json::Value::Object(map) => {
+            html.push_str("<tr><th>Name</th><th>Value</th></tr>\n");
+            for (key, value) in map {
+                html.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", escape_html(&humanize_key(key)), escape_html(&json_cell(value))));
+            }
+        }
+        value => html.push_str(&format!("<tr><td>{}</td></tr>\n", escape_html(&json_cell(value)))),
+    }
+    html.push_str("</table>\n");
+}
+
+fn json_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Number(number) => number.as_f64().map(format_report_number).unwrap_or_else(|| number.to_string()),
+        other => other.to_string(),
+    }
+}
+
+fn format_report_number(value: f64) -> String {
+    let formatted = format!("{:.2}", value);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `avg_cpu_usage` -> `Avg cpu usage`
+fn humanize_key(key: &str) -> String {
+    let spaced = key.replace('_', " ");
+    let mut chars = spaced.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => spaced,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Package the HTML rendering of report `report_id` as `format`. PDF conversion runs an
+/// external converter, so render the HTML with `export_report_html` and release the engine first.
+pub async fn export_report(report_id: &str, html: String, format: ReportFormat) -> Result<ExportedReport> {
+    let data = match format {
+        ReportFormat::Html => html.into_bytes(),
+        ReportFormat::Pdf => html_to_pdf(&html).await?,
+    };
+    Ok(ExportedReport {
+        file_name: format!("report-{}.{}", report_id, format.extension()),
+        mime_type: format.mime_type().to_string(),
+        data,
+    })
+}
+
+/// Print HTML to PDF with whichever headless converter is installed
+async fn html_to_pdf(html: &str) -> Result<Vec<u8>> {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("report.html");
+    let output = dir.path().join("report.pdf");
+    tokio::fs::write(&input, html).await?;
+
+    let converters: [(&str, Vec<String>); 4] = [
+        ("wkhtmltopdf", vec!["--quiet".to_string(), input.display().to_string(), output.display().to_string()]),
+        ("chromium", chromium_pdf_args(&input, &output)),
+        ("chromium-browser", chromium_pdf_args(&input, &output)),
+        ("google-chrome", chromium_pdf_args(&input, &output)),
+    ];
+
+    for (program, args) in converters {
+        // A converter stuck on the page is killed when the timeout drops it
+        let run = tokio::process::Command::new(program).args(&args).kill_on_drop(true).output();
+        let status = match tokio::time::timeout(PDF_CONVERSION_TIMEOUT, run).await {
+            Ok(Ok(result)) => result.status,
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Ok(Err(e)) => return Err(anyhow!("Failed to run {}: {}", program, e)),
+            Err(_) => return Err(anyhow!("{} did not convert the report to PDF within {}s", program, PDF_CONVERSION_TIMEOUT.as_secs())),
         };
-        
-        Ok(TimeRange { start, end })
+        let pdf = tokio::fs::read(&output).await.unwrap_or_default();
+        if status.success() && pdf.starts_with(b"%PDF") {
+            return Ok(pdf);
+        }
+        return Err(anyhow!("{} could not convert the report to PDF ({})", program, status));
     }
+
+    Err(anyhow!("PDF export needs wkhtmltopdf or a Chromium-based browser on PATH"))
+}
+
+fn chromium_pdf_args(input: &std::path::Path, output: &std::path::Path) -> Vec<String> {
+    vec![
+        "--headless".to_string(),
+        "--disable-gpu".to_string(),
+        "--no-pdf-header-footer".to_string(),
+        format!("--print-to-pdf={}", output.display()),
+        format!("file://{}", input.display()),
+    ]
 }
 
 #[cfg(test)]
@@ -1096,6 +2334,27 @@ mod tests {
         assert!(engine.insights.is_empty());
     }
 
+    #[test]
+    fn test_token_usage_is_aggregated_per_model() {
+        let mut engine = AnalyticsEngine::new();
+        // Counts as parsed from mocked Ollama replies (prompt_eval_count, eval_count)
+        for (model, prompt, completion) in [("llama3:8b", 26, 290), ("codellama", 120, 40), ("llama3:8b", 14, 10)] {
+            engine.record_token_usage(model, prompt, completion);
+        }
+
+        let summary = engine.get_token_usage(None);
+        assert_eq!(summary.prompt_tokens, 160);
+        assert_eq!(summary.completion_tokens, 340);
+        assert_eq!(summary.total_tokens, 500);
+        assert_eq!(summary.models.len(), 2);
+        let llama = &summary.models[0];
+        assert_eq!((llama.model.as_str(), llama.requests, llama.prompt_tokens, llama.completion_tokens), ("llama3:8b", 2, 40, 300));
+        assert_eq!(summary.models[1].total_tokens, 160);
+
+        let past = TimeRange { start: Utc::now() - Duration::days(2), end: Utc::now() - Duration::days(1) };
+        assert_eq!(engine.get_token_usage(Some(past)).total_tokens, 0);
+    }
+
     #[test]
     fn test_record_metric() {
         let mut engine = AnalyticsEngine::new();
@@ -1154,4 +2413,324 @@ mod tests {
         let memory_suggestion = suggestions.iter().find(|s| matches!(s.category, OptimizationCategory::Memory));
         assert!(memory_suggestion.is_some());
     }
+
+    #[test]
+    fn test_action_plan_ranks_by_impact_over_effort() {
+        let mut engine = AnalyticsEngine::new();
+        engine.record_metric("cpu_usage".to_string(), 85.0, HashMap::new());
+        engine.record_metric("memory_usage".to_string(), 90.0, HashMap::new());
+        engine.record_metric("network_latency".to_string(), 150.0, HashMap::new());
+        engine.analyze_performance();
+        engine.generate_optimization_suggestions();
+
+        let plan = engine.get_action_plan();
+        let areas: Vec<&str> = plan.items.iter().map(|item| item.area.as_str()).collect();
+        // Network has the lowest impact but is the only low-effort fix
+        assert_eq!(areas, vec!["network", "cpu", "memory"]);
+        assert!(plan.items.windows(2).all(|w| w[0].priority_score >= w[1].priority_score));
+        assert!(!plan.ai_enriched);
+    }
+
+    #[test]
+    fn test_action_plan_collapses_related_items() {
+        let mut engine = AnalyticsEngine::new();
+        engine.record_metric("memory_usage".to_string(), 90.0, HashMap::new());
+        engine.analyze_performance();
+        engine.analyze_performance();
+        engine.generate_optimization_suggestions();
+
+        let plan = engine.get_action_plan();
+        assert_eq!(plan.items.len(), 1);
+
+        let memory = &plan.items[0];
+        assert_eq!(memory.area, "memory");
+        assert_eq!(memory.title, "Optimize Memory Usage");
+        assert_eq!(memory.related, vec!["High Memory Usage".to_string()]);
+        assert_eq!(memory.source_ids.len(), 3);
+        let unique_steps: std::collections::HashSet<_> = memory.steps.iter().collect();
+        assert_eq!(unique_steps.len(), memory.steps.len());
+
+        let mut plan = plan;
+        assert!(!plan.apply_ai_steps("not json"));
+        assert!(plan.apply_ai_steps("Sure: {\"memory\": [\"Run heaptrack on the PTY reader\"]}"));
+        assert_eq!(plan.items[0].steps, vec!["Run heaptrack on the PTY reader".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_time_range_relative_and_presets() {
+        let now = DateTime::parse_from_rfc3339("2024-03-14T15:30:00Z").unwrap().with_timezone(&Utc);
+
+        let range = parse_time_range_at("90m", now).unwrap();
+        assert_eq!(range.end - range.start, Duration::minutes(90));
+        assert_eq!(parse_time_range_at("7d", now).unwrap().start, now - Duration::days(7));
+        assert_eq!(parse_time_range_at("24", now).unwrap().start, now - Duration::hours(24));
+        assert_eq!(parse_time_range_at("week", now).unwrap().start, now - Duration::days(7));
+
+        let today = parse_time_range_at("today", now).unwrap();
+        assert_eq!(today.start.to_rfc3339(), "2024-03-14T00:00:00+00:00");
+        assert_eq!(today.end, now);
+        // 2024-03-14 is a Thursday
+        let this_week = parse_time_range_at("This_Week", now).unwrap();
+        assert_eq!(this_week.start.to_rfc3339(), "2024-03-11T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_time_range_absolute_and_invalid() {
+        let range = parse_time_range("2024-01-01..2024-02-01").unwrap();
+        assert_eq!(range.start.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(range.end - range.start, Duration::days(31));
+
+        let range = parse_time_range("2024-01-01T08:00:00+02:00..2024-01-01T12:00:00Z").unwrap();
+        assert_eq!(range.end - range.start, Duration::hours(6));
+
+        let error = |input: &str| parse_time_range(input).unwrap_err().to_string();
+        assert!(error("").contains("empty"));
+        assert!(error("7y").contains("unknown unit 'y'"));
+        assert!(error("0d").contains("greater than zero"));
+        assert!(error("soon").contains("expected a span"));
+        assert!(error("2024-13-01..2024-02-01").contains("range start '2024-13-01'"));
+        assert!(error("2024-02-01..2024-01-01").contains("end must be after start"));
+        assert!(error("99999999999999999999d").contains("too large"));
+    }
+
+    #[test]
+    fn test_export_prometheus_is_valid_exposition_format() {
+        let mut engine = AnalyticsEngine::new();
+        let mut tags = HashMap::new();
+        tags.insert("host".to_string(), "dev \"box\"".to_string());
+        engine.record_metric("cpu_usage".to_string(), 40.0, tags.clone());
+        engine.record_metric("cpu_usage".to_string(), 55.0, tags);
+        engine.record_metric("1-load.avg".to_string(), 0.7, HashMap::new());
+        engine.metrics.insert(
+            "commands.run".to_string(),
+            MetricSeries {
+                name: "commands.run".to_string(),
+                metric_type: MetricType::Counter,
+                data_points: [3.0, 4.0]
+                    .iter()
+                    .map(|&value| DataPoint { timestamp: Utc::now(), value, tags: HashMap::new() })
+                    .collect(),
+                aggregation: AggregationType::Sum,
+                retention_days: 30,
+            },
+        );
+
+        let output = engine.export_prometheus();
+        let type_line = regex::Regex::new(r"^# TYPE ([a-zA-Z_:][a-zA-Z0-9_:]*) (counter|gauge|summary)$").unwrap();
+        let sample_line = regex::Regex::new(
+            r#"^([a-zA-Z_:][a-zA-Z0-9_:]*)(\{[a-zA-Z_][a-zA-Z0-9_]*="(?:\\.|[^"\\])*"(?:,[a-zA-Z_][a-zA-Z0-9_]*="(?:\\.|[^"\\])*")*\})? (\S+)$"#,
+        )
+        .unwrap();
+
+        let mut family = String::new();
+        for line in output.lines() {
+            if let Some(captures) = type_line.captures(line) {
+                family = captures[1].to_string();
+                continue;
+            }
+            let captures = sample_line.captures(line).unwrap_or_else(|| panic!("invalid line: {}", line));
+            assert!(captures[1].starts_with(&family), "sample outside its family: {}", line);
+            assert!(captures[3].parse::<f64>().is_ok(), "invalid value: {}", line);
+        }
+
+        assert!(output.contains("# TYPE commands_run counter\ncommands_run 7\n"));
+        assert!(output.contains("# TYPE cpu_usage gauge\ncpu_usage{host=\"dev \\\"box\\\"\"} 55\n"));
+        assert!(output.contains("# TYPE _1_load_avg gauge\n"));
+    }
+
+    #[test]
+    fn test_detect_anomalies_reports_single_spike() {
+        let mut engine = AnalyticsEngine::new();
+        // Noisy but stable series with one spike among the latest points
+        for i in 0..60 {
+            let value = if i == 55 { 95.0 } else { 50.0 + ((i * 7) % 11) as f64 - 5.0 };
+            engine.record_metric("cpu_usage".to_string(), value, HashMap::new());
+        }
+        for _ in 0..60 {
+            engine.record_metric("flat_metric".to_string(), 10.0, HashMap::new());
+        }
+
+        let anomalies = engine.detect_anomalies();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].metric_names, vec!["cpu_usage".to_string()]);
+        assert_eq!(anomalies[0].tags["window_size"], "50");
+        assert!(anomalies[0].tags["z_score"].parse::<f64>().unwrap() > 3.0);
+    }
+
+    #[test]
+    fn test_anomaly_threshold_is_configurable() {
+        let values: Vec<f64> = (0..40).map(|i| if i == 39 { 58.0 } else { 50.0 + (i % 5) as f64 - 2.0 }).collect();
+
+        assert!(strongest_anomaly(&values, &AnomalyConfig::default()).is_some());
+        let lenient = AnomalyConfig { z_threshold: 10.0, ..AnomalyConfig::default() };
+        assert!(strongest_anomaly(&values, &lenient).is_none());
+    }
+
+    #[test]
+    fn test_timer_percentiles_approximate_exact_values() {
+        let mut engine = AnalyticsEngine::new();
+        let start = Utc::now() - Duration::hours(100);
+        let mut seed: u64 = 42;
+        let mut values = Vec::with_capacity(100_000);
+        for i in 0..100_000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let value = ((seed >> 33) % 5000) as f64 / 10.0 + 1.0;
+            values.push(value);
+            let data_point = DataPoint {
+                timestamp: start + Duration::milliseconds(i * 3600),
+                value,
+                tags: HashMap::new(),
+            };
+            engine.record_point("command_execution_time".to_string(), MetricType::Timer, data_point);
+        }
+        values.sort_by(f64::total_cmp);
+
+        for p in [50.0, 90.0, 99.0] {
+            let exact = values[(p / 100.0 * (values.len() - 1) as f64) as usize];
+            let estimate = engine.get_percentile("command_execution_time", p, None).unwrap();
+            assert!(!estimate.exact);
+            assert_eq!(estimate.sample_count, 100_000);
+            assert!((estimate.value - exact).abs() <= estimate.error_bound, "p{}: {} vs {}", p, estimate.value, exact);
+        }
+
+        // A range cutting through hours mixes sketches with raw edge points
+        let range = TimeRange {
+            start: start + Duration::minutes(90),
+            end: start + Duration::minutes(630),
+        };
+        let mut in_range: Vec<f64> = engine.metrics["command_execution_time"]
+            .data_points
+            .iter()
+            .filter(|dp| dp.timestamp >= range.start && dp.timestamp <= range.end)
+            .map(|dp| dp.value)
+            .collect();
+        in_range.sort_by(f64::total_cmp);
+        let exact = in_range[(0.9 * (in_range.len() - 1) as f64) as usize];
+        let estimate = engine.get_percentile("command_execution_time", 90.0, Some(range)).unwrap();
+        assert_eq!(estimate.sample_count, in_range.len());
+        assert!((estimate.value - exact).abs() <= estimate.error_bound);
+    }
+
+    #[test]
+    fn test_performance_report_html_contains_sections_and_charts() {
+        let mut engine = AnalyticsEngine::new();
+        for (cpu, memory) in [(35.0, 900.0), (62.5, 1100.0), (48.0, 1024.0)] {
+            engine.record_metric("cpu_usage".to_string(), cpu, HashMap::new());
+            engine.record_metric("memory_usage".to_string(), memory, HashMap::new());
+        }
+
+        let range = TimeRange { start: Utc::now() - Duration::hours(1), end: Utc::now() + Duration::minutes(1) };
+        let report = engine.generate_report(ReportType::Performance, range).unwrap();
+        let html = engine.export_report_html(&report.id).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        for title in ["Performance Report", "Summary", "CPU Usage", "Memory Usage", "Performance Summary"] {
+            assert!(html.contains(&format!(">{}</h", title)), "missing section {}", title);
+        }
+        assert!(html.contains("class=\"chart chart-line\""));
+        assert!(html.contains("class=\"chart chart-area\""));
+        assert!(html.contains("<polygon class=\"area\""));
+        assert_eq!(html.matches("<polyline class=\"line\"").count(), 2);
+        assert!(html.contains("stroke=\"#FF6B6B\""));
+        assert!(html.contains("<div class=\"label\">Avg cpu usage</div><div class=\"value\">48.5</div>"));
+        assert!(!html.contains("src=\"http"), "report must be self-contained");
+
+        assert!(engine.export_report_html("missing").is_err());
+    }
+
+    #[test]
+    fn test_report_html_falls_back_to_tables() {
+        let now = Utc::now();
+        let report = AnalyticsReport {
+            id: "custom".to_string(),
+            name: "Custom <Report>".to_string(),
+            report_type: ReportType::Custom,
+            time_range: TimeRange { start: now - Duration::days(1), end: now },
+            sections: vec![
+                ReportSection {
+                    title: "Raw Chart".to_string(),
+                    content_type: ContentType::Chart,
+                    data: serde_json::json!([{ "host": "a", "load": 1.5 }, { "host": "b", "load": 0.25 }]),
+                    visualization: None,
+                },
+                ReportSection {
+                    title: "Commands".to_string(),
+                    content_type: ContentType::Chart,
+                    data: serde_json::json!({ "git": 150, "ls": 120 }),
+                    visualization: Some(VisualizationConfig {
+                        chart_type: ChartType::Bar,
+                        x_axis: "command".to_string(),
+                        y_axis: "usage_count".to_string(),
+                        series: vec!["Command Usage".to_string()],
+                        colors: Vec::new(),
+                    }),
+                },
+            ],
+            generated_at: now,
+            summary: ReportSummary { total_metrics: 0, insights_found: 0, performance_score: None, key_findings: Vec::new() },
+        };
+
+        let html = render_report_html(&report);
+        assert!(html.contains("<h1>Custom &lt;Report&gt;</h1>"));
+        assert!(html.contains("<tr><th>Host</th><th>Load</th></tr>"));
+        assert!(html.contains("<tr><td>b</td><td>0.25</td></tr>"));
+        assert_eq!(html.matches("<rect ").count(), 2);
+        assert!(html.contains("<title>git: 150</title>"));
+    }
+
+    #[test]
+    fn test_custom_histogram_metric_survives_restart_and_reports_percentiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom_metrics.json");
+
+        let mut engine = AnalyticsEngine::new();
+        engine.load_metric_definitions(path.clone()).unwrap();
+        let definition = engine
+            .define_metric("deploy_duration_s", MetricType::Histogram, AggregationType::Percentile(90.0), 14)
+            .unwrap();
+        assert_eq!(definition.retention_days, 14);
+
+        for value in 1..=100 {
+            engine.record_custom("deploy_duration_s", value as f64, HashMap::new()).unwrap();
+        }
+        let p90 = engine.get_percentile("deploy_duration_s", 90.0, None).unwrap();
+        assert_eq!((p90.value, p90.exact, p90.sample_count), (90.0, true, 100));
+        assert_eq!(engine.get_metric_value("deploy_duration_s", None), Some(90.0));
+
+        engine.record_metric("cpu_usage".to_string(), 12.0, HashMap::new());
+        let listed = engine.list_metrics();
+        assert_eq!(listed.iter().map(|m| (m.name.as_str(), m.custom)).collect::<Vec<_>>(), vec![("cpu_usage", false), ("deploy_duration_s", true)]);
+        assert_eq!((listed[1].data_points, listed[1].last_value), (100, Some(100.0)));
+
+        let mut restarted = AnalyticsEngine::new();
+        restarted.load_metric_definitions(path).unwrap();
+        let listed = restarted.list_metrics();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((listed[0].metric_type.clone(), listed[0].aggregation.clone(), listed[0].retention_days), (MetricType::Histogram, AggregationType::Percentile(90.0), 14));
+        restarted.record_custom("deploy_duration_s", 42.0, HashMap::new()).unwrap();
+        assert_eq!(restarted.get_percentile("deploy_duration_s", 50.0, None).unwrap().value, 42.0);
+    }
+
+    #[test]
+    fn test_custom_metric_type_mismatches_are_rejected() {
+        let mut engine = AnalyticsEngine::new();
+        engine.define_metric("builds_total", MetricType::Counter, AggregationType::Sum, 30).unwrap();
+        engine.record_custom("builds_total", 3.0, HashMap::new()).unwrap();
+
+        // A counter going down is a gauge-style reset
+        assert!(engine.record_custom("builds_total", -3.0, HashMap::new()).is_err());
+        assert!(engine.record_custom("builds_total", f64::NAN, HashMap::new()).is_err());
+        assert!(engine.define_metric("builds_total", MetricType::Gauge, AggregationType::Average, 30).is_err());
+        assert!(engine.record_custom("undefined_metric", 1.0, HashMap::new()).is_err());
+
+        engine.record_metric("cpu_usage".to_string(), 40.0, HashMap::new());
+        assert!(engine.define_metric("cpu_usage", MetricType::Counter, AggregationType::Sum, 30).is_err());
+        assert!(engine.define_metric("bad name", MetricType::Gauge, AggregationType::Average, 30).is_err());
+        assert!(engine.define_metric("slow", MetricType::Timer, AggregationType::Percentile(120.0), 30).is_err());
+        assert!(engine.define_metric("forever", MetricType::Gauge, AggregationType::Average, 0).is_err());
+
+        // Redefining with the same type may change aggregation and retention
+        engine.define_metric("builds_total", MetricType::Counter, AggregationType::Count, 7).unwrap();
+        assert_eq!(engine.get_metric_value("builds_total", None), Some(1.0));
+    }
 }
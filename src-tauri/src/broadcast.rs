@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::process::Command;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
@@ -14,6 +16,9 @@ pub struct TerminalSession {
     pub host: String,
     pub port: Option<u16>,
     pub username: Option<String>,
+    /// Private key used to authenticate remote sessions
+    #[serde(default)]
+    pub key_path: Option<String>,
     pub session_type: SessionType,
     pub status: SessionStatus,
     pub tags: Vec<String>,
@@ -47,6 +52,8 @@ pub struct SessionResult {
     pub session_name: String,
     pub status: String,
     pub output: String,
+    #[serde(default)]
+    pub stderr: String,
     pub exit_code: i32,
     pub execution_time: u64,
     pub timestamp: DateTime<Utc>,
@@ -69,12 +76,236 @@ pub struct BroadcastSummary {
     pub successful_sessions: usize,
     pub failed_sessions: usize,
     pub average_execution_time: u64,
+    /// Sessions clustered by identical output, largest group first
+    #[serde(default)]
+    pub output_groups: Vec<OutputGroup>,
+    /// Distinct exit codes observed, ascending
+    #[serde(default)]
+    pub exit_codes: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputGroup {
+    /// Hash of the normalized stdout shared by every session in the group
+    pub output_hash: String,
+    pub output: String,
+    pub session_ids: Vec<String>,
+    pub session_names: Vec<String>,
+}
+
+/// Line endings and trailing whitespace vary between hosts without changing what a command said
+fn normalize_output(output: &str) -> String {
+    output
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+fn group_outputs(results: &[SessionResult]) -> Vec<OutputGroup> {
+    let mut groups: Vec<OutputGroup> = Vec::new();
+    for result in results {
+        let output = normalize_output(&result.output);
+        let output_hash = format!("{:x}", Sha256::digest(output.as_bytes()))[..16].to_string();
+        match groups.iter_mut().find(|group| group.output_hash == output_hash) {
+            Some(group) => {
+                group.session_ids.push(result.session_id.clone());
+                group.session_names.push(result.session_name.clone());
+            }
+            None => groups.push(OutputGroup {
+                output_hash,
+                output,
+                session_ids: vec![result.session_id.clone()],
+                session_names: vec![result.session_name.clone()],
+            }),
+        }
+    }
+    // Stable sort keeps first-seen order among groups of equal size
+    groups.sort_by_key(|group| std::cmp::Reverse(group.session_ids.len()));
+    groups
+}
+
+#[derive(Debug)]
+struct CommandOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+}
+
+impl CommandOutput {
+    fn from_process(output: std::process::Output) -> Self {
+        Self {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: output.status.code().unwrap_or(-1),
+        }
+    }
+}
+
+/// Exit status `ssh` uses for its own connection and authentication errors
+const SSH_ERROR_EXIT: i32 = 255;
+
+/// Pools SSH connections through OpenSSH control masters so that repeated commands to the
+/// same target reuse one authenticated connection instead of handshaking every time.
+#[derive(Debug)]
+pub struct SshConnectionPool {
+    program: PathBuf,
+    control_dir: PathBuf,
+    /// Per-target connection state; each target has its own lock so a slow handshake
+    /// only holds up commands to that target
+    connected: Mutex<HashMap<String, Arc<Mutex<bool>>>>,
+}
+
+impl SshConnectionPool {
+    pub fn new(program: PathBuf, control_dir: PathBuf) -> Self {
+        Self {
+            program,
+            control_dir,
+            connected: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Refuse hosts and usernames ssh would parse as options, e.g. `-oProxyCommand=...`
+    fn validate_destination(session: &TerminalSession) -> Result<()> {
+        if session.host.is_empty() || session.host.starts_with('-') {
+            return Err(anyhow!("Invalid SSH host: {}", session.host));
+        }
+        if let Some(username) = &session.username {
+            if username.is_empty() || username.starts_with('-') || username.contains('@') {
+                return Err(anyhow!("Invalid SSH username: {}", username));
+            }
+        }
+        Ok(())
+    }
+
+    fn destination(session: &TerminalSession) -> String {
+        match &session.username {
+            Some(username) => format!("{}@{}", username, session.host),
+            None => session.host.clone(),
+        }
+    }
+
+    /// Connection key; sessions that share a target also share its connection
+    fn target_key(session: &TerminalSession) -> String {
+        format!(
+            "{}:{}:{}",
+            Self::destination(session),
+            session.port.unwrap_or(22),
+            session.key_path.as_deref().unwrap_or("")
+        )
+    }
+
+    fn control_path(&self, key: &str) -> PathBuf {
+        // Unix socket paths are short, so name the socket after a hash of the target
+        let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+        self.control_dir.join(&digest[..16])
+    }
+
+    fn ssh_command(&self, session: &TerminalSession, control_path: &std::path::Path) -> Command {
+        let mut command = Command::new(&self.program);
+        command.arg("-S").arg(control_path).args(["-o", "BatchMode=yes"]);
+        if let Some(port) = session.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        if let Some(key_path) = &session.key_path {
+            command.arg("-i").arg(key_path).args(["-o", "IdentitiesOnly=yes"]);
+        }
+        command
+    }
+
+    async fn target_state(&self, key: &str) -> Arc<Mutex<bool>> {
+        self.connected.lock().await.entry(key.to_string()).or_default().clone()
+    }
+
+    async fn ensure_connected(&self, session: &TerminalSession, key: &str, control_path: &std::path::Path) -> Result<()> {
+        // Held across the handshake so concurrent commands to a new target connect only once
+        let state = self.target_state(key).await;
+        let mut connected = state.lock().await;
+        if *connected {
+            return Ok(());
+        }
+
+        tokio::fs::create_dir_all(&self.control_dir).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            tokio::fs::set_permissions(&self.control_dir, std::fs::Permissions::from_mode(0o700)).await?;
+        }
+
+        let output = self
+            .ssh_command(session, control_path)
+            .args(["-M", "-N", "-f", "-o", "ControlPersist=600", "-o", "ConnectTimeout=10"])
+            .arg("--")
+            .arg(Self::destination(session))
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to run {}: {}", self.program.display(), e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if stderr.contains("Permission denied") || stderr.contains("Authentication failed") {
+                return Err(anyhow!("SSH authentication failed for {}: {}", Self::destination(session), stderr));
+            }
+            return Err(anyhow!("SSH connection to {} failed: {}", Self::destination(session), stderr));
+        }
+
+        *connected = true;
+        Ok(())
+    }
+
+    async fn execute(&self, session: &TerminalSession, command: &str) -> Result<CommandOutput> {
+        Self::validate_destination(session)?;
+        let key = Self::target_key(session);
+        let control_path = self.control_path(&key);
+        self.ensure_connected(session, &key, &control_path).await?;
+
+        let output = self
+            .ssh_command(session, &control_path)
+            .args(["-o", "ControlMaster=no"])
+            .arg("--")
+            .arg(Self::destination(session))
+            .arg(command)
+            .output()
+            .await?;
+        let output = CommandOutput::from_process(output);
+        if output.exit_code == SSH_ERROR_EXIT {
+            // The master may have died; reconnect on the next command
+            *self.target_state(&key).await.lock().await = false;
+        }
+        Ok(output)
+    }
+
+    /// Close every pooled connection
+    pub async fn close_all(&self) {
+        let targets: Vec<(String, Arc<Mutex<bool>>)> = self.connected.lock().await.drain().collect();
+        for (key, state) in targets {
+            if !*state.lock().await {
+                continue;
+            }
+            let destination = key.split(':').next().unwrap_or_default().to_string();
+            let _ = Command::new(&self.program)
+                .arg("-S")
+                .arg(self.control_path(&key))
+                .args(["-O", "exit", "--"])
+                .arg(destination)
+                .output()
+                .await;
+        }
+    }
+}
+
+impl Default for SshConnectionPool {
+    fn default() -> Self {
+        Self::new(PathBuf::from("ssh"), std::env::temp_dir().join(format!("nexus-ssh-{}", std::process::id())))
+    }
 }
 
 #[derive(Debug)]
 pub struct BroadcastManager {
     sessions: Arc<RwLock<HashMap<String, TerminalSession>>>,
     active_broadcasts: Arc<RwLock<HashMap<String, tokio::task::JoinHandle<()>>>>,
+    ssh_pool: SshConnectionPool,
 }
 
 impl BroadcastManager {
@@ -82,9 +313,15 @@ impl BroadcastManager {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             active_broadcasts: Arc::new(RwLock::new(HashMap::new())),
+            ssh_pool: SshConnectionPool::default(),
         }
     }
 
+    pub fn with_ssh_pool(mut self, ssh_pool: SshConnectionPool) -> Self {
+        self.ssh_pool = ssh_pool;
+        self
+    }
+
     /// Register a new terminal session for broadcasting
     pub async fn register_session(&self, session: TerminalSession) -> Result<()> {
         let mut sessions = self.sessions.write().await;
@@ -111,6 +348,31 @@ impl BroadcastManager {
         Ok(sessions.get(session_id).cloned())
     }
 
+    /// Sessions carrying `tag`, compared case-insensitively
+    pub async fn get_sessions_by_tag(&self, tag: &str) -> Result<Vec<TerminalSession>> {
+        self.resolve_tags(&[tag.to_string()], false).await
+    }
+
+    /// Sessions matching any (or, with `match_all`, every) one of `tags`, each listed once
+    async fn resolve_tags(&self, tags: &[String], match_all: bool) -> Result<Vec<TerminalSession>> {
+        let wanted: HashSet<String> = tags.iter().map(|tag| tag.to_lowercase()).collect();
+        let sessions = self.sessions.read().await;
+        let mut matched: Vec<TerminalSession> = sessions
+            .values()
+            .filter(|session| {
+                let session_tags: HashSet<String> = session.tags.iter().map(|tag| tag.to_lowercase()).collect();
+                if match_all {
+                    wanted.is_subset(&session_tags)
+                } else {
+                    !wanted.is_disjoint(&session_tags)
+                }
+            })
+            .cloned()
+            .collect();
+        matched.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        Ok(matched)
+    }
+
     /// Update session status
     pub async fn update_session_status(&self, session_id: &str, status: SessionStatus) -> Result<()> {
         let mut sessions = self.sessions.write().await;
@@ -145,21 +407,33 @@ impl BroadcastManager {
             Ok(output) => Ok(SessionResult {
                 session_id: session.id.clone(),
                 session_name: session.name.clone(),
-                status: "success".to_string(),
-                output,
-                exit_code: 0,
-                execution_time,
-                timestamp,
-            }),
-            Err(e) => Ok(SessionResult {
-                session_id: session.id.clone(),
-                session_name: session.name.clone(),
-                status: "failed".to_string(),
-                output: format!("Error: {}", e),
-                exit_code: 1,
+                status: if output.exit_code == 0 { "success" } else { "failed" }.to_string(),
+                output: output.stdout,
+                stderr: output.stderr,
+                exit_code: output.exit_code,
                 execution_time,
                 timestamp,
             }),
+            Err(e) => {
+                let message = e.to_string();
+                let status = if message.contains("authentication failed") {
+                    "auth_failed"
+                } else if message.contains("connection to") {
+                    "connection_failed"
+                } else {
+                    "failed"
+                };
+                Ok(SessionResult {
+                    session_id: session.id.clone(),
+                    session_name: session.name.clone(),
+                    status: status.to_string(),
+                    output: format!("Error: {}", message),
+                    stderr: String::new(),
+                    exit_code: 1,
+                    execution_time,
+                    timestamp,
+                })
+            }
         }
     }
 
@@ -189,6 +463,7 @@ impl BroadcastManager {
                         session_name: "unknown".to_string(),
                         status: "failed".to_string(),
                         output: format!("Error: {}", e),
+                        stderr: String::new(),
                         exit_code: 1,
                         execution_time: 0,
                         timestamp: Utc::now(),
@@ -228,10 +503,41 @@ impl BroadcastManager {
                 successful_sessions,
                 failed_sessions,
                 average_execution_time,
+                output_groups: Vec::new(),
+                exit_codes: Vec::new(),
             },
         })
     }
+
+    /// Execute command on multiple sessions and roll up identical outputs and exit codes
+    pub async fn broadcast_command_with_summary(&self, session_ids: &[String], command: &str) -> Result<BroadcastResult> {
+        let mut result = self.broadcast_command(session_ids, command).await?;
+        result.summary.output_groups = group_outputs(&result.results);
+        let mut exit_codes: Vec<i32> = result.results.iter().map(|r| r.exit_code).collect();
+        exit_codes.sort_unstable();
+        exit_codes.dedup();
+        result.summary.exit_codes = exit_codes;
+        Ok(result)
+    }
     
+    /// Execute command on every session whose tags match `tags`
+    pub async fn broadcast_to_tags(&self, tags: &[String], command: &str, match_all: bool) -> Result<BroadcastResult> {
+        if tags.is_empty() {
+            return Err(anyhow!("No tags given to broadcast to"));
+        }
+        let sessions = self.resolve_tags(tags, match_all).await?;
+        if sessions.is_empty() {
+            return Err(anyhow!(
+                "No sessions match {} of the tags: {}",
+                if match_all { "all" } else { "any" },
+                tags.join(", ")
+            ));
+        }
+
+        let session_ids: Vec<String> = sessions.into_iter().map(|session| session.id).collect();
+        self.broadcast_command(&session_ids, command).await
+    }
+
     /// Start tracking a broadcast operation
     async fn start_broadcast_tracking(&self, broadcast_id: String, _session_ids: Vec<String>) -> tokio::task::JoinHandle<()> {
         let active_broadcasts = self.active_broadcasts.clone();
@@ -271,7 +577,7 @@ impl BroadcastManager {
     }
 
     /// Execute a local command
-    async fn execute_local_command(&self, command: &str) -> Result<String> {
+    async fn execute_local_command(&self, command: &str) -> Result<CommandOutput> {
         let output = if cfg!(target_os = "windows") {
             Command::new("cmd")
                 .args(["/C", command])
@@ -285,28 +591,27 @@ impl BroadcastManager {
                 .await?
         };
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        Ok(CommandOutput::from_process(output))
     }
 
-    /// Execute a remote command via SSH
-    async fn execute_remote_command(&self, session: &TerminalSession, command: &str) -> Result<String> {
-        let ssh_command = if let Some(username) = &session.username {
-            format!("ssh {}@{} '{}'", username, session.host, command)
-        } else {
-            format!("ssh {} '{}'", session.host, command)
-        };
+    /// Execute a remote command via SSH over a pooled connection
+    async fn execute_remote_command(&self, session: &TerminalSession, command: &str) -> Result<CommandOutput> {
+        self.ssh_pool.execute(session, command).await
+    }
 
-        self.execute_local_command(&ssh_command).await
+    /// Close pooled SSH connections to remote sessions
+    pub async fn close_remote_connections(&self) {
+        self.ssh_pool.close_all().await;
     }
 
     /// Execute a command in a Docker container
-    async fn execute_container_command(&self, session: &TerminalSession, command: &str) -> Result<String> {
+    async fn execute_container_command(&self, session: &TerminalSession, command: &str) -> Result<CommandOutput> {
         let docker_command = format!("docker exec {} {}", session.host, command);
         self.execute_local_command(&docker_command).await
     }
 
     /// Execute a command in WSL
-    async fn execute_wsl_command(&self, session: &TerminalSession, command: &str) -> Result<String> {
+    async fn execute_wsl_command(&self, session: &TerminalSession, command: &str) -> Result<CommandOutput> {
         let wsl_command = format!("wsl -d {} -- {}", session.host, command);
         self.execute_local_command(&wsl_command).await
     }
@@ -337,6 +642,7 @@ impl BroadcastManager {
             host: "localhost".to_string(),
             port: None,
             username: None,
+            key_path: None,
             session_type: SessionType::Local,
             status: SessionStatus::Active,
             tags: vec!["local".to_string()],
@@ -347,6 +653,31 @@ impl BroadcastManager {
             last_active: Utc::now(),
         }
     }
+
+    /// Create a session that runs commands on `host` over SSH
+    pub fn create_ssh_session(
+        name: String,
+        host: String,
+        port: Option<u16>,
+        username: Option<String>,
+        key_path: Option<String>,
+    ) -> TerminalSession {
+        TerminalSession {
+            id: Uuid::new_v4().to_string(),
+            name,
+            host,
+            port,
+            username,
+            key_path,
+            session_type: SessionType::Remote,
+            status: SessionStatus::Inactive,
+            tags: vec!["ssh".to_string()],
+            environment: HashMap::new(),
+            working_directory: None,
+            created_at: Utc::now(),
+            last_active: Utc::now(),
+        }
+    }
 }
 
 /// Global broadcast manager instance
@@ -356,3 +687,207 @@ static BROADCAST_MANAGER: once_cell::sync::Lazy<BroadcastManager> =
 pub fn get_broadcast_manager() -> &'static BroadcastManager {
     &BROADCAST_MANAGER
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in for `ssh` that behaves like a server accepting only `deploy@` logins
+    const FAKE_SSH: &str = r#"#!/bin/sh
+log="$(dirname "$0")/connections.log"
+control=""; master=0; destination=""
+while [ $# -gt 0 ]; do
+    case "$1" in
+        -S) control="$2"; shift 2 ;;
+        -o|-p|-i) shift 2 ;;
+        -M) master=1; shift ;;
+        -N|-f) shift ;;
+        --) destination="$2"; shift 2; break ;;
+        -*) echo "unexpected option $1" >&2; exit 255 ;;
+    esac
+done
+if [ $master -eq 1 ]; then
+    case "$destination" in
+        deploy@*) echo "master $destination" >> "$log"; touch "$control"; exit 0 ;;
+        *) echo "$destination: Permission denied (publickey)." >&2; exit 255 ;;
+    esac
+fi
+[ -e "$control" ] || { echo "Control socket connect($control): No such file or directory" >&2; exit 255; }
+echo "exec $destination" >> "$log"
+exec sh -c "$1"
+"#;
+
+    fn manager_with_fake_ssh(dir: &std::path::Path) -> BroadcastManager {
+        let program = dir.join("ssh");
+        std::fs::write(&program, FAKE_SSH).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        BroadcastManager::new().with_ssh_pool(SshConnectionPool::new(program, dir.join("control")))
+    }
+
+    async fn manager_with_tagged_sessions() -> BroadcastManager {
+        let manager = BroadcastManager::new();
+        for (name, tags) in [("web-1", vec!["Web", "prod"]), ("web-2", vec!["web", "staging"]), ("db-1", vec!["db", "PROD"])] {
+            let mut session = BroadcastManager::create_local_session(name.to_string());
+            session.tags = tags.into_iter().map(String::from).collect();
+            manager.register_session(session).await.unwrap();
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_any_matching_tag() {
+        let manager = manager_with_tagged_sessions().await;
+
+        let web: Vec<String> = manager.get_sessions_by_tag("WEB").await.unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(web, vec!["web-1", "web-2"]);
+
+        // web-1 matches both tags but only runs the command once
+        let tags = vec!["web".to_string(), "prod".to_string()];
+        let result = manager.broadcast_to_tags(&tags, "echo hi", false).await.unwrap();
+        let mut names: Vec<&str> = result.results.iter().map(|r| r.session_name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["db-1", "web-1", "web-2"]);
+        assert_eq!(result.summary.total_sessions, 3);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_all_matching_tags() {
+        let manager = manager_with_tagged_sessions().await;
+        let tags = vec!["prod".to_string(), "Web".to_string()];
+        let result = manager.broadcast_to_tags(&tags, "echo hi", true).await.unwrap();
+        assert_eq!(result.results.len(), 1);
+        assert_eq!(result.results[0].session_name, "web-1");
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_to_unmatched_tags_is_an_error() {
+        let manager = manager_with_tagged_sessions().await;
+        assert!(manager.get_sessions_by_tag("cache").await.unwrap().is_empty());
+        let error = manager.broadcast_to_tags(&["db".to_string(), "staging".to_string()], "echo hi", true).await.unwrap_err();
+        assert!(error.to_string().contains("No sessions match"));
+        assert!(manager.broadcast_to_tags(&[], "echo hi", false).await.is_err());
+    }
+
+    fn session_result(name: &str, output: &str, exit_code: i32) -> SessionResult {
+        SessionResult {
+            session_id: format!("id-{}", name),
+            session_name: name.to_string(),
+            status: if exit_code == 0 { "success" } else { "failed" }.to_string(),
+            output: output.to_string(),
+            stderr: String::new(),
+            exit_code,
+            execution_time: 10,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_identical_outputs_are_grouped() {
+        let results = vec![
+            session_result("web-1", "nginx 1.24\n", 0),
+            session_result("db-1", "command not found\n", 127),
+            session_result("web-2", "nginx 1.24\r\n", 0),
+            session_result("web-3", "nginx 1.24  \n\n", 0),
+            session_result("web-4", "nginx 1.22\n", 0),
+        ];
+
+        let groups = group_outputs(&results);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].output, "nginx 1.24");
+        assert_eq!(groups[0].session_names, vec!["web-1", "web-2", "web-3"]);
+        assert_eq!(groups[1].session_names, vec!["db-1"]);
+        assert_eq!(groups[2].session_ids, vec!["id-web-4"]);
+        assert_ne!(groups[0].output_hash, groups[2].output_hash);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_with_summary_rolls_up_sessions() {
+        let manager = manager_with_tagged_sessions().await;
+        let ids: Vec<String> = manager.get_sessions().await.unwrap().into_iter().map(|s| s.id).collect();
+
+        let result = manager.broadcast_command_with_summary(&ids, "echo same; exit 4").await.unwrap();
+        assert_eq!(result.summary.failed_sessions, 3);
+        assert_eq!(result.summary.successful_sessions, 0);
+        assert_eq!(result.summary.exit_codes, vec![4]);
+        assert_eq!(result.summary.output_groups.len(), 1);
+        assert_eq!(result.summary.output_groups[0].session_ids.len(), 3);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_broadcast_over_pooled_ssh_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with_fake_ssh(dir.path());
+        let web = BroadcastManager::create_ssh_session(
+            "web".to_string(),
+            "web.example.com".to_string(),
+            Some(2222),
+            Some("deploy".to_string()),
+            Some("/home/me/.ssh/id_ed25519".to_string()),
+        );
+        let ids = vec![web.id.clone()];
+        manager.register_session(web).await.unwrap();
+
+        let first = manager.broadcast_command(&ids, "echo hello; echo careful >&2").await.unwrap();
+        let result = &first.results[0];
+        assert_eq!(result.status, "success");
+        assert_eq!(result.output, "hello\n");
+        assert_eq!(result.stderr, "careful\n");
+        assert_eq!(result.exit_code, 0);
+
+        let second = manager.broadcast_command(&ids, "exit 3").await.unwrap();
+        assert_eq!(second.results[0].status, "failed");
+        assert_eq!(second.results[0].exit_code, 3);
+
+        // One handshake served both broadcasts
+        let log = std::fs::read_to_string(dir.path().join("connections.log")).unwrap();
+        assert_eq!(log.lines().filter(|line| line.starts_with("master")).count(), 1);
+        assert_eq!(log.lines().filter(|line| line.starts_with("exec")).count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ssh_auth_failure_is_reported_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with_fake_ssh(dir.path());
+        let denied = BroadcastManager::create_ssh_session("db".to_string(), "db.example.com".to_string(), None, Some("root".to_string()), None);
+        let local = BroadcastManager::create_local_session("local".to_string());
+        let ids = vec![denied.id.clone(), local.id.clone()];
+        manager.register_session(denied).await.unwrap();
+        manager.register_session(local).await.unwrap();
+
+        let broadcast = manager.broadcast_command(&ids, "echo ok").await.unwrap();
+        assert_eq!(broadcast.overall_status, "partial");
+        assert_eq!(broadcast.results[0].status, "auth_failed");
+        assert!(broadcast.results[0].output.contains("Permission denied"));
+        assert_eq!(broadcast.results[1].status, "success");
+        assert_eq!(broadcast.results[1].output, "ok\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_option_like_ssh_destinations_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with_fake_ssh(dir.path());
+        let host = BroadcastManager::create_ssh_session("evil".to_string(), "-oProxyCommand=touch pwned".to_string(), None, None, None);
+        let user = BroadcastManager::create_ssh_session(
+            "evil-user".to_string(),
+            "web.example.com".to_string(),
+            None,
+            Some("-oProxyCommand=touch pwned".to_string()),
+            None,
+        );
+        let ids = vec![host.id.clone(), user.id.clone()];
+        manager.register_session(host).await.unwrap();
+        manager.register_session(user).await.unwrap();
+
+        let broadcast = manager.broadcast_command(&ids, "echo ok").await.unwrap();
+        assert!(broadcast.results.iter().all(|result| result.status != "success"));
+        assert!(broadcast.results.iter().all(|result| result.output.contains("Invalid SSH")));
+        assert!(!dir.path().join("connections.log").exists());
+    }
+}
@@ -0,0 +1,365 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Limits and persistence settings for a [`Cache`]
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub max_entries: usize,
+    pub max_bytes: Option<usize>,
+    pub ttl: Option<Duration>,
+    pub persist_path: Option<PathBuf>,
+}
+
+impl CacheConfig {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            max_bytes: None,
+            ttl: None,
+            persist_path: None,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn persist_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.persist_path = Some(path.into());
+        self
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+/// Hit/miss counters for a cache, reported to analytics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+impl CacheMetrics {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry<V> {
+    value: V,
+    bytes: usize,
+    inserted_at: DateTime<Utc>,
+    last_access: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedEntry<K, V> {
+    key: K,
+    value: V,
+    inserted_at: DateTime<Utc>,
+}
+
+/// Bounded LRU cache with optional TTL, byte budget and disk persistence
+#[derive(Debug)]
+pub struct Cache<K, V> {
+    config: CacheConfig,
+    entries: HashMap<K, CacheEntry<V>>,
+    /// Access tick -> key, oldest first
+    recency: BTreeMap<u64, K>,
+    tick: u64,
+    total_bytes: usize,
+    weigher: fn(&V) -> usize,
+    metrics: CacheMetrics,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Cache<K, V> {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            total_bytes: 0,
+            weigher: |_| std::mem::size_of::<V>(),
+            metrics: CacheMetrics::default(),
+        }
+    }
+
+    /// Use `weigher` to estimate the size of each value for the byte budget
+    pub fn with_weigher(mut self, weigher: fn(&V) -> usize) -> Self {
+        self.weigher = weigher;
+        self
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        if self.is_expired(key) {
+            self.remove_entry(key);
+            self.metrics.expirations += 1;
+        }
+
+        let tick = self.next_tick();
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.recency.remove(&entry.last_access);
+                self.recency.insert(tick, key.clone());
+                entry.last_access = tick;
+                self.metrics.hits += 1;
+                Some(entry.value.clone())
+            }
+            None => {
+                self.metrics.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.insert_at(key, value, Utc::now());
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|entry| entry.value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.total_bytes = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every entry older than the TTL
+    pub fn purge_expired(&mut self) {
+        let expired: Vec<K> = self
+            .entries
+            .keys()
+            .filter(|key| self.is_expired(key))
+            .cloned()
+            .collect();
+        for key in expired {
+            self.remove_entry(&key);
+            self.metrics.expirations += 1;
+        }
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            entries: self.entries.len(),
+            bytes: self.total_bytes,
+            ..self.metrics.clone()
+        }
+    }
+
+    fn insert_at(&mut self, key: K, value: V, inserted_at: DateTime<Utc>) {
+        self.remove_entry(&key);
+
+        let bytes = (self.weigher)(&value);
+        let tick = self.next_tick();
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                bytes,
+                inserted_at,
+                last_access: tick,
+            },
+        );
+        self.total_bytes += bytes;
+        self.metrics.inserts += 1;
+
+        self.evict_over_budget();
+    }
+
+    fn evict_over_budget(&mut self) {
+        while self.entries.len() > self.config.max_entries
+            || self.config.max_bytes.is_some_and(|max| self.total_bytes > max)
+        {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.total_bytes -= entry.bytes;
+                self.metrics.evictions += 1;
+            }
+        }
+    }
+
+    fn is_expired(&self, key: &K) -> bool {
+        let (Some(ttl), Some(entry)) = (self.config.ttl, self.entries.get(key)) else {
+            return false;
+        };
+        chrono::Duration::from_std(ttl).is_ok_and(|ttl| Utc::now() - entry.inserted_at >= ttl)
+    }
+
+    fn remove_entry(&mut self, key: &K) -> Option<CacheEntry<V>> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_access);
+        self.total_bytes -= entry.bytes;
+        Some(entry)
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Eq + Hash + Clone + Serialize + DeserializeOwned,
+    V: Clone + Serialize + DeserializeOwned,
+{
+    /// Create a cache, restoring entries from `config.persist_path` when it exists
+    pub fn load(config: CacheConfig) -> Result<Self> {
+        let path = config.persist_path.clone();
+        let mut cache = Self::new(config);
+
+        if let Some(path) = path.filter(|p| p.exists()) {
+            let data = std::fs::read(&path).with_context(|| format!("Failed to read cache {:?}", path))?;
+            let persisted: Vec<PersistedEntry<K, V>> =
+                serde_json::from_slice(&data).with_context(|| format!("Failed to parse cache {:?}", path))?;
+            for entry in persisted {
+                cache.insert_at(entry.key, entry.value, entry.inserted_at);
+            }
+            cache.purge_expired();
+            cache.metrics = CacheMetrics::default();
+        }
+
+        Ok(cache)
+    }
+
+    /// Write entries to `config.persist_path`, least recently used first
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.config.persist_path else {
+            return Ok(());
+        };
+
+        let persisted: Vec<PersistedEntry<&K, &V>> = self
+            .recency
+            .values()
+            .filter_map(|key| {
+                self.entries.get(key).map(|entry| PersistedEntry {
+                    key,
+                    value: &entry.value,
+                    inserted_at: entry.inserted_at,
+                })
+            })
+            .collect();
+
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(&persisted)?)
+            .with_context(|| format!("Failed to write cache {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ttl_expiry() {
+        let mut cache = Cache::new(CacheConfig::new(10).with_ttl(Duration::from_millis(30)));
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.get(&"a".to_string()), Some(1));
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(cache.get(&"a".to_string()), None);
+        assert!(cache.is_empty());
+        assert_eq!(cache.metrics().expirations, 1);
+    }
+
+    #[test]
+    fn test_lru_eviction_by_count_and_bytes() {
+        let mut cache = Cache::new(CacheConfig::new(2));
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        cache.get(&1);
+        cache.insert(3, "three");
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some("one"));
+        assert_eq!(cache.get(&3), Some("three"));
+
+        let mut cache = Cache::new(CacheConfig::new(100).with_max_bytes(10)).with_weigher(|v: &String| v.len());
+        cache.insert(1, "aaaa".to_string());
+        cache.insert(2, "bbbb".to_string());
+        cache.get(&1);
+        cache.insert(3, "cccc".to_string());
+        assert_eq!(cache.get(&2), None);
+        assert!(cache.get(&1).is_some());
+        assert_eq!(cache.metrics().bytes, 8);
+        assert_eq!(cache.metrics().evictions, 1);
+    }
+
+    #[test]
+    fn test_persistence_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig::new(10).persist_to(dir.path().join("cache.json"));
+
+        let mut cache: Cache<String, Vec<u32>> = Cache::new(config.clone());
+        cache.insert("old".to_string(), vec![1]);
+        cache.insert("new".to_string(), vec![2, 3]);
+        cache.save().unwrap();
+
+        let mut restored: Cache<String, Vec<u32>> = Cache::load(config.clone()).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.get(&"new".to_string()), Some(vec![2, 3]));
+
+        // Recency survives the round trip: "old" is still evicted first
+        let mut restored: Cache<String, Vec<u32>> = Cache::load(CacheConfig { max_entries: 2, ..config }).unwrap();
+        restored.insert("newest".to_string(), vec![4]);
+        assert_eq!(restored.get(&"old".to_string()), None);
+    }
+
+    #[test]
+    fn test_metrics_count_hits_and_misses() {
+        let mut cache = Cache::new(CacheConfig::default());
+        cache.insert("k", 1);
+        cache.get(&"k");
+        cache.get(&"k");
+        cache.get(&"missing");
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.hits, 2);
+        assert_eq!(metrics.misses, 1);
+        assert_eq!(metrics.inserts, 1);
+        assert_eq!(metrics.entries, 1);
+        assert!((metrics.hit_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,589 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Lines of unchanged context around each diff hunk
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// A single AI-proposed edit anchored to a line range of a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeSuggestion {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub file: Option<String>,
+    /// First line of the region to replace, 1-based
+    pub start_line: usize,
+    /// Last line of the region to replace, inclusive
+    pub end_line: usize,
+    pub original: String,
+    pub replacement: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Result of applying a suggestion to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedSuggestion {
+    pub suggestion_id: String,
+    pub file: String,
+    pub backup_path: String,
+    pub diff: String,
+}
+
+/// `ai_suggest_improvements` output: prose by default, anchored edits when requested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ImprovementSuggestions {
+    Prose(String),
+    Structured(Vec<CodeSuggestion>),
+}
+
+#[derive(Debug)]
+struct AppliedEdit {
+    suggestion: CodeSuggestion,
+    backup_path: PathBuf,
+    applied_content: String,
+}
+
+/// Parse the JSON array of suggestions in an AI response, assigning ids and the target file
+pub fn parse_suggestions(response: &str, file: Option<&str>) -> Result<Vec<CodeSuggestion>> {
+    let json = match (response.find('['), response.rfind(']')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => return Err(anyhow!("AI response does not contain a list of suggestions")),
+    };
+    let mut suggestions: Vec<CodeSuggestion> =
+        serde_json::from_str(json).context("Failed to parse AI suggestions")?;
+
+    suggestions.retain(|s| s.start_line >= 1 && s.end_line >= s.start_line);
+    for suggestion in &mut suggestions {
+        suggestion.id = Uuid::new_v4().to_string();
+        if let Some(file) = file {
+            suggestion.file = Some(file.to_string());
+        }
+    }
+    Ok(suggestions)
+}
+
+/// Most suggestions offered when the AI's answer can't be used
+const MAX_FALLBACK_SUGGESTIONS: usize = 8;
+
+/// A shell command offered while the user types
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub command: String,
+    pub description: String,
+    /// How likely the command is what the user wants, 0 to 1
+    pub confidence: f64,
+    pub category: String,
+    #[serde(default)]
+    pub context: Vec<String>,
+    #[serde(rename = "learnedFrom", default, skip_serializing_if = "Option::is_none")]
+    pub learned_from: Option<String>,
+}
+
+impl Suggestion {
+    fn is_valid(&self) -> bool {
+        !self.command.trim().is_empty() && (0.0..=1.0).contains(&self.confidence)
+    }
+}
+
+/// Extract command suggestions from an AI response that may wrap its JSON array in
+/// prose or code fences; entries that don't match the schema are dropped
+pub fn parse_command_suggestions(response: &str) -> Vec<Suggestion> {
+    let fenced = fenced_blocks(response);
+    let candidates = fenced.iter().copied().chain(std::iter::once(response));
+
+    for candidate in candidates {
+        for array in json_arrays(candidate) {
+            let suggestions: Vec<Suggestion> = array
+                .into_iter()
+                .filter_map(|entry| serde_json::from_value::<Suggestion>(entry).ok())
+                .filter(Suggestion::is_valid)
+                .map(|mut suggestion| {
+                    suggestion.command = suggestion.command.trim().to_string();
+                    suggestion.learned_from = Some("ai".to_string());
+                    suggestion
+                })
+                .collect();
+            if !suggestions.is_empty() {
+                return suggestions;
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Suggestions built from the user's own history and the project they're in, for when
+/// the AI gave nothing usable. `recent_commands` is oldest first.
+pub fn fallback_suggestions(partial: &str, recent_commands: &[String], project_type: &str) -> Vec<Suggestion> {
+    let partial = partial.trim_start();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for command in recent_commands {
+        *counts.entry(command.trim()).or_default() += 1;
+    }
+
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for command in recent_commands.iter().rev().map(|c| c.trim()) {
+        if command.is_empty() || !command.starts_with(partial) || suggestions.iter().any(|s| s.command == command) {
+            continue;
+        }
+        let runs = counts[command];
+        suggestions.push(Suggestion {
+            command: command.to_string(),
+            description: format!("Run again ({} time{} recently)", runs, if runs == 1 { "" } else { "s" }),
+            confidence: (0.6 + 0.1 * runs.min(3) as f64).min(0.9),
+            category: category_for(command).to_string(),
+            context: vec!["recent-history".to_string()],
+            learned_from: Some("history".to_string()),
+        });
+    }
+
+    for (command, description) in project_commands(project_type) {
+        if !command.starts_with(partial) || suggestions.iter().any(|s| s.command == *command) {
+            continue;
+        }
+        suggestions.push(Suggestion {
+            command: command.to_string(),
+            description: description.to_string(),
+            confidence: 0.5,
+            category: "dev".to_string(),
+            context: vec![format!("{}-project", project_type)],
+            learned_from: Some("context".to_string()),
+        });
+    }
+
+    suggestions.truncate(MAX_FALLBACK_SUGGESTIONS);
+    suggestions
+}
+
+/// Project type of a directory from the files it contains
+pub fn detect_project_type(entries: &[String]) -> &'static str {
+    let has = |name: &str| entries.iter().any(|entry| entry == name);
+    if has("package.json") {
+        "nodejs"
+    } else if has("Cargo.toml") {
+        "rust"
+    } else if has("requirements.txt") || has("setup.py") {
+        "python"
+    } else if has("go.mod") {
+        "go"
+    } else {
+        "other"
+    }
+}
+
+fn project_commands(project_type: &str) -> &'static [(&'static str, &'static str)] {
+    match project_type {
+        "rust" => &[
+            ("cargo build", "Build the crate"),
+            ("cargo test", "Run the test suite"),
+            ("cargo run", "Build and run the binary"),
+            ("cargo clippy", "Lint the crate"),
+        ],
+        "nodejs" => &[
+            ("npm install", "Install dependencies"),
+            ("npm test", "Run the test suite"),
+            ("npm run dev", "Start the dev server"),
+            ("npm run build", "Build for production"),
+        ],
+        "python" => &[
+            ("pip install -r requirements.txt", "Install dependencies"),
+            ("pytest", "Run the test suite"),
+            ("python -m venv .venv", "Create a virtual environment"),
+        ],
+        "go" => &[
+            ("go build ./...", "Build all packages"),
+            ("go test ./...", "Run the test suite"),
+            ("go run .", "Build and run the module"),
+        ],
+        _ => &[],
+    }
+}
+
+fn category_for(command: &str) -> &'static str {
+    match command.split_whitespace().next().unwrap_or_default() {
+        "git" => "git",
+        "docker" | "docker-compose" | "podman" => "docker",
+        "cd" | "ls" | "pwd" | "tree" => "navigation",
+        "grep" | "rg" | "find" | "fd" => "search",
+        "cp" | "mv" | "rm" | "mkdir" | "touch" | "cat" | "chmod" => "file-ops",
+        "curl" | "wget" | "ssh" | "ping" | "scp" => "network",
+        "cargo" | "npm" | "yarn" | "pnpm" | "pip" | "python" | "pytest" | "go" | "make" => "dev",
+        _ => "system",
+    }
+}
+
+/// Contents of each ``` fenced block, in order
+fn fenced_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find("```") {
+        let after = &rest[open + 3..];
+        // Skip the info string, e.g. "json"
+        let body_start = after.find('\n').map(|i| i + 1).unwrap_or(after.len());
+        let body = &after[body_start..];
+        match body.find("```") {
+            Some(close) => {
+                blocks.push(&body[..close]);
+                rest = &body[close + 3..];
+            }
+            None => break,
+        }
+    }
+    blocks
+}
+
+/// Every balanced, well-formed JSON array in `text`, outermost first
+fn json_arrays(text: &str) -> Vec<Vec<serde_json::Value>> {
+    let bytes = text.as_bytes();
+    let mut arrays = Vec::new();
+    let mut start = 0;
+    while let Some(offset) = text[start..].find('[') {
+        let open = start + offset;
+        match matching_bracket(bytes, open) {
+            Some(close) => match serde_json::from_str::<Vec<serde_json::Value>>(&text[open..=close]) {
+                Ok(array) => {
+                    arrays.push(array);
+                    start = close + 1;
+                }
+                Err(_) => start = open + 1,
+            },
+            None => start = open + 1,
+        }
+    }
+    arrays
+}
+
+/// Index of the `]` closing the `[` at `open`, ignoring brackets inside strings
+fn matching_bracket(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &byte) in bytes.iter().enumerate().skip(open) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return (byte == b']').then_some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Staged AI suggestions and the backups needed to undo applied ones
+#[derive(Debug)]
+pub struct SuggestionManager {
+    staged: HashMap<String, CodeSuggestion>,
+    applied: HashMap<PathBuf, Vec<AppliedEdit>>,
+    backup_dir: PathBuf,
+}
+
+impl SuggestionManager {
+    pub fn new(backup_dir: PathBuf) -> Self {
+        Self {
+            staged: HashMap::new(),
+            applied: HashMap::new(),
+            backup_dir,
+        }
+    }
+
+    pub fn stage(&mut self, suggestions: &[CodeSuggestion]) {
+        for suggestion in suggestions {
+            self.staged.insert(suggestion.id.clone(), suggestion.clone());
+        }
+    }
+
+    pub fn staged_for(&self, file: &str) -> Vec<CodeSuggestion> {
+        let mut suggestions: Vec<CodeSuggestion> = self
+            .staged
+            .values()
+            .filter(|s| s.file.as_deref() == Some(file))
+            .cloned()
+            .collect();
+        suggestions.sort_by_key(|s| s.start_line);
+        suggestions
+    }
+
+    /// Apply one staged suggestion, rejecting it if the target lines no longer match
+    pub fn apply(&mut self, file: &str, suggestion_id: &str) -> Result<AppliedSuggestion> {
+        let suggestion = self
+            .staged
+            .get(suggestion_id)
+            .ok_or_else(|| anyhow!("Suggestion not found: {}", suggestion_id))?
+            .clone();
+        if suggestion.file.as_deref().is_some_and(|f| f != file) {
+            return Err(anyhow!("Suggestion {} does not target {}", suggestion_id, file));
+        }
+
+        let path = Path::new(file);
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", file))?;
+        let lines: Vec<&str> = content.lines().collect();
+
+        let start = suggestion.start_line - 1;
+        let end = suggestion.end_line;
+        if end > lines.len() || lines[start..end].join("\n") != suggestion.original.trim_end_matches('\n') {
+            return Err(anyhow!(
+                "Suggestion {} is stale: lines {}-{} of {} no longer match",
+                suggestion_id,
+                suggestion.start_line,
+                suggestion.end_line,
+                file
+            ));
+        }
+
+        let replacement: Vec<&str> = suggestion.replacement.lines().collect();
+        let mut updated: Vec<&str> = Vec::with_capacity(lines.len() - (end - start) + replacement.len());
+        updated.extend_from_slice(&lines[..start]);
+        updated.extend_from_slice(&replacement);
+        updated.extend_from_slice(&lines[end..]);
+        let new_content = join_lines(&updated, content.ends_with('\n'));
+
+        let backup_path = self.write_backup(path, &content)?;
+        std::fs::write(path, &new_content).with_context(|| format!("Failed to write {}", file))?;
+
+        let diff = unified_diff(file, &lines, &updated, start, end - start, replacement.len());
+        self.staged.remove(suggestion_id);
+        self.applied.entry(path.to_path_buf()).or_default().push(AppliedEdit {
+            suggestion: suggestion.clone(),
+            backup_path: backup_path.clone(),
+            applied_content: new_content,
+        });
+
+        Ok(AppliedSuggestion {
+            suggestion_id: suggestion.id,
+            file: file.to_string(),
+            backup_path: backup_path.to_string_lossy().to_string(),
+            diff,
+        })
+    }
+
+    /// Restore the file from the backup taken before the last applied suggestion.
+    ///
+    /// The suggestion is staged again so it can be re-applied.
+    pub fn undo_last(&mut self, file: &str) -> Result<String> {
+        let path = Path::new(file);
+        let edit = self
+            .applied
+            .get(path)
+            .and_then(|edits| edits.last())
+            .ok_or_else(|| anyhow!("No applied suggestions to undo for {}", file))?;
+
+        let current = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", file))?;
+        if current != edit.applied_content {
+            return Err(anyhow!("{} changed since the suggestion was applied; refusing to overwrite it", file));
+        }
+
+        let original = std::fs::read_to_string(&edit.backup_path)
+            .with_context(|| format!("Failed to read backup {:?}", edit.backup_path))?;
+        std::fs::write(path, &original).with_context(|| format!("Failed to write {}", file))?;
+
+        let edit = self.applied.get_mut(path).and_then(|edits| edits.pop()).expect("edit checked above");
+        let _ = std::fs::remove_file(&edit.backup_path);
+
+        let start = edit.suggestion.start_line - 1;
+        let current_lines: Vec<&str> = current.lines().collect();
+        let original_lines: Vec<&str> = original.lines().collect();
+        let diff = unified_diff(
+            file,
+            &current_lines,
+            &original_lines,
+            start,
+            edit.suggestion.replacement.lines().count(),
+            edit.suggestion.end_line - start,
+        );
+
+        self.staged.insert(edit.suggestion.id.clone(), edit.suggestion);
+        Ok(diff)
+    }
+
+    fn write_backup(&self, path: &Path, content: &str) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.backup_dir).context("Failed to create suggestion backup directory")?;
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let backup_path = self
+            .backup_dir
+            .join(format!("{}.{}.bak", name, Utc::now().format("%Y%m%d%H%M%S%3f")));
+        std::fs::write(&backup_path, content).with_context(|| format!("Failed to write backup {:?}", backup_path))?;
+        Ok(backup_path)
+    }
+}
+
+impl Default for SuggestionManager {
+    fn default() -> Self {
+        Self::new(
+            dirs::data_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("nexus-terminal")
+                .join("suggestion-backups"),
+        )
+    }
+}
+
+fn join_lines(lines: &[&str], trailing_newline: bool) -> String {
+    let mut joined = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Single-hunk unified diff for a region replaced at the same starting line
+fn unified_diff(file: &str, before: &[&str], after: &[&str], start: usize, old_len: usize, new_len: usize) -> String {
+    let context_start = start.saturating_sub(DIFF_CONTEXT_LINES);
+    let context_end = (start + old_len + DIFF_CONTEXT_LINES).min(before.len());
+    let old_count = context_end - context_start;
+    let new_count = old_count - old_len + new_len;
+
+    let mut diff = format!(
+        "--- a/{}\n+++ b/{}\n@@ -{},{} +{},{} @@\n",
+        file,
+        file,
+        context_start + 1,
+        old_count,
+        context_start + 1,
+        new_count
+    );
+    for line in &before[context_start..start] {
+        diff.push_str(&format!(" {}\n", line));
+    }
+    for line in &before[start..start + old_len] {
+        diff.push_str(&format!("-{}\n", line));
+    }
+    for line in &after[start..start + new_len] {
+        diff.push_str(&format!("+{}\n", line));
+    }
+    for line in &before[start + old_len..context_end] {
+        diff.push_str(&format!(" {}\n", line));
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n";
+
+    fn setup() -> (tempfile::TempDir, String, SuggestionManager, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs").to_string_lossy().to_string();
+        std::fs::write(&file, SOURCE).unwrap();
+
+        let response = r#"Here you go:
+[{"start_line": 2, "end_line": 3, "original": "    let x = 1;\n    println!(\"{}\", x);",
+  "replacement": "    println!(\"1\");", "description": "Inline the constant"}]"#;
+        let suggestions = parse_suggestions(response, Some(&file)).unwrap();
+        let mut manager = SuggestionManager::new(dir.path().join("backups"));
+        manager.stage(&suggestions);
+        let id = suggestions[0].id.clone();
+        (dir, file, manager, id)
+    }
+
+    #[test]
+    fn test_apply_suggestion_edits_file_and_returns_diff() {
+        let (_dir, file, mut manager, id) = setup();
+
+        let applied = manager.apply(&file, &id).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn main() {\n    println!(\"1\");\n}\n");
+        assert!(applied.diff.contains("@@ -1,4 +1,3 @@"));
+        assert!(applied.diff.contains("-    let x = 1;\n"));
+        assert!(applied.diff.contains("+    println!(\"1\");\n"));
+        assert_eq!(std::fs::read_to_string(&applied.backup_path).unwrap(), SOURCE);
+        assert!(manager.staged_for(&file).is_empty());
+    }
+
+    #[test]
+    fn test_stale_suggestion_is_rejected() {
+        let (_dir, file, mut manager, id) = setup();
+        std::fs::write(&file, "fn main() {\n    let x = 2;\n    println!(\"{}\", x);\n}\n").unwrap();
+
+        let err = manager.apply(&file, &id).unwrap_err().to_string();
+        assert!(err.contains("stale"));
+        assert!(std::fs::read_to_string(&file).unwrap().contains("let x = 2;"));
+    }
+
+    #[test]
+    fn test_undo_restores_original() {
+        let (_dir, file, mut manager, id) = setup();
+        manager.apply(&file, &id).unwrap();
+
+        let diff = manager.undo_last(&file).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), SOURCE);
+        assert!(diff.contains("+    let x = 1;\n"));
+        assert_eq!(manager.staged_for(&file).len(), 1);
+        assert!(manager.undo_last(&file).is_err());
+    }
+
+    #[test]
+    fn test_command_suggestions_from_fenced_json() {
+        let response = "Sure! Here are some ideas:\n```json\n[\n  {\"command\": \"git status\", \"description\": \"Show [changes]\", \"confidence\": 0.9, \"category\": \"git\"}\n]\n```\nLet me know [if] that helps.";
+
+        let suggestions = parse_command_suggestions(response);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].command, "git status");
+        assert_eq!(suggestions[0].description, "Show [changes]");
+        assert_eq!(suggestions[0].learned_from.as_deref(), Some("ai"));
+    }
+
+    #[test]
+    fn test_command_suggestions_from_prose_wrapped_json() {
+        let response = r#"Based on [your context], try: [{"command": "cargo test", "description": "Run tests", "confidence": 0.8, "category": "dev", "context": ["rust-project"]}] Good luck!"#;
+
+        let suggestions = parse_command_suggestions(response);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].command, "cargo test");
+        assert_eq!(suggestions[0].context, vec!["rust-project".to_string()]);
+    }
+
+    #[test]
+    fn test_malformed_suggestion_entries_are_dropped() {
+        let response = r#"[
+            {"command": "ls -la", "description": "List files", "confidence": 0.7, "category": "navigation"},
+            {"command": "rm -rf build", "description": "No confidence", "category": "file-ops"},
+            {"command": "", "description": "Empty", "confidence": 0.5, "category": "system"},
+            {"command": "pwd", "description": "Too sure", "confidence": 4, "category": "navigation"},
+            "just a string",
+            {"command": "cd ..", "description": "Go up", "confidence": "high", "category": "navigation"},
+            {"command": "du -sh .", "description": "Disk usage", "confidence": 0.4, "category": "system"}
+        ]"#;
+
+        let commands: Vec<String> = parse_command_suggestions(response).into_iter().map(|s| s.command).collect();
+        assert_eq!(commands, vec!["ls -la", "du -sh ."]);
+        assert!(parse_command_suggestions("I can't help with that.").is_empty());
+    }
+
+    #[test]
+    fn test_fallback_uses_history_then_project_commands() {
+        let history: Vec<String> = ["cargo build", "git pull", "cargo test --lib", "ls", "cargo test --lib"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        let suggestions = fallback_suggestions("cargo", &history, "rust");
+        let commands: Vec<&str> = suggestions.iter().map(|s| s.command.as_str()).collect();
+        assert_eq!(commands, vec!["cargo test --lib", "cargo build", "cargo test", "cargo run", "cargo clippy"]);
+        assert!(suggestions[0].confidence > suggestions[1].confidence);
+        assert_eq!(suggestions[0].learned_from.as_deref(), Some("history"));
+        assert_eq!(suggestions[2].learned_from.as_deref(), Some("context"));
+
+        assert_eq!(detect_project_type(&["go.mod".to_string()]), "go");
+        assert!(fallback_suggestions("docker", &history, "other").is_empty());
+    }
+}
@@ -1,10 +1,20 @@
 use anyhow::{Result, anyhow};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use chrono::{DateTime, Utc};
 use tokio::sync::{RwLock, broadcast};
 use std::sync::Arc;
 
+/// Out-of-order ops held per participant while waiting for the gap to fill
+const MAX_PENDING_OPS: usize = 256;
+
+/// Committed ops kept per shared terminal for replay; older ones are dropped
+const MAX_TERMINAL_OPS: usize = 10_000;
+
+/// Participants without a heartbeat for this long are removed from their session
+const DEFAULT_HEARTBEAT_TIMEOUT_SECONDS: i64 = 60;
+
 // Missing types expected by main.rs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JoinResult {
@@ -14,7 +24,7 @@ pub struct JoinResult {
     pub message: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TerminalPermissions {
     pub can_read: bool,
     pub can_write: bool,
@@ -49,6 +59,9 @@ pub struct CollaborativeSession {
     pub created_at: DateTime<Utc>,
     pub last_activity: DateTime<Utc>,
     pub settings: SessionSettings,
+    /// Issued at creation; remote participants present it to the collaboration server
+    #[serde(default)]
+    pub access_token: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,9 +74,20 @@ pub struct Participant {
     pub joined_at: DateTime<Utc>,
     pub last_seen: DateTime<Utc>,
     pub cursor_position: Option<CursorPosition>,
+    /// Terminal the participant is currently looking at
+    #[serde(default)]
+    pub active_terminal_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Heartbeat from a participant; unset fields keep their previous value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PresenceUpdate {
+    pub status: Option<ParticipantStatus>,
+    pub active_terminal_id: Option<String>,
+    pub cursor_position: Option<CursorPosition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ParticipantRole {
     Owner,
     Admin,
@@ -84,7 +108,7 @@ pub struct UserPermissions {
     pub restricted_paths: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ParticipantStatus {
     Online,
     Away,
@@ -221,6 +245,78 @@ pub enum CollaborationEventType {
     CommandExecuted,
     PermissionChanged,
     SettingUpdated,
+    TerminalOp,
+}
+
+/// Input to a shared terminal, placed in the total order every participant replays
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerminalOp {
+    pub terminal_id: String,
+    /// Position in the terminal's log, starting at 1
+    pub seq: u64,
+    pub user_id: String,
+    /// The sender's own counter, starting at 1, so their keystrokes keep their order
+    pub client_seq: u64,
+    pub data: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Ordered-append input log for a terminal shared into a session
+#[derive(Debug, Clone)]
+struct SharedTerminal {
+    session_id: String,
+    /// Applies to every participant without an override
+    permissions: TerminalPermissions,
+    participant_permissions: HashMap<String, TerminalPermissions>,
+    /// The most recent `MAX_TERMINAL_OPS` ops of the log
+    ops: VecDeque<TerminalOp>,
+    /// Ops committed so far, including any dropped from `ops`
+    committed_ops: u64,
+    next_client_seq: HashMap<String, u64>,
+    pending: HashMap<String, BTreeMap<u64, String>>,
+}
+
+impl SharedTerminal {
+    /// Append `data` if it is the sender's next op, then any buffered ops it unblocks
+    fn submit(&mut self, terminal_id: &str, user_id: &str, client_seq: u64, data: String) -> Result<Vec<TerminalOp>> {
+        let expected = *self.next_client_seq.get(user_id).unwrap_or(&1);
+        if client_seq < expected {
+            // Retransmission of an op that is already in the log
+            return Ok(Vec::new());
+        }
+
+        let pending = self.pending.entry(user_id.to_string()).or_default();
+        if client_seq > expected {
+            if pending.len() >= MAX_PENDING_OPS {
+                return Err(anyhow!("Too many out-of-order ops from {}; expected op {}", user_id, expected));
+            }
+            pending.insert(client_seq, data);
+            return Ok(Vec::new());
+        }
+
+        pending.insert(client_seq, data);
+        let mut next = expected;
+        let mut committed = Vec::new();
+        while let Some(data) = pending.remove(&next) {
+            self.committed_ops += 1;
+            let op = TerminalOp {
+                terminal_id: terminal_id.to_string(),
+                seq: self.committed_ops,
+                user_id: user_id.to_string(),
+                client_seq: next,
+                data,
+                timestamp: Utc::now(),
+            };
+            if self.ops.len() >= MAX_TERMINAL_OPS {
+                self.ops.pop_front();
+            }
+            self.ops.push_back(op.clone());
+            committed.push(op);
+            next += 1;
+        }
+        self.next_client_seq.insert(user_id.to_string(), next);
+        Ok(committed)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -388,6 +484,9 @@ pub struct CollaborationManager {
     workspaces: Arc<RwLock<HashMap<String, TeamWorkspace>>>,
     event_sender: broadcast::Sender<CollaborationEvent>,
     active_connections: Arc<RwLock<HashMap<String, ConnectionInfo>>>,
+    /// Input logs by terminal id
+    shared_terminals: Arc<RwLock<HashMap<String, SharedTerminal>>>,
+    heartbeat_timeout: chrono::Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -410,9 +509,16 @@ impl CollaborationManager {
             workspaces: Arc::new(RwLock::new(HashMap::new())),
             event_sender,
             active_connections: Arc::new(RwLock::new(HashMap::new())),
+            shared_terminals: Arc::new(RwLock::new(HashMap::new())),
+            heartbeat_timeout: chrono::Duration::seconds(DEFAULT_HEARTBEAT_TIMEOUT_SECONDS),
         }
     }
 
+    pub fn with_heartbeat_timeout(mut self, timeout: chrono::Duration) -> Self {
+        self.heartbeat_timeout = timeout;
+        self
+    }
+
 
 
     pub async fn leave_session(&self, session_id: &str, user_id: &str) -> Result<()> {
@@ -634,6 +740,65 @@ impl CollaborationManager {
         }
     }
 
+    /// Record a heartbeat and whatever presence details changed
+    pub async fn update_presence(&self, session_id: &str, user_id: &str, presence: PresenceUpdate) -> Result<Participant> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).ok_or_else(|| anyhow!("Session not found"))?;
+        let participant = session.participants.iter_mut().find(|p| p.user_id == user_id)
+            .ok_or_else(|| anyhow!("User not found in session"))?;
+
+        participant.last_seen = Utc::now();
+        if let Some(status) = presence.status {
+            participant.status = status;
+        }
+        if presence.active_terminal_id.is_some() {
+            participant.active_terminal_id = presence.active_terminal_id;
+        }
+        if presence.cursor_position.is_some() {
+            participant.cursor_position = presence.cursor_position;
+        }
+        Ok(participant.clone())
+    }
+
+    pub async fn get_participants(&self, session_id: &str) -> Result<Vec<Participant>> {
+        let sessions = self.sessions.read().await;
+        sessions.get(session_id)
+            .map(|session| session.participants.clone())
+            .ok_or_else(|| anyhow!("Session not found"))
+    }
+
+    /// Remove participants whose last heartbeat is older than the timeout as of `now`.
+    ///
+    /// Session owners are never expired. Returns the number of participants removed.
+    pub async fn prune_stale_participants(&self, now: DateTime<Utc>) -> Result<usize> {
+        let cutoff = now - self.heartbeat_timeout;
+        let mut expired = Vec::new();
+        {
+            let mut sessions = self.sessions.write().await;
+            for session in sessions.values_mut() {
+                session.participants.retain(|p| {
+                    let stale = p.role != ParticipantRole::Owner && p.last_seen < cutoff;
+                    if stale {
+                        expired.push((session.id.clone(), p.user_id.clone(), p.last_seen));
+                    }
+                    !stale
+                });
+            }
+        }
+
+        for (session_id, user_id, last_seen) in &expired {
+            self.broadcast_event(CollaborationEvent {
+                id: uuid::Uuid::new_v4().to_string(),
+                session_id: session_id.clone(),
+                user_id: user_id.clone(),
+                event_type: CollaborationEventType::UserLeft,
+                timestamp: now,
+                data: serde_json::json!({ "reason": "heartbeat_timeout", "last_seen": last_seen }),
+            }).await?;
+        }
+        Ok(expired.len())
+    }
+
     pub async fn get_session(&self, session_id: &str) -> Result<CollaborativeSession> {
         let sessions = self.sessions.read().await;
         sessions.get(session_id).cloned().ok_or_else(|| anyhow!("Session not found"))
@@ -713,7 +878,8 @@ impl CollaborationManager {
     }
 
     async fn broadcast_event(&self, event: CollaborationEvent) -> Result<()> {
-        self.event_sender.send(event).map_err(|e| anyhow!("Failed to broadcast event: {}", e))?;
+        // Sending only fails when nobody is subscribed, which is not an error for the session
+        let _ = self.event_sender.send(event);
         Ok(())
     }
 
@@ -787,6 +953,7 @@ impl CollaborationManager {
                 joined_at: Utc::now(),
                 last_seen: Utc::now(),
                 cursor_position: None,
+                active_terminal_id: None,
             }],
             permissions,
             status: SessionStatus::Active,
@@ -811,6 +978,7 @@ impl CollaborationManager {
                 record_session: false,
                 notifications_enabled: true,
             },
+            access_token: format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple()),
         };
 
         let mut sessions = self.sessions.write().await;
@@ -832,13 +1000,26 @@ impl CollaborationManager {
         let mut sessions = self.sessions.write().await;
         
         if let Some(session) = sessions.get_mut(session_id) {
-            // Check if user is already in session
-            if session.participants.iter().any(|p| p.user_id == user_id) {
+            // Rejoining (e.g. after a reconnect) refreshes the existing participant
+            if let Some(participant) = session.participants.iter_mut().find(|p| p.user_id == user_id) {
+                participant.status = ParticipantStatus::Online;
+                participant.last_seen = Utc::now();
+                session.last_activity = Utc::now();
+
+                self.broadcast_event(CollaborationEvent {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    session_id: session_id.to_string(),
+                    user_id: user_id.to_string(),
+                    event_type: CollaborationEventType::UserJoined,
+                    timestamp: Utc::now(),
+                    data: serde_json::json!({ "rejoined": true }),
+                }).await?;
+
                 return Ok(JoinResult {
-                    success: false,
+                    success: true,
                     session_id: session_id.to_string(),
                     participant_id: user_id.to_string(),
-                    message: "User already in session".to_string(),
+                    message: "Rejoined session".to_string(),
                 });
             }
 
@@ -871,6 +1052,7 @@ impl CollaborationManager {
                 joined_at: Utc::now(),
                 last_seen: Utc::now(),
                 cursor_position: None,
+                active_terminal_id: None,
             };
 
             session.participants.push(participant);
@@ -908,6 +1090,23 @@ impl CollaborationManager {
             session.terminal_id = Some(terminal_id.to_string());
             session.last_activity = Utc::now();
 
+            let mut shared_terminals = self.shared_terminals.write().await;
+            match shared_terminals.get_mut(terminal_id) {
+                Some(shared) if shared.session_id == session_id => shared.permissions = permissions.clone(),
+                _ => {
+                    shared_terminals.insert(terminal_id.to_string(), SharedTerminal {
+                        session_id: session_id.to_string(),
+                        permissions: permissions.clone(),
+                        participant_permissions: HashMap::new(),
+                        ops: VecDeque::new(),
+                        committed_ops: 0,
+                        next_client_seq: HashMap::new(),
+                        pending: HashMap::new(),
+                    });
+                }
+            }
+            drop(shared_terminals);
+
             // Add terminal-specific permissions to shared state
             let terminal_info = serde_json::json!({
                 "terminal_id": terminal_id,
@@ -930,6 +1129,130 @@ impl CollaborationManager {
         }
     }
 
+    /// Override the shared terminal permissions for one participant
+    pub async fn set_terminal_permissions(&self, terminal_id: &str, user_id: &str, permissions: TerminalPermissions) -> Result<()> {
+        let mut shared_terminals = self.shared_terminals.write().await;
+        let shared = shared_terminals.get_mut(terminal_id)
+            .ok_or_else(|| anyhow!("Terminal is not shared: {}", terminal_id))?;
+        shared.participant_permissions.insert(user_id.to_string(), permissions);
+        Ok(())
+    }
+
+    /// Order a participant's input into the shared terminal's log.
+    ///
+    /// Returns the ops committed by this call, in log order, which may be empty while an
+    /// earlier op from the same participant is still missing.
+    pub async fn submit_terminal_op(
+        &self,
+        terminal_id: &str,
+        user_id: &str,
+        client_seq: u64,
+        data: String,
+    ) -> Result<Vec<TerminalOp>> {
+        let sessions = self.sessions.read().await;
+        let mut shared_terminals = self.shared_terminals.write().await;
+        let shared = shared_terminals.get_mut(terminal_id)
+            .ok_or_else(|| anyhow!("Terminal is not shared: {}", terminal_id))?;
+        let permissions = Self::terminal_permissions(&sessions, shared, user_id)?;
+        if !permissions.can_write {
+            return Err(anyhow!("User {} has read-only access to terminal {}", user_id, terminal_id));
+        }
+
+        let committed = shared.submit(terminal_id, user_id, client_seq, data)?;
+        let session_id = shared.session_id.clone();
+        drop(shared_terminals);
+        drop(sessions);
+
+        for op in &committed {
+            self.broadcast_event(CollaborationEvent {
+                id: uuid::Uuid::new_v4().to_string(),
+                session_id: session_id.clone(),
+                user_id: op.user_id.clone(),
+                event_type: CollaborationEventType::TerminalOp,
+                timestamp: op.timestamp,
+                data: serde_json::to_value(op)?,
+            }).await?;
+        }
+        Ok(committed)
+    }
+
+    /// The session a terminal is shared into, if it is shared
+    pub async fn shared_terminal_session(&self, terminal_id: &str) -> Option<String> {
+        let shared_terminals = self.shared_terminals.read().await;
+        shared_terminals.get(terminal_id).map(|shared| shared.session_id.clone())
+    }
+
+    /// Ops after `after_seq`, so a late joiner can replay the terminal's input. Only the
+    /// latest `MAX_TERMINAL_OPS` are kept; a first op past `after_seq + 1` means older input was dropped.
+    pub async fn terminal_ops_since(&self, terminal_id: &str, user_id: &str, after_seq: u64) -> Result<Vec<TerminalOp>> {
+        let sessions = self.sessions.read().await;
+        let shared_terminals = self.shared_terminals.read().await;
+        let shared = shared_terminals.get(terminal_id)
+            .ok_or_else(|| anyhow!("Terminal is not shared: {}", terminal_id))?;
+        if !Self::terminal_permissions(&sessions, shared, user_id)?.can_read {
+            return Err(anyhow!("User {} cannot read terminal {}", user_id, terminal_id));
+        }
+        Ok(shared.ops.iter().filter(|op| op.seq > after_seq).cloned().collect())
+    }
+
+    /// Effective permissions of a session participant; the owner always has full control
+    fn terminal_permissions(
+        sessions: &HashMap<String, CollaborativeSession>,
+        shared: &SharedTerminal,
+        user_id: &str,
+    ) -> Result<TerminalPermissions> {
+        let session = sessions.get(&shared.session_id)
+            .ok_or_else(|| anyhow!("Session not found: {}", shared.session_id))?;
+        let participant = session.participants.iter().find(|p| p.user_id == user_id)
+            .ok_or_else(|| anyhow!("User {} is not in session {}", user_id, shared.session_id))?;
+
+        if participant.role == ParticipantRole::Owner {
+            return Ok(TerminalPermissions {
+                can_read: true,
+                can_write: true,
+                can_execute: true,
+                can_resize: true,
+                can_control: true,
+            });
+        }
+
+        let mut permissions = shared.participant_permissions.get(user_id)
+            .unwrap_or(&shared.permissions)
+            .clone();
+        if participant.role == ParticipantRole::Viewer {
+            permissions.can_write = false;
+        }
+        Ok(permissions)
+    }
+
+    /// Check a remote participant's token against the one issued for `session_id`
+    pub async fn verify_session_token(&self, session_id: &str, token: &str) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)
+            .ok_or_else(|| anyhow!("Session not found: {}", session_id))?;
+
+        // Compare every byte so the time taken doesn't reveal how much of the token matched
+        let expected = session.access_token.as_bytes();
+        let matches = expected.len() == token.len()
+            && expected.iter().zip(token.as_bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0;
+        if expected.is_empty() || !matches {
+            return Err(anyhow!("Invalid access token for session {}", session_id));
+        }
+        Ok(())
+    }
+
+    /// Check a remote participant's answer to `nonce` against the token issued for
+    /// `session_id`; see `token_proof`
+    pub async fn verify_session_proof(&self, session_id: &str, nonce: &str, proof: &str) -> Result<()> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)
+            .ok_or_else(|| anyhow!("Session not found: {}", session_id))?;
+        if session.access_token.is_empty() || !verify_token_proof(&session.access_token, nonce, proof) {
+            return Err(anyhow!("Invalid access token for session {}", session_id));
+        }
+        Ok(())
+    }
+
     pub async fn get_sessions(&self) -> Result<Vec<CollaborationSession>> {
         let sessions = self.sessions.read().await;
         Ok(sessions.values().cloned().collect())
@@ -977,6 +1300,22 @@ impl CollaborationManager {
     }
 }
 
+/// Proof of holding `token` without sending it: the base64 HMAC-SHA256 of a server's
+/// one-time `nonce`, keyed with the token
+pub fn token_proof(token: &str, nonce: &str) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, token.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(ring::hmac::sign(&key, nonce.as_bytes()))
+}
+
+/// Constant-time check of a `token_proof`
+pub fn verify_token_proof(token: &str, nonce: &str, proof: &str) -> bool {
+    let Ok(tag) = base64::engine::general_purpose::STANDARD.decode(proof) else {
+        return false;
+    };
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, token.as_bytes());
+    ring::hmac::verify(&key, nonce.as_bytes(), &tag).is_ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1006,6 +1345,16 @@ mod tests {
         assert_eq!(session.name, "Test Session");
         assert_eq!(session.participants.len(), 1);
         assert_eq!(session.participants[0].role, ParticipantRole::Owner);
+
+        assert_eq!(session.access_token.len(), 64);
+        manager.verify_session_token(&session_id, &session.access_token).await.unwrap();
+        assert!(manager.verify_session_token(&session_id, "guess").await.is_err());
+        assert!(manager.verify_session_token("missing", &session.access_token).await.is_err());
+
+        let proof = token_proof(&session.access_token, "nonce-1");
+        manager.verify_session_proof(&session_id, "nonce-1", &proof).await.unwrap();
+        assert!(manager.verify_session_proof(&session_id, "nonce-2", &proof).await.is_err());
+        assert!(manager.verify_session_proof(&session_id, "nonce-1", &token_proof("guess", "nonce-1")).await.is_err());
     }
 
     #[tokio::test]
@@ -1030,4 +1379,170 @@ mod tests {
         assert_eq!(session.participants.len(), 2);
         assert_eq!(session.participants[1].user_id, "user2");
     }
+
+    async fn shared_terminal_session(manager: &CollaborationManager, can_write: bool) -> String {
+        let permissions = SessionPermissions {
+            is_public: false,
+            allow_anonymous: false,
+            max_participants: 10,
+            require_approval: false,
+            allow_recording: false,
+            password_protected: false,
+        };
+        let session = manager.create_session("Pairing", permissions).await.unwrap();
+        manager.join_session(&session.id, "alice").await.unwrap();
+        manager.join_session(&session.id, "bob").await.unwrap();
+
+        let terminal_permissions = TerminalPermissions {
+            can_read: true,
+            can_write,
+            can_execute: can_write,
+            can_resize: false,
+            can_control: false,
+        };
+        manager.share_terminal("term-1", &session.id, terminal_permissions).await.unwrap();
+        session.id
+    }
+
+    fn replay(ops: &[TerminalOp]) -> String {
+        ops.iter().map(|op| op.data.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_input_is_applied_in_one_order() {
+        let manager = CollaborationManager::new();
+        shared_terminal_session(&manager, true).await;
+        let mut events = manager.subscribe_to_events();
+
+        // bob's second keystroke overtakes his first and is held until the gap fills
+        manager.submit_terminal_op("term-1", "alice", 1, "l".to_string()).await.unwrap();
+        assert!(manager.submit_terminal_op("term-1", "bob", 2, "c".to_string()).await.unwrap().is_empty());
+        manager.submit_terminal_op("term-1", "alice", 2, "s".to_string()).await.unwrap();
+        let flushed = manager.submit_terminal_op("term-1", "bob", 1, "d".to_string()).await.unwrap();
+        assert_eq!(flushed.iter().map(|op| op.seq).collect::<Vec<_>>(), vec![3, 4]);
+
+        // A retransmitted op is not applied twice
+        assert!(manager.submit_terminal_op("term-1", "alice", 1, "l".to_string()).await.unwrap().is_empty());
+
+        let ops = manager.terminal_ops_since("term-1", "bob", 0).await.unwrap();
+        assert_eq!(replay(&ops), "lsdc");
+        assert_eq!(replay(&manager.terminal_ops_since("term-1", "alice", 2).await.unwrap()), "dc");
+
+        let mut emitted = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let CollaborationEventType::TerminalOp = event.event_type {
+                emitted.push(serde_json::from_value::<TerminalOp>(event.data).unwrap());
+            }
+        }
+        assert_eq!(emitted, ops);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_participants_keep_their_own_order() {
+        let manager = CollaborationManager::new();
+        shared_terminal_session(&manager, true).await;
+
+        let typist = |user: &'static str| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                for client_seq in 1..=50u64 {
+                    manager
+                        .submit_terminal_op("term-1", user, client_seq, format!("{}{} ", user, client_seq))
+                        .await
+                        .unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        let (alice, bob) = (typist("alice"), typist("bob"));
+        alice.await.unwrap();
+        bob.await.unwrap();
+
+        let ops = manager.terminal_ops_since("term-1", "alice", 0).await.unwrap();
+        assert_eq!(ops.iter().map(|op| op.seq).collect::<Vec<_>>(), (1..=100).collect::<Vec<_>>());
+        for user in ["alice", "bob"] {
+            let sent: Vec<u64> = ops.iter().filter(|op| op.user_id == user).map(|op| op.client_seq).collect();
+            assert_eq!(sent, (1..=50).collect::<Vec<_>>());
+        }
+
+        // A late joiner replaying the log sees exactly what everyone else saw
+        let late = manager.terminal_ops_since("term-1", "bob", 0).await.unwrap();
+        assert_eq!(replay(&late), replay(&ops));
+    }
+
+    #[tokio::test]
+    async fn test_op_log_keeps_only_recent_ops() {
+        let manager = CollaborationManager::new();
+        shared_terminal_session(&manager, true).await;
+
+        let total = MAX_TERMINAL_OPS as u64 + 5;
+        for client_seq in 1..=total {
+            manager.submit_terminal_op("term-1", "alice", client_seq, "x".to_string()).await.unwrap();
+        }
+
+        let ops = manager.terminal_ops_since("term-1", "bob", 0).await.unwrap();
+        assert_eq!(ops.len(), MAX_TERMINAL_OPS);
+        assert_eq!((ops[0].seq, ops.last().unwrap().seq), (6, total));
+        let recent = manager.terminal_ops_since("term-1", "bob", total - 2).await.unwrap();
+        assert_eq!(recent.iter().map(|op| op.seq).collect::<Vec<_>>(), vec![total - 1, total]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_participant_cannot_submit_ops() {
+        let manager = CollaborationManager::new();
+        shared_terminal_session(&manager, true).await;
+        let read_only = TerminalPermissions {
+            can_read: true,
+            can_write: false,
+            can_execute: false,
+            can_resize: false,
+            can_control: false,
+        };
+        manager.set_terminal_permissions("term-1", "bob", read_only).await.unwrap();
+
+        let err = manager.submit_terminal_op("term-1", "bob", 1, "rm -rf /".to_string()).await.unwrap_err();
+        assert!(err.to_string().contains("read-only"));
+        manager.submit_terminal_op("term-1", "alice", 1, "ls".to_string()).await.unwrap();
+        assert_eq!(replay(&manager.terminal_ops_since("term-1", "bob", 0).await.unwrap()), "ls");
+        assert!(manager.submit_terminal_op("term-1", "mallory", 1, "x".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_participant_missing_heartbeats_is_pruned() {
+        let manager = CollaborationManager::new().with_heartbeat_timeout(chrono::Duration::seconds(30));
+        let session_id = shared_terminal_session(&manager, true).await;
+        let mut events = manager.subscribe_to_events();
+
+        let presence = PresenceUpdate {
+            active_terminal_id: Some("term-1".to_string()),
+            ..Default::default()
+        };
+        let alice = manager.update_presence(&session_id, "alice", presence).await.unwrap();
+        assert_eq!(alice.active_terminal_id.as_deref(), Some("term-1"));
+
+        assert_eq!(manager.prune_stale_participants(Utc::now()).await.unwrap(), 0);
+        let later = Utc::now() + chrono::Duration::seconds(31);
+        assert_eq!(manager.prune_stale_participants(later).await.unwrap(), 2);
+
+        let remaining = manager.get_participants(&session_id).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].role, ParticipantRole::Owner);
+        let left = events.try_recv().unwrap();
+        assert!(matches!(left.event_type, CollaborationEventType::UserLeft));
+    }
+
+    #[tokio::test]
+    async fn test_rejoin_with_same_id_does_not_duplicate() {
+        let manager = CollaborationManager::new();
+        let session_id = shared_terminal_session(&manager, true).await;
+        let away = PresenceUpdate { status: Some(ParticipantStatus::Away), ..Default::default() };
+        manager.update_presence(&session_id, "bob", away).await.unwrap();
+
+        let rejoined = manager.join_session(&session_id, "bob").await.unwrap();
+        assert!(rejoined.success);
+        let participants = manager.get_participants(&session_id).await.unwrap();
+        let bobs: Vec<&Participant> = participants.iter().filter(|p| p.user_id == "bob").collect();
+        assert_eq!(bobs.len(), 1);
+        assert_eq!(bobs[0].status, ParticipantStatus::Online);
+    }
 }
@@ -0,0 +1,830 @@
+use crate::collaboration::{
+    token_proof, verify_token_proof, ChatMessage, CollaborationEvent, CollaborationEventType, CollaborationManager, MessageType,
+};
+use anyhow::{anyhow, Context, Result};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Events kept per session for clients that reconnect after missing some
+const REPLAY_BUFFER_EVENTS: usize = 1024;
+
+/// A connection that hasn't sent its hello within this long is closed
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the relay drops the buffers of sessions that have ended
+const RELAY_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Delay before the first reconnect attempt, doubled after each failure up to the max
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(250);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+type ClientSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Messages a remote participant sends to the collaboration server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Must be the first message on a connection, answering the server's challenge.
+    /// `proof` is the `token_proof` of the session token and `rejoin_proof` that of the
+    /// rejoin key from an earlier welcome. `last_seq` is the newest event the client has
+    /// seen; events after it are replayed, and `None` means only new events.
+    Hello {
+        session_id: String,
+        user_id: String,
+        proof: String,
+        #[serde(default)]
+        rejoin_proof: Option<String>,
+        #[serde(default)]
+        last_seq: Option<u64>,
+    },
+    Chat {
+        content: String,
+    },
+    TerminalOp {
+        terminal_id: String,
+        client_seq: u64,
+        data: String,
+    },
+    Leave,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Sent first on every connection; the hello proves the client holds the session
+    /// token by answering this nonce, so the token itself never crosses the wire
+    Challenge {
+        nonce: String,
+    },
+    /// Sent once the hello is accepted, before any replayed events. `replay_complete`
+    /// is false when the events after the client's `last_seq` were no longer buffered.
+    /// `rejoin_key` is what the client must prove to reconnect under the same user id.
+    Welcome {
+        session_id: String,
+        head_seq: u64,
+        replay_complete: bool,
+        rejoin_key: String,
+    },
+    Event {
+        seq: u64,
+        event: CollaborationEvent,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A session event numbered in the order the server relayed it, starting at 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub event: CollaborationEvent,
+}
+
+struct SessionLog {
+    head_seq: u64,
+    events: VecDeque<SequencedEvent>,
+    live: broadcast::Sender<SequencedEvent>,
+}
+
+impl SessionLog {
+    fn new() -> Self {
+        let (live, _) = broadcast::channel(REPLAY_BUFFER_EVENTS);
+        Self { head_seq: 0, events: VecDeque::new(), live }
+    }
+}
+
+struct Attachment {
+    head_seq: u64,
+    replay_complete: bool,
+    replay: Vec<SequencedEvent>,
+    live: broadcast::Receiver<SequencedEvent>,
+}
+
+/// Per-session event numbering, replay buffers and fan-out to connections
+#[derive(Default)]
+struct Relay {
+    sessions: Mutex<HashMap<String, SessionLog>>,
+    /// Rejoin keys of the remote participants this server admitted, by session and user id
+    rejoin_keys: Mutex<HashMap<(String, String), String>>,
+}
+
+impl Relay {
+    /// Admit `user_id` to `session_id` and return its new rejoin key. An id that is
+    /// already in the session is only taken back with a proof of the key it was given,
+    /// so a token holder can't speak as the owner or another participant.
+    fn admit(&self, session_id: &str, user_id: &str, registered: bool, nonce: &str, rejoin_proof: Option<&str>) -> Result<String> {
+        let mut rejoin_keys = self.rejoin_keys.lock().unwrap();
+        let id = (session_id.to_string(), user_id.to_string());
+        if registered || rejoin_keys.contains_key(&id) {
+            let proven = match (rejoin_keys.get(&id), rejoin_proof) {
+                (Some(key), Some(proof)) => verify_token_proof(key, nonce, proof),
+                _ => false,
+            };
+            if !proven {
+                return Err(anyhow!("{} is already a participant in session {}", user_id, session_id));
+            }
+        }
+        let key = uuid::Uuid::new_v4().simple().to_string();
+        rejoin_keys.insert(id, key.clone());
+        Ok(key)
+    }
+
+    fn record(&self, event: CollaborationEvent) {
+        if matches!(event.event_type, CollaborationEventType::UserLeft) {
+            self.rejoin_keys.lock().unwrap().remove(&(event.session_id.clone(), event.user_id.clone()));
+        }
+        let mut sessions = self.sessions.lock().unwrap();
+        let log = sessions.entry(event.session_id.clone()).or_insert_with(SessionLog::new);
+        log.head_seq += 1;
+        let sequenced = SequencedEvent { seq: log.head_seq, event };
+        if log.events.len() == REPLAY_BUFFER_EVENTS {
+            log.events.pop_front();
+        }
+        log.events.push_back(sequenced.clone());
+        let _ = log.live.send(sequenced);
+    }
+
+    /// The events after `last_seq` together with a receiver for everything later, taken
+    /// under one lock so no event falls between the two
+    fn attach(&self, session_id: &str, last_seq: Option<u64>) -> Attachment {
+        let mut sessions = self.sessions.lock().unwrap();
+        let log = sessions.entry(session_id.to_string()).or_insert_with(SessionLog::new);
+        let (replay, replay_complete) = match last_seq {
+            None => (Vec::new(), true),
+            // The client numbered events from an earlier server, so send all we have
+            Some(seen) if seen > log.head_seq => (log.events.iter().cloned().collect(), false),
+            Some(seen) => (
+                log.events.iter().filter(|e| e.seq > seen).cloned().collect(),
+                log.events.front().is_none_or(|first| first.seq <= seen + 1),
+            ),
+        };
+        Attachment { head_seq: log.head_seq, replay_complete, replay, live: log.live.subscribe() }
+    }
+
+    fn session_ids(&self) -> Vec<String> {
+        self.sessions.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Drop everything kept for a session that has ended
+    fn remove_session(&self, session_id: &str) {
+        self.sessions.lock().unwrap().remove(session_id);
+        self.rejoin_keys.lock().unwrap().retain(|(session, _), _| session != session_id);
+    }
+}
+
+/// WebSocket server relaying a `CollaborationManager`'s session events to remote
+/// participants, who authenticate with the session's access token
+#[derive(Debug)]
+pub struct CollaborationServer {
+    local_addr: SocketAddr,
+    shutdown: CancellationToken,
+}
+
+impl CollaborationServer {
+    pub async fn start(manager: CollaborationManager, bind_addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(bind_addr)
+            .await
+            .with_context(|| format!("Failed to bind collaboration server to {}", bind_addr))?;
+        let local_addr = listener.local_addr()?;
+        let shutdown = CancellationToken::new();
+        let relay = Arc::new(Relay::default());
+
+        // Subscribe before accepting so every event after startup is numbered
+        let mut events = manager.subscribe_to_events();
+        let pump_relay = relay.clone();
+        let pump_manager = manager.clone();
+        let pump_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let mut prune = tokio::time::interval(RELAY_PRUNE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = pump_shutdown.cancelled() => break,
+                    event = events.recv() => match event {
+                        Ok(event) => pump_relay.record(event),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("Collaboration relay dropped {} session events", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = prune.tick() => {
+                        for session_id in pump_relay.session_ids() {
+                            if pump_manager.get_session(&session_id).await.is_err() {
+                                pump_relay.remove_session(&session_id);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let accept_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = accept_shutdown.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            let manager = manager.clone();
+                            let relay = relay.clone();
+                            let shutdown = accept_shutdown.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = serve_connection(stream, manager, relay, shutdown).await {
+                                    debug!("Collaboration connection from {} ended: {}", peer, e);
+                                }
+                            });
+                        }
+                        Err(e) => warn!("Failed to accept collaboration connection: {}", e),
+                    },
+                }
+            }
+        });
+
+        info!("Collaboration server listening on {}", local_addr);
+        Ok(Self { local_addr, shutdown })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.local_addr)
+    }
+
+    /// Stop accepting connections and close the open ones
+    pub fn stop(&self) {
+        self.shutdown.cancel();
+    }
+}
+
+impl Drop for CollaborationServer {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    manager: CollaborationManager,
+    relay: Arc<Relay>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let socket = tokio_tungstenite::accept_async(stream).await?;
+    let (mut sink, mut stream) = socket.split();
+
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    send_message(&mut sink, &ServerMessage::Challenge { nonce: nonce.clone() }).await?;
+    let hello = tokio::time::timeout(HELLO_TIMEOUT, next_message::<ClientMessage, _>(&mut stream))
+        .await
+        .map_err(|_| anyhow!("No hello within {:?}", HELLO_TIMEOUT))??;
+    let Some(ClientMessage::Hello { session_id, user_id, proof, rejoin_proof, last_seq }) = hello else {
+        return reject(&mut sink, "Expected a hello message").await;
+    };
+    if let Err(e) = manager.verify_session_proof(&session_id, &nonce, &proof).await {
+        return reject(&mut sink, &e.to_string()).await;
+    }
+    let registered = manager.get_participants(&session_id).await?.iter().any(|p| p.user_id == user_id);
+    let rejoin_key = match relay.admit(&session_id, &user_id, registered, &nonce, rejoin_proof.as_deref()) {
+        Ok(key) => key,
+        Err(e) => return reject(&mut sink, &e.to_string()).await,
+    };
+
+    let attachment = relay.attach(&session_id, last_seq);
+    let joined = manager.join_session(&session_id, &user_id).await?;
+    if !joined.success {
+        return reject(&mut sink, &joined.message).await;
+    }
+
+    send_message(&mut sink, &ServerMessage::Welcome {
+        session_id: session_id.clone(),
+        head_seq: attachment.head_seq,
+        replay_complete: attachment.replay_complete,
+        rejoin_key,
+    }).await?;
+    for sequenced in attachment.replay {
+        send_message(&mut sink, &ServerMessage::Event { seq: sequenced.seq, event: sequenced.event }).await?;
+    }
+
+    let mut live = attachment.live;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => {
+                let _ = sink.close().await;
+                return Ok(());
+            }
+            event = live.recv() => match event {
+                Ok(sequenced) => {
+                    send_message(&mut sink, &ServerMessage::Event { seq: sequenced.seq, event: sequenced.event }).await?;
+                }
+                // Dropping a client that fell behind makes it reconnect and replay from the buffer
+                Err(broadcast::error::RecvError::Lagged(_)) => return Err(anyhow!("Client fell behind the relay")),
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            message = next_message::<ClientMessage, _>(&mut stream) => {
+                let Some(message) = message? else {
+                    return Ok(());
+                };
+                let leaving = matches!(message, ClientMessage::Leave);
+                if let Err(e) = handle_client_message(&manager, &session_id, &user_id, message).await {
+                    send_message(&mut sink, &ServerMessage::Error { message: e.to_string() }).await?;
+                }
+                if leaving {
+                    let _ = sink.close().await;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+async fn handle_client_message(
+    manager: &CollaborationManager,
+    session_id: &str,
+    user_id: &str,
+    message: ClientMessage,
+) -> Result<()> {
+    match message {
+        ClientMessage::Hello { .. } => Err(anyhow!("Connection is already authenticated")),
+        ClientMessage::Chat { content } => {
+            manager.send_message(session_id, ChatMessage {
+                id: None,
+                author_id: user_id.to_string(),
+                content,
+                timestamp: None,
+                message_type: MessageType::Text,
+            }).await
+        }
+        ClientMessage::TerminalOp { terminal_id, client_seq, data } => {
+            // The token only admits the client to this session's terminals
+            if manager.shared_terminal_session(&terminal_id).await.as_deref() != Some(session_id) {
+                return Err(anyhow!("Terminal {} is not shared in session {}", terminal_id, session_id));
+            }
+            manager.submit_terminal_op(&terminal_id, user_id, client_seq, data).await.map(|_| ())
+        }
+        ClientMessage::Leave => manager.leave_session(session_id, user_id).await,
+    }
+}
+
+async fn reject<S>(sink: &mut S, message: &str) -> Result<()>
+where
+    S: Sink<Message, Error = WsError> + Unpin,
+{
+    send_message(sink, &ServerMessage::Error { message: message.to_string() }).await?;
+    let _ = sink.close().await;
+    Err(anyhow!("Rejected connection: {}", message))
+}
+
+/// The next JSON message, skipping control frames; `None` once the peer closes
+async fn next_message<T, S>(stream: &mut S) -> Result<Option<T>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = Result<Message, WsError>> + Unpin,
+{
+    while let Some(frame) = stream.next().await {
+        match frame? {
+            Message::Text(text) => {
+                return Ok(Some(serde_json::from_str(&text).context("Malformed collaboration message")?));
+            }
+            Message::Close(_) => return Ok(None),
+            _ => continue,
+        }
+    }
+    Ok(None)
+}
+
+async fn send_message<T, S>(sink: &mut S, message: &T) -> Result<()>
+where
+    T: Serialize,
+    S: Sink<Message, Error = WsError> + Unpin,
+{
+    sink.send(Message::Text(serde_json::to_string(message)?)).await?;
+    Ok(())
+}
+
+/// What a client proves on every connection
+#[derive(Debug, Clone)]
+struct Credentials {
+    session_id: String,
+    user_id: String,
+    token: String,
+    /// From the latest welcome; lets a reconnect reclaim the same user id
+    rejoin_key: Option<String>,
+}
+
+enum Handshake {
+    Welcome { socket: Box<ClientSocket>, head_seq: u64, replay_complete: bool, rejoin_key: String },
+    Rejected(String),
+}
+
+async fn handshake(url: &str, credentials: &Credentials, last_seq: Option<u64>) -> Result<Handshake> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+    let nonce = match next_message::<ServerMessage, _>(&mut socket).await? {
+        Some(ServerMessage::Challenge { nonce }) => nonce,
+        Some(ServerMessage::Error { message }) => return Ok(Handshake::Rejected(message)),
+        Some(_) => return Err(anyhow!("Server did not open with a challenge")),
+        None => return Err(anyhow!("Server closed the connection during the handshake")),
+    };
+    let hello = ClientMessage::Hello {
+        session_id: credentials.session_id.clone(),
+        user_id: credentials.user_id.clone(),
+        proof: token_proof(&credentials.token, &nonce),
+        rejoin_proof: credentials.rejoin_key.as_deref().map(|key| token_proof(key, &nonce)),
+        last_seq,
+    };
+    send_message(&mut socket, &hello).await?;
+    match next_message::<ServerMessage, _>(&mut socket).await? {
+        Some(ServerMessage::Welcome { head_seq, replay_complete, rejoin_key, .. }) => {
+            Ok(Handshake::Welcome { socket: Box::new(socket), head_seq, replay_complete, rejoin_key })
+        }
+        Some(ServerMessage::Error { message }) => Ok(Handshake::Rejected(message)),
+        Some(ServerMessage::Event { .. } | ServerMessage::Challenge { .. }) => {
+            Err(anyhow!("Server sent an unexpected message before accepting the hello"))
+        }
+        None => Err(anyhow!("Server closed the connection during the handshake")),
+    }
+}
+
+/// A remote participant's connection to a collaboration server. Dropped connections are
+/// re-established in the background, replaying the session events missed meanwhile.
+#[derive(Debug)]
+pub struct CollaborationClient {
+    outgoing: mpsc::UnboundedSender<ClientMessage>,
+    last_seq: Arc<AtomicU64>,
+    shutdown: CancellationToken,
+}
+
+impl CollaborationClient {
+    /// Join `session_id` through the server at `url`, returning the client and the
+    /// session's events in relay order. Fails if the first connection is refused.
+    pub async fn connect(
+        url: &str,
+        session_id: &str,
+        user_id: &str,
+        token: &str,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<SequencedEvent>)> {
+        let mut credentials = Credentials {
+            session_id: session_id.to_string(),
+            user_id: user_id.to_string(),
+            token: token.to_string(),
+            rejoin_key: None,
+        };
+        let (socket, head_seq) = match handshake(url, &credentials, None).await? {
+            Handshake::Welcome { socket, head_seq, rejoin_key, .. } => {
+                credentials.rejoin_key = Some(rejoin_key);
+                (*socket, head_seq)
+            }
+            Handshake::Rejected(message) => return Err(anyhow!("Collaboration server refused to join: {}", message)),
+        };
+
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let (events, events_rx) = mpsc::unbounded_channel();
+        let last_seq = Arc::new(AtomicU64::new(head_seq));
+        let shutdown = CancellationToken::new();
+        tokio::spawn(run_client(
+            url.to_string(),
+            credentials,
+            socket,
+            outgoing_rx,
+            events,
+            last_seq.clone(),
+            shutdown.clone(),
+        ));
+
+        Ok((Self { outgoing, last_seq, shutdown }, events_rx))
+    }
+
+    /// Sequence number of the newest event received
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq.load(Ordering::SeqCst)
+    }
+
+    pub fn send_chat(&self, content: &str) -> Result<()> {
+        self.send(ClientMessage::Chat { content: content.to_string() })
+    }
+
+    pub fn send_terminal_op(&self, terminal_id: &str, client_seq: u64, data: &str) -> Result<()> {
+        self.send(ClientMessage::TerminalOp {
+            terminal_id: terminal_id.to_string(),
+            client_seq,
+            data: data.to_string(),
+        })
+    }
+
+    /// Leave the session, then close the connection
+    pub fn leave(&self) -> Result<()> {
+        self.send(ClientMessage::Leave)
+    }
+
+    /// Close the connection without leaving, as if the network dropped for good
+    pub fn disconnect(&self) {
+        self.shutdown.cancel();
+    }
+
+    fn send(&self, message: ClientMessage) -> Result<()> {
+        self.outgoing.send(message).map_err(|_| anyhow!("Collaboration client is disconnected"))
+    }
+}
+
+impl Drop for CollaborationClient {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn run_client(
+    url: String,
+    mut credentials: Credentials,
+    mut socket: ClientSocket,
+    mut outgoing: mpsc::UnboundedReceiver<ClientMessage>,
+    events: mpsc::UnboundedSender<SequencedEvent>,
+    last_seq: Arc<AtomicU64>,
+    shutdown: CancellationToken,
+) {
+    // A message whose send failed is retried on the next connection
+    let mut unsent = None;
+    loop {
+        match pump_client(socket, &mut outgoing, &mut unsent, &events, &last_seq, &shutdown).await {
+            Ok(true) => return,
+            Ok(false) => debug!("Collaboration server closed the connection; reconnecting"),
+            Err(e) => debug!("Collaboration connection dropped: {}; reconnecting", e),
+        }
+
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        socket = loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            let seen = last_seq.load(Ordering::SeqCst);
+            match handshake(&url, &credentials, Some(seen)).await {
+                Ok(Handshake::Welcome { socket, head_seq, replay_complete, rejoin_key }) => {
+                    credentials.rejoin_key = Some(rejoin_key);
+                    if !replay_complete {
+                        warn!("Collaboration events after {} were no longer buffered; some were missed", seen);
+                    }
+                    if seen > head_seq {
+                        last_seq.store(head_seq, Ordering::SeqCst);
+                    }
+                    break *socket;
+                }
+                Ok(Handshake::Rejected(message)) => {
+                    warn!("Collaboration server refused to rejoin: {}", message);
+                    return;
+                }
+                Err(e) => {
+                    debug!("Collaboration reconnect to {} failed: {}", url, e);
+                    delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+                }
+            }
+        };
+    }
+}
+
+/// Relay one connection until it drops (`Ok(false)`) or the client is finished (`Ok(true)`)
+async fn pump_client(
+    socket: ClientSocket,
+    outgoing: &mut mpsc::UnboundedReceiver<ClientMessage>,
+    unsent: &mut Option<ClientMessage>,
+    events: &mpsc::UnboundedSender<SequencedEvent>,
+    last_seq: &AtomicU64,
+    shutdown: &CancellationToken,
+) -> Result<bool> {
+    let (mut sink, mut stream) = socket.split();
+    if let Some(message) = unsent.take() {
+        if let Err(e) = send_message(&mut sink, &message).await {
+            *unsent = Some(message);
+            return Err(e);
+        }
+    }
+
+    loop {
+        tokio::select! {
+            // Queued messages go out first, so a leave sent just before dropping the client is delivered
+            biased;
+            message = outgoing.recv() => {
+                let Some(message) = message else {
+                    let _ = sink.close().await;
+                    return Ok(true);
+                };
+                let leaving = matches!(message, ClientMessage::Leave);
+                if let Err(e) = send_message(&mut sink, &message).await {
+                    *unsent = Some(message);
+                    return Err(e);
+                }
+                if leaving {
+                    let _ = sink.close().await;
+                    return Ok(true);
+                }
+            }
+            _ = shutdown.cancelled() => {
+                let _ = sink.close().await;
+                return Ok(true);
+            }
+            message = next_message::<ServerMessage, _>(&mut stream) => match message? {
+                Some(ServerMessage::Event { seq, event }) => {
+                    last_seq.store(seq, Ordering::SeqCst);
+                    let _ = events.send(SequencedEvent { seq, event });
+                }
+                Some(ServerMessage::Error { message }) => warn!("Collaboration server error: {}", message),
+                Some(ServerMessage::Welcome { .. } | ServerMessage::Challenge { .. }) => {}
+                None => return Ok(false),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collaboration::{CollaborationEventType, SessionPermissions};
+    use std::sync::atomic::AtomicUsize;
+    use tokio::task::JoinHandle;
+
+    async fn session(manager: &CollaborationManager) -> (String, String) {
+        let permissions = SessionPermissions {
+            is_public: false,
+            allow_anonymous: false,
+            max_participants: 10,
+            require_approval: false,
+            allow_recording: false,
+            password_protected: false,
+        };
+        let session = manager.create_session("Remote pairing", permissions).await.unwrap();
+        (session.id, session.access_token)
+    }
+
+    /// The next chat message, skipping joins and other events
+    async fn next_chat(events: &mut mpsc::UnboundedReceiver<SequencedEvent>) -> (u64, String) {
+        loop {
+            let sequenced = tokio::time::timeout(Duration::from_secs(10), events.recv())
+                .await
+                .expect("timed out waiting for a chat message")
+                .expect("client stopped");
+            if matches!(sequenced.event.event_type, CollaborationEventType::MessageSent) {
+                return (sequenced.seq, sequenced.event.data["content"].as_str().unwrap().to_string());
+            }
+        }
+    }
+
+    /// TCP proxy whose open links can be severed to simulate a network drop
+    struct Proxy {
+        url: String,
+        connections: Arc<AtomicUsize>,
+        links: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    }
+
+    impl Proxy {
+        async fn start(target: SocketAddr) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("ws://{}", listener.local_addr().unwrap());
+            let connections = Arc::new(AtomicUsize::new(0));
+            let links = Arc::new(Mutex::new(Vec::new()));
+            let (accepted, accepted_links) = (connections.clone(), links.clone());
+            tokio::spawn(async move {
+                while let Ok((mut inbound, _)) = listener.accept().await {
+                    accepted.fetch_add(1, Ordering::SeqCst);
+                    let link = tokio::spawn(async move {
+                        let mut outbound = TcpStream::connect(target).await.unwrap();
+                        let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    });
+                    accepted_links.lock().unwrap().push(link);
+                }
+            });
+            Self { url, connections, links }
+        }
+
+        fn cut(&self) {
+            for link in self.links.lock().unwrap().drain(..) {
+                link.abort();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_clients_receive_broadcast_chat() {
+        let manager = CollaborationManager::new();
+        let (session_id, token) = session(&manager).await;
+        let server = CollaborationServer::start(manager.clone(), "127.0.0.1:0").await.unwrap();
+
+        let (alice, _alice_events) = CollaborationClient::connect(&server.url(), &session_id, "alice", &token).await.unwrap();
+        let (_bob, mut bob_events) = CollaborationClient::connect(&server.url(), &session_id, "bob", &token).await.unwrap();
+
+        alice.send_chat("hello from alice").unwrap();
+        let (_, content) = next_chat(&mut bob_events).await;
+        assert_eq!(content, "hello from alice");
+
+        let participants = manager.get_participants(&session_id).await.unwrap();
+        assert!(participants.iter().any(|p| p.user_id == "alice"));
+        assert!(participants.iter().any(|p| p.user_id == "bob"));
+
+        let refused = CollaborationClient::connect(&server.url(), &session_id, "mallory", "not-the-token").await;
+        assert!(refused.err().unwrap().to_string().contains("Invalid access token"));
+    }
+
+    #[tokio::test]
+    async fn test_token_holders_cannot_claim_existing_participants() {
+        let manager = CollaborationManager::new();
+        let (session_id, token) = session(&manager).await;
+        let server = CollaborationServer::start(manager.clone(), "127.0.0.1:0").await.unwrap();
+        let (_alice, _events) = CollaborationClient::connect(&server.url(), &session_id, "alice", &token).await.unwrap();
+
+        // The owner is a participant from the start; alice became one by joining
+        for claimed in ["system", "alice"] {
+            let refused = CollaborationClient::connect(&server.url(), &session_id, claimed, &token).await;
+            assert!(refused.err().unwrap().to_string().contains("already a participant"));
+        }
+
+        // Once alice leaves, her id is free again
+        manager.leave_session(&session_id, "alice").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(CollaborationClient::connect(&server.url(), &session_id, "alice", &token).await.is_ok());
+    }
+
+    #[test]
+    fn test_relay_forgets_ended_sessions() {
+        let relay = Relay::default();
+        relay.attach("s", None);
+        let key = relay.admit("s", "bob", false, "n1", None).unwrap();
+        assert!(relay.admit("s", "bob", true, "n2", None).is_err());
+        assert!(relay.admit("s", "bob", true, "n2", Some(&token_proof(&key, "n2"))).is_ok());
+
+        relay.remove_session("s");
+        assert!(relay.session_ids().is_empty());
+        assert!(relay.rejoin_keys.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_replays_missed_events() {
+        let manager = CollaborationManager::new();
+        let (session_id, token) = session(&manager).await;
+        let server = CollaborationServer::start(manager.clone(), "127.0.0.1:0").await.unwrap();
+        let proxy = Proxy::start(server.local_addr()).await;
+
+        let (bob, mut events) = CollaborationClient::connect(&proxy.url, &session_id, "bob", &token).await.unwrap();
+        let chat = |content: &str| ChatMessage {
+            id: None,
+            author_id: "alice".to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            message_type: MessageType::Text,
+        };
+        manager.send_message(&session_id, chat("before")).await.unwrap();
+        let (before_seq, content) = next_chat(&mut events).await;
+        assert_eq!(content, "before");
+
+        proxy.cut();
+        manager.send_message(&session_id, chat("missed 1")).await.unwrap();
+        manager.send_message(&session_id, chat("missed 2")).await.unwrap();
+
+        let (first_seq, first) = next_chat(&mut events).await;
+        let (second_seq, second) = next_chat(&mut events).await;
+        assert_eq!((first.as_str(), second.as_str()), ("missed 1", "missed 2"));
+        assert!(before_seq < first_seq && first_seq < second_seq);
+        assert!(proxy.connections.load(Ordering::SeqCst) >= 2);
+        // Rejoining adds its own join event after the replay
+        assert!(bob.last_seq() >= second_seq);
+    }
+
+    #[test]
+    fn test_replay_buffer_is_bounded() {
+        let relay = Relay::default();
+        for i in 0..REPLAY_BUFFER_EVENTS + 5 {
+            relay.record(CollaborationEvent {
+                id: i.to_string(),
+                session_id: "s".to_string(),
+                user_id: "alice".to_string(),
+                event_type: CollaborationEventType::MessageSent,
+                timestamp: chrono::Utc::now(),
+                data: serde_json::json!({}),
+            });
+        }
+
+        let stale = relay.attach("s", Some(0));
+        assert!(!stale.replay_complete);
+        assert_eq!(stale.replay.len(), REPLAY_BUFFER_EVENTS);
+        assert_eq!(stale.replay[0].seq, 6);
+
+        let recent = relay.attach("s", Some(REPLAY_BUFFER_EVENTS as u64));
+        assert!(recent.replay_complete);
+        let seqs: Vec<u64> = recent.replay.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, (REPLAY_BUFFER_EVENTS as u64 + 1..=REPLAY_BUFFER_EVENTS as u64 + 5).collect::<Vec<_>>());
+
+        let live_only = relay.attach("s", None);
+        assert!(live_only.replay.is_empty());
+        assert_eq!(live_only.head_seq, REPLAY_BUFFER_EVENTS as u64 + 5);
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use chrono::{DateTime, Utc};
 
 // Missing types expected by main.rs
@@ -20,6 +21,264 @@ pub struct DependencyGraph {
     pub nodes: Vec<DependencyNode>,
     pub edges: Vec<DependencyEdge>,
     pub metadata: HashMap<String, String>,
+    /// Node ids of each circular dependency, in node order
+    #[serde(default)]
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl DependencyGraph {
+    /// Strongly-connected components that form cycles, found with Tarjan's algorithm.
+    ///
+    /// A component is reported when it has more than one node or a node depends on itself.
+    pub fn detect_cycles(&self) -> Vec<Vec<String>> {
+        let index_of: HashMap<&str, usize> = self.nodes.iter().enumerate().map(|(i, node)| (node.id.as_str(), i)).collect();
+        let mut successors = vec![Vec::new(); self.nodes.len()];
+        let mut self_loop = vec![false; self.nodes.len()];
+        for edge in &self.edges {
+            if let (Some(&from), Some(&to)) = (index_of.get(edge.source.as_str()), index_of.get(edge.target.as_str())) {
+                successors[from].push(to);
+                self_loop[from] |= from == to;
+            }
+        }
+
+        const UNVISITED: usize = usize::MAX;
+        let mut index = vec![UNVISITED; self.nodes.len()];
+        let mut lowlink = vec![0; self.nodes.len()];
+        let mut on_stack = vec![false; self.nodes.len()];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut components = Vec::new();
+
+        for root in 0..self.nodes.len() {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            // Explicit call stack of (node, next successor to visit), so long chains cannot overflow
+            let mut calls = vec![(root, 0)];
+            index[root] = next_index;
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some(frame) = calls.last_mut() {
+                let node = frame.0;
+                if let Some(&successor) = successors[node].get(frame.1) {
+                    frame.1 += 1;
+                    if index[successor] == UNVISITED {
+                        index[successor] = next_index;
+                        lowlink[successor] = next_index;
+                        next_index += 1;
+                        stack.push(successor);
+                        on_stack[successor] = true;
+                        calls.push((successor, 0));
+                    } else if on_stack[successor] {
+                        lowlink[node] = lowlink[node].min(index[successor]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                }
+                if lowlink[node] == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    if component.len() > 1 || self_loop[node] {
+                        component.sort_unstable();
+                        components.push(component);
+                    }
+                }
+            }
+        }
+
+        components.sort();
+        components
+            .into_iter()
+            .map(|component| component.into_iter().map(|i| self.nodes[i].id.clone()).collect())
+            .collect()
+    }
+
+    /// Render the graph as a Graphviz DOT digraph. Cycles are emitted as-is; Graphviz lays them out fine.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+        dot.push_str("    rankdir=LR;\n");
+        dot.push_str("    node [shape=box, style=\"rounded,filled\", fontname=\"monospace\"];\n");
+
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\", fillcolor=\"{}\", tooltip=\"{}\"];\n",
+                dot_escape(&node.id),
+                dot_escape(&node.command),
+                category_color(&node.category),
+                dot_escape(&node.category)
+            ));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\", color=\"{}\", penwidth={}];\n",
+                dot_escape(&edge.source),
+                dot_escape(&edge.target),
+                dot_escape(&edge.edge_type),
+                edge_color(&edge.edge_type),
+                edge.weight.clamp(0.5, 5.0)
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph as a Mermaid flowchart for embedding in markdown
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+
+        for node in &self.nodes {
+            mermaid.push_str(&format!("    {}[\"{}\"]\n", mermaid_id(&node.id), mermaid_escape(&node.command)));
+        }
+        for edge in &self.edges {
+            mermaid.push_str(&format!(
+                "    {} -->|{}| {}\n",
+                mermaid_id(&edge.source),
+                mermaid_escape(&edge.edge_type),
+                mermaid_id(&edge.target)
+            ));
+        }
+
+        let mut categories: Vec<&str> = self.nodes.iter().map(|node| node.category.as_str()).collect();
+        categories.sort_unstable();
+        categories.dedup();
+        for category in categories {
+            mermaid.push_str(&format!("    classDef {} fill:{}\n", mermaid_class(category), category_color(category)));
+        }
+        for node in &self.nodes {
+            mermaid.push_str(&format!("    class {} {}\n", mermaid_id(&node.id), mermaid_class(&node.category)));
+        }
+        for (index, edge) in self.edges.iter().enumerate() {
+            mermaid.push_str(&format!("    linkStyle {} stroke:{}\n", index, edge_color(&edge.edge_type)));
+        }
+
+        mermaid
+    }
+}
+
+fn category_color(category: &str) -> &'static str {
+    match category {
+        "System" => "#FF6B6B",
+        "Git" => "#4ECDC4",
+        "Development" => "#45B7D1",
+        "Network" => "#FFA07A",
+        "FileSystem" => "#98D8C8",
+        "Process" => "#F7DC6F",
+        _ => "#DDA0DD",
+    }
+}
+
+fn edge_color(edge_type: &str) -> &'static str {
+    match edge_type {
+        "sequential" | "Pipeline" => "#555555",
+        "runtime" | "RequiresBefore" | "RequiresAfter" => "#1F77B4",
+        "DataFlow" => "#2CA02C",
+        "ConditionalOn" => "#FF7F0E",
+        "ErrorHandling" => "#D62728",
+        _ => "#7F7F7F",
+    }
+}
+
+/// Files a command reads and writes, judged from redirections, `tee`, `--output` and the
+/// path-like arguments of each program (not the program names or words handed to `echo`)
+fn file_reads_and_writes(command: &str) -> (HashSet<String>, HashSet<String>) {
+    let tokens = shell_words::split(command)
+        .unwrap_or_else(|_| command.split_whitespace().map(str::to_string).collect());
+    let mut reads = HashSet::new();
+    let mut writes = HashSet::new();
+    let mut in_tee = false;
+    let mut program: Option<&str> = None;
+    let mut iter = tokens.iter().peekable();
+
+    while let Some(token) = iter.next() {
+        // `2>err.log` and `&>all.log` redirect like `>`
+        let redirect = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '&');
+        let (operator, target) = if let Some(rest) = redirect.strip_prefix(">>") {
+            (Some('>'), rest)
+        } else if let Some(rest) = redirect.strip_prefix('>') {
+            (Some('>'), rest)
+        } else if let Some(rest) = redirect.strip_prefix('<') {
+            (Some('<'), rest)
+        } else {
+            (None, "")
+        };
+
+        if let Some(operator) = operator {
+            let target = if target.is_empty() { iter.next().map(String::as_str) } else { Some(target) };
+            // `2>&1` duplicates a descriptor rather than naming a file
+            if let Some(target) = target.filter(|t| !t.starts_with('&')) {
+                if operator == '>' { &mut writes } else { &mut reads }.insert(target.to_string());
+            }
+            continue;
+        }
+
+        match token.as_str() {
+            "|" | "&&" | "||" | ";" => {
+                in_tee = false;
+                program = None;
+            }
+            "--output" => {
+                if let Some(target) = iter.next() {
+                    writes.insert(target.clone());
+                }
+            }
+            _ if token.starts_with("--output=") => {
+                writes.insert(token["--output=".len()..].to_string());
+            }
+            _ if token.starts_with('-') => {}
+            _ if program.is_none() => {
+                in_tee = token == "tee";
+                program = Some(token);
+            }
+            _ if in_tee => {
+                writes.insert(token.clone());
+            }
+            _ if matches!(program, Some("echo" | "printf")) => {}
+            _ if looks_like_path(token) => {
+                reads.insert(token.clone());
+            }
+            _ => {}
+        }
+    }
+
+    (reads, writes)
+}
+
+/// Arguments such as `build.log` or `src/main.rs`, as opposed to subcommands, patterns and URLs
+fn looks_like_path(token: &str) -> bool {
+    !token.contains("://") && (token.contains('/') || token.trim_start_matches('.').contains('.'))
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;").replace('|', "#124;").replace('\n', " ")
+}
+
+/// Mermaid node ids must be plain identifiers
+fn mermaid_id(id: &str) -> String {
+    let sanitized: String = id.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    format!("n_{}", sanitized)
+}
+
+fn mermaid_class(category: &str) -> String {
+    let sanitized: String = category.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect();
+    format!("category_{}", sanitized)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +313,51 @@ pub struct ExecutionVisualization {
     pub resource_usage: HashMap<String, f64>,
     pub performance_metrics: HashMap<String, f64>,
     pub bottlenecks: Vec<String>,
+    #[serde(default)]
+    pub gantt: GanttTimeline,
+}
+
+/// Layout for drawing an execution as a Gantt chart; offsets are milliseconds from execution start
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GanttTimeline {
+    pub total_duration_ms: u64,
+    pub lane_count: usize,
+    pub bars: Vec<GanttBar>,
+    pub dependencies: Vec<GanttDependency>,
+    /// Stretches where no command was running
+    pub idle_gaps: Vec<IdleGap>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GanttBar {
+    pub node_id: String,
+    pub command: String,
+    pub start_offset_ms: u64,
+    pub end_offset_ms: u64,
+    /// Row to draw on; overlapping commands never share a lane
+    pub lane: usize,
+    pub status: ExecutionStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GanttDependency {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdleGap {
+    pub start_offset_ms: u64,
+    pub end_offset_ms: u64,
+}
+
+/// One command's extent while a Gantt timeline is assembled from the execution log
+struct CommandSpan<'a> {
+    /// The command's first log entry
+    entry: &'a ExecutionLogEntry,
+    start: Option<u64>,
+    end: Option<u64>,
+    status: ExecutionStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +368,21 @@ pub struct TimelineEvent {
     pub duration_ms: Option<u64>,
 }
 
+/// The chain of dependent commands that determined an execution's total runtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriticalPath {
+    pub execution_id: String,
+    pub steps: Vec<CriticalPathStep>,
+    pub total_duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriticalPathStep {
+    pub node_id: String,
+    pub command: String,
+    pub duration_ms: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionRecord {
     pub id: String,
@@ -583,15 +902,7 @@ impl CommandFlowEngine {
     }
 
     fn get_category_color(&self, category: &str) -> String {
-        match category {
-            "System" => "#FF6B6B".to_string(),
-            "Git" => "#4ECDC4".to_string(),
-            "Development" => "#45B7D1".to_string(),
-            "Network" => "#FFA07A".to_string(),
-            "FileSystem" => "#98D8C8".to_string(),
-            "Process" => "#F7DC6F".to_string(),
-            _ => "#DDA0DD".to_string(),
-        }
+        category_color(category).to_string()
     }
 
     fn find_critical_path(&self, flow: &CommandFlow) -> Vec<String> {
@@ -803,11 +1114,38 @@ impl CommandFlowEngine {
             }
         }
 
-        Ok(DependencyGraph {
+        // A command reading a file an earlier command wrote depends on that writer
+        let file_usage: Vec<(HashSet<String>, HashSet<String>)> = commands.iter().map(|command| file_reads_and_writes(command)).collect();
+        let mut writers: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, (_, writes)) in file_usage.iter().enumerate() {
+            for file in writes {
+                writers.entry(file.as_str()).or_default().push(i);
+            }
+        }
+        let mut data_flows = HashSet::new();
+        for (reader, (reads, _)) in file_usage.iter().enumerate() {
+            for writer in reads.iter().filter_map(|file| writers.get(file.as_str())).flatten() {
+                // Later writers cannot have produced what this command reads
+                if *writer < reader && data_flows.insert((*writer, reader)) {
+                    edges.push(DependencyEdge {
+                        source: format!("node_{}", writer),
+                        target: format!("node_{}", reader),
+                        weight: 1.0,
+                        edge_type: "DataFlow".to_string(),
+                    });
+                }
+            }
+        }
+
+        let mut graph = DependencyGraph {
             nodes,
             edges,
             metadata: HashMap::new(),
-        })
+            cycles: Vec::new(),
+        };
+        graph.cycles = graph.detect_cycles();
+        graph.metadata.insert("cycle_count".to_string(), graph.cycles.len().to_string());
+        Ok(graph)
     }
 
     pub async fn get_command_dependencies(&self, command: &str) -> Result<Vec<CommandDependency>> {
@@ -855,6 +1193,7 @@ impl CommandFlowEngine {
                 resource_usage,
                 performance_metrics,
                 bottlenecks: vec!["slow_command_1".to_string()],
+                gantt: self.gantt_timeline(execution_id)?,
             })
         } else {
             Err(anyhow::anyhow!("Execution not found: {}", execution_id))
@@ -897,6 +1236,184 @@ impl CommandFlowEngine {
         Ok(execution_id)
     }
 
+    /// Longest duration-weighted path through the flow's dependency DAG, using the timings
+    /// recorded for each command of the execution. Parallel branches contribute only their slowest chain.
+    pub fn critical_path(&self, execution_id: &str) -> Result<CriticalPath> {
+        let execution = self
+            .executions
+            .get(execution_id)
+            .ok_or_else(|| anyhow::anyhow!("Execution not found: {}", execution_id))?;
+
+        let mut timings: HashMap<&str, (&str, u64)> = HashMap::new();
+        for entry in &execution.execution_log {
+            if matches!(entry.event, ExecutionEvent::Completed | ExecutionEvent::Failed) {
+                timings.insert(&entry.node_id, (&entry.command, entry.duration));
+            }
+        }
+
+        let Some(flow) = self.flows.get(&execution.flow_id) else {
+            // Single tracked commands have no flow; the command itself is the whole path
+            let command = execution.current_node.clone().unwrap_or_else(|| "unknown".to_string());
+            let duration_ms = timings.values().map(|(_, duration)| *duration).max().unwrap_or_else(|| {
+                execution.completed_at.map_or(0, |end| (end - execution.started_at).num_milliseconds().max(0) as u64)
+            });
+            return Ok(CriticalPath {
+                execution_id: execution_id.to_string(),
+                steps: vec![CriticalPathStep { node_id: command.clone(), command, duration_ms }],
+                total_duration_ms: duration_ms,
+            });
+        };
+
+        // Longest path ending at each executed node, with the predecessor it came through
+        let mut longest: HashMap<&str, (u64, Option<&str>)> = HashMap::new();
+        for node_id in self.topological_sort(flow)? {
+            let Some((node_id, (_, duration))) = timings.get_key_value(node_id.as_str()).map(|(id, timing)| (*id, *timing)) else {
+                continue;
+            };
+            let best_predecessor = flow
+                .edges
+                .iter()
+                .filter(|edge| edge.to == node_id)
+                .filter_map(|edge| longest.get_key_value(edge.from.as_str()).map(|(from, (total, _))| (*from, *total)))
+                .max_by_key(|(_, total)| *total);
+            let entry = match best_predecessor {
+                Some((from, total)) => (total + duration, Some(from)),
+                None => (duration, None),
+            };
+            longest.insert(node_id, entry);
+        }
+
+        let mut steps = Vec::new();
+        let mut current = longest.iter().max_by_key(|(_, (total, _))| *total).map(|(node_id, _)| *node_id);
+        let total_duration_ms = current.map_or(0, |node_id| longest[node_id].0);
+        while let Some(node_id) = current {
+            let (command, duration_ms) = timings[node_id];
+            steps.push(CriticalPathStep { node_id: node_id.to_string(), command: command.to_string(), duration_ms });
+            current = longest[node_id].1;
+        }
+        steps.reverse();
+
+        Ok(CriticalPath {
+            execution_id: execution_id.to_string(),
+            steps,
+            total_duration_ms,
+        })
+    }
+
+    /// Start and end of every command in an execution, packed into as few lanes as possible.
+    ///
+    /// A command's span runs from its `Started` log entry (or its end minus its duration) to its
+    /// last `Completed`/`Failed` entry; commands still running extend to the latest log entry.
+    pub fn gantt_timeline(&self, execution_id: &str) -> Result<GanttTimeline> {
+        let execution = self
+            .executions
+            .get(execution_id)
+            .ok_or_else(|| anyhow::anyhow!("Execution not found: {}", execution_id))?;
+        let offset = |time: DateTime<Utc>| (time - execution.started_at).num_milliseconds().max(0) as u64;
+        let last_logged = execution.execution_log.iter().map(|entry| offset(entry.timestamp)).max().unwrap_or(0);
+
+        // In order of each command's first log entry
+        let mut spans: Vec<CommandSpan> = Vec::new();
+        for entry in &execution.execution_log {
+            let index = match spans.iter().position(|span| span.entry.node_id == entry.node_id) {
+                Some(index) => index,
+                None => {
+                    spans.push(CommandSpan { entry, start: None, end: None, status: ExecutionStatus::Running });
+                    spans.len() - 1
+                }
+            };
+            let span = &mut spans[index];
+            match entry.event {
+                ExecutionEvent::Started => span.start = Some(span.start.unwrap_or(offset(entry.timestamp))),
+                ExecutionEvent::Completed | ExecutionEvent::Failed => {
+                    let end = offset(entry.timestamp);
+                    span.start = Some(span.start.unwrap_or(end.saturating_sub(entry.duration)));
+                    span.end = Some(end);
+                    span.status = if matches!(entry.event, ExecutionEvent::Completed) {
+                        ExecutionStatus::Completed
+                    } else {
+                        ExecutionStatus::Failed
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        let mut bars: Vec<GanttBar> = spans
+            .into_iter()
+            .filter_map(|span| {
+                let start = span.start?;
+                Some(GanttBar {
+                    node_id: span.entry.node_id.clone(),
+                    command: span.entry.command.clone(),
+                    start_offset_ms: start,
+                    end_offset_ms: span.end.unwrap_or(last_logged).max(start),
+                    lane: 0,
+                    status: span.status,
+                })
+            })
+            .collect();
+        bars.sort_by_key(|bar| (bar.start_offset_ms, bar.end_offset_ms));
+
+        // Greedy interval colouring: reuse the lowest lane whose last command has already ended
+        let mut busy: BinaryHeap<Reverse<(u64, usize)>> = BinaryHeap::new();
+        let mut free: BinaryHeap<Reverse<usize>> = BinaryHeap::new();
+        let mut lane_count = 0;
+        for bar in &mut bars {
+            while let Some(&Reverse((end, lane))) = busy.peek() {
+                if end > bar.start_offset_ms {
+                    break;
+                }
+                busy.pop();
+                free.push(Reverse(lane));
+            }
+            bar.lane = free.pop().map(|Reverse(lane)| lane).unwrap_or_else(|| {
+                lane_count += 1;
+                lane_count - 1
+            });
+            busy.push(Reverse((bar.end_offset_ms, bar.lane)));
+        }
+
+        let total_duration_ms = execution
+            .completed_at
+            .map(offset)
+            .unwrap_or(0)
+            .max(bars.iter().map(|bar| bar.end_offset_ms).max().unwrap_or(0));
+
+        let mut idle_gaps = Vec::new();
+        let mut covered_until = 0;
+        for bar in &bars {
+            if bar.start_offset_ms > covered_until {
+                idle_gaps.push(IdleGap { start_offset_ms: covered_until, end_offset_ms: bar.start_offset_ms });
+            }
+            covered_until = covered_until.max(bar.end_offset_ms);
+        }
+        if total_duration_ms > covered_until {
+            idle_gaps.push(IdleGap { start_offset_ms: covered_until, end_offset_ms: total_duration_ms });
+        }
+
+        let dependencies = self
+            .flows
+            .get(&execution.flow_id)
+            .map(|flow| {
+                let drawn: HashSet<&str> = bars.iter().map(|bar| bar.node_id.as_str()).collect();
+                flow.edges
+                    .iter()
+                    .filter(|edge| drawn.contains(edge.from.as_str()) && drawn.contains(edge.to.as_str()))
+                    .map(|edge| GanttDependency { from: edge.from.clone(), to: edge.to.clone() })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(GanttTimeline {
+            total_duration_ms,
+            lane_count,
+            bars,
+            dependencies,
+            idle_gaps,
+        })
+    }
+
     pub async fn get_execution_history(&self, limit: Option<u32>) -> Result<Vec<ExecutionRecord>> {
         let mut records = Vec::new();
         let limit = limit.unwrap_or(100) as usize;
@@ -1077,4 +1594,302 @@ mod tests {
         engine.register_command(command);
         assert!(engine.command_registry.contains_key("test-cmd"));
     }
+
+    fn cyclic_graph() -> DependencyGraph {
+        let node = |id: &str, command: &str, category: &str| DependencyNode {
+            id: id.to_string(),
+            command: command.to_string(),
+            weight: 1.0,
+            category: category.to_string(),
+        };
+        let edge = |source: &str, target: &str, edge_type: &str| DependencyEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            weight: 1.0,
+            edge_type: edge_type.to_string(),
+        };
+        DependencyGraph {
+            nodes: vec![
+                node("node_0", "git pull", "Git"),
+                node("node_1", r#"grep "TODO" src/*.rs | wc -l"#, "FileSystem"),
+                node("node_2", "cargo build", "Development"),
+            ],
+            edges: vec![
+                edge("node_0", "node_1", "sequential"),
+                edge("node_1", "node_2", "DataFlow"),
+                edge("node_2", "node_0", "ErrorHandling"),
+            ],
+            metadata: HashMap::new(),
+            cycles: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_dot_export_round_trips_through_parser() {
+        use graphviz_rust::dot_structures::{Edge, EdgeTy, Graph, Id, Node, Stmt, Vertex};
+
+        let dot = cyclic_graph().to_dot();
+        let Graph::DiGraph { stmts, .. } = graphviz_rust::parse(&dot).unwrap() else {
+            panic!("expected a digraph");
+        };
+        let unquote = |id: &Id| match id {
+            Id::Escaped(text) => text.trim_matches('"').replace("\\\"", "\""),
+            Id::Html(text) | Id::Plain(text) | Id::Anonymous(text) => text.clone(),
+        };
+
+        let nodes: Vec<(String, String)> = stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Node(Node { id, attributes }) => {
+                    let label = attributes.iter().find(|a| unquote(&a.0) == "label").map(|a| unquote(&a.1))?;
+                    Some((unquote(&id.0), label))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[1], ("node_1".to_string(), r#"grep "TODO" src/*.rs | wc -l"#.to_string()));
+
+        let edges: Vec<(String, String)> = stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Edge(Edge { ty: EdgeTy::Pair(Vertex::N(from), Vertex::N(to)), .. }) => Some((unquote(&from.0), unquote(&to.0))),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(edges, vec![
+            ("node_0".to_string(), "node_1".to_string()),
+            ("node_1".to_string(), "node_2".to_string()),
+            ("node_2".to_string(), "node_0".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_critical_path_follows_slowest_branch() {
+        let mut engine = CommandFlowEngine::new();
+        let flow_id = engine.create_flow("Release".to_string(), "Build and ship".to_string());
+        // fetch -> (compile | lint) -> package, with docs running alongside everything
+        let durations = [("fetch", 100), ("compile", 300), ("lint", 200), ("package", 50), ("docs", 400)];
+        for (id, _) in durations {
+            engine.register_command(CommandNode {
+                id: id.to_string(),
+                command: format!("make {}", id),
+                description: String::new(),
+                category: CommandCategory::Development,
+                execution_time: None,
+                success_rate: 1.0,
+                dependencies: vec![],
+                dependents: vec![],
+                metadata: HashMap::new(),
+                last_executed: None,
+            });
+            engine.add_command_to_flow(&flow_id, id).unwrap();
+        }
+        for (from, to) in [("fetch", "compile"), ("fetch", "lint"), ("compile", "package"), ("lint", "package")] {
+            engine.add_dependency(&flow_id, from, to, EdgeType::RequiresBefore).unwrap();
+        }
+
+        let execution_log = durations
+            .iter()
+            .map(|(id, duration)| ExecutionLogEntry {
+                timestamp: Utc::now(),
+                node_id: id.to_string(),
+                command: format!("make {}", id),
+                duration: *duration,
+                success: true,
+                output: None,
+                error: None,
+                event: ExecutionEvent::Completed,
+                details: String::new(),
+            })
+            .collect();
+        engine.executions.insert("run-1".to_string(), FlowExecution {
+            id: "run-1".to_string(),
+            flow_id: flow_id.clone(),
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            status: ExecutionStatus::Completed,
+            current_node: None,
+            executed_nodes: durations.iter().map(|(id, _)| id.to_string()).collect(),
+            failed_nodes: vec![],
+            execution_log,
+        });
+
+        let path = engine.critical_path("run-1").unwrap();
+        let nodes: Vec<&str> = path.steps.iter().map(|step| step.node_id.as_str()).collect();
+        assert_eq!(nodes, vec!["fetch", "compile", "package"]);
+        assert_eq!(path.steps[1].command, "make compile");
+        assert_eq!(path.steps[1].duration_ms, 300);
+        assert_eq!(path.total_duration_ms, 450);
+
+        assert!(engine.critical_path("missing").is_err());
+    }
+
+    #[test]
+    fn test_mermaid_export_styles_nodes_and_edges() {
+        let mermaid = cyclic_graph().to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains(r#"n_node_1["grep #quot;TODO#quot; src/*.rs #124; wc -l"]"#));
+        assert!(mermaid.contains("n_node_2 -->|ErrorHandling| n_node_0"));
+        assert!(mermaid.contains("classDef category_git fill:#4ECDC4"));
+        assert!(mermaid.contains("class n_node_2 category_development"));
+        assert!(mermaid.contains("linkStyle 2 stroke:#D62728"));
+    }
+
+    #[test]
+    fn test_tarjan_reports_hand_built_cycle() {
+        let mut graph = cyclic_graph();
+        assert_eq!(graph.detect_cycles(), vec![vec!["node_0", "node_1", "node_2"]]);
+
+        graph.edges.pop();
+        assert!(graph.detect_cycles().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dependency_graph_without_cycles() {
+        let engine = CommandFlowEngine::new();
+        let commands: Vec<String> = ["cargo build > build.log 2>&1", "grep -c warning build.log", "tar czf dist.tgz target/release", "ls"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        let graph = engine.create_dependency_graph(&commands).await.unwrap();
+        assert!(graph.edges.iter().any(|e| e.edge_type == "DataFlow" && e.source == "node_0" && e.target == "node_1"));
+        assert!(graph.cycles.is_empty());
+        assert_eq!(graph.metadata["cycle_count"], "0");
+    }
+
+    #[tokio::test]
+    async fn test_data_flows_only_from_earlier_writers() {
+        let engine = CommandFlowEngine::new();
+        let commands: Vec<String> = [
+            "echo start",
+            "sort < c.txt > a.txt",
+            "uniq < a.txt | tee b.txt",
+            "cat b.txt > c.txt",
+            "grep -o error b.txt",
+            "echo a.txt",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+
+        let graph = engine.create_dependency_graph(&commands).await.unwrap();
+        let mut flows: Vec<(&str, &str)> = graph.edges.iter()
+            .filter(|e| e.edge_type == "DataFlow")
+            .map(|e| (e.source.as_str(), e.target.as_str()))
+            .collect();
+        flows.sort_unstable();
+        // c.txt is read before it is rewritten, `-o` is grep's flag and `echo` only prints the name
+        assert_eq!(flows, vec![("node_1", "node_2"), ("node_2", "node_3"), ("node_2", "node_4")]);
+        assert!(graph.cycles.is_empty());
+
+        let (reads, writes) = file_reads_and_writes("git commit -m release && curl --output out.json https://example.com/api.json");
+        assert!(reads.is_empty());
+        assert_eq!(writes, HashSet::from(["out.json".to_string()]));
+    }
+
+    #[test]
+    fn test_cycle_detection_scales_to_long_chains() {
+        let nodes: Vec<DependencyNode> = (0..5000)
+            .map(|i| DependencyNode { id: format!("node_{}", i), command: "true".to_string(), weight: 1.0, category: "unknown".to_string() })
+            .collect();
+        let mut edges: Vec<DependencyEdge> = (1..5000)
+            .map(|i| DependencyEdge { source: format!("node_{}", i - 1), target: format!("node_{}", i), weight: 1.0, edge_type: "sequential".to_string() })
+            .collect();
+        edges.push(DependencyEdge { source: "node_4999".to_string(), target: "node_4000".to_string(), weight: 1.0, edge_type: "DataFlow".to_string() });
+        let graph = DependencyGraph { nodes, edges, metadata: HashMap::new(), cycles: Vec::new() };
+
+        let cycles = graph.detect_cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].len(), 1000);
+        assert_eq!(cycles[0][0], "node_4000");
+    }
+
+    #[tokio::test]
+    async fn test_gantt_timeline_assigns_lanes_to_overlapping_commands() {
+        let mut engine = CommandFlowEngine::new();
+        let flow_id = engine.create_flow("Release".to_string(), "Build and ship".to_string());
+        for id in ["fetch", "compile", "lint", "docs", "package"] {
+            engine.register_command(CommandNode {
+                id: id.to_string(),
+                command: format!("make {}", id),
+                description: String::new(),
+                category: CommandCategory::Development,
+                execution_time: None,
+                success_rate: 1.0,
+                dependencies: vec![],
+                dependents: vec![],
+                metadata: HashMap::new(),
+                last_executed: None,
+            });
+            engine.add_command_to_flow(&flow_id, id).unwrap();
+        }
+        for (from, to) in [("fetch", "compile"), ("fetch", "lint"), ("compile", "package"), ("lint", "package")] {
+            engine.add_dependency(&flow_id, from, to, EdgeType::RequiresBefore).unwrap();
+        }
+
+        let started_at = Utc::now();
+        let at = |ms: i64| started_at + chrono::Duration::milliseconds(ms);
+        let entry = |ms: i64, id: &str, event: ExecutionEvent, duration: u64| ExecutionLogEntry {
+            timestamp: at(ms),
+            node_id: id.to_string(),
+            command: format!("make {}", id),
+            duration,
+            success: !matches!(event, ExecutionEvent::Failed),
+            output: None,
+            error: None,
+            event,
+            details: String::new(),
+        };
+        // docs has only a completion entry, so its start comes from its duration
+        let execution_log = vec![
+            entry(20, "fetch", ExecutionEvent::Started, 0),
+            entry(100, "fetch", ExecutionEvent::Completed, 80),
+            entry(100, "compile", ExecutionEvent::Started, 0),
+            entry(150, "lint", ExecutionEvent::Started, 0),
+            entry(300, "docs", ExecutionEvent::Completed, 180),
+            entry(350, "lint", ExecutionEvent::Failed, 200),
+            entry(400, "compile", ExecutionEvent::Completed, 300),
+            entry(500, "package", ExecutionEvent::Started, 0),
+            entry(550, "package", ExecutionEvent::Completed, 50),
+        ];
+        engine.executions.insert("run-1".to_string(), FlowExecution {
+            id: "run-1".to_string(),
+            flow_id: flow_id.clone(),
+            started_at,
+            completed_at: Some(at(600)),
+            status: ExecutionStatus::Failed,
+            current_node: None,
+            executed_nodes: vec![],
+            failed_nodes: vec!["lint".to_string()],
+            execution_log,
+        });
+
+        let gantt = engine.visualize_execution("run-1").await.unwrap().gantt;
+        let bars: Vec<(&str, u64, u64, usize)> = gantt
+            .bars
+            .iter()
+            .map(|bar| (bar.node_id.as_str(), bar.start_offset_ms, bar.end_offset_ms, bar.lane))
+            .collect();
+        assert_eq!(bars, vec![
+            ("fetch", 20, 100, 0),
+            ("compile", 100, 400, 0),
+            ("docs", 120, 300, 1),
+            ("lint", 150, 350, 2),
+            ("package", 500, 550, 0),
+        ]);
+        assert_eq!(gantt.lane_count, 3);
+        assert_eq!(gantt.total_duration_ms, 600);
+        assert_eq!(gantt.bars[3].status, ExecutionStatus::Failed);
+        assert_eq!(gantt.idle_gaps, vec![
+            IdleGap { start_offset_ms: 0, end_offset_ms: 20 },
+            IdleGap { start_offset_ms: 400, end_offset_ms: 500 },
+            IdleGap { start_offset_ms: 550, end_offset_ms: 600 },
+        ]);
+        assert_eq!(gantt.dependencies.len(), 4);
+        assert!(gantt.dependencies.iter().any(|dep| dep.from == "lint" && dep.to == "package"));
+
+        assert!(engine.gantt_timeline("missing").is_err());
+    }
 }
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::security_scanner;
+
+/// Default number of commands kept per shell
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Recent commands included in the AI's terminal context
+pub const CONTEXT_COMMANDS: usize = 20;
+
+/// Commands included as the longer shell history in the AI's terminal context
+pub const CONTEXT_SHELL_HISTORY: usize = 100;
+
+/// A command the user ran in a terminal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub shell: String,
+    pub terminal_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Commands typed into terminals, persisted as one JSON-lines file per shell
+#[derive(Debug)]
+pub struct CommandHistoryStore {
+    dir: PathBuf,
+    max_entries: usize,
+    /// Patterns whose matches are replaced before a command is stored
+    redactions: Vec<Regex>,
+    shells: HashMap<String, VecDeque<HistoryEntry>>,
+    /// Input typed since the last newline, per terminal
+    pending_lines: HashMap<String, String>,
+}
+
+impl CommandHistoryStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_entries: DEFAULT_HISTORY_SIZE,
+            redactions: Vec::new(),
+            shells: HashMap::new(),
+            pending_lines: HashMap::new(),
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Replace anything matching the security scanner's secret patterns with `[REDACTED]`
+    pub fn with_secret_redaction(mut self) -> Self {
+        self.redactions = security_scanner::builtin_secret_rules()
+            .iter()
+            .filter_map(|rule| Regex::new(&rule.pattern).ok())
+            .collect();
+        self
+    }
+
+    /// Load every shell's history from `dir`
+    pub fn open(mut self) -> Result<Self> {
+        if !self.dir.exists() {
+            return Ok(self);
+        }
+        for entry in std::fs::read_dir(&self.dir).with_context(|| format!("Failed to read {}", self.dir.display()))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(shell) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+                continue;
+            };
+            let entries = self.load_shell(&path)?;
+            self.shells.insert(shell, entries);
+        }
+        Ok(self)
+    }
+
+    fn load_shell(&self, path: &Path) -> Result<VecDeque<HistoryEntry>> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut entries: VecDeque<HistoryEntry> = content
+            .lines()
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Skipping unreadable history entry in {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
+
+        if entries.len() > self.max_entries {
+            entries.drain(..entries.len() - self.max_entries);
+            rewrite(path, &entries)?;
+        }
+        Ok(entries)
+    }
+
+    /// Feed raw terminal input; each completed line is recorded as a command
+    pub fn record_input(&mut self, terminal_id: &str, shell: &str, data: &str) -> Result<()> {
+        let mut completed = Vec::new();
+        {
+            let line = self.pending_lines.entry(terminal_id.to_string()).or_default();
+            let mut chars = data.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\r' | '\n' => completed.push(std::mem::take(line)),
+                    // Backspace / delete
+                    '\u{7f}' | '\u{8}' => {
+                        line.pop();
+                    }
+                    // Ctrl-C and Ctrl-U abandon the line
+                    '\u{3}' | '\u{15}' => line.clear(),
+                    // Escape sequences (arrow keys etc.) move the cursor; skip them
+                    '\u{1b}' => {
+                        if chars.next_if_eq(&'[').is_some() || chars.next_if_eq(&'O').is_some() {
+                            while chars.next_if(|c| !c.is_ascii_alphabetic() && *c != '~').is_some() {}
+                        }
+                        chars.next();
+                    }
+                    // Tab asks the shell to complete, it isn't part of the command
+                    c if c.is_control() => {}
+                    c => line.push(c),
+                }
+            }
+        }
+
+        for command in completed {
+            self.record(shell, &command, Some(terminal_id))?;
+        }
+        Ok(())
+    }
+
+    /// Record a command, skipping blanks and repeats of the shell's previous command
+    pub fn record(&mut self, shell: &str, command: &str, terminal_id: Option<&str>) -> Result<Option<HistoryEntry>> {
+        let command = self.redact(command.trim());
+        if command.is_empty() {
+            return Ok(None);
+        }
+
+        let shell = shell_name(shell);
+        let path = self.shell_path(&shell);
+        let entries = self.shells.entry(shell.clone()).or_default();
+        if entries.back().is_some_and(|last| last.command == command) {
+            return Ok(None);
+        }
+
+        let entry = HistoryEntry {
+            command,
+            shell: shell.clone(),
+            terminal_id: terminal_id.map(str::to_string),
+            timestamp: Utc::now(),
+        };
+        entries.push_back(entry.clone());
+
+        if entries.len() > self.max_entries {
+            entries.pop_front();
+            rewrite(&path, entries)?;
+        } else {
+            append(&path, &entry)?;
+        }
+        Ok(Some(entry))
+    }
+
+    /// The most recent `limit` commands across all shells, oldest first
+    pub fn recent(&self, limit: usize) -> Vec<HistoryEntry> {
+        let mut entries: Vec<&HistoryEntry> = self.shells.values().flatten().collect();
+        entries.sort_by_key(|entry| entry.timestamp);
+        let skip = entries.len().saturating_sub(limit);
+        entries.into_iter().skip(skip).cloned().collect()
+    }
+
+    pub fn recent_commands(&self, limit: usize) -> Vec<String> {
+        self.recent(limit).into_iter().map(|entry| entry.command).collect()
+    }
+
+    /// Forget every shell's history, on disk too
+    pub fn clear(&mut self) -> Result<()> {
+        for shell in std::mem::take(&mut self.shells).into_keys() {
+            let path = self.shell_path(&shell);
+            if path.exists() {
+                std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+        self.pending_lines.clear();
+        Ok(())
+    }
+
+    /// Drop the partially typed line of a closed terminal
+    pub fn forget_terminal(&mut self, terminal_id: &str) {
+        self.pending_lines.remove(terminal_id);
+    }
+
+    fn redact(&self, command: &str) -> String {
+        self.redactions.iter().fold(command.to_string(), |command, regex| {
+            regex.replace_all(&command, "[REDACTED]").into_owned()
+        })
+    }
+
+    fn shell_path(&self, shell: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", shell))
+    }
+}
+
+/// `/usr/bin/zsh` -> `zsh`, so each shell gets one history file however it was launched
+fn shell_name(shell: &str) -> String {
+    let name = Path::new(shell)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .trim_start_matches('-');
+    let name: String = name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-').collect();
+    if name.is_empty() {
+        "shell".to_string()
+    } else {
+        name
+    }
+}
+
+fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn rewrite(path: &Path, entries: &VecDeque<HistoryEntry>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry)?);
+        content.push('\n');
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_lines_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CommandHistoryStore::new(dir.path());
+
+        store.record_input("t1", "/bin/bash", "ls -l").unwrap();
+        store.record_input("t1", "/bin/bash", "a\r").unwrap();
+        // Typo fixed with backspace, arrow keys ignored, Ctrl-C discards the line
+        store.record_input("t1", "/bin/bash", "gti\u{7f}\u{7f}it status\u{1b}[A\r").unwrap();
+        store.record_input("t1", "/bin/bash", "rm -rf /\u{3}").unwrap();
+        // Tab completion requests are dropped
+        store.record_input("t2", "/usr/bin/zsh", "cargo te\tst\r").unwrap();
+
+        assert_eq!(store.recent_commands(10), vec!["ls -la", "git status", "cargo test"]);
+        assert_eq!(store.recent(10)[2].shell, "zsh");
+
+        let reopened = CommandHistoryStore::new(dir.path()).open().unwrap();
+        assert_eq!(reopened.recent_commands(10), vec!["ls -la", "git status", "cargo test"]);
+    }
+
+    #[test]
+    fn test_recent_respects_limit_and_max_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CommandHistoryStore::new(dir.path()).with_max_entries(3);
+        for i in 0..5 {
+            store.record("bash", &format!("echo {}", i), None).unwrap();
+        }
+
+        assert_eq!(store.recent_commands(2), vec!["echo 3", "echo 4"]);
+        assert_eq!(store.recent_commands(10), vec!["echo 2", "echo 3", "echo 4"]);
+        let reopened = CommandHistoryStore::new(dir.path()).with_max_entries(3).open().unwrap();
+        assert_eq!(reopened.recent_commands(10), vec!["echo 2", "echo 3", "echo 4"]);
+
+        store.clear().unwrap();
+        assert!(store.recent(10).is_empty());
+        assert!(CommandHistoryStore::new(dir.path()).open().unwrap().recent(10).is_empty());
+    }
+
+    #[test]
+    fn test_consecutive_duplicates_and_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CommandHistoryStore::new(dir.path()).with_secret_redaction();
+
+        assert!(store.record("bash", "make", None).unwrap().is_some());
+        assert!(store.record("bash", "  make ", None).unwrap().is_none());
+        assert!(store.record("bash", "", None).unwrap().is_none());
+        store.record("bash", "make test", None).unwrap();
+        store.record("bash", "make", None).unwrap();
+        store.record("bash", "mysql --password=hunter2secret", None).unwrap();
+
+        assert_eq!(store.recent_commands(10), vec!["make", "make test", "make", "mysql --[REDACTED]"]);
+        let on_disk = std::fs::read_to_string(dir.path().join("bash.jsonl")).unwrap();
+        assert!(!on_disk.contains("hunter2"));
+    }
+}
@@ -1,8 +1,12 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid;
 use crate::ai::AIConfig;
+use crate::plugin_system::TrustedKey;
+use crate::secret_store::{is_secret_ref, SecretStore};
+use crate::terminal::OutputBatchingConfig;
+use crate::utils::CommandPolicy;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PathsConfig {
@@ -19,14 +23,129 @@ pub struct VisionConfig {
     pub enabled: bool,
 }
 
+/// Schema version written by this build; bump it together with a new entry in `MIGRATIONS`
+pub const CONFIG_VERSION: u32 = 3;
+
+/// Upgrades a config document by one version. `MIGRATIONS[i]` turns version `i + 1` into `i + 2`.
+type Migration = fn(&mut toml::Table) -> Result<()>;
+
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// v2 introduced the `version` field and dropped the EasyOCR engine
+fn migrate_v1_to_v2(config: &mut toml::Table) -> Result<()> {
+    if let Some(vision) = config.get_mut("vision").and_then(|vision| vision.as_table_mut()) {
+        if vision.get("ocr_engine").and_then(|engine| engine.as_str()) == Some("easyocr") {
+            vision.insert("ocr_engine".to_string(), toml::Value::String("tesseract".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// v3 split the shell syntax rules out of `command_policy.denied_patterns`, so that they no
+/// longer apply to template scripts
+fn migrate_v2_to_v3(config: &mut toml::Table) -> Result<()> {
+    const SHELL_SYNTAX_RULES: &[&str] = &["command-chaining", "command-substitution", "redirection"];
+
+    let Some(policy) = config.get_mut("command_policy").and_then(|policy| policy.as_table_mut()) else {
+        return Ok(());
+    };
+    let Some(denied) = policy.get_mut("denied_patterns").and_then(|denied| denied.as_array_mut()) else {
+        return Ok(());
+    };
+    let (shell_syntax, dangerous): (Vec<toml::Value>, Vec<toml::Value>) = denied.drain(..).partition(|pattern| {
+        pattern.get("name").and_then(|name| name.as_str()).is_some_and(|name| SHELL_SYNTAX_RULES.contains(&name))
+    });
+    *denied = dangerous;
+    if !shell_syntax.is_empty() && !policy.contains_key("shell_syntax_patterns") {
+        policy.insert("shell_syntax_patterns".to_string(), toml::Value::Array(shell_syntax));
+    }
+    Ok(())
+}
+
+/// Configs written before versioning are version 1
+fn document_version(config: &toml::Table) -> Result<u32> {
+    match config.get("version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|&version| version > 0)
+            .ok_or_else(|| anyhow!("Config version must be a positive integer, found {}", version)),
+    }
+}
+
+/// A single invalid config value, keyed by its dotted field path (e.g. `ai.timeout_seconds`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(errors: &mut Vec<ConfigError>, field: &str, value: T, min: T, max: T) {
+    if value < min || value > max {
+        errors.push(ConfigError::new(field, format!("must be between {} and {}, got {}", min, max, value)));
+    }
+}
+
+/// A directory is usable if it exists, or if its nearest existing ancestor is a writable directory
+fn check_directory(errors: &mut Vec<ConfigError>, field: &str, dir: &Path) {
+    if dir.as_os_str().is_empty() {
+        errors.push(ConfigError::new(field, "must not be empty"));
+        return;
+    }
+    let Some(existing) = dir.ancestors().find(|ancestor| ancestor.exists()) else {
+        errors.push(ConfigError::new(field, format!("{} has no existing parent directory", dir.display())));
+        return;
+    };
+    match std::fs::metadata(existing) {
+        Ok(metadata) if !metadata.is_dir() => {
+            errors.push(ConfigError::new(field, format!("{} is not a directory", existing.display())));
+        }
+        Ok(metadata) if metadata.permissions().readonly() => {
+            errors.push(ConfigError::new(field, format!("{} is not writable", existing.display())));
+        }
+        Ok(_) => {}
+        Err(e) => errors.push(ConfigError::new(field, format!("cannot access {}: {}", existing.display(), e))),
+    }
+}
+
+fn default_config_version() -> u32 {
+    CONFIG_VERSION
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    #[serde(default = "default_config_version")]
+    pub version: u32,
     pub ai: AIConfig,
     pub terminal: TerminalConfig,
     pub appearance: AppearanceConfig,
     pub shortcuts: ShortcutsConfig,
     pub paths: PathsConfig,
     pub vision: VisionConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub command_policy: CommandPolicy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    /// Publisher keys plugin bundles may be signed with
+    #[serde(default)]
+    pub trusted_keys: Vec<TrustedKey>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +156,24 @@ pub struct TerminalConfig {
     pub cursor_blink: bool,
     pub cursor_style: String,
     pub scroll_back: u32,
+    #[serde(default)]
+    pub restore_sessions_on_launch: bool,
+    /// Commands remembered per shell for AI context
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+    /// Replace secrets (API keys, passwords) in recorded commands with `[REDACTED]`
+    #[serde(default = "default_redact_history")]
+    pub redact_history: bool,
+    #[serde(default)]
+    pub output: OutputBatchingConfig,
+}
+
+fn default_history_size() -> usize {
+    crate::command_history::DEFAULT_HISTORY_SIZE
+}
+
+fn default_redact_history() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,12 +226,15 @@ impl Default for VisionConfig {
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             ai: AIConfig::default(),
             terminal: TerminalConfig::default(),
             appearance: AppearanceConfig::default(),
             shortcuts: ShortcutsConfig::default(),
             paths: PathsConfig::default(),
             vision: VisionConfig::default(),
+            plugins: PluginsConfig::default(),
+            command_policy: CommandPolicy::default(),
         }
     }
 }
@@ -108,6 +248,10 @@ impl Default for TerminalConfig {
             cursor_blink: true,
             cursor_style: "block".to_string(),
             scroll_back: 10000,
+            restore_sessions_on_launch: false,
+            history_size: default_history_size(),
+            redact_history: default_redact_history(),
+            output: OutputBatchingConfig::default(),
         }
     }
 }
@@ -139,7 +283,8 @@ impl Default for ShortcutsConfig {
 }
 
 impl AppConfig {
-    pub fn config_path() -> Result<PathBuf> {
+    /// Application config directory, created if missing
+    pub fn config_dir() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .context("Failed to get config directory")?
             .join("nexus-terminal");
@@ -147,38 +292,176 @@ impl AppConfig {
         std::fs::create_dir_all(&config_dir)
             .context("Failed to create config directory")?;
         
-        Ok(config_dir.join("config.toml"))
+        Ok(config_dir)
+    }
+
+    /// Config file of the active profile
+    pub fn config_path() -> Result<PathBuf> {
+        let profiles = ProfileStore::new(Self::config_dir()?);
+        Ok(profiles.profile_path(&profiles.active_profile()))
     }
 
     pub fn load() -> Result<Self> {
-        let config_path = Self::config_path()?;
-        
-        if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)
-                .context("Failed to read config file")?;
-            
-            let config: AppConfig = toml::from_str(&content)
-                .context("Failed to parse config file")?;
-            
-            Ok(config)
-        } else {
+        Self::load_from(&Self::config_path()?)
+    }
+
+    /// Load the config at `config_path`, migrating older schema versions in place.
+    /// The file is backed up as `<name>.v<N>.bak` before a migrated copy replaces it.
+    pub fn load_from(config_path: &Path) -> Result<Self> {
+        if !config_path.exists() {
             let default_config = Self::default();
-            default_config.save()?;
-            Ok(default_config)
+            default_config.save_to(config_path)?;
+            return Ok(default_config);
+        }
+
+        let content = std::fs::read_to_string(config_path)
+            .context("Failed to read config file")?;
+        let mut document: toml::Table = toml::from_str(&content)
+            .context("Failed to parse config file")?;
+
+        let version = document_version(&document)?;
+        if version > CONFIG_VERSION {
+            return Err(Self::newer_version_error(config_path, version));
+        }
+        if version < CONFIG_VERSION {
+            let backup_path = config_path.with_extension(format!("toml.v{}.bak", version));
+            std::fs::copy(config_path, &backup_path)
+                .with_context(|| format!("Failed to back up config to {}", backup_path.display()))?;
+
+            for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+                migrate(&mut document)
+                    .with_context(|| format!("Failed to migrate config from version {} to {}", from + 1, from + 2))?;
+            }
+            document.insert("version".to_string(), toml::Value::Integer(CONFIG_VERSION.into()));
+        }
+
+        let config: AppConfig = document.try_into()
+            .context("Failed to parse config file")?;
+        if version < CONFIG_VERSION {
+            config.save_to(config_path)?;
         }
+        Ok(config)
     }
 
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path()?;
-        
+        self.save_to(&Self::config_path()?)
+    }
+
+    /// Check every field the app depends on, collecting all problems rather than stopping at the first
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        match url::Url::parse(&self.ai.ollama_url) {
+            Ok(url) if !matches!(url.scheme(), "http" | "https") => {
+                errors.push(ConfigError::new("ai.ollama_url", format!("unsupported scheme '{}', use http or https", url.scheme())));
+            }
+            Ok(url) if url.host_str().is_none_or(str::is_empty) => {
+                errors.push(ConfigError::new("ai.ollama_url", "must include a host"));
+            }
+            Ok(url) if url.port() == Some(0) => {
+                errors.push(ConfigError::new("ai.ollama_url", "port must be between 1 and 65535"));
+            }
+            Ok(_) => {}
+            Err(e) => errors.push(ConfigError::new("ai.ollama_url", format!("is not a valid URL: {}", e))),
+        }
+        if self.ai.default_model.trim().is_empty() {
+            errors.push(ConfigError::new("ai.default_model", "must not be empty"));
+        }
+        check_range(&mut errors, "ai.timeout_seconds", self.ai.timeout_seconds, 1, 3600);
+        check_range(&mut errors, "ai.temperature", self.ai.temperature, 0.0, 2.0);
+        check_range(&mut errors, "ai.max_tokens", self.ai.max_tokens, 1, 1_000_000);
+        check_range(&mut errors, "ai.circuit_failure_threshold", self.ai.circuit_failure_threshold, 1, 100);
+        check_range(&mut errors, "ai.circuit_cooldown_seconds", self.ai.circuit_cooldown_seconds, 1, 3600);
+        check_range(&mut errors, "ai.retry_max_attempts", self.ai.retry_max_attempts, 1, 10);
+        check_range(&mut errors, "ai.retry_backoff_base_ms", self.ai.retry_backoff_base_ms, 1, 60_000);
+        check_range(&mut errors, "ai.context_process_limit", self.ai.context_process_limit, 0, 200);
+        check_range(&mut errors, "ai.cache.max_entries", self.ai.cache.max_entries, 1, 100_000);
+        check_range(&mut errors, "ai.cache.ttl_seconds", self.ai.cache.ttl_seconds, 1, 7 * 24 * 3600);
+        let weights = &self.ai.queue_weights;
+        for (field, weight) in [
+            ("ai.queue_weights.critical", weights.critical),
+            ("ai.queue_weights.high", weights.high),
+            ("ai.queue_weights.normal", weights.normal),
+            ("ai.queue_weights.low", weights.low),
+            ("ai.queue_weights.background", weights.background),
+        ] {
+            check_range(&mut errors, field, weight, 1, 1000);
+        }
+        check_range(&mut errors, "ai.pool.max_connections", self.ai.pool.max_connections, 1, 256);
+        check_range(&mut errors, "ai.pool.min_connections", self.ai.pool.min_connections, 1, self.ai.pool.max_connections.max(1));
+        check_range(&mut errors, "ai.pool.target_latency_ms", self.ai.pool.target_latency_ms, 1, 600_000);
+        check_range(&mut errors, "ai.embeddings.batch_size", self.ai.embeddings.batch_size(), 1, 2048);
+
+        check_range(&mut errors, "terminal.font_size", self.terminal.font_size, 6, 72);
+        check_range(&mut errors, "terminal.scroll_back", self.terminal.scroll_back, 0, 1_000_000);
+        check_range(&mut errors, "terminal.history_size", self.terminal.history_size, 1, 100_000);
+        check_range(&mut errors, "terminal.output.frame_interval_ms", self.terminal.output.frame_interval_ms, 1, 1000);
+        check_range(&mut errors, "terminal.output.max_frame_bytes", self.terminal.output.max_frame_bytes, 1024, 16 * 1024 * 1024);
+        check_range(&mut errors, "terminal.output.buffer_bytes", self.terminal.output.buffer_bytes, self.terminal.output.max_frame_bytes, 256 * 1024 * 1024);
+        check_range(&mut errors, "appearance.opacity", self.appearance.opacity, 0.1, 1.0);
+
+        check_directory(&mut errors, "paths.temp_dir", &self.paths.temp_dir);
+        check_directory(&mut errors, "paths.cache_dir", &self.paths.cache_dir);
+        check_directory(&mut errors, "paths.data_dir", &self.paths.data_dir);
+        check_directory(&mut errors, "paths.log_dir", &self.paths.log_dir);
+
+        let policy = &self.command_policy;
+        for (list, patterns) in [("denied_patterns", &policy.denied_patterns), ("shell_syntax_patterns", &policy.shell_syntax_patterns)] {
+            for (index, denied) in patterns.iter().enumerate() {
+                if let Err(e) = regex::Regex::new(&denied.pattern) {
+                    errors.push(ConfigError::new(&format!("command_policy.{}[{}]", list, index), format!("is not a valid regex: {}", e)));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Write the config to `config_path`, refusing to replace a file from a newer schema version
+    pub fn save_to(&self, config_path: &Path) -> Result<()> {
+        if let Ok(existing) = std::fs::read_to_string(config_path) {
+            if let Some(version) = toml::from_str::<toml::Table>(&existing).ok().and_then(|doc| document_version(&doc).ok()) {
+                if version > CONFIG_VERSION {
+                    return Err(Self::newer_version_error(config_path, version));
+                }
+            }
+        }
+
         let content = toml::to_string_pretty(self)
             .context("Failed to serialize config")?;
         
-        std::fs::write(&config_path, content)
+        std::fs::write(config_path, content)
             .context("Failed to write config file")?;
         
         Ok(())
     }
+
+    /// Move a plaintext `ai.api_key` into `store` under the profile's name, leaving a `secret://`
+    /// reference behind. Returns whether the config changed and needs saving.
+    pub fn migrate_secrets(&mut self, store: &SecretStore, profile: &str) -> Result<bool> {
+        match self.ai.api_key.as_deref() {
+            Some(key) if !is_secret_ref(key) => {
+                let reference = store.store(&format!("ai/{}/api_key", profile), key)?;
+                self.ai.api_key = Some(reference);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn newer_version_error(config_path: &Path, version: u32) -> anyhow::Error {
+        anyhow!(
+            "Config file {} uses schema version {}, but this build only understands up to version {}. \
+             It was left untouched; update Nexus Terminal to use it.",
+            config_path.display(),
+            version,
+            CONFIG_VERSION
+        )
+    }
     
     /// Ensure all configured directories exist
     pub fn ensure_directories(&self) -> Result<()> {
@@ -203,6 +486,11 @@ impl AppConfig {
         self.paths.cache_dir.join(name)
     }
     
+    /// Directory holding the per-shell command history files
+    pub fn command_history_dir() -> Result<PathBuf> {
+        Ok(Self::config_dir()?.join("history"))
+    }
+
     /// Get the AI models cache directory
     pub fn ai_models_cache_dir(&self) -> PathBuf {
         self.cache_file_path("ai_models")
@@ -221,3 +509,397 @@ impl AppConfig {
         Ok(temp_dir.join(filename))
     }
 }
+
+/// Profile backed by `config.toml` itself, so configs from before profiles keep working
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Named configurations stored next to the main config. The default profile lives in
+/// `config.toml`; every other profile is `profiles/<name>.toml`, and `active_profile`
+/// records which one `AppConfig::load` reads.
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    config_dir: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(config_dir: PathBuf) -> Self {
+        Self { config_dir }
+    }
+
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(AppConfig::config_dir()?))
+    }
+
+    fn profiles_dir(&self) -> PathBuf {
+        self.config_dir.join("profiles")
+    }
+
+    fn active_marker(&self) -> PathBuf {
+        self.config_dir.join("active_profile")
+    }
+
+    pub fn profile_path(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_PROFILE {
+            self.config_dir.join("config.toml")
+        } else {
+            self.profiles_dir().join(format!("{}.toml", name))
+        }
+    }
+
+    fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid profile name '{}': use letters, digits, '-' or '_'", name))
+        }
+    }
+
+    fn existing_profile_path(&self, name: &str) -> Result<PathBuf> {
+        Self::validate_name(name)?;
+        let path = self.profile_path(name);
+        if name != DEFAULT_PROFILE && !path.exists() {
+            return Err(anyhow!("Profile '{}' does not exist", name));
+        }
+        Ok(path)
+    }
+
+    /// Name of the active profile; falls back to the default profile if the marker is missing or stale
+    pub fn active_profile(&self) -> String {
+        std::fs::read_to_string(self.active_marker())
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| Self::validate_name(name).is_ok() && self.profile_path(name).exists())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    pub fn list_profiles(&self) -> Result<Vec<String>> {
+        let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+        if let Ok(entries) = std::fs::read_dir(self.profiles_dir()) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                        if name != DEFAULT_PROFILE && Self::validate_name(name).is_ok() {
+                            profiles.push(name.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        profiles[1..].sort();
+        Ok(profiles)
+    }
+
+    /// Create a profile from a copy of `base_on`, or from defaults
+    pub fn create_profile(&self, name: &str, base_on: Option<&str>) -> Result<AppConfig> {
+        Self::validate_name(name)?;
+        let path = self.profile_path(name);
+        if name == DEFAULT_PROFILE || path.exists() {
+            return Err(anyhow!("Profile '{}' already exists", name));
+        }
+
+        let config = match base_on {
+            Some(base) => self.load_profile(base)?,
+            None => AppConfig::default(),
+        };
+        std::fs::create_dir_all(self.profiles_dir()).context("Failed to create profiles directory")?;
+        config.save_to(&path)?;
+        Ok(config)
+    }
+
+    /// Read a profile's config without making it active
+    pub fn load_profile(&self, name: &str) -> Result<AppConfig> {
+        AppConfig::load_from(&self.existing_profile_path(name)?)
+    }
+
+    pub fn set_active_profile(&self, name: &str) -> Result<()> {
+        self.existing_profile_path(name)?;
+        // Write then rename so a crash never leaves a half-written marker
+        let staging = self.config_dir.join("active_profile.tmp");
+        std::fs::write(&staging, name).context("Failed to record active profile")?;
+        std::fs::rename(&staging, self.active_marker()).context("Failed to record active profile")?;
+        Ok(())
+    }
+
+    pub fn delete_profile(&self, name: &str) -> Result<()> {
+        let path = self.existing_profile_path(name)?;
+        if name == DEFAULT_PROFILE {
+            return Err(anyhow!("The default profile cannot be deleted"));
+        }
+        if name == self.active_profile() {
+            return Err(anyhow!("Profile '{}' is active; switch to another profile before deleting it", name));
+        }
+        std::fs::remove_file(path).with_context(|| format!("Failed to delete profile '{}'", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_CONFIG: &str = r#"
+[ai]
+ollama_url = "http://gpu-box:11434"
+default_model = "codellama"
+timeout_seconds = 90
+temperature = 0.2
+max_tokens = 4096
+
+[terminal]
+font_family = "Fira Code"
+font_size = 18
+cursor_blink = false
+cursor_style = "bar"
+scroll_back = 50000
+
+[appearance]
+theme = "solarized"
+opacity = 0.8
+blur_background = false
+show_tabs = true
+show_title_bar = true
+
+[shortcuts]
+new_terminal = "Ctrl+T"
+close_terminal = "Ctrl+W"
+copy = "Ctrl+C"
+paste = "Ctrl+V"
+find = "Ctrl+F"
+ai_chat = "Ctrl+Space"
+command_palette = "Ctrl+K"
+
+[paths]
+temp_dir = "/tmp/nexus"
+cache_dir = "/home/me/.cache/nexus"
+data_dir = "/home/me/.local/share/nexus"
+log_dir = "/home/me/.local/state/nexus"
+
+[vision]
+ocr_engine = "easyocr"
+vision_model = "llava:13b"
+enabled = false
+"#;
+
+    #[test]
+    fn test_v1_config_is_migrated_and_backed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, V1_CONFIG).unwrap();
+
+        let config = AppConfig::load_from(&path).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.ai.ollama_url, "http://gpu-box:11434");
+        assert_eq!(config.ai.max_tokens, 4096);
+        assert_eq!(config.terminal.font_family, "Fira Code");
+        assert_eq!(config.terminal.font_size, 18);
+        assert_eq!(config.appearance.theme, "solarized");
+        assert_eq!(config.shortcuts.command_palette, "Ctrl+K");
+        assert_eq!(config.paths.data_dir, PathBuf::from("/home/me/.local/share/nexus"));
+        assert_eq!(config.vision.ocr_engine, "tesseract");
+        assert_eq!(config.vision.vision_model, "llava:13b");
+        assert!(!config.vision.enabled);
+
+        let backup = dir.path().join("config.toml.v1.bak");
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), V1_CONFIG);
+        let rewritten = AppConfig::load_from(&path).unwrap();
+        assert_eq!(rewritten.terminal.font_size, 18);
+        assert!(std::fs::read_to_string(&path).unwrap().contains(&format!("version = {}", CONFIG_VERSION)));
+    }
+
+    #[test]
+    fn test_v2_shell_syntax_rules_move_out_of_the_denylist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut document = toml::Table::try_from(AppConfig::default()).unwrap();
+        document.insert("version".to_string(), toml::Value::Integer(2));
+        let policy = document["command_policy"].as_table_mut().unwrap();
+        let mut denied = policy.remove("shell_syntax_patterns").unwrap().as_array().unwrap().clone();
+        denied.extend(policy["denied_patterns"].as_array().unwrap().iter().cloned());
+        denied.push(toml::Value::Table(toml::from_str("name = \"no-curl\"\npattern = \"curl\"").unwrap()));
+        policy.insert("denied_patterns".to_string(), toml::Value::Array(denied));
+        std::fs::write(&path, toml::to_string(&document).unwrap()).unwrap();
+
+        let config = AppConfig::load_from(&path).unwrap();
+        let names = |patterns: &[crate::utils::DeniedPattern]| patterns.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&config.command_policy.shell_syntax_patterns), ["command-chaining", "command-substitution", "redirection"]);
+        assert_eq!(names(&config.command_policy.denied_patterns), ["recursive-delete-root", "pipe-to-shell", "privilege-escalation", "no-curl"]);
+        assert!(config.command_policy.check_denied("cargo build && cargo test").is_ok());
+        assert!(config.command_policy.check_denied("curl example.com").is_err());
+    }
+
+    #[test]
+    fn test_version_zero_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, format!("version = 0\n{}", V1_CONFIG)).unwrap();
+
+        let error = AppConfig::load_from(&path).unwrap_err();
+        assert!(error.to_string().contains("positive integer"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("version = 0\n{}", V1_CONFIG));
+    }
+
+    fn valid_config(dir: &Path) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.ai.ollama_url = "http://127.0.0.1:11434".to_string();
+        config.paths = PathsConfig {
+            temp_dir: dir.join("temp"),
+            cache_dir: dir.join("cache"),
+            data_dir: dir.join("data/nested"),
+            log_dir: dir.to_path_buf(),
+        };
+        config
+    }
+
+    fn invalid_fields(config: &AppConfig) -> Vec<String> {
+        config.validate().err().unwrap_or_default().into_iter().map(|error| error.field).collect()
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(valid_config(dir.path()).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_ai_url_must_be_well_formed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = valid_config(dir.path());
+        for good in ["https://ollama.internal", "http://[::1]:8080", "http://gpu-box:65535/"] {
+            config.ai.ollama_url = good.to_string();
+            assert!(invalid_fields(&config).is_empty(), "{}", good);
+        }
+        for bad in ["localhost:11434", "ftp://host:21", "http://host:70000", "http://host:0", "not a url"] {
+            config.ai.ollama_url = bad.to_string();
+            assert_eq!(invalid_fields(&config), vec!["ai.ollama_url"], "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_model_name_must_not_be_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = valid_config(dir.path());
+        config.ai.default_model = "llama3:8b".to_string();
+        assert!(invalid_fields(&config).is_empty());
+        config.ai.default_model = "  ".to_string();
+        assert_eq!(invalid_fields(&config), vec!["ai.default_model"]);
+    }
+
+    #[test]
+    fn test_numeric_limits_are_enforced() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = valid_config(dir.path());
+        config.ai.timeout_seconds = 3600;
+        config.ai.temperature = 0.0;
+        config.terminal.font_size = 6;
+        config.appearance.opacity = 1.0;
+        assert!(invalid_fields(&config).is_empty());
+
+        config.ai.timeout_seconds = 0;
+        config.ai.temperature = 2.5;
+        config.ai.max_tokens = 0;
+        config.ai.circuit_failure_threshold = 0;
+        config.ai.circuit_cooldown_seconds = 86_400;
+        config.terminal.font_size = 200;
+        config.terminal.scroll_back = 5_000_000;
+        config.appearance.opacity = 0.0;
+        assert_eq!(invalid_fields(&config), vec![
+            "ai.timeout_seconds",
+            "ai.temperature",
+            "ai.max_tokens",
+            "ai.circuit_failure_threshold",
+            "ai.circuit_cooldown_seconds",
+            "terminal.font_size",
+            "terminal.scroll_back",
+            "appearance.opacity",
+        ]);
+    }
+
+    #[test]
+    fn test_directories_must_be_creatable() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("not-a-dir");
+        std::fs::write(&file, "").unwrap();
+
+        let mut config = valid_config(dir.path());
+        config.paths.cache_dir = file.join("cache");
+        config.paths.log_dir = PathBuf::new();
+        assert_eq!(invalid_fields(&config), vec!["paths.cache_dir", "paths.log_dir"]);
+    }
+
+    #[test]
+    fn test_create_switch_and_delete_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = ProfileStore::new(dir.path().to_path_buf());
+        let mut personal = AppConfig::default();
+        personal.appearance.theme = "light".to_string();
+        personal.ai.default_model = "mistral".to_string();
+        personal.save_to(&profiles.profile_path(DEFAULT_PROFILE)).unwrap();
+
+        let work = profiles.create_profile("work", None).unwrap();
+        assert_eq!(work.appearance.theme, AppConfig::default().appearance.theme);
+        let mut work = profiles.load_profile("work").unwrap();
+        work.ai.default_model = "codellama:34b".to_string();
+        work.save_to(&profiles.profile_path("work")).unwrap();
+        let copy = profiles.create_profile("work-copy", Some("work")).unwrap();
+        assert_eq!(copy.ai.default_model, "codellama:34b");
+        assert!(profiles.create_profile("work", None).is_err());
+        assert!(profiles.create_profile("../escape", None).is_err());
+        assert_eq!(profiles.list_profiles().unwrap(), vec!["default", "work", "work-copy"]);
+
+        assert_eq!(profiles.active_profile(), DEFAULT_PROFILE);
+        profiles.set_active_profile("work").unwrap();
+        assert_eq!(profiles.active_profile(), "work");
+        assert_eq!(profiles.load_profile(&profiles.active_profile()).unwrap().ai.default_model, "codellama:34b");
+
+        let error = profiles.delete_profile("work").unwrap_err();
+        assert!(error.to_string().contains("switch to another profile"));
+        assert!(profiles.delete_profile(DEFAULT_PROFILE).is_err());
+        assert!(profiles.set_active_profile("missing").is_err());
+
+        profiles.set_active_profile(DEFAULT_PROFILE).unwrap();
+        let active = profiles.load_profile(&profiles.active_profile()).unwrap();
+        assert_eq!(active.appearance.theme, "light");
+        assert_eq!(active.ai.default_model, "mistral");
+        profiles.delete_profile("work").unwrap();
+        assert_eq!(profiles.list_profiles().unwrap(), vec!["default", "work-copy"]);
+    }
+
+    #[test]
+    fn test_newer_config_is_rejected_and_left_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let future = format!("version = {}\n{}", CONFIG_VERSION + 1, V1_CONFIG);
+        std::fs::write(&path, &future).unwrap();
+
+        let error = AppConfig::load_from(&path).unwrap_err();
+        assert!(error.to_string().contains("only understands up to version"));
+        assert!(AppConfig::default().save_to(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), future);
+    }
+
+    #[test]
+    fn test_plaintext_api_key_moves_to_the_secret_store() {
+        use crate::secret_store::tests::MockKeyring;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config = AppConfig::default();
+        config.ai.api_key = Some("sk-plaintext".to_string());
+        let error = config.save_to(&path).unwrap_err();
+        assert!(format!("{:#}", error).contains("not moved to the secret store"), "{:#}", error);
+
+        let store = SecretStore::with_backend(Box::new(MockKeyring::default()));
+        assert!(config.migrate_secrets(&store, "work").unwrap());
+        assert_eq!(config.ai.api_key.as_deref(), Some("secret://ai/work/api_key"));
+        assert!(!config.migrate_secrets(&store, "work").unwrap());
+        assert_eq!(store.get_secret("ai/work/api_key").unwrap().as_deref(), Some("sk-plaintext"));
+
+        config.save_to(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("secret://ai/work/api_key") && !saved.contains("sk-plaintext"));
+        assert_eq!(AppConfig::load_from(&path).unwrap().ai.api_key, config.ai.api_key);
+    }
+}
@@ -3,7 +3,21 @@ use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use chrono::{DateTime, Utc, Timelike, Duration};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use redb::{Database, ReadableTable, TableDefinition};
+use std::path::{Path, PathBuf};
+
+/// Gap between the two `/proc` samples used to compute per-process CPU usage
+const PROCESS_SAMPLE_INTERVAL_MS: u64 = 250;
+
+/// Retention limits shared by the in-memory learning database and its redb store
+const MAX_COMMAND_EXECUTIONS: usize = 10000;
+const MAX_CONTEXT_SNAPSHOTS: usize = 5000;
+const MAX_USER_INTERACTIONS: usize = 10000;
+
+const COMMAND_EXECUTIONS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("command_executions");
+const CONTEXT_SNAPSHOTS_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("context_snapshots");
+const LEARNING_PATTERNS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("learning_patterns");
 // Removed unused imports
 
 // Basic type definitions for missing structs
@@ -32,6 +46,8 @@ pub struct ProcessCrash {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
     pub pid: u32,
+    #[serde(default)]
+    pub ppid: u32,
     pub name: String,
     pub state: String,
     pub cpu_percent: f64,
@@ -620,6 +636,8 @@ pub struct ContextPattern {
     pub conditions: HashMap<String, String>,
     pub associated_commands: Vec<String>,
     pub success_rate: f64,
+    #[serde(default)]
+    pub executions: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -628,6 +646,9 @@ pub struct WorkflowPattern {
     pub steps: Vec<String>,
     pub success_rate: f64,
     pub context_requirements: Vec<String>,
+    /// Times the whole sequence was seen
+    #[serde(default)]
+    pub support: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -865,15 +886,19 @@ pub struct AdaptiveLearningEngine {
     behavior_predictor: BehaviorPredictor,
     context_correlator: ContextCorrelator,
     learning_database: Arc<RwLock<LearningDatabase>>,
+    store: Option<LearningStore>,
 }
 
 #[derive(Debug)]
 pub struct PatternRecognizer {
+    /// Counts per sequence of base commands, keyed by the steps joined with `SEQUENCE_SEPARATOR`
     command_patterns: HashMap<String, CommandPatternData>,
     temporal_patterns: Vec<TemporalPattern>,
     context_patterns: Vec<ContextPattern>,
     error_patterns: Vec<ErrorPattern>,
     workflow_patterns: Vec<WorkflowPattern>,
+    /// The last `SEQUENCE_LENGTH` base commands
+    recent_commands: VecDeque<String>,
 }
 
 #[derive(Debug)]
@@ -902,6 +927,15 @@ pub struct LearningDatabase {
 }
 
 // Implementation
+impl Drop for EcosystemAwareness {
+    fn drop(&mut self) {
+        self.stop_monitoring();
+        if let Some(batcher) = self.learning_batcher.take() {
+            batcher.task.abort();
+        }
+    }
+}
+
 impl Default for EcosystemAwareness {
     fn default() -> Self {
         Self {
@@ -911,9 +945,12 @@ impl Default for EcosystemAwareness {
                 behavior_predictor: BehaviorPredictor::new(),
                 context_correlator: ContextCorrelator::new(),
                 learning_database: Arc::new(RwLock::new(LearningDatabase::new())),
+                store: None,
             })),
             monitoring_tasks: Vec::new(),
+            monitoring_intervals: MonitoringIntervals::default(),
             adaptation_engine: AdaptationEngine::new(),
+            learning_batcher: None,
         }
     }
 }
@@ -927,7 +964,95 @@ impl EcosystemAwareness {
             current_state: Arc::new(RwLock::new(state)),
             learning_engine: Arc::new(RwLock::new(learning_engine)),
             monitoring_tasks: Vec::new(),
+            monitoring_intervals: MonitoringIntervals::default(),
             adaptation_engine: AdaptationEngine::new(),
+            learning_batcher: None,
+        })
+    }
+
+    /// Persist learned data to a redb file at `path`, restoring whatever it already holds
+    pub async fn attach_learning_store(&self, path: &Path) -> Result<()> {
+        self.learning_engine.write().await.attach_store(path).await
+    }
+
+    /// Write the persisted learning data to `path` as JSON for inspection
+    pub async fn export_learning_data(&self, path: &Path) -> Result<()> {
+        let learning = self.learning_engine.read().await;
+        let db = learning.learning_database.read().await;
+        let export = serde_json::json!({
+            "command_executions": db.command_executions,
+            "context_snapshots": db.context_snapshots,
+            "learning_patterns": db.learning_patterns,
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&export)?)?;
+        Ok(())
+    }
+
+    pub fn with_monitoring_intervals(mut self, intervals: MonitoringIntervals) -> Self {
+        self.monitoring_intervals = intervals;
+        self
+    }
+
+    pub fn is_monitoring(&self) -> bool {
+        !self.monitoring_tasks.is_empty()
+    }
+
+    /// Spawn the background tasks that keep `current_state` fresh. Calling this while
+    /// monitoring is already running is a no-op.
+    pub fn start_monitoring(&mut self) {
+        if self.is_monitoring() {
+            return;
+        }
+
+        let intervals = self.monitoring_intervals;
+        self.monitoring_tasks = vec![
+            Self::spawn_refresh(self.current_state.clone(), intervals.performance, "performance", |state| async move {
+                let performance = EcosystemState::collect_performance_state().await?;
+                state.write().await.performance = performance;
+                Ok(())
+            }),
+            Self::spawn_refresh(self.current_state.clone(), intervals.processes, "process", |state| async move {
+                let processes = EcosystemState::collect_process_state().await?;
+                state.write().await.processes = processes;
+                Ok(())
+            }),
+            Self::spawn_refresh(self.current_state.clone(), intervals.network, "network", |state| async move {
+                let network = EcosystemState::collect_network_state().await?;
+                state.write().await.network = network;
+                Ok(())
+            }),
+        ];
+    }
+
+    /// Cancel all background refresh tasks
+    pub fn stop_monitoring(&mut self) {
+        for task in self.monitoring_tasks.drain(..) {
+            task.abort();
+        }
+    }
+
+    /// Run `refresh` every `period`. Each collection is awaited before the next tick is taken and
+    /// ticks missed while it ran are skipped, so a slow collection never overlaps the next one.
+    fn spawn_refresh<F, Fut>(
+        state: Arc<RwLock<EcosystemState>>,
+        period: std::time::Duration,
+        name: &'static str,
+        refresh: F,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn(Arc<RwLock<EcosystemState>>) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = Result<()>> + Send,
+    {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                match refresh(state.clone()).await {
+                    Ok(()) => state.write().await.timestamp = Utc::now(),
+                    Err(e) => eprintln!("Warning: Failed to refresh {} state: {}", name, e),
+                }
+            }
         })
     }
 
@@ -960,6 +1085,96 @@ impl EcosystemAwareness {
         Ok(())
     }
 
+    /// Buffer interactions from `queue_interaction` and write them in batches every
+    /// `config.flush_interval`, or as soon as `config.max_batch` are waiting. Calling this
+    /// while the batcher is already running is a no-op.
+    pub fn start_learning_batcher(&mut self, config: LearningBatchConfig) {
+        if self.learning_batcher.is_some() {
+            return;
+        }
+
+        let pending = Arc::new(std::sync::Mutex::new(PendingInteractions::default()));
+        let full = Arc::new(tokio::sync::Notify::new());
+        let task = tokio::spawn({
+            let pending = pending.clone();
+            let full = full.clone();
+            let learning_engine = self.learning_engine.clone();
+            let current_state = self.current_state.clone();
+            async move {
+                let mut ticker = tokio::time::interval(config.flush_interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    tokio::select! {
+                        _ = ticker.tick() => {}
+                        _ = full.notified() => {}
+                    }
+                    flush_pending(&pending, config.max_batch, &learning_engine, &current_state).await;
+                }
+            }
+        });
+        self.learning_batcher = Some(LearningBatcher { config, pending, full, task });
+    }
+
+    /// Record a command for learning without waiting for it to be written. A repeat of the
+    /// previous command with the same outcome is coalesced into it.
+    pub fn queue_interaction(&self, interaction: UserInteraction) {
+        let Some(batcher) = &self.learning_batcher else {
+            // No batcher running: write this one alone, still off the caller's path
+            let learning_engine = self.learning_engine.clone();
+            let current_state = self.current_state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = learn_batch(&learning_engine, &current_state, vec![interaction]).await {
+                    tracing::error!("Failed to record command for learning: {}", e);
+                }
+            });
+            return;
+        };
+
+        let Ok(mut pending) = batcher.pending.lock() else {
+            return;
+        };
+        let key = (interaction.command.clone(), interaction.success);
+        if pending.last.as_ref() == Some(&key) {
+            pending.stats.coalesced += 1;
+            return;
+        }
+        pending.last = Some(key);
+        pending.stats.queued += 1;
+        pending.buffer.push(interaction);
+        if pending.buffer.len() >= batcher.config.max_batch {
+            batcher.full.notify_one();
+        }
+    }
+
+    /// Write whatever the batcher is holding now instead of waiting for the next flush
+    pub async fn flush_learning(&self) {
+        if let Some(batcher) = &self.learning_batcher {
+            flush_pending(&batcher.pending, batcher.config.max_batch, &self.learning_engine, &self.current_state).await;
+        }
+    }
+
+    pub fn learning_batch_stats(&self) -> LearningBatchStats {
+        self.learning_batcher
+            .as_ref()
+            .and_then(|batcher| batcher.pending.lock().ok().map(|pending| pending.stats))
+            .unwrap_or_default()
+    }
+
+    /// Learned per-command frequency and success patterns, most used first
+    pub async fn command_patterns(&self) -> Vec<LearningPattern> {
+        self.flush_learning().await;
+        let learning = self.learning_engine.read().await;
+        let db = learning.learning_database.read().await;
+        let mut patterns: Vec<LearningPattern> = db
+            .learning_patterns
+            .values()
+            .filter(|pattern| pattern.pattern_type == "command_frequency")
+            .cloned()
+            .collect();
+        patterns.sort_by(|a, b| b.occurrences.cmp(&a.occurrences).then_with(|| a.pattern_id.cmp(&b.pattern_id)));
+        patterns
+    }
+
     pub async fn predict_user_intent(&self, partial_input: &str) -> Result<Vec<IntentPrediction>> {
         let learning = self.learning_engine.read().await;
         let current_state = self.current_state.read().await;
@@ -984,6 +1199,9 @@ impl EcosystemAwareness {
             error_patterns: learning.analyze_error_patterns(&current_state).await?,
             optimization_opportunities: learning.identify_optimizations(&current_state).await?,
             predictive_maintenance: learning.predict_maintenance_needs(&current_state).await?,
+            command_sequences: learning.pattern_recognizer.command_sequences(),
+            workflow_patterns: learning.pattern_recognizer.workflows().to_vec(),
+            context_patterns: learning.pattern_recognizer.contexts().to_vec(),
         })
     }
 
@@ -1089,6 +1307,13 @@ impl EcosystemAwareness {
             
         Ok(*correlation)
     }
+
+    /// Rank likely recovery commands for a failed command, combining what the
+    /// user historically ran after the same failure with the error pattern KB
+    pub async fn suggest_recovery(&self, failed_command: &str, error_output: &str) -> Result<Vec<RecoverySuggestion>> {
+        let learning = self.learning_engine.read().await;
+        learning.suggest_recovery(failed_command, error_output).await
+    }
     
     // Helper method for analyzing command frequencies
     async fn analyze_command_frequencies(&self, executions: &VecDeque<CommandExecution>) -> HashMap<String, usize> {
@@ -1393,6 +1618,209 @@ impl Default for EcosystemState {
     }
 }
 
+/// One process as read from `/proc/<pid>/stat` and `/proc/<pid>/status`
+#[derive(Debug, Clone)]
+struct ProcSample {
+    pid: u32,
+    ppid: u32,
+    name: String,
+    state: String,
+    tty_nr: i32,
+    cpu_ticks: u64,
+    memory_usage: u64,
+}
+
+/// All processes plus the total CPU jiffies at the moment they were read
+#[derive(Debug, Clone)]
+struct ProcSnapshot {
+    total_jiffies: u64,
+    processes: Vec<ProcSample>,
+}
+
+/// Read every numeric entry under a `/proc`-style `root`. Processes that exit mid-scan are skipped.
+fn read_proc_snapshot(root: &Path) -> Result<ProcSnapshot> {
+    let stat = std::fs::read_to_string(root.join("stat"))?;
+    let total_jiffies = stat
+        .lines()
+        .find(|line| line.starts_with("cpu "))
+        .ok_or_else(|| anyhow!("No aggregate cpu line in {}", root.join("stat").display()))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|field| field.parse::<u64>().ok())
+        .sum();
+
+    let mut processes = Vec::new();
+    for entry in std::fs::read_dir(root)?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let (Ok(stat), Ok(status)) = (
+            std::fs::read_to_string(entry.path().join("stat")),
+            std::fs::read_to_string(entry.path().join("status")),
+        ) else {
+            continue;
+        };
+        if let Some(sample) = parse_proc_sample(pid, &stat, &status) {
+            processes.push(sample);
+        }
+    }
+    processes.sort_by_key(|p| p.pid);
+
+    Ok(ProcSnapshot { total_jiffies, processes })
+}
+
+fn parse_proc_sample(pid: u32, stat: &str, status: &str) -> Option<ProcSample> {
+    // The command name is wrapped in parentheses and may itself contain spaces or ')'
+    let name_start = stat.find('(')?;
+    let name_end = stat.rfind(')')?;
+    let fields: Vec<&str> = stat.get(name_end + 1..)?.split_whitespace().collect();
+    // Fields after the name start at `state` (field 3 in proc(5))
+    let field = |n: usize| fields.get(n - 3).copied();
+
+    let status_value = |key: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .map(|value| value.trim().to_string())
+    };
+    let name = status_value("Name:").unwrap_or_else(|| stat[name_start + 1..name_end].to_string());
+    // VmRSS is absent for kernel threads
+    let memory_usage = status_value("VmRSS:")
+        .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+        .unwrap_or(0);
+
+    let utime: u64 = field(14)?.parse().ok()?;
+    let stime: u64 = field(15)?.parse().ok()?;
+
+    Some(ProcSample {
+        pid,
+        ppid: field(4)?.parse().ok()?,
+        name,
+        state: field(3)?.to_string(),
+        tty_nr: field(7)?.parse().ok()?,
+        cpu_ticks: utime + stime,
+        memory_usage,
+    })
+}
+
+/// Build `ProcessInfo`s from the later snapshot, with CPU usage as each process's share of the
+/// jiffies that elapsed between the two
+fn processes_between(before: &ProcSnapshot, after: &ProcSnapshot) -> Vec<ProcessInfo> {
+    let previous_ticks: HashMap<u32, u64> = before.processes.iter().map(|p| (p.pid, p.cpu_ticks)).collect();
+    let elapsed = after.total_jiffies.saturating_sub(before.total_jiffies);
+
+    after
+        .processes
+        .iter()
+        .map(|sample| {
+            let cpu_percent = match previous_ticks.get(&sample.pid) {
+                Some(&previous) if elapsed > 0 => sample.cpu_ticks.saturating_sub(previous) as f64 / elapsed as f64 * 100.0,
+                _ => 0.0,
+            };
+            ProcessInfo {
+                pid: sample.pid,
+                ppid: sample.ppid,
+                name: sample.name.clone(),
+                state: sample.state.clone(),
+                cpu_percent,
+                memory_usage: sample.memory_usage,
+                is_daemon: sample.tty_nr == 0 && sample.ppid == 1,
+            }
+        })
+        .collect()
+}
+
+/// Where process listings come from; tests substitute a fixed list
+#[async_trait::async_trait]
+pub trait ProcessSource: Send + Sync {
+    async fn processes(&self) -> Result<Vec<ProcessInfo>>;
+}
+
+/// Reads a `/proc`-style directory twice, `PROCESS_SAMPLE_INTERVAL_MS` apart, to measure CPU usage
+pub struct ProcfsSource {
+    root: PathBuf,
+}
+
+impl ProcfsSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl Default for ProcfsSource {
+    fn default() -> Self {
+        Self::new("/proc")
+    }
+}
+
+#[async_trait::async_trait]
+impl ProcessSource for ProcfsSource {
+    async fn processes(&self) -> Result<Vec<ProcessInfo>> {
+        if !self.root.join("stat").exists() {
+            tracing::warn!("{} is unavailable; process listing is only supported on Linux", self.root.display());
+            return Ok(vec![]);
+        }
+
+        let before = read_proc_snapshot(&self.root)?;
+        tokio::time::sleep(std::time::Duration::from_millis(PROCESS_SAMPLE_INTERVAL_MS)).await;
+        let after = read_proc_snapshot(&self.root)?;
+        Ok(processes_between(&before, &after))
+    }
+}
+
+/// Kernel threads are children of `kthreadd` (pid 2)
+fn is_kernel_thread(process: &ProcessInfo) -> bool {
+    process.pid == 2 || process.ppid == 2
+}
+
+/// Processes worth showing the AI: everything running under the app's terminal shells, then the
+/// top CPU and memory consumers, at most `limit` in total
+pub fn select_active_processes(processes: Vec<ProcessInfo>, terminal_pids: &[u32], limit: usize) -> Vec<ProcessInfo> {
+    let processes: Vec<ProcessInfo> = processes.into_iter().filter(|p| !is_kernel_thread(p)).collect();
+
+    let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, process) in processes.iter().enumerate() {
+        children.entry(process.ppid).or_default().push(index);
+    }
+    let mut from_terminals = Vec::new();
+    let mut stack: Vec<u32> = terminal_pids.to_vec();
+    while let Some(pid) = stack.pop() {
+        for &index in children.get(&pid).into_iter().flatten() {
+            from_terminals.push(index);
+            stack.push(processes[index].pid);
+        }
+    }
+    from_terminals.sort_by_key(|&index| processes[index].pid);
+
+    let mut by_cpu: Vec<usize> = (0..processes.len()).collect();
+    by_cpu.sort_by(|&a, &b| processes[b].cpu_percent.total_cmp(&processes[a].cpu_percent));
+    let mut by_memory: Vec<usize> = (0..processes.len()).collect();
+    by_memory.sort_by_key(|&index| std::cmp::Reverse(processes[index].memory_usage));
+
+    // Alternate CPU and memory leaders so neither list crowds out the other
+    let leaders = by_cpu.into_iter().zip(by_memory).flat_map(|(cpu, memory)| [cpu, memory]);
+    let mut seen = std::collections::HashSet::new();
+    from_terminals
+        .into_iter()
+        .chain(leaders)
+        .filter(|&index| seen.insert(index))
+        .take(limit)
+        .map(|index| processes[index].clone())
+        .collect()
+}
+
+/// Snapshot for the AI context; failures are logged and yield an empty list
+pub async fn active_processes(source: &dyn ProcessSource, terminal_pids: &[u32], limit: usize) -> Vec<ProcessInfo> {
+    match source.processes().await {
+        Ok(processes) => select_active_processes(processes, terminal_pids, limit),
+        Err(e) => {
+            tracing::warn!("Failed to list processes: {}", e);
+            vec![]
+        }
+    }
+}
+
 impl EcosystemState {
     pub async fn collect_initial_state() -> Result<Self> {
         tokio::try_join!(
@@ -1633,13 +2061,18 @@ impl EcosystemState {
     }
 
     async fn get_all_processes() -> Result<Vec<ProcessInfo>> {
-        // Placeholder implementation - would parse /proc/*/stat files
-        Ok(vec![])
+        ProcfsSource::default().processes().await
     }
 
-    async fn build_process_tree(_processes: &[ProcessInfo]) -> Result<HashMap<u32, Vec<u32>>> {
-        // Placeholder implementation - would build parent->children mapping
-        Ok(HashMap::new())
+    async fn build_process_tree(processes: &[ProcessInfo]) -> Result<HashMap<u32, Vec<u32>>> {
+        let mut tree: HashMap<u32, Vec<u32>> = HashMap::new();
+        for process in processes.iter().filter(|p| p.ppid != 0) {
+            tree.entry(process.ppid).or_default().push(process.pid);
+        }
+        for children in tree.values_mut() {
+            children.sort_unstable();
+        }
+        Ok(tree)
     }
 
     async fn get_recent_process_crashes() -> Result<Vec<ProcessCrash>> {
@@ -1858,23 +2291,71 @@ impl AdaptiveLearningEngine {
             behavior_predictor: BehaviorPredictor::new(),
             context_correlator: ContextCorrelator::new(),
             learning_database: Arc::new(RwLock::new(LearningDatabase::new())),
+            store: None,
         })
     }
 
+    /// Back the learning database with a redb file, loading the most recent rows it already holds
+    pub async fn attach_store(&mut self, path: &Path) -> Result<()> {
+        let store = LearningStore::open(path)?;
+        let mut db = self.learning_database.write().await;
+        db.command_executions = store.recent_command_executions(MAX_COMMAND_EXECUTIONS)?.into();
+        db.context_snapshots = store.recent_context_snapshots(MAX_CONTEXT_SNAPSHOTS)?.into();
+        db.learning_patterns = store.learning_patterns()?;
+        drop(db);
+        self.store = Some(store);
+        Ok(())
+    }
+
     pub async fn process_interaction(&mut self, interaction: UserInteraction, context: &EcosystemState) -> Result<()> {
-        // Store the interaction
-        self.learning_database.write().await.command_executions.push_back(CommandExecution {
-            command: interaction.command.clone(),
-            timestamp: Utc::now(),
-            success: interaction.success,
-            duration: interaction.execution_time,
-            error_message: if !interaction.success { Some("Command failed".to_string()) } else { None },
-        });
+        self.process_batch(vec![interaction], context).await
+    }
+
+    /// Learn from several interactions, persisting them all in one store transaction
+    pub async fn process_batch(&mut self, interactions: Vec<UserInteraction>, context: &EcosystemState) -> Result<()> {
+        // Store the interactions
+        let active_user = std::env::var("USER").unwrap_or_default();
+        {
+            let mut db = self.learning_database.write().await;
+            let rows: Vec<(CommandExecution, ContextSnapshot, LearningPattern)> = interactions
+                .iter()
+                .map(|interaction| {
+                    let execution = CommandExecution {
+                        command: interaction.command.clone(),
+                        timestamp: Utc::now(),
+                        success: interaction.success,
+                        duration: interaction.execution_time,
+                        error_message: if !interaction.success {
+                            Some(interaction.error_output.clone().unwrap_or_else(|| "Command failed".to_string()))
+                        } else {
+                            None
+                        },
+                    };
+                    let snapshot = ContextSnapshot {
+                        timestamp: execution.timestamp,
+                        state: context.clone(),
+                        active_user: active_user.clone(),
+                        session_id: interaction.user_context.clone(),
+                    };
+                    let pattern = db.record_command_pattern(&execution);
+                    (execution, snapshot, pattern)
+                })
+                .collect();
+            if let Some(store) = &self.store {
+                store.append(&rows)?;
+            }
+            for (execution, snapshot, _) in rows {
+                db.command_executions.push_back(execution);
+                db.context_snapshots.push_back(snapshot);
+            }
+        }
 
         // Update patterns
-        self.pattern_recognizer.process_command(&interaction.command, context).await?;
-        self.behavior_predictor.update_predictions(&interaction, context).await?;
-        self.context_correlator.update_correlations(&interaction, context).await?;
+        for interaction in &interactions {
+            self.pattern_recognizer.process_command(&interaction.command, interaction.success, context).await?;
+            self.behavior_predictor.update_predictions(interaction, context).await?;
+            self.context_correlator.update_correlations(interaction, context).await?;
+        }
 
         // Trim old data if necessary
         self.maintain_database_size().await?;
@@ -1988,9 +2469,28 @@ impl AdaptiveLearningEngine {
 pub struct EcosystemAwareness {
     current_state: Arc<RwLock<EcosystemState>>,
     learning_engine: Arc<RwLock<AdaptiveLearningEngine>>,
-    #[allow(dead_code)]
     monitoring_tasks: Vec<tokio::task::JoinHandle<()>>,
+    monitoring_intervals: MonitoringIntervals,
     adaptation_engine: AdaptationEngine,
+    learning_batcher: Option<LearningBatcher>,
+}
+
+/// How often each background monitoring task refreshes its part of the ecosystem state
+#[derive(Debug, Clone, Copy)]
+pub struct MonitoringIntervals {
+    pub performance: std::time::Duration,
+    pub processes: std::time::Duration,
+    pub network: std::time::Duration,
+}
+
+impl Default for MonitoringIntervals {
+    fn default() -> Self {
+        Self {
+            performance: std::time::Duration::from_secs(5),
+            processes: std::time::Duration::from_secs(15),
+            network: std::time::Duration::from_secs(30),
+        }
+    }
 }
 
 // Supporting structures for comprehensive ecosystem awareness
@@ -2004,6 +2504,129 @@ pub struct ComprehensiveContext {
     pub adaptation_recommendations: Vec<AdaptationRecommendation>,
 }
 
+/// Longest prompt text `ContextSummary::to_prompt` produces, about 500 tokens
+pub const CONTEXT_SUMMARY_MAX_CHARS: usize = 2000;
+/// Entries kept per section of a `ContextSummary`
+const CONTEXT_SUMMARY_ITEMS: usize = 5;
+/// Longest single entry, so one huge error message can't crowd out the rest
+const CONTEXT_SUMMARY_ITEM_CHARS: usize = 160;
+
+/// The parts of a `ComprehensiveContext` worth spending prompt tokens on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextSummary {
+    pub system: String,
+    pub top_processes: Vec<String>,
+    pub git_status: Vec<String>,
+    pub project_types: Vec<String>,
+    /// Failed commands and process crashes, most recent first
+    pub recent_errors: Vec<String>,
+    pub predicted_actions: Vec<String>,
+}
+
+impl ContextSummary {
+    pub fn from_context(context: &ComprehensiveContext) -> Self {
+        let state = &context.ecosystem_state;
+        let (load_1, load_5, load_15) = state.system.system_load;
+        let take = |items: Vec<String>| -> Vec<String> {
+            items.into_iter().take(CONTEXT_SUMMARY_ITEMS).map(|item| clip(&item, CONTEXT_SUMMARY_ITEM_CHARS)).collect()
+        };
+
+        let mut errors: Vec<(DateTime<Utc>, String)> = state
+            .user_context
+            .shell_history
+            .iter()
+            .filter(|execution| !execution.success)
+            .map(|execution| {
+                let message = execution.error_message.as_deref().map(|e| e.lines().next().unwrap_or_default()).unwrap_or("failed");
+                (execution.timestamp, format!("`{}`: {}", execution.command, message))
+            })
+            .chain(state.processes.recent_crashes.iter().map(|crash| {
+                (crash.crash_time, format!("{} (pid {}) crashed with {}", crash.name, crash.pid, crash.signal))
+            }))
+            .collect();
+        errors.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+
+        Self {
+            system: format!(
+                "{} {} ({}), load {:.2} {:.2} {:.2}",
+                state.system.distribution, state.system.kernel_version, state.system.architecture, load_1, load_5, load_15
+            )
+            .trim()
+            .to_string(),
+            top_processes: take(
+                state
+                    .processes
+                    .top_cpu_processes
+                    .iter()
+                    .map(|p| format!("{} (pid {}) {:.1}% CPU, {} MB", p.name, p.pid, p.cpu_percent, p.memory_usage / (1024 * 1024)))
+                    .collect(),
+            ),
+            git_status: take(
+                state
+                    .development
+                    .version_control
+                    .iter()
+                    .map(|vcs| {
+                        let status = if vcs.has_changes { "uncommitted changes" } else { "clean" };
+                        format!("{} on {}: {}", vcs.repository_path, vcs.branch, status)
+                    })
+                    .collect(),
+            ),
+            project_types: take(
+                state
+                    .development
+                    .active_projects
+                    .iter()
+                    .map(|project| format!("{} ({})", project.name, project.project_type))
+                    .collect(),
+            ),
+            recent_errors: take(errors.into_iter().map(|(_, error)| error).collect()),
+            predicted_actions: take(
+                context
+                    .predicted_actions
+                    .iter()
+                    .map(|prediction| format!("{} ({:.0}%)", prediction.action, prediction.confidence * 100.0))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Prompt text, never longer than `CONTEXT_SUMMARY_MAX_CHARS`; sections that don't fit are dropped
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = String::from("System context:\n");
+        let push = |prompt: &mut String, section: String| {
+            if prompt.chars().count() + section.chars().count() <= CONTEXT_SUMMARY_MAX_CHARS {
+                prompt.push_str(&section);
+            }
+        };
+        if !self.system.is_empty() {
+            push(&mut prompt, format!("System: {}\n", self.system));
+        }
+        for (title, items) in [
+            ("Top processes", &self.top_processes),
+            ("Git", &self.git_status),
+            ("Projects", &self.project_types),
+            ("Recent errors", &self.recent_errors),
+            ("Likely next actions", &self.predicted_actions),
+        ] {
+            if !items.is_empty() {
+                let lines: String = items.iter().map(|item| format!("- {}\n", item)).collect();
+                push(&mut prompt, format!("{}:\n{}", title, lines));
+            }
+        }
+        prompt
+    }
+}
+
+fn clip(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut clipped: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    clipped.push('…');
+    clipped
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionPrediction {
     pub action: String,
@@ -2060,6 +2683,14 @@ pub struct SystemPatternAnalysis {
     pub error_patterns: Vec<ErrorPattern>,
     pub optimization_opportunities: Vec<OptimizationOpportunity>,
     pub predictive_maintenance: Vec<MaintenanceRecommendation>,
+    /// Learned sequences of base commands, most frequent first
+    #[serde(default)]
+    pub command_sequences: Vec<CommandPatternData>,
+    #[serde(default)]
+    pub workflow_patterns: Vec<WorkflowPattern>,
+    /// Success rate of commands per context
+    #[serde(default)]
+    pub context_patterns: Vec<ContextPattern>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -2079,6 +2710,26 @@ pub struct AdaptiveSuggestion {
     pub personalization_factor: f64,
 }
 
+/// Where a recovery suggestion came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoverySource {
+    /// Commands the user historically ran after the same failure
+    History,
+    /// Built-in error pattern knowledge base
+    KnowledgeBase,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoverySuggestion {
+    pub command: String,
+    pub confidence: f64,
+    pub sources: Vec<RecoverySource>,
+    /// How many times this recovery followed the failure in history
+    pub occurrences: usize,
+    pub rationale: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PatternAnalysis {
     pub pattern_type: String,
@@ -2119,6 +2770,8 @@ pub struct UserInteraction {
     pub execution_time: u64,
     pub user_context: String,
     pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub error_output: Option<String>,
 }
 
 
@@ -2166,27 +2819,49 @@ impl PatternRecognizer {
             command_patterns: HashMap::new(),
             temporal_patterns: Vec::new(),
             context_patterns: Vec::new(),
-            error_patterns: Vec::new(),
+            error_patterns: default_error_patterns(),
             workflow_patterns: Vec::new(),
+            recent_commands: VecDeque::new(),
         }
     }
 
-    pub async fn process_command(&mut self, command: &str, context: &EcosystemState) -> Result<()> {
-        // Analyze command patterns and update internal state
-        let pattern_data = CommandPatternData {
-            pattern: command.to_string(),
-            frequency: 1,
-            success_rate: 1.0,
-            context_conditions: vec![format!("OS: {}", context.system.os)],
-        };
-        
-        // Update command patterns
-        if let Some(existing) = self.command_patterns.get_mut(command) {
-            existing.frequency += 1;
-        } else {
-            self.command_patterns.insert(command.to_string(), pattern_data);
+    /// Learn from one executed command: count the sequences of base commands it ends, promote
+    /// sequences seen `WORKFLOW_SUPPORT` times to workflows, and track success per context
+    pub async fn process_command(&mut self, command: &str, success: bool, context: &EcosystemState) -> Result<()> {
+        let signature = command_signature(command);
+        if signature.is_empty() {
+            return Ok(());
         }
-        
+        let context_key = context_key(context);
+
+        self.recent_commands.push_back(signature.clone());
+        while self.recent_commands.len() > SEQUENCE_LENGTH {
+            self.recent_commands.pop_front();
+        }
+
+        // Every suffix of the window is an n-gram ending here; shorter ones first so a
+        // longer workflow promoted afterwards can absorb them
+        let window: Vec<String> = self.recent_commands.iter().cloned().collect();
+        for n in 1..=window.len() {
+            let steps = &window[window.len() - n..];
+            let key = steps.join(SEQUENCE_SEPARATOR);
+            let pattern = self.command_patterns.entry(key.clone()).or_insert_with(|| CommandPatternData {
+                pattern: key,
+                frequency: 0,
+                success_rate: 0.0,
+                context_conditions: Vec::new(),
+            });
+            pattern.success_rate = running_rate(pattern.success_rate, pattern.frequency, success);
+            pattern.frequency += 1;
+            if !pattern.context_conditions.contains(&context_key) && pattern.context_conditions.len() < MAX_PATTERN_CONTEXTS {
+                pattern.context_conditions.push(context_key.clone());
+            }
+            if n >= 2 && pattern.frequency >= WORKFLOW_SUPPORT {
+                let pattern = pattern.clone();
+                self.promote_workflow(steps, &pattern);
+            }
+        }
+
         // Update temporal patterns based on time of day
         let hour = context.timestamp.hour();
         let time_range = match hour {
@@ -2198,37 +2873,43 @@ impl PatternRecognizer {
         
         let temporal_pattern = TemporalPattern {
             time_range: time_range.to_string(),
-            commands: vec![command.to_string()],
+            commands: vec![signature.clone()],
             frequency: 1,
             confidence: 0.8,
         };
         
         if let Some(existing) = self.temporal_patterns.iter_mut().find(|p| p.time_range == time_range) {
             existing.frequency += 1;
-            if !existing.commands.contains(&command.to_string()) {
-                existing.commands.push(command.to_string());
+            if !existing.commands.contains(&signature) && existing.commands.len() < MAX_PATTERN_COMMANDS {
+                existing.commands.push(signature.clone());
             }
         } else {
             self.temporal_patterns.push(temporal_pattern);
         }
         
-        // Update context patterns
-        let context_pattern = ContextPattern {
-            context_type: "system".to_string(),
-            conditions: {
-                let mut cond = HashMap::new();
-                cond.insert("os".to_string(), context.system.os.clone());
-                cond.insert("cpu_usage".to_string(), context.performance.cpu_usage.current.to_string());
-                cond
-            },
-            associated_commands: vec![command.to_string()],
-            success_rate: 1.0,
+        // Success rate of everything run in this context
+        let index = match self.context_patterns.iter().position(|p| p.conditions.get("context") == Some(&context_key)) {
+            Some(index) => index,
+            None => {
+                self.context_patterns.push(ContextPattern {
+                    context_type: context_key.split(':').next().unwrap_or("system").to_string(),
+                    conditions: HashMap::from([("context".to_string(), context_key.clone())]),
+                    associated_commands: Vec::new(),
+                    success_rate: 0.0,
+                    executions: 0,
+                });
+                self.context_patterns.len() - 1
+            }
         };
+        let context_pattern = &mut self.context_patterns[index];
+        context_pattern.success_rate = running_rate(context_pattern.success_rate, context_pattern.executions, success);
+        context_pattern.executions += 1;
+        if !context_pattern.associated_commands.contains(&signature) && context_pattern.associated_commands.len() < MAX_PATTERN_COMMANDS {
+            context_pattern.associated_commands.push(signature.clone());
+        }
         
-        self.context_patterns.push(context_pattern);
-        
-        // Update error patterns if this command commonly fails
-        if command.contains("git") || command.contains("cargo") {
+        // Update error patterns when a build or VCS command fails
+        if !success && (command.contains("git") || command.contains("cargo")) {
             let error_pattern = ErrorPattern {
                 error_type: "build_error".to_string(),
                 frequency: 1,
@@ -2237,13 +2918,140 @@ impl PatternRecognizer {
                 recommended_fixes: vec!["check dependencies".to_string()],
             };
             
-            if !self.error_patterns.iter().any(|p| p.error_type == "build_error") {
-                self.error_patterns.push(error_pattern);
+            match self.error_patterns.iter_mut().find(|p| p.error_type == "build_error") {
+                Some(existing) => existing.frequency += 1,
+                None => self.error_patterns.push(error_pattern),
             }
         }
-        
+
+        self.prune();
         Ok(())
     }
+
+    fn promote_workflow(&mut self, steps: &[String], pattern: &CommandPatternData) {
+        let contains = |outer: &[String], inner: &[String]| outer.len() > inner.len() && outer.windows(inner.len()).any(|w| w == inner);
+
+        // Already covered by a longer workflow seen at least as often
+        if self.workflow_patterns.iter().any(|w| contains(&w.steps, steps) && w.support >= pattern.frequency) {
+            return;
+        }
+        self.workflow_patterns.retain(|w| !(contains(steps, &w.steps) && w.support <= pattern.frequency));
+
+        let workflow = WorkflowPattern {
+            name: pattern.pattern.clone(),
+            steps: steps.to_vec(),
+            success_rate: pattern.success_rate,
+            context_requirements: pattern.context_conditions.clone(),
+            support: pattern.frequency,
+        };
+        match self.workflow_patterns.iter_mut().find(|w| w.steps == steps) {
+            Some(existing) => *existing = workflow,
+            None => self.workflow_patterns.push(workflow),
+        }
+    }
+
+    /// Keep memory bounded by dropping the least frequent sequences and workflows
+    fn prune(&mut self) {
+        if self.command_patterns.len() > MAX_COMMAND_PATTERNS {
+            let mut frequencies: Vec<u32> = self.command_patterns.values().map(|p| p.frequency).collect();
+            frequencies.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = frequencies[MAX_COMMAND_PATTERNS * 3 / 4];
+            // Ties at the cutoff go too, so a flood of one-off commands is cleared in one pass
+            self.command_patterns.retain(|_, p| p.frequency > cutoff);
+        }
+        if self.workflow_patterns.len() > MAX_WORKFLOW_PATTERNS {
+            self.workflow_patterns.sort_by_key(|w| std::cmp::Reverse(w.support));
+            self.workflow_patterns.truncate(MAX_WORKFLOW_PATTERNS);
+        }
+    }
+
+    /// Learned command sequences of two or more steps, most frequent first
+    pub fn command_sequences(&self) -> Vec<CommandPatternData> {
+        let mut sequences: Vec<CommandPatternData> = self
+            .command_patterns
+            .values()
+            .filter(|p| p.pattern.contains(SEQUENCE_SEPARATOR))
+            .cloned()
+            .collect();
+        sequences.sort_by(|a, b| b.frequency.cmp(&a.frequency).then_with(|| a.pattern.cmp(&b.pattern)));
+        sequences
+    }
+
+    pub fn workflows(&self) -> &[WorkflowPattern] {
+        &self.workflow_patterns
+    }
+
+    pub fn contexts(&self) -> &[ContextPattern] {
+        &self.context_patterns
+    }
+}
+
+/// Base commands per tracked sequence
+const SEQUENCE_LENGTH: usize = 3;
+/// Times a sequence must be seen before it becomes a workflow
+const WORKFLOW_SUPPORT: u32 = 3;
+const SEQUENCE_SEPARATOR: &str = " -> ";
+/// Sequences kept before the least frequent are pruned
+const MAX_COMMAND_PATTERNS: usize = 500;
+const MAX_WORKFLOW_PATTERNS: usize = 50;
+/// Commands remembered per temporal or context pattern
+const MAX_PATTERN_COMMANDS: usize = 50;
+const MAX_PATTERN_CONTEXTS: usize = 10;
+
+/// Where a command ran, e.g. `project:rust`, for per-context success rates
+fn context_key(context: &EcosystemState) -> String {
+    match context.development.active_projects.first() {
+        Some(project) => format!("project:{}", project.project_type),
+        None => "system".to_string(),
+    }
+}
+
+/// Mean success after adding one more outcome to `count` earlier ones
+fn running_rate(rate: f64, count: u32, success: bool) -> f64 {
+    (rate * count as f64 + if success { 1.0 } else { 0.0 }) / (count as f64 + 1.0)
+}
+
+/// How many commands after a failure are considered as its recovery
+const RECOVERY_LOOKAHEAD: usize = 3;
+
+/// A recovery must run within this many minutes of the failure
+const RECOVERY_WINDOW_MINUTES: i64 = 10;
+
+/// Knowledge base fixes rank below anything seen in the user's own history
+const KNOWLEDGE_BASE_CONFIDENCE: f64 = 0.4;
+
+/// Tool plus subcommand, e.g. `npm install` for `npm install lodash --save`
+fn command_signature(command: &str) -> String {
+    command
+        .split_whitespace()
+        .filter(|part| !part.starts_with('-'))
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Built-in knowledge base of common failures and their usual fixes.
+///
+/// `error_type` is matched case-insensitively against the error output and
+/// `{command}` in a fix is replaced with the failed command.
+fn default_error_patterns() -> Vec<ErrorPattern> {
+    let pattern = |error_type: &str, components: &[&str], cause: &str, fixes: &[&str]| ErrorPattern {
+        error_type: error_type.to_string(),
+        frequency: 0,
+        affected_components: components.iter().map(|c| c.to_string()).collect(),
+        potential_causes: vec![cause.to_string()],
+        recommended_fixes: fixes.iter().map(|f| f.to_string()).collect(),
+    };
+
+    vec![
+        pattern("ERESOLVE", &["npm"], "Conflicting peer dependencies", &["npm install --legacy-peer-deps", "npm ci"]),
+        pattern("Permission denied", &[], "Insufficient permissions", &["sudo {command}"]),
+        pattern("not a git repository", &["git"], "Not inside a git repository", &["git init"]),
+        pattern("failed to push some refs", &["git"], "Remote has commits not present locally", &["git pull --rebase"]),
+        pattern("No module named", &["python", "python3", "pip", "pytest"], "Missing Python dependency", &["pip install -r requirements.txt"]),
+        pattern("could not find `Cargo.toml`", &["cargo"], "Not inside a Cargo project", &["cargo init"]),
+        pattern("failed to select a version", &["cargo"], "Outdated Cargo.lock or registry index", &["cargo update"]),
+    ]
 }
 
 impl BehaviorPredictor {
@@ -2375,6 +3183,232 @@ impl LearningDatabase {
             adaptation_history: Vec::new(),
         }
     }
+
+    /// Fold a command execution into the frequency pattern for its base command
+    fn record_command_pattern(&mut self, execution: &CommandExecution) -> LearningPattern {
+        let base_command = execution.command.split_whitespace().next().unwrap_or_default();
+        let pattern_id = format!("command:{}", base_command);
+        let pattern = self.learning_patterns.entry(pattern_id.clone()).or_insert_with(|| LearningPattern {
+            pattern_id,
+            pattern_type: "command_frequency".to_string(),
+            confidence: 0.0,
+            occurrences: 0,
+            last_updated: execution.timestamp,
+            metadata: HashMap::new(),
+        });
+
+        let successes = pattern.metadata.get("successes").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0)
+            + execution.success as u32;
+        pattern.occurrences += 1;
+        pattern.confidence = successes as f64 / pattern.occurrences as f64;
+        pattern.last_updated = execution.timestamp;
+        pattern.metadata.insert("successes".to_string(), successes.to_string());
+        pattern.clone()
+    }
+}
+
+/// How `EcosystemAwareness::queue_interaction` batches writes to the learning database
+#[derive(Debug, Clone, Copy)]
+pub struct LearningBatchConfig {
+    pub flush_interval: std::time::Duration,
+    /// Flush early once this many interactions are waiting
+    pub max_batch: usize,
+}
+
+impl Default for LearningBatchConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: std::time::Duration::from_secs(5),
+            max_batch: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LearningBatchStats {
+    /// Interactions buffered for writing
+    pub queued: u64,
+    /// Repeats of the previous command folded into it
+    pub coalesced: u64,
+    pub batches_written: u64,
+    pub interactions_written: u64,
+    /// Batches dropped because they could not be written
+    pub failed_batches: u64,
+}
+
+#[derive(Debug, Default)]
+struct PendingInteractions {
+    buffer: Vec<UserInteraction>,
+    /// Command and outcome of the latest interaction, kept across flushes for coalescing
+    last: Option<(String, bool)>,
+    stats: LearningBatchStats,
+}
+
+#[derive(Debug)]
+struct LearningBatcher {
+    config: LearningBatchConfig,
+    pending: Arc<std::sync::Mutex<PendingInteractions>>,
+    /// Wakes the flush task when the buffer reaches `max_batch`
+    full: Arc<tokio::sync::Notify>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// Write out the buffered interactions in batches of at most `max_batch`. Failures are logged
+/// and the batch dropped, so a broken store never backs up the command path.
+async fn flush_pending(
+    pending: &std::sync::Mutex<PendingInteractions>,
+    max_batch: usize,
+    learning_engine: &RwLock<AdaptiveLearningEngine>,
+    current_state: &RwLock<EcosystemState>,
+) {
+    let buffered = match pending.lock() {
+        Ok(mut pending) => std::mem::take(&mut pending.buffer),
+        Err(_) => return,
+    };
+
+    let mut batches = buffered.into_iter().peekable();
+    while batches.peek().is_some() {
+        let batch: Vec<UserInteraction> = batches.by_ref().take(max_batch.max(1)).collect();
+        let size = batch.len() as u64;
+        let result = learn_batch(learning_engine, current_state, batch).await;
+        if let Err(e) = &result {
+            tracing::error!("Failed to record {} command(s) for learning: {}", size, e);
+        }
+        if let Ok(mut pending) = pending.lock() {
+            match result {
+                Ok(()) => {
+                    pending.stats.batches_written += 1;
+                    pending.stats.interactions_written += size;
+                }
+                Err(_) => pending.stats.failed_batches += 1,
+            }
+        }
+    }
+}
+
+async fn learn_batch(
+    learning_engine: &RwLock<AdaptiveLearningEngine>,
+    current_state: &RwLock<EcosystemState>,
+    batch: Vec<UserInteraction>,
+) -> Result<()> {
+    let state = current_state.read().await.clone();
+    let mut learning = learning_engine.write().await;
+    learning.process_batch(batch, &state).await?;
+    learning.update_patterns().await?;
+    learning.adapt_predictions().await
+}
+
+/// On-disk copy of the learning database so learned behaviour survives restarts
+pub struct LearningStore {
+    path: PathBuf,
+    database: Database,
+    transactions: std::sync::atomic::AtomicU64,
+}
+
+impl std::fmt::Debug for LearningStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LearningStore").field("path", &self.path).finish()
+    }
+}
+
+impl LearningStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let database = Database::create(path)?;
+
+        // Create the tables up front so readers never see a missing table
+        let txn = database.begin_write()?;
+        txn.open_table(COMMAND_EXECUTIONS_TABLE)?;
+        txn.open_table(CONTEXT_SNAPSHOTS_TABLE)?;
+        txn.open_table(LEARNING_PATTERNS_TABLE)?;
+        txn.commit()?;
+
+        Ok(Self { path: path.to_path_buf(), database, transactions: Default::default() })
+    }
+
+    /// Append the rows of a batch of interactions in a single transaction
+    pub fn append(&self, rows: &[(CommandExecution, ContextSnapshot, LearningPattern)]) -> Result<()> {
+        let txn = self.database.begin_write()?;
+        {
+            let mut executions = txn.open_table(COMMAND_EXECUTIONS_TABLE)?;
+            let mut snapshots = txn.open_table(CONTEXT_SNAPSHOTS_TABLE)?;
+            let mut patterns = txn.open_table(LEARNING_PATTERNS_TABLE)?;
+            for (execution, snapshot, pattern) in rows {
+                let next = executions.last()?.map(|(key, _)| key.value() + 1).unwrap_or(0);
+                executions.insert(next, serde_json::to_vec(execution)?.as_slice())?;
+
+                let next = snapshots.last()?.map(|(key, _)| key.value() + 1).unwrap_or(0);
+                snapshots.insert(next, serde_json::to_vec(snapshot)?.as_slice())?;
+
+                // A later row for the same command carries the newer pattern
+                patterns.insert(pattern.pattern_id.as_str(), serde_json::to_vec(pattern)?.as_slice())?;
+            }
+        }
+        txn.commit()?;
+        self.transactions.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Write transactions committed by `append` since the store was opened
+    pub fn append_transactions(&self) -> u64 {
+        self.transactions.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn recent_command_executions(&self, limit: usize) -> Result<Vec<CommandExecution>> {
+        self.recent_rows(COMMAND_EXECUTIONS_TABLE, limit)
+    }
+
+    pub fn recent_context_snapshots(&self, limit: usize) -> Result<Vec<ContextSnapshot>> {
+        self.recent_rows(CONTEXT_SNAPSHOTS_TABLE, limit)
+    }
+
+    pub fn learning_patterns(&self) -> Result<HashMap<String, LearningPattern>> {
+        let txn = self.database.begin_read()?;
+        let table = txn.open_table(LEARNING_PATTERNS_TABLE)?;
+        let mut patterns = HashMap::new();
+        for row in table.iter()? {
+            let (key, value) = row?;
+            patterns.insert(key.value().to_string(), serde_json::from_slice(value.value())?);
+        }
+        Ok(patterns)
+    }
+
+    /// Drop the oldest rows beyond the retention limits
+    pub fn prune(&self, max_command_executions: usize, max_context_snapshots: usize) -> Result<()> {
+        let txn = self.database.begin_write()?;
+        for (definition, keep) in [
+            (COMMAND_EXECUTIONS_TABLE, max_command_executions),
+            (CONTEXT_SNAPSHOTS_TABLE, max_context_snapshots),
+        ] {
+            let mut table = txn.open_table(definition)?;
+            let excess = (table.len()? as usize).saturating_sub(keep);
+            let stale: Vec<u64> = table.iter()?.take(excess).map(|row| row.map(|(key, _)| key.value())).collect::<Result<_, _>>()?;
+            for key in stale {
+                table.remove(key)?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// The newest `limit` rows of a sequence-keyed table, oldest first
+    fn recent_rows<T: serde::de::DeserializeOwned>(&self, definition: TableDefinition<u64, &[u8]>, limit: usize) -> Result<Vec<T>> {
+        let txn = self.database.begin_read()?;
+        let table = txn.open_table(definition)?;
+        let mut rows = table
+            .iter()?
+            .rev()
+            .take(limit)
+            .map(|row| -> Result<T> {
+                let (_, value) = row?;
+                Ok(serde_json::from_slice(value.value())?)
+            })
+            .collect::<Result<Vec<T>>>()?;
+        rows.reverse();
+        Ok(rows)
+    }
 }
 
 #[derive(Debug)]
@@ -2509,20 +3543,21 @@ impl AdaptiveLearningEngine {
     pub async fn maintain_database_size(&mut self) -> Result<()> {
         let mut db = self.learning_database.write().await;
         
-        // Keep only the last 10000 command executions
-        while db.command_executions.len() > 10000 {
+        while db.command_executions.len() > MAX_COMMAND_EXECUTIONS {
             db.command_executions.pop_front();
         }
         
-        // Keep only the last 5000 context snapshots
-        while db.context_snapshots.len() > 5000 {
+        while db.context_snapshots.len() > MAX_CONTEXT_SNAPSHOTS {
             db.context_snapshots.pop_front();
         }
         
-        // Keep only the last 10000 user interactions
-        while db.user_interactions.len() > 10000 {
+        while db.user_interactions.len() > MAX_USER_INTERACTIONS {
             db.user_interactions.pop_front();
         }
+
+        if let Some(store) = &self.store {
+            store.prune(MAX_COMMAND_EXECUTIONS, MAX_CONTEXT_SNAPSHOTS)?;
+        }
         
         Ok(())
     }
@@ -2576,6 +3611,78 @@ impl AdaptiveLearningEngine {
     pub async fn predict_maintenance_needs(&self, _context: &EcosystemState) -> Result<Vec<MaintenanceRecommendation>> {
         Ok(vec![])
     }
+
+    pub async fn suggest_recovery(&self, failed_command: &str, error_output: &str) -> Result<Vec<RecoverySuggestion>> {
+        let db = self.learning_database.read().await;
+        let signature = command_signature(failed_command);
+        let executions: Vec<&CommandExecution> = db.command_executions.iter().collect();
+
+        // Count the first successful, different command run shortly after each matching failure
+        let mut failures = 0usize;
+        let mut followups: HashMap<String, usize> = HashMap::new();
+        for (i, execution) in executions.iter().enumerate() {
+            if execution.success || command_signature(&execution.command) != signature {
+                continue;
+            }
+            failures += 1;
+
+            let recovery = executions[i + 1..]
+                .iter()
+                .take(RECOVERY_LOOKAHEAD)
+                .take_while(|next| next.timestamp - execution.timestamp <= Duration::minutes(RECOVERY_WINDOW_MINUTES))
+                .find(|next| next.success && command_signature(&next.command) != signature);
+            if let Some(next) = recovery {
+                *followups.entry(next.command.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut suggestions: Vec<RecoverySuggestion> = followups
+            .into_iter()
+            .map(|(command, count)| RecoverySuggestion {
+                confidence: 0.5 + 0.5 * (count as f64 / failures as f64),
+                rationale: format!("Ran after {} of {} similar failures", count, failures),
+                command,
+                sources: vec![RecoverySource::History],
+                occurrences: count,
+            })
+            .collect();
+
+        let error_lower = error_output.to_lowercase();
+        let tool = failed_command.split_whitespace().next().unwrap_or("");
+        for pattern in &self.pattern_recognizer.error_patterns {
+            let applies_to_tool = pattern.affected_components.is_empty()
+                || pattern.affected_components.iter().any(|c| c == tool);
+            if !applies_to_tool || !error_lower.contains(&pattern.error_type.to_lowercase()) {
+                continue;
+            }
+
+            for fix in &pattern.recommended_fixes {
+                let command = fix.replace("{command}", failed_command);
+                if let Some(existing) = suggestions.iter_mut().find(|s| s.command == command) {
+                    if !existing.sources.contains(&RecoverySource::KnowledgeBase) {
+                        existing.sources.push(RecoverySource::KnowledgeBase);
+                        existing.confidence = (existing.confidence + 0.1).min(1.0);
+                    }
+                } else {
+                    suggestions.push(RecoverySuggestion {
+                        command,
+                        confidence: KNOWLEDGE_BASE_CONFIDENCE,
+                        sources: vec![RecoverySource::KnowledgeBase],
+                        occurrences: 0,
+                        rationale: pattern.potential_causes.join("; "),
+                    });
+                }
+            }
+        }
+
+        suggestions.sort_by(|a, b| {
+            b.confidence
+                .partial_cmp(&a.confidence)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.occurrences.cmp(&a.occurrences))
+        });
+        Ok(suggestions)
+    }
 }
 
 // Additional supporting structures for comprehensive analysis
@@ -2807,3 +3914,379 @@ impl ContextPredictor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (pid, name, state, ppid, tty_nr, utime, VmRSS in kB)
+    type FixtureProcess<'a> = (u32, &'a str, &'a str, u32, i32, u64, Option<u64>);
+
+    fn write_proc_fixture(root: &Path, total_jiffies: u64, processes: &[FixtureProcess]) {
+        std::fs::write(root.join("stat"), format!("cpu  {} 0 0 0 0 0 0 0 0 0\ncpu0 0 0 0 0\n", total_jiffies)).unwrap();
+        for &(pid, name, state, ppid, tty_nr, ticks, rss_kb) in processes {
+            let dir = root.join(pid.to_string());
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(
+                dir.join("stat"),
+                format!("{} ({}) {} {} {} {} {} -1 4194560 100 0 0 0 {} 0 0 0 20 0 1 0 500 1000 200\n", pid, name, state, ppid, pid, pid, tty_nr, ticks),
+            )
+            .unwrap();
+            let rss = rss_kb.map(|kb| format!("VmRSS:\t{} kB\n", kb)).unwrap_or_default();
+            std::fs::write(dir.join("status"), format!("Name:\t{}\nState:\t{}\nPPid:\t{}\n{}", name, state, ppid, rss)).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_state_parsed_from_proc_fixture() {
+        let root = tempfile::tempdir().unwrap();
+        let processes = [
+            (1, "systemd", "S", 0, 0, 500, Some(12_000)),
+            (420, "sshd", "S", 1, 0, 40, Some(6_000)),
+            (900, "tmux: server", "S", 1, 34816, 10, Some(3_000)),
+            (901, "bash", "S", 900, 34817, 20, Some(4_000)),
+            (950, "cargo (build)", "R", 901, 34817, 100, Some(250_000)),
+            (2, "kthreadd", "S", 0, 0, 0, None),
+        ];
+        write_proc_fixture(root.path(), 10_000, &processes);
+        std::fs::create_dir_all(root.path().join("self")).unwrap();
+        let before = read_proc_snapshot(root.path()).unwrap();
+
+        let mut later = processes;
+        later[4].5 = 300; // cargo burns 200 of 1000 elapsed jiffies
+        later[1].5 = 50;
+        write_proc_fixture(root.path(), 11_000, &later);
+        let after = read_proc_snapshot(root.path()).unwrap();
+
+        let all = processes_between(&before, &after);
+        let by_pid = |pid: u32| all.iter().find(|p| p.pid == pid).unwrap();
+        assert_eq!(all.len(), 6);
+        assert_eq!(by_pid(950).name, "cargo (build)");
+        assert_eq!(by_pid(950).state, "R");
+        assert_eq!(by_pid(950).memory_usage, 250_000 * 1024);
+        assert!((by_pid(950).cpu_percent - 20.0).abs() < 1e-9);
+        assert!((by_pid(420).cpu_percent - 1.0).abs() < 1e-9);
+        assert_eq!(by_pid(2).memory_usage, 0);
+
+        // Only processes without a terminal that were reparented to init count as daemons
+        assert!(by_pid(420).is_daemon);
+        assert!(!by_pid(900).is_daemon);
+        assert!(!by_pid(1).is_daemon);
+
+        let tree = EcosystemState::build_process_tree(&all).await.unwrap();
+        assert_eq!(tree[&1], vec![420, 900]);
+        assert_eq!(tree[&900], vec![901]);
+        assert_eq!(tree[&901], vec![950]);
+        assert!(!tree.contains_key(&0));
+    }
+
+    struct FixedProcesses(Vec<ProcessInfo>);
+
+    #[async_trait::async_trait]
+    impl ProcessSource for FixedProcesses {
+        async fn processes(&self) -> Result<Vec<ProcessInfo>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn process(pid: u32, ppid: u32, name: &str, cpu_percent: f64, memory_mb: u64) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            ppid,
+            name: name.to_string(),
+            state: "S".to_string(),
+            cpu_percent,
+            memory_usage: memory_mb * 1024 * 1024,
+            is_daemon: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_active_processes_prefer_terminal_children_and_truncate() {
+        let source = FixedProcesses(vec![
+            process(1, 0, "systemd", 0.1, 12),
+            process(2, 0, "kthreadd", 0.0, 0),
+            process(30, 2, "kworker/0:1", 90.0, 0),
+            process(400, 1, "firefox", 35.0, 2048),
+            process(410, 1, "postgres", 60.0, 300),
+            process(500, 1, "bash", 0.0, 4),
+            process(510, 500, "cargo", 5.0, 200),
+            process(520, 510, "rustc", 80.0, 900),
+            process(600, 1, "code", 1.0, 1500),
+        ]);
+
+        let processes = active_processes(&source, &[500], 4).await;
+        let pids: Vec<u32> = processes.iter().map(|p| p.pid).collect();
+        // Terminal descendants first, then CPU and memory leaders in turn (rustc, firefox, postgres);
+        // kernel threads never appear
+        assert_eq!(pids, vec![510, 520, 400, 410]);
+
+        let all = active_processes(&source, &[], 100).await;
+        assert_eq!(all.len(), 7);
+        assert!(all.iter().all(|p| p.pid != 2 && p.ppid != 2));
+
+        let missing = active_processes(&ProcfsSource::new("/nonexistent/proc"), &[500], 4).await;
+        assert!(missing.is_empty());
+    }
+
+    fn interaction(command: &str, success: bool, error_output: Option<&str>) -> UserInteraction {
+        UserInteraction {
+            command: command.to_string(),
+            success,
+            execution_time: 100,
+            user_context: "test".to_string(),
+            timestamp: Utc::now(),
+            error_output: error_output.map(|e| e.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorded_recovery_ranks_above_unrelated_commands() {
+        let awareness = EcosystemAwareness::default();
+        let sequence = [
+            ("ls", true),
+            ("npm install", false),
+            ("npm ci", true),
+            ("git status", true),
+            ("npm install lodash", false),
+            ("npm ci", true),
+            ("cargo build", true),
+        ];
+        for (command, success) in sequence {
+            let error = (!success).then_some("npm ERR! code ERESOLVE");
+            awareness.learn_from_interaction(interaction(command, success, error)).await.unwrap();
+        }
+
+        let suggestions = awareness.suggest_recovery("npm install", "npm ERR! code ERESOLVE").await.unwrap();
+        assert_eq!(suggestions[0].command, "npm ci");
+        assert_eq!(suggestions[0].occurrences, 2);
+        assert!(suggestions[0].sources.contains(&RecoverySource::History));
+        assert!(suggestions[0].sources.contains(&RecoverySource::KnowledgeBase));
+        assert!(!suggestions.iter().any(|s| s.command == "git status" || s.command == "cargo build"));
+    }
+
+    #[tokio::test]
+    async fn test_monitoring_refreshes_state_until_stopped() {
+        let period = std::time::Duration::from_millis(40);
+        let mut awareness = EcosystemAwareness::default().with_monitoring_intervals(MonitoringIntervals {
+            performance: period,
+            processes: std::time::Duration::from_secs(3600),
+            network: std::time::Duration::from_secs(3600),
+        });
+        let initial = awareness.current_state.read().await.timestamp;
+
+        awareness.start_monitoring();
+        assert!(awareness.is_monitoring());
+        tokio::time::sleep(period * 2).await;
+        let refreshed = awareness.current_state.read().await.timestamp;
+        assert!(refreshed > initial);
+
+        awareness.stop_monitoring();
+        assert!(!awareness.is_monitoring());
+        tokio::time::sleep(period * 2).await;
+        assert_eq!(awareness.current_state.read().await.timestamp, refreshed);
+    }
+
+    #[tokio::test]
+    async fn test_learning_data_survives_reopening_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("learning.redb");
+
+        let awareness = EcosystemAwareness::default();
+        awareness.attach_learning_store(&path).await.unwrap();
+        awareness.learn_from_interaction(interaction("cargo build", false, Some("error[E0425]"))).await.unwrap();
+        awareness.learn_from_interaction(interaction("cargo build --release", true, None)).await.unwrap();
+        awareness.learn_from_interaction(interaction("git status", true, None)).await.unwrap();
+        drop(awareness);
+
+        let reopened = EcosystemAwareness::default();
+        reopened.attach_learning_store(&path).await.unwrap();
+        let learning = reopened.learning_engine.read().await;
+        let db = learning.learning_database.read().await;
+        let commands: Vec<&str> = db.command_executions.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["cargo build", "cargo build --release", "git status"]);
+        assert_eq!(db.command_executions[0].error_message.as_deref(), Some("error[E0425]"));
+        assert_eq!(db.context_snapshots.len(), 3);
+        assert_eq!(db.learning_patterns["command:cargo"].occurrences, 2);
+        assert!((db.learning_patterns["command:cargo"].confidence - 0.5).abs() < f64::EPSILON);
+
+        // Pruning the store keeps only the newest rows
+        learning.store.as_ref().unwrap().prune(1, 1).unwrap();
+        let store = learning.store.as_ref().unwrap();
+        assert_eq!(store.recent_command_executions(10).unwrap()[0].command, "git status");
+        assert_eq!(store.recent_context_snapshots(10).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_command_burst_is_coalesced_and_written_in_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut awareness = EcosystemAwareness::default();
+        awareness.attach_learning_store(&dir.path().join("learning.redb")).await.unwrap();
+        awareness.start_learning_batcher(LearningBatchConfig {
+            flush_interval: std::time::Duration::from_secs(3600),
+            max_batch: 3,
+        });
+
+        // A fast burst, including a held-down Enter and a retry that failed the second time
+        for (command, success) in [
+            ("ls", true), ("ls", true), ("ls", true),
+            ("cargo build", true), ("cargo build", false),
+            ("git status", true), ("git status", true),
+            ("cargo test", true), ("ls", true),
+        ] {
+            awareness.queue_interaction(interaction(command, success, None));
+        }
+        let stats = awareness.learning_batch_stats();
+        assert_eq!((stats.queued, stats.coalesced, stats.batches_written), (6, 3, 0));
+
+        // The full buffer woke the flush task, which writes in batches of three
+        for _ in 0..100 {
+            if awareness.learning_batch_stats().interactions_written == 6 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let stats = awareness.learning_batch_stats();
+        assert_eq!((stats.batches_written, stats.interactions_written, stats.failed_batches), (2, 6, 0));
+        {
+            let learning = awareness.learning_engine.read().await;
+            assert_eq!(learning.store.as_ref().unwrap().append_transactions(), 2);
+            let db = learning.learning_database.read().await;
+            let commands: Vec<&str> = db.command_executions.iter().map(|e| e.command.as_str()).collect();
+            assert_eq!(commands, vec!["ls", "cargo build", "cargo build", "git status", "cargo test", "ls"]);
+        }
+
+        // A repeat of the last command is coalesced even after a flush; patterns include pending writes
+        awareness.queue_interaction(interaction("ls", true, None));
+        awareness.queue_interaction(interaction("make", true, None));
+        let patterns = awareness.command_patterns().await;
+        let summary: Vec<(&str, u32)> = patterns.iter().map(|p| (p.pattern_id.as_str(), p.occurrences)).collect();
+        assert_eq!(summary, vec![("command:cargo", 3), ("command:ls", 2), ("command:git", 1), ("command:make", 1)]);
+        assert_eq!(awareness.learning_batch_stats().coalesced, 4);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_knowledge_base_without_history() {
+        let awareness = EcosystemAwareness::default();
+
+        let suggestions = awareness
+            .suggest_recovery("git push", "error: failed to push some refs to 'origin'")
+            .await
+            .unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].command, "git pull --rebase");
+        assert_eq!(suggestions[0].sources, vec![RecoverySource::KnowledgeBase]);
+
+        let suggestions = awareness.suggest_recovery("./deploy.sh", "bash: ./deploy.sh: Permission denied").await.unwrap();
+        assert_eq!(suggestions[0].command, "sudo ./deploy.sh");
+    }
+
+    #[test]
+    fn test_context_summary_includes_key_fields_within_cap() {
+        let mut state = EcosystemState::default();
+        state.system.distribution = "Arch Linux".to_string();
+        state.system.system_load = (0.5, 0.25, 0.1);
+        for pid in 0..20 {
+            state.processes.top_cpu_processes.push(ProcessInfo {
+                pid,
+                ppid: 1,
+                name: format!("worker-{}", pid),
+                state: "R".to_string(),
+                cpu_percent: 90.0 - pid as f64,
+                memory_usage: 64 * 1024 * 1024,
+                is_daemon: false,
+            });
+        }
+        state.development.version_control.push(VcsInfo {
+            vcs_type: "git".to_string(),
+            repository_path: "/home/me/nexus".to_string(),
+            branch: "main".to_string(),
+            has_changes: true,
+            remote_url: None,
+        });
+        state.development.active_projects.push(ProjectInfo {
+            name: "nexus".to_string(),
+            path: "/home/me/nexus".to_string(),
+            project_type: "rust".to_string(),
+            last_modified: Utc::now(),
+            size: 0,
+        });
+        for n in 0..50 {
+            state.user_context.shell_history.push_back(CommandExecution {
+                command: format!("cargo build -p crate{}", n),
+                timestamp: Utc::now() + Duration::seconds(n),
+                success: false,
+                duration: 10,
+                error_message: Some(format!("error[E0425]: {}", "x".repeat(1000))),
+            });
+        }
+        let context = ComprehensiveContext {
+            ecosystem_state: state,
+            predicted_actions: Vec::new(),
+            contextual_suggestions: Vec::new(),
+            identified_patterns: Vec::new(),
+            learning_insights: Vec::new(),
+            adaptation_recommendations: Vec::new(),
+        };
+
+        let summary = ContextSummary::from_context(&context);
+        assert_eq!(summary.top_processes.len(), CONTEXT_SUMMARY_ITEMS);
+        assert!(summary.top_processes[0].starts_with("worker-0 (pid 0) 90.0% CPU, 64 MB"));
+        assert_eq!(summary.git_status, vec!["/home/me/nexus on main: uncommitted changes".to_string()]);
+        assert_eq!(summary.project_types, vec!["nexus (rust)".to_string()]);
+        assert!(summary.recent_errors[0].starts_with("`cargo build -p crate49`: error[E0425]"));
+        assert!(summary.recent_errors.iter().all(|e| e.chars().count() <= CONTEXT_SUMMARY_ITEM_CHARS));
+
+        let prompt = summary.to_prompt();
+        assert!(prompt.chars().count() <= CONTEXT_SUMMARY_MAX_CHARS, "{} chars", prompt.chars().count());
+        for field in ["System: Arch Linux", "Top processes:", "Git:", "Projects:\n- nexus (rust)", "Recent errors:"] {
+            assert!(prompt.contains(field), "missing {}: {}", field, prompt);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeating_sequence_becomes_workflow_with_support() {
+        let awareness = EcosystemAwareness::default();
+        let sequence = ["git add .", "git commit -m 'wip'", "git push origin main"];
+        for round in 0..3 {
+            for command in sequence {
+                // The second push is rejected
+                let success = !(round == 1 && command.starts_with("git push"));
+                awareness.learn_from_interaction(interaction(command, success, None)).await.unwrap();
+            }
+            let workflows = awareness.analyze_system_patterns().await.unwrap().workflow_patterns;
+            assert_eq!(workflows.is_empty(), round < 2, "round {}: {:?}", round, workflows);
+        }
+
+        let analysis = awareness.analyze_system_patterns().await.unwrap();
+        // The pairs inside the full sequence are absorbed by it
+        assert_eq!(analysis.workflow_patterns.len(), 1, "{:?}", analysis.workflow_patterns);
+        let workflow = &analysis.workflow_patterns[0];
+        assert_eq!(workflow.steps, vec!["git add", "git commit", "git push"]);
+        assert_eq!(workflow.support, 3);
+        assert!((workflow.success_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(workflow.context_requirements, vec!["system".to_string()]);
+
+        assert_eq!(analysis.command_sequences[0].frequency, 3);
+        let context = &analysis.context_patterns[0];
+        assert_eq!(context.executions, 9);
+        assert!((context.success_rate - 8.0 / 9.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_pattern_memory_is_bounded() {
+        let mut recognizer = PatternRecognizer::new();
+        let state = EcosystemState::default();
+        for _ in 0..4 {
+            for step in ["make", "make install"] {
+                recognizer.process_command(step, true, &state).await.unwrap();
+            }
+        }
+        for n in 0..1000 {
+            recognizer.process_command(&format!("tool{} run", n), true, &state).await.unwrap();
+            assert!(recognizer.command_patterns.len() <= MAX_COMMAND_PATTERNS);
+        }
+        assert!(recognizer.command_patterns.contains_key("make -> make install"));
+        assert!(recognizer.context_patterns[0].associated_commands.len() <= MAX_PATTERN_COMMANDS);
+    }
+}
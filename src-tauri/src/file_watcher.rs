@@ -0,0 +1,476 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Default number of files remembered across all watches
+pub const DEFAULT_MAX_TRACKED: usize = 200;
+
+/// Recently modified files included in the AI's terminal context
+pub const CONTEXT_FILES: usize = 20;
+
+/// Paths waiting out the debounce window; beyond this a burst (a checkout, an unpacked archive)
+/// is not what the user is editing and further paths are dropped until it settles
+const MAX_PENDING: usize = 1000;
+
+/// Directories whose contents are tool output or VCS internals, not files the user is working on
+const IGNORED_DIRS: &[&str] = &[
+    ".git", ".hg", ".svn", "target", "node_modules", "dist", "build", "__pycache__", ".venv", ".next", ".cache",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: String,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// A watched directory and, if the watch died, why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatus {
+    pub path: String,
+    pub active: bool,
+    pub error: Option<String>,
+}
+
+/// Events shared between the notify callbacks and the manager
+#[derive(Debug, Default)]
+struct ActivityLog {
+    /// Paths with events still inside the debounce window
+    pending: HashMap<PathBuf, (Instant, DateTime<Utc>)>,
+    recent: HashMap<PathBuf, DateTime<Utc>>,
+    /// Directories created under a watch that still need a watch of their own
+    new_dirs: HashSet<PathBuf>,
+    /// Watch root -> error that stopped it
+    failures: HashMap<PathBuf, String>,
+}
+
+impl ActivityLog {
+    fn record_pending(&mut self, path: PathBuf, modified_at: DateTime<Utc>) {
+        if self.pending.len() >= MAX_PENDING && !self.pending.contains_key(&path) {
+            return;
+        }
+        self.pending.insert(path, (Instant::now(), modified_at));
+    }
+
+    /// Move paths that have been quiet for `debounce` into the recent set, keeping at most `max_tracked`
+    fn settle(&mut self, debounce: Duration, max_tracked: usize) {
+        let settled: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, (seen, _))| seen.elapsed() >= debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            if let Some((_, modified_at)) = self.pending.remove(&path) {
+                self.recent.insert(path, modified_at);
+            }
+        }
+
+        if self.recent.len() > max_tracked {
+            let mut by_age: Vec<(PathBuf, DateTime<Utc>)> = self.recent.drain().collect();
+            by_age.sort_by_key(|(_, modified_at)| std::cmp::Reverse(*modified_at));
+            by_age.truncate(max_tracked);
+            self.recent = by_age.into_iter().collect();
+        }
+    }
+}
+
+/// One watched root: a non-recursive watch on each of its directories outside `IGNORED_DIRS`
+struct Watch {
+    watcher: RecommendedWatcher,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Watch {
+    /// Watch `dir` and every directory below it that is not ignored
+    fn add_tree(&mut self, root: &Path, dir: &Path) -> notify::Result<()> {
+        let walker = walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_entry(|entry| entry.file_type().is_dir() && !is_ignored(root, entry.path()));
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            if self.dirs.contains(entry.path()) {
+                continue;
+            }
+            match self.watcher.watch(entry.path(), RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.dirs.insert(entry.path().to_path_buf());
+                }
+                // Removed while walking
+                Err(notify::Error { kind: notify::ErrorKind::PathNotFound, .. }) if entry.path() != root => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Watches directories and tracks which files in them changed recently
+pub struct FileWatchManager {
+    watches: HashMap<PathBuf, Watch>,
+    log: Arc<Mutex<ActivityLog>>,
+    debounce: Duration,
+    max_tracked: usize,
+}
+
+impl std::fmt::Debug for FileWatchManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatchManager")
+            .field("watches", &self.watches.keys().collect::<Vec<_>>())
+            .field("debounce", &self.debounce)
+            .field("max_tracked", &self.max_tracked)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for FileWatchManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileWatchManager {
+    pub fn new() -> Self {
+        Self {
+            watches: HashMap::new(),
+            log: Arc::new(Mutex::new(ActivityLog::default())),
+            debounce: Duration::from_millis(500),
+            max_tracked: DEFAULT_MAX_TRACKED,
+        }
+    }
+
+    /// Only count a file as modified once its events have stopped for `debounce`
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn with_max_tracked(mut self, max_tracked: usize) -> Self {
+        self.max_tracked = max_tracked.max(1);
+        self
+    }
+
+    /// Start watching `path` and the directories below it, skipping `IGNORED_DIRS`;
+    /// watching an already watched path is a no-op
+    pub fn start_watch(&mut self, path: &Path) -> Result<()> {
+        self.refresh();
+        let root = path
+            .canonicalize()
+            .with_context(|| format!("Cannot watch {}", path.display()))?;
+        if self.watches.contains_key(&root) {
+            return Ok(());
+        }
+
+        let log = Arc::clone(&self.log);
+        let watch_root = root.clone();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let Ok(mut log) = log.lock() else {
+                return;
+            };
+            match result {
+                Ok(event) => record_event(&mut log, &watch_root, event),
+                Err(e) => {
+                    warn!("File watch on {} failed: {}", watch_root.display(), e);
+                    log.failures.insert(watch_root.clone(), describe_error(&e));
+                }
+            }
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to create file watcher: {}", describe_error(&e)))?;
+
+        let mut watch = Watch { watcher, dirs: HashSet::new() };
+        watch
+            .add_tree(&root, &root)
+            .map_err(|e| anyhow::anyhow!("Failed to watch {}: {}", root.display(), describe_error(&e)))?;
+
+        if let Ok(mut log) = self.log.lock() {
+            log.failures.remove(&root);
+        }
+        info!("Watching {} for file changes", root.display());
+        self.watches.insert(root, watch);
+        Ok(())
+    }
+
+    pub fn stop_watch(&mut self, path: &Path) -> Result<()> {
+        let root = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if let Ok(mut log) = self.log.lock() {
+            log.failures.remove(&root);
+        }
+        self.watches
+            .remove(&root)
+            .map(|_| info!("Stopped watching {}", root.display()))
+            .ok_or_else(|| anyhow::anyhow!("{} is not being watched", path.display()))
+    }
+
+    /// Every watch, including ones stopped by an error since the last call
+    pub fn list_watches(&mut self) -> Vec<WatchStatus> {
+        self.refresh();
+        let log = self.log.lock().map(|log| log.failures.clone()).unwrap_or_default();
+        let mut statuses: Vec<WatchStatus> = self
+            .watches
+            .keys()
+            .map(|path| WatchStatus { path: path.to_string_lossy().to_string(), active: true, error: None })
+            .chain(log.into_iter().map(|(path, error)| WatchStatus {
+                path: path.to_string_lossy().to_string(),
+                active: false,
+                error: Some(error),
+            }))
+            .collect();
+        statuses.sort_by(|a, b| a.path.cmp(&b.path));
+        statuses
+    }
+
+    /// Most recently modified files first
+    pub fn recently_modified(&mut self, limit: usize) -> Vec<RecentFile> {
+        self.refresh();
+        let Ok(mut log) = self.log.lock() else {
+            return Vec::new();
+        };
+        log.settle(self.debounce, self.max_tracked);
+
+        let mut files: Vec<RecentFile> = log
+            .recent
+            .iter()
+            .map(|(path, modified_at)| RecentFile { path: path.to_string_lossy().to_string(), modified_at: *modified_at })
+            .collect();
+        files.sort_by(|a, b| b.modified_at.cmp(&a.modified_at).then_with(|| a.path.cmp(&b.path)));
+        files.truncate(limit);
+        files
+    }
+
+    fn refresh(&mut self) {
+        self.watch_new_dirs();
+        self.reap_failed();
+    }
+
+    /// Watch directories created since the last call. Files already written into them
+    /// count as modified, since their events came before the watch existed.
+    fn watch_new_dirs(&mut self) {
+        let Ok(mut log) = self.log.lock() else {
+            return;
+        };
+        for dir in std::mem::take(&mut log.new_dirs) {
+            let Some((root, watch)) = self.watches.iter_mut().find(|(root, _)| dir.starts_with(root)) else {
+                continue;
+            };
+            if let Err(e) = watch.add_tree(root, &dir) {
+                warn!("File watch on {} failed: {}", root.display(), e);
+                log.failures.insert(root.clone(), describe_error(&e));
+                continue;
+            }
+            let files = walkdir::WalkDir::new(&dir)
+                .into_iter()
+                .filter_entry(|entry| !is_ignored(root, entry.path()))
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file());
+            for entry in files {
+                let modified_at = entry.metadata().ok().and_then(|metadata| metadata.modified().ok()).map(DateTime::<Utc>::from);
+                log.record_pending(entry.into_path(), modified_at.unwrap_or_else(Utc::now));
+            }
+        }
+    }
+
+    /// Drop watchers whose callback reported an error; the error stays visible in `list_watches`
+    fn reap_failed(&mut self) {
+        let failed: Vec<PathBuf> = match self.log.lock() {
+            Ok(log) => self.watches.keys().filter(|root| log.failures.contains_key(*root)).cloned().collect(),
+            Err(_) => return,
+        };
+        for root in failed {
+            warn!("Stopping failed file watch on {}", root.display());
+            self.watches.remove(&root);
+        }
+    }
+}
+
+fn record_event(log: &mut ActivityLog, watch_root: &Path, event: Event) {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(notify::event::ModifyKind::Data(_) | notify::event::ModifyKind::Name(_) | notify::event::ModifyKind::Any) => {
+            for path in event.paths {
+                if is_ignored(watch_root, &path) {
+                    continue;
+                }
+                if path.is_dir() {
+                    if log.new_dirs.len() < MAX_PENDING {
+                        log.new_dirs.insert(path);
+                    }
+                    continue;
+                }
+                log.record_pending(path, Utc::now());
+            }
+        }
+        EventKind::Remove(_) => {
+            for path in event.paths {
+                if path == watch_root {
+                    log.failures.insert(watch_root.to_path_buf(), "watched directory was deleted".to_string());
+                }
+                log.pending.remove(&path);
+                log.recent.remove(&path);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_ignored(watch_root: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(watch_root).unwrap_or(path);
+    relative.components().any(|component| {
+        component.as_os_str().to_str().is_some_and(|name| IGNORED_DIRS.contains(&name))
+    }) || path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with('~') || name.ends_with(".swp") || name.ends_with(".swx") || name.starts_with(".#"))
+}
+
+/// Explain watch errors the user can act on, such as the inotify watch limit
+fn describe_error(error: &notify::Error) -> String {
+    match &error.kind {
+        notify::ErrorKind::MaxFilesWatch => {
+            "too many files to watch; raise fs.inotify.max_user_watches or watch a smaller directory".to_string()
+        }
+        notify::ErrorKind::Io(io) if io.raw_os_error() == Some(24) => {
+            "too many open files; close other watches or raise the open file limit".to_string()
+        }
+        notify::ErrorKind::PathNotFound => "directory no longer exists".to_string(),
+        _ => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for(manager: &mut FileWatchManager, condition: impl Fn(&[RecentFile]) -> bool) -> Vec<RecentFile> {
+        for _ in 0..50 {
+            let files = manager.recently_modified(10);
+            if condition(&files) {
+                return files;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        manager.recently_modified(10)
+    }
+
+    fn names(files: &[RecentFile]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| Path::new(&file.path).file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_modified_files_are_tracked() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("existing.txt"), "old").unwrap();
+
+        let mut manager = FileWatchManager::new().with_debounce(Duration::from_millis(100));
+        manager.start_watch(dir.path()).unwrap();
+
+        std::fs::write(dir.path().join("src").join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join(".git").join("index"), "ignored").unwrap();
+        std::fs::write(dir.path().join("notes.md~"), "ignored").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(dir.path().join("existing.txt"), "new").unwrap();
+
+        let files = wait_for(&mut manager, |files| files.len() >= 2);
+        assert_eq!(names(&files), vec!["existing.txt", "main.rs"]);
+
+        std::fs::remove_file(dir.path().join("existing.txt")).unwrap();
+        let files = wait_for(&mut manager, |files| files.len() == 1);
+        assert_eq!(names(&files), vec!["main.rs"]);
+
+        manager.stop_watch(dir.path()).unwrap();
+        assert!(manager.list_watches().is_empty());
+    }
+
+    #[test]
+    fn test_tracked_set_is_capped_and_debounced() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = FileWatchManager::new()
+            .with_debounce(Duration::from_millis(300))
+            .with_max_tracked(3);
+        manager.start_watch(dir.path()).unwrap();
+
+        for i in 0..5 {
+            std::fs::write(dir.path().join(format!("file{}.txt", i)), "x").unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // Still inside the debounce window
+        assert!(manager.recently_modified(10).is_empty());
+
+        let files = wait_for(&mut manager, |files| names(files).contains(&"file4.txt".to_string()));
+        assert_eq!(names(&files), vec!["file4.txt", "file3.txt", "file2.txt"]);
+    }
+
+    #[test]
+    fn test_ignored_directories_are_never_watched() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules").join("lodash")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+
+        let mut manager = FileWatchManager::new().with_debounce(Duration::from_millis(50));
+        manager.start_watch(dir.path()).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let watched = |manager: &FileWatchManager| {
+            let mut dirs: Vec<String> = manager.watches[&root]
+                .dirs
+                .iter()
+                .map(|dir| dir.strip_prefix(&root).unwrap().to_string_lossy().to_string())
+                .collect();
+            dirs.sort();
+            dirs
+        };
+        assert_eq!(watched(&manager), vec!["", "src"]);
+
+        // New directories are watched once the manager next looks, and files already in them count
+        std::fs::create_dir_all(root.join("docs").join("target")).unwrap();
+        std::fs::write(root.join("docs").join("guide.md"), "x").unwrap();
+        let files = wait_for(&mut manager, |files| !files.is_empty());
+        assert_eq!(names(&files), vec!["guide.md"]);
+        assert_eq!(watched(&manager), vec!["", "docs", "src"]);
+
+        std::fs::write(root.join("src").join("lib.rs"), "x").unwrap();
+        let files = wait_for(&mut manager, |files| files.len() == 2);
+        assert_eq!(names(&files)[0], "lib.rs");
+    }
+
+    #[test]
+    fn test_pending_events_are_capped() {
+        let mut log = ActivityLog::default();
+        for i in 0..MAX_PENDING + 10 {
+            log.record_pending(PathBuf::from(format!("/project/file{}", i)), Utc::now());
+        }
+        assert_eq!(log.pending.len(), MAX_PENDING);
+        // Paths already pending still have their time refreshed
+        log.record_pending(PathBuf::from("/project/file0"), Utc::now());
+        assert_eq!(log.pending.len(), MAX_PENDING);
+    }
+
+    #[test]
+    fn test_deleted_watch_root_stops_the_watch() {
+        let parent = tempfile::tempdir().unwrap();
+        let watched = parent.path().join("project");
+        std::fs::create_dir_all(&watched).unwrap();
+
+        let mut manager = FileWatchManager::new();
+        manager.start_watch(&watched).unwrap();
+        std::fs::remove_dir_all(&watched).unwrap();
+
+        let mut statuses = manager.list_watches();
+        for _ in 0..50 {
+            if !statuses[0].active {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+            statuses = manager.list_watches();
+        }
+        assert!(!statuses[0].active);
+        assert!(statuses[0].error.is_some());
+        assert!(manager.start_watch(&watched).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use git2::{Repository, StatusOptions};
+use sha2::{Digest, Sha256};
 
 pub fn get_status(path: &str) -> Result<String> {
     let repo = Repository::open(path)
@@ -16,6 +17,7 @@ pub fn get_status(path: &str) -> Result<String> {
     for entry in statuses.iter() {
         let status = entry.status();
         let path = entry.path().unwrap_or("<unknown>");
+        let lfs = if lfs_tracked(&repo, path) { " [lfs]" } else { "" };
         
         let status_str = match status {
             s if s.is_wt_new() => "??",
@@ -29,7 +31,7 @@ pub fn get_status(path: &str) -> Result<String> {
             _ => "  ",
         };
         
-        result.push_str(&format!("{} {}\n", status_str, path));
+        result.push_str(&format!("{} {}{}\n", status_str, path, lfs));
     }
     
     Ok(result)
@@ -43,7 +45,12 @@ pub fn get_diff(path: &str) -> Result<String> {
     let diff = repo.diff_tree_to_workdir(Some(&head), None)?;
     
     let mut result = String::new();
-    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+    diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
+        // Pointer text says nothing about the asset; LFS changes are reported by `get_diff_stat`
+        let file = delta.new_file().path().or_else(|| delta.old_file().path());
+        if file.is_some_and(|file| lfs_tracked(&repo, &file.to_string_lossy())) {
+            return true;
+        }
         match line.origin() {
             '+' | '-' | ' ' => result.push(line.origin()),
             _ => {}
@@ -426,6 +433,76 @@ pub struct StashEntry {
     pub oid: String,
 }
 
+/// Outcome of applying or popping a stash
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StashApplyResult {
+    Applied,
+    /// The stash was applied with conflict markers in `files`; a popped stash is kept
+    Conflicted { files: Vec<String> },
+}
+
+/// Stash local changes, optionally including untracked files, and return the new stash
+pub fn create_stash(path: &str, message: Option<String>, include_untracked: bool) -> Result<StashEntry> {
+    let before = get_stash_list(path)?.len();
+    let mut args = vec!["stash", "push"];
+    if include_untracked {
+        args.push("--include-untracked");
+    }
+    if let Some(message) = message.as_deref() {
+        args.extend(["--message", message]);
+    }
+    run_git(path, &args)?;
+
+    let mut stashes = get_stash_list(path)?;
+    if stashes.len() == before {
+        return Err(anyhow::anyhow!("No local changes to stash"));
+    }
+    Ok(stashes.remove(0))
+}
+
+/// Apply stash `index`, keeping it in the stash list
+pub fn apply_stash(path: &str, index: usize) -> Result<StashApplyResult> {
+    run_stash_apply(path, "apply", index)
+}
+
+/// Apply stash `index` and drop it, unless applying it conflicted
+pub fn pop_stash(path: &str, index: usize) -> Result<StashApplyResult> {
+    run_stash_apply(path, "pop", index)
+}
+
+pub fn drop_stash(path: &str, index: usize) -> Result<()> {
+    let stash = stash_ref(path, index)?;
+    run_git(path, &["stash", "drop", &stash])?;
+    Ok(())
+}
+
+fn run_stash_apply(path: &str, action: &str, index: usize) -> Result<StashApplyResult> {
+    let stash = stash_ref(path, index)?;
+    match run_git(path, &["stash", action, &stash]) {
+        Ok(_) => Ok(StashApplyResult::Applied),
+        Err(e) => {
+            // A merge conflict leaves conflicted index entries; anything else (e.g. local
+            // changes that would be overwritten) changed nothing and is a plain error
+            let files: Vec<String> = get_conflicts(path)?.into_iter().map(|f| f.path).collect();
+            if files.is_empty() {
+                Err(e)
+            } else {
+                Ok(StashApplyResult::Conflicted { files })
+            }
+        }
+    }
+}
+
+/// `stash@{index}`, after checking the stash exists
+fn stash_ref(path: &str, index: usize) -> Result<String> {
+    let count = get_stash_list(path)?.len();
+    if index >= count {
+        return Err(anyhow::anyhow!("Stash {} does not exist ({} stashes)", index, count));
+    }
+    Ok(format!("stash@{{{}}}", index))
+}
+
 /// Get file changes for specific commit
 pub fn get_commit_changes(path: &str, commit_hash: &str) -> Result<Vec<FileChange>> {
     let repo = Repository::open(path)
@@ -473,12 +550,23 @@ pub fn get_commit_changes(path: &str, commit_hash: &str) -> Result<Vec<FileChang
                 .or_else(|| old_file.path())
                 .map(|p| p.display().to_string())
                 .unwrap_or_else(|| "<unknown>".to_string());
+
+            let lfs = lfs_tracked(&repo, &file_path);
+            let lfs_size = if !lfs {
+                None
+            } else if delta.status() == git2::Delta::Deleted {
+                blob_lfs_size(&repo, old_file.id())
+            } else {
+                blob_lfs_size(&repo, new_file.id())
+            };
             
             changes.push(FileChange {
                 path: file_path,
                 change_type: change_type.to_string(),
                 additions: 0, // Would need line-by-line diff for accurate count
                 deletions: 0, // Would need line-by-line diff for accurate count
+                lfs,
+                lfs_size,
             });
             
             true
@@ -497,6 +585,37 @@ pub struct FileChange {
     pub change_type: String,
     pub additions: usize,
     pub deletions: usize,
+    /// Stored as a Git LFS pointer
+    #[serde(default)]
+    pub lfs: bool,
+    /// Size of the LFS object the pointer refers to
+    #[serde(default)]
+    pub lfs_size: Option<u64>,
+}
+
+/// Whether `file` (relative to the repository root) is stored with Git LFS, per `.gitattributes`
+pub fn is_lfs_tracked(path: &str, file: &str) -> Result<bool> {
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+    Ok(lfs_tracked(&repo, file))
+}
+
+fn lfs_tracked(repo: &Repository, file: &str) -> bool {
+    repo.get_attr(std::path::Path::new(file), "filter", git2::AttrCheckFlags::FILE_THEN_INDEX)
+        .is_ok_and(|filter| filter == Some("lfs"))
+}
+
+/// Object size from the text of an LFS pointer file
+fn parse_lfs_pointer(content: &[u8]) -> Option<u64> {
+    let content = std::str::from_utf8(content).ok()?;
+    if !content.starts_with("version https://git-lfs.github.com/spec/") {
+        return None;
+    }
+    content.lines().find_map(|line| line.strip_prefix("size ")?.trim().parse().ok())
+}
+
+fn blob_lfs_size(repo: &Repository, id: git2::Oid) -> Option<u64> {
+    parse_lfs_pointer(repo.find_blob(id).ok()?.content())
 }
 
 /// Get detailed repository statistics
@@ -607,3 +726,1170 @@ fn count_tags(repo: &Repository) -> Result<usize> {
     
     Ok(count)
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TagInfo {
+    pub name: String,
+    /// Object the tag points at once annotations are peeled; a commit unless the tag names a tree or blob
+    pub target: String,
+    pub annotated: bool,
+    /// `Name <email>` of the tagger, annotated tags only
+    pub tagger: Option<String>,
+    pub message: Option<String>,
+}
+
+/// List tags in version order, so `v1.10` sorts after `v1.9`
+pub fn list_tags(path: &str) -> Result<Vec<TagInfo>> {
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        tags.push(tag_info(&repo, name)?);
+    }
+
+    tags.sort_by(|a, b| compare_versions(&a.name, &b.name));
+    Ok(tags)
+}
+
+/// Tag `commit` (HEAD by default); a message makes it an annotated tag
+pub fn create_tag(path: &str, name: &str, message: Option<String>, commit: Option<String>) -> Result<TagInfo> {
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+
+    if repo.find_reference(&format!("refs/tags/{}", name)).is_ok() {
+        return Err(anyhow::anyhow!("Tag '{}' already exists", name));
+    }
+
+    let spec = commit.as_deref().unwrap_or("HEAD");
+    let target = repo.revparse_single(spec)
+        .and_then(|object| object.peel(git2::ObjectType::Commit))
+        .with_context(|| format!("Cannot resolve commit '{}'", spec))?;
+
+    match message {
+        Some(message) => {
+            let tagger = repo.signature().context("Tagger identity is not configured")?;
+            repo.tag(name, &target, &tagger, &message, false)?;
+        }
+        None => {
+            repo.tag_lightweight(name, &target, false)?;
+        }
+    }
+
+    tag_info(&repo, name)
+}
+
+pub fn delete_tag(path: &str, name: &str) -> Result<()> {
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+    repo.tag_delete(name)
+        .with_context(|| format!("Failed to delete tag '{}'", name))
+}
+
+fn tag_info(repo: &Repository, name: &str) -> Result<TagInfo> {
+    let reference = repo.find_reference(&format!("refs/tags/{}", name))
+        .with_context(|| format!("Tag '{}' not found", name))?;
+    let target = reference.peel(git2::ObjectType::Any)?.id().to_string();
+
+    let tag = reference.peel_to_tag().ok();
+    Ok(TagInfo {
+        name: name.to_string(),
+        target,
+        annotated: tag.is_some(),
+        tagger: tag.as_ref().and_then(|t| t.tagger()).map(|sig| {
+            format!("{} <{}>", sig.name().unwrap_or(""), sig.email().unwrap_or(""))
+        }),
+        message: tag.as_ref().and_then(|t| t.message()).map(|m| m.trim_end().to_string()),
+    })
+}
+
+/// Natural ordering: runs of digits compare numerically, everything else byte-wise
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let mut a = a.as_bytes();
+    let mut b = b.as_bytes();
+
+    while let (Some(&ca), Some(&cb)) = (a.first(), b.first()) {
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let a_len = a.iter().take_while(|c| c.is_ascii_digit()).count();
+            let b_len = b.iter().take_while(|c| c.is_ascii_digit()).count();
+            let a_num = std::str::from_utf8(&a[..a_len]).unwrap_or("").trim_start_matches('0');
+            let b_num = std::str::from_utf8(&b[..b_len]).unwrap_or("").trim_start_matches('0');
+
+            let ordering = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+            if ordering != std::cmp::Ordering::Equal {
+                return ordering;
+            }
+            a = &a[a_len..];
+            b = &b[b_len..];
+        } else {
+            if ca != cb {
+                return ca.cmp(&cb);
+            }
+            a = &a[1..];
+            b = &b[1..];
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictType {
+    BothModified,
+    BothAdded,
+    BothDeleted,
+    DeletedByUs,
+    DeletedByThem,
+    AddedByUs,
+    AddedByThem,
+}
+
+/// One `<<<<<<<` ... `>>>>>>>` block in a conflicted file
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConflictHunk {
+    /// Line of the `<<<<<<<` marker, 1-based
+    pub start_line: usize,
+    /// Line of the `>>>>>>>` marker
+    pub end_line: usize,
+    pub ours: String,
+    pub theirs: String,
+    /// Common ancestor content, only present with `merge.conflictStyle=diff3`
+    pub base: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ConflictedFile {
+    pub path: String,
+    pub conflict_type: ConflictType,
+    /// Binary files have no hunks and can only be resolved with ours or theirs
+    pub binary: bool,
+    pub hunks: Vec<ConflictHunk>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "content", rename_all = "snake_case")]
+pub enum ConflictResolution {
+    Ours,
+    Theirs,
+    Custom(String),
+}
+
+/// List files left conflicted by a merge or rebase, with their parsed conflict hunks
+pub fn get_conflicts(path: &str) -> Result<Vec<ConflictedFile>> {
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+    let workdir = repo.workdir().context("Repository has no working directory")?;
+    let index = repo.index()?;
+
+    let mut files = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let entry = conflict.our.as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref())
+            .context("Conflict has no index entries")?;
+        let file_path = String::from_utf8_lossy(&entry.path).to_string();
+
+        let conflict_type = match (&conflict.ancestor, &conflict.our, &conflict.their) {
+            (Some(_), Some(_), Some(_)) => ConflictType::BothModified,
+            (None, Some(_), Some(_)) => ConflictType::BothAdded,
+            (Some(_), None, Some(_)) => ConflictType::DeletedByUs,
+            (Some(_), Some(_), None) => ConflictType::DeletedByThem,
+            (None, Some(_), None) => ConflictType::AddedByUs,
+            (None, None, Some(_)) => ConflictType::AddedByThem,
+            _ => ConflictType::BothDeleted,
+        };
+
+        let mut binary = false;
+        for side in [&conflict.our, &conflict.their].into_iter().flatten() {
+            binary |= repo.find_blob(side.id)?.is_binary();
+        }
+
+        let hunks = match std::fs::read(workdir.join(&file_path)) {
+            Ok(bytes) if !binary => parse_conflict_hunks(&String::from_utf8_lossy(&bytes)),
+            _ => Vec::new(),
+        };
+
+        files.push(ConflictedFile {
+            path: file_path,
+            conflict_type,
+            binary,
+            hunks,
+        });
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Resolve a conflicted file with our side, their side, or custom content, and stage it
+pub fn resolve_conflict(path: &str, file: &str, resolution: ConflictResolution) -> Result<()> {
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+    let workdir = repo.workdir().context("Repository has no working directory")?.to_path_buf();
+    let mut index = repo.index()?;
+
+    let conflict = index.conflicts()?
+        .filter_map(|c| c.ok())
+        .find(|c| {
+            [&c.our, &c.their, &c.ancestor].into_iter().flatten()
+                .any(|entry| entry.path == file.as_bytes())
+        })
+        .with_context(|| format!("{} is not conflicted", file))?;
+
+    let side = match resolution {
+        ConflictResolution::Ours => conflict.our,
+        ConflictResolution::Theirs => conflict.their,
+        ConflictResolution::Custom(content) => {
+            let binary = [&conflict.our, &conflict.their].into_iter().flatten()
+                .any(|entry| repo.find_blob(entry.id).map(|b| b.is_binary()).unwrap_or(false));
+            if binary {
+                return Err(anyhow::anyhow!("{} is binary; resolve it with ours or theirs", file));
+            }
+            std::fs::write(workdir.join(file), content)
+                .with_context(|| format!("Failed to write {}", file))?;
+            index.add_path(std::path::Path::new(file))?;
+            index.write()?;
+            return Ok(());
+        }
+    };
+
+    let file_path = std::path::Path::new(file);
+    match side {
+        Some(entry) => {
+            let blob = repo.find_blob(entry.id)?;
+            std::fs::write(workdir.join(file), blob.content())
+                .with_context(|| format!("Failed to write {}", file))?;
+            index.add_path(file_path)?;
+        }
+        // The chosen side deleted the file
+        None => {
+            let full_path = workdir.join(file);
+            if full_path.exists() {
+                std::fs::remove_file(&full_path)
+                    .with_context(|| format!("Failed to remove {}", file))?;
+            }
+            index.remove_path(file_path)?;
+        }
+    }
+    index.write()?;
+    Ok(())
+}
+
+/// One `@@` hunk of a file's diff, stageable on its own
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Hunk {
+    /// Hash of the hunk's text; changes whenever the hunk does
+    pub id: String,
+    /// The `@@ -a,b +c,d @@` line
+    pub header: String,
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    /// Header line and body, as `git diff` printed them
+    pub patch: String,
+}
+
+/// Hunks of `file` in the working tree that are not staged. Untracked files have none.
+pub fn get_unstaged_hunks(path: &str, file: &str) -> Result<Vec<Hunk>> {
+    Ok(file_hunks(path, file, false)?.1)
+}
+
+/// Hunks of `file` staged in the index
+pub fn get_staged_hunks(path: &str, file: &str) -> Result<Vec<Hunk>> {
+    Ok(file_hunks(path, file, true)?.1)
+}
+
+/// Add one unstaged hunk of `file` to the index, leaving the rest of the file unstaged
+pub fn stage_hunk(path: &str, file: &str, hunk_id: &str) -> Result<()> {
+    apply_hunk(path, file, hunk_id, false)
+}
+
+/// Remove one staged hunk of `file` from the index, keeping it in the working tree
+pub fn unstage_hunk(path: &str, file: &str, hunk_id: &str) -> Result<()> {
+    apply_hunk(path, file, hunk_id, true)
+}
+
+/// Re-diffs `file` so the hunk is applied exactly as it stands now; a hunk read before the
+/// file or index changed will no longer be found
+fn apply_hunk(path: &str, file: &str, hunk_id: &str, staged: bool) -> Result<()> {
+    let (file_header, hunks) = file_hunks(path, file, staged)?;
+    let hunk = hunks.iter().find(|hunk| hunk.id == hunk_id).with_context(|| {
+        format!(
+            "Hunk {} of {} no longer applies: the file changed since its hunks were read",
+            hunk_id, file
+        )
+    })?;
+
+    let patch = format!("{}{}", file_header, hunk.patch);
+    let mut args = vec!["apply", "--cached", "--whitespace=nowarn"];
+    if staged {
+        args.push("--reverse");
+    }
+    run_git_with_input(path, &args, &patch)
+        .with_context(|| format!("Hunk {} of {} does not apply cleanly to the index", hunk_id, file))?;
+    Ok(())
+}
+
+/// The `diff --git` ... `+++` lines and the hunks of `file`'s diff against the index
+/// (working tree changes) or HEAD (`staged`)
+fn file_hunks(path: &str, file: &str, staged: bool) -> Result<(String, Vec<Hunk>)> {
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff", "--no-renames"];
+    if staged {
+        args.push("--cached");
+    }
+    args.extend(["--", file]);
+    Ok(parse_hunks(&run_git(path, &args)?))
+}
+
+fn parse_hunks(diff: &str) -> (String, Vec<Hunk>) {
+    let mut file_header = String::new();
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("@@") {
+            let header = line.trim_end().to_string();
+            let (old_start, old_lines, new_start, new_lines) = parse_hunk_header(&header).unwrap_or_default();
+            hunks.push(Hunk { id: String::new(), header, old_start, old_lines, new_start, new_lines, patch: String::new() });
+        }
+        match hunks.last_mut() {
+            Some(hunk) => hunk.patch.push_str(line),
+            None => file_header.push_str(line),
+        }
+    }
+    // The `index` line and the `+` range change whenever another part of the file is edited,
+    // so only the lines being replaced and where they sit in the old side identify a hunk
+    for hunk in &mut hunks {
+        let body = hunk.patch.split_once('\n').map_or("", |(_, body)| body);
+        let digest = Sha256::digest(format!("-{},{}\n{}", hunk.old_start, hunk.old_lines, body));
+        hunk.id = format!("{:x}", digest)[..12].to_string();
+    }
+    (file_header, hunks)
+}
+
+/// `@@ -1,3 +1,4 @@ fn main` -> (1, 3, 1, 4); an omitted count means one line
+fn parse_hunk_header(header: &str) -> Option<(usize, usize, usize, usize)> {
+    let mut ranges = header.strip_prefix("@@ ")?.split_whitespace();
+    let range = |range: &str, sign: char| -> Option<(usize, usize)> {
+        let range = range.strip_prefix(sign)?;
+        match range.split_once(',') {
+            Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(ranges.next()?, '-')?;
+    let (new_start, new_lines) = range(ranges.next()?, '+')?;
+    Some((old_start, old_lines, new_start, new_lines))
+}
+
+/// One entry of `git worktree list`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WorktreeInfo {
+    pub path: String,
+    /// Short branch name; `None` for a detached HEAD or a bare repository
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    pub locked: bool,
+    /// The worktree directory is gone and `git worktree prune` would drop it
+    pub prunable: bool,
+}
+
+pub fn list_worktrees(path: &str) -> Result<Vec<WorktreeInfo>> {
+    let output = run_git(path, &["worktree", "list", "--porcelain"])?;
+    Ok(parse_worktree_list(&output))
+}
+
+/// Check out `branch` in a new worktree at `new_path`, creating the branch from HEAD if needed
+pub fn add_worktree(repo_path: &str, new_path: &str, branch: &str) -> Result<WorktreeInfo> {
+    if branch.starts_with('-') || !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+        return Err(anyhow::anyhow!("Invalid branch name '{}'", branch));
+    }
+    let repo = Repository::open(repo_path)
+        .context("Failed to open git repository")?;
+
+    if let Some(existing) = list_worktrees(repo_path)?
+        .into_iter()
+        .find(|w| w.branch.as_deref() == Some(branch))
+    {
+        return Err(anyhow::anyhow!(
+            "Branch '{}' is already checked out in worktree {}",
+            branch,
+            existing.path
+        ));
+    }
+
+    if repo.find_branch(branch, git2::BranchType::Local).is_ok() {
+        run_git(repo_path, &["worktree", "add", "--", new_path, branch])?;
+    } else {
+        run_git(repo_path, &["worktree", "add", "-b", branch, "--", new_path])?;
+    }
+
+    let new_path = std::fs::canonicalize(new_path)
+        .with_context(|| format!("Worktree {} was not created", new_path))?;
+    list_worktrees(repo_path)?
+        .into_iter()
+        .find(|w| same_path(&w.path, &new_path))
+        .context("New worktree is missing from the worktree list")
+}
+
+/// Remove a linked worktree; `force` discards uncommitted changes in it
+pub fn remove_worktree(repo_path: &str, worktree_path: &str, force: bool) -> Result<()> {
+    let mut args = vec!["worktree", "remove"];
+    if force {
+        args.push("--force");
+    }
+    args.extend(["--", worktree_path]);
+    run_git(repo_path, &args)?;
+    Ok(())
+}
+
+/// A submodule of a superproject, as declared in `.gitmodules` and reported by `git submodule status`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SubmoduleInfo {
+    pub name: String,
+    pub path: String,
+    pub url: Option<String>,
+    pub branch: Option<String>,
+    /// Commit the superproject records for the submodule
+    pub recorded_commit: Option<String>,
+    /// Commit checked out in the submodule; `None` until it is initialized
+    pub current_commit: Option<String>,
+    pub initialized: bool,
+    /// The checked-out commit differs from the recorded one
+    pub out_of_sync: bool,
+    /// The submodule has merge conflicts in the superproject
+    pub conflicted: bool,
+}
+
+/// Every submodule declared in `.gitmodules`, with its checkout state
+pub fn get_submodules(path: &str) -> Result<Vec<SubmoduleInfo>> {
+    let workdir = Repository::open(path)
+        .context("Failed to open git repository")?
+        .workdir()
+        .context("Repository has no working directory")?
+        .to_path_buf();
+    if !workdir.join(".gitmodules").exists() {
+        return Ok(Vec::new());
+    }
+
+    // Exits non-zero when no key matches, e.g. for an empty .gitmodules
+    let declared = run_git(path, &["config", "-f", ".gitmodules", "--get-regexp", r"^submodule\..*\.(path|url|branch)$"])
+        .unwrap_or_default();
+    let mut submodules = parse_gitmodules(&declared);
+
+    let current = parse_submodule_status(&run_git(path, &["submodule", "status"])?);
+    let recorded = parse_submodule_status(&run_git(path, &["submodule", "status", "--cached"])?);
+    for submodule in &mut submodules {
+        if let Some((flag, commit)) = current.iter().find(|(p, _, _)| *p == submodule.path).map(|(_, f, c)| (*f, c)) {
+            submodule.initialized = flag != '-';
+            submodule.out_of_sync = flag == '+';
+            submodule.conflicted = flag == 'U';
+            if submodule.initialized && !submodule.conflicted {
+                submodule.current_commit = Some(commit.clone());
+            }
+        }
+        submodule.recorded_commit = recorded
+            .iter()
+            .find(|(p, _, _)| *p == submodule.path)
+            .map(|(_, _, commit)| commit.clone())
+            .filter(|commit| !commit.trim_start_matches('0').is_empty());
+    }
+    Ok(submodules)
+}
+
+/// Check out the recorded commit of every submodule; `init` also sets up uninitialized
+/// ones and `recursive` descends into nested submodules
+pub fn update_submodules(path: &str, init: bool, recursive: bool) -> Result<Vec<SubmoduleInfo>> {
+    let mut args = vec!["submodule", "update"];
+    if init {
+        args.push("--init");
+    }
+    if recursive {
+        args.push("--recursive");
+    }
+    run_git(path, &args)?;
+    get_submodules(path)
+}
+
+/// Submodules from `git config --get-regexp` output over `.gitmodules`, in declaration order
+fn parse_gitmodules(output: &str) -> Vec<SubmoduleInfo> {
+    let mut submodules: Vec<SubmoduleInfo> = Vec::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        // Names may themselves contain dots, so split the setting off the end
+        let Some((name, setting)) = key.strip_prefix("submodule.").and_then(|k| k.rsplit_once('.')) else {
+            continue;
+        };
+
+        let index = match submodules.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                submodules.push(SubmoduleInfo {
+                    name: name.to_string(),
+                    path: name.to_string(),
+                    url: None,
+                    branch: None,
+                    recorded_commit: None,
+                    current_commit: None,
+                    initialized: false,
+                    out_of_sync: false,
+                    conflicted: false,
+                });
+                submodules.len() - 1
+            }
+        };
+        let submodule = &mut submodules[index];
+        match setting {
+            "path" => submodule.path = value.to_string(),
+            "url" => submodule.url = Some(value.to_string()),
+            "branch" => submodule.branch = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    submodules
+}
+
+/// `(path, state flag, commit)` for each line of `git submodule status`
+fn parse_submodule_status(output: &str) -> Vec<(String, char, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let flag = line.chars().next()?;
+            let mut fields = line[flag.len_utf8()..].split_whitespace();
+            let commit = fields.next()?.to_string();
+            let path = fields.next()?.to_string();
+            Some((path, flag, commit))
+        })
+        .collect()
+}
+
+/// Size of a change: line counts per file and in total
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DiffStat {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files: Vec<FileDiffStat>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FileDiffStat {
+    pub path: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Binary files have no line counts
+    pub binary: bool,
+    /// Stored as a Git LFS pointer; pointer text is not counted as changed lines
+    #[serde(default)]
+    pub lfs: bool,
+    /// Size of the LFS object the pointer refers to
+    #[serde(default)]
+    pub lfs_size: Option<u64>,
+}
+
+/// Diff statistics for commit `rev`, or for the working tree against HEAD when `rev` is `None`
+pub fn get_diff_stat(path: &str, rev: Option<String>) -> Result<DiffStat> {
+    let output = match rev.as_deref() {
+        Some(rev) if rev.starts_with('-') => return Err(anyhow::anyhow!("Invalid revision '{}'", rev)),
+        // --root so the first commit is compared with the empty tree
+        Some(rev) => run_git(path, &["diff-tree", "-r", "--root", "--no-commit-id", "--no-renames", "--numstat", rev])?,
+        None => run_git(path, &["diff", "--no-renames", "--numstat", "HEAD"])?,
+    };
+    let mut stat = parse_numstat(&output);
+
+    let repo = Repository::open(path)
+        .context("Failed to open git repository")?;
+    for file in stat.files.iter_mut().filter(|file| lfs_tracked(&repo, &file.path)) {
+        stat.insertions -= file.insertions;
+        stat.deletions -= file.deletions;
+        file.insertions = 0;
+        file.deletions = 0;
+        file.binary = false;
+        file.lfs = true;
+        file.lfs_size = lfs_object_size(path, &repo, rev.as_deref(), &file.path);
+    }
+    Ok(stat)
+}
+
+/// Size of the LFS object for `file` as of `rev` (the working tree when `None`), falling back
+/// to the parent commit, or HEAD, when the file was deleted
+fn lfs_object_size(path: &str, repo: &Repository, rev: Option<&str>, file: &str) -> Option<u64> {
+    let from_rev = |spec: String| {
+        let blob = repo.revparse_single(&spec).ok()?.peel_to_blob().ok()?;
+        parse_lfs_pointer(blob.content())
+    };
+    match rev {
+        Some(rev) => from_rev(format!("{}:{}", rev, file)).or_else(|| from_rev(format!("{}^:{}", rev, file))),
+        None => match std::fs::read(std::path::Path::new(path).join(file)) {
+            // With git-lfs installed the checkout holds the real content rather than the pointer
+            Ok(content) => parse_lfs_pointer(&content).or(Some(content.len() as u64)),
+            Err(_) => from_rev(format!("HEAD:{}", file)),
+        },
+    }
+}
+
+fn parse_numstat(output: &str) -> DiffStat {
+    let mut stat = DiffStat::default();
+    for line in output.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(file)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        // Binary files are listed as `-\t-\tpath`
+        let binary = added == "-" && deleted == "-";
+        let insertions = added.parse().unwrap_or(0);
+        let deletions = deleted.parse().unwrap_or(0);
+
+        stat.insertions += insertions;
+        stat.deletions += deletions;
+        stat.files.push(FileDiffStat {
+            path: file.to_string(),
+            insertions,
+            deletions,
+            binary,
+            lfs: false,
+            lfs_size: None,
+        });
+    }
+    stat.files_changed = stat.files.len();
+    stat
+}
+
+fn parse_worktree_list(output: &str) -> Vec<WorktreeInfo> {
+    let mut worktrees = Vec::new();
+    let mut current: Option<WorktreeInfo> = None;
+
+    for line in output.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key == "worktree" {
+            worktrees.extend(current.take());
+            current = Some(WorktreeInfo {
+                path: value.to_string(),
+                branch: None,
+                head: None,
+                locked: false,
+                prunable: false,
+            });
+            continue;
+        }
+        let Some(worktree) = current.as_mut() else {
+            continue;
+        };
+
+        match key {
+            "HEAD" => worktree.head = Some(value.to_string()),
+            "branch" => worktree.branch = Some(value.trim_start_matches("refs/heads/").to_string()),
+            "locked" => worktree.locked = true,
+            "prunable" => worktree.prunable = true,
+            _ => {}
+        }
+    }
+
+    worktrees.extend(current);
+    worktrees
+}
+
+fn same_path(listed: &str, path: &std::path::Path) -> bool {
+    std::fs::canonicalize(listed).is_ok_and(|listed| listed == path)
+}
+
+fn run_git(repo_path: &str, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.iter().take(2).copied().collect::<Vec<_>>().join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn run_git_with_input(repo_path: &str, args: &[&str], input: &str) -> Result<String> {
+    use std::io::Write;
+
+    let mut child = std::process::Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+    child.stdin.take().context("git stdin unavailable")?.write_all(input.as_bytes())?;
+    let output = child.wait_with_output().context("Failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.iter().take(2).copied().collect::<Vec<_>>().join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+enum HunkSection {
+    Ours,
+    Base,
+    Theirs,
+}
+
+struct OpenHunk<'a> {
+    start_line: usize,
+    section: HunkSection,
+    ours: Vec<&'a str>,
+    base: Option<Vec<&'a str>>,
+    theirs: Vec<&'a str>,
+}
+
+fn parse_conflict_hunks(content: &str) -> Vec<ConflictHunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<OpenHunk> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        if line.starts_with("<<<<<<<") {
+            current = Some(OpenHunk {
+                start_line: line_number,
+                section: HunkSection::Ours,
+                ours: Vec::new(),
+                base: None,
+                theirs: Vec::new(),
+            });
+            continue;
+        }
+        let Some(hunk) = current.as_mut() else {
+            continue;
+        };
+
+        if line.starts_with("|||||||") {
+            hunk.section = HunkSection::Base;
+            hunk.base = Some(Vec::new());
+        } else if line.starts_with("=======") {
+            hunk.section = HunkSection::Theirs;
+        } else if line.starts_with(">>>>>>>") {
+            hunks.push(ConflictHunk {
+                start_line: hunk.start_line,
+                end_line: line_number,
+                ours: hunk.ours.join("\n"),
+                theirs: hunk.theirs.join("\n"),
+                base: hunk.base.as_ref().map(|lines| lines.join("\n")),
+            });
+            current = None;
+        } else {
+            match hunk.section {
+                HunkSection::Ours => hunk.ours.push(line),
+                HunkSection::Base => hunk.base.get_or_insert_with(Vec::new).push(line),
+                HunkSection::Theirs => hunk.theirs.push(line),
+            }
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(repo_path: &std::path::Path, args: &[&str]) -> std::process::Output {
+        std::process::Command::new("git")
+            .args(args)
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to run git")
+    }
+
+    /// Repository mid-merge with a text conflict in `app.txt` and a binary one in `logo.bin`
+    fn conflicting_merge() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.email", "test@example.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+        git(path, &["config", "merge.conflictStyle", "diff3"]);
+
+        std::fs::write(path.join("app.txt"), "header\nvalue = 1\nfooter\n").unwrap();
+        std::fs::write(path.join("logo.bin"), [0u8, 1, 2, 3]).unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "Base"]);
+
+        git(path, &["checkout", "-q", "-b", "feature"]);
+        std::fs::write(path.join("app.txt"), "header\nvalue = 3\nfooter\n").unwrap();
+        std::fs::write(path.join("logo.bin"), [0u8, 9, 9, 9]).unwrap();
+        git(path, &["commit", "-q", "-am", "Feature"]);
+
+        git(path, &["checkout", "-q", "main"]);
+        std::fs::write(path.join("app.txt"), "header\nvalue = 2\nfooter\n").unwrap();
+        std::fs::write(path.join("logo.bin"), [0u8, 7, 7, 7]).unwrap();
+        git(path, &["commit", "-q", "-am", "Main"]);
+
+        assert!(!git(path, &["merge", "feature"]).status.success());
+        dir
+    }
+
+    #[test]
+    fn test_get_conflicts_parses_hunks() {
+        let repo = conflicting_merge();
+        let conflicts = get_conflicts(repo.path().to_str().unwrap()).unwrap();
+        assert_eq!(conflicts.len(), 2);
+
+        let text = &conflicts[0];
+        assert_eq!(text.path, "app.txt");
+        assert_eq!(text.conflict_type, ConflictType::BothModified);
+        assert!(!text.binary);
+        assert_eq!(text.hunks.len(), 1);
+        assert_eq!(text.hunks[0].start_line, 2);
+        assert_eq!(text.hunks[0].ours, "value = 2");
+        assert_eq!(text.hunks[0].theirs, "value = 3");
+        assert_eq!(text.hunks[0].base.as_deref(), Some("value = 1"));
+
+        let binary = &conflicts[1];
+        assert_eq!(binary.path, "logo.bin");
+        assert!(binary.binary);
+        assert!(binary.hunks.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_conflict_stages_file() {
+        let repo = conflicting_merge();
+        let path = repo.path().to_str().unwrap();
+
+        let custom = ConflictResolution::Custom("header\nvalue = 4\nfooter\n".to_string());
+        assert!(resolve_conflict(path, "logo.bin", custom.clone()).is_err());
+
+        resolve_conflict(path, "app.txt", custom).unwrap();
+        resolve_conflict(path, "logo.bin", ConflictResolution::Theirs).unwrap();
+
+        assert!(get_conflicts(path).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(repo.path().join("app.txt")).unwrap(), "header\nvalue = 4\nfooter\n");
+        assert_eq!(std::fs::read(repo.path().join("logo.bin")).unwrap(), vec![0u8, 9, 9, 9]);
+        assert!(git(repo.path(), &["commit", "-q", "--no-edit"]).status.success());
+    }
+
+    #[test]
+    fn test_add_and_remove_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path().join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        git(&repo_path, &["init", "-q", "-b", "main"]);
+        git(&repo_path, &["config", "user.email", "test@example.com"]);
+        git(&repo_path, &["config", "user.name", "Test User"]);
+        std::fs::write(repo_path.join("README.md"), "hello\n").unwrap();
+        git(&repo_path, &["add", "."]);
+        git(&repo_path, &["commit", "-q", "-m", "Initial"]);
+
+        let repo = repo_path.to_str().unwrap();
+        let worktree_path = dir.path().join("feature-wt");
+        let worktree = worktree_path.to_str().unwrap();
+
+        let added = add_worktree(repo, worktree, "feature").unwrap();
+        assert_eq!(added.branch.as_deref(), Some("feature"));
+        assert!(added.head.is_some());
+        assert!(!added.locked && !added.prunable);
+        assert_eq!(list_worktrees(repo).unwrap().len(), 2);
+
+        let elsewhere = dir.path().join("other-wt");
+        let err = add_worktree(repo, elsewhere.to_str().unwrap(), "main").unwrap_err().to_string();
+        assert!(err.contains("already checked out"), "{}", err);
+        assert!(!elsewhere.exists());
+
+        // Option-like arguments are never passed to git as options
+        let err = add_worktree(repo, elsewhere.to_str().unwrap(), "--detach").unwrap_err().to_string();
+        assert!(err.contains("Invalid branch name"), "{}", err);
+        assert!(remove_worktree(repo, "--force", false).is_err());
+        assert_eq!(list_worktrees(repo).unwrap().len(), 2);
+
+        remove_worktree(repo, worktree, false).unwrap();
+        let worktrees = list_worktrees(repo).unwrap();
+        assert_eq!(worktrees.len(), 1);
+        assert_eq!(worktrees[0].branch.as_deref(), Some("main"));
+        assert!(!worktree_path.exists());
+    }
+
+    #[test]
+    fn test_diff_stat_counts_text_and_flags_binary() {
+        let dir = repo_with_commit("one\ntwo\nthree\n");
+        let path = dir.path();
+
+        std::fs::write(path.join("app.txt"), "one\n2\nthree\nfour\nfive\n").unwrap();
+        std::fs::write(path.join("logo.bin"), [0u8, 1, 2, 3]).unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "Change"]);
+        let repo = path.to_str().unwrap();
+
+        let stat = get_diff_stat(repo, Some("HEAD".to_string())).unwrap();
+        assert_eq!((stat.files_changed, stat.insertions, stat.deletions), (2, 3, 1));
+        assert_eq!(stat.files[0], FileDiffStat { path: "app.txt".to_string(), insertions: 3, deletions: 1, binary: false, lfs: false, lfs_size: None });
+        assert_eq!(stat.files[1], FileDiffStat { path: "logo.bin".to_string(), insertions: 0, deletions: 0, binary: true, lfs: false, lfs_size: None });
+
+        let root = get_diff_stat(repo, Some("HEAD~1".to_string())).unwrap();
+        assert_eq!((root.files_changed, root.insertions, root.deletions), (1, 3, 0));
+
+        // Working tree, staged or not, against HEAD
+        std::fs::write(path.join("app.txt"), "one\n").unwrap();
+        std::fs::write(path.join("logo.bin"), [9u8, 9]).unwrap();
+        git(path, &["add", "logo.bin"]);
+        let stat = get_diff_stat(repo, None).unwrap();
+        assert_eq!((stat.files_changed, stat.insertions, stat.deletions), (2, 0, 4));
+        assert!(stat.files.iter().any(|f| f.path == "logo.bin" && f.binary));
+
+        assert!(get_diff_stat(repo, Some("--output=/tmp/x".to_string())).is_err());
+        assert!(get_diff_stat(repo, Some("no-such-rev".to_string())).is_err());
+    }
+
+    fn repo_with_commit(content: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.email", "test@example.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+        std::fs::write(path.join("app.txt"), content).unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "Initial"]);
+        dir
+    }
+
+    fn lfs_pointer(size: u64) -> String {
+        format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{:064x}\nsize {}\n",
+            size, size
+        )
+    }
+
+    #[test]
+    fn test_lfs_pointers_are_flagged_not_counted() {
+        let dir = repo_with_commit("one\n");
+        let path = dir.path();
+        std::fs::write(path.join(".gitattributes"), "*.psd filter=lfs diff=lfs merge=lfs -text\n").unwrap();
+        std::fs::write(path.join("cover.psd"), lfs_pointer(1_048_576)).unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "Add cover"]);
+        let repo = path.to_str().unwrap();
+
+        assert!(is_lfs_tracked(repo, "cover.psd").unwrap());
+        assert!(is_lfs_tracked(repo, "art/other.psd").unwrap());
+        assert!(!is_lfs_tracked(repo, "app.txt").unwrap());
+
+        std::fs::write(path.join("app.txt"), "one\ntwo\n").unwrap();
+        std::fs::write(path.join("cover.psd"), lfs_pointer(2_097_152)).unwrap();
+        git(path, &["commit", "-q", "-am", "Update cover"]);
+
+        let stat = get_diff_stat(repo, Some("HEAD".to_string())).unwrap();
+        assert_eq!((stat.files_changed, stat.insertions, stat.deletions), (2, 1, 0));
+        let cover = stat.files.iter().find(|f| f.path == "cover.psd").unwrap();
+        assert_eq!((cover.lfs, cover.lfs_size, cover.insertions, cover.deletions), (true, Some(2_097_152), 0, 0));
+
+        let head = git(path, &["rev-parse", "HEAD"]);
+        let changes = get_commit_changes(repo, String::from_utf8_lossy(&head.stdout).trim()).unwrap();
+        let cover = changes.iter().find(|c| c.path == "cover.psd").unwrap();
+        assert_eq!((cover.lfs, cover.lfs_size), (true, Some(2_097_152)));
+        assert!(changes.iter().any(|c| c.path == "app.txt" && !c.lfs));
+
+        // Working tree: status marks the asset, the patch leaves its pointer text out
+        std::fs::remove_file(path.join("cover.psd")).unwrap();
+        std::fs::write(path.join("app.txt"), "one\n").unwrap();
+        let stat = get_diff_stat(repo, None).unwrap();
+        assert_eq!((stat.insertions, stat.deletions), (0, 1));
+        assert!(stat.files.iter().any(|f| f.path == "cover.psd" && f.lfs && f.lfs_size == Some(2_097_152)));
+        assert!(get_status(repo).unwrap().contains(" D cover.psd [lfs]"));
+        let diff = get_diff(repo).unwrap();
+        assert!(diff.contains("-two") && !diff.contains("git-lfs"), "{}", diff);
+    }
+
+    /// Superproject with `vendor/lib` as a submodule, deinitialized again so that
+    /// updating it needs no clone (local clones are refused inside `git submodule`)
+    fn superproject_with_submodule() -> tempfile::TempDir {
+        let lib = repo_with_commit("library\n");
+        let dir = repo_with_commit("app\n");
+        let path = dir.path();
+        let lib_path = lib.path().to_str().unwrap();
+        assert!(git(path, &["-c", "protocol.file.allow=always", "submodule", "add", "-q", lib_path, "vendor/lib"]).status.success());
+        git(path, &["commit", "-q", "-m", "Add lib"]);
+        git(path, &["submodule", "deinit", "-q", "-f", "vendor/lib"]);
+        dir
+    }
+
+    #[test]
+    fn test_submodule_status_and_update() {
+        let dir = superproject_with_submodule();
+        let path = dir.path().to_str().unwrap();
+
+        let submodules = get_submodules(path).unwrap();
+        assert_eq!(submodules.len(), 1);
+        let lib = &submodules[0];
+        assert_eq!((lib.name.as_str(), lib.path.as_str()), ("vendor/lib", "vendor/lib"));
+        assert!(lib.url.is_some());
+        assert!(!lib.initialized && !lib.out_of_sync);
+        assert_eq!(lib.current_commit, None);
+        let recorded = lib.recorded_commit.clone().unwrap();
+
+        let updated = update_submodules(path, true, true).unwrap();
+        assert!(updated[0].initialized && !updated[0].out_of_sync);
+        assert_eq!(updated[0].current_commit.as_deref(), Some(recorded.as_str()));
+        assert!(dir.path().join("vendor/lib/app.txt").exists());
+
+        // Moving the submodule's checkout ahead leaves the superproject's record behind
+        let checkout = dir.path().join("vendor/lib");
+        git(&checkout, &["config", "user.email", "test@example.com"]);
+        git(&checkout, &["config", "user.name", "Test User"]);
+        git(&checkout, &["commit", "-q", "--allow-empty", "-m", "Ahead"]);
+        let ahead = &get_submodules(path).unwrap()[0];
+        assert!(ahead.out_of_sync);
+        assert_eq!(ahead.recorded_commit.as_deref(), Some(recorded.as_str()));
+        assert_ne!(ahead.current_commit.as_deref(), Some(recorded.as_str()));
+
+        // The library itself has no submodules
+        assert!(get_submodules(checkout.to_str().unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stash_and_pop_restores_working_tree() {
+        let dir = repo_with_commit("value = 1\n");
+        let path = dir.path();
+        let repo = path.to_str().unwrap();
+        assert!(create_stash(repo, None, false).is_err());
+
+        std::fs::write(path.join("app.txt"), "value = 2\n").unwrap();
+        std::fs::write(path.join("notes.txt"), "draft\n").unwrap();
+        let stash = create_stash(repo, Some("wip".to_string()), true).unwrap();
+        assert_eq!(stash.index, 0);
+        assert!(stash.message.contains("wip"));
+        assert_eq!(std::fs::read_to_string(path.join("app.txt")).unwrap(), "value = 1\n");
+        assert!(!path.join("notes.txt").exists());
+
+        assert!(pop_stash(repo, 1).is_err());
+        assert_eq!(pop_stash(repo, 0).unwrap(), StashApplyResult::Applied);
+        assert_eq!(std::fs::read_to_string(path.join("app.txt")).unwrap(), "value = 2\n");
+        assert_eq!(std::fs::read_to_string(path.join("notes.txt")).unwrap(), "draft\n");
+        assert!(get_stash_list(repo).unwrap().is_empty());
+
+        create_stash(repo, None, true).unwrap();
+        assert_eq!(apply_stash(repo, 0).unwrap(), StashApplyResult::Applied);
+        assert_eq!(get_stash_list(repo).unwrap().len(), 1);
+        drop_stash(repo, 0).unwrap();
+        assert!(get_stash_list(repo).unwrap().is_empty());
+        assert!(drop_stash(repo, 0).is_err());
+    }
+
+    #[test]
+    fn test_conflicting_pop_reports_files_and_keeps_stash() {
+        let dir = repo_with_commit("value = 1\n");
+        let path = dir.path();
+        let repo = path.to_str().unwrap();
+
+        std::fs::write(path.join("app.txt"), "value = 2\n").unwrap();
+        create_stash(repo, None, false).unwrap();
+        std::fs::write(path.join("app.txt"), "value = 3\n").unwrap();
+        git(path, &["commit", "-q", "-am", "Change value"]);
+
+        assert_eq!(
+            pop_stash(repo, 0).unwrap(),
+            StashApplyResult::Conflicted { files: vec!["app.txt".to_string()] }
+        );
+        assert_eq!(get_stash_list(repo).unwrap().len(), 1);
+        assert!(std::fs::read_to_string(path.join("app.txt")).unwrap().contains("<<<<<<<"));
+    }
+
+    #[test]
+    fn test_tags_round_trip_in_version_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.email", "test@example.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+        std::fs::write(path.join("README.md"), "hello\n").unwrap();
+        git(path, &["add", "."]);
+        git(path, &["commit", "-q", "-m", "Initial"]);
+        let repo = path.to_str().unwrap();
+        let head = Repository::open(path).unwrap().head().unwrap().peel_to_commit().unwrap().id().to_string();
+
+        let annotated = create_tag(repo, "v1.10", Some("Release 1.10\n".to_string()), None).unwrap();
+        assert!(annotated.annotated);
+        assert_eq!(annotated.target, head);
+        assert_eq!(annotated.tagger.as_deref(), Some("Test User <test@example.com>"));
+        assert_eq!(annotated.message.as_deref(), Some("Release 1.10"));
+
+        let lightweight = create_tag(repo, "v1.9", None, Some(head.clone())).unwrap();
+        assert!(!lightweight.annotated);
+        assert_eq!(lightweight.target, head);
+        assert!(lightweight.tagger.is_none() && lightweight.message.is_none());
+        create_tag(repo, "v1.2", None, None).unwrap();
+        assert!(create_tag(repo, "v1.9", None, None).is_err());
+
+        let tags = list_tags(repo).unwrap();
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["v1.2", "v1.9", "v1.10"]);
+        assert_eq!(tags[2], annotated);
+
+        // Tags may name trees, as the kernel's v2.6.11-tree does
+        let tree = Repository::open(path).unwrap().head().unwrap().peel_to_tree().unwrap().id().to_string();
+        git(path, &["tag", "v1.0-tree", &tree]);
+        let tags = list_tags(repo).unwrap();
+        assert_eq!(tags.iter().find(|t| t.name == "v1.0-tree").map(|t| t.target.as_str()), Some(tree.as_str()));
+        git(path, &["tag", "-d", "v1.0-tree"]);
+
+        delete_tag(repo, "v1.9").unwrap();
+        assert_eq!(list_tags(repo).unwrap().len(), 2);
+        assert!(delete_tag(repo, "v1.9").is_err());
+    }
+
+    #[test]
+    fn test_stage_and_unstage_single_hunk() {
+        let original: String = (1..=30).map(|n| format!("line {}\n", n)).collect();
+        let dir = repo_with_commit(&original);
+        let path = dir.path();
+        let repo = path.to_str().unwrap();
+        let modified = original.replace("line 2\n", "line two\n").replace("line 25\n", "line twenty-five\n");
+        std::fs::write(path.join("app.txt"), &modified).unwrap();
+
+        let hunks = get_unstaged_hunks(repo, "app.txt").unwrap();
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[1].old_start, hunks[1].old_lines, hunks[1].new_start, hunks[1].new_lines), (22, 7, 22, 7));
+        assert!(hunks[1].header.starts_with("@@ -22,7 +22,7 @@"));
+
+        // Editing the other hunk changes the file's blob hash but not this hunk's id
+        let modified = modified.replace("line two\n", "line 2a\nline 2b\n");
+        std::fs::write(path.join("app.txt"), &modified).unwrap();
+        let edited = get_unstaged_hunks(repo, "app.txt").unwrap();
+        assert_ne!(edited[0].id, hunks[0].id);
+        assert_eq!(edited[1].id, hunks[1].id);
+
+        stage_hunk(repo, "app.txt", &hunks[1].id).unwrap();
+        let index = String::from_utf8(git(path, &["show", ":app.txt"]).stdout).unwrap();
+        assert!(index.contains("line twenty-five\n") && index.contains("line 2\n"), "{}", index);
+        let remaining = get_unstaged_hunks(repo, "app.txt").unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].patch.contains("+line 2a"));
+        assert_eq!(std::fs::read_to_string(path.join("app.txt")).unwrap(), modified);
+
+        let staged = get_staged_hunks(repo, "app.txt").unwrap();
+        assert_eq!(staged.len(), 1);
+        unstage_hunk(repo, "app.txt", &staged[0].id).unwrap();
+        assert!(get_staged_hunks(repo, "app.txt").unwrap().is_empty());
+        assert_eq!(get_unstaged_hunks(repo, "app.txt").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_stale_hunk_is_rejected() {
+        let dir = repo_with_commit("value = 1\n");
+        let path = dir.path();
+        let repo = path.to_str().unwrap();
+        std::fs::write(path.join("app.txt"), "value = 2\n").unwrap();
+        let hunks = get_unstaged_hunks(repo, "app.txt").unwrap();
+
+        std::fs::write(path.join("app.txt"), "value = 3\n").unwrap();
+        let error = stage_hunk(repo, "app.txt", &hunks[0].id).unwrap_err();
+        assert!(error.to_string().contains("no longer applies"), "{}", error);
+        assert!(get_staged_hunks(repo, "app.txt").unwrap().is_empty());
+    }
+}
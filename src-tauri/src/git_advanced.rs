@@ -19,6 +19,49 @@ pub struct GitNode {
     pub x: f64,
     pub y: f64,
     pub branch: Option<String>,
+    /// Filled in by `generate_visual_graph`; other listings leave it as `None`
+    #[serde(default)]
+    pub signature: SignatureStatus,
+}
+
+/// GPG/SSH signature verification result, from git's `%G?` placeholder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureStatus {
+    /// Valid signature from a trusted key
+    Good,
+    /// Valid signature from a key of unknown validity
+    Untrusted,
+    /// Valid signature, but the signature or the key has expired
+    Expired,
+    Revoked,
+    Bad,
+    /// Signed, but the key isn't available to check it
+    Unverifiable,
+    #[default]
+    None,
+}
+
+impl SignatureStatus {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "G" => SignatureStatus::Good,
+            "U" => SignatureStatus::Untrusted,
+            "X" | "Y" => SignatureStatus::Expired,
+            "R" => SignatureStatus::Revoked,
+            "B" => SignatureStatus::Bad,
+            "E" => SignatureStatus::Unverifiable,
+            _ => SignatureStatus::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitSignature {
+    pub hash: String,
+    pub status: SignatureStatus,
+    pub signer: Option<String>,
+    pub key_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +81,19 @@ pub struct GitGraph {
     pub head_commit: String,
 }
 
+/// One page of the commit graph, children before parents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitGraphPage {
+    /// `x` is the lane and `y` the row, both stable across pages
+    pub nodes: Vec<GitNode>,
+    /// Edges to each node's parents, which may arrive on later pages
+    pub edges: Vec<GitEdge>,
+    /// Pass as `after` to fetch the following page; `None` once history is exhausted
+    pub next_cursor: Option<String>,
+    /// Lanes used so far, for sizing the graph
+    pub lane_count: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BranchInfo {
     pub name: String,
@@ -116,6 +172,61 @@ pub struct AuthorStats {
     pub last_commit: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RebaseAction {
+    Pick,
+    Reword,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    fn todo_command(self) -> &'static str {
+        match self {
+            RebaseAction::Pick | RebaseAction::Reword => "pick",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseStep {
+    pub hash: String,
+    pub short_hash: String,
+    pub message: String,
+    pub action: RebaseAction,
+    /// New commit message for `Reword` steps
+    #[serde(default)]
+    pub new_message: Option<String>,
+}
+
+/// Commits between `onto` and `from`, oldest first, each with the action to apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebasePlan {
+    pub onto: String,
+    pub from: String,
+    pub steps: Vec<RebaseStep>,
+}
+
+/// Where a rebase stopped on conflicts; the rebase has already been aborted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseConflict {
+    pub commit: Option<String>,
+    pub conflicted_files: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RebaseOutcome {
+    Completed { head: String },
+    Conflict(RebaseConflict),
+}
+
 pub struct GitAdvanced {
     pub repo_path: String,
 }
@@ -135,7 +246,7 @@ impl GitAdvanced {
             .args([
                 "log", 
                 &format!("--max-count={}", limit),
-                "--pretty=format:%H|%h|%P|%s|%an|%ae|%ai|%D",
+                GRAPH_LOG_FORMAT,
                 "--all"
             ])
             .current_dir(&self.repo_path)
@@ -153,53 +264,16 @@ impl GitAdvanced {
         let mut edges = Vec::new();
 
         for line in log_output.lines() {
-            if !line.trim().is_empty() {
-                let parts: Vec<&str> = line.split('|').collect();
-                if parts.len() >= 7 {
-                    let hash = parts[0].trim().to_string();
-                    let short_hash = parts[1].trim().to_string();
-                    let parents: Vec<String> = if !parts[2].trim().is_empty() {
-                        parts[2].trim().split_whitespace().map(|s| s.to_string()).collect()
-                    } else {
-                        Vec::new()
-                    };
-                    let message = parts[3].trim().to_string();
-                    let author = parts[4].trim().to_string();
-                    let author_email = parts[5].trim().to_string();
-                    let date = DateTime::parse_from_rfc3339(parts[6].trim())?.with_timezone(&Utc);
-                    let refs_str = if parts.len() > 7 { parts[7].trim() } else { "" };
-                    let refs: Vec<String> = if !refs_str.is_empty() {
-                        refs_str.split(", ").map(|s| s.to_string()).collect()
-                    } else {
-                        Vec::new()
-                    };
-
-                    let node = GitNode {
-                        hash: hash.clone(),
-                        short_hash,
-                        message,
-                        author,
-                        author_email,
-                        date,
-                        parents: parents.clone(),
-                        children: Vec::new(),
-                        refs,
-                        x: 0.0,
-                        y: 0.0,
+            if let Some(node) = parse_graph_line(line)? {
+                for parent in &node.parents {
+                    edges.push(GitEdge {
+                        from: parent.clone(),
+                        to: node.hash.clone(),
                         branch: None,
-                    };
-
-                    nodes.insert(hash.clone(), node);
-
-                    for parent in parents {
-                        edges.push(GitEdge {
-                            from: parent,
-                            to: hash.clone(),
-                            branch: None,
-                            merge: false,
-                        });
-                    }
+                        merge: false,
+                    });
                 }
+                nodes.insert(node.hash.clone(), node);
             }
         }
 
@@ -210,6 +284,12 @@ impl GitAdvanced {
             }
         }
 
+        for signature in self.signature_log(&[&format!("--max-count={}", limit), "--all"]).await? {
+            if let Some(node) = nodes.get_mut(&signature.hash) {
+                node.signature = signature.status;
+            }
+        }
+
         // Position nodes
         self.position_nodes(&mut nodes);
 
@@ -261,6 +341,110 @@ impl GitAdvanced {
         }
     }
 
+    /// Like `generate_visual_graph`, but one page of `limit` commits at a time, starting after
+    /// the `after` cursor. Lane state is cached per repository so that following pages continue
+    /// the same columns instead of being laid out afresh.
+    pub async fn generate_visual_graph_paged(&self, after: Option<String>, limit: u32) -> Result<GitGraphPage> {
+        if limit == 0 {
+            return Err(anyhow!("Page limit must be at least 1"));
+        }
+
+        let tips = self.ref_tips().await?;
+        let cached = GRAPH_LANES.lock().unwrap().remove(&self.repo_path);
+        let mut lanes = match (after.as_deref(), cached) {
+            (None, _) => GraphLanes { tips, ..Default::default() },
+            (Some(after), Some(cached)) if cached.tips == tips && cached.last.as_deref() == Some(after) => cached,
+            // Not the page we left off at, or history moved: walk up to the cursor again
+            (Some(after), _) => self.replay_lanes(after, tips).await?,
+        };
+
+        // One extra commit tells whether another page follows
+        let skip = format!("--skip={}", lanes.rows);
+        let max_count = format!("--max-count={}", limit + 1);
+        let log_args = ["--all", "--topo-order", skip.as_str(), max_count.as_str()];
+        let mut args = vec!["log", GRAPH_LOG_FORMAT];
+        args.extend_from_slice(&log_args);
+        let output = self.git(&args).await?;
+        if !output.status.success() {
+            return Err(anyhow!("Git log failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut has_more = false;
+        for line in String::from_utf8(output.stdout)?.lines() {
+            let Some(mut node) = parse_graph_line(line)? else {
+                continue;
+            };
+            if nodes.len() == limit as usize {
+                has_more = true;
+                break;
+            }
+
+            let (column, children) = lanes.place(&node.hash, &node.parents);
+            node.x = column as f64;
+            node.y = (lanes.rows - 1) as f64;
+            node.children = children;
+            for parent in &node.parents {
+                edges.push(GitEdge {
+                    from: parent.clone(),
+                    to: node.hash.clone(),
+                    branch: None,
+                    merge: node.parents.len() > 1,
+                });
+            }
+            nodes.push(node);
+        }
+
+        let signatures: HashMap<String, SignatureStatus> = self
+            .signature_log(&log_args)
+            .await?
+            .into_iter()
+            .map(|signature| (signature.hash, signature.status))
+            .collect();
+        for node in &mut nodes {
+            node.signature = signatures.get(&node.hash).copied().unwrap_or_default();
+        }
+
+        let page = GitGraphPage {
+            nodes,
+            edges,
+            next_cursor: if has_more { lanes.last.clone() } else { None },
+            lane_count: lanes.width,
+        };
+        GRAPH_LANES.lock().unwrap().insert(self.repo_path.clone(), lanes);
+        Ok(page)
+    }
+
+    /// Lane state as it stood right after placing `after`
+    async fn replay_lanes(&self, after: &str, tips: String) -> Result<GraphLanes> {
+        let output = self.git(&["log", "--all", "--topo-order", "--pretty=format:%H %P"]).await?;
+        if !output.status.success() {
+            return Err(anyhow!("Git log failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let mut lanes = GraphLanes { tips, ..Default::default() };
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut hashes = line.split_whitespace();
+            let Some(hash) = hashes.next() else {
+                continue;
+            };
+            let parents: Vec<String> = hashes.map(str::to_string).collect();
+            lanes.place(hash, &parents);
+            if hash == after {
+                return Ok(lanes);
+            }
+        }
+        Err(anyhow!("Commit {} is not in the graph; reload it from the first page", after))
+    }
+
+    /// Every ref and the commit it points at, to notice history changing between pages
+    async fn ref_tips(&self) -> Result<String> {
+        // `show-ref` exits non-zero when there are no refs at all, which is still a valid state
+        let output = self.git(&["show-ref", "--head"]).await?;
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
     fn calculate_x_position(&self, refs: &[String], parents: &[String]) -> f64 {
         let mut x = 0.0;
         
@@ -397,7 +581,7 @@ impl GitAdvanced {
 
     async fn get_branch_last_commit(&self, branch: &str) -> Result<(DateTime<Utc>, String)> {
         let output = TokioCommand::new("git")
-            .args(["log", "-1", "--format=%ai|%an", branch])
+            .args(["log", "-1", "--format=%aI|%an", branch])
             .current_dir(&self.repo_path)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -464,7 +648,7 @@ impl GitAdvanced {
 
     async fn get_commit_details(&self, commit: &str) -> Result<(DateTime<Utc>, String)> {
         let output = TokioCommand::new("git")
-            .args(["show", "-s", "--format=%ai|%an", commit])
+            .args(["show", "-s", "--format=%aI|%an", commit])
             .current_dir(&self.repo_path)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -654,7 +838,7 @@ impl GitAdvanced {
 
     async fn get_commit_frequency(&self) -> Result<HashMap<String, u32>> {
         let output = TokioCommand::new("git")
-            .args(["log", "--pretty=format:%ai"])
+            .args(["log", "--pretty=format:%aI"])
             .current_dir(&self.repo_path)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -680,7 +864,7 @@ impl GitAdvanced {
 
     async fn get_author_statistics(&self) -> Result<HashMap<String, AuthorStats>> {
         let output = TokioCommand::new("git")
-            .args(["log", "--pretty=format:%an|%ai", "--numstat"])
+            .args(["log", "--pretty=format:%an|%aI", "--numstat"])
             .current_dir(&self.repo_path)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -797,6 +981,23 @@ impl GitAdvanced {
         Ok(format!("Created branch '{}' from commit {}", branch_name, commit))
     }
     
+    /// Signature status of the most recent commits on the current branch
+    pub async fn get_commit_signatures(&self, max_commits: Option<u32>) -> Result<Vec<CommitSignature>> {
+        let limit = max_commits.unwrap_or(100);
+        self.signature_log(&[&format!("--max-count={}", limit)]).await
+    }
+
+    async fn signature_log(&self, args: &[&str]) -> Result<Vec<CommitSignature>> {
+        let mut log_args = vec!["log", "--pretty=format:%H%x1f%G?%x1f%GS%x1f%GK"];
+        log_args.extend_from_slice(args);
+        let output = self.git(&log_args).await?;
+
+        if !output.status.success() {
+            return Err(anyhow!("Git log failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(parse_signature_log(&String::from_utf8_lossy(&output.stdout)))
+    }
+
     pub async fn get_commit_history(&self, max_commits: Option<u32>) -> Result<Vec<GitNode>> {
         let limit = max_commits.unwrap_or(10);
         
@@ -804,7 +1005,7 @@ impl GitAdvanced {
             .args([
                 "log", 
                 &format!("--max-count={}", limit),
-                "--pretty=format:%H|%h|%P|%s|%an|%ae|%ai|%D"
+                "--pretty=format:%H|%h|%P|%s|%an|%ae|%aI|%D"
             ])
             .current_dir(&self.repo_path)
             .stdout(Stdio::piped())
@@ -855,6 +1056,7 @@ impl GitAdvanced {
                         x: 0.0,
                         y: 0.0,
                         branch: None,
+                        signature: SignatureStatus::None,
                     });
                 }
             }
@@ -862,6 +1064,274 @@ impl GitAdvanced {
 
         Ok(commits)
     }
+
+    /// List the commits in `onto..from` as a rebase plan, every step defaulting to pick
+    pub async fn generate_rebase_plan(&self, onto: &str, from: &str) -> Result<RebasePlan> {
+        let output = self
+            .git(&["log", "--reverse", "--pretty=format:%H|%h|%s", &format!("{}..{}", onto, from)])
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow!("Git log failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let steps = String::from_utf8(output.stdout)?
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, '|');
+                Some(RebaseStep {
+                    hash: parts.next()?.to_string(),
+                    short_hash: parts.next()?.to_string(),
+                    message: parts.next().unwrap_or("").to_string(),
+                    action: RebaseAction::Pick,
+                    new_message: None,
+                })
+            })
+            .collect();
+
+        Ok(RebasePlan {
+            onto: onto.to_string(),
+            from: from.to_string(),
+            steps,
+        })
+    }
+
+    /// Run `git rebase -i` with the plan as its todo list, without opening an editor.
+    ///
+    /// On conflicts the rebase is aborted so the repository is left as it was.
+    pub async fn apply_rebase_plan(&self, plan: &RebasePlan) -> Result<RebaseOutcome> {
+        match plan.steps.iter().find(|step| step.action != RebaseAction::Drop) {
+            None if plan.steps.is_empty() => return Err(anyhow!("Rebase plan has no commits")),
+            Some(step) if matches!(step.action, RebaseAction::Squash | RebaseAction::Fixup) => {
+                return Err(anyhow!(
+                    "Cannot {:?} {}: there is no earlier commit to fold it into",
+                    step.action,
+                    step.short_hash
+                ));
+            }
+            _ => {}
+        }
+
+        let scratch = tempfile::tempdir()?;
+        let mut todo = String::new();
+        for (index, step) in plan.steps.iter().enumerate() {
+            todo.push_str(&format!("{} {} {}\n", step.action.todo_command(), step.hash, step.message));
+
+            // Reword without an editor: pick, then amend with the new message
+            if let (RebaseAction::Reword, Some(message)) = (step.action, &step.new_message) {
+                let message_path = scratch.path().join(format!("message-{}", index));
+                std::fs::write(&message_path, message)?;
+                todo.push_str(&format!(
+                    "exec git commit --amend --allow-empty --quiet -F {}\n",
+                    shell_quote(&message_path.to_string_lossy())
+                ));
+            }
+        }
+        let todo_path = scratch.path().join("git-rebase-todo");
+        std::fs::write(&todo_path, todo)?;
+
+        let output = TokioCommand::new("git")
+            .args(["rebase", "-i", &plan.onto, &plan.from])
+            .env("GIT_SEQUENCE_EDITOR", format!("cp {}", shell_quote(&todo_path.to_string_lossy())))
+            // Keep git's combined message for squashes
+            .env("GIT_EDITOR", "true")
+            .current_dir(&self.repo_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .await?;
+
+        if output.status.success() {
+            return Ok(RebaseOutcome::Completed {
+                head: self.rev_parse("HEAD").await?,
+            });
+        }
+
+        if !self.rebase_in_progress().await? {
+            return Err(anyhow!("Rebase failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let conflict = RebaseConflict {
+            commit: self.rev_parse("REBASE_HEAD").await.ok(),
+            conflicted_files: self.conflicted_files().await?,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        };
+        self.abort_rebase().await?;
+        Ok(RebaseOutcome::Conflict(conflict))
+    }
+
+    pub async fn abort_rebase(&self) -> Result<()> {
+        if !self.rebase_in_progress().await? {
+            return Ok(());
+        }
+
+        let output = self.git(&["rebase", "--abort"]).await?;
+        if !output.status.success() {
+            return Err(anyhow!("Failed to abort rebase: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(())
+    }
+
+    async fn rebase_in_progress(&self) -> Result<bool> {
+        for state_dir in ["rebase-merge", "rebase-apply"] {
+            let output = self.git(&["rev-parse", "--git-path", state_dir]).await?;
+            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if std::path::Path::new(&self.repo_path).join(path).exists() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn conflicted_files(&self) -> Result<Vec<String>> {
+        let output = self.git(&["diff", "--name-only", "--diff-filter=U"]).await?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    async fn rev_parse(&self, rev: &str) -> Result<String> {
+        let output = self.git(&["rev-parse", "--verify", "--quiet", rev]).await?;
+        if !output.status.success() {
+            return Err(anyhow!("Unknown revision: {}", rev));
+        }
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    }
+
+    async fn git(&self, args: &[&str]) -> Result<std::process::Output> {
+        Ok(TokioCommand::new("git")
+            .args(args)
+            .current_dir(&self.repo_path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .await?)
+    }
+}
+
+const GRAPH_LOG_FORMAT: &str = "--pretty=format:%H|%h|%P|%s|%an|%ae|%aI|%D";
+
+/// Lane state of paged graph walks, by repository path
+static GRAPH_LANES: once_cell::sync::Lazy<std::sync::Mutex<HashMap<String, GraphLanes>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Column assignment carried from one graph page to the next
+#[derive(Debug, Default)]
+struct GraphLanes {
+    /// `git show-ref --head` output the walk was made against
+    tips: String,
+    /// Commits placed so far
+    rows: usize,
+    last: Option<String>,
+    /// Per column, the commit the lane is waiting for
+    lanes: Vec<Option<String>>,
+    /// Children of commits not placed yet
+    children: HashMap<String, Vec<String>>,
+    width: usize,
+}
+
+impl GraphLanes {
+    /// Place the next commit in topological order, returning its column and its children
+    fn place(&mut self, hash: &str, parents: &[String]) -> (usize, Vec<String>) {
+        let column = match self.lanes.iter().position(|lane| lane.as_deref() == Some(hash)) {
+            Some(column) => column,
+            None => self.free_column(),
+        };
+        // Branches that forked from this commit end here
+        for lane in &mut self.lanes {
+            if lane.as_deref() == Some(hash) {
+                *lane = None;
+            }
+        }
+
+        self.lanes[column] = parents.first().cloned();
+        for parent in parents.iter().skip(1) {
+            if !self.lanes.iter().any(|lane| lane.as_ref() == Some(parent)) {
+                let free = self.free_column();
+                self.lanes[free] = Some(parent.clone());
+            }
+        }
+        self.width = self.width.max(self.lanes.len());
+        while self.lanes.last().is_some_and(Option::is_none) {
+            self.lanes.pop();
+        }
+
+        for parent in parents {
+            self.children.entry(parent.clone()).or_default().push(hash.to_string());
+        }
+        self.rows += 1;
+        self.last = Some(hash.to_string());
+        (column, self.children.remove(hash).unwrap_or_default())
+    }
+
+    fn free_column(&mut self) -> usize {
+        match self.lanes.iter().position(Option::is_none) {
+            Some(column) => column,
+            None => {
+                self.lanes.push(None);
+                self.lanes.len() - 1
+            }
+        }
+    }
+}
+
+/// Parse a `GRAPH_LOG_FORMAT` line into an unpositioned node
+fn parse_graph_line(line: &str) -> Result<Option<GitNode>> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let parts: Vec<&str> = line.split('|').collect();
+    if parts.len() < 7 {
+        return Ok(None);
+    }
+
+    let parents: Vec<String> = if !parts[2].trim().is_empty() {
+        parts[2].split_whitespace().map(|s| s.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    let refs_str = if parts.len() > 7 { parts[7].trim() } else { "" };
+    let refs: Vec<String> = if !refs_str.is_empty() {
+        refs_str.split(", ").map(|s| s.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(GitNode {
+        hash: parts[0].trim().to_string(),
+        short_hash: parts[1].trim().to_string(),
+        message: parts[3].trim().to_string(),
+        author: parts[4].trim().to_string(),
+        author_email: parts[5].trim().to_string(),
+        date: DateTime::parse_from_rfc3339(parts[6].trim())?.with_timezone(&Utc),
+        parents,
+        children: Vec::new(),
+        refs,
+        x: 0.0,
+        y: 0.0,
+        branch: None,
+        signature: SignatureStatus::None,
+    }))
+}
+
+/// Parse `%H%x1f%G?%x1f%GS%x1f%GK` lines
+fn parse_signature_log(output: &str) -> Vec<CommitSignature> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            let hash = fields.next().filter(|hash| !hash.trim().is_empty())?.trim().to_string();
+            let status = SignatureStatus::from_code(fields.next().unwrap_or("N").trim());
+            let non_empty = |field: Option<&str>| field.map(str::trim).filter(|f| !f.is_empty()).map(str::to_string);
+            let signer = non_empty(fields.next());
+            let key_id = non_empty(fields.next());
+            Some(CommitSignature { hash, status, signer, key_id })
+        })
+        .collect()
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
 }
 
 impl Default for BranchState {
@@ -875,3 +1345,173 @@ impl Default for BranchState {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(repo_path: &std::path::Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to run git");
+        assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn commit_file(repo_path: &std::path::Path, name: &str, contents: &str, message: &str) {
+        std::fs::write(repo_path.join(name), contents).expect("Failed to write file");
+        git(repo_path, &["add", name]);
+        git(repo_path, &["commit", "-q", "-m", message]);
+    }
+
+    fn init_repo() -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let path = temp_dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.email", "test@example.com"]);
+        git(path, &["config", "user.name", "Test User"]);
+        commit_file(path, "base.txt", "base", "Initial commit");
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_squash_removes_one_commit() {
+        let repo = init_repo();
+        let path = repo.path();
+        commit_file(path, "a.txt", "a", "Add a");
+        commit_file(path, "b.txt", "b", "Add b");
+        commit_file(path, "c.txt", "c", "Add c");
+
+        let git_advanced = GitAdvanced::new(path.to_str().unwrap());
+        let mut plan = git_advanced.generate_rebase_plan("HEAD~3", "main").await.unwrap();
+        let messages: Vec<&str> = plan.steps.iter().map(|s| s.message.as_str()).collect();
+        assert_eq!(messages, vec!["Add a", "Add b", "Add c"]);
+        assert!(plan.steps.iter().all(|s| s.action == RebaseAction::Pick));
+
+        plan.steps[1].action = RebaseAction::Squash;
+        plan.steps[2].action = RebaseAction::Reword;
+        plan.steps[2].new_message = Some("Add c, reworded".to_string());
+        let outcome = git_advanced.apply_rebase_plan(&plan).await.unwrap();
+        assert!(matches!(outcome, RebaseOutcome::Completed { .. }));
+
+        let log = git(path, &["log", "--pretty=format:%s"]);
+        let subjects: Vec<&str> = log.lines().collect();
+        assert_eq!(subjects, vec!["Add c, reworded", "Add a", "Initial commit"]);
+        assert_eq!(git(path, &["show", "--name-only", "--pretty=format:", "HEAD~1"]), "a.txt\nb.txt");
+    }
+
+    #[test]
+    fn test_parse_signature_log() {
+        let output = "aaa\x1fG\x1fTest User <test@example.com>\x1fSHA256:abc123\n\
+                      bbb\x1fB\x1fMallory\x1f0123ABCD\n\
+                      ccc\x1fN\x1f\x1f\n";
+        let signatures = parse_signature_log(output);
+
+        assert_eq!(signatures[0], CommitSignature {
+            hash: "aaa".to_string(),
+            status: SignatureStatus::Good,
+            signer: Some("Test User <test@example.com>".to_string()),
+            key_id: Some("SHA256:abc123".to_string()),
+        });
+        assert_eq!(signatures[1].status, SignatureStatus::Bad);
+        assert_eq!(signatures[1].key_id.as_deref(), Some("0123ABCD"));
+        assert_eq!(signatures[2], CommitSignature {
+            hash: "ccc".to_string(),
+            status: SignatureStatus::None,
+            signer: None,
+            key_id: None,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_commits_have_no_signature() {
+        let repo = init_repo();
+        let path = repo.path();
+        commit_file(path, "a.txt", "a", "Add a");
+
+        let git_advanced = GitAdvanced::new(path.to_str().unwrap());
+        let signatures = git_advanced.get_commit_signatures(None).await.unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert!(signatures.iter().all(|s| s.status == SignatureStatus::None && s.signer.is_none()));
+
+        let graph = git_advanced.generate_visual_graph(None).await.unwrap();
+        assert!(graph.nodes.values().all(|node| node.signature == SignatureStatus::None));
+    }
+
+    #[tokio::test]
+    async fn test_paged_graph_covers_history_with_stable_lanes() {
+        let repo = init_repo();
+        let path = repo.path();
+        for i in 0..12 {
+            commit_file(path, "main.txt", &i.to_string(), &format!("Main {}", i));
+        }
+        git(path, &["checkout", "-q", "-b", "feature", "HEAD~6"]);
+        for i in 0..8 {
+            commit_file(path, "feature.txt", &i.to_string(), &format!("Feature {}", i));
+        }
+        git(path, &["checkout", "-q", "main"]);
+        git(path, &["merge", "-q", "--no-ff", "-m", "Merge feature", "feature"]);
+        commit_file(path, "main.txt", "last", "After merge");
+
+        let git_advanced = GitAdvanced::new(path.to_str().unwrap());
+        let whole = git_advanced.generate_visual_graph_paged(None, 1000).await.unwrap();
+        assert!(whole.next_cursor.is_none());
+        let expected = git(path, &["log", "--all", "--topo-order", "--pretty=format:%H"]);
+        let expected: Vec<&str> = expected.lines().collect();
+        assert_eq!(whole.nodes.iter().map(|n| n.hash.as_str()).collect::<Vec<_>>(), expected);
+        assert!(whole.lane_count >= 2);
+
+        let mut paged = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = git_advanced.generate_visual_graph_paged(cursor, 5).await.unwrap();
+            assert!(page.nodes.len() <= 5);
+            paged.extend(page.nodes);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+            // Drop the cached lanes now and then to exercise rebuilding them from the cursor
+            if paged.len() == 10 {
+                GRAPH_LANES.lock().unwrap().remove(&git_advanced.repo_path);
+            }
+        }
+
+        assert_eq!(paged.len(), expected.len());
+        for (paged, whole) in paged.iter().zip(&whole.nodes) {
+            assert_eq!(paged.hash, whole.hash);
+            assert_eq!((paged.x, paged.y), (whole.x, whole.y), "{} moved between layouts", paged.message);
+            assert_eq!(paged.children, whole.children);
+        }
+
+        let bogus = "0".repeat(40);
+        assert!(git_advanced.generate_visual_graph_paged(Some(bogus), 5).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_conflict_is_reported_and_aborted() {
+        let repo = init_repo();
+        let path = repo.path();
+        commit_file(path, "shared.txt", "one", "Write one");
+        commit_file(path, "shared.txt", "two", "Write two");
+        let head_before = git(path, &["rev-parse", "HEAD"]);
+
+        let git_advanced = GitAdvanced::new(path.to_str().unwrap());
+        let mut plan = git_advanced.generate_rebase_plan("HEAD~2", "main").await.unwrap();
+        // Dropping the first write makes the second conflict with the base
+        plan.steps[0].action = RebaseAction::Drop;
+        let outcome = git_advanced.apply_rebase_plan(&plan).await.unwrap();
+
+        match outcome {
+            RebaseOutcome::Conflict(conflict) => {
+                assert_eq!(conflict.conflicted_files, vec!["shared.txt".to_string()]);
+                assert_eq!(conflict.commit.as_deref(), Some(head_before.as_str()));
+            }
+            other => panic!("expected conflict, got {:?}", other),
+        }
+        assert!(!git_advanced.rebase_in_progress().await.unwrap());
+        assert_eq!(git(path, &["rev-parse", "HEAD"]), head_before);
+    }
+}
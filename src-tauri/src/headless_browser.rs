@@ -0,0 +1,427 @@
+use anyhow::{anyhow, Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::debug;
+
+/// Overrides the browser binary found on PATH
+const BROWSER_PATH_ENV: &str = "NEXUS_BROWSER_PATH";
+
+/// Chromium-based browsers that speak the DevTools protocol, in order of preference
+const BROWSER_CANDIDATES: &[&str] = &[
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "microsoft-edge",
+    "brave-browser",
+];
+
+pub const DEFAULT_RENDER_TIMEOUT_MS: u64 = 30_000;
+
+const SELECTOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Serializes the rendered document, doctype included
+const SERIALIZE_DOCUMENT: &str = "(document.doctype ? new XMLSerializer().serializeToString(document.doctype) + '\\n' : '') \
+     + document.documentElement.outerHTML";
+
+type CdpSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// How long and for what to wait before taking the rendered DOM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderOptions {
+    /// CSS selector that must match before the page counts as rendered, without waiting
+    /// for the network to go idle; otherwise the page is taken once it does
+    #[serde(default)]
+    pub wait_for_selector: Option<String>,
+    /// Budget for the whole render, browser start-up included
+    #[serde(default = "default_render_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Proxy the browser connects through; Chrome does not accept credentials here
+    #[serde(default)]
+    pub proxy_server: Option<String>,
+}
+
+fn default_render_timeout_ms() -> u64 {
+    DEFAULT_RENDER_TIMEOUT_MS
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            wait_for_selector: None,
+            timeout_ms: DEFAULT_RENDER_TIMEOUT_MS,
+            proxy_server: None,
+        }
+    }
+}
+
+/// Load `url` in a headless browser and return the DOM after scripts have run.
+/// Only http and https pages are rendered, and error statuses fail the render.
+///
+/// A fresh browser with a throwaway profile is started for each render and killed
+/// afterwards, including when the render fails or times out.
+pub async fn render_page(url: &str, options: &RenderOptions) -> Result<String> {
+    check_render_url(url)?;
+    let render = async {
+        let mut browser = HeadlessBrowser::launch(options.proxy_server.as_deref()).await?;
+        let rendered = browser.render(url, options.wait_for_selector.as_deref()).await;
+        browser.close().await;
+        rendered
+    };
+
+    tokio::time::timeout(Duration::from_millis(options.timeout_ms), render)
+        .await
+        .map_err(|_| anyhow!("Rendering {} timed out after {}ms", url, options.timeout_ms))?
+}
+
+/// Keep the browser away from local files and browser-internal pages
+fn check_render_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).with_context(|| format!("Invalid URL: {}", url))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(anyhow!("Only http and https pages can be rendered, not {}:", scheme)),
+    }
+}
+
+/// First DevTools-capable browser on this machine
+pub fn find_browser() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(BROWSER_PATH_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let search_path = std::env::var_os("PATH")?;
+    BROWSER_CANDIDATES.iter().find_map(|name| {
+        std::env::split_paths(&search_path)
+            .map(|dir| dir.join(name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// The browser-level WebSocket URL from a "DevTools listening on ..." stderr line
+fn parse_devtools_url(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("DevTools listening on ")
+        .filter(|url| url.starts_with("ws://"))
+}
+
+/// A headless browser process and its DevTools connection
+struct HeadlessBrowser {
+    child: Child,
+    cdp: CdpConnection,
+    /// Removed on drop, after the process is gone
+    _profile: tempfile::TempDir,
+}
+
+impl HeadlessBrowser {
+    async fn launch(proxy_server: Option<&str>) -> Result<Self> {
+        let binary = find_browser().ok_or_else(|| {
+            anyhow!(
+                "No headless browser found. Install Chromium or Google Chrome, or set {}",
+                BROWSER_PATH_ENV
+            )
+        })?;
+        let profile = tempfile::tempdir()?;
+
+        let mut cmd = Command::new(&binary);
+        cmd.arg("--headless=new")
+            .arg("--disable-gpu")
+            .arg("--no-first-run")
+            .arg("--no-default-browser-check")
+            .arg("--mute-audio")
+            .arg("--remote-debugging-port=0")
+            .arg(format!("--user-data-dir={}", profile.path().display()))
+            .arg("about:blank")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(proxy) = proxy_server {
+            cmd.arg(format!("--proxy-server={}", proxy));
+        }
+        // Own process group, so renderer and GPU helpers die with the browser
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to start {}", binary.display()))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("Browser stderr not captured"))?;
+
+        let mut lines = BufReader::new(stderr).lines();
+        let url = loop {
+            match lines.next_line().await? {
+                Some(line) => {
+                    if let Some(url) = parse_devtools_url(&line) {
+                        break url.to_string();
+                    }
+                }
+                None => return Err(anyhow!("{} exited before opening a DevTools endpoint", binary.display())),
+            }
+        };
+        // Keep draining stderr so a chatty browser never blocks on a full pipe
+        tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+
+        debug!("Headless browser listening on {}", url);
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str()).await?;
+        Ok(Self {
+            child,
+            cdp: CdpConnection::new(socket),
+            _profile: profile,
+        })
+    }
+
+    async fn render(&mut self, url: &str, wait_for_selector: Option<&str>) -> Result<String> {
+        let target = self.cdp.call(None, "Target.createTarget", json!({ "url": "about:blank" })).await?;
+        let target_id = target["targetId"].as_str().ok_or_else(|| anyhow!("Browser did not create a page"))?;
+        let attached = self
+            .cdp
+            .call(None, "Target.attachToTarget", json!({ "targetId": target_id, "flatten": true }))
+            .await?;
+        let session = attached["sessionId"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Browser did not attach to the page"))?
+            .to_string();
+        let session = Some(session.as_str());
+
+        self.cdp.call(session, "Page.enable", json!({})).await?;
+        self.cdp.call(session, "Page.setLifecycleEventsEnabled", json!({ "enabled": true })).await?;
+        self.cdp.call(session, "Network.enable", json!({})).await?;
+
+        let navigation = self.cdp.call(session, "Page.navigate", json!({ "url": url })).await?;
+        if let Some(error) = navigation["errorText"].as_str() {
+            return Err(anyhow!("Failed to load {}: {}", url, error));
+        }
+
+        // Same-document navigations have no loader and no lifecycle of their own
+        if let Some(loader_id) = navigation["loaderId"].as_str() {
+            let status = loop {
+                let event = self.cdp.next_event(session).await?;
+                let params = &event["params"];
+                if event["method"] == "Network.responseReceived" && params["type"] == "Document" && params["loaderId"] == loader_id {
+                    break params["response"]["status"].as_u64().unwrap_or_default();
+                }
+            };
+            if status >= 400 {
+                return Err(anyhow!("{} returned HTTP {}", url, status));
+            }
+            self.cdp.call(session, "Network.disable", json!({})).await?;
+
+            // A selector says when the page is ready; pages that keep polling never go idle
+            if wait_for_selector.is_none() {
+                loop {
+                    let event = self.cdp.next_event(session).await?;
+                    if event["method"] == "Page.lifecycleEvent"
+                        && event["params"]["name"] == "networkIdle"
+                        && event["params"]["loaderId"] == loader_id
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(selector) = wait_for_selector {
+            let probe = format!("document.querySelector({}) !== null", Value::from(selector));
+            while self.cdp.evaluate(session, &probe).await? != Value::Bool(true) {
+                tokio::time::sleep(SELECTOR_POLL_INTERVAL).await;
+            }
+        }
+
+        match self.cdp.evaluate(session, SERIALIZE_DOCUMENT).await? {
+            Value::String(html) => Ok(html),
+            other => Err(anyhow!("Unexpected document serialization: {}", other)),
+        }
+    }
+
+    async fn close(&mut self) {
+        let _ = tokio::time::timeout(Duration::from_secs(2), self.cdp.call(None, "Browser.close", json!({}))).await;
+        if tokio::time::timeout(Duration::from_secs(2), self.child.wait()).await.is_err() {
+            let _ = self.child.start_kill();
+        }
+    }
+}
+
+impl Drop for HeadlessBrowser {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+        }
+    }
+}
+
+/// Request/response and event plumbing over a DevTools WebSocket
+struct CdpConnection {
+    socket: CdpSocket,
+    next_id: u64,
+    /// Events that arrived while waiting for a response
+    pending_events: VecDeque<Value>,
+}
+
+impl CdpConnection {
+    fn new(socket: CdpSocket) -> Self {
+        Self {
+            socket,
+            next_id: 0,
+            pending_events: VecDeque::new(),
+        }
+    }
+
+    async fn call(&mut self, session: Option<&str>, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let mut request = json!({ "id": id, "method": method, "params": params });
+        if let Some(session) = session {
+            request["sessionId"] = Value::from(session);
+        }
+        self.socket.send(Message::Text(request.to_string())).await?;
+
+        loop {
+            let mut message = self.read().await?;
+            if message["id"] == id {
+                if let Some(error) = message.get("error") {
+                    return Err(anyhow!("{} failed: {}", method, error["message"].as_str().unwrap_or("unknown error")));
+                }
+                return Ok(message["result"].take());
+            }
+            if message.get("method").is_some() {
+                self.pending_events.push_back(message);
+            }
+        }
+    }
+
+    /// Next event for `session`, dropping events for other targets
+    async fn next_event(&mut self, session: Option<&str>) -> Result<Value> {
+        loop {
+            let event = match self.pending_events.pop_front() {
+                Some(event) => event,
+                None => self.read().await?,
+            };
+            if event.get("method").is_some() && event["sessionId"].as_str() == session {
+                return Ok(event);
+            }
+        }
+    }
+
+    async fn evaluate(&mut self, session: Option<&str>, expression: &str) -> Result<Value> {
+        let mut result = self
+            .call(session, "Runtime.evaluate", json!({ "expression": expression, "returnByValue": true }))
+            .await?;
+        if let Some(details) = result.get("exceptionDetails") {
+            return Err(anyhow!("Page script failed: {}", details["text"].as_str().unwrap_or("exception")));
+        }
+        Ok(result["result"]["value"].take())
+    }
+
+    async fn read(&mut self) -> Result<Value> {
+        loop {
+            match self.socket.next().await {
+                Some(Ok(Message::Text(text))) => return Ok(serde_json::from_str(&text)?),
+                Some(Ok(Message::Close(_))) | None => return Err(anyhow!("Browser closed the DevTools connection")),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_devtools_url() {
+        let line = "DevTools listening on ws://127.0.0.1:41237/devtools/browser/5d0c8b2e\n";
+        assert_eq!(parse_devtools_url(line), Some("ws://127.0.0.1:41237/devtools/browser/5d0c8b2e"));
+        assert_eq!(parse_devtools_url("[0101/000000.000:ERROR:gpu_init.cc] GPU unavailable"), None);
+    }
+
+    #[tokio::test]
+    async fn test_only_web_pages_are_rendered() {
+        assert!(check_render_url("https://example.com/app").is_ok());
+        for url in ["file:///etc/passwd", "chrome://settings", "javascript:alert(1)", "not a url"] {
+            assert!(render_page(url, &RenderOptions::default()).await.is_err(), "{}", url);
+        }
+    }
+
+    #[cfg(feature = "browser-tests")]
+    async fn serve_script_page() -> String {
+        serve_script_page_with_status("200 OK").await
+    }
+
+    /// Serves a page whose content only exists once its script has run
+    #[cfg(feature = "browser-tests")]
+    async fn serve_script_page_with_status(status: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        const PAGE: &str = "<!DOCTYPE html><html><body><div id=\"app\"></div><script>\
+            setTimeout(() => { const p = document.createElement('p'); p.className = 'ready'; \
+            p.textContent = ['Rendered', 'by', 'script'].join(' '); \
+            document.getElementById('app').appendChild(p); }, 200);</script></body></html>";
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    PAGE.len(),
+                    PAGE
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    #[cfg(feature = "browser-tests")]
+    #[tokio::test]
+    async fn test_render_runs_page_scripts() {
+        let url = serve_script_page().await;
+
+        let raw = reqwest::get(&url).await.unwrap().text().await.unwrap();
+        assert!(!raw.contains("Rendered by script"));
+
+        let options = RenderOptions {
+            wait_for_selector: Some("p.ready".to_string()),
+            ..RenderOptions::default()
+        };
+        let rendered = render_page(&url, &options).await.unwrap();
+        assert!(rendered.starts_with("<!DOCTYPE html>"), "{}", rendered);
+        assert!(rendered.contains("<p class=\"ready\">Rendered by script</p>"), "{}", rendered);
+    }
+
+    #[cfg(feature = "browser-tests")]
+    #[tokio::test]
+    async fn test_render_times_out_waiting_for_selector() {
+        let url = serve_script_page().await;
+        let options = RenderOptions {
+            wait_for_selector: Some("#never-rendered".to_string()),
+            timeout_ms: 3_000,
+            ..RenderOptions::default()
+        };
+        let error = render_page(&url, &options).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"), "{}", error);
+    }
+
+    #[cfg(feature = "browser-tests")]
+    #[tokio::test]
+    async fn test_render_fails_on_error_status() {
+        let url = serve_script_page_with_status("404 Not Found").await;
+        let options = RenderOptions { wait_for_selector: Some("p.ready".to_string()), ..RenderOptions::default() };
+        let error = render_page(&url, &options).await.unwrap_err();
+        assert!(error.to_string().contains("HTTP 404"), "{}", error);
+    }
+}
@@ -7,29 +7,52 @@ use tauri::State;
 use tokio::sync::RwLock;
 use anyhow::Result;
 use chrono::Timelike;
-use tracing::info;
+use tracing::{info, warn};
 
 mod ai;
+mod ai_quality;
+mod code_suggestions;
 mod git;
 mod git_advanced;
 mod terminal;
+mod command_history;
+mod file_watcher;
+mod recording;
 mod ai_optimized;
 mod vision_commands;
 mod config;
 mod utils;
 mod broadcast;
 mod web_scraper;
+mod headless_browser;
+mod scraper_cookies;
+mod scraper_proxy;
+mod scrape_export;
 mod vision;
+mod ocr;
 mod security_scanner;
+mod advisory_db;
+mod remediation;
 mod command_flow;
 mod plugin_system;
+mod plugin_runtime;
 mod collaboration;
+mod collaboration_transport;
 mod workflow_automation;
+mod workflow_triggers;
+mod workflow_webhooks;
 mod analytics;
+mod quantile_sketch;
+mod cache;
 mod cloud_integration;
+mod s3_backend;
 mod ecosystem_awareness;
 mod local_recall;
 mod ollama_config;
+mod secret_store;
+mod system_scan;
+mod vector_store;
+mod redaction;
 
 use ai::AIService;
 use ai_optimized::RequestPriority;
@@ -49,10 +72,24 @@ struct AppState {
     command_flow_engine: Arc<RwLock<command_flow::CommandFlowEngine>>,
     plugin_system: Arc<RwLock<plugin_system::PluginSystem>>,
     collaboration_manager: Arc<RwLock<collaboration::CollaborationManager>>,
+    collaboration_server: Arc<RwLock<Option<collaboration_transport::CollaborationServer>>>,
+    /// Connections to remote collaboration servers by session id
+    collaboration_clients: Arc<RwLock<HashMap<String, collaboration_transport::CollaborationClient>>>,
     workflow_engine: Arc<RwLock<workflow_automation::WorkflowEngine>>,
+    /// Cancels workflow executions without waiting on `workflow_engine`, which they hold while running
+    workflow_canceller: workflow_automation::ExecutionCanceller,
+    workflow_triggers: Arc<RwLock<workflow_triggers::FileTriggerManager>>,
+    webhook_server: Arc<RwLock<Option<workflow_webhooks::WebhookServer>>>,
     analytics_engine: Arc<RwLock<analytics::AnalyticsEngine>>,
     cloud_manager: Arc<RwLock<cloud_integration::CloudIntegrationManager>>,
     ecosystem_awareness: Arc<RwLock<ecosystem_awareness::EcosystemAwareness>>,
+    quality_tracker: Arc<RwLock<ai_quality::QualityTracker>>,
+    code_suggestions: Arc<RwLock<code_suggestions::SuggestionManager>>,
+    secret_store: Arc<secret_store::SecretStore>,
+    command_history: Arc<std::sync::Mutex<command_history::CommandHistoryStore>>,
+    file_watcher: Arc<RwLock<file_watcher::FileWatchManager>>,
+    system_scan_cancel: Arc<RwLock<Option<tokio_util::sync::CancellationToken>>>,
+    vector_store: Arc<RwLock<vector_store::VectorStore>>,
 }
 
 // AI-related commands
@@ -60,13 +97,26 @@ struct AppState {
 async fn ai_chat(
     message: String,
     context: Option<String>,
+    nondeterministic: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     let ai_service = state.ai_service.read().await;
-    ai_service
-        .chat(&message, context.as_deref())
+    let options = ai::ChatOptions {
+        nondeterministic: nondeterministic.unwrap_or(false),
+        ..ai::ChatOptions::default()
+    };
+    let reply = ai_service
+        .chat_with_options(&message, context.as_deref(), options)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    if let Some(usage) = reply.usage {
+        state
+            .analytics_engine
+            .write()
+            .await
+            .record_token_usage(&reply.model, usage.prompt_tokens, usage.completion_tokens);
+    }
+    Ok(reply.content)
 }
 
 #[tauri::command]
@@ -87,12 +137,16 @@ async fn ai_explain_error(
     error_output: String,
     command: String,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<ai_quality::TrackedResponse<String>, String> {
     let ai_service = state.ai_service.read().await;
-    ai_service
+    let explanation = ai_service
         .explain_error(&error_output, &command)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    let prompt = format!("{}\n{}", command, error_output);
+    let interaction_id = state.quality_tracker.write().await.record_interaction("explain_error", &prompt, &explanation);
+    Ok(ai_quality::TrackedResponse { interaction_id, response: explanation })
 }
 
 #[tauri::command]
@@ -100,14 +154,50 @@ async fn ai_generate_code(
     description: String,
     language: String,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<ai_quality::TrackedResponse<String>, String> {
     let ai_service = state.ai_service.read().await;
-    ai_service
+    let code = ai_service
         .generate_code(&description, &language)
         .await
+        .map_err(|e| e.to_string())?;
+
+    let prompt = format!("[{}] {}", language, description);
+    let interaction_id = state.quality_tracker.write().await.record_interaction("generate_code", &prompt, &code);
+    Ok(ai_quality::TrackedResponse { interaction_id, response: code })
+}
+
+// AI quality signal commands
+#[tauri::command]
+async fn ai_record_quality_signal(
+    interaction_id: String,
+    signal: ai_quality::QualitySignal,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut quality_tracker = state.quality_tracker.write().await;
+    quality_tracker
+        .record_signal(&interaction_id, signal)
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn ai_get_recent_interactions(
+    command: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ai_quality::AIInteraction>, String> {
+    let quality_tracker = state.quality_tracker.read().await;
+    Ok(quality_tracker.recent_interactions(command.as_deref(), limit.unwrap_or(20)))
+}
+
+#[tauri::command]
+async fn ai_get_quality_stats(
+    command: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ai_quality::QualityStats>, String> {
+    let quality_tracker = state.quality_tracker.read().await;
+    Ok(quality_tracker.get_stats(command.as_deref()))
+}
+
 // Terminal-related commands
 #[tauri::command]
 async fn create_terminal(
@@ -163,6 +253,18 @@ async fn resize_terminal(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn send_terminal_signal(
+    terminal_id: String,
+    signal: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .send_signal(&terminal_id, &signal)
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn kill_terminal(
     terminal_id: String,
@@ -175,6 +277,118 @@ async fn kill_terminal(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn get_terminal_scrollback(
+    terminal_id: String,
+    max_lines: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .get_scrollback(&terminal_id, max_lines)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_terminal_scrollback(
+    terminal_id: String,
+    file_path: String,
+    format: Option<terminal::ScrollbackFormat>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .export_scrollback(&terminal_id, &file_path, format.unwrap_or(terminal::ScrollbackFormat::Plain))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn terminal_start_recording(
+    terminal_id: String,
+    output_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .start_recording(&terminal_id, output_path)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn terminal_stop_recording(
+    recording_id: String,
+    state: State<'_, AppState>,
+) -> Result<recording::RecordingInfo, String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .stop_recording(&recording_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn terminal_pause_recording(
+    recording_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .pause_recording(&recording_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn terminal_resume_recording(
+    recording_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .resume_recording(&recording_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_terminal_position(
+    terminal_id: String,
+    position: terminal::WindowPosition,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    terminal_manager
+        .set_terminal_position(&terminal_id, position)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn save_session_layout(
+    init_commands: Option<HashMap<String, Vec<String>>>,
+    state: State<'_, AppState>,
+) -> Result<terminal::SessionLayout, String> {
+    let terminal_manager = state.terminal_manager.read().await;
+    let layout = terminal_manager
+        .snapshot_layout(&init_commands.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    layout.save().map_err(|e| e.to_string())?;
+    Ok(layout)
+}
+
+#[tauri::command]
+async fn restore_session_layout(
+    replay_init_commands: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let layout = match terminal::SessionLayout::load().map_err(|e| e.to_string())? {
+        Some(layout) => layout,
+        None => return Ok(Vec::new()),
+    };
+    let mut terminal_manager = state.terminal_manager.write().await;
+    terminal_manager
+        .restore_layout(&layout, replay_init_commands.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // Git integration commands
 #[tauri::command]
 async fn git_status(path: String) -> Result<String, String> {
@@ -187,9 +401,19 @@ async fn git_generate_commit(
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     let changes = git::get_diff(&path).map_err(|e| e.to_string())?;
+    let lfs_assets: Vec<String> = git::get_diff_stat(&path, None)
+        .map_err(|e| e.to_string())?
+        .files
+        .into_iter()
+        .filter(|file| file.lfs)
+        .map(|file| match file.lfs_size {
+            Some(size) => format!("{} ({} bytes)", file.path, size),
+            None => file.path,
+        })
+        .collect();
     let ai_service = state.ai_service.read().await;
     ai_service
-        .generate_commit_message(&changes)
+        .generate_commit_message(&changes, &lfs_assets)
         .await
         .map_err(|e| e.to_string())
 }
@@ -234,6 +458,30 @@ async fn git_get_stash_list(path: String) -> Result<Vec<git::StashEntry>, String
     git::get_stash_list(&path).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn git_create_stash(
+    path: String,
+    message: Option<String>,
+    include_untracked: bool,
+) -> Result<git::StashEntry, String> {
+    git::create_stash(&path, message, include_untracked).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_apply_stash(path: String, index: usize) -> Result<git::StashApplyResult, String> {
+    git::apply_stash(&path, index).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_pop_stash(path: String, index: usize) -> Result<git::StashApplyResult, String> {
+    git::pop_stash(&path, index).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_drop_stash(path: String, index: usize) -> Result<(), String> {
+    git::drop_stash(&path, index).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn git_get_commit_changes(path: String, commit_hash: String) -> Result<Vec<git::FileChange>, String> {
     git::get_commit_changes(&path, &commit_hash).map_err(|e| e.to_string())
@@ -244,6 +492,94 @@ async fn git_get_repository_stats(path: String) -> Result<git::RepositoryStats,
     git::get_repository_stats(&path).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn git_get_diff_stat(path: String, rev: Option<String>) -> Result<git::DiffStat, String> {
+    git::get_diff_stat(&path, rev).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_get_conflicts(path: String) -> Result<Vec<git::ConflictedFile>, String> {
+    git::get_conflicts(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_resolve_conflict(
+    path: String,
+    file: String,
+    resolution: git::ConflictResolution,
+) -> Result<(), String> {
+    git::resolve_conflict(&path, &file, resolution).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_get_unstaged_hunks(path: String, file: String) -> Result<Vec<git::Hunk>, String> {
+    git::get_unstaged_hunks(&path, &file).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_get_staged_hunks(path: String, file: String) -> Result<Vec<git::Hunk>, String> {
+    git::get_staged_hunks(&path, &file).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_stage_hunk(path: String, file: String, hunk_id: String) -> Result<(), String> {
+    git::stage_hunk(&path, &file, &hunk_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_unstage_hunk(path: String, file: String, hunk_id: String) -> Result<(), String> {
+    git::unstage_hunk(&path, &file, &hunk_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_list_tags(path: String) -> Result<Vec<git::TagInfo>, String> {
+    git::list_tags(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_create_tag(
+    path: String,
+    name: String,
+    message: Option<String>,
+    commit: Option<String>,
+) -> Result<git::TagInfo, String> {
+    git::create_tag(&path, &name, message, commit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_delete_tag(path: String, name: String) -> Result<(), String> {
+    git::delete_tag(&path, &name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_list_worktrees(path: String) -> Result<Vec<git::WorktreeInfo>, String> {
+    git::list_worktrees(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_add_worktree(path: String, new_path: String, branch: String) -> Result<git::WorktreeInfo, String> {
+    git::add_worktree(&path, &new_path, &branch).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_remove_worktree(path: String, worktree_path: String, force: Option<bool>) -> Result<(), String> {
+    git::remove_worktree(&path, &worktree_path, force.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_get_submodules(path: String) -> Result<Vec<git::SubmoduleInfo>, String> {
+    git::get_submodules(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_update_submodules(
+    path: String,
+    init: Option<bool>,
+    recursive: Option<bool>,
+) -> Result<Vec<git::SubmoduleInfo>, String> {
+    git::update_submodules(&path, init.unwrap_or(true), recursive.unwrap_or(true)).map_err(|e| e.to_string())
+}
+
 // Advanced Git Integration commands
 #[tauri::command]
 async fn git_generate_visual_graph(
@@ -254,6 +590,16 @@ async fn git_generate_visual_graph(
     git_advanced.generate_visual_graph(max_commits).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn git_generate_visual_graph_paged(
+    path: String,
+    after: Option<String>,
+    limit: u32,
+) -> Result<git_advanced::GitGraphPage, String> {
+    let git_advanced = git_advanced::GitAdvanced::new(&path);
+    git_advanced.generate_visual_graph_paged(after, limit).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn git_generate_time_travel(
     path: String,
@@ -307,6 +653,40 @@ async fn git_create_branch_from_commit(
     git_advanced.create_branch_from_commit(&branch_name, &commit).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn git_generate_rebase_plan(
+    path: String,
+    onto: String,
+    from: String,
+) -> Result<git_advanced::RebasePlan, String> {
+    let git_advanced = git_advanced::GitAdvanced::new(&path);
+    git_advanced.generate_rebase_plan(&onto, &from).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_apply_rebase_plan(
+    path: String,
+    plan: git_advanced::RebasePlan,
+) -> Result<git_advanced::RebaseOutcome, String> {
+    let git_advanced = git_advanced::GitAdvanced::new(&path);
+    git_advanced.apply_rebase_plan(&plan).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_abort_rebase(path: String) -> Result<(), String> {
+    let git_advanced = git_advanced::GitAdvanced::new(&path);
+    git_advanced.abort_rebase().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn git_get_commit_signatures(
+    path: String,
+    max_commits: Option<u32>,
+) -> Result<Vec<git_advanced::CommitSignature>, String> {
+    let git_advanced = git_advanced::GitAdvanced::new(&path);
+    git_advanced.get_commit_signatures(max_commits).await.map_err(|e| e.to_string())
+}
+
 // Contextual suggestions commands
 #[tauri::command]
 async fn get_contextual_suggestions(
@@ -314,7 +694,7 @@ async fn get_contextual_suggestions(
     context: serde_json::Value,
     _filter: Option<serde_json::Value>,
     state: State<'_, AppState>,
-) -> Result<Vec<serde_json::Value>, String> {
+) -> Result<Vec<code_suggestions::Suggestion>, String> {
     let ai_service = state.ai_service.read().await;
     
     // Convert context to a structured prompt for AI
@@ -331,40 +711,36 @@ async fn get_contextual_suggestions(
     );
     
     let response = ai_service.chat(&prompt, Some(&context_str)).await.map_err(|e| e.to_string())?;
-    
-    // Parse AI response and return structured suggestions
-    // For now, return a fallback response if parsing fails
-    match serde_json::from_str::<Vec<serde_json::Value>>(&response) {
-        Ok(suggestions) => Ok(suggestions),
-        Err(_) => {
-            // Fallback: generate basic suggestions based on partial command
-            let mut suggestions = Vec::new();
-            
-            if partial_command.starts_with("git") {
-                suggestions.push(serde_json::json!({
-                    "command": "git status",
-                    "description": "Show working tree status",
-                    "confidence": 0.8,
-                    "category": "git",
-                    "context": ["git-command"]
-                }));
-            } else if partial_command.starts_with("ls") {
-                suggestions.push(serde_json::json!({
-                    "command": "ls -la",
-                    "description": "List files with details",
-                    "confidence": 0.9,
-                    "category": "navigation",
-                    "context": ["file-listing"]
-                }));
-            }
-            
-            Ok(suggestions)
-        }
+    drop(ai_service);
+
+    let suggestions = code_suggestions::parse_command_suggestions(&response);
+    if !suggestions.is_empty() {
+        return Ok(suggestions);
     }
+
+    // Nothing usable from the AI: fall back to the user's own history and project
+    let recent_commands = state
+        .command_history
+        .lock()
+        .map(|history| history.recent_commands(command_history::DEFAULT_HISTORY_SIZE))
+        .unwrap_or_default();
+    let project_type = match context.get("projectType").and_then(|v| v.as_str()) {
+        Some(project_type) => project_type.to_string(),
+        None => {
+            let entries: Vec<String> = context
+                .get("currentDirectory")
+                .and_then(|v| v.as_str())
+                .and_then(|dir| std::fs::read_dir(dir).ok())
+                .map(|dir| dir.filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().to_string()).collect())
+                .unwrap_or_default();
+            code_suggestions::detect_project_type(&entries).to_string()
+        }
+    };
+    Ok(code_suggestions::fallback_suggestions(&partial_command, &recent_commands, &project_type))
 }
 
 #[tauri::command]
-async fn get_current_context() -> Result<serde_json::Value, String> {
+async fn get_current_context(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
     use std::env;
     // Process commands would be used for system integration
     
@@ -395,17 +771,7 @@ async fn get_current_context() -> Result<serde_json::Value, String> {
     };
     
     // Determine project type
-    let project_type = if dir_contents.contains(&"package.json".to_string()) {
-        "nodejs"
-    } else if dir_contents.contains(&"Cargo.toml".to_string()) {
-        "rust"
-    } else if dir_contents.contains(&"requirements.txt".to_string()) || dir_contents.contains(&"setup.py".to_string()) {
-        "python"
-    } else if dir_contents.contains(&"go.mod".to_string()) {
-        "go"
-    } else {
-        "other"
-    };
+    let project_type = code_suggestions::detect_project_type(&dir_contents);
     
     // Get time of day
     let hour = chrono::Local::now().hour();
@@ -416,6 +782,37 @@ async fn get_current_context() -> Result<serde_json::Value, String> {
         _ => "night"
     };
     
+    let working_on_files: Vec<String> = state
+        .file_watcher
+        .write()
+        .await
+        .recently_modified(file_watcher::CONTEXT_FILES)
+        .into_iter()
+        .map(|file| file.path)
+        .collect();
+
+    let shell_pids = state.terminal_manager.read().await.shell_pids();
+    let process_limit = state.config.read().await.ai.context_process_limit;
+    let active_processes = ecosystem_awareness::active_processes(
+        &ecosystem_awareness::ProcfsSource::default(),
+        &shell_pids,
+        process_limit,
+    )
+    .await;
+
+    // This context is sent to the AI provider as-is, so keep secrets out of it
+    let redactor = state.ai_service.read().await.redactor.clone();
+    let shell_history: Vec<String> = state
+        .command_history
+        .lock()
+        .map(|history| history.recent_commands(command_history::CONTEXT_SHELL_HISTORY))
+        .unwrap_or_default()
+        .iter()
+        .map(|command| redactor.redact_logged(command, "command history context"))
+        .collect();
+    let recent_commands = &shell_history[shell_history.len().saturating_sub(command_history::CONTEXT_COMMANDS)..];
+    let environment_vars = redactor.redact_env(std::env::vars().collect());
+
     Ok(serde_json::json!({
         "currentDirectory": current_dir,
         "directoryContents": dir_contents,
@@ -423,30 +820,77 @@ async fn get_current_context() -> Result<serde_json::Value, String> {
         "projectType": project_type,
         "timeOfDay": time_of_day,
         "dayOfWeek": chrono::Local::now().format("%A").to_string(),
-        "recentCommands": Vec::<String>::new(), // Would need to be tracked separately
-        "workingOnFiles": Vec::<String>::new(), // Would need file monitoring
-        "activeProcesses": Vec::<serde_json::Value>::new(), // Would need process monitoring
-        "environmentVars": std::env::vars().collect::<std::collections::HashMap<String, String>>(),
-        "shellHistory": Vec::<String>::new() // Would need shell history integration
+        "recentCommands": recent_commands,
+        "workingOnFiles": working_on_files,
+        "activeProcesses": active_processes,
+        "environmentVars": environment_vars,
+        "shellHistory": shell_history
     }))
 }
 
+#[tauri::command]
+async fn get_command_history(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<command_history::HistoryEntry>, String> {
+    let history = state.command_history.lock().map_err(|e| e.to_string())?;
+    Ok(history.recent(limit.unwrap_or(100)))
+}
+
+#[tauri::command]
+async fn clear_command_history(state: State<'_, AppState>) -> Result<(), String> {
+    let mut history = state.command_history.lock().map_err(|e| e.to_string())?;
+    history.clear().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn start_file_watch(path: String, state: State<'_, AppState>) -> Result<(), String> {
+    let mut file_watcher = state.file_watcher.write().await;
+    file_watcher
+        .start_watch(std::path::Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn stop_file_watch(path: String, state: State<'_, AppState>) -> Result<(), String> {
+    let mut file_watcher = state.file_watcher.write().await;
+    file_watcher
+        .stop_watch(std::path::Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_file_watches(state: State<'_, AppState>) -> Result<Vec<file_watcher::WatchStatus>, String> {
+    Ok(state.file_watcher.write().await.list_watches())
+}
+
+#[tauri::command]
+async fn get_recently_modified(
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<file_watcher::RecentFile>, String> {
+    let limit = limit.unwrap_or(file_watcher::CONTEXT_FILES);
+    Ok(state.file_watcher.write().await.recently_modified(limit))
+}
+
+/// Queue a finished command for the learning engine; it is written later in a batch, so
+/// this never waits on the learning database
 #[tauri::command]
 async fn learn_from_command(
     command: String,
     successful: bool,
     context: serde_json::Value,
+    state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // Log command learning for future analysis
-    tracing::info!(
-        "Learning from command: {} (success: {}) in context: {}",
+    let interaction = ecosystem_awareness::UserInteraction {
         command,
-        successful,
-        context.get("currentDirectory").and_then(|v| v.as_str()).unwrap_or("unknown")
-    );
-    
-    // In a real implementation, this would store learning data in a database
-    // For now, we just log it
+        success: successful,
+        execution_time: context.get("executionTime").and_then(|v| v.as_u64()).unwrap_or(0),
+        user_context: context.get("currentDirectory").and_then(|v| v.as_str()).unwrap_or("unknown").to_string(),
+        timestamp: chrono::Utc::now(),
+        error_output: context.get("errorOutput").and_then(|v| v.as_str()).map(str::to_string),
+    };
+    state.ecosystem_awareness.read().await.queue_interaction(interaction);
     Ok(())
 }
 
@@ -477,14 +921,77 @@ async fn create_temp_screenshot_path(state: State<'_, AppState>) -> Result<Strin
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn list_profiles() -> Result<Vec<String>, String> {
+    let profiles = config::ProfileStore::open().map_err(|e| e.to_string())?;
+    profiles.list_profiles().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_active_profile() -> Result<String, String> {
+    let profiles = config::ProfileStore::open().map_err(|e| e.to_string())?;
+    Ok(profiles.active_profile())
+}
+
+#[tauri::command]
+async fn create_profile(name: String, base_on: Option<String>) -> Result<AppConfig, String> {
+    let profiles = config::ProfileStore::open().map_err(|e| e.to_string())?;
+    profiles.create_profile(&name, base_on.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn switch_profile(name: String, state: State<'_, AppState>) -> Result<AppConfig, String> {
+    let profiles = config::ProfileStore::open().map_err(|e| e.to_string())?;
+    let mut new_config = profiles.load_profile(&name).map_err(|e| e.to_string())?;
+    if new_config.migrate_secrets(&state.secret_store, &name).map_err(|e| e.to_string())? {
+        new_config.save_to(&profiles.profile_path(&name)).map_err(|e| e.to_string())?;
+    }
+    new_config.ensure_directories().map_err(|e| e.to_string())?;
+    // Build the replacement services before committing so a failure leaves the current profile in place
+    let new_ai_service = AIService::new(&new_config.ai)
+        .await
+        .map_err(|e| e.to_string())?
+        .with_secret_store(state.secret_store.clone());
+    let stats = state.ai_service.read().await.stats.clone();
+    let mut new_optimized_service = OptimizedAIService::new(&new_config.ai)
+        .await
+        .map_err(|e| e.to_string())?
+        .with_request_stats(stats);
+    new_optimized_service.start_background_tasks().await.map_err(|e| e.to_string())?;
+
+    OptimizedAIService::replace(&state.optimized_ai_service, new_optimized_service, AI_RESTART_DRAIN_TIMEOUT)
+        .await
+        .map_err(|e| e.to_string())?;
+    ai::replace_service(&state.ai_service, new_ai_service, AI_RESTART_DRAIN_TIMEOUT)
+        .await
+        .map_err(|e| e.to_string())?;
+    *state.config.write().await = new_config.clone();
+    profiles.set_active_profile(&name).map_err(|e| e.to_string())?;
+    info!("Switched to config profile: {}", name);
+    Ok(new_config)
+}
+
+#[tauri::command]
+async fn delete_profile(name: String) -> Result<(), String> {
+    let profiles = config::ProfileStore::open().map_err(|e| e.to_string())?;
+    profiles.delete_profile(&name).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn update_config(
-    new_config: AppConfig,
+    mut new_config: AppConfig,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let mut config = state.config.write().await;
-    *config = new_config.clone();
-    config.save().map_err(|e| e.to_string())
+) -> Result<(), Vec<config::ConfigError>> {
+    new_config.validate()?;
+    let profile = config::ProfileStore::open()
+        .map(|profiles| profiles.active_profile())
+        .unwrap_or_else(|_| config::DEFAULT_PROFILE.to_string());
+    new_config
+        .migrate_secrets(&state.secret_store, &profile)
+        .map_err(|e| vec![config::ConfigError { field: "ai.api_key".to_string(), message: e.to_string() }])?;
+    new_config.save().map_err(|e| vec![config::ConfigError { field: String::new(), message: e.to_string() }])?;
+    *state.config.write().await = new_config;
+    Ok(())
 }
 
 #[tauri::command]
@@ -548,13 +1055,58 @@ async fn ai_analyze_repository(
 async fn ai_suggest_improvements(
     code: String,
     language: String,
+    file: Option<String>,
+    structured: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
+) -> Result<ai_quality::TrackedResponse<code_suggestions::ImprovementSuggestions>, String> {
+    let structured = structured.unwrap_or(false);
     let ai_service = state.ai_service.read().await;
-    ai_service
-        .suggest_improvements(&code, &language)
-        .await
-        .map_err(|e| e.to_string())
+    let response = if structured {
+        ai_service.suggest_improvements_structured(&code, &language).await
+    } else {
+        ai_service.suggest_improvements(&code, &language).await
+    }
+    .map_err(|e| e.to_string())?;
+
+    let prompt = format!("[{}] {}", language, code);
+    let interaction_id = state.quality_tracker.write().await.record_interaction("suggest_improvements", &prompt, &response);
+
+    if !structured {
+        return Ok(ai_quality::TrackedResponse { interaction_id, response: code_suggestions::ImprovementSuggestions::Prose(response) });
+    }
+
+    let suggestions = code_suggestions::parse_suggestions(&response, file.as_deref()).map_err(|e| e.to_string())?;
+    if file.is_some() {
+        state.code_suggestions.write().await.stage(&suggestions);
+    }
+    Ok(ai_quality::TrackedResponse { interaction_id, response: code_suggestions::ImprovementSuggestions::Structured(suggestions) })
+}
+
+#[tauri::command]
+async fn apply_code_suggestion(
+    file: String,
+    suggestion_id: String,
+    state: State<'_, AppState>,
+) -> Result<code_suggestions::AppliedSuggestion, String> {
+    let mut code_suggestions = state.code_suggestions.write().await;
+    code_suggestions.apply(&file, &suggestion_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn undo_last_suggestion(
+    file: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let mut code_suggestions = state.code_suggestions.write().await;
+    code_suggestions.undo_last(&file).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_code_suggestions(
+    file: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<code_suggestions::CodeSuggestion>, String> {
+    Ok(state.code_suggestions.read().await.staged_for(&file))
 }
 
 #[tauri::command]
@@ -593,11 +1145,14 @@ async fn set_ai_model(
         config_guard.ai.clone()
     };
     
-    let new_ai_service = AIService::new(&config).await.map_err(|e| e.to_string())?;
+    let new_ai_service = AIService::new(&config)
+        .await
+        .map_err(|e| e.to_string())?
+        .with_secret_store(state.secret_store.clone());
     
     {
         let mut ai_service_guard = state.ai_service.write().await;
-        *ai_service_guard = new_ai_service;
+        *ai_service_guard = new_ai_service.with_stats(ai_service_guard.stats.clone());
     }
     
     info!("AI model changed to: {}", model);
@@ -614,10 +1169,11 @@ async fn get_available_models(state: State<'_, AppState>) -> Result<Vec<String>,
 async fn send_ai_message(
     message: String,
     context: serde_json::Value,
+    include_ecosystem: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
     // Extract context information
-    let context_str = match context {
+    let mut context_str = match context {
         serde_json::Value::Object(obj) => {
             let working_dir = obj.get("workingDirectory")
                 .and_then(|v| v.as_str())
@@ -633,13 +1189,34 @@ async fn send_ai_message(
         },
         _ => "Basic terminal context".to_string()
     };
+
+    let ai_service = state.ai_service.read().await;
+    if include_ecosystem.unwrap_or(false) {
+        // A summary rather than the full state, which would blow the token budget
+        let ecosystem = state.ecosystem_awareness.read().await.get_comprehensive_context().await;
+        match ecosystem {
+            Ok(ecosystem) => {
+                let summary = ecosystem_awareness::ContextSummary::from_context(&ecosystem).to_prompt();
+                context_str.push_str("\n\n");
+                context_str.push_str(&ai_service.redactor.redact_logged(&summary, "ecosystem context"));
+            }
+            Err(e) => warn!("Ecosystem context unavailable: {}", e),
+        }
+    }
     
     // Use the memory-enabled AI chat with a unique conversation ID for the AI Assistant
-    let ai_service = state.ai_service.read().await;
-    ai_service
+    let reply = ai_service
         .chat_with_memory(&message, "ai_assistant_main", Some(&context_str))
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    if let Some(usage) = reply.usage {
+        state
+            .analytics_engine
+            .write()
+            .await
+            .record_token_usage(&reply.model, usage.prompt_tokens, usage.completion_tokens);
+    }
+    Ok(reply.content)
 }
 
 #[tauri::command]
@@ -709,23 +1286,41 @@ async fn close_window(window: tauri::Window) -> Result<(), String> {
 }
 
 // AI service management commands
+const AI_RESTART_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Restart both AI services, letting in-flight requests finish for up to `drain_timeout_ms`
+/// before cancelling them. Queued requests are answered with an error rather than migrated.
 #[tauri::command]
-async fn restart_ai_service(state: State<'_, AppState>) -> Result<(), String> {
+async fn restart_ai_service(
+    drain_timeout_ms: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<ai::DrainSummary, String> {
     let config = {
         let config_guard = state.config.read().await;
         config_guard.ai.clone()
     };
-    
-    // Recreate AI service
-    let new_ai_service = AIService::new(&config).await.map_err(|e| e.to_string())?;
-    
-    // Replace the AI service in state
-    {
-        let mut ai_service_guard = state.ai_service.write().await;
-        *ai_service_guard = new_ai_service;
-    }
-    
-    Ok(())
+    let drain_timeout = drain_timeout_ms.map(std::time::Duration::from_millis).unwrap_or(AI_RESTART_DRAIN_TIMEOUT);
+
+    // Build the replacements first so a failure leaves the running services untouched
+    let new_ai_service = AIService::new(&config)
+        .await
+        .map_err(|e| e.to_string())?
+        .with_secret_store(state.secret_store.clone());
+    let stats = state.ai_service.read().await.stats.clone();
+    let mut new_optimized_service = OptimizedAIService::new(&config)
+        .await
+        .map_err(|e| e.to_string())?
+        .with_request_stats(stats);
+    new_optimized_service.start_background_tasks().await.map_err(|e| e.to_string())?;
+
+    let optimized_summary =
+        OptimizedAIService::replace(&state.optimized_ai_service, new_optimized_service, drain_timeout)
+            .await
+            .map_err(|e| e.to_string())?;
+    let summary = ai::replace_service(&state.ai_service, new_ai_service, drain_timeout)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(summary + optimized_summary)
 }
 
 // OptimizedAIService commands
@@ -758,17 +1353,29 @@ async fn optimized_ai_chat(
     
     let response = optimized_service.chat_async(&request.prompt, request.context.as_deref()).await
         .map_err(|e| e.to_string())?;
+    if let Some(usage) = response.usage {
+        state
+            .analytics_engine
+            .write()
+            .await
+            .record_token_usage(&response.model_used, usage.prompt_tokens, usage.completion_tokens);
+    }
     
     Ok(response.content)
 }
 
+/// Request counters from the AI service with queue depths from the request processor
+async fn collect_ai_service_stats(state: &AppState) -> ai::AIServiceStats {
+    let mut stats = state.ai_service.read().await.service_stats();
+    let pool = state.optimized_ai_service.read().await.get_pool_stats().await;
+    stats.pending_requests = pool.pending_requests;
+    stats.queue_depths = pool.queue_by_priority;
+    stats
+}
+
 #[tauri::command]
-async fn get_ai_service_stats(
-    state: State<'_, AppState>,
-) -> Result<ai_optimized::PoolStats, String> {
-    let optimized_service = state.optimized_ai_service.read().await;
-    let stats = optimized_service.get_pool_stats().await;
-    Ok(stats)
+async fn get_ai_service_stats(state: State<'_, AppState>) -> Result<ai::AIServiceStats, String> {
+    Ok(collect_ai_service_stats(&state).await)
 }
 
 #[tauri::command]
@@ -835,11 +1442,15 @@ async fn analyze_screen_with_ai(
     image_data: Vec<u8>,
     _prompt: String,
     state: State<'_, AppState>,
+    app: tauri::AppHandle,
 ) -> Result<String, String> {
+    use tauri::Emitter;
     let vision_service = state.vision_service.read().await;
     let capture_id = uuid::Uuid::new_v4().to_string();
     vision_service
-        .analyze_screen_comprehensive(&capture_id, image_data)
+        .analyze_screen_with_progress(&capture_id, image_data, &|progress| {
+            let _ = app.emit(vision::VISION_PROGRESS_EVENT, &progress);
+        })
         .await
         .map(|analysis| analysis.summary)
         .map_err(|e| e.to_string())
@@ -848,16 +1459,71 @@ async fn analyze_screen_with_ai(
 #[tauri::command]
 async fn capture_and_analyze_screen(
     state: State<'_, AppState>,
+    app: tauri::AppHandle,
 ) -> Result<vision::ScreenAnalysis, String> {
+    use tauri::Emitter;
     let vision_service = state.vision_service.read().await;
-    let capture = vision_service.capture_full_screen().await.map_err(|e| e.to_string())?;
+    let capture = vision_service.capture_full_screen(None).await.map_err(|e| e.to_string())?;
     let capture_id = uuid::Uuid::new_v4().to_string();
     vision_service
-        .analyze_screen_comprehensive(&capture_id, capture.data)
+        .analyze_screen_with_progress(&capture_id, capture.data, &|progress| {
+            let _ = app.emit(vision::VISION_PROGRESS_EVENT, &progress);
+        })
         .await
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn ai_generate_embeddings(
+    texts: Vec<String>,
+    model: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Vec<f32>>, String> {
+    let ai_service = state.ai_service.read().await;
+    ai_service.embed(texts, model).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn semantic_index_text(
+    id: String,
+    text: String,
+    tags: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let vector = {
+        let ai_service = state.ai_service.read().await;
+        let mut embeddings = ai_service.embed(vec![text.clone()], None).await.map_err(|e| e.to_string())?;
+        embeddings.pop().ok_or("Embedding provider returned no vector")?
+    };
+    let metadata = vector_store::VectorMetadata { text, tags: tags.unwrap_or_default() };
+    let mut vector_store = state.vector_store.write().await;
+    vector_store.add(&id, vector, metadata).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn semantic_search(
+    query: String,
+    top_k: Option<usize>,
+    tag: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<vector_store::ScoredHit>, String> {
+    let vector = {
+        let ai_service = state.ai_service.read().await;
+        let mut embeddings = ai_service.embed(vec![query], None).await.map_err(|e| e.to_string())?;
+        embeddings.pop().ok_or("Embedding provider returned no vector")?
+    };
+    let vector_store = state.vector_store.read().await;
+    vector_store
+        .search_tagged(&vector, top_k.unwrap_or(10), tag.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ai_clear_cache(state: State<'_, AppState>) -> Result<(), String> {
+    state.ai_service.read().await.clear_cache();
+    Ok(())
+}
+
 #[tauri::command]
 async fn ai_clear_completed_requests(state: State<'_, AppState>) -> Result<(), String> {
     let ai_service = state.ai_service.read().await;
@@ -917,12 +1583,15 @@ async fn ai_batch_process(
 }
 
 #[tauri::command]
-async fn ai_get_service_stats(state: State<'_, AppState>) -> Result<String, String> {
-    let ai_service = state.ai_service.read().await;
-    ai_service
-        .get_service_stats()
-        .await
-        .map_err(|e| e.to_string())
+async fn ai_get_service_stats(state: State<'_, AppState>) -> Result<ai::AIServiceStats, String> {
+    Ok(collect_ai_service_stats(&state).await)
+}
+
+#[tauri::command]
+async fn ai_reset_stats(state: State<'_, AppState>) -> Result<(), String> {
+    state.ai_service.read().await.reset_stats();
+    let path = state.config.read().await.paths.data_dir.join("ai_stats.json");
+    ai::RequestStats::default().save(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -972,15 +1641,31 @@ async fn ai_submit_async_request(
     
     let request = ai_optimized::AIRequest::simple(prompt)
         .with_priority(priority);
-    
-    if let Some(model) = model {
-        let request = request.with_model(model);
-        let _rx = ai_service.submit_request_async(request).await.map_err(|e| e.to_string())?;
-    } else {
-        let _rx = ai_service.submit_request_async(request).await.map_err(|e| e.to_string())?;
-    }
-    
-    Ok("Request submitted successfully".to_string())
+    let request = match model {
+        Some(model) => request.with_model(model),
+        None => request,
+    };
+
+    // The id lets the frontend cancel the request with `ai_cancel_request`
+    let request_id = request.id.clone();
+    let _rx = ai_service.submit_request_async(request).await.map_err(|e| e.to_string())?;
+
+    Ok(request_id)
+}
+
+#[tauri::command]
+async fn ai_get_circuit_state(state: State<'_, AppState>) -> Result<String, String> {
+    let ai_service = state.optimized_ai_service.read().await;
+    Ok(ai_service.circuit_state().await.as_str().to_string())
+}
+
+#[tauri::command]
+async fn ai_cancel_request(
+    request_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let ai_service = state.optimized_ai_service.read().await;
+    Ok(ai_service.cancel_request(&request_id).await)
 }
 
 #[tauri::command]
@@ -1138,6 +1823,7 @@ async fn ai_analyze_response(
             .to_string(),
         processing_time: Duration::from_millis(processing_time_ms),
         tokens_used,
+        usage: None,
         success,
         error: response_data.get("error")
             .and_then(|v| v.as_str())
@@ -1167,22 +1853,45 @@ async fn vision_initialize_service() -> Result<(), String> {
 }
 
 #[tauri::command]
-async fn vision_capture_full_screen() -> Result<vision::ScreenCapture, String> {
+async fn vision_capture_full_screen(display_index: Option<usize>) -> Result<vision::ScreenCapture, String> {
     let vision_service = vision::get_vision_service();
     let service = vision_service.lock().await;
-    service.capture_full_screen().await.map_err(|e| e.to_string())
+    service.capture_full_screen(display_index).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vision_diff_captures(
+    before: vision::ScreenCapture,
+    after: vision::ScreenCapture,
+    options: Option<vision::DiffOptions>,
+) -> Result<vision::VisualDiff, String> {
+    let vision_service = vision::get_vision_service();
+    let service = vision_service.lock().await;
+    service
+        .diff_captures(&before, &after, &options.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vision_list_displays() -> Result<Vec<vision::DisplayInfo>, String> {
+    let vision_service = vision::get_vision_service();
+    let service = vision_service.lock().await;
+    service.list_displays().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 async fn vision_capture_region(
-    x: u32,
-    y: u32,
-    width: u32,
-    height: u32,
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+    display_index: Option<usize>,
 ) -> Result<vision::ScreenCapture, String> {
     let vision_service = vision::get_vision_service();
     let service = vision_service.lock().await;
-    service.capture_screen_region(x, y, width, height).await.map_err(|e| e.to_string())
+    let request = vision::RegionRequest { x, y, width, height, display_index };
+    service.capture_screen_region(request).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -1195,6 +1904,13 @@ async fn vision_perform_ocr(
     service.perform_ocr(&image_path, &engine).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn vision_list_ocr_engines() -> Result<Vec<ocr::OcrEngineInfo>, String> {
+    let vision_service = vision::get_vision_service();
+    let service = vision_service.lock().await;
+    Ok(service.list_ocr_engines().await)
+}
+
 #[tauri::command]
 async fn vision_detect_ui_elements(
     image_path: String,
@@ -1222,13 +1938,24 @@ async fn vision_analyze_with_ai(
 async fn vision_comprehensive_analysis(
     capture_id: String,
     image_data: Vec<u8>,
+    app: tauri::AppHandle,
 ) -> Result<vision::ScreenAnalysis, String> {
+    use tauri::Emitter;
     let vision_service = vision::get_vision_service();
     let service = vision_service.lock().await;
-    service.analyze_screen_comprehensive(&capture_id, image_data)
+    service
+        .analyze_screen_with_progress(&capture_id, image_data, &|progress| {
+            let _ = app.emit(vision::VISION_PROGRESS_EVENT, &progress);
+        })
         .await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn vision_clear_ocr_cache(state: State<'_, AppState>) -> Result<(), String> {
+    state.vision_service.read().await.clear_ocr_cache().map_err(|e| e.to_string())?;
+    vision::get_vision_service().lock().await.clear_ocr_cache().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn vision_check_dependencies() -> Result<(), String> {
     let vision_service = vision::get_vision_service();
@@ -1332,8 +2059,17 @@ async fn search_files(
 }
 
 #[tauri::command]
-async fn execute_safe_system_command(command: String) -> Result<String, String> {
-    utils::execute_safe_command(&command).await.map_err(|e| e.to_string())
+async fn execute_safe_system_command(
+    command: String,
+    state: State<'_, AppState>,
+) -> Result<String, serde_json::Value> {
+    let policy = state.config.read().await.command_policy.clone();
+    utils::execute_safe_command(&command, &policy)
+        .await
+        .map_err(|e| match e.downcast::<utils::CommandRejected>() {
+            Ok(rejected) => serde_json::to_value(rejected).unwrap_or_default(),
+            Err(e) => serde_json::Value::String(e.to_string()),
+        })
 }
 
 // AI System Diagnostic and Repair Commands
@@ -1463,8 +2199,18 @@ async fn ai_auto_fix(
 async fn execute_template_command(
     command: String,
     working_directory: Option<String>,
+    state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
     use tokio::process::Command;
+
+    // Templates are arbitrary shell scripts, so only the denylist applies
+    state
+        .config
+        .read()
+        .await
+        .command_policy
+        .check_denied(&command)
+        .map_err(|e| e.to_string())?;
     
     let mut cmd = if cfg!(target_os = "windows") {
         let mut c = Command::new("cmd");
@@ -1534,6 +2280,48 @@ async fn start_web_scraping(
     scraper.start_scraping(options).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn scrape_with_auth(
+    url: String,
+    login_steps: Vec<web_scraper::LoginStep>,
+    output_path: Option<String>,
+) -> Result<web_scraper::DownloadedFile, String> {
+    let scraper = {
+        let guard = web_scraper::get_web_scraper().lock().map_err(|e| e.to_string())?;
+        guard.clone()
+    };
+    scraper.scrape_with_auth(&url, &login_steps, output_path).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_scrape_result(
+    job_id: String,
+    format: String,
+    output_path: String,
+) -> Result<scrape_export::ExportSummary, String> {
+    let format: scrape_export::ExportFormat = format.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    let result = {
+        let scraper = web_scraper::get_web_scraper().lock().map_err(|e| e.to_string())?;
+        scraper.get_scraping_progress(&job_id).map_err(|e| e.to_string())?
+    };
+    scrape_export::export_scrape_result(&result, format, &output_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn scraper_import_cookies(path: String) -> Result<usize, String> {
+    let scraper = web_scraper::get_web_scraper().lock().map_err(|e| e.to_string())?;
+    scraper.import_cookie_file(&path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn scraper_clear_cookies() -> Result<(), String> {
+    let scraper = web_scraper::get_web_scraper().lock().map_err(|e| e.to_string())?;
+    scraper.clear_cookies();
+    Ok(())
+}
+
 #[tauri::command]
 async fn get_scraping_progress(job_id: String) -> Result<web_scraper::ScrapingResult, String> {
     let scraper = web_scraper::get_web_scraper().lock().map_err(|e| e.to_string())?;
@@ -1544,12 +2332,20 @@ async fn get_scraping_progress(job_id: String) -> Result<web_scraper::ScrapingRe
 async fn scrape_single_page(
     url: String,
     output_path: Option<String>,
+    render_js: Option<bool>,
+    wait_for_selector: Option<String>,
+    render_timeout_ms: Option<u64>,
 ) -> Result<web_scraper::DownloadedFile, String> {
     let scraper = {
         let guard = web_scraper::get_web_scraper().lock().map_err(|e| e.to_string())?;
         guard.clone()
     };
-    scraper.scrape_single_page(&url, output_path).await.map_err(|e| e.to_string())
+    let render = render_js.unwrap_or(false).then(|| headless_browser::RenderOptions {
+        wait_for_selector,
+        timeout_ms: render_timeout_ms.unwrap_or(headless_browser::DEFAULT_RENDER_TIMEOUT_MS),
+        proxy_server: None,
+    });
+    scraper.scrape_single_page(&url, output_path, render.as_ref()).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -1656,6 +2452,31 @@ async fn broadcast_command(
     manager.broadcast_command(&session_ids, &command).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn broadcast_command_with_summary(
+    session_ids: Vec<String>,
+    command: String,
+) -> Result<broadcast::BroadcastResult, String> {
+    let manager = broadcast::get_broadcast_manager();
+    manager.broadcast_command_with_summary(&session_ids, &command).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn broadcast_to_tags(
+    tags: Vec<String>,
+    command: String,
+    match_all: bool,
+) -> Result<broadcast::BroadcastResult, String> {
+    let manager = broadcast::get_broadcast_manager();
+    manager.broadcast_to_tags(&tags, &command, match_all).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_sessions_by_tag(tag: String) -> Result<Vec<broadcast::TerminalSession>, String> {
+    let manager = broadcast::get_broadcast_manager();
+    manager.get_sessions_by_tag(&tag).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn import_broadcast_sessions(
     sessions: Vec<broadcast::TerminalSession>,
@@ -1675,12 +2496,56 @@ async fn create_local_session(name: String) -> Result<broadcast::TerminalSession
     Ok(broadcast::BroadcastManager::create_local_session(name))
 }
 
+#[tauri::command]
+async fn create_ssh_session(
+    name: String,
+    host: String,
+    port: Option<u16>,
+    username: Option<String>,
+    key_path: Option<String>,
+) -> Result<broadcast::TerminalSession, String> {
+    Ok(broadcast::BroadcastManager::create_ssh_session(name, host, port, username, key_path))
+}
+
 #[tauri::command]
 async fn get_active_broadcasts() -> Result<Vec<String>, String> {
     let manager = broadcast::get_broadcast_manager();
     manager.get_active_broadcasts().await.map_err(|e| e.to_string())
 }
 
+// Full system scan commands
+#[tauri::command]
+async fn full_system_scan(
+    options: Option<system_scan::SystemScanOptions>,
+    state: State<'_, AppState>,
+) -> Result<system_scan::SystemScanReport, String> {
+    let cancel = tokio_util::sync::CancellationToken::new();
+    {
+        let mut current = state.system_scan_cancel.write().await;
+        if current.is_some() {
+            return Err("A system scan is already running".to_string());
+        }
+        *current = Some(cancel.clone());
+    }
+
+    let scanner = system_scan::SystemScanner::new(state.security_scanner.clone());
+    let report = scanner.full_system_scan(&options.unwrap_or_default(), &cancel).await;
+
+    *state.system_scan_cancel.write().await = None;
+    Ok(report)
+}
+
+#[tauri::command]
+async fn cancel_system_scan(state: State<'_, AppState>) -> Result<bool, String> {
+    match state.system_scan_cancel.read().await.as_ref() {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
 // Security Scanner commands
 #[tauri::command]
 async fn security_scan_directory(
@@ -1688,15 +2553,18 @@ async fn security_scan_directory(
     scan_type: String,
     state: State<'_, AppState>,
 ) -> Result<security_scanner::ScanResult, String> {
-    let security_scanner = state.security_scanner.read().await;
     let scan_type = match scan_type.as_str() {
         "vulnerabilities" => security_scanner::ScanType::Vulnerabilities,
         "malware" => security_scanner::ScanType::Malware,
         "secrets" => security_scanner::ScanType::Secrets,
         "dependencies" => security_scanner::ScanType::Dependencies,
+        "full" => security_scanner::ScanType::FullRescan,
         _ => security_scanner::ScanType::Comprehensive,
     };
-    security_scanner.scan_directory(&path, scan_type).await.map_err(|e| e.to_string())
+    // Scan under the read lock so other security commands aren't blocked by a long walk
+    let result = state.security_scanner.read().await.run_scan(&path, scan_type).await.map_err(|e| e.to_string())?;
+    state.security_scanner.write().await.store_scan_result(result.clone());
+    Ok(result)
 }
 
 #[tauri::command]
@@ -1713,6 +2581,49 @@ async fn security_scan_real_time(
     }
 }
 
+#[tauri::command]
+async fn security_create_baseline(
+    scan_id: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let mut security_scanner = state.security_scanner.write().await;
+    security_scanner.create_baseline(&scan_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_clear_baseline(
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut security_scanner = state.security_scanner.write().await;
+    security_scanner.clear_baseline().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_update_advisory_db(
+    state: State<'_, AppState>,
+) -> Result<advisory_db::AdvisoryDbInfo, String> {
+    let source = state.security_scanner.read().await.advisory_db_source().map_err(|e| e.to_string())?;
+    let database = advisory_db::AdvisoryDatabase::fetch(&source).await.map_err(|e| e.to_string())?;
+    let mut security_scanner = state.security_scanner.write().await;
+    security_scanner.install_advisory_db(database).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_get_advisory_db_info(
+    state: State<'_, AppState>,
+) -> Result<advisory_db::AdvisoryDbInfo, String> {
+    let security_scanner = state.security_scanner.read().await;
+    Ok(security_scanner.advisory_db_info())
+}
+
+#[tauri::command]
+async fn security_get_monitoring_status(
+    state: State<'_, AppState>,
+) -> Result<security_scanner::MonitoringStatus, String> {
+    let security_scanner = state.security_scanner.read().await;
+    Ok(security_scanner.monitoring_status())
+}
+
 #[tauri::command]
 async fn security_get_scan_results(
     scan_id: String,
@@ -1740,6 +2651,36 @@ async fn security_update_rules(
     security_scanner.update_rules(rules).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn security_load_rules_from_file(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<security_scanner::SecurityRule>, String> {
+    let mut security_scanner = state.security_scanner.write().await;
+    security_scanner
+        .load_rules_from_file(std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_export_sarif(
+    scan_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let security_scanner = state.security_scanner.read().await;
+    security_scanner.export_sarif(&scan_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_list_rules(
+    tag: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<security_scanner::SecurityRule>, String> {
+    let security_scanner = state.security_scanner.read().await;
+    Ok(security_scanner.rules(tag.as_deref()))
+}
+
 #[tauri::command]
 async fn security_get_vulnerabilities(
     severity: Option<String>,
@@ -1753,10 +2694,32 @@ async fn security_get_vulnerabilities(
 async fn security_remediate_vulnerability(
     vulnerability_id: String,
     auto_fix: bool,
+    dry_run: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<security_scanner::RemediationResult, String> {
     let mut security_scanner = state.security_scanner.write().await;
-    security_scanner.remediate_vulnerability(&vulnerability_id, auto_fix).await.map_err(|e| e.to_string())
+    security_scanner
+        .remediate_vulnerability(&vulnerability_id, auto_fix, dry_run.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_rollback_remediation(
+    remediation_id: String,
+    state: State<'_, AppState>,
+) -> Result<security_scanner::RemediationResult, String> {
+    let security_scanner = state.security_scanner.read().await;
+    security_scanner.rollback_remediation(&remediation_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn security_confirm_remediation(
+    remediation_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let security_scanner = state.security_scanner.read().await;
+    security_scanner.confirm_remediation(&remediation_id).await.map_err(|e| e.to_string())
 }
 
 // Command Flow Visualization commands
@@ -1779,6 +2742,16 @@ async fn command_flow_create_graph(
     command_flow_engine.create_dependency_graph(&commands).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn command_flow_export_dot(graph: command_flow::DependencyGraph) -> Result<String, String> {
+    Ok(graph.to_dot())
+}
+
+#[tauri::command]
+async fn command_flow_export_mermaid(graph: command_flow::DependencyGraph) -> Result<String, String> {
+    Ok(graph.to_mermaid())
+}
+
 #[tauri::command]
 async fn command_flow_get_dependencies(
     command: String,
@@ -1797,6 +2770,15 @@ async fn command_flow_visualize_execution(
     command_flow_engine.visualize_execution(&execution_id).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn command_flow_critical_path(
+    execution_id: String,
+    state: State<'_, AppState>,
+) -> Result<command_flow::CriticalPath, String> {
+    let command_flow_engine = state.command_flow_engine.read().await;
+    command_flow_engine.critical_path(&execution_id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn command_flow_track_execution(
     command: String,
@@ -1820,10 +2802,11 @@ async fn command_flow_get_execution_history(
 #[tauri::command]
 async fn plugin_install(
     plugin_path: String,
+    allow_unsigned: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<plugin_system::InstallResult, String> {
     let mut plugin_system = state.plugin_system.write().await;
-    plugin_system.install_plugin(&plugin_path).await.map_err(|e| e.to_string())
+    plugin_system.install_plugin(&plugin_path, allow_unsigned.unwrap_or(false)).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -1889,6 +2872,58 @@ async fn plugin_get_info(
     plugin_system.get_plugin_info(&plugin_id).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn plugin_add_trusted_key(
+    name: String,
+    pubkey: String,
+    state: State<'_, AppState>,
+) -> Result<plugin_system::TrustedKey, String> {
+    let mut plugin_system = state.plugin_system.write().await;
+    let key = plugin_system.add_trusted_key(&name, &pubkey).map_err(|e| e.to_string())?;
+
+    let mut config = state.config.write().await;
+    config.plugins.trusted_keys = plugin_system.trusted_keys().to_vec();
+    config.save().map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+#[tauri::command]
+async fn plugin_list_trusted_keys(
+    state: State<'_, AppState>,
+) -> Result<Vec<plugin_system::TrustedKey>, String> {
+    let plugin_system = state.plugin_system.read().await;
+    Ok(plugin_system.trusted_keys().to_vec())
+}
+
+#[tauri::command]
+async fn plugin_get_permissions(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<plugin_system::PluginPermissions, String> {
+    let plugin_system = state.plugin_system.read().await;
+    plugin_system.get_permissions(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn plugin_grant_capability(
+    id: String,
+    cap: plugin_system::Capability,
+    state: State<'_, AppState>,
+) -> Result<plugin_system::PluginPermissions, String> {
+    let mut plugin_system = state.plugin_system.write().await;
+    plugin_system.grant_capability(&id, cap).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn plugin_revoke_capability(
+    id: String,
+    cap: plugin_system::Capability,
+    state: State<'_, AppState>,
+) -> Result<plugin_system::PluginPermissions, String> {
+    let mut plugin_system = state.plugin_system.write().await;
+    plugin_system.revoke_capability(&id, &cap).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn plugin_update(
     plugin_id: String,
@@ -1906,56 +2941,204 @@ async fn collaboration_create_session(
     state: State<'_, AppState>,
 ) -> Result<collaboration::CollaborationSession, String> {
     let collaboration_manager = state.collaboration_manager.write().await;
-    collaboration_manager.create_session(&name, permissions).await.map_err(|e| e.to_string())
+    collaboration_manager.create_session(&name, permissions).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_join_session(
+    session_id: String,
+    user_id: String,
+    state: State<'_, AppState>,
+) -> Result<collaboration::JoinResult, String> {
+    let collaboration_manager = state.collaboration_manager.write().await;
+    collaboration_manager.join_session(&session_id, &user_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_leave_session(
+    session_id: String,
+    user_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let collaboration_manager = state.collaboration_manager.write().await;
+    collaboration_manager.leave_session(&session_id, &user_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_share_terminal(
+    terminal_id: String,
+    session_id: String,
+    permissions: collaboration::TerminalPermissions,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let collaboration_manager = state.collaboration_manager.write().await;
+    collaboration_manager.share_terminal(&terminal_id, &session_id, permissions).await.map_err(|e| e.to_string())
+}
+
+/// Order a participant's keystrokes into a shared terminal and write the committed ops to it
+#[tauri::command]
+async fn collaboration_submit_terminal_op(
+    terminal_id: String,
+    user_id: String,
+    client_seq: u64,
+    data: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<collaboration::TerminalOp>, String> {
+    // Holding the write lock keeps ops reaching the terminal in log order
+    let collaboration_manager = state.collaboration_manager.write().await;
+    let ops = collaboration_manager
+        .submit_terminal_op(&terminal_id, &user_id, client_seq, data)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let terminal_manager = state.terminal_manager.read().await;
+    for op in &ops {
+        terminal_manager
+            .write_to_terminal(&terminal_id, &op.data)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(ops)
+}
+
+#[tauri::command]
+async fn collaboration_get_terminal_ops(
+    terminal_id: String,
+    user_id: String,
+    after_seq: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<collaboration::TerminalOp>, String> {
+    let collaboration_manager = state.collaboration_manager.read().await;
+    collaboration_manager
+        .terminal_ops_since(&terminal_id, &user_id, after_seq.unwrap_or(0))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_update_presence(
+    session_id: String,
+    user_id: String,
+    presence: collaboration::PresenceUpdate,
+    state: State<'_, AppState>,
+) -> Result<collaboration::Participant, String> {
+    let collaboration_manager = state.collaboration_manager.read().await;
+    collaboration_manager.update_presence(&session_id, &user_id, presence).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_get_participants(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<collaboration::Participant>, String> {
+    let collaboration_manager = state.collaboration_manager.read().await;
+    collaboration_manager.get_participants(&session_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_get_sessions(
+    state: State<'_, AppState>,
+) -> Result<Vec<collaboration::CollaborationSession>, String> {
+    let collaboration_manager = state.collaboration_manager.read().await;
+    collaboration_manager.get_sessions().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn collaboration_send_message(
+    session_id: String,
+    message: collaboration::ChatMessage,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let collaboration_manager = state.collaboration_manager.write().await;
+    collaboration_manager.send_message(&session_id, message).await.map_err(|e| e.to_string())
 }
 
+/// Start relaying local sessions over WebSocket, returning the server's URL
 #[tauri::command]
-async fn collaboration_join_session(
-    session_id: String,
-    user_id: String,
+async fn collaboration_start_server(
+    bind_addr: String,
     state: State<'_, AppState>,
-) -> Result<collaboration::JoinResult, String> {
-    let collaboration_manager = state.collaboration_manager.write().await;
-    collaboration_manager.join_session(&session_id, &user_id).await.map_err(|e| e.to_string())
+) -> Result<String, String> {
+    let mut server = state.collaboration_server.write().await;
+    if let Some(running) = server.as_ref() {
+        return Ok(running.url());
+    }
+    let manager = state.collaboration_manager.read().await.clone();
+    let started = collaboration_transport::CollaborationServer::start(manager, &bind_addr)
+        .await
+        .map_err(|e| e.to_string())?;
+    let url = started.url();
+    *server = Some(started);
+    Ok(url)
 }
 
 #[tauri::command]
-async fn collaboration_leave_session(
-    session_id: String,
-    user_id: String,
-    state: State<'_, AppState>,
-) -> Result<(), String> {
-    let collaboration_manager = state.collaboration_manager.write().await;
-    collaboration_manager.leave_session(&session_id, &user_id).await.map_err(|e| e.to_string())
+async fn collaboration_stop_server(state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(server) = state.collaboration_server.write().await.take() {
+        server.stop();
+    }
+    Ok(())
 }
 
+/// Join a session hosted by a remote collaboration server; its events are emitted as
+/// "collaboration-remote-event"
 #[tauri::command]
-async fn collaboration_share_terminal(
-    terminal_id: String,
+async fn collaboration_connect(
+    url: String,
     session_id: String,
-    permissions: collaboration::TerminalPermissions,
+    user_id: String,
+    token: String,
+    app: tauri::AppHandle,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let collaboration_manager = state.collaboration_manager.write().await;
-    collaboration_manager.share_terminal(&terminal_id, &session_id, permissions).await.map_err(|e| e.to_string())
+    let (client, mut events) = collaboration_transport::CollaborationClient::connect(&url, &session_id, &user_id, &token)
+        .await
+        .map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn(async move {
+        use tauri::Emitter;
+        while let Some(event) = events.recv().await {
+            if let Err(e) = app.emit("collaboration-remote-event", &event) {
+                eprintln!("Warning: Failed to emit remote collaboration event: {}", e);
+            }
+        }
+    });
+
+    let mut clients = state.collaboration_clients.write().await;
+    if let Some(previous) = clients.insert(session_id, client) {
+        previous.disconnect();
+    }
+    Ok(())
 }
 
 #[tauri::command]
-async fn collaboration_get_sessions(
+async fn collaboration_disconnect(
+    session_id: String,
+    leave: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<Vec<collaboration::CollaborationSession>, String> {
-    let collaboration_manager = state.collaboration_manager.read().await;
-    collaboration_manager.get_sessions().await.map_err(|e| e.to_string())
+) -> Result<(), String> {
+    let mut clients = state.collaboration_clients.write().await;
+    let client = clients
+        .remove(&session_id)
+        .ok_or_else(|| format!("Not connected to session {}", session_id))?;
+    if leave.unwrap_or(true) {
+        client.leave().map_err(|e| e.to_string())?;
+    } else {
+        client.disconnect();
+    }
+    Ok(())
 }
 
 #[tauri::command]
-async fn collaboration_send_message(
+async fn collaboration_send_remote_message(
     session_id: String,
-    message: collaboration::ChatMessage,
+    content: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let collaboration_manager = state.collaboration_manager.write().await;
-    collaboration_manager.send_message(&session_id, message).await.map_err(|e| e.to_string())
+    let clients = state.collaboration_clients.read().await;
+    let client = clients
+        .get(&session_id)
+        .ok_or_else(|| format!("Not connected to session {}", session_id))?;
+    client.send_chat(&content).map_err(|e| e.to_string())
 }
 
 // Workflow Automation commands
@@ -1994,7 +3177,76 @@ async fn workflow_delete(
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let mut workflow_engine = state.workflow_engine.write().await;
-    workflow_engine.delete_workflow_by_id(&workflow_id).await.map_err(|e| e.to_string())
+    workflow_engine.delete_workflow_by_id(&workflow_id).await.map_err(|e| e.to_string())?;
+    state.workflow_triggers.write().await.sync(&workflow_engine).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workflow_add_trigger(
+    workflow_id: String,
+    trigger: workflow_automation::WorkflowTrigger,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut workflow_engine = state.workflow_engine.write().await;
+    workflow_engine.add_trigger(&workflow_id, trigger).map_err(|e| e.to_string())?;
+    state.workflow_triggers.write().await.sync(&workflow_engine).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn workflow_set_trigger_enabled(
+    workflow_id: String,
+    trigger_id: String,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut workflow_engine = state.workflow_engine.write().await;
+    workflow_engine.set_trigger_enabled(&workflow_id, &trigger_id, enabled).map_err(|e| e.to_string())?;
+    state.workflow_triggers.write().await.sync(&workflow_engine).map_err(|e| e.to_string())
+}
+
+/// Start the webhook listener (localhost by default) and return the URL of every webhook trigger
+#[tauri::command]
+async fn workflow_start_webhook_server(
+    addr: Option<String>,
+    secret: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<workflow_webhooks::WebhookServerInfo, String> {
+    let mut server = state.webhook_server.write().await;
+    if server.is_none() {
+        let bind_addr = addr.unwrap_or_else(|| workflow_webhooks::DEFAULT_WEBHOOK_ADDR.to_string());
+        let started = workflow_webhooks::WebhookServer::start(state.workflow_engine.clone(), &bind_addr, secret)
+            .await
+            .map_err(|e| e.to_string())?;
+        *server = Some(started);
+    }
+    let running = server.as_ref().expect("webhook server was just started");
+    let workflow_engine = state.workflow_engine.read().await;
+    Ok(running.info(&workflow_engine))
+}
+
+#[tauri::command]
+async fn workflow_stop_webhook_server(state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(server) = state.webhook_server.write().await.take() {
+        server.stop();
+    }
+    Ok(())
+}
+
+/// Stop a running execution after its current node, killing that node's process
+#[tauri::command]
+async fn workflow_cancel_execution(execution_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    if state.workflow_canceller.cancel(&execution_id) {
+        Ok(())
+    } else {
+        Err(format!("No running execution {}", execution_id))
+    }
+}
+
+#[tauri::command]
+async fn workflow_running_executions(
+    state: State<'_, AppState>,
+) -> Result<Vec<workflow_automation::RunningExecution>, String> {
+    Ok(state.workflow_canceller.running())
 }
 
 #[tauri::command]
@@ -2072,6 +3324,141 @@ async fn analytics_get_command_patterns(
     analytics_engine.get_command_patterns().await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn analytics_get_action_plan(
+    use_ai: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<analytics::ActionPlan, String> {
+    let mut plan = state.analytics_engine.read().await.get_action_plan();
+
+    // Rule-based ranking stands on its own; the AI only fills in concrete steps
+    if use_ai.unwrap_or(true) && !plan.items.is_empty() {
+        let ai_service = state.ai_service.read().await;
+        match ai_service.chat(&plan.enrichment_prompt(), None).await {
+            Ok(response) => {
+                if !plan.apply_ai_steps(&response) {
+                    eprintln!("Warning: AI action plan response was not usable, keeping rule-based steps");
+                }
+            }
+            Err(e) => eprintln!("Warning: AI unavailable for action plan, keeping rule-based steps: {}", e),
+        }
+    }
+
+    Ok(plan)
+}
+
+#[tauri::command]
+async fn analytics_get_percentile(
+    name: String,
+    percentile: f64,
+    time_range: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<analytics::PercentileEstimate>, String> {
+    if !(0.0..=100.0).contains(&percentile) {
+        return Err(format!("Percentile must be between 0 and 100, got {}", percentile));
+    }
+    let range = time_range
+        .map(|range| analytics::parse_time_range(&range))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    Ok(state.analytics_engine.read().await.get_percentile(&name, percentile, range))
+}
+
+#[tauri::command]
+async fn ai_get_token_usage(
+    time_range: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<analytics::TokenUsageSummary, String> {
+    let range = time_range
+        .map(|range| analytics::parse_time_range(&range))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    Ok(state.analytics_engine.read().await.get_token_usage(range))
+}
+
+#[tauri::command]
+async fn analytics_define_metric(
+    name: String,
+    metric_type: analytics::MetricType,
+    aggregation: analytics::AggregationType,
+    retention_days: u32,
+    state: State<'_, AppState>,
+) -> Result<analytics::MetricDefinition, String> {
+    let mut analytics_engine = state.analytics_engine.write().await;
+    analytics_engine
+        .define_metric(&name, metric_type, aggregation, retention_days)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn analytics_record_custom(
+    name: String,
+    value: f64,
+    tags: Option<HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut analytics_engine = state.analytics_engine.write().await;
+    analytics_engine
+        .record_custom(&name, value, tags.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn analytics_list_metrics(state: State<'_, AppState>) -> Result<Vec<analytics::MetricInfo>, String> {
+    Ok(state.analytics_engine.read().await.list_metrics())
+}
+
+#[tauri::command]
+async fn analytics_generate_report(
+    report_type: analytics::ReportType,
+    time_range: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<analytics::AnalyticsReport, String> {
+    let range = analytics::parse_time_range(time_range.as_deref().unwrap_or("day")).map_err(|e| e.to_string())?;
+    let mut analytics_engine = state.analytics_engine.write().await;
+    analytics_engine.generate_report(report_type, range).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn analytics_export_report(
+    report_id: String,
+    format: analytics::ReportFormat,
+    state: State<'_, AppState>,
+) -> Result<analytics::ExportedReport, String> {
+    let html = state.analytics_engine.read().await.export_report_html(&report_id).map_err(|e| e.to_string())?;
+    analytics::export_report(&report_id, html, format).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn analytics_export_prometheus(state: State<'_, AppState>) -> Result<String, String> {
+    Ok(state.analytics_engine.read().await.export_prometheus())
+}
+
+#[tauri::command]
+async fn analytics_get_cache_metrics(
+    state: State<'_, AppState>,
+) -> Result<HashMap<String, cache::CacheMetrics>, String> {
+    let mut metrics = HashMap::new();
+    metrics.insert(
+        "ai_responses".to_string(),
+        state.optimized_ai_service.read().await.cache_metrics().await,
+    );
+    metrics.insert(
+        "ai_chat".to_string(),
+        state.ai_service.read().await.cache_metrics(),
+    );
+    metrics.insert(
+        "ocr".to_string(),
+        vision::get_vision_service().lock().await.ocr_cache_metrics(),
+    );
+
+    let mut analytics_engine = state.analytics_engine.write().await;
+    for (name, cache_metrics) in &metrics {
+        analytics_engine.record_cache_metrics(name, cache_metrics);
+    }
+    Ok(metrics)
+}
+
 #[tauri::command]
 async fn analytics_get_optimization_suggestions(
     state: State<'_, AppState>,
@@ -2089,12 +3476,31 @@ async fn ecosystem_get_comprehensive_context(
     ecosystem_awareness.get_comprehensive_context().await.map_err(|e| e.to_string())
 }
 
+/// The comprehensive ecosystem context with secrets in the environment masked
+#[tauri::command]
+async fn get_ecosystem_context(
+    state: State<'_, AppState>,
+) -> Result<ecosystem_awareness::ComprehensiveContext, String> {
+    let mut context = state
+        .ecosystem_awareness
+        .read()
+        .await
+        .get_comprehensive_context()
+        .await
+        .map_err(|e| e.to_string())?;
+    let redactor = state.ai_service.read().await.redactor.clone();
+    let environment = &mut context.ecosystem_state.environment;
+    environment.environment_variables = redactor.redact_env(std::mem::take(&mut environment.environment_variables));
+    Ok(context)
+}
+
 #[tauri::command]
 async fn ecosystem_learn_from_interaction(
     command: String,
     success: bool,
     execution_time: u64,
     user_context: String,
+    error_output: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let ecosystem_awareness = state.ecosystem_awareness.read().await;
@@ -2104,6 +3510,7 @@ async fn ecosystem_learn_from_interaction(
         execution_time,
         user_context,
         timestamp: chrono::Utc::now(),
+        error_output,
     };
     ecosystem_awareness.learn_from_interaction(interaction).await.map_err(|e| e.to_string())
 }
@@ -2181,6 +3588,33 @@ async fn ecosystem_analyze_system_patterns(
     ecosystem_awareness.analyze_system_patterns().await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn ecosystem_suggest_recovery(
+    failed_command: String,
+    error_output: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ecosystem_awareness::RecoverySuggestion>, String> {
+    let ecosystem_awareness = state.ecosystem_awareness.read().await;
+    ecosystem_awareness.suggest_recovery(&failed_command, &error_output).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ecosystem_get_command_patterns(
+    state: State<'_, AppState>,
+) -> Result<Vec<ecosystem_awareness::LearningPattern>, String> {
+    let ecosystem_awareness = state.ecosystem_awareness.read().await;
+    Ok(ecosystem_awareness.command_patterns().await)
+}
+
+#[tauri::command]
+async fn ecosystem_export_learning_data(
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let ecosystem_awareness = state.ecosystem_awareness.read().await;
+    ecosystem_awareness.export_learning_data(std::path::Path::new(&path)).await.map_err(|e| e.to_string())
+}
+
 // Cloud Integration commands
 #[tauri::command]
 async fn cloud_backup_config(
@@ -2188,8 +3622,10 @@ async fn cloud_backup_config(
     config: cloud_integration::BackupConfig,
     state: State<'_, AppState>,
 ) -> Result<cloud_integration::BackupResult, String> {
-    let mut cloud_manager = state.cloud_manager.write().await;
-    cloud_manager.backup_configuration(&provider, config).await.map_err(|e| e.to_string())
+    let job = state.cloud_manager.read().await.prepare_backup(&provider, config).map_err(|e| e.to_string())?;
+    let result = job.run().await;
+    state.cloud_manager.write().await.record_backup(&job, &result);
+    Ok(result)
 }
 
 #[tauri::command]
@@ -2247,6 +3683,73 @@ async fn cloud_get_providers(
     cloud_manager.get_available_providers().await.map_err(|e| e.to_string())
 }
 
+/// Settle the sync conflicts of one data type, or of one file in it
+#[tauri::command]
+async fn cloud_resolve_conflict(
+    provider: String,
+    data_type: String,
+    resolution: cloud_integration::ConflictResolution,
+    file_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let mut cloud_manager = state.cloud_manager.write().await;
+    cloud_manager
+        .resolve_conflict(&provider, &data_type, file_path.as_deref(), resolution)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Set or, with no schedule, clear a provider's automatic backups
+#[tauri::command]
+async fn cloud_set_backup_schedule(
+    provider: String,
+    schedule: Option<cloud_integration::ScheduledBackup>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut cloud_manager = state.cloud_manager.write().await;
+    cloud_manager
+        .set_backup_schedule(&provider, schedule, chrono::Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cloud_get_backup_schedule(
+    provider: String,
+    state: State<'_, AppState>,
+) -> Result<Option<cloud_integration::BackupScheduleStatus>, String> {
+    let cloud_manager = state.cloud_manager.read().await;
+    Ok(cloud_manager.get_backup_schedule(&provider))
+}
+
+// Secret store commands
+#[tauri::command]
+async fn set_secret(
+    key: String,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    state.secret_store.store(&key, &value).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn has_secret(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    state.secret_store
+        .get_secret(&key)
+        .map(|secret| secret.is_some())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_secret(
+    key: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.secret_store.delete_secret(&key).map_err(|e| e.to_string())
+}
+
 // LocalRecall RAG commands
 #[tauri::command]
 async fn local_recall_health_check() -> Result<(), String> {
@@ -2417,6 +3920,7 @@ async fn ai_chat_with_memory(
     ai_service
         .chat_with_memory(&message, &conversation_id, context.as_deref())
         .await
+        .map(|reply| reply.content)
         .map_err(|e| e.to_string())
 }
 
@@ -2463,6 +3967,33 @@ async fn ollama_ensure_configured() -> Result<(), String> {
 
 
 
+/// Persist the terminal layout when the window closes, if session restore is enabled
+fn save_layout_on_close(window: &tauri::Window) {
+    use tauri::Manager;
+
+    let state = window.state::<AppState>();
+    let restore_enabled = state.config
+        .try_read()
+        .map(|config| config.terminal.restore_sessions_on_launch)
+        .unwrap_or(false);
+    if !restore_enabled {
+        return;
+    }
+
+    let Ok(terminal_manager) = state.terminal_manager.try_read() else {
+        eprintln!("Warning: Terminal manager busy, session layout not saved");
+        return;
+    };
+    match terminal_manager.snapshot_layout(&HashMap::new()) {
+        Ok(layout) => {
+            if let Err(e) = layout.save() {
+                eprintln!("Warning: Failed to save session layout: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Warning: Failed to snapshot session layout: {}", e),
+    }
+}
+
 #[tokio::main]
 async fn main() {
     // Load .env file first for environment configuration
@@ -2486,7 +4017,7 @@ async fn main() {
     }
 
     // Initialize application state
-    let config = AppConfig::load().unwrap_or_else(|e| {
+    let mut config = AppConfig::load().unwrap_or_else(|e| {
         eprintln!("Warning: Failed to load config, using defaults: {}", e);
         AppConfig::default()
     });
@@ -2495,7 +4026,46 @@ async fn main() {
     if let Err(e) = config.ensure_directories() {
         eprintln!("Warning: Failed to create directories: {}", e);
     }
-    let terminal_manager = TerminalManager::new();
+    let secret_store = match secret_store::SecretStore::new(&config.paths.data_dir) {
+        Ok(store) => Arc::new(store),
+        Err(e) => {
+            eprintln!("Fatal: Could not initialize secret store: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let active_profile = config::ProfileStore::open()
+        .map(|profiles| profiles.active_profile())
+        .unwrap_or_else(|_| config::DEFAULT_PROFILE.to_string());
+    match config.migrate_secrets(&secret_store, &active_profile) {
+        Ok(true) => {
+            if let Err(e) = config.save() {
+                eprintln!("Warning: Failed to save config after moving the AI API key: {}", e);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("Warning: Failed to move the AI API key into the secret store: {}", e),
+    }
+    let history_dir = AppConfig::command_history_dir().unwrap_or_else(|_| config.paths.data_dir.join("history"));
+    let new_history = || {
+        let history = command_history::CommandHistoryStore::new(&history_dir)
+            .with_max_entries(config.terminal.history_size);
+        if config.terminal.redact_history {
+            history.with_secret_redaction()
+        } else {
+            history
+        }
+    };
+    let history = new_history().open().unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to load command history: {}", e);
+        new_history()
+    });
+    let command_history = Arc::new(std::sync::Mutex::new(history));
+    // Nothing is watched until the frontend asks; the launch directory may be $HOME or /
+    let file_watch_manager = file_watcher::FileWatchManager::new();
+    let terminal_manager = TerminalManager::with_scrollback_lines(config.terminal.scroll_back as usize)
+        .with_command_history(command_history.clone())
+        .with_output_batching(config.terminal.output.clone());
+    let restore_sessions = config.terminal.restore_sessions_on_launch;
     let ai_service = match AIService::new(&config.ai).await {
         Ok(service) => {
             println!("✅ AI service initialized successfully");
@@ -2506,7 +4076,17 @@ async fn main() {
             eprintln!("🔧 Attempting fallback AI service...");
             AIService::default()
         }
-    };
+    }
+    .with_secret_store(secret_store.clone());
+    let quality_tracker = ai_quality::QualityTracker::open(config.paths.data_dir.join("ai_quality.jsonl")).unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to load AI quality log: {}", e);
+        ai_quality::QualityTracker::new()
+    })
+    .with_redactor(ai_service.redactor.clone());
+    let ai_stats_path = config.paths.data_dir.join("ai_stats.json");
+    if let Err(e) = ai_service.load_stats(&ai_stats_path) {
+        eprintln!("Warning: Could not load AI service stats: {}", e);
+    }
     
     let optimized_ai_service = match OptimizedAIService::new(&config.ai).await {
         Ok(service) => service,
@@ -2522,6 +4102,11 @@ async fn main() {
             }
         }
     };
+    // Requests through the queue are counted alongside direct ones
+    let mut optimized_ai_service = optimized_ai_service.with_request_stats(ai_service.stats.clone());
+    if let Err(e) = optimized_ai_service.start_background_tasks().await {
+        eprintln!("Warning: Failed to start AI request processor: {}", e);
+    }
     
     let mut vision_service = VisionService::new();
     if let Err(e) = vision_service.initialize().await {
@@ -2529,16 +4114,38 @@ async fn main() {
     }
 
     // Initialize Phase 4 services
-    let security_scanner = security_scanner::SecurityScanner::new(security_scanner::SecurityConfig::default());
+    let security_scanner = security_scanner::SecurityScanner::new(security_scanner::SecurityConfig::default())
+        .with_manifest_dir(config.paths.data_dir.join("security-scans"))
+        .with_advisory_db(config.paths.data_dir.join("advisories.json"));
+    let mut security_findings = security_scanner.subscribe_findings();
     let command_flow_engine = command_flow::CommandFlowEngine::new();
-    let plugin_system = plugin_system::PluginSystem::new(config.paths.data_dir.join("plugins"));
+    let mut plugin_system = match plugin_system::PluginSystem::new(config.paths.data_dir.join("plugins")) {
+        Ok(plugin_system) => plugin_system,
+        Err(e) => {
+            eprintln!("Fatal: Could not initialize plugin system: {}", e);
+            std::process::exit(1);
+        }
+    };
+    plugin_system.set_trusted_keys(config.plugins.trusted_keys.clone());
     let collaboration_manager = collaboration::CollaborationManager::new();
+    let mut collaboration_events = collaboration_manager.subscribe_to_events();
+    let presence_manager = collaboration_manager.clone();
     let workflow_engine = workflow_automation::WorkflowEngine::new();
-    let analytics_engine = analytics::AnalyticsEngine::new();
-    let cloud_manager = cloud_integration::CloudIntegrationManager::new();
+    let mut analytics_engine = analytics::AnalyticsEngine::new();
+    if let Err(e) = analytics_engine.load_metric_definitions(config.paths.data_dir.join("custom_metrics.json")) {
+        eprintln!("Warning: Could not load custom metric definitions: {}", e);
+    }
+    let mut cloud_manager = cloud_integration::CloudIntegrationManager::new()
+        .with_secret_store(secret_store.clone())
+        .with_sync_root("plugins", config.paths.data_dir.join("plugins"));
+    if let Ok(config_dir) = AppConfig::config_dir() {
+        cloud_manager = cloud_manager
+            .with_sync_root("history", config_dir.join("history"))
+            .with_sync_state_dir(config_dir.join("sync"));
+    }
     
     // Initialize Ecosystem Awareness with Adaptive Learning
-    let ecosystem_awareness = match ecosystem_awareness::EcosystemAwareness::new().await {
+    let mut ecosystem_awareness = match ecosystem_awareness::EcosystemAwareness::new().await {
         Ok(awareness) => awareness,
         Err(e) => {
             eprintln!("Warning: Failed to initialize ecosystem awareness: {}", e);
@@ -2546,6 +4153,23 @@ async fn main() {
             ecosystem_awareness::EcosystemAwareness::default()
         }
     };
+    if let Err(e) = ecosystem_awareness.attach_learning_store(&config.paths.data_dir.join("learning.redb")).await {
+        eprintln!("Warning: Failed to open learning database: {}", e);
+    }
+    ecosystem_awareness.start_monitoring();
+    ecosystem_awareness.start_learning_batcher(ecosystem_awareness::LearningBatchConfig::default());
+
+    let vector_store = match vector_store::VectorStore::open(&config.paths.data_dir.join("vectors.redb")) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Warning: Failed to open vector store, semantic search will not persist: {}", e);
+            vector_store::VectorStore::in_memory().expect("in-memory vector store")
+        }
+    };
+
+    let workflow_canceller = workflow_engine.canceller();
+    let workflow_engine = Arc::new(RwLock::new(workflow_engine));
+    let workflow_triggers = workflow_triggers::FileTriggerManager::new(workflow_engine.clone());
 
     let app_state = AppState {
         terminal_manager: Arc::new(RwLock::new(terminal_manager)),
@@ -2557,19 +4181,146 @@ async fn main() {
         command_flow_engine: Arc::new(RwLock::new(command_flow_engine)),
         plugin_system: Arc::new(RwLock::new(plugin_system)),
         collaboration_manager: Arc::new(RwLock::new(collaboration_manager)),
-        workflow_engine: Arc::new(RwLock::new(workflow_engine)),
+        collaboration_server: Arc::new(RwLock::new(None)),
+        collaboration_clients: Arc::new(RwLock::new(HashMap::new())),
+        workflow_engine,
+        workflow_canceller,
+        workflow_triggers: Arc::new(RwLock::new(workflow_triggers)),
+        webhook_server: Arc::new(RwLock::new(None)),
         analytics_engine: Arc::new(RwLock::new(analytics_engine)),
         cloud_manager: Arc::new(RwLock::new(cloud_manager)),
         ecosystem_awareness: Arc::new(RwLock::new(ecosystem_awareness)),
+        quality_tracker: Arc::new(RwLock::new(quality_tracker)),
+        code_suggestions: Arc::new(RwLock::new(code_suggestions::SuggestionManager::default())),
+        secret_store,
+        command_history,
+        file_watcher: Arc::new(RwLock::new(file_watch_manager)),
+        system_scan_cancel: Arc::new(RwLock::new(None)),
+        vector_store: Arc::new(RwLock::new(vector_store)),
     };
 
+    let backup_scheduler = app_state.cloud_manager.clone();
+    let ai_stats = app_state.ai_service.clone();
+    let restore_terminals = restore_sessions.then(|| app_state.terminal_manager.clone());
+
     tauri::Builder::default()
         .manage(app_state)
         .setup(|app| {
             // Initialize terminal app handle for event emission
             terminal::init_app_handle(app.handle().clone());
+
+            // Restored shells emit output as soon as they start, so they wait for the app handle
+            if let Some(terminal_manager) = restore_terminals {
+                tauri::async_runtime::spawn(async move {
+                    match terminal::SessionLayout::load() {
+                        Ok(Some(layout)) => {
+                            if let Err(e) = terminal_manager.write().await.restore_layout(&layout, true).await {
+                                eprintln!("Warning: Failed to restore terminal sessions: {}", e);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => eprintln!("Warning: Failed to load saved session layout: {}", e),
+                    }
+                });
+            }
+
+            // Forward ordered shared-terminal input so every client applies the same log
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match collaboration_events.recv().await {
+                        Ok(event) => {
+                            let result = match event.event_type {
+                                collaboration::CollaborationEventType::TerminalOp => {
+                                    app_handle.emit("terminal-op", &event.data)
+                                }
+                                collaboration::CollaborationEventType::UserJoined => {
+                                    app_handle.emit("participant-joined", &event)
+                                }
+                                collaboration::CollaborationEventType::UserLeft => {
+                                    app_handle.emit("participant-left", &event)
+                                }
+                                _ => Ok(()),
+                            };
+                            if let Err(e) = result {
+                                eprintln!("Warning: Failed to emit collaboration event: {}", e);
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            eprintln!("Warning: Dropped {} collaboration events; clients can replay from the op log", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Forward findings from real-time security monitoring
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                use tauri::Emitter;
+                use tokio::sync::broadcast::error::RecvError;
+                loop {
+                    match security_findings.recv().await {
+                        Ok(finding) => {
+                            if let Err(e) = app_handle.emit("security-finding", &finding) {
+                                eprintln!("Warning: Failed to emit security finding: {}", e);
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            eprintln!("Warning: Dropped {} security findings; run a scan to list them all", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+
+            // Snapshot AI stats so a restart continues from them
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+                loop {
+                    interval.tick().await;
+                    let stats = ai_stats.read().await.stats.clone();
+                    let snapshot = stats.lock().map(|stats| stats.clone());
+                    if let Ok(snapshot) = snapshot {
+                        if let Err(e) = snapshot.save(&ai_stats_path) {
+                            eprintln!("Warning: Failed to save AI service stats: {}", e);
+                        }
+                    }
+                }
+            });
+
+            // Expire participants who stopped sending presence heartbeats
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = presence_manager.prune_stale_participants(chrono::Utc::now()).await {
+                        eprintln!("Warning: Failed to prune stale participants: {}", e);
+                    }
+                }
+            });
+
+            // Run scheduled cloud backups and surface providers that keep failing
+            let app_handle = app.handle().clone();
+            tauri::async_runtime::spawn(cloud_integration::run_backup_scheduler(
+                backup_scheduler,
+                std::time::Duration::from_secs(30),
+                move |alert| {
+                    use tauri::Emitter;
+                    if let Err(e) = app_handle.emit("cloud-backup-alert", &alert) {
+                        eprintln!("Warning: Failed to emit backup alert: {}", e);
+                    }
+                },
+            ));
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { .. } = event {
+                save_layout_on_close(window);
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // AI commands
             ai_chat,
@@ -2578,6 +4329,9 @@ async fn main() {
             ai_generate_code,
             ai_analyze_repository,
             ai_suggest_improvements,
+            apply_code_suggestion,
+            undo_last_suggestion,
+            list_code_suggestions,
             ai_explain_concept,
             check_ai_connection,
             get_current_model,
@@ -2585,6 +4339,10 @@ async fn main() {
             get_available_models,
             send_ai_message,
             get_terminal_context,
+            // AI quality signals
+            ai_record_quality_signal,
+            ai_get_recent_interactions,
+            ai_get_quality_stats,
             // AI System Diagnostic and Repair
             ai_diagnose_system,
             ai_fix_compilation,
@@ -2617,11 +4375,21 @@ async fn main() {
             create_simple_terminal,
             write_to_terminal,
             resize_terminal,
+            send_terminal_signal,
             kill_terminal,
             close_terminal,
             get_terminal_info,
             list_terminals,
             get_terminal_count,
+            get_terminal_scrollback,
+            export_terminal_scrollback,
+            terminal_start_recording,
+            terminal_stop_recording,
+            terminal_pause_recording,
+            terminal_resume_recording,
+            set_terminal_position,
+            save_session_layout,
+            restore_session_layout,
             // Git commands
             git_status,
             git_generate_commit,
@@ -2633,22 +4401,57 @@ async fn main() {
             git_get_branch_info,
             git_get_all_branches,
             git_get_stash_list,
+            git_create_stash,
+            git_apply_stash,
+            git_pop_stash,
+            git_drop_stash,
             git_get_commit_changes,
             git_get_repository_stats,
+            git_get_diff_stat,
+            git_get_conflicts,
+            git_resolve_conflict,
+            git_get_unstaged_hunks,
+            git_get_staged_hunks,
+            git_stage_hunk,
+            git_unstage_hunk,
+            git_list_tags,
+            git_create_tag,
+            git_delete_tag,
+            git_list_worktrees,
+            git_add_worktree,
+            git_remove_worktree,
+            git_get_submodules,
+            git_update_submodules,
             // Advanced Git Integration commands
             git_generate_visual_graph,
+            git_generate_visual_graph_paged,
             git_generate_time_travel,
             git_generate_statistics,
             git_generate_visualization,
             git_get_advanced_branch_info,
             git_time_travel_to_commit,
             git_create_branch_from_commit,
+            git_generate_rebase_plan,
+            git_apply_rebase_plan,
+            git_abort_rebase,
+            git_get_commit_signatures,
             // Contextual suggestions commands
             get_contextual_suggestions,
             get_current_context,
+            get_command_history,
+            clear_command_history,
+            start_file_watch,
+            stop_file_watch,
+            list_file_watches,
+            get_recently_modified,
             learn_from_command,
             // Config commands
             get_config,
+            list_profiles,
+            get_active_profile,
+            create_profile,
+            switch_profile,
+            delete_profile,
             update_config,
             get_temp_file_path,
             get_cache_file_path,
@@ -2672,6 +4475,10 @@ async fn main() {
             start_web_scraping,
             get_scraping_progress,
             scrape_single_page,
+            scrape_with_auth,
+            scraper_import_cookies,
+            export_scrape_result,
+            scraper_clear_cookies,
             extract_links,
             generate_site_map,
             check_robots_txt,
@@ -2685,13 +4492,21 @@ async fn main() {
             update_session_status,
             execute_on_session,
             broadcast_command,
+            broadcast_command_with_summary,
+            broadcast_to_tags,
+            get_sessions_by_tag,
             import_broadcast_sessions,
             export_broadcast_sessions,
             create_local_session,
+            create_ssh_session,
             get_active_broadcasts,
             // AI service management
             restart_ai_service,
             ai_clear_completed_requests,
+            ai_clear_cache,
+            ai_generate_embeddings,
+            semantic_index_text,
+            semantic_search,
             // Optimized AI service commands - missing functions
             optimized_ai_chat,
             get_ai_service_stats,
@@ -2701,10 +4516,13 @@ async fn main() {
             ai_submit_priority_request,
             ai_batch_process,
             ai_get_service_stats,
+            ai_reset_stats,
             ai_clear_completed,
             ai_analyze_critical_error,
             ai_chat_async,
             ai_submit_async_request,
+            ai_cancel_request,
+            ai_get_circuit_state,
             // Advanced AI Request Builder commands
             ai_create_simple_request,
             ai_create_custom_request,
@@ -2713,28 +4531,48 @@ async fn main() {
             // Direct Vision Service commands
             vision_initialize_service,
             vision_capture_full_screen,
+            vision_list_displays,
+            vision_diff_captures,
             vision_capture_region,
             vision_perform_ocr,
+            vision_list_ocr_engines,
             vision_detect_ui_elements,
             vision_analyze_with_ai,
             vision_comprehensive_analysis,
+            vision_clear_ocr_cache,
             vision_check_dependencies,
             // HTTP Client Pool Management
             ai_create_optimized_service,
             ai_get_pool_stats,
+            // Full system scan commands
+            full_system_scan,
+            cancel_system_scan,
             // Security Scanner commands
             security_scan_directory,
             security_scan_real_time,
+            security_get_monitoring_status,
+            security_create_baseline,
+            security_clear_baseline,
+            security_update_advisory_db,
+            security_get_advisory_db_info,
             security_get_scan_results,
             security_set_scan_config,
             security_update_rules,
+            security_load_rules_from_file,
+            security_list_rules,
+            security_export_sarif,
             security_get_vulnerabilities,
             security_remediate_vulnerability,
+            security_rollback_remediation,
+            security_confirm_remediation,
             // Command Flow Visualization commands
             command_flow_analyze,
             command_flow_create_graph,
+            command_flow_export_dot,
+            command_flow_export_mermaid,
             command_flow_get_dependencies,
             command_flow_visualize_execution,
+            command_flow_critical_path,
             command_flow_track_execution,
             command_flow_get_execution_history,
             // Plugin System commands
@@ -2747,13 +4585,27 @@ async fn main() {
             plugin_execute_command,
             plugin_get_info,
             plugin_update,
+            plugin_add_trusted_key,
+            plugin_list_trusted_keys,
+            plugin_get_permissions,
+            plugin_grant_capability,
+            plugin_revoke_capability,
             // Collaboration commands
             collaboration_create_session,
             collaboration_join_session,
             collaboration_leave_session,
             collaboration_share_terminal,
+            collaboration_submit_terminal_op,
+            collaboration_get_terminal_ops,
+            collaboration_update_presence,
+            collaboration_get_participants,
             collaboration_get_sessions,
             collaboration_send_message,
+            collaboration_start_server,
+            collaboration_stop_server,
+            collaboration_connect,
+            collaboration_disconnect,
+            collaboration_send_remote_message,
             // Workflow Automation commands
             workflow_create,
             workflow_execute,
@@ -2762,6 +4614,12 @@ async fn main() {
             workflow_record_macro,
             workflow_stop_recording,
             workflow_get_execution_history,
+            workflow_add_trigger,
+            workflow_set_trigger_enabled,
+            workflow_start_webhook_server,
+            workflow_stop_webhook_server,
+            workflow_cancel_execution,
+            workflow_running_executions,
             // Analytics commands
             analytics_get_performance,
             analytics_get_usage_stats,
@@ -2769,8 +4627,19 @@ async fn main() {
             analytics_track_command,
             analytics_get_command_patterns,
             analytics_get_optimization_suggestions,
+            analytics_get_cache_metrics,
+            analytics_get_action_plan,
+            analytics_define_metric,
+            analytics_record_custom,
+            analytics_list_metrics,
+            analytics_generate_report,
+            analytics_export_report,
+            ai_get_token_usage,
+            analytics_export_prometheus,
+            analytics_get_percentile,
             // Ecosystem Awareness commands
             ecosystem_get_comprehensive_context,
+            get_ecosystem_context,
             ecosystem_learn_from_interaction,
             ecosystem_predict_intent,
             ecosystem_get_adaptive_suggestions,
@@ -2780,6 +4649,9 @@ async fn main() {
             ecosystem_get_context_correlation,
             ecosystem_predict_user_intent,
             ecosystem_analyze_system_patterns,
+            ecosystem_suggest_recovery,
+            ecosystem_export_learning_data,
+            ecosystem_get_command_patterns,
             // Cloud Integration commands
             cloud_backup_config,
             cloud_sync_data,
@@ -2788,6 +4660,13 @@ async fn main() {
             cloud_configure_provider,
             cloud_list_backups,
             cloud_get_providers,
+            cloud_set_backup_schedule,
+            cloud_resolve_conflict,
+            cloud_get_backup_schedule,
+            // Secret store commands
+            set_secret,
+            has_secret,
+            delete_secret,
             // LocalRecall RAG commands
             local_recall_health_check,
             local_recall_list_collections,
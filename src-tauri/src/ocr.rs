@@ -0,0 +1,339 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::vision::{BoundingBox, OCRResult};
+
+/// What `vision_list_ocr_engines` reports for each registered engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrEngineInfo {
+    pub id: String,
+    pub name: String,
+    pub available: bool,
+    /// How to make the engine available when it is not
+    pub install_hint: String,
+}
+
+#[async_trait]
+pub trait OcrEngine: std::fmt::Debug + Send + Sync {
+    /// Identifier accepted by `perform_ocr`'s `engine` argument
+    fn id(&self) -> &str;
+
+    fn name(&self) -> &str;
+
+    fn install_hint(&self) -> String;
+
+    async fn is_available(&self) -> bool;
+
+    async fn recognize(&self, image_path: &Path) -> Result<Vec<OCRResult>>;
+
+    async fn info(&self) -> OcrEngineInfo {
+        OcrEngineInfo {
+            id: self.id().to_string(),
+            name: self.name().to_string(),
+            available: self.is_available().await,
+            install_hint: self.install_hint(),
+        }
+    }
+}
+
+/// Runs the `tesseract` binary and reads its TSV word boxes
+#[derive(Debug, Default)]
+pub struct TesseractEngine;
+
+#[async_trait]
+impl OcrEngine for TesseractEngine {
+    fn id(&self) -> &str {
+        "tesseract"
+    }
+
+    fn name(&self) -> &str {
+        "Tesseract"
+    }
+
+    fn install_hint(&self) -> String {
+        "Install Tesseract with: sudo pacman -S tesseract tesseract-data-eng".to_string()
+    }
+
+    async fn is_available(&self) -> bool {
+        matches!(
+            tokio::process::Command::new("tesseract").arg("--version").output().await,
+            Ok(output) if output.status.success()
+        )
+    }
+
+    async fn recognize(&self, image_path: &Path) -> Result<Vec<OCRResult>> {
+        let output = tokio::process::Command::new("tesseract")
+            .arg(image_path)
+            .args(["stdout", "-l", "eng", "tsv"])
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to run tesseract: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+
+        Ok(parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// (page, block, paragraph, line) numbers identifying one Tesseract text line
+type TsvLineKey = (u32, u32, u32, u32);
+
+/// Fold Tesseract's word-level TSV rows into one result per text line
+fn parse_tesseract_tsv(tsv: &str) -> Vec<OCRResult> {
+    // Words with their boxes and confidences, per line
+    let mut lines: BTreeMap<TsvLineKey, Vec<(String, BoundingBox, f64)>> = BTreeMap::new();
+
+    for row in tsv.lines().skip(1) {
+        let fields: Vec<&str> = row.splitn(12, '\t').collect();
+        if fields.len() < 12 || fields[0] != "5" {
+            continue;
+        }
+        let number = |i: usize| fields[i].trim().parse::<f64>().ok();
+        let (Some(confidence), text) = (number(10), fields[11].trim()) else {
+            continue;
+        };
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+        let key = (
+            number(1).unwrap_or(0.0) as u32,
+            number(2).unwrap_or(0.0) as u32,
+            number(3).unwrap_or(0.0) as u32,
+            number(4).unwrap_or(0.0) as u32,
+        );
+        let bbox = BoundingBox {
+            x: number(6).unwrap_or(0.0) as u32,
+            y: number(7).unwrap_or(0.0) as u32,
+            width: number(8).unwrap_or(0.0) as u32,
+            height: number(9).unwrap_or(0.0) as u32,
+        };
+        lines.entry(key).or_default().push((text.to_string(), bbox, confidence / 100.0));
+    }
+
+    lines
+        .into_values()
+        .map(|words| {
+            let left = words.iter().map(|(_, b, _)| b.x).min().unwrap_or(0);
+            let top = words.iter().map(|(_, b, _)| b.y).min().unwrap_or(0);
+            let right = words.iter().map(|(_, b, _)| b.x + b.width).max().unwrap_or(0);
+            let bottom = words.iter().map(|(_, b, _)| b.y + b.height).max().unwrap_or(0);
+            OCRResult {
+                text: words.iter().map(|(text, _, _)| text.as_str()).collect::<Vec<_>>().join(" "),
+                confidence: words.iter().map(|(_, _, c)| c).sum::<f64>() / words.len() as f64,
+                bounding_box: BoundingBox { x: left, y: top, width: right - left, height: bottom - top },
+                engine: "tesseract".to_string(),
+                substituted_for: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "onnx-ocr")]
+pub use onnx::OnnxOcrEngine;
+
+/// Text-line recognizer running a CTC recognition model (PaddleOCR layout) through ONNX Runtime
+#[cfg(feature = "onnx-ocr")]
+mod onnx {
+    use super::*;
+    use ort::session::Session;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    const MODEL_FILE: &str = "rec.onnx";
+    const CHARSET_FILE: &str = "keys.txt";
+    const INPUT_HEIGHT: u32 = 48;
+    /// Luma distance from the background that counts as ink when segmenting lines
+    const INK_THRESHOLD: u8 = 48;
+
+    /// Loaded recognition model and its charset
+    type Recognizer = (Session, Vec<String>);
+
+    /// Expects `rec.onnx` and its `keys.txt` charset (one symbol per line) in `model_dir`
+    #[derive(Debug)]
+    pub struct OnnxOcrEngine {
+        model_dir: PathBuf,
+        recognizer: Arc<Mutex<Option<Recognizer>>>,
+    }
+
+    impl OnnxOcrEngine {
+        pub fn new(model_dir: PathBuf) -> Self {
+            Self { model_dir, recognizer: Arc::new(Mutex::new(None)) }
+        }
+
+        fn recognize_blocking(model_dir: &Path, recognizer: &Mutex<Option<Recognizer>>, image_path: &Path) -> Result<Vec<OCRResult>> {
+            let mut guard = recognizer.lock().map_err(|_| anyhow!("ONNX session lock poisoned"))?;
+            if guard.is_none() {
+                let session = Session::builder()?.commit_from_file(model_dir.join(MODEL_FILE))?;
+                let charset = std::fs::read_to_string(model_dir.join(CHARSET_FILE))?
+                    .lines()
+                    .map(str::to_string)
+                    .collect();
+                *guard = Some((session, charset));
+            }
+            let (session, charset) = guard.as_mut().expect("session initialised above");
+
+            let image = image::open(image_path).map_err(|e| anyhow!("Failed to open image: {}", e))?.to_luma8();
+            let mut results = Vec::new();
+            for bounds in segment_lines(&image) {
+                let line = image::imageops::crop_imm(&image, bounds.x, bounds.y, bounds.width, bounds.height).to_image();
+                let width = ((line.width() * INPUT_HEIGHT) / line.height().max(1)).max(INPUT_HEIGHT);
+                let resized = image::imageops::resize(&line, width, INPUT_HEIGHT, image::imageops::FilterType::Triangle);
+
+                let input = ndarray::Array4::from_shape_fn((1, 3, INPUT_HEIGHT as usize, width as usize), |(_, _, y, x)| {
+                    (resized.get_pixel(x as u32, y as u32)[0] as f32 / 255.0 - 0.5) / 0.5
+                });
+                let outputs = session.run(ort::inputs![ort::value::Tensor::from_array(input)?])?;
+                let (shape, probabilities) = outputs[0].try_extract_tensor::<f32>()?;
+                let classes = *shape.last().ok_or_else(|| anyhow!("Recognition model returned a scalar"))? as usize;
+
+                if let Some((text, confidence)) = ctc_decode(probabilities, classes, charset) {
+                    results.push(OCRResult {
+                        text,
+                        confidence,
+                        bounding_box: bounds,
+                        engine: "onnx".to_string(),
+                        substituted_for: None,
+                    });
+                }
+            }
+            Ok(results)
+        }
+    }
+
+    #[async_trait]
+    impl OcrEngine for OnnxOcrEngine {
+        fn id(&self) -> &str {
+            "onnx"
+        }
+
+        fn name(&self) -> &str {
+            "ONNX text recognizer"
+        }
+
+        fn install_hint(&self) -> String {
+            format!(
+                "Place a PaddleOCR recognition model ({}) and its charset ({}) in {} and install the ONNX Runtime library",
+                MODEL_FILE,
+                CHARSET_FILE,
+                self.model_dir.display()
+            )
+        }
+
+        async fn is_available(&self) -> bool {
+            self.model_dir.join(MODEL_FILE).exists() && self.model_dir.join(CHARSET_FILE).exists()
+        }
+
+        async fn recognize(&self, image_path: &Path) -> Result<Vec<OCRResult>> {
+            let (model_dir, recognizer, image_path) = (self.model_dir.clone(), self.recognizer.clone(), image_path.to_path_buf());
+            tokio::task::spawn_blocking(move || Self::recognize_blocking(&model_dir, &recognizer, &image_path)).await?
+        }
+    }
+
+    /// Horizontal bands of rows that contain ink, trimmed to the ink's horizontal extent
+    fn segment_lines(image: &image::GrayImage) -> Vec<BoundingBox> {
+        let mut histogram = [0u32; 256];
+        for pixel in image.pixels() {
+            histogram[pixel[0] as usize] += 1;
+        }
+        let background = (0..256).max_by_key(|&luma| histogram[luma]).unwrap_or(255) as u8;
+        let is_ink = |x: u32, y: u32| image.get_pixel(x, y)[0].abs_diff(background) > INK_THRESHOLD;
+
+        let mut lines = Vec::new();
+        let mut start = None;
+        for y in 0..=image.height() {
+            let has_ink = y < image.height() && (0..image.width()).any(|x| is_ink(x, y));
+            match (has_ink, start) {
+                (true, None) => start = Some(y),
+                (false, Some(top)) => {
+                    let columns: Vec<u32> = (0..image.width()).filter(|&x| (top..y).any(|row| is_ink(x, row))).collect();
+                    let (left, right) = (columns[0], columns[columns.len() - 1]);
+                    lines.push(BoundingBox { x: left, y: top, width: right - left + 1, height: y - top });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        lines
+    }
+
+    /// Greedy CTC decoding: best class per timestep, collapsing repeats and dropping blanks (class 0)
+    fn ctc_decode(probabilities: &[f32], classes: usize, charset: &[String]) -> Option<(String, f64)> {
+        let mut text = String::new();
+        let mut scores = Vec::new();
+        let mut previous = 0;
+        for step in probabilities.chunks_exact(classes) {
+            let (best, score) = step
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(class, score)| (class, *score))?;
+            if best != 0 && best != previous {
+                // Class 0 is the CTC blank, so the charset starts at class 1; PaddleOCR appends a space class
+                text.push_str(charset.get(best - 1).map(String::as_str).unwrap_or(" "));
+                scores.push(score as f64);
+            }
+            previous = best;
+        }
+        let text = text.trim().to_string();
+        (!text.is_empty()).then(|| (text, scores.iter().sum::<f64>() / scores.len() as f64))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_segment_lines_and_ctc_decode() {
+            let mut image = image::GrayImage::from_pixel(40, 30, image::Luma([250]));
+            for x in 5..20 {
+                for y in 3..8 {
+                    image.put_pixel(x, y, image::Luma([10]));
+                }
+            }
+            for x in 2..30 {
+                image.put_pixel(x, 20, image::Luma([10]));
+            }
+            let lines = segment_lines(&image);
+            assert_eq!(lines.len(), 2);
+            assert_eq!((lines[0].x, lines[0].y, lines[0].width, lines[0].height), (5, 3, 15, 5));
+            assert_eq!((lines[1].x, lines[1].y, lines[1].width, lines[1].height), (2, 20, 28, 1));
+
+            let charset = vec!["l".to_string(), "s".to_string()];
+            // l l <blank> l s s
+            let steps = [[0.1, 0.8, 0.1], [0.1, 0.9, 0.0], [0.9, 0.05, 0.05], [0.2, 0.7, 0.1], [0.0, 0.1, 0.9], [0.1, 0.1, 0.8]];
+            let flat: Vec<f32> = steps.iter().flatten().copied().collect();
+            let (text, confidence) = ctc_decode(&flat, 3, &charset).unwrap();
+            assert_eq!(text, "lls");
+            assert!((confidence - 0.8).abs() < 1e-6);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tesseract_tsv_groups_words_into_lines() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t\n\
+                   4\t1\t1\t1\t1\t0\t10\t10\t200\t20\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t10\t60\t20\t96.5\tcargo\n\
+                   5\t1\t1\t1\t1\t2\t80\t12\t130\t18\t89.5\tbuild\n\
+                   5\t1\t1\t1\t2\t1\t10\t40\t90\t20\t70\terror[E0425]\n\
+                   5\t1\t1\t1\t2\t2\t110\t40\t10\t20\t-1\t \n";
+
+        let lines = parse_tesseract_tsv(tsv);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "cargo build");
+        assert!((lines[0].confidence - 0.93).abs() < 1e-9);
+        let bbox = &lines[0].bounding_box;
+        assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (10, 10, 200, 20));
+        assert_eq!(lines[1].text, "error[E0425]");
+        assert_eq!(lines[1].engine, "tesseract");
+    }
+}
@@ -0,0 +1,407 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wasmtime::{Caller, Config, Engine, ExternType, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+use crate::plugin_system::{Capability, ResourceLimits};
+
+/// Module name plugins import host functions from
+pub const HOST_MODULE: &str = "nexus";
+
+/// Prefix of the error a plugin gets for a host call it has not been granted
+pub const PERMISSION_DENIED_ERROR: &str = "permission_denied";
+
+/// Granularity of the wall-clock limit on plugin execution
+const EPOCH_TICK: Duration = Duration::from_millis(100);
+
+/// Most text a single invocation may emit, and the largest HTTP response body it may fetch
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Largest string a plugin may pass to the host in one call
+const MAX_GUEST_STRING_BYTES: usize = 64 * 1024;
+
+/// Everything a single plugin invocation can see
+#[derive(Debug, Clone)]
+pub struct PluginInvocation {
+    pub command: String,
+    pub args: Vec<String>,
+    pub config: serde_json::Value,
+    /// Capabilities the user granted; checked on every gated host call
+    pub granted: Vec<Capability>,
+    pub limits: ResourceLimits,
+}
+
+struct HostState {
+    invocation: PluginInvocation,
+    output: String,
+    limits: StoreLimits,
+    deadline: Instant,
+    runtime: tokio::runtime::Handle,
+}
+
+/// Compiles and runs WebAssembly plugins in a sandbox.
+///
+/// Plugins export `memory` and `run() -> i32` and can only reach the outside world through
+/// the `nexus` host functions:
+///
+/// | function | signature | capability |
+/// |---|---|---|
+/// | `command` | `(ptr, cap) -> len` | |
+/// | `arg_count` | `() -> count` | |
+/// | `arg` | `(index, ptr, cap) -> len`, `-1` past the end | |
+/// | `config` | `(key_ptr, key_len, ptr, cap) -> len`, `-1` if unset | `config_read` |
+/// | `emit` | `(ptr, len)` | `terminal_write` |
+/// | `http_get` | `(url_ptr, url_len, ptr, cap) -> len`, `-1` on failure; traps past 1 MiB | `network` for the host |
+/// | `read_file` | `(path_ptr, path_len, ptr, cap) -> len`, `-1` on failure | `filesystem` covering the path |
+///
+/// Functions that return a length copy at most `cap` bytes and return the full length, so
+/// the plugin can retry with a larger buffer. A gated call without a matching grant traps
+/// with a `permission_denied` error.
+pub struct PluginRuntime {
+    engine: Engine,
+    modules: Mutex<HashMap<String, Module>>,
+    /// Tells the epoch thread to exit once the runtime is dropped
+    stop_ticker: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for PluginRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginRuntime")
+            .field("modules", &self.modules.lock().map(|m| m.len()).unwrap_or_default())
+            .finish()
+    }
+}
+
+impl PluginRuntime {
+    pub fn new() -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+
+        // One shared clock for every store; each store sets its deadline in ticks
+        let ticker = engine.clone();
+        let stop_ticker = Arc::new(AtomicBool::new(false));
+        let stopped = stop_ticker.clone();
+        std::thread::Builder::new()
+            .name("plugin-epoch".to_string())
+            .spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker.increment_epoch();
+                }
+            })
+            .context("Failed to start plugin timer")?;
+
+        Ok(Self {
+            engine,
+            modules: Mutex::new(HashMap::new()),
+            stop_ticker,
+        })
+    }
+
+    /// Compile and check a plugin module.
+    ///
+    /// It must export `memory` and `run`, and may only import host functions whose
+    /// capabilities its manifest requests.
+    pub fn load(&self, plugin_id: &str, wasm_path: &Path, requested: &[Capability]) -> Result<()> {
+        let bytes = std::fs::read(wasm_path).with_context(|| format!("Failed to read {:?}", wasm_path))?;
+        if !bytes.starts_with(b"\0asm") {
+            return Err(anyhow!("{:?} is not a WebAssembly module", wasm_path));
+        }
+        let module = Module::new(&self.engine, &bytes).with_context(|| format!("Invalid WebAssembly in {:?}", wasm_path))?;
+
+        if !matches!(module.get_export("memory"), Some(ExternType::Memory(_))) {
+            return Err(anyhow!("Plugin module must export its memory as `memory`"));
+        }
+        if !matches!(module.get_export("run"), Some(ExternType::Func(_))) {
+            return Err(anyhow!("Plugin module must export a `run` function"));
+        }
+        for import in module.imports() {
+            if import.module() != HOST_MODULE {
+                return Err(anyhow!(
+                    "Plugin imports {}.{}; only `{}` host functions are available",
+                    import.module(),
+                    import.name(),
+                    HOST_MODULE
+                ));
+            }
+            if let Some(kind) = host_function_capability(import.name())? {
+                if !requested.iter().any(|c| c.kind() == kind) {
+                    return Err(anyhow!(
+                        "Plugin uses {}.{} but its manifest does not request the {} capability",
+                        HOST_MODULE,
+                        import.name(),
+                        kind
+                    ));
+                }
+            }
+        }
+
+        self.modules.lock().unwrap().insert(plugin_id.to_string(), module);
+        Ok(())
+    }
+
+    pub fn is_loaded(&self, plugin_id: &str) -> bool {
+        self.modules.lock().unwrap().contains_key(plugin_id)
+    }
+
+    pub fn unload(&self, plugin_id: &str) {
+        self.modules.lock().unwrap().remove(plugin_id);
+    }
+
+    /// Run a loaded plugin's `run` export and return the text it emitted
+    pub async fn execute(&self, plugin_id: &str, invocation: PluginInvocation) -> Result<String> {
+        let module = self
+            .modules
+            .lock()
+            .unwrap()
+            .get(plugin_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Plugin module not loaded: {}", plugin_id))?;
+
+        let engine = self.engine.clone();
+        let runtime = tokio::runtime::Handle::current();
+        tokio::task::spawn_blocking(move || run_module(&engine, &module, invocation, runtime))
+            .await
+            .context("Plugin execution panicked")?
+    }
+}
+
+impl Drop for PluginRuntime {
+    fn drop(&mut self) {
+        self.stop_ticker.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Capability kind needed to import a host function; `None` for functions every plugin gets
+fn host_function_capability(name: &str) -> Result<Option<&'static str>> {
+    match name {
+        "command" | "arg_count" | "arg" => Ok(None),
+        "config" => Ok(Some("config_read")),
+        "emit" => Ok(Some("terminal_write")),
+        "http_get" => Ok(Some("network")),
+        "read_file" => Ok(Some("filesystem")),
+        _ => Err(anyhow!("Unknown host function {}.{}", HOST_MODULE, name)),
+    }
+}
+
+fn require(caller: &Caller<'_, HostState>, allowed: impl Fn(&Capability) -> bool, what: &str) -> Result<()> {
+    if caller.data().invocation.granted.iter().any(allowed) {
+        Ok(())
+    } else {
+        Err(anyhow!("{}: plugin has not been granted {}", PERMISSION_DENIED_ERROR, what))
+    }
+}
+
+fn run_module(engine: &Engine, module: &Module, invocation: PluginInvocation, runtime: tokio::runtime::Handle) -> Result<String> {
+    let limits = invocation.limits.clone();
+    let timeout = Duration::from_secs(limits.max_execution_time_seconds.max(1));
+    let memory_bytes = usize::try_from(limits.max_memory_mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX);
+
+    let mut store = Store::new(
+        engine,
+        HostState {
+            invocation,
+            output: String::new(),
+            limits: StoreLimitsBuilder::new().memory_size(memory_bytes).instances(1).build(),
+            deadline: Instant::now() + timeout,
+            runtime,
+        },
+    );
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(limits.max_fuel)?;
+    store.set_epoch_deadline((timeout.as_millis() / EPOCH_TICK.as_millis()).max(1) as u64);
+
+    let mut linker = Linker::new(engine);
+    define_host_functions(&mut linker)?;
+
+    let result = linker
+        .instantiate(&mut store, module)
+        .and_then(|instance| instance.get_typed_func::<(), i32>(&mut store, "run"))
+        .and_then(|run| run.call(&mut store, ()));
+
+    match result {
+        Ok(0) => Ok(store.into_data().output),
+        Ok(status) => Err(anyhow!("Plugin exited with status {}: {}", status, store.into_data().output.trim())),
+        Err(e) => match e.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => Err(anyhow!("Plugin exceeded its instruction budget")),
+            Some(Trap::Interrupt) => Err(anyhow!("Plugin exceeded its time limit of {}s", timeout.as_secs())),
+            // Host call errors, e.g. permission_denied, are reported as they are
+            None if e.root_cause().to_string().starts_with(PERMISSION_DENIED_ERROR) => {
+                Err(anyhow!(e.root_cause().to_string()))
+            }
+            _ => Err(anyhow!("Plugin execution failed: {}", e.root_cause())),
+        },
+    }
+}
+
+fn define_host_functions(linker: &mut Linker<HostState>) -> Result<()> {
+    linker.func_wrap(HOST_MODULE, "command", |mut caller: Caller<'_, HostState>, ptr: i32, cap: i32| {
+        let command = caller.data().invocation.command.clone();
+        write_guest(&mut caller, command.as_bytes(), ptr, cap)
+    })?;
+
+    linker.func_wrap(HOST_MODULE, "arg_count", |caller: Caller<'_, HostState>| {
+        caller.data().invocation.args.len() as i32
+    })?;
+
+    linker.func_wrap(HOST_MODULE, "arg", |mut caller: Caller<'_, HostState>, index: i32, ptr: i32, cap: i32| {
+        let arg = usize::try_from(index).ok().and_then(|i| caller.data().invocation.args.get(i).cloned());
+        match arg {
+            Some(arg) => write_guest(&mut caller, arg.as_bytes(), ptr, cap),
+            None => Ok(-1),
+        }
+    })?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "config",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32, ptr: i32, cap: i32| {
+            require(&caller, |c| *c == Capability::ConfigRead, "config_read")?;
+            let key = read_guest(&mut caller, key_ptr, key_len)?;
+            let value = match caller.data().invocation.config.get(&key) {
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => return Ok(-1),
+            };
+            write_guest(&mut caller, value.as_bytes(), ptr, cap)
+        },
+    )?;
+
+    linker.func_wrap(HOST_MODULE, "emit", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        require(&caller, |c| *c == Capability::TerminalWrite, "terminal_write")?;
+        let text = read_guest(&mut caller, ptr, len)?;
+        let output = &mut caller.data_mut().output;
+        if output.len() + text.len() > MAX_OUTPUT_BYTES {
+            return Err(anyhow!("Plugin output exceeded {} bytes", MAX_OUTPUT_BYTES));
+        }
+        output.push_str(&text);
+        Ok(())
+    })?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "http_get",
+        |mut caller: Caller<'_, HostState>, url_ptr: i32, url_len: i32, ptr: i32, cap: i32| {
+            let url = read_guest(&mut caller, url_ptr, url_len)?;
+            let parsed = reqwest::Url::parse(&url).with_context(|| format!("Invalid URL from plugin: {}", url))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(anyhow!("Plugin may only make HTTP requests, got {}", url));
+            }
+            let host = parsed.host_str().unwrap_or_default().to_string();
+            require(&caller, |c| *c == Capability::Network(host.clone()), &format!("network access to {}", host))?;
+
+            // Redirects are followed only to hosts the plugin may also reach directly
+            let allowed_hosts: Vec<String> = caller
+                .data()
+                .invocation
+                .granted
+                .iter()
+                .filter_map(|c| match c {
+                    Capability::Network(host) => Some(host.clone()),
+                    _ => None,
+                })
+                .collect();
+            let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+                let host = attempt.url().host_str().unwrap_or_default();
+                if !allowed_hosts.iter().any(|allowed| allowed == host) {
+                    let error = anyhow!("{}: redirect to {} is not granted", PERMISSION_DENIED_ERROR, host);
+                    attempt.error(error)
+                } else if attempt.previous().len() >= 10 {
+                    attempt.error(anyhow!("Too many redirects"))
+                } else {
+                    attempt.follow()
+                }
+            });
+
+            let remaining = caller.data().deadline.saturating_duration_since(Instant::now());
+            // Streamed so an oversized response is cut off instead of buffered whole
+            let body = caller.data().runtime.block_on(async move {
+                let client = reqwest::Client::builder().redirect(redirects).build()?;
+                let mut response = client.get(parsed).timeout(remaining).send().await?.error_for_status()?;
+                let mut body = Vec::new();
+                while let Some(chunk) = response.chunk().await? {
+                    if body.len() + chunk.len() > MAX_OUTPUT_BYTES {
+                        return Ok(None);
+                    }
+                    body.extend_from_slice(&chunk);
+                }
+                Ok::<_, reqwest::Error>(Some(body))
+            });
+            match body {
+                Ok(Some(body)) => write_guest(&mut caller, &body, ptr, cap),
+                Ok(None) => Err(anyhow!("HTTP response from {} exceeded {} bytes", host, MAX_OUTPUT_BYTES)),
+                Err(_) => Ok(-1),
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "read_file",
+        |mut caller: Caller<'_, HostState>, path_ptr: i32, path_len: i32, ptr: i32, cap: i32| {
+            let path = PathBuf::from(read_guest(&mut caller, path_ptr, path_len)?);
+            // Resolve symlinks and `..` before comparing against the granted directories
+            let Ok(path) = path.canonicalize() else {
+                return Ok(-1);
+            };
+            let covered = |c: &Capability| match c {
+                Capability::Filesystem(root) => root.canonicalize().is_ok_and(|root| path.starts_with(root)),
+                _ => false,
+            };
+            require(&caller, covered, &format!("filesystem access to {}", path.display()))?;
+
+            // Read only what fits in the plugin's buffer, but report the full size
+            let read = std::fs::File::open(&path).and_then(|file| {
+                let size = file.metadata()?.len();
+                let mut content = Vec::new();
+                file.take(u64::try_from(cap).unwrap_or(0)).read_to_end(&mut content)?;
+                Ok((size, content))
+            });
+            match read {
+                Ok((size, content)) => {
+                    write_guest(&mut caller, &content, ptr, cap)?;
+                    Ok(i32::try_from(size).unwrap_or(i32::MAX))
+                }
+                Err(_) => Ok(-1),
+            }
+        },
+    )?;
+
+    Ok(())
+}
+
+fn read_guest(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String> {
+    let (Ok(ptr), Ok(len)) = (usize::try_from(ptr), usize::try_from(len)) else {
+        return Err(anyhow!("Negative pointer or length from plugin"));
+    };
+    if len > MAX_GUEST_STRING_BYTES {
+        return Err(anyhow!("Plugin passed a {} byte string; the limit is {}", len, MAX_GUEST_STRING_BYTES));
+    }
+
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow!("Plugin does not export memory"))?;
+    let mut buffer = vec![0; len];
+    memory.read(&*caller, ptr, &mut buffer)?;
+    String::from_utf8(buffer).context("Plugin passed invalid UTF-8")
+}
+
+fn write_guest(caller: &mut Caller<'_, HostState>, bytes: &[u8], ptr: i32, cap: i32) -> Result<i32> {
+    let (Ok(ptr), Ok(cap)) = (usize::try_from(ptr), usize::try_from(cap)) else {
+        return Err(anyhow!("Negative pointer or capacity from plugin"));
+    };
+
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow!("Plugin does not export memory"))?;
+    memory.write(&mut *caller, ptr, &bytes[..bytes.len().min(cap)])?;
+    Ok(i32::try_from(bytes.len()).unwrap_or(i32::MAX))
+}
@@ -1,13 +1,26 @@
 use anyhow::{Result, anyhow};
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 #[allow(unused_imports)]
 use std::collections::{HashSet, VecDeque};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use chrono::{DateTime, Utc};
-use std::process::Stdio;
 use tokio::process::Command;
 
+use crate::plugin_runtime::{PluginInvocation, PluginRuntime};
+
+/// Instructions a plugin invocation may execute before it is stopped
+const DEFAULT_PLUGIN_FUEL: u64 = 1_000_000_000;
+
+/// Base64 ed25519 signature over the bundle digest, next to `plugin.json`
+const SIGNATURE_FILE: &str = "plugin.sig";
+
+/// Optional plugin settings, passed to every invocation
+const CONFIG_FILE: &str = "config.json";
+
 // Missing types expected by main.rs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallResult {
@@ -15,6 +28,29 @@ pub struct InstallResult {
     pub success: bool,
     pub message: String,
     pub install_path: Option<PathBuf>,
+    /// Signature check result, if the bundle got far enough to be checked
+    pub verification: Option<VerificationStatus>,
+    /// Trusted key that signed the bundle
+    pub publisher: Option<String>,
+}
+
+/// Outcome of checking a plugin bundle's signature against the trusted publisher keys
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Verified,
+    #[default]
+    Unsigned,
+    /// Signed, but not by a trusted key or the bundle changed after signing
+    Mismatched,
+}
+
+/// Publisher public key that plugin bundles may be signed with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedKey {
+    pub name: String,
+    /// Base64 raw 32-byte ed25519 public key
+    pub public_key: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +66,11 @@ pub struct PluginInfo {
     pub install_path: Option<PathBuf>,
     pub installed_at: Option<DateTime<Utc>>,
     pub last_updated: Option<DateTime<Utc>>,
+    /// Capabilities the manifest requests
+    pub capabilities: Vec<Capability>,
+    pub granted_capabilities: Vec<Capability>,
+    pub verification: VerificationStatus,
+    pub publisher: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,6 +110,29 @@ pub struct Plugin {
     pub install_path: Option<PathBuf>,
     pub installed_at: Option<DateTime<Utc>>,
     pub last_updated: Option<DateTime<Utc>>,
+    /// Set from the bundle signature, never from the manifest itself
+    #[serde(default, skip_deserializing)]
+    pub verification: VerificationStatus,
+    #[serde(default, skip_deserializing)]
+    pub publisher: Option<String>,
+}
+
+/// Signature verification failure that blocks an install without `allow_unsigned`
+#[derive(Debug, thiserror::Error)]
+#[error("Plugin {plugin_id} is {}; pass allow_unsigned to install it anyway", .status.describe())]
+struct UnverifiedPlugin {
+    plugin_id: String,
+    status: VerificationStatus,
+}
+
+impl VerificationStatus {
+    fn describe(&self) -> &'static str {
+        match self {
+            VerificationStatus::Verified => "signed by a trusted publisher",
+            VerificationStatus::Unsigned => "unsigned",
+            VerificationStatus::Mismatched => "not signed by any trusted publisher key",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -96,6 +160,7 @@ pub enum PluginStatus {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginManifest {
+    /// WebAssembly module relative to the plugin directory
     pub entry_point: String,
     pub commands: Vec<PluginCommand>,
     pub hooks: Vec<PluginHook>,
@@ -104,6 +169,69 @@ pub struct PluginManifest {
     pub api_version: String,
     pub platform_requirements: Vec<String>,
     pub config_schema: Option<serde_json::Value>,
+    /// What the plugin's host calls may touch; each must be granted by the user
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+}
+
+/// Something a plugin may access through the host ABI
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Capability {
+    /// Read files under this directory
+    Filesystem(PathBuf),
+    /// Make HTTP requests to this host
+    Network(String),
+    TerminalWrite,
+    ConfigRead,
+}
+
+impl Capability {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Capability::Filesystem(_) => "filesystem",
+            Capability::Network(_) => "network",
+            Capability::TerminalWrite => "terminal_write",
+            Capability::ConfigRead => "config_read",
+        }
+    }
+
+    /// Whether granting `self` stays within what `requested` asked for. Paths are compared
+    /// after resolving symlinks, so both must exist, and `..` is never allowed.
+    fn within(&self, requested: &Capability) -> bool {
+        match (self, requested) {
+            (Capability::Filesystem(path), Capability::Filesystem(root)) => {
+                if path.components().any(|c| c == Component::ParentDir) {
+                    return false;
+                }
+                match (path.canonicalize(), root.canonicalize()) {
+                    (Ok(path), Ok(root)) => path.starts_with(root),
+                    _ => false,
+                }
+            }
+            _ => self == requested,
+        }
+    }
+}
+
+impl PluginPermission {
+    /// The host ABI capability a legacy permission maps to, if it has one. Filesystem
+    /// reads are confined to the plugin's own directory.
+    fn capability(&self, plugin_dir: &Path) -> Option<Capability> {
+        match self {
+            PluginPermission::TerminalAccess => Some(Capability::TerminalWrite),
+            PluginPermission::FileSystemRead => Some(Capability::Filesystem(plugin_dir.to_path_buf())),
+            _ => None,
+        }
+    }
+}
+
+/// Requested and granted capabilities of an installed plugin
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginPermissions {
+    pub plugin_id: String,
+    pub requested: Vec<Capability>,
+    pub granted: Vec<Capability>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -236,6 +364,12 @@ pub struct ResourceLimits {
     pub max_cpu_percent: f32,
     pub max_execution_time_seconds: u64,
     pub max_file_size_mb: u64,
+    #[serde(default = "default_plugin_fuel")]
+    pub max_fuel: u64,
+}
+
+fn default_plugin_fuel() -> u64 {
+    DEFAULT_PLUGIN_FUEL
 }
 
 #[derive(Debug)]
@@ -246,19 +380,53 @@ pub struct PluginSystem {
     marketplace: Option<PluginMarketplace>,
     plugins_dir: PathBuf,
     sandboxes: HashMap<String, PluginSandbox>,
+    /// Capability grants by plugin id, persisted in `permissions.json`
+    permissions: HashMap<String, PluginPermissions>,
+    runtime: PluginRuntime,
+    trusted_keys: Vec<TrustedKey>,
 }
 
 #[allow(dead_code)]
 impl PluginSystem {
-    pub fn new(plugins_dir: PathBuf) -> Self {
-        Self {
+    pub fn new(plugins_dir: PathBuf) -> Result<Self> {
+        Ok(Self {
             plugins: HashMap::new(),
             enabled_plugins: Vec::new(),
             plugin_executions: HashMap::new(),
             marketplace: None,
+            permissions: load_permissions(&plugins_dir),
             plugins_dir,
             sandboxes: HashMap::new(),
+            runtime: PluginRuntime::new()?,
+            trusted_keys: Vec::new(),
+        })
+    }
+
+    /// Replace the publisher keys used to verify bundles, normally from `AppConfig`
+    pub fn set_trusted_keys(&mut self, keys: Vec<TrustedKey>) {
+        self.trusted_keys = keys;
+    }
+
+    pub fn trusted_keys(&self) -> &[TrustedKey] {
+        &self.trusted_keys
+    }
+
+    /// Trust a base64 ed25519 public key, replacing any key with the same name
+    pub fn add_trusted_key(&mut self, name: &str, public_key: &str) -> Result<TrustedKey> {
+        if name.trim().is_empty() {
+            return Err(anyhow!("Trusted key name cannot be empty"));
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(public_key.trim())
+            .map_err(|e| anyhow!("Public key is not valid base64: {}", e))?;
+        if bytes.len() != 32 {
+            return Err(anyhow!("Expected a 32-byte ed25519 public key, got {} bytes", bytes.len()));
         }
+
+        let key = TrustedKey { name: name.trim().to_string(), public_key: public_key.trim().to_string() };
+        self.trusted_keys.retain(|k| k.name != key.name);
+        self.trusted_keys.push(key.clone());
+        Ok(key)
     }
 
     pub async fn initialize(&mut self) -> Result<()> {
@@ -288,7 +456,14 @@ impl PluginSystem {
                 
                 if manifest_path.exists() {
                     match self.load_plugin_manifest(&manifest_path).await {
-                        Ok(plugin) => {
+                        Ok(mut plugin) => {
+                            match self.verify_bundle(&plugin_dir, &plugin) {
+                                Ok((status, publisher)) => {
+                                    plugin.verification = status;
+                                    plugin.publisher = publisher;
+                                }
+                                Err(e) => eprintln!("Warning: Failed to verify plugin {}: {}", plugin.id, e),
+                            }
                             self.plugins.insert(plugin.id.clone(), plugin);
                         }
                         Err(e) => {
@@ -308,36 +483,51 @@ impl PluginSystem {
         Ok(plugin)
     }
 
-    async fn install_plugin_internal(&mut self, plugin_id: &str, source: PluginSource) -> Result<()> {
+    async fn install_plugin_internal(&mut self, plugin_id: &str, source: PluginSource, allow_unsigned: bool) -> Result<()> {
         if self.plugins.contains_key(plugin_id) {
             return Err(anyhow!("Plugin already installed: {}", plugin_id));
         }
 
-        let plugin = match source {
+        // A git clone lives in a temp dir removed on return, so a rejected bundle leaves nothing behind
+        let (plugin, package_dir, _clone) = match source {
             PluginSource::Marketplace => {
-                self.download_from_marketplace(plugin_id).await?
+                (self.download_from_marketplace(plugin_id).await?, None, None)
             }
             PluginSource::Git(url) => {
-                self.install_from_git(plugin_id, &url).await?
+                let (plugin, clone) = self.install_from_git(&url).await?;
+                let package_dir = clone.path().to_path_buf();
+                (plugin, Some(package_dir), Some(clone))
             }
             PluginSource::Local(path) => {
-                self.install_from_local(&path).await?
+                (self.install_from_local(&path).await?, Some(path), None)
             }
             PluginSource::Archive(path) => {
-                self.install_from_archive(&path).await?
+                (self.install_from_archive(&path).await?, Some(path), None)
             }
         };
+        let package_dir = package_dir
+            .ok_or_else(|| anyhow!("Plugin {} has no package to install", plugin_id))?;
+
+        let (verification, publisher) = self.verify_bundle(&package_dir, &plugin)?;
+        if verification != VerificationStatus::Verified && !allow_unsigned {
+            return Err(UnverifiedPlugin { plugin_id: plugin_id.to_string(), status: verification }.into());
+        }
+
+        // Only validated WebAssembly modules are installed
+        let install_path = self.plugins_dir.join(&plugin.id);
+        self.install_package(plugin_id, &plugin, &package_dir, &install_path).await?;
 
         // Create sandbox for the plugin
         let sandbox = self.create_sandbox(&plugin)?;
         self.sandboxes.insert(plugin_id.to_string(), sandbox);
 
         // Install the plugin
-        let install_path = self.plugins_dir.join(&plugin.id);
         let mut installed_plugin = plugin;
         installed_plugin.status = PluginStatus::Installed;
         installed_plugin.install_path = Some(install_path);
         installed_plugin.installed_at = Some(Utc::now());
+        installed_plugin.verification = verification;
+        installed_plugin.publisher = publisher;
 
         self.plugins.insert(plugin_id.to_string(), installed_plugin);
         
@@ -367,17 +557,21 @@ impl PluginSystem {
                 api_version: "1.0".to_string(),
                 platform_requirements: vec!["linux".to_string()],
                 config_schema: None,
+                capabilities: vec![],
             },
             status: PluginStatus::Available,
             install_path: None,
             installed_at: None,
             last_updated: None,
+            verification: VerificationStatus::Unsigned,
+            publisher: None,
         })
     }
 
-    async fn install_from_git(&self, plugin_id: &str, git_url: &str) -> Result<Plugin> {
-        let clone_path = self.plugins_dir.join(plugin_id);
-        
+    /// Clone `git_url` into a temp dir outside `plugins_dir`; the bundle is copied in from there once verified
+    async fn install_from_git(&self, git_url: &str) -> Result<(Plugin, tempfile::TempDir)> {
+        let clone_dir = tempfile::tempdir()?;
+        let clone_path = clone_dir.path();
         let output = Command::new("git")
             .args(&["clone", git_url, &clone_path.to_string_lossy()])
             .output()
@@ -388,7 +582,7 @@ impl PluginSystem {
         }
 
         let manifest_path = clone_path.join("plugin.json");
-        self.load_plugin_manifest(&manifest_path).await
+        Ok((self.load_plugin_manifest(&manifest_path).await?, clone_dir))
     }
 
     async fn install_from_local(&self, local_path: &PathBuf) -> Result<Plugin> {
@@ -401,6 +595,134 @@ impl PluginSystem {
         self.install_from_local(archive_path).await
     }
 
+    /// Validate the plugin's WebAssembly entry point and copy the package into `install_path`
+    async fn install_package(&self, plugin_id: &str, plugin: &Plugin, package_dir: &Path, install_path: &Path) -> Result<()> {
+        let entry_point = Path::new(&plugin.manifest.entry_point);
+        if entry_point.extension().and_then(|e| e.to_str()) != Some("wasm") {
+            return Err(anyhow!("Plugin entry point must be a .wasm module, got {}", plugin.manifest.entry_point));
+        }
+        if !entry_point.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(anyhow!("Plugin entry point must be a path inside the plugin: {}", plugin.manifest.entry_point));
+        }
+        if let Some(permission) = plugin.manifest.permissions.iter().find(|p| p.capability(install_path).is_none()) {
+            return Err(anyhow!(
+                "Legacy permission {:?} has no sandbox equivalent; declare it under `capabilities` instead",
+                permission
+            ));
+        }
+        let requested = self.requested_capabilities(plugin);
+        for capability in &requested {
+            match capability {
+                Capability::Network(host) if host.is_empty() || host.contains(['/', ':']) => {
+                    return Err(anyhow!("Network capability must name a bare host, got {:?}", host));
+                }
+                Capability::Filesystem(path) if !path.is_absolute() => {
+                    return Err(anyhow!("Filesystem capability must be an absolute path, got {:?}", path));
+                }
+                _ => {}
+            }
+        }
+
+        self.runtime.load(plugin_id, &package_dir.join(entry_point), &requested)?;
+
+        if package_dir != install_path {
+            if let Some(parent) = install_path.join(entry_point).parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            for file in bundle_files(&plugin.manifest.entry_point).into_iter().chain([SIGNATURE_FILE]) {
+                let source = package_dir.join(file);
+                if source.exists() {
+                    tokio::fs::copy(&source, install_path.join(file)).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check `plugin.sig` in `package_dir` against every trusted key, returning the matching publisher
+    fn verify_bundle(&self, package_dir: &Path, plugin: &Plugin) -> Result<(VerificationStatus, Option<String>)> {
+        let signature_path = package_dir.join(SIGNATURE_FILE);
+        if !signature_path.exists() {
+            return Ok((VerificationStatus::Unsigned, None));
+        }
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let signature = match engine.decode(std::fs::read_to_string(&signature_path)?.trim()) {
+            Ok(signature) => signature,
+            Err(_) => return Ok((VerificationStatus::Mismatched, None)),
+        };
+        let digest = bundle_digest(package_dir, &plugin.manifest.entry_point)?;
+
+        let publisher = self.trusted_keys.iter().find(|key| {
+            engine.decode(&key.public_key).is_ok_and(|public_key| {
+                UnparsedPublicKey::new(&ED25519, public_key).verify(&digest, &signature).is_ok()
+            })
+        });
+        Ok(match publisher {
+            Some(key) => (VerificationStatus::Verified, Some(key.name.clone())),
+            None => (VerificationStatus::Mismatched, None),
+        })
+    }
+
+    pub fn get_permissions(&self, plugin_id: &str) -> Result<PluginPermissions> {
+        let plugin = self.plugins.get(plugin_id)
+            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))?;
+        Ok(PluginPermissions {
+            plugin_id: plugin_id.to_string(),
+            requested: self.requested_capabilities(plugin),
+            granted: self.granted_capabilities(plugin_id),
+        })
+    }
+
+    /// Grant a capability the plugin's manifest requested, or a narrower filesystem path
+    pub fn grant_capability(&mut self, plugin_id: &str, capability: Capability) -> Result<PluginPermissions> {
+        let mut permissions = self.get_permissions(plugin_id)?;
+        if !permissions.requested.iter().any(|requested| capability.within(requested)) {
+            return Err(anyhow!("Plugin {} did not request {:?}", plugin_id, capability));
+        }
+
+        if !permissions.granted.contains(&capability) {
+            permissions.granted.push(capability);
+        }
+        self.permissions.insert(plugin_id.to_string(), permissions.clone());
+        self.save_permissions()?;
+        Ok(permissions)
+    }
+
+    pub fn revoke_capability(&mut self, plugin_id: &str, capability: &Capability) -> Result<PluginPermissions> {
+        let mut permissions = self.get_permissions(plugin_id)?;
+        permissions.granted.retain(|granted| granted != capability);
+        self.permissions.insert(plugin_id.to_string(), permissions.clone());
+        self.save_permissions()?;
+        Ok(permissions)
+    }
+
+    /// What the plugin may be granted: its manifest capabilities plus those implied by
+    /// legacy `permissions` entries
+    fn requested_capabilities(&self, plugin: &Plugin) -> Vec<Capability> {
+        let plugin_dir = self.plugins_dir.join(&plugin.id);
+        let mut requested = plugin.manifest.capabilities.clone();
+        for capability in plugin.manifest.permissions.iter().filter_map(|p| p.capability(&plugin_dir)) {
+            if !requested.contains(&capability) {
+                requested.push(capability);
+            }
+        }
+        requested
+    }
+
+    fn granted_capabilities(&self, plugin_id: &str) -> Vec<Capability> {
+        self.permissions.get(plugin_id).map(|p| p.granted.clone()).unwrap_or_default()
+    }
+
+    fn save_permissions(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.plugins_dir)?;
+        std::fs::write(
+            self.plugins_dir.join("permissions.json"),
+            serde_json::to_string_pretty(&self.permissions)?,
+        )?;
+        Ok(())
+    }
+
     fn create_sandbox(&self, plugin: &Plugin) -> Result<PluginSandbox> {
         let allowed_paths = vec![
             self.plugins_dir.join(&plugin.id),
@@ -416,6 +738,7 @@ impl PluginSystem {
             max_cpu_percent: 50.0,
             max_execution_time_seconds: 30,
             max_file_size_mb: 100,
+            max_fuel: DEFAULT_PLUGIN_FUEL,
         };
 
         let network_access = plugin.manifest.permissions.contains(&PluginPermission::NetworkAccess);
@@ -465,121 +788,42 @@ impl PluginSystem {
     }
 
     async fn execute_hook(&self, plugin_id: &str, hook: &PluginHook) -> Result<()> {
-        if let Some(plugin) = self.plugins.get(plugin_id) {
-            if let Some(install_path) = &plugin.install_path {
-                let entry_point = install_path.join(&plugin.manifest.entry_point);
-                
-                let mut cmd = Command::new("node"); // Assuming JavaScript plugins
-                cmd.arg(&entry_point)
-                   .arg("--hook")
-                   .arg(&hook.name)
-                   .current_dir(install_path)
-                   .stdout(Stdio::piped())
-                   .stderr(Stdio::piped());
-
-                // Apply sandbox restrictions
-                if let Some(sandbox) = self.sandboxes.get(plugin_id) {
-                    for (key, value) in &sandbox.environment_vars {
-                        cmd.env(key, value);
-                    }
-                }
-
-                let output = cmd.output().await?;
-                if !output.status.success() {
-                    eprintln!("Hook execution failed for {}: {}", hook.name, String::from_utf8_lossy(&output.stderr));
-                }
-            }
+        if let Err(e) = self.execute_sandboxed_command(plugin_id, &hook.name, Vec::new()).await {
+            eprintln!("Hook execution failed for {}: {}", hook.name, e);
         }
         Ok(())
     }
 
-
+    /// Run a command in the plugin's WebAssembly sandbox.
+    ///
+    /// Host calls needing a capability the user has not granted fail with `permission_denied`.
     async fn execute_sandboxed_command(&self, plugin_id: &str, command: &str, args: Vec<String>) -> Result<String> {
-        if let Some(plugin) = self.plugins.get(plugin_id) {
-            if let Some(install_path) = &plugin.install_path {
-                let entry_point = install_path.join(&plugin.manifest.entry_point);
-                
-                let mut cmd = Command::new("node");
-                cmd.arg(&entry_point)
-                   .arg("--command")
-                   .arg(command)
-                   .args(&args)
-                   .current_dir(install_path)
-                   .stdout(Stdio::piped())
-                   .stderr(Stdio::piped());
-
-                // Apply sandbox restrictions
-                if let Some(sandbox) = self.sandboxes.get(plugin_id) {
-                    for (key, value) in &sandbox.environment_vars {
-                        cmd.env(key, value);
-                    }
-                    
-                    // Apply resource limits
-                    // Memory limits
-                    if sandbox.resource_limits.max_memory_mb > 0 {
-                        #[cfg(target_os = "linux")]
-                        {
-                            cmd.env("PLUGIN_MEMORY_LIMIT", sandbox.resource_limits.max_memory_mb.to_string());
-                            // Use cgroup v2 if available
-                            if Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
-                                let cgroup_path = format!("/sys/fs/cgroup/nexus-plugins/{}", plugin_id);
-                                let _ = std::fs::create_dir_all(&cgroup_path);
-                                let _ = std::fs::write(
-                                    format!("{}/memory.max", cgroup_path), 
-                                    format!("{}", sandbox.resource_limits.max_memory_mb * 1024 * 1024)
-                                );
-                                cmd.env("NEXUS_PLUGIN_CGROUP", cgroup_path);
-                            }
-                        }
-                    }
-                    
-                    // CPU limits
-                    if sandbox.resource_limits.max_cpu_percent > 0.0 {
-                        cmd.env("PLUGIN_CPU_LIMIT", sandbox.resource_limits.max_cpu_percent.to_string());
-                        // For Node.js plugins, we can use --max-old-space-size to limit memory
-                        if command == "node" {
-                            let max_old_space = sandbox.resource_limits.max_memory_mb;
-                            cmd.arg(format!("--max-old-space-size={}", max_old_space));
-                        }
-                    }
-                    
-                    // File descriptor limits
-                    #[cfg(target_family = "unix")]
-                    {
-                        // use std::os::unix::process::CommandExt;
-                        // File descriptor limits are not yet implemented in ResourceLimits
-                        // if let Some(max_files) = sandbox.resource_limits.max_files {
-                        //     unsafe {
-                        //         cmd.pre_exec(move || {
-                        //             let res = libc::setrlimit(
-                        //                 libc::RLIMIT_NOFILE,
-                        //                 &libc::rlimit {
-                        //                     rlim_cur: max_files as libc::rlim_t,
-                        //                     rlim_max: max_files as libc::rlim_t,
-                        //                 },
-                        //             );
-                        //             if res != 0 {
-                        //                 return Err(std::io::Error::last_os_error());
-                        //             }
-                        //             Ok(())
-                        //         });
-                        //     }
-                        // }
-                    }
-                }
-
-                let output = cmd.output().await?;
-                if output.status.success() {
-                    Ok(String::from_utf8_lossy(&output.stdout).to_string())
-                } else {
-                    Err(anyhow!("Command execution failed: {}", String::from_utf8_lossy(&output.stderr)))
-                }
-            } else {
-                Err(anyhow!("Plugin not installed: {}", plugin_id))
-            }
-        } else {
-            Err(anyhow!("Plugin not found: {}", plugin_id))
+        let plugin = self.plugins.get(plugin_id)
+            .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))?;
+        let install_path = plugin.install_path.as_ref()
+            .ok_or_else(|| anyhow!("Plugin not installed: {}", plugin_id))?;
+
+        if !self.runtime.is_loaded(plugin_id) {
+            let entry_point = install_path.join(&plugin.manifest.entry_point);
+            self.runtime.load(plugin_id, &entry_point, &self.requested_capabilities(plugin))?;
         }
+
+        let limits = match self.sandboxes.get(plugin_id) {
+            Some(sandbox) => sandbox.resource_limits.clone(),
+            None => self.create_sandbox(plugin)?.resource_limits,
+        };
+        let config = match tokio::fs::read_to_string(install_path.join(CONFIG_FILE)).await {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(_) => serde_json::Value::Null,
+        };
+
+        self.runtime.execute(plugin_id, PluginInvocation {
+            command: command.to_string(),
+            args,
+            config,
+            granted: self.granted_capabilities(plugin_id),
+            limits,
+        }).await
     }
 
     pub async fn uninstall_plugin(&mut self, plugin_id: &str) -> Result<()> {
@@ -587,8 +831,12 @@ impl PluginSystem {
             // Disable first
             self.enabled_plugins.retain(|id| id != plugin_id);
             
-            // Remove sandbox
+            // Remove sandbox, compiled module and grants
             self.sandboxes.remove(plugin_id);
+            self.runtime.unload(plugin_id);
+            if self.permissions.remove(plugin_id).is_some() {
+                self.save_permissions()?;
+            }
 
             // Remove installation directory
             if let Some(install_path) = &plugin.install_path {
@@ -658,7 +906,8 @@ impl PluginSystem {
     }
 
     // Methods expected by main.rs
-    pub async fn install_plugin(&mut self, plugin_path: &str) -> Result<InstallResult> {
+    /// Install a plugin bundle; unsigned or untrusted bundles need `allow_unsigned`
+    pub async fn install_plugin(&mut self, plugin_path: &str, allow_unsigned: bool) -> Result<InstallResult> {
         // Determine source from path
         let source = if plugin_path.starts_with("http://") || plugin_path.starts_with("https://") {
             if plugin_path.contains("github.com") || plugin_path.ends_with(".git") {
@@ -681,14 +930,22 @@ impl PluginSystem {
             .unwrap_or("unknown-plugin")
             .to_string();
 
-        match self.install_plugin_internal(&plugin_id, source).await {
+        match self.install_plugin_internal(&plugin_id, source, allow_unsigned).await {
             Ok(()) => {
                 let install_path = self.plugins_dir.join(&plugin_id);
+                let plugin = self.plugins.get(&plugin_id);
+                let verification = plugin.map(|p| p.verification.clone()).unwrap_or_default();
+                let message = match verification {
+                    VerificationStatus::Verified => format!("Plugin {} installed successfully", plugin_id),
+                    _ => format!("Plugin {} installed successfully ({})", plugin_id, verification.describe()),
+                };
                 Ok(InstallResult {
                     plugin_id: plugin_id.clone(),
                     success: true,
-                    message: format!("Plugin {} installed successfully", plugin_id),
+                    message,
                     install_path: Some(install_path),
+                    publisher: plugin.and_then(|p| p.publisher.clone()),
+                    verification: Some(verification),
                 })
             }
             Err(e) => Ok(InstallResult {
@@ -696,6 +953,8 @@ impl PluginSystem {
                 success: false,
                 message: e.to_string(),
                 install_path: None,
+                verification: e.downcast_ref::<UnverifiedPlugin>().map(|u| u.status.clone()),
+                publisher: None,
             })
         }
     }
@@ -717,6 +976,10 @@ impl PluginSystem {
                 install_path: plugin.install_path.clone(),
                 installed_at: plugin.installed_at,
                 last_updated: plugin.last_updated,
+                capabilities: self.requested_capabilities(plugin),
+                granted_capabilities: self.granted_capabilities(&plugin.id),
+                verification: plugin.verification.clone(),
+                publisher: plugin.publisher.clone(),
             });
         }
         
@@ -750,11 +1013,14 @@ impl PluginSystem {
                             api_version: "1.0".to_string(),
                             platform_requirements: vec!["linux".to_string(), "macos".to_string()],
                             config_schema: None,
+                            capabilities: vec![Capability::TerminalWrite],
                         },
                         status: PluginStatus::Available,
                         install_path: None,
                         installed_at: None,
                         last_updated: None,
+                        verification: VerificationStatus::Unsigned,
+                        publisher: None,
                     },
                     downloads: 1250,
                     rating: 4.5,
@@ -835,6 +1101,10 @@ impl PluginSystem {
                 install_path: plugin.install_path.clone(),
                 installed_at: plugin.installed_at,
                 last_updated: plugin.last_updated,
+                capabilities: self.requested_capabilities(plugin),
+                granted_capabilities: self.granted_capabilities(plugin_id),
+                verification: plugin.verification.clone(),
+                publisher: plugin.publisher.clone(),
             })
         } else {
             Err(anyhow!("Plugin not found: {}", plugin_id))
@@ -874,6 +1144,35 @@ impl PluginSystem {
     }
 }
 
+/// Every file installed from a bundle besides its signature; only `config.json` may be missing
+fn bundle_files(entry_point: &str) -> [&str; 3] {
+    ["plugin.json", entry_point, CONFIG_FILE]
+}
+
+/// SHA-256 over every installed bundle file, the files a bundle signature covers
+fn bundle_digest(package_dir: &Path, entry_point: &str) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    for file in bundle_files(entry_point) {
+        let content = match std::fs::read(package_dir.join(file)) {
+            Ok(content) => content,
+            Err(e) if file == CONFIG_FILE && e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(anyhow!("Failed to read {} from the plugin bundle: {}", file, e)),
+        };
+        hasher.update(file.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+fn load_permissions(plugins_dir: &Path) -> HashMap<String, PluginPermissions> {
+    std::fs::read_to_string(plugins_dir.join("permissions.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
 pub enum PluginSource {
     Marketplace,
@@ -885,27 +1184,26 @@ pub enum PluginSource {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempdir::TempDir;
 
     #[tokio::test]
     async fn test_plugin_system_creation() {
-        let temp_dir = TempDir::new("plugins").unwrap();
-        let system = PluginSystem::new(temp_dir.path().to_path_buf());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let system = PluginSystem::new(temp_dir.path().to_path_buf()).unwrap();
         assert!(system.plugins.is_empty());
     }
 
     #[tokio::test]
     async fn test_plugin_system_initialization() {
-        let temp_dir = TempDir::new("plugins").unwrap();
-        let mut system = PluginSystem::new(temp_dir.path().to_path_buf());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut system = PluginSystem::new(temp_dir.path().to_path_buf()).unwrap();
         assert!(system.initialize().await.is_ok());
         assert!(temp_dir.path().exists());
     }
 
     #[test]
     fn test_plugin_search() {
-        let temp_dir = TempDir::new("plugins").unwrap();
-        let mut system = PluginSystem::new(temp_dir.path().to_path_buf());
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut system = PluginSystem::new(temp_dir.path().to_path_buf()).unwrap();
         
         let plugin = Plugin {
             id: "test-plugin".to_string(),
@@ -927,11 +1225,14 @@ mod tests {
                 api_version: "1.0".to_string(),
                 platform_requirements: vec!["linux".to_string()],
                 config_schema: None,
+                capabilities: vec![],
             },
             status: PluginStatus::Installed,
             install_path: None,
             installed_at: None,
             last_updated: None,
+            verification: VerificationStatus::Unsigned,
+            publisher: None,
         };
 
         system.plugins.insert(plugin.id.clone(), plugin);
@@ -943,4 +1244,322 @@ mod tests {
         let results = system.search_plugins("development", Some(PluginCategory::Development));
         assert_eq!(results.len(), 1);
     }
+
+    const ECHO_PLUGIN: &str = r#"
+        (module
+          (import "nexus" "arg_count" (func $arg_count (result i32)))
+          (import "nexus" "arg" (func $arg (param i32 i32 i32) (result i32)))
+          (import "nexus" "emit" (func $emit (param i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) " ")
+          (func (export "run") (result i32)
+            (local $i i32)
+            (local $len i32)
+            (block $done
+              (loop $next
+                (br_if $done (i32.ge_s (local.get $i) (call $arg_count)))
+                (if (i32.gt_s (local.get $i) (i32.const 0))
+                  (then (call $emit (i32.const 0) (i32.const 1))))
+                (local.set $len (call $arg (local.get $i) (i32.const 16) (i32.const 1024)))
+                (call $emit (i32.const 16) (local.get $len))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))
+            (i32.const 0)))
+    "#;
+
+    fn write_package(dir: &Path, wat: &str, capabilities: Vec<Capability>) -> PathBuf {
+        let package = dir.join("echo");
+        std::fs::create_dir_all(&package).unwrap();
+        std::fs::write(package.join("echo.wasm"), wat::parse_str(wat).unwrap()).unwrap();
+
+        let plugin = Plugin {
+            id: "echo".to_string(),
+            name: "Echo".to_string(),
+            version: "1.0.0".to_string(),
+            description: "Echoes its arguments".to_string(),
+            author: "Test Author".to_string(),
+            license: "MIT".to_string(),
+            repository: None,
+            homepage: None,
+            tags: vec![],
+            category: PluginCategory::Terminal,
+            manifest: PluginManifest {
+                entry_point: "echo.wasm".to_string(),
+                commands: vec![],
+                hooks: vec![],
+                permissions: vec![],
+                dependencies: vec![],
+                api_version: "1.0".to_string(),
+                platform_requirements: vec![],
+                config_schema: None,
+                capabilities,
+            },
+            status: PluginStatus::Available,
+            install_path: None,
+            installed_at: None,
+            last_updated: None,
+            verification: VerificationStatus::Unsigned,
+            publisher: None,
+        };
+        std::fs::write(package.join("plugin.json"), serde_json::to_string(&plugin).unwrap()).unwrap();
+        package
+    }
+
+    #[tokio::test]
+    async fn test_wasm_plugin_echoes_args_through_host_abi() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+
+        let installed = system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        assert!(installed.success, "{}", installed.message);
+        assert!(temp_dir.path().join("plugins/echo/echo.wasm").exists());
+        system.enable_plugin("echo").await.unwrap();
+        system.grant_capability("echo", Capability::TerminalWrite).unwrap();
+
+        let args = vec!["hello".to_string(), "wasm".to_string()];
+        let result = system.execute_plugin_command("echo", "echo", &args).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output, "hello wasm");
+    }
+
+    #[tokio::test]
+    async fn test_grant_and_revoke_capabilities() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+        system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        system.enable_plugin("echo").await.unwrap();
+        let args = vec!["hi".to_string()];
+
+        // Nothing is granted at install time, so the emit host call is denied
+        let denied = system.execute_plugin_command("echo", "echo", &args).await.unwrap();
+        assert!(!denied.success);
+        assert!(denied.error.unwrap().starts_with("permission_denied"));
+
+        let permissions = system.grant_capability("echo", Capability::TerminalWrite).unwrap();
+        assert_eq!(permissions.granted, vec![Capability::TerminalWrite]);
+        assert!(system.grant_capability("echo", Capability::Network("example.com".to_string())).is_err());
+        assert!(system.execute_plugin_command("echo", "echo", &args).await.unwrap().success);
+
+        // Grants survive a restart
+        let reloaded = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+        assert_eq!(reloaded.permissions["echo"].granted, vec![Capability::TerminalWrite]);
+
+        let permissions = system.revoke_capability("echo", &Capability::TerminalWrite).unwrap();
+        assert!(permissions.granted.is_empty());
+        assert_eq!(permissions.requested, vec![Capability::TerminalWrite]);
+        let denied = system.execute_plugin_command("echo", "echo", &args).await.unwrap();
+        assert!(denied.error.unwrap().starts_with("permission_denied"));
+    }
+
+    #[tokio::test]
+    async fn test_install_rejects_undeclared_capabilities() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![]);
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+
+        let installed = system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        assert!(!installed.success);
+        assert!(installed.message.contains("terminal_write"), "{}", installed.message);
+        assert!(system.get_plugin("echo").is_none());
+    }
+
+    fn set_legacy_permissions(package: &Path, permissions: Vec<PluginPermission>) {
+        let manifest_path = package.join("plugin.json");
+        let mut plugin: Plugin = serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        plugin.manifest.permissions = permissions;
+        std::fs::write(&manifest_path, serde_json::to_string(&plugin).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_legacy_permissions_imply_capabilities() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![]);
+        set_legacy_permissions(&package, vec![PluginPermission::TerminalAccess]);
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+
+        let installed = system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        assert!(installed.success, "{}", installed.message);
+        assert_eq!(system.get_permissions("echo").unwrap().requested, vec![Capability::TerminalWrite]);
+        system.enable_plugin("echo").await.unwrap();
+        system.grant_capability("echo", Capability::TerminalWrite).unwrap();
+        let result = system.execute_plugin_command("echo", "echo", &["legacy".to_string()]).await.unwrap();
+        assert_eq!(result.output, "legacy");
+
+        // Unscoped legacy permissions can't be enforced, so they are refused rather than ignored
+        let other = tempfile::tempdir().unwrap();
+        let package = write_package(other.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        set_legacy_permissions(&package, vec![PluginPermission::NetworkAccess]);
+        let mut system = PluginSystem::new(other.path().join("plugins")).unwrap();
+        let installed = system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        assert!(!installed.success);
+        assert!(installed.message.contains("NetworkAccess"), "{}", installed.message);
+    }
+
+    #[test]
+    fn test_filesystem_grants_stay_inside_the_requested_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("allowed");
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("secret")).unwrap();
+        let requested = Capability::Filesystem(root.clone());
+
+        assert!(Capability::Filesystem(root.join("logs")).within(&requested));
+        assert!(!Capability::Filesystem(root.join("../secret")).within(&requested));
+        assert!(!Capability::Filesystem(root.join("logs/../../secret")).within(&requested));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path().join("secret"), root.join("link")).unwrap();
+            assert!(!Capability::Filesystem(root.join("link")).within(&requested));
+        }
+    }
+
+    fn sign_package(package: &Path, key_pair: &ring::signature::Ed25519KeyPair) {
+        let digest = bundle_digest(package, "echo.wasm").unwrap();
+        let signature = base64::engine::general_purpose::STANDARD.encode(key_pair.sign(&digest));
+        std::fs::write(package.join(SIGNATURE_FILE), signature).unwrap();
+    }
+
+    fn publisher_key() -> (ring::signature::Ed25519KeyPair, String) {
+        use ring::signature::KeyPair;
+        let pkcs8 = ring::signature::Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new()).unwrap();
+        let key_pair = ring::signature::Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public_key = base64::engine::general_purpose::STANDARD.encode(key_pair.public_key().as_ref());
+        (key_pair, public_key)
+    }
+
+    #[tokio::test]
+    async fn test_signed_bundle_installs_as_verified() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        let (key_pair, public_key) = publisher_key();
+        sign_package(&package, &key_pair);
+
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+        system.add_trusted_key("acme", &public_key).unwrap();
+        let installed = system.install_plugin(package.to_str().unwrap(), false).await.unwrap();
+        assert!(installed.success, "{}", installed.message);
+        assert_eq!(installed.verification, Some(VerificationStatus::Verified));
+        assert_eq!(installed.publisher.as_deref(), Some("acme"));
+
+        // The signature is checked again when installed plugins are rediscovered
+        let mut reloaded = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+        reloaded.set_trusted_keys(system.trusted_keys().to_vec());
+        reloaded.initialize().await.unwrap();
+        assert_eq!(reloaded.get_plugin_info("echo").await.unwrap().verification, VerificationStatus::Verified);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_bundle_requires_allow_unsigned() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        let (key_pair, public_key) = publisher_key();
+        sign_package(&package, &key_pair);
+        let manifest = std::fs::read_to_string(package.join("plugin.json")).unwrap();
+        std::fs::write(package.join("plugin.json"), manifest.replace("Echoes its arguments", "Totally harmless")).unwrap();
+
+        // Settings handed to the plugin are covered by the signature too
+        let config_only = write_package(&temp_dir.path().join("config-only"), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        sign_package(&config_only, &key_pair);
+        std::fs::write(config_only.join(CONFIG_FILE), r#"{"target": "/etc/shadow"}"#).unwrap();
+
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+        system.add_trusted_key("acme", &public_key).unwrap();
+        let rejected = system.install_plugin(package.to_str().unwrap(), false).await.unwrap();
+        assert!(!rejected.success);
+        assert_eq!(rejected.verification, Some(VerificationStatus::Mismatched));
+        assert!(system.get_plugin("echo").is_none());
+        let rejected = system.install_plugin(config_only.to_str().unwrap(), false).await.unwrap();
+        assert_eq!(rejected.verification, Some(VerificationStatus::Mismatched));
+        assert!(system.get_plugin("echo").is_none());
+
+        let installed = system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        assert!(installed.success, "{}", installed.message);
+        let info = system.get_plugin_info("echo").await.unwrap();
+        assert_eq!(info.verification, VerificationStatus::Mismatched);
+        assert!(info.publisher.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_bundle_requires_allow_unsigned() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+
+        let rejected = system.install_plugin(package.to_str().unwrap(), false).await.unwrap();
+        assert!(!rejected.success);
+        assert_eq!(rejected.verification, Some(VerificationStatus::Unsigned));
+        assert!(rejected.message.contains("allow_unsigned"), "{}", rejected.message);
+
+        let installed = system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        assert!(installed.success, "{}", installed.message);
+        assert_eq!(system.get_plugin_info("echo").await.unwrap().verification, VerificationStatus::Unsigned);
+        assert!(system.add_trusted_key("short", "AAAA").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_git_bundle_leaves_nothing_behind() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let package = write_package(temp_dir.path(), ECHO_PLUGIN, vec![Capability::TerminalWrite]);
+        for args in [&["init", "-q"][..], &["add", "."], &["-c", "user.name=Test", "-c", "user.email=test@example.com", "commit", "-qm", "echo"]] {
+            let status = std::process::Command::new("git").args(args).current_dir(&package).status().unwrap();
+            assert!(status.success());
+        }
+        let source = || PluginSource::Git(package.to_string_lossy().to_string());
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+
+        let error = system.install_plugin_internal("echo", source(), false).await.unwrap_err();
+        assert!(error.downcast_ref::<UnverifiedPlugin>().is_some(), "{}", error);
+        assert!(!temp_dir.path().join("plugins/echo").exists());
+
+        // Nothing is left in the way of a retry
+        system.install_plugin_internal("echo", source(), true).await.unwrap();
+        assert!(temp_dir.path().join("plugins/echo/echo.wasm").exists());
+        assert!(!temp_dir.path().join("plugins/echo/.git").exists());
+    }
+
+    /// Fetches the URL given as its first argument and discards the body
+    const FETCH_PLUGIN: &str = r#"
+        (module
+          (import "nexus" "arg" (func $arg (param i32 i32 i32) (result i32)))
+          (import "nexus" "http_get" (func $http_get (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "run") (result i32)
+            (drop (call $http_get
+              (i32.const 0) (call $arg (i32.const 0) (i32.const 0) (i32.const 1024))
+              (i32.const 1024) (i32.const 1024)))
+            (i32.const 0)))
+    "#;
+
+    #[tokio::test]
+    async fn test_http_get_stops_at_the_response_size_limit() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 1024];
+                let read = std::io::Read::read(&mut stream, &mut request).unwrap();
+                let body = if request[..read].starts_with(b"GET /big ") { vec![b'x'; 2 * 1024 * 1024] } else { b"ok".to_vec() };
+                let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = std::io::Write::write_all(&mut stream, head.as_bytes());
+                let _ = std::io::Write::write_all(&mut stream, &body);
+            }
+        });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let network = Capability::Network("127.0.0.1".to_string());
+        let package = write_package(temp_dir.path(), FETCH_PLUGIN, vec![network.clone()]);
+        let mut system = PluginSystem::new(temp_dir.path().join("plugins")).unwrap();
+        system.install_plugin(package.to_str().unwrap(), true).await.unwrap();
+        system.enable_plugin("echo").await.unwrap();
+        system.grant_capability("echo", network).unwrap();
+
+        let small = system.execute_plugin_command("echo", "fetch", &[format!("{}/small", url)]).await.unwrap();
+        assert!(small.success, "{:?}", small.error);
+        let big = system.execute_plugin_command("echo", "fetch", &[format!("{}/big", url)]).await.unwrap();
+        assert!(!big.success);
+        assert!(big.error.as_deref().unwrap_or_default().contains("exceeded"), "{:?}", big.error);
+    }
 }
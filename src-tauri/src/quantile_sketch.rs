@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+/// Values closer to zero than this are counted in the zero bucket
+const MIN_INDEXABLE_VALUE: f64 = 1e-9;
+
+/// Log-bucketed quantile sketch (DDSketch).
+///
+/// Every estimate is within `relative_accuracy` of the exact quantile, using memory that grows
+/// with the logarithm of the value range rather than with the number of samples.
+#[derive(Debug, Clone)]
+pub struct QuantileSketch {
+    relative_accuracy: f64,
+    gamma: f64,
+    gamma_ln: f64,
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+    zero_count: u64,
+    count: u64,
+}
+
+impl QuantileSketch {
+    pub fn new(relative_accuracy: f64) -> Self {
+        let relative_accuracy = relative_accuracy.clamp(1e-6, 0.5);
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+        Self {
+            relative_accuracy,
+            gamma,
+            gamma_ln: gamma.ln(),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zero_count: 0,
+            count: 0,
+        }
+    }
+
+    pub fn relative_accuracy(&self) -> f64 {
+        self.relative_accuracy
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn add(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+
+        if value.abs() < MIN_INDEXABLE_VALUE {
+            self.zero_count += 1;
+        } else if value > 0.0 {
+            *self.positive.entry(self.index(value)).or_default() += 1;
+        } else {
+            *self.negative.entry(self.index(-value)).or_default() += 1;
+        }
+        self.count += 1;
+    }
+
+    /// Fold another sketch with the same accuracy into this one
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        for (&index, &count) in &other.positive {
+            *self.positive.entry(index).or_default() += count;
+        }
+        for (&index, &count) in &other.negative {
+            *self.negative.entry(index).or_default() += count;
+        }
+        self.zero_count += other.zero_count;
+        self.count += other.count;
+    }
+
+    /// Estimated value at quantile `q` (0.0-1.0), using the same rank as an exact
+    /// lookup of `sorted[(q * (n - 1)) as usize]`
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64) as u64;
+        let mut seen = 0;
+
+        // Negative buckets hold magnitudes, so the most negative values have the highest index
+        for (&index, &count) in self.negative.iter().rev() {
+            seen += count;
+            if seen > rank {
+                return Some(-self.bucket_value(index));
+            }
+        }
+
+        seen += self.zero_count;
+        if seen > rank {
+            return Some(0.0);
+        }
+
+        for (&index, &count) in &self.positive {
+            seen += count;
+            if seen > rank {
+                return Some(self.bucket_value(index));
+            }
+        }
+
+        None
+    }
+
+    fn index(&self, magnitude: f64) -> i32 {
+        (magnitude.ln() / self.gamma_ln).ceil() as i32
+    }
+
+    /// Representative of bucket `index`, which covers `(gamma^(index-1), gamma^index]`
+    fn bucket_value(&self, index: i32) -> f64 {
+        2.0 * (index as f64 * self.gamma_ln).exp() / (1.0 + self.gamma)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_within_relative_accuracy() {
+        let mut sketch = QuantileSketch::new(0.01);
+        let mut values: Vec<f64> = (1..=1000).map(|i| i as f64 * 0.5).collect();
+        values.extend([0.0, -3.0, -40.0]);
+        for value in &values {
+            sketch.add(*value);
+        }
+        values.sort_by(f64::total_cmp);
+
+        for q in [0.0, 0.001, 0.25, 0.5, 0.9, 0.99, 1.0] {
+            let exact = values[(q * (values.len() - 1) as f64) as usize];
+            let estimate = sketch.quantile(q).unwrap();
+            assert!((estimate - exact).abs() <= exact.abs() * 0.01 + 1e-12, "q={} {} vs {}", q, estimate, exact);
+        }
+    }
+
+    #[test]
+    fn test_merge_matches_single_sketch() {
+        let mut combined = QuantileSketch::new(0.02);
+        let mut left = QuantileSketch::new(0.02);
+        let mut right = QuantileSketch::new(0.02);
+        for i in 1..=200 {
+            combined.add(i as f64);
+            let half = if i % 2 == 0 { &mut left } else { &mut right };
+            half.add(i as f64);
+        }
+
+        left.merge(&right);
+        assert_eq!(left.count(), 200);
+        assert_eq!(left.quantile(0.9), combined.quantile(0.9));
+        assert!(QuantileSketch::new(0.01).quantile(0.5).is_none());
+    }
+}
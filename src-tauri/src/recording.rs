@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::error;
+use uuid::Uuid;
+
+/// Header line of an asciinema v2 cast file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    pub timestamp: i64,
+    pub env: HashMap<String, String>,
+}
+
+/// Summary returned when a recording is stopped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    pub terminal_id: String,
+    pub path: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub event_count: usize,
+}
+
+/// PTY output captured with timing for an asciinema v2 cast.
+///
+/// The header and every event are written to the cast file as they happen, so a crash
+/// loses nothing that was already recorded. Time spent paused is cut from the timeline,
+/// as `asciinema rec` does.
+#[derive(Debug)]
+pub struct CastRecording {
+    id: String,
+    terminal_id: String,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+    event_count: usize,
+    output_path: PathBuf,
+    file: File,
+    /// Set after the first failed write so a full disk logs once, not once per event
+    write_failed: bool,
+}
+
+impl CastRecording {
+    /// Start recording now, writing the cast header immediately. Without `output_path`
+    /// the cast goes to [`Self::default_path`].
+    pub fn new(terminal_id: &str, cols: u16, rows: u16, shell: &str, output_path: Option<PathBuf>) -> Result<Self> {
+        let id = Uuid::new_v4().to_string();
+        let started_at = Utc::now();
+        let mut env = HashMap::new();
+        env.insert("SHELL".to_string(), shell.to_string());
+        env.insert("TERM".to_string(), "xterm-256color".to_string());
+        let header = CastHeader {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: started_at.timestamp(),
+            env,
+        };
+
+        let output_path = output_path.unwrap_or_else(|| Self::default_path(&id));
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent).context("Failed to create recordings directory")?;
+        }
+        let mut file = File::create(&output_path)
+            .with_context(|| format!("Failed to create recording {:?}", output_path))?;
+        writeln!(file, "{}", serde_json::to_string(&header)?)
+            .with_context(|| format!("Failed to write recording {:?}", output_path))?;
+
+        Ok(Self {
+            output_path,
+            id,
+            terminal_id: terminal_id.to_string(),
+            started_at,
+            started: Instant::now(),
+            paused_at: None,
+            paused_total: Duration::ZERO,
+            event_count: 0,
+            file,
+            write_failed: false,
+        })
+    }
+
+    /// Default location for a recording: `<data dir>/nexus-terminal/recordings/<id>.cast`
+    pub fn default_path(recording_id: &str) -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("nexus-terminal")
+            .join("recordings")
+            .join(format!("{}.cast", recording_id))
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.paused_total += paused_at.elapsed();
+        }
+    }
+
+    pub fn record_output(&mut self, data: &str) {
+        self.push_event("o", data.to_string());
+    }
+
+    pub fn record_resize(&mut self, cols: u16, rows: u16) {
+        self.push_event("r", format!("{}x{}", cols, rows));
+    }
+
+    /// Finish the cast file and return a summary of the recording
+    pub fn save(self) -> Result<RecordingInfo> {
+        self.file.sync_all()
+            .with_context(|| format!("Failed to write recording to {:?}", self.output_path))?;
+
+        Ok(RecordingInfo {
+            id: self.id.clone(),
+            terminal_id: self.terminal_id.clone(),
+            path: self.output_path.to_string_lossy().to_string(),
+            started_at: self.started_at,
+            duration_secs: self.elapsed(),
+            event_count: self.event_count,
+        })
+    }
+
+    fn push_event(&mut self, kind: &'static str, data: String) {
+        if self.is_paused() {
+            return;
+        }
+        let time = self.elapsed();
+        // One write per event, so everything recorded so far survives a crash
+        let written = serde_json::to_string(&(time, kind, &data))
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(self.file.write_all(format!("{}\n", line).as_bytes())?));
+        match written {
+            Ok(()) => self.event_count += 1,
+            Err(e) if !self.write_failed => {
+                self.write_failed = true;
+                error!("Failed to write recording {:?}: {}", self.output_path, e);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Seconds since the recording started, excluding paused time
+    fn elapsed(&self) -> f64 {
+        let paused = self.paused_total + self.paused_at.map(|p| p.elapsed()).unwrap_or_default();
+        self.started.elapsed().saturating_sub(paused).as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_cast(cast: &str) -> (serde_json::Value, Vec<(f64, String, String)>) {
+        let mut lines = cast.lines();
+        let header = serde_json::from_str(lines.next().unwrap()).unwrap();
+        let events = lines.map(|l| serde_json::from_str(l).unwrap()).collect();
+        (header, events)
+    }
+
+    #[test]
+    fn test_recording_produces_valid_cast_with_resize() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.cast");
+        let mut recording = CastRecording::new("term-1", 80, 24, "/bin/bash", Some(path.clone())).unwrap();
+
+        recording.record_output("$ ls\r\n");
+        std::thread::sleep(Duration::from_millis(20));
+        recording.record_resize(120, 40);
+        recording.record_output("Cargo.toml  src\r\n");
+
+        // Already on disk before the recording is stopped
+        let (_, events) = parse_cast(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(events.len(), 3);
+
+        let info = recording.save().unwrap();
+        assert_eq!(info.event_count, 3);
+
+        let (header, events) = parse_cast(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(header["version"], 2);
+        assert_eq!(header["width"], 80);
+        assert_eq!(header["height"], 24);
+        assert_eq!(header["env"]["SHELL"], "/bin/bash");
+
+        let kinds: Vec<&str> = events.iter().map(|(_, kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, vec!["o", "r", "o"]);
+        assert_eq!(events[1].2, "120x40");
+        assert!(events[1].0 >= 0.02);
+        assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+    }
+
+    #[test]
+    fn test_paused_time_is_excluded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("paused.cast");
+        let mut recording = CastRecording::new("term-1", 80, 24, "/bin/sh", Some(path.clone())).unwrap();
+
+        recording.pause();
+        recording.record_output("hidden");
+        std::thread::sleep(Duration::from_millis(50));
+        recording.resume();
+        recording.record_output("visible");
+
+        let (_, events) = parse_cast(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].2, "visible");
+        assert!(events[0].0 < 0.05);
+    }
+}
@@ -0,0 +1,412 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Unchanged lines shown around each change in a preview diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Backups hold the replaced secrets in clear text, so unconfirmed ones are deleted after this long
+const BACKUP_RETENTION_DAYS: i64 = 7;
+
+/// One line replaced by a remediation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineEdit {
+    /// 1-based line number
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Edits to one file, tied to the content they were computed from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEdit {
+    pub path: String,
+    /// sha256 of the file when the plan was made; applying refuses to write if it has changed
+    pub original_hash: String,
+    pub edits: Vec<LineEdit>,
+}
+
+/// The exact edits a remediation makes, computed once for the preview and reused to apply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemediationPlan {
+    pub vulnerability_id: String,
+    pub summary: String,
+    pub files: Vec<FileEdit>,
+    /// Unified diff of `files`
+    pub diff: String,
+    /// What the user still has to do afterwards, such as refreshing a lockfile
+    pub follow_up: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RemediationPlan {
+    fn new(vulnerability_id: &str, summary: String, file: FileEdit, original: &str, follow_up: Option<String>) -> Self {
+        Self {
+            vulnerability_id: vulnerability_id.to_string(),
+            summary,
+            diff: unified_diff(&file.path, original, &file.edits),
+            files: vec![file],
+            follow_up,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Original contents of the files a remediation changed, saved before writing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemediationBackup {
+    pub id: String,
+    pub vulnerability_id: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub path: String,
+    pub original: String,
+    /// sha256 right after the remediation; rollback refuses to overwrite later edits
+    pub applied_hash: String,
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+async fn read_file(path: &str) -> Result<String> {
+    tokio::fs::read_to_string(path).await.with_context(|| format!("Failed to read {}", path))
+}
+
+/// Whether `path` is read by something that expands `${VAR}`: dotenv files and YAML such as compose files.
+/// Anywhere else the reference would just become the new literal value.
+fn interpolates_variables(path: &str) -> bool {
+    let name = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or_default().to_ascii_lowercase();
+    name == ".env" || name.starts_with(".env.") || name.ends_with(".env") || name.ends_with(".yml") || name.ends_with(".yaml")
+}
+
+/// Replace the hardcoded value matched by `rule` on `line` of `path` with an environment
+/// variable reference named after its key, e.g. `api_key: "${API_KEY}"`. Only offered for
+/// files that interpolate variables; secrets in source code have to be moved by hand.
+pub async fn plan_secret_removal(vulnerability_id: &str, path: &str, line: usize, rule: &Regex) -> Result<RemediationPlan> {
+    if !interpolates_variables(path) {
+        return Err(anyhow!("No automatic fix for the secret on line {} of {}; read it from the environment instead and rotate it", line, path));
+    }
+    let original = read_file(path).await?;
+    let before = original
+        .lines()
+        .nth(line.saturating_sub(1))
+        .ok_or_else(|| anyhow!("{} no longer has line {}", path, line))?;
+    let found = rule.find(before).ok_or_else(|| anyhow!("The secret on line {} of {} is gone; scan again", line, path))?;
+
+    let assignment = Regex::new(r#"(?P<key>[A-Za-z0-9_.\-]+)(?P<sep>['"]?\s*[:=]\s*)(?P<quote>['"]?)(?P<value>[^\s'"]+)"#)
+        .expect("valid assignment pattern");
+    let caps = assignment
+        .captures(found.as_str())
+        .ok_or_else(|| anyhow!("No automatic fix for the secret on line {} of {}; move it out of the file by hand", line, path))?;
+    let value = caps.name("value").expect("value group");
+    let variable: String = caps["key"]
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    let start = found.start() + value.start();
+    let end = found.start() + value.end();
+    let after = format!("{}${{{}}}{}", &before[..start], variable, &before[end..]);
+
+    let file = FileEdit {
+        path: path.to_string(),
+        original_hash: content_hash(&original),
+        edits: vec![LineEdit { line, before: before.to_string(), after }],
+    };
+    let summary = format!("Replace the hardcoded secret on line {} with ${{{}}}", line, variable);
+    let follow_up = format!("Set {} in the environment and rotate the exposed secret", variable);
+    Ok(RemediationPlan::new(vulnerability_id, summary, file, &original, Some(follow_up)))
+}
+
+/// Raise `package` to `fixed_version` where the project declares it: `requirements.txt` itself,
+/// or the `Cargo.toml`/`package.json` next to a `Cargo.lock`/`package-lock.json`
+pub async fn plan_dependency_bump(vulnerability_id: &str, lockfile: &str, package: &str, fixed_version: &str) -> Result<RemediationPlan> {
+    let lockfile_path = Path::new(lockfile);
+    let name = regex::escape(package);
+    let (manifest, pattern, follow_up) = match lockfile_path.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.lock") => (
+            lockfile_path.with_file_name("Cargo.toml"),
+            format!(r#"^(?P<head>\s*{}\s*=\s*(?:\{{[^}}]*\bversion\s*=\s*)?")(?P<req>[^"]+)""#, name),
+            Some(format!("Run `cargo update -p {}` to refresh Cargo.lock", package)),
+        ),
+        Some("package-lock.json") => (
+            lockfile_path.with_file_name("package.json"),
+            format!(r#"^(?P<head>\s*"{}"\s*:\s*")(?P<req>[^"]+)""#, name),
+            Some("Run `npm install` to refresh package-lock.json".to_string()),
+        ),
+        Some("requirements.txt") => {
+            // PyPI treats runs of -, _ and . in names as equal, in any case
+            let name = package.split(['-', '_', '.']).filter(|part| !part.is_empty()).map(regex::escape).collect::<Vec<_>>().join("[-_.]+");
+            (lockfile_path.to_path_buf(), format!(r"(?i)^(?P<head>\s*{}(?:\[[^\]]*\])?\s*===?\s*)(?P<req>[^\s;#]+)", name), None)
+        }
+        _ => return Err(anyhow!("No automatic fix for dependencies pinned in {}", lockfile)),
+    };
+    let manifest = manifest.to_string_lossy().to_string();
+    let original = read_file(&manifest).await?;
+    let pattern = Regex::new(&pattern)?;
+
+    let mut edits = Vec::new();
+    for (index, line) in original.lines().enumerate() {
+        let Some(caps) = pattern.captures(line) else {
+            continue;
+        };
+        let req = caps.name("req").expect("req group");
+        let operator: String = req.as_str().chars().take_while(|c| matches!(c, '^' | '~' | '=')).collect();
+        if req.as_str()[operator.len()..].contains([',', '<', '>', '*', '|', ' ']) {
+            return Err(anyhow!("{} requires {} \"{}\", which cannot be raised automatically", manifest, package, req.as_str()));
+        }
+        let after = format!("{}{}{}{}", &line[..req.start()], operator, fixed_version, &line[req.end()..]);
+        edits.push(LineEdit { line: index + 1, before: line.to_string(), after });
+    }
+    if edits.is_empty() {
+        return Err(anyhow!("{} is not declared directly in {}; upgrade the package that depends on it", package, manifest));
+    }
+
+    let file = FileEdit { path: manifest, original_hash: content_hash(&original), edits };
+    let summary = format!("Upgrade {} to {}", package, fixed_version);
+    Ok(RemediationPlan::new(vulnerability_id, summary, file, &original, follow_up))
+}
+
+/// `original` with each edit applied, keeping line endings; fails if an edited line differs from the plan
+fn apply_edits(path: &str, original: &str, edits: &[LineEdit]) -> Result<String> {
+    let mut updated = String::with_capacity(original.len());
+    for (index, line) in original.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']);
+        match edits.iter().find(|edit| edit.line == index + 1) {
+            Some(edit) if edit.before == content => {
+                updated.push_str(&edit.after);
+                updated.push_str(&line[content.len()..]);
+            }
+            Some(edit) => return Err(anyhow!("Line {} of {} does not match the remediation plan", edit.line, path)),
+            None => updated.push_str(line),
+        }
+    }
+    Ok(updated)
+}
+
+/// Write `plan` after checking every file is exactly as it was when the plan was made.
+/// Nothing is written unless every file checks out, and originals are saved under
+/// `backup_dir` first, readable only by the owner, so `rollback` can restore them until
+/// the remediation is confirmed or the backup expires.
+pub async fn apply(plan: &RemediationPlan, backup_dir: &Path) -> Result<RemediationBackup> {
+    let mut writes = Vec::new();
+    for file in &plan.files {
+        let original = read_file(&file.path).await?;
+        if content_hash(&original) != file.original_hash {
+            return Err(anyhow!("{} changed after the remediation was previewed; preview it again", file.path));
+        }
+        let updated = apply_edits(&file.path, &original, &file.edits)?;
+        writes.push((file.path.clone(), original, updated));
+    }
+
+    let backup = RemediationBackup {
+        id: uuid::Uuid::new_v4().to_string(),
+        vulnerability_id: plan.vulnerability_id.clone(),
+        created_at: Utc::now(),
+        files: writes
+            .iter()
+            .map(|(path, original, updated)| BackupFile { path: path.clone(), original: original.clone(), applied_hash: content_hash(updated) })
+            .collect(),
+    };
+    prune_backups(backup_dir).await;
+    write_private(&backup_path(backup_dir, &backup.id)?, &serde_json::to_vec(&backup)?)?;
+
+    for (path, _, updated) in &writes {
+        tokio::fs::write(path, updated).await.with_context(|| format!("Failed to write {}", path))?;
+    }
+    Ok(backup)
+}
+
+/// Restore the files changed by remediation `id` and discard its backup
+pub async fn rollback(backup_dir: &Path, id: &str) -> Result<RemediationBackup> {
+    let path = backup_path(backup_dir, id)?;
+    let json = tokio::fs::read_to_string(&path).await.map_err(|_| anyhow!("No backup found for remediation {}", id))?;
+    let backup: RemediationBackup = serde_json::from_str(&json)?;
+
+    for file in &backup.files {
+        let current = tokio::fs::read_to_string(&file.path).await.unwrap_or_default();
+        if content_hash(&current) != file.applied_hash {
+            return Err(anyhow!("{} was edited after the remediation; restore it by hand from {}", file.path, path.display()));
+        }
+    }
+    for file in &backup.files {
+        tokio::fs::write(&file.path, &file.original).await.with_context(|| format!("Failed to restore {}", file.path))?;
+    }
+    tokio::fs::remove_file(&path).await?;
+    Ok(backup)
+}
+
+/// Keep remediation `id` and delete its backup
+pub async fn confirm(backup_dir: &Path, id: &str) -> Result<()> {
+    let path = backup_path(backup_dir, id)?;
+    tokio::fs::remove_file(&path).await.map_err(|_| anyhow!("No backup found for remediation {}", id))
+}
+
+/// Delete backups older than `BACKUP_RETENTION_DAYS`
+async fn prune_backups(backup_dir: &Path) {
+    let Ok(mut entries) = tokio::fs::read_dir(backup_dir).await else {
+        return;
+    };
+    let cutoff = Utc::now() - chrono::Duration::days(BACKUP_RETENTION_DAYS);
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let expired = tokio::fs::read_to_string(entry.path())
+            .await
+            .ok()
+            .and_then(|json| serde_json::from_str::<RemediationBackup>(&json).ok())
+            .is_some_and(|backup| backup.created_at < cutoff);
+        if expired {
+            if let Err(e) = tokio::fs::remove_file(entry.path()).await {
+                tracing::warn!("Failed to delete expired remediation backup {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+/// Create `path` readable only by the owner, in a directory only the owner can list
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let dir = path.parent().ok_or_else(|| anyhow!("{} has no parent directory", path.display()))?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(dir)?;
+
+    let mut file = options.open(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(contents)?;
+    Ok(())
+}
+
+fn backup_path(backup_dir: &Path, id: &str) -> Result<PathBuf> {
+    // Ids come from the frontend; keep them from naming files outside the backup directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow!("Invalid remediation id: {}", id));
+    }
+    Ok(backup_dir.join(format!("{}.json", id)))
+}
+
+/// Unified diff for line replacements, with `DIFF_CONTEXT` lines around each change
+pub fn unified_diff(path: &str, original: &str, edits: &[LineEdit]) -> String {
+    let lines: Vec<&str> = original.lines().collect();
+    let mut edits: Vec<&LineEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| edit.line);
+
+    // Changes close enough to share context go in one hunk
+    let mut hunks: Vec<Vec<&LineEdit>> = Vec::new();
+    for edit in edits {
+        match hunks.last_mut() {
+            Some(hunk) if edit.line - hunk.last().expect("hunks are never empty").line <= 2 * DIFF_CONTEXT => hunk.push(edit),
+            _ => hunks.push(vec![edit]),
+        }
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", path, path);
+    for hunk in hunks {
+        let first = hunk[0].line.saturating_sub(DIFF_CONTEXT).max(1);
+        let last = (hunk[hunk.len() - 1].line + DIFF_CONTEXT).min(lines.len());
+        let count = last + 1 - first;
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n", first, count, first, count));
+        for number in first..=last {
+            match hunk.iter().find(|edit| edit.line == number) {
+                Some(edit) => diff.push_str(&format!("-{}\n+{}\n", edit.before, edit.after)),
+                None => diff.push_str(&format!(" {}\n", lines[number - 1])),
+            }
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dependency_bump_edits_the_declaring_manifest() {
+        let project = tempfile::tempdir().unwrap();
+        let cargo_toml = project.path().join("Cargo.toml");
+        std::fs::write(
+            &cargo_toml,
+            "[package]\nname = \"app\"\n\n[dependencies]\ntokio = { version = \"^1.18\", features = [\"full\"] }\nserde = \"1.0\"\n",
+        )
+        .unwrap();
+        let lockfile = project.path().join("Cargo.lock").to_string_lossy().to_string();
+
+        let plan = plan_dependency_bump("v1", &lockfile, "tokio", "1.18.4").await.unwrap();
+        assert_eq!(plan.files[0].edits.len(), 1);
+        assert_eq!(plan.files[0].edits[0].after, "tokio = { version = \"^1.18.4\", features = [\"full\"] }");
+        assert!(plan.follow_up.as_deref().unwrap().contains("cargo update -p tokio"));
+        assert!(plan_dependency_bump("v1", &lockfile, "mio", "0.8.11").await.is_err());
+
+        let requirements = project.path().join("requirements.txt");
+        std::fs::write(&requirements, "Django==3.2.0\npy_yaml[extra] == 5.3 ; python_version > '3'\n").unwrap();
+        let plan = plan_dependency_bump("v2", &requirements.to_string_lossy(), "PY-YAML", "5.4.0").await.unwrap();
+        assert_eq!(plan.files[0].edits[0].after, "py_yaml[extra] == 5.4.0 ; python_version > '3'");
+
+        std::fs::write(project.path().join("package.json"), "{\n  \"dependencies\": {\n    \"lodash\": \">=4.0.0 <5\"\n  }\n}\n").unwrap();
+        let lockfile = project.path().join("package-lock.json").to_string_lossy().to_string();
+        assert!(plan_dependency_bump("v3", &lockfile, "lodash", "4.17.21").await.unwrap_err().to_string().contains("cannot be raised"));
+    }
+
+    #[tokio::test]
+    async fn test_secret_removal_is_only_offered_where_variables_expand() {
+        let project = tempfile::tempdir().unwrap();
+        let rule = Regex::new(r#"(?i)password\s*[:=]\s*['"]?[^\s'"]{8,}['"]?"#).unwrap();
+        let source = project.path().join("settings.py");
+        std::fs::write(&source, "password = \"hunter2hunter2\"\n").unwrap();
+        let refused = plan_secret_removal("v1", &source.to_string_lossy(), 1, &rule).await.unwrap_err();
+        assert!(refused.to_string().contains("No automatic fix"));
+
+        let compose = project.path().join("docker-compose.yml");
+        std::fs::write(&compose, "services:\n  db:\n    environment:\n      password: \"hunter2hunter2\"\n").unwrap();
+        let plan = plan_secret_removal("v1", &compose.to_string_lossy(), 4, &rule).await.unwrap();
+        assert_eq!(plan.files[0].edits[0].after, "      password: \"${PASSWORD}\"");
+    }
+
+    #[tokio::test]
+    async fn test_backups_are_private_and_expire() {
+        let project = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let backup_dir = data.path().join("remediation-backups");
+        let env = project.path().join(".env");
+        std::fs::write(&env, "PASSWORD=hunter2hunter2\n").unwrap();
+        let rule = Regex::new(r"PASSWORD=\S+").unwrap();
+
+        let plan = plan_secret_removal("v1", &env.to_string_lossy(), 1, &rule).await.unwrap();
+        let backup = apply(&plan, &backup_dir).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&env).unwrap(), "PASSWORD=${PASSWORD}\n");
+        let path = backup_path(&backup_dir, &backup.id).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert_eq!(std::fs::metadata(&backup_dir).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+
+        confirm(&backup_dir, &backup.id).await.unwrap();
+        assert!(!path.exists());
+        assert!(rollback(&backup_dir, &backup.id).await.is_err());
+
+        // An unconfirmed backup is dropped once it is older than the retention period
+        let stale = RemediationBackup { created_at: Utc::now() - chrono::Duration::days(BACKUP_RETENTION_DAYS + 1), ..backup };
+        let stale_path = backup_path(&backup_dir, &stale.id).unwrap();
+        std::fs::write(&stale_path, serde_json::to_vec(&stale).unwrap()).unwrap();
+        std::fs::write(&env, "PASSWORD=hunter2hunter2\n").unwrap();
+        let plan = plan_secret_removal("v1", &env.to_string_lossy(), 1, &rule).await.unwrap();
+        let fresh = apply(&plan, &backup_dir).await.unwrap();
+        assert!(!stale_path.exists());
+        assert!(backup_path(&backup_dir, &fresh.id).unwrap().exists());
+    }
+}
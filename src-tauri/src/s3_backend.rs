@@ -0,0 +1,642 @@
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use aws_sdk_s3::config::{
+    BehaviorVersion, Credentials, Region, RequestChecksumCalculation, ResponseChecksumValidation,
+};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, Object, ServerSideEncryption};
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Uploads larger than this are split into parts of this size
+pub const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Where and as whom to talk to an S3-compatible store (AWS S3, MinIO, R2)
+#[derive(Clone)]
+pub struct S3Settings {
+    pub bucket: String,
+    /// Custom endpoint such as `http://localhost:9000`; implies path-style addressing
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+    /// Ask the store to encrypt objects at rest (SSE-S3)
+    pub server_side_encryption: bool,
+}
+
+/// Object as returned by [`S3Backend::list`], with its user metadata
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct S3Backend {
+    client: Client,
+    bucket: String,
+    part_size: usize,
+    server_side_encryption: Option<ServerSideEncryption>,
+}
+
+impl S3Backend {
+    pub fn new(settings: S3Settings) -> Self {
+        let credentials = Credentials::new(
+            settings.access_key,
+            settings.secret_key,
+            settings.session_token,
+            None,
+            "nexus-terminal",
+        );
+        // Checksums only when required: several S3-compatible stores reject aws-chunked uploads
+        let mut builder = aws_sdk_s3::config::Builder::new()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(settings.region.unwrap_or_else(|| "us-east-1".to_string())))
+            .credentials_provider(credentials)
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
+        if let Some(endpoint) = settings.endpoint {
+            builder = builder.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Self {
+            client: Client::from_conf(builder.build()),
+            bucket: settings.bucket,
+            part_size: DEFAULT_PART_SIZE,
+            server_side_encryption: settings.server_side_encryption.then_some(ServerSideEncryption::Aes256),
+        }
+    }
+
+    /// Split uploads into parts of this many bytes; S3 requires at least 5 MiB
+    pub fn with_part_size(mut self, part_size: usize) -> Self {
+        self.part_size = part_size.max(1);
+        self
+    }
+
+    /// Check the bucket exists and the credentials can reach it
+    pub async fn check(&self) -> Result<()> {
+        self.client
+            .head_bucket()
+            .bucket(&self.bucket)
+            .send()
+            .await
+            .with_context(|| format!("Cannot access bucket {}", self.bucket))?;
+        Ok(())
+    }
+
+    /// Upload `data`, multipart when it exceeds the part size, returning the object's ETag.
+    ///
+    /// Each request carries a `Content-MD5` the store checks before accepting it; ETags aren't
+    /// compared since they aren't MD5 digests for SSE-KMS and SSE-C objects.
+    pub async fn put(&self, key: &str, data: Vec<u8>, metadata: HashMap<String, String>) -> Result<String> {
+        if data.len() > self.part_size {
+            return self.put_multipart(key, data, metadata).await;
+        }
+
+        let output = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_metadata(Some(metadata))
+            .set_server_side_encryption(self.server_side_encryption.clone())
+            .content_md5(content_md5(&data))
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload {}", key))?;
+        Ok(unquote(output.e_tag().unwrap_or_default()))
+    }
+
+    async fn put_multipart(&self, key: &str, data: Vec<u8>, metadata: HashMap<String, String>) -> Result<String> {
+        let upload = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_metadata(Some(metadata))
+            .set_server_side_encryption(self.server_side_encryption.clone())
+            .send()
+            .await
+            .with_context(|| format!("Failed to start multipart upload of {}", key))?;
+        let upload_id = upload
+            .upload_id()
+            .ok_or_else(|| anyhow!("Multipart upload of {} returned no upload id", key))?
+            .to_string();
+
+        match self.upload_parts(key, &upload_id, &data).await {
+            Ok(etag) => Ok(etag),
+            Err(e) => {
+                let _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(&upload_id)
+                    .send()
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn upload_parts(&self, key: &str, upload_id: &str, data: &[u8]) -> Result<String> {
+        let mut parts = Vec::new();
+        for (index, chunk) in data.chunks(self.part_size).enumerate() {
+            let part_number = index as i32 + 1;
+            let output = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .content_md5(content_md5(chunk))
+                .body(ByteStream::from(chunk.to_vec()))
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload part {} of {}", part_number, key))?;
+
+            let etag = unquote(output.e_tag().unwrap_or_default());
+            parts.push(CompletedPart::builder().part_number(part_number).e_tag(etag).build());
+        }
+
+        let output = self
+            .client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+            .await
+            .with_context(|| format!("Failed to complete multipart upload of {}", key))?;
+        Ok(unquote(output.e_tag().unwrap_or_default()))
+    }
+
+    /// Objects under `prefix`, with the user metadata of each
+    pub async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        for object in self.list_objects(prefix).await? {
+            let Some(key) = object.key() else { continue };
+            let head = self
+                .client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send()
+                .await
+                .with_context(|| format!("Failed to read metadata of {}", key))?;
+            objects.push(StoredObject {
+                key: key.to_string(),
+                size: object.size().unwrap_or_default().max(0) as u64,
+                last_modified: object.last_modified().and_then(|t| DateTime::from_timestamp(t.secs(), 0)),
+                metadata: head.metadata().cloned().unwrap_or_default(),
+            });
+        }
+        Ok(objects)
+    }
+
+    /// Keys under `prefix`, without fetching any metadata
+    pub async fn list_keys(&self, prefix: &str) -> Result<HashSet<String>> {
+        Ok(self
+            .list_objects(prefix)
+            .await?
+            .iter()
+            .filter_map(|object| object.key().map(str::to_string))
+            .collect())
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<Object>> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let page = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .with_context(|| format!("Failed to list {} in bucket {}", prefix, self.bucket))?;
+            objects.extend(page.contents().iter().cloned());
+
+            match page.next_continuation_token() {
+                Some(token) if page.is_truncated().unwrap_or(false) => continuation_token = Some(token.to_string()),
+                _ => break,
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Download an object and its user metadata
+    pub async fn get(&self, key: &str) -> Result<(Vec<u8>, HashMap<String, String>)> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", key))?;
+        let metadata = output.metadata().cloned().unwrap_or_default();
+        let data = output
+            .body
+            .collect()
+            .await
+            .with_context(|| format!("Failed to read {}", key))?
+            .into_bytes()
+            .to_vec();
+        Ok((data, metadata))
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to delete {}", key))?;
+        Ok(())
+    }
+}
+
+/// Base64 MD5 digest for the `Content-MD5` header
+fn content_md5(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(md5::compute(data).0)
+}
+
+fn unquote(etag: &str) -> String {
+    etag.trim_matches('"').to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[derive(Debug, Clone)]
+    pub struct MockObject {
+        pub data: Vec<u8>,
+        pub metadata: HashMap<String, String>,
+        pub etag: String,
+        pub last_modified: DateTime<Utc>,
+        /// `x-amz-server-side-encryption` the object was stored with
+        pub encryption: Option<String>,
+    }
+
+    #[derive(Default)]
+    struct MockUpload {
+        key: String,
+        metadata: HashMap<String, String>,
+        encryption: Option<String>,
+        parts: HashMap<u32, Vec<u8>>,
+    }
+
+    /// Just enough of the S3 REST API, path-style, to exercise [`S3Backend`]
+    #[derive(Clone, Default)]
+    pub struct MockS3 {
+        pub objects: Arc<Mutex<HashMap<String, MockObject>>>,
+        uploads: Arc<Mutex<HashMap<String, MockUpload>>>,
+        /// Answer uploads with an ETag that isn't the data's MD5, as for SSE-KMS objects
+        pub opaque_etags: Arc<AtomicBool>,
+        /// Flip a byte of every uploaded body, as if it were damaged in transit
+        pub corrupt_uploads: Arc<AtomicBool>,
+        pub multipart_uploads: Arc<Mutex<u32>>,
+        pub list_requests: Arc<Mutex<u32>>,
+    }
+
+    impl MockS3 {
+        /// Serve on a local port, returning the endpoint URL
+        pub async fn start() -> (Self, String) {
+            let mock = Self::default();
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let endpoint = format!("http://{}", listener.local_addr().unwrap());
+            let server = mock.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let server = server.clone();
+                    tokio::spawn(async move {
+                        let (read, mut write) = stream.into_split();
+                        let mut reader = BufReader::new(read);
+                        while let Some(request) = read_request(&mut reader).await {
+                            let response = server.handle(request);
+                            if write.write_all(&response).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            });
+            (mock, endpoint)
+        }
+
+        pub fn settings(endpoint: &str) -> S3Settings {
+            S3Settings {
+                bucket: "backups".to_string(),
+                endpoint: Some(endpoint.to_string()),
+                region: None,
+                access_key: "minio".to_string(),
+                secret_key: "minio-secret".to_string(),
+                session_token: None,
+                server_side_encryption: false,
+            }
+        }
+
+        fn etag(&self, etag: String) -> String {
+            if self.opaque_etags.load(Ordering::SeqCst) {
+                "00000000000000000000000000000000".to_string()
+            } else {
+                etag
+            }
+        }
+
+        fn handle(&self, mut request: MockRequest) -> Vec<u8> {
+            if self.corrupt_uploads.load(Ordering::SeqCst) {
+                if let Some(byte) = request.body.first_mut() {
+                    *byte ^= 0xff;
+                }
+            }
+            if request.content_md5.as_ref().is_some_and(|md5| *md5 != content_md5(&request.body)) {
+                let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>BadDigest</Code><Message>Content-MD5 mismatch</Message></Error>";
+                return response(400, &[], body.as_bytes().to_vec());
+            }
+            let mut segments = request.path.trim_start_matches('/').splitn(2, '/');
+            let _bucket = segments.next().unwrap_or_default();
+            let key = percent_decode(segments.next().unwrap_or_default());
+            let query = &request.query;
+
+            match (request.method.as_str(), key.is_empty()) {
+                ("HEAD", true) => response(200, &[], Vec::new()),
+                ("GET", true) => {
+                    *self.list_requests.lock().unwrap() += 1;
+                    let prefix = query.get("prefix").cloned().unwrap_or_default();
+                    let objects = self.objects.lock().unwrap();
+                    let mut keys: Vec<&String> = objects.keys().filter(|k| k.starts_with(&prefix)).collect();
+                    keys.sort();
+                    let contents: String = keys
+                        .iter()
+                        .map(|k| {
+                            let object = &objects[*k];
+                            format!(
+                                "<Contents><Key>{}</Key><Size>{}</Size><LastModified>{}</LastModified><ETag>\"{}\"</ETag></Contents>",
+                                k,
+                                object.data.len(),
+                                object.last_modified.format("%Y-%m-%dT%H:%M:%S.000Z"),
+                                object.etag
+                            )
+                        })
+                        .collect();
+                    let body = format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><Name>backups</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount><IsTruncated>false</IsTruncated>{}</ListBucketResult>",
+                        prefix,
+                        keys.len(),
+                        contents
+                    );
+                    response(200, &[], body.into_bytes())
+                }
+                ("PUT", false) if query.contains_key("uploadId") => {
+                    let part_number: u32 = query["partNumber"].parse().unwrap();
+                    let etag = self.etag(format!("{:x}", md5::compute(&request.body)));
+                    let mut uploads = self.uploads.lock().unwrap();
+                    uploads.get_mut(&query["uploadId"]).unwrap().parts.insert(part_number, request.body);
+                    response(200, &[("ETag", format!("\"{}\"", etag))], Vec::new())
+                }
+                ("PUT", false) => {
+                    let data = request.body;
+                    let etag = format!("{:x}", md5::compute(&data));
+                    let object = MockObject {
+                        data,
+                        metadata: request.metadata,
+                        etag: etag.clone(),
+                        last_modified: Utc::now(),
+                        encryption: request.encryption,
+                    };
+                    self.objects.lock().unwrap().insert(key, object);
+                    response(200, &[("ETag", format!("\"{}\"", self.etag(etag)))], Vec::new())
+                }
+                ("POST", false) if query.contains_key("uploads") => {
+                    let upload_id = uuid::Uuid::new_v4().to_string();
+                    *self.multipart_uploads.lock().unwrap() += 1;
+                    let upload = MockUpload {
+                        key: key.clone(),
+                        metadata: request.metadata,
+                        encryption: request.encryption,
+                        parts: HashMap::new(),
+                    };
+                    self.uploads.lock().unwrap().insert(upload_id.clone(), upload);
+                    let body = format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><InitiateMultipartUploadResult><Bucket>backups</Bucket><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+                        key, upload_id
+                    );
+                    response(200, &[], body.into_bytes())
+                }
+                ("POST", false) => {
+                    let upload = self.uploads.lock().unwrap().remove(&query["uploadId"]).unwrap();
+                    let mut numbers: Vec<u32> = upload.parts.keys().copied().collect();
+                    numbers.sort();
+                    let mut data = Vec::new();
+                    let mut digests = Vec::new();
+                    for number in &numbers {
+                        data.extend_from_slice(&upload.parts[number]);
+                        digests.extend_from_slice(&md5::compute(&upload.parts[number]).0);
+                    }
+                    let etag = format!("{:x}-{}", md5::compute(&digests), numbers.len());
+                    let object = MockObject {
+                        data,
+                        metadata: upload.metadata,
+                        etag: etag.clone(),
+                        last_modified: Utc::now(),
+                        encryption: upload.encryption,
+                    };
+                    self.objects.lock().unwrap().insert(upload.key.clone(), object);
+                    let body = format!(
+                        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><CompleteMultipartUploadResult><Bucket>backups</Bucket><Key>{}</Key><ETag>\"{}\"</ETag></CompleteMultipartUploadResult>",
+                        upload.key,
+                        self.etag(etag)
+                    );
+                    response(200, &[], body.into_bytes())
+                }
+                ("DELETE", false) => {
+                    match query.get("uploadId") {
+                        Some(upload_id) => self.uploads.lock().unwrap().remove(upload_id).map(|_| ()),
+                        None => self.objects.lock().unwrap().remove(&key).map(|_| ()),
+                    };
+                    response(204, &[], Vec::new())
+                }
+                (method @ ("GET" | "HEAD"), false) => {
+                    let Some(object) = self.objects.lock().unwrap().get(&key).cloned() else {
+                        let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>NoSuchKey</Code><Message>Not found</Message></Error>";
+                        return response(404, &[], body.as_bytes().to_vec());
+                    };
+                    let mut headers = vec![
+                        ("ETag", format!("\"{}\"", object.etag)),
+                        ("Last-Modified", object.last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+                    ];
+                    let meta_headers: Vec<(String, String)> = object
+                        .metadata
+                        .iter()
+                        .map(|(k, v)| (format!("x-amz-meta-{}", k), v.clone()))
+                        .collect();
+                    headers.extend(meta_headers.iter().map(|(k, v)| (k.as_str(), v.clone())));
+                    if method == "HEAD" {
+                        headers.push(("Content-Length", object.data.len().to_string()));
+                        head_response(&headers)
+                    } else {
+                        response(200, &headers, object.data)
+                    }
+                }
+                _ => response(400, &[], Vec::new()),
+            }
+        }
+    }
+
+    struct MockRequest {
+        method: String,
+        path: String,
+        query: HashMap<String, String>,
+        metadata: HashMap<String, String>,
+        content_md5: Option<String>,
+        encryption: Option<String>,
+        body: Vec<u8>,
+    }
+
+    async fn read_request<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Option<MockRequest> {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await.ok()? == 0 {
+            return None;
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?.to_string();
+
+        let mut content_length = 0;
+        let mut metadata = HashMap::new();
+        let mut content_md5 = None;
+        let mut encryption = None;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.ok()?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':')?;
+            let name = name.trim().to_lowercase();
+            if name == "content-length" {
+                content_length = value.trim().parse().ok()?;
+            } else if name == "content-md5" {
+                content_md5 = Some(value.trim().to_string());
+            } else if name == "x-amz-server-side-encryption" {
+                encryption = Some(value.trim().to_string());
+            } else if let Some(meta) = name.strip_prefix("x-amz-meta-") {
+                metadata.insert(meta.to_string(), value.trim().to_string());
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await.ok()?;
+
+        let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(k), percent_decode(v))
+            })
+            .collect();
+        Some(MockRequest { method, path: path.to_string(), query, metadata, content_md5, encryption, body })
+    }
+
+    fn percent_decode(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                if let Ok(byte) = u8::from_str_radix(&value[i + 1..i + 3], 16) {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+        String::from_utf8_lossy(&decoded).to_string()
+    }
+
+    fn response(status: u16, headers: &[(&str, String)], body: Vec<u8>) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {} OK\r\nContent-Length: {}\r\n", status, body.len());
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut bytes = head.into_bytes();
+        bytes.extend(body);
+        bytes
+    }
+
+    fn head_response(headers: &[(&str, String)]) -> Vec<u8> {
+        let mut head = "HTTP/1.1 200 OK\r\n".to_string();
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        head.into_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_round_trips() {
+        let (mock, endpoint) = MockS3::start().await;
+        let backend = S3Backend::new(MockS3::settings(&endpoint)).with_part_size(1024);
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let metadata = HashMap::from([("backup-id".to_string(), "b1".to_string())]);
+
+        let etag = backend.put("nexus/b1", data.clone(), metadata).await.unwrap();
+        assert!(etag.ends_with("-5"), "{}", etag);
+        assert_eq!(*mock.multipart_uploads.lock().unwrap(), 1);
+
+        let (downloaded, metadata) = backend.get("nexus/b1").await.unwrap();
+        assert_eq!(downloaded, data);
+        assert_eq!(metadata["backup-id"], "b1");
+        let listed = backend.list("nexus/").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].size, 5000);
+    }
+
+    #[tokio::test]
+    async fn test_damaged_uploads_are_rejected_by_the_store() {
+        let (mock, endpoint) = MockS3::start().await;
+        let backend = S3Backend::new(MockS3::settings(&endpoint)).with_part_size(1024);
+        mock.corrupt_uploads.store(true, Ordering::SeqCst);
+
+        assert!(backend.put("nexus/b2", b"hello".to_vec(), HashMap::new()).await.is_err());
+        assert!(backend.put("nexus/b3", vec![7; 3000], HashMap::new()).await.is_err());
+        assert!(mock.objects.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_uploads_with_opaque_etags_are_kept() {
+        let (mock, endpoint) = MockS3::start().await;
+        let settings = S3Settings { server_side_encryption: true, ..MockS3::settings(&endpoint) };
+        let backend = S3Backend::new(settings).with_part_size(1024);
+        mock.opaque_etags.store(true, Ordering::SeqCst);
+
+        backend.put("nexus/small", b"hello".to_vec(), HashMap::new()).await.unwrap();
+        backend.put("nexus/large", vec![7; 3000], HashMap::new()).await.unwrap();
+        let objects = mock.objects.lock().unwrap();
+        assert_eq!(objects.len(), 2);
+        assert!(objects.values().all(|object| object.encryption.as_deref() == Some("AES256")));
+    }
+}
@@ -0,0 +1,523 @@
+use crate::web_scraper::{DownloadedFile, ScrapingResult};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+use uuid::Uuid;
+
+/// Artifact formats a finished scraping job can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Every page converted to Markdown, one section per source URL
+    Markdown,
+    /// The job result with each page's saved content
+    Json,
+    /// An ISO 28500 web archive with one resource record per page
+    Warc,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            "warc" => Ok(Self::Warc),
+            other => Err(anyhow!("Unknown export format '{}'; use markdown, json or warc", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub format: ExportFormat,
+    pub output_path: String,
+    pub pages: usize,
+    /// Pages the job failed to fetch plus saved pages that could no longer be read
+    pub failed_pages: usize,
+    pub bytes_written: u64,
+}
+
+/// A saved page as read back for export
+struct ExportedPage<'a> {
+    file: &'a DownloadedFile,
+    content: std::result::Result<Vec<u8>, String>,
+}
+
+/// Write `result` to `output_path` in `format`
+pub async fn export_scrape_result(result: &ScrapingResult, format: ExportFormat, output_path: &str) -> Result<ExportSummary> {
+    let mut pages = Vec::with_capacity(result.downloaded_files.len());
+    for file in &result.downloaded_files {
+        let content = tokio::fs::read(&file.local_path).await.map_err(|e| e.to_string());
+        pages.push(ExportedPage { file, content });
+    }
+
+    let bytes = match format {
+        ExportFormat::Markdown => markdown_bundle(result, &pages).into_bytes(),
+        ExportFormat::Json => serde_json::to_vec_pretty(&json_dump(result, &pages))?,
+        ExportFormat::Warc => warc_archive(result, &pages),
+    };
+
+    if let Some(parent) = Path::new(output_path).parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(output_path, &bytes)
+        .await
+        .with_context(|| format!("Failed to write {}", output_path))?;
+
+    let unreadable = pages.iter().filter(|page| page.content.is_err()).count();
+    Ok(ExportSummary {
+        format,
+        output_path: output_path.to_string(),
+        pages: pages.len() - unreadable,
+        failed_pages: result.errors.len() + unreadable,
+        bytes_written: bytes.len() as u64,
+    })
+}
+
+fn is_html(file: &DownloadedFile) -> bool {
+    file.mime_type.contains("html") || file.local_path.ends_with(".html") || file.local_path.ends_with(".htm")
+}
+
+fn markdown_bundle(result: &ScrapingResult, pages: &[ExportedPage]) -> String {
+    let mut out = format!(
+        "# Scraping job {}\n\nExported {} from {} pages; {} failed.\n",
+        result.id,
+        Utc::now().to_rfc3339(),
+        pages.len(),
+        result.errors.len()
+    );
+
+    for page in pages {
+        out.push_str(&format!("\n## {}\n\n", page.file.url));
+        match &page.content {
+            Ok(bytes) if is_html(page.file) => {
+                let base = Url::parse(&page.file.url).ok();
+                // Nest the page's own headings under its URL heading
+                out.push_str(&convert_html(&String::from_utf8_lossy(bytes), base.as_ref(), 2));
+            }
+            Ok(_) => out.push_str(&format!("_{} content saved at `{}`_", page.file.mime_type, page.file.local_path)),
+            Err(e) => out.push_str(&format!("_Content unavailable: {}_", e)),
+        }
+        out.push('\n');
+    }
+
+    if !result.errors.is_empty() {
+        out.push_str("\n## Failed pages\n\n");
+        for error in &result.errors {
+            out.push_str(&format!("- <{}>: {}\n", error.url, error.error));
+        }
+    }
+    out
+}
+
+fn json_dump(result: &ScrapingResult, pages: &[ExportedPage]) -> serde_json::Value {
+    let pages: Vec<serde_json::Value> = pages
+        .iter()
+        .map(|page| {
+            let mut entry = serde_json::to_value(page.file).unwrap_or_default();
+            match &page.content {
+                Ok(bytes) => entry["content"] = String::from_utf8_lossy(bytes).into(),
+                Err(e) => entry["error"] = e.as_str().into(),
+            }
+            entry
+        })
+        .collect();
+    serde_json::json!({ "job": result, "pages": pages })
+}
+
+fn warc_record(out: &mut Vec<u8>, headers: &[(&str, String)], block: &[u8]) {
+    use sha2::{Digest, Sha256};
+
+    out.extend_from_slice(b"WARC/1.1\r\n");
+    let mut fields: Vec<(&str, String)> = vec![
+        ("WARC-Record-ID", format!("<urn:uuid:{}>", Uuid::new_v4())),
+        ("WARC-Block-Digest", format!("sha256:{:x}", Sha256::digest(block))),
+        ("Content-Length", block.len().to_string()),
+    ];
+    fields.splice(0..0, headers.iter().cloned());
+    for (name, value) in fields {
+        out.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(block);
+    out.extend_from_slice(b"\r\n\r\n");
+}
+
+fn warc_archive(result: &ScrapingResult, pages: &[ExportedPage]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let now = Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+    let info = format!(
+        "software: Nexus Terminal {}\r\nformat: WARC File Format 1.1\r\njob: {}\r\n",
+        env!("CARGO_PKG_VERSION"),
+        result.id
+    );
+    warc_record(
+        &mut out,
+        &[
+            ("WARC-Type", "warcinfo".to_string()),
+            ("WARC-Date", now.clone()),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ],
+        info.as_bytes(),
+    );
+
+    for page in pages {
+        let date = page.file.timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        match &page.content {
+            Ok(bytes) => warc_record(
+                &mut out,
+                &[
+                    ("WARC-Type", "resource".to_string()),
+                    ("WARC-Target-URI", page.file.url.clone()),
+                    ("WARC-Date", date),
+                    ("Content-Type", page.file.mime_type.clone()),
+                ],
+                bytes,
+            ),
+            Err(e) => warc_failure(&mut out, &page.file.url, &date, e),
+        }
+    }
+
+    for error in &result.errors {
+        let date = error.timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        warc_failure(&mut out, &error.url, &date, &error.error);
+    }
+    out
+}
+
+/// Pages without content are kept as metadata records naming the error
+fn warc_failure(out: &mut Vec<u8>, url: &str, date: &str, error: &str) {
+    warc_record(
+        out,
+        &[
+            ("WARC-Type", "metadata".to_string()),
+            ("WARC-Target-URI", url.to_string()),
+            ("WARC-Date", date.to_string()),
+            ("Content-Type", "application/warc-fields".to_string()),
+        ],
+        format!("fetch-error: {}\r\n", error.replace(['\r', '\n'], " ")).as_bytes(),
+    );
+}
+
+/// Convert an HTML page to Markdown, keeping headings, links, lists, emphasis and code.
+/// Relative links are resolved against `base_url` when given.
+pub fn html_to_markdown(html: &str, base_url: Option<&Url>) -> String {
+    convert_html(html, base_url, 0)
+}
+
+/// `html_to_markdown` with every heading moved `heading_offset` levels down
+fn convert_html(html: &str, base_url: Option<&Url>, heading_offset: usize) -> String {
+    let document = Html::parse_document(html);
+    let body = Selector::parse("body")
+        .ok()
+        .and_then(|selector| document.select(&selector).next())
+        .unwrap_or_else(|| document.root_element());
+
+    let mut converter = MarkdownConverter { out: String::new(), base_url, heading_offset, lists: Vec::new() };
+    converter.children(body);
+    let mut markdown = converter.out.trim().to_string();
+    markdown.push('\n');
+    markdown
+}
+
+struct MarkdownConverter<'a> {
+    out: String,
+    base_url: Option<&'a Url>,
+    heading_offset: usize,
+    /// Open lists, innermost last; `Some(n)` is an ordered list whose next item is `n`
+    lists: Vec<Option<usize>>,
+}
+
+impl MarkdownConverter<'_> {
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Inline text with HTML whitespace collapsed
+    fn text(&mut self, text: &str) {
+        let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mid_line = !self.out.is_empty() && !self.out.ends_with([' ', '\n']);
+        if text.starts_with(char::is_whitespace) && mid_line {
+            self.out.push(' ');
+        }
+        self.out.push_str(&words);
+        if text.ends_with(char::is_whitespace) && !words.is_empty() {
+            self.out.push(' ');
+        }
+    }
+
+    fn end_line(&mut self) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// Separate the next block from the previous one with a blank line
+    fn block_break(&mut self) {
+        self.end_line();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn wrapped(&mut self, element: ElementRef, marker: &str) {
+        let text = collapse(element);
+        if !text.is_empty() {
+            self.out.push_str(&format!("{}{}{}", marker, text, marker));
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        match name {
+            "script" | "style" | "head" | "noscript" | "template" | "svg" => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = (name[1..].parse().unwrap_or(1) + self.heading_offset).min(6);
+                self.block_break();
+                self.out.push_str(&format!("{} {}", "#".repeat(level), collapse(element)));
+                self.block_break();
+            }
+            "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "nav" | "aside" | "table" | "tr"
+            | "figure" => {
+                if self.lists.is_empty() {
+                    self.block_break();
+                    self.children(element);
+                    self.block_break();
+                } else {
+                    self.children(element);
+                }
+            }
+            "br" => {
+                self.end_line();
+            }
+            "hr" => {
+                self.block_break();
+                self.out.push_str("---");
+                self.block_break();
+            }
+            "a" => {
+                let text = collapse(element);
+                match element.value().attr("href").map(|href| self.resolve(href)) {
+                    Some(href) if !text.is_empty() => self.out.push_str(&format!("[{}]({})", text, href)),
+                    _ => self.out.push_str(&text),
+                }
+            }
+            "img" => {
+                if let Some(src) = element.value().attr("src") {
+                    let alt = element.value().attr("alt").unwrap_or("");
+                    let src = self.resolve(src);
+                    self.out.push_str(&format!("![{}]({})", alt, src));
+                }
+            }
+            "strong" | "b" => self.wrapped(element, "**"),
+            "em" | "i" => self.wrapped(element, "_"),
+            "code" => {
+                let code: String = element.text().collect();
+                let fence = if code.contains('`') { "``" } else { "`" };
+                self.out.push_str(&format!("{}{}{}", fence, code, fence));
+            }
+            "pre" => {
+                let code: String = element.text().collect();
+                let language = Selector::parse("code")
+                    .ok()
+                    .and_then(|selector| element.select(&selector).next())
+                    .and_then(|code| code.value().classes().find_map(|class| class.strip_prefix("language-")))
+                    .unwrap_or("");
+                self.block_break();
+                self.out.push_str(&format!("```{}\n{}\n```", language, code.trim_end_matches('\n')));
+                self.block_break();
+            }
+            "blockquote" => {
+                let mut inner = MarkdownConverter {
+                    out: String::new(),
+                    base_url: self.base_url,
+                    heading_offset: self.heading_offset,
+                    lists: Vec::new(),
+                };
+                inner.children(element);
+                self.block_break();
+                for line in inner.out.trim().lines() {
+                    self.out.push_str(&format!("> {}\n", line).replace(">  \n", ">\n"));
+                }
+                self.block_break();
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block_break();
+                } else {
+                    self.end_line();
+                }
+                self.lists.push((name == "ol").then_some(1));
+                self.children(element);
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block_break();
+                } else {
+                    self.end_line();
+                }
+            }
+            "li" => {
+                self.end_line();
+                let indent = "    ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(next)) => {
+                        *next += 1;
+                        format!("{}. ", *next - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.out.push_str(&indent);
+                self.out.push_str(&marker);
+                self.children(element);
+            }
+            _ => self.children(element),
+        }
+    }
+
+    fn resolve(&self, href: &str) -> String {
+        self.base_url
+            .and_then(|base| base.join(href).ok())
+            .map(|url| url.to_string())
+            .unwrap_or_else(|| href.to_string())
+    }
+}
+
+/// An element's text on a single line
+fn collapse(element: ElementRef) -> String {
+    element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web_scraper::ScrapingError;
+
+    const PAGE: &str = r#"<!DOCTYPE html><html><head><title>Guide</title><style>p { color: red }</style></head>
+<body>
+  <h1>Install   guide</h1>
+  <p>Read the <a href="/docs/faq">FAQ</a> first, it is <strong>short</strong>.</p>
+  <ul>
+    <li>Linux
+      <ol><li>Download</li><li>Run <code>make</code></li></ol>
+    </li>
+    <li>macOS</li>
+  </ul>
+  <pre><code class="language-bash">cargo build --release
+./target/release/nexus
+</code></pre>
+  <script>document.write("ignored")</script>
+</body></html>"#;
+
+    #[test]
+    fn test_html_to_markdown_preserves_structure() {
+        let base = Url::parse("https://example.com/guide/install").unwrap();
+        let markdown = html_to_markdown(PAGE, Some(&base));
+        assert_eq!(
+            markdown,
+            "# Install guide\n\n\
+             Read the [FAQ](https://example.com/docs/faq) first, it is **short**.\n\n\
+             - Linux\n    1. Download\n    2. Run `make`\n- macOS\n\n\
+             ```bash\ncargo build --release\n./target/release/nexus\n```\n"
+        );
+    }
+
+    fn result_with_pages(dir: &Path) -> ScrapingResult {
+        let page = |name: &str, url: &str| DownloadedFile {
+            url: url.to_string(),
+            local_path: dir.join(name).to_string_lossy().to_string(),
+            size: 0,
+            mime_type: "text/html".to_string(),
+            timestamp: Utc::now(),
+            proxy: None,
+        };
+        std::fs::write(dir.join("index.html"), PAGE).unwrap();
+        std::fs::write(dir.join("about.html"), "<h2>About</h2><p>Made by <em>us</em>.</p>").unwrap();
+
+        ScrapingResult {
+            id: "job-1".to_string(),
+            status: "completed".to_string(),
+            start_time: Utc::now(),
+            end_time: Some(Utc::now()),
+            total_pages: 4,
+            scraped_pages: 3,
+            total_size: 0,
+            errors: vec![ScrapingError {
+                url: "https://example.com/broken".to_string(),
+                error: "HTTP 500 Internal Server Error".to_string(),
+                timestamp: Utc::now(),
+                status_code: Some(500),
+            }],
+            downloaded_files: vec![
+                page("index.html", "https://example.com/guide/install"),
+                page("about.html", "https://example.com/about"),
+                page("deleted.html", "https://example.com/gone"),
+            ],
+            current_url: None,
+            estimated_time_remaining: None,
+            skipped_urls: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_markdown_export_sections_pages_and_notes_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = result_with_pages(dir.path());
+        let output = dir.path().join("export/bundle.md");
+
+        let summary = export_scrape_result(&result, ExportFormat::Markdown, output.to_str().unwrap()).await.unwrap();
+        assert_eq!((summary.pages, summary.failed_pages), (2, 2));
+
+        let markdown = std::fs::read_to_string(&output).unwrap();
+        let headings: Vec<&str> = markdown.lines().filter(|line| line.starts_with("## ")).collect();
+        assert_eq!(
+            headings,
+            vec![
+                "## https://example.com/guide/install",
+                "## https://example.com/about",
+                "## https://example.com/gone",
+                "## Failed pages",
+            ]
+        );
+        assert!(markdown.contains("### Install guide\n\nRead the [FAQ](https://example.com/docs/faq)"));
+        assert!(markdown.contains("- Linux\n    1. Download\n"));
+        assert!(markdown.contains("```bash\ncargo build --release\n"));
+        assert!(markdown.contains("#### About\n\nMade by _us_."));
+        assert!(markdown.contains("_Content unavailable:"));
+        assert!(markdown.contains("- <https://example.com/broken>: HTTP 500 Internal Server Error"));
+    }
+
+    #[tokio::test]
+    async fn test_warc_export_writes_one_record_per_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = result_with_pages(dir.path());
+        let output = dir.path().join("job.warc");
+
+        export_scrape_result(&result, ExportFormat::Warc, output.to_str().unwrap()).await.unwrap();
+        let warc = std::fs::read(&output).unwrap();
+        let warc = String::from_utf8_lossy(&warc);
+
+        let types: Vec<&str> = warc.lines().filter_map(|line| line.strip_prefix("WARC-Type: ")).collect();
+        assert_eq!(types, vec!["warcinfo", "resource", "resource", "metadata", "metadata"]);
+        assert_eq!(warc.matches("WARC/1.1\r\n").count(), 5);
+        assert!(warc.contains("WARC-Target-URI: https://example.com/about\r\nWARC-Date: "));
+        assert!(warc.contains(&format!("Content-Length: {}\r\n", PAGE.len())));
+    }
+}
@@ -0,0 +1,464 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use url::Url;
+
+/// Longest lifetime honoured for a cookie, as in RFC 6265bis
+const MAX_COOKIE_AGE_SECS: i64 = 400 * 24 * 60 * 60;
+
+/// Widely used multi-label public suffixes, which no site may set cookies for. Not the full
+/// Public Suffix List; single-label suffixes such as `com` are refused separately.
+const PUBLIC_SUFFIXES: &[&str] = &[
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "me.uk", "ltd.uk", "plc.uk", "net.uk",
+    "com.au", "net.au", "org.au", "edu.au", "gov.au", "co.nz", "org.nz", "net.nz",
+    "co.jp", "ne.jp", "or.jp", "ac.jp", "go.jp", "co.kr", "or.kr", "com.cn", "net.cn", "org.cn",
+    "com.tw", "com.hk", "com.sg", "co.in", "net.in", "org.in", "co.za", "org.za",
+    "com.br", "net.br", "org.br", "com.ar", "com.mx", "com.tr", "co.il", "com.pl",
+    "github.io", "gitlab.io", "herokuapp.com", "appspot.com", "blogspot.com",
+    "azurewebsites.net", "cloudfront.net", "pages.dev", "workers.dev", "netlify.app", "vercel.app",
+];
+
+/// `Expires` formats seen in practice besides RFC 2822 (RFC 6265 5.1.1)
+const COOKIE_DATE_FORMATS: &[&str] = &[
+    "%a, %d-%b-%Y %H:%M:%S GMT",
+    "%a, %d-%b-%y %H:%M:%S GMT",
+    "%A, %d-%b-%y %H:%M:%S GMT",
+    "%a, %d %b %y %H:%M:%S GMT",
+    "%a %b %e %H:%M:%S %Y",
+];
+
+/// A cookie as stored in the jar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Lower-cased host or domain the cookie belongs to, without a leading dot
+    pub domain: String,
+    /// Sent only to `domain` itself rather than to its subdomains too
+    pub host_only: bool,
+    pub path: String,
+    /// `None` for session cookies
+    pub expires: Option<DateTime<Utc>>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl Cookie {
+    /// A session cookie for exactly `host`, sent on every path
+    pub fn new(name: impl Into<String>, value: impl Into<String>, host: &str) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            domain: host.trim_start_matches('.').to_lowercase(),
+            host_only: true,
+            path: "/".to_string(),
+            expires: None,
+            secure: false,
+            http_only: false,
+        }
+    }
+
+    /// Parse a `Set-Cookie` header received from `url`. Returns `None` for malformed
+    /// cookies and for ones that claim a domain `url` does not belong to, a public suffix,
+    /// or any other domain when `url` names an IP address.
+    pub fn parse_set_cookie(header: &str, url: &Url) -> Option<Self> {
+        let host = url.host_str()?.to_lowercase();
+        let is_ip = !matches!(url.host(), Some(url::Host::Domain(_)));
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = Self::new(name, value.trim().trim_matches('"'), &host);
+        cookie.path = default_path(url);
+        let mut max_age = None;
+
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => (attribute.trim().to_lowercase(), ""),
+            };
+            match key.as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_lowercase();
+                    // Naming the host itself is the same as naming no domain
+                    if domain == host {
+                        continue;
+                    }
+                    if is_ip || !domain_matches(&host, &domain) || is_public_suffix(&domain) {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "expires" => {
+                    if let Some(expires) = parse_cookie_date(value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "max-age" => max_age = value.parse::<i64>().ok(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+
+        // Max-Age wins over Expires; zero or less expires the cookie immediately
+        if let Some(seconds) = max_age {
+            cookie.expires = Some(Utc::now() + Duration::seconds(seconds.min(MAX_COOKIE_AGE_SECS)));
+        }
+        Some(cookie)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether this cookie should be sent with a request to `url`
+    pub fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.to_lowercase();
+        let host_ok = if self.host_only { host == self.domain } else { domain_matches(&host, &self.domain) };
+        host_ok && path_matches(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+}
+
+/// `host` is `domain` or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// A single label such as `com` or a known multi-label public suffix such as `co.uk`
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || PUBLIC_SUFFIXES.contains(&domain)
+}
+
+fn parse_cookie_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    COOKIE_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|date| date.and_utc())
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    match request_path.strip_prefix(cookie_path) {
+        Some(rest) => cookie_path.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The directory of the request path, used when a cookie names no path (RFC 6265 5.1.4)
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(end) => url.path()[..end].to_string(),
+    }
+}
+
+/// Cookies for the scraper, grouped by the domain they belong to and optionally
+/// persisted between runs
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: RwLock<HashMap<String, Vec<Cookie>>>,
+    persist_path: Option<PathBuf>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the jar persisted at `path`, starting empty if there is none yet
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut cookies: HashMap<String, Vec<Cookie>> = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        let now = Utc::now();
+        for jar in cookies.values_mut() {
+            jar.retain(|cookie| !cookie.is_expired(now));
+        }
+        cookies.retain(|_, jar| !jar.is_empty());
+
+        Ok(Self {
+            cookies: RwLock::new(cookies),
+            persist_path: Some(path),
+        })
+    }
+
+    /// Write unexpired persistent cookies to the file the jar was loaded from.
+    /// Session cookies are not written.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.persist_path else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let mut persisted: HashMap<String, Vec<Cookie>> = HashMap::new();
+        for (domain, jar) in self.read()?.iter() {
+            let kept: Vec<Cookie> = jar.iter().filter(|c| c.expires.is_some() && !c.is_expired(now)).cloned().collect();
+            if !kept.is_empty() {
+                persisted.insert(domain.clone(), kept);
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Cookies are credentials: readable by the owner only, from the moment the file exists
+        let json = serde_json::to_string(&persisted)?;
+        let temp = path.with_extension("json.tmp");
+        let _ = std::fs::remove_file(&temp);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&temp)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        std::fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Add or replace a cookie; an already-expired cookie deletes its namesake instead
+    pub fn insert(&self, cookie: Cookie) {
+        let Ok(mut cookies) = self.cookies.write() else {
+            return;
+        };
+        let jar = cookies.entry(cookie.domain.clone()).or_default();
+        jar.retain(|c| !(c.name == cookie.name && c.path == cookie.path && c.host_only == cookie.host_only));
+        if !cookie.is_expired(Utc::now()) {
+            jar.push(cookie);
+        }
+    }
+
+    /// Unexpired cookies that apply to `url`, most specific path first
+    pub fn cookies_for(&self, url: &Url) -> Vec<Cookie> {
+        let Ok(cookies) = self.read() else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        let mut matching: Vec<Cookie> = cookies
+            .values()
+            .flatten()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        matching
+    }
+
+    /// Number of cookies held, expired ones included until they are next pruned
+    pub fn len(&self) -> usize {
+        self.read().map(|cookies| cookies.values().map(Vec::len).sum()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut cookies) = self.cookies.write() {
+            cookies.clear();
+        }
+    }
+
+    /// Add every cookie from a Netscape/Mozilla `cookies.txt`, returning how many were read
+    pub fn import_netscape(&self, content: &str) -> Result<usize> {
+        let cookies = parse_netscape_cookies(content)?;
+        let count = cookies.len();
+        for cookie in cookies {
+            self.insert(cookie);
+        }
+        Ok(count)
+    }
+
+    pub fn import_netscape_file(&self, path: &Path) -> Result<usize> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        self.import_netscape(&content)
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, HashMap<String, Vec<Cookie>>>> {
+        self.cookies.read().map_err(|_| anyhow!("Cookie jar lock poisoned"))
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        for header in cookie_headers {
+            if let Some(cookie) = header.to_str().ok().and_then(|h| Cookie::parse_set_cookie(h, url)) {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .cookies_for(url)
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+/// Parse the tab-separated Netscape cookie file format used by curl, wget and browser exporters
+pub fn parse_netscape_cookies(content: &str) -> Result<Vec<Cookie>> {
+    let mut cookies = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        // curl marks HttpOnly cookies with a prefix on an otherwise commented-out line
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, include_subdomains, path, secure, expires, name, value] = fields[..] else {
+            return Err(anyhow!("Line {}: expected 7 tab-separated fields, found {}", number + 1, fields.len()));
+        };
+        let expires: i64 = expires
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Line {}: invalid expiry '{}'", number + 1, expires))?;
+
+        cookies.push(Cookie {
+            name: name.to_string(),
+            value: value.trim_end_matches('\r').to_string(),
+            domain: domain.trim_start_matches('.').to_lowercase(),
+            host_only: !include_subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            expires: match expires {
+                0 => None,
+                seconds => Utc.timestamp_opt(seconds, 0).single(),
+            },
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+        });
+    }
+    Ok(cookies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn names(jar: &CookieJar, target: &str) -> Vec<String> {
+        let mut names: Vec<String> = jar.cookies_for(&url(target)).into_iter().map(|c| c.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_set_cookie_scoping_and_expiry() {
+        let jar = CookieJar::new();
+        let origin = url("https://app.example.com/account/login");
+        for header in [
+            "session=abc; Path=/; HttpOnly",
+            "shared=1; Domain=.example.com; Path=/",
+            "local=1",
+            "secure_only=1; Path=/; Secure",
+            "stale=1; Path=/; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            "foreign=1; Domain=other.org",
+        ] {
+            if let Some(cookie) = Cookie::parse_set_cookie(header, &origin) {
+                jar.insert(cookie);
+            }
+        }
+
+        assert_eq!(names(&jar, "https://app.example.com/account/settings"), vec!["local", "secure_only", "session", "shared"]);
+        assert_eq!(names(&jar, "https://cdn.example.com/"), vec!["shared"]);
+        assert_eq!(names(&jar, "http://app.example.com/"), vec!["session", "shared"]);
+        assert!(names(&jar, "https://other.org/").is_empty());
+
+        // Max-Age=0 deletes the existing cookie
+        jar.insert(Cookie::parse_set_cookie("session=; Path=/; Max-Age=0", &origin).unwrap());
+        assert!(!names(&jar, "https://app.example.com/").contains(&"session".to_string()));
+    }
+
+    #[test]
+    fn test_public_suffix_and_ip_domains_are_refused() {
+        let parse = |header: &str, origin: &str| Cookie::parse_set_cookie(header, &url(origin));
+        assert!(parse("a=1; Domain=com", "https://example.com/").is_none());
+        assert!(parse("a=1; Domain=co.uk", "https://shop.example.co.uk/").is_none());
+        assert!(parse("a=1; Domain=example.co.uk", "https://shop.example.co.uk/").is_some_and(|c| !c.host_only));
+        assert!(parse("a=1; Domain=0.1", "http://10.0.0.1/").is_none());
+        assert!(parse("a=1; Domain=10.0.0.1", "http://10.0.0.1/").is_some_and(|c| c.host_only));
+        assert!(parse("a=1; Domain=localhost", "http://localhost/").is_some_and(|c| c.host_only));
+    }
+
+    #[test]
+    fn test_netscape_style_expires_dates() {
+        let origin = url("https://example.com/");
+        let expires = |date: &str| Cookie::parse_set_cookie(&format!("a=1; Expires={}", date), &origin).unwrap().expires;
+        let expected = Utc.with_ymd_and_hms(2035, 10, 21, 7, 28, 0).single();
+        assert_eq!(expires("Sun, 21 Oct 2035 07:28:00 GMT"), expected);
+        assert_eq!(expires("Sun, 21-Oct-2035 07:28:00 GMT"), expected);
+        assert_eq!(expires("Sunday, 21-Oct-35 07:28:00 GMT"), expected);
+        assert_eq!(expires("Sun Oct 21 07:28:00 2035"), expected);
+        assert_eq!(expires("not a date"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_saved_jar_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cookies.json");
+        let jar = CookieJar::load(&path).unwrap();
+        jar.insert(Cookie::parse_set_cookie("sid=s3cr3t; Max-Age=3600", &url("https://example.com/")).unwrap());
+        jar.save().unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(CookieJar::load(&path).unwrap().cookies_for(&url("https://example.com/")).len(), 1);
+    }
+
+    #[test]
+    fn test_import_netscape_cookie_file() {
+        let file = "# Netscape HTTP Cookie File\n\
+            .example.com\tTRUE\t/\tFALSE\t0\ttheme\tdark\n\
+            #HttpOnly_app.example.com\tFALSE\t/\tTRUE\t4102444800\tsid\ts3cr3t\n\
+            app.example.com\tFALSE\t/\tFALSE\t1000000000\told\tgone\n";
+        let jar = CookieJar::new();
+        assert_eq!(jar.import_netscape(file).unwrap(), 3);
+
+        let cookies = jar.cookies_for(&url("https://app.example.com/"));
+        let sid = cookies.iter().find(|c| c.name == "sid").unwrap();
+        assert!(sid.http_only && sid.secure && sid.host_only);
+        assert_eq!(names(&jar, "https://app.example.com/"), vec!["sid", "theme"]);
+        assert_eq!(names(&jar, "https://www.example.com/"), vec!["theme"]);
+
+        assert!(jar.import_netscape("example.com\tTRUE\t/\n").is_err());
+    }
+}
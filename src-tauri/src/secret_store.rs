@@ -0,0 +1,341 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Service name used for OS keyring entries
+const KEYRING_SERVICE: &str = "nexus-terminal";
+
+/// Prefix marking a config value as a reference into the secret store
+pub const SECRET_REF_PREFIX: &str = "secret://";
+
+/// Storage backend for secrets
+pub trait SecretBackend: Send + Sync + std::fmt::Debug {
+    fn set(&self, key: &str, value: &str) -> Result<()>;
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn delete(&self, key: &str) -> Result<()>;
+    fn name(&self) -> &'static str;
+}
+
+/// Secrets stored in the operating system keyring
+#[derive(Debug)]
+pub struct KeyringBackend {
+    service: String,
+}
+
+impl KeyringBackend {
+    pub fn new() -> Self {
+        Self {
+            service: KEYRING_SERVICE.to_string(),
+        }
+    }
+
+    /// Check that a keyring daemon is reachable without creating any entry
+    pub fn is_available(&self) -> bool {
+        match keyring::Entry::new(&self.service, "__nexus_probe__") {
+            Ok(entry) => matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry)),
+            Err(_) => false,
+        }
+    }
+
+    fn entry(&self, key: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&self.service, key).context("Failed to open keyring entry")
+    }
+}
+
+impl Default for KeyringBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretBackend for KeyringBackend {
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.entry(key)?
+            .set_password(value)
+            .context("Failed to write secret to keyring")
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        match self.entry(key)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Failed to read secret from keyring: {}", e)),
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        match self.entry(key)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!("Failed to delete secret from keyring: {}", e)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+}
+
+/// AES-256-GCM encrypted file, used when no OS keyring is available.
+///
+/// The key lives in a separate file readable only by the current user.
+#[derive(Debug)]
+pub struct EncryptedFileBackend {
+    secrets_path: PathBuf,
+    key_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileBackend {
+    pub fn new(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir).context("Failed to create secrets directory")?;
+        Ok(Self {
+            secrets_path: dir.join("secrets.enc"),
+            key_path: dir.join("secrets.key"),
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn cipher(&self) -> Result<Aes256Gcm> {
+        let key_bytes = if self.key_path.exists() {
+            std::fs::read(&self.key_path).context("Failed to read secrets key")?
+        } else {
+            let key = Aes256Gcm::generate_key(OsRng);
+            write_private(&self.key_path, key.as_slice())?;
+            key.to_vec()
+        };
+
+        if key_bytes.len() != 32 {
+            return Err(anyhow!("Secrets key file is corrupted"));
+        }
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes)))
+    }
+
+    fn load(&self) -> Result<HashMap<String, String>> {
+        if !self.secrets_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let data = std::fs::read(&self.secrets_path).context("Failed to read secrets file")?;
+        if data.len() < 12 {
+            return Err(anyhow!("Secrets file is corrupted"));
+        }
+
+        let (nonce, ciphertext) = data.split_at(12);
+        let plaintext = self
+            .cipher()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt secrets file"))?;
+
+        serde_json::from_slice(&plaintext).context("Failed to parse secrets file")
+    }
+
+    fn save(&self, secrets: &HashMap<String, String>) -> Result<()> {
+        let plaintext = serde_json::to_vec(secrets)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()?
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt secrets"))?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        write_private(&self.secrets_path, &data)
+    }
+}
+
+impl SecretBackend for EncryptedFileBackend {
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        let _guard = self.lock.lock().map_err(|_| anyhow!("Secret store lock poisoned"))?;
+        let mut secrets = self.load()?;
+        secrets.insert(key.to_string(), value.to_string());
+        self.save(&secrets)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let _guard = self.lock.lock().map_err(|_| anyhow!("Secret store lock poisoned"))?;
+        Ok(self.load()?.remove(key))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let _guard = self.lock.lock().map_err(|_| anyhow!("Secret store lock poisoned"))?;
+        let mut secrets = self.load()?;
+        if secrets.remove(key).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "encrypted-file"
+    }
+}
+
+/// Replace `path` with `data`, readable by the owner only from the moment the file exists
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp = PathBuf::from(temp_name);
+    let _ = std::fs::remove_file(&temp);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&temp)
+        .and_then(|mut file| file.write_all(data))
+        .with_context(|| format!("Failed to write {:?}", temp))?;
+    std::fs::rename(&temp, path).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(())
+}
+
+/// Central store for credentials; config only ever holds `secret://` references
+#[derive(Debug)]
+pub struct SecretStore {
+    backend: Box<dyn SecretBackend>,
+}
+
+impl SecretStore {
+    /// Use the OS keyring when available, otherwise an encrypted file under `data_dir`
+    pub fn new(data_dir: &Path) -> Result<Self> {
+        let keyring = KeyringBackend::new();
+        if keyring.is_available() {
+            info!("Using OS keyring for secret storage");
+            return Ok(Self::with_backend(Box::new(keyring)));
+        }
+
+        warn!("OS keyring unavailable, falling back to encrypted file secret storage");
+        let backend = EncryptedFileBackend::new(&data_dir.join("secrets"))?;
+        Ok(Self::with_backend(Box::new(backend)))
+    }
+
+    pub fn with_backend(backend: Box<dyn SecretBackend>) -> Self {
+        Self { backend }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    pub fn set_secret(&self, key: &str, value: &str) -> Result<()> {
+        self.backend.set(key, value)
+    }
+
+    pub fn get_secret(&self, key: &str) -> Result<Option<String>> {
+        self.backend.get(key)
+    }
+
+    pub fn delete_secret(&self, key: &str) -> Result<()> {
+        self.backend.delete(key)
+    }
+
+    /// Store `value` under `key` and return the reference to keep in config
+    pub fn store(&self, key: &str, value: &str) -> Result<String> {
+        self.set_secret(key, value)?;
+        Ok(secret_ref(key))
+    }
+
+    /// Resolve a value that may be a `secret://` reference
+    pub fn resolve(&self, value: &str) -> Result<Option<String>> {
+        match value.strip_prefix(SECRET_REF_PREFIX) {
+            Some(key) => self.get_secret(key),
+            None => Ok(Some(value.to_string())),
+        }
+    }
+}
+
+pub fn secret_ref(key: &str) -> String {
+    format!("{}{}", SECRET_REF_PREFIX, key)
+}
+
+pub fn is_secret_ref(value: &str) -> bool {
+    value.starts_with(SECRET_REF_PREFIX)
+}
+
+/// `serialize_with` for credential fields: they only ever leave memory as secret store
+/// references, and a plaintext value means no secret store took it, which is an error
+/// rather than something to drop quietly
+pub fn serialize_secret_ref<S: serde::Serializer>(value: &Option<String>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match value {
+        Some(v) if is_secret_ref(v) => serializer.serialize_some(v),
+        Some(_) => Err(serde::ser::Error::custom("credential was not moved to the secret store and cannot be serialized")),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// In-memory stand-in for the OS keyring
+    #[derive(Debug, Default)]
+    pub(crate) struct MockKeyring {
+        entries: Mutex<HashMap<String, String>>,
+    }
+
+    impl SecretBackend for MockKeyring {
+        fn set(&self, key: &str, value: &str) -> Result<()> {
+            self.entries.lock().unwrap().insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+
+        fn get(&self, key: &str) -> Result<Option<String>> {
+            Ok(self.entries.lock().unwrap().get(key).cloned())
+        }
+
+        fn delete(&self, key: &str) -> Result<()> {
+            self.entries.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+    }
+
+    #[test]
+    fn test_secret_round_trip_and_delete() {
+        let store = SecretStore::with_backend(Box::new(MockKeyring::default()));
+
+        let reference = store.store("ai/api_key", "sk-test").unwrap();
+        assert_eq!(reference, "secret://ai/api_key");
+        assert_eq!(store.resolve(&reference).unwrap().as_deref(), Some("sk-test"));
+        assert_eq!(store.resolve("plain-value").unwrap().as_deref(), Some("plain-value"));
+
+        store.delete_secret("ai/api_key").unwrap();
+        assert_eq!(store.get_secret("ai/api_key").unwrap(), None);
+    }
+
+    #[test]
+    fn test_encrypted_file_backend_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = EncryptedFileBackend::new(dir.path()).unwrap();
+
+        backend.set("token", "hunter2").unwrap();
+        assert_eq!(backend.get("token").unwrap().as_deref(), Some("hunter2"));
+
+        let raw = std::fs::read(dir.path().join("secrets.enc")).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("hunter2"));
+
+        backend.delete("token").unwrap();
+        assert_eq!(backend.get("token").unwrap(), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in ["secrets.enc", "secrets.key"] {
+                let mode = std::fs::metadata(dir.path().join(file)).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600, "{}", file);
+            }
+        }
+    }
+}
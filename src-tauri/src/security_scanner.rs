@@ -1,11 +1,20 @@
 use anyhow::{Result, anyhow};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::Instant;
 use chrono::{DateTime, Utc};
-#[allow(unused_imports)]
 use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::advisory_db::{AdvisoryDatabase, AdvisoryDbInfo};
+use crate::remediation::RemediationPlan;
+use crate::utils::glob_match;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VulnerabilityResult {
@@ -18,6 +27,33 @@ pub struct VulnerabilityResult {
     pub cvss_score: Option<f32>,
     pub remediation: Option<String>,
     pub detected_at: DateTime<Utc>,
+    /// Rule that produced the finding, when it came from a pattern rule
+    #[serde(default)]
+    pub rule_id: Option<String>,
+    #[serde(default)]
+    pub line: Option<u32>,
+    /// Text the rule matched; redacted for secret findings
+    #[serde(default)]
+    pub matched_text: Option<String>,
+}
+
+impl VulnerabilityResult {
+    fn from_rule(rule: &SecurityRule, file_path: String, line: u32, matched_text: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            severity: rule.severity.clone(),
+            title: rule.name.clone(),
+            description: rule.description.clone(),
+            affected_files: vec![file_path],
+            cve_id: None,
+            cvss_score: None,
+            remediation: Some(rule.remediation.clone()),
+            detected_at: Utc::now(),
+            rule_id: Some(rule.id.clone()),
+            line: Some(line),
+            matched_text: Some(matched_text),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,6 +94,9 @@ pub struct SecurityConfig {
     pub severity_threshold: VulnerabilitySeverity,
     pub exclude_patterns: Vec<String>,
     pub custom_rules: Vec<SecurityRule>,
+    /// URL or file path of the advisory feed `update_advisory_db` downloads
+    #[serde(default)]
+    pub advisory_db_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +107,274 @@ pub struct SecurityRule {
     pub severity: VulnerabilitySeverity,
     pub description: String,
     pub remediation: String,
+    /// Globs limiting the rule to matching files; empty applies it everywhere
+    #[serde(default)]
+    pub file_globs: Vec<String>,
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+const SECRET_TAG: &str = "secret";
+
+/// Patterns `scan_secrets` always checks for
+pub(crate) fn builtin_secret_rules() -> Vec<SecurityRule> {
+    [
+        ("api-key", r#"(?i)api[_-]?key\s*[:=]\s*['"]?[a-zA-Z0-9]{20,}['"]?"#, "API Key"),
+        ("password", r#"(?i)password\s*[:=]\s*['"]?[^\s'"]{8,}['"]?"#, "Password"),
+        ("secret-key", r#"(?i)secret[_-]?key\s*[:=]\s*['"]?[a-zA-Z0-9]{20,}['"]?"#, "Secret Key"),
+        ("token", r#"(?i)token\s*[:=]\s*['"]?[a-zA-Z0-9]{20,}['"]?"#, "Token"),
+        ("private-key", r"-----BEGIN [A-Z]+ PRIVATE KEY-----", "Private Key"),
+    ]
+    .into_iter()
+    .map(|(id, pattern, secret_type)| SecurityRule {
+        id: format!("secret/{}", id),
+        name: format!("Potential {} Exposure", secret_type),
+        pattern: pattern.to_string(),
+        severity: VulnerabilitySeverity::High,
+        description: format!("Potential {} found in code", secret_type),
+        remediation: "Move sensitive data to environment variables or secure configuration".to_string(),
+        file_globs: vec![],
+        enabled: true,
+        tags: vec![SECRET_TAG.to_string()],
+    })
+    .collect()
+}
+
+fn redact_secret(_matched: &str) -> String {
+    "[REDACTED]".to_string()
+}
+
+/// Findings for one rule in one file's content
+fn match_rule(rule: &SecurityRule, regex: &Regex, file_path: &str, content: &str) -> Vec<VulnerabilityResult> {
+    let is_secret = rule.tags.iter().any(|tag| tag == SECRET_TAG);
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let found = regex.find(line)?;
+            let mut finding = VulnerabilityResult::from_rule(rule, file_path.to_string(), index as u32 + 1, found.as_str().to_string());
+            if is_secret {
+                // Keep the secret itself out of reports, scan manifests and the scan cache
+                finding.matched_text = Some(redact_secret(found.as_str()));
+                finding.cvss_score = Some(8.0);
+            }
+            Some(finding)
+        })
+        .collect()
+}
+
+/// Per-directory record of the last file scan, used to skip unchanged files
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ScanManifest {
+    ruleset_hash: String,
+    files: HashMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    fingerprint: String,
+    findings: Vec<VulnerabilityResult>,
+}
+
+#[derive(Debug, Default)]
+struct FileScan {
+    findings: Vec<VulnerabilityResult>,
+    /// Relative paths of the files that were actually read
+    examined: Vec<String>,
+    /// Findings in files matched by `.nexus-scanignore`
+    ignored: usize,
+}
+
+/// Gitignore-style globs, read from the root of a scanned directory, for paths whose findings are not reported
+pub const SCAN_IGNORE_FILE: &str = ".nexus-scanignore";
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// `glob_match` patterns equivalent to the gitignore line
+    globs: Vec<String>,
+    negated: bool,
+}
+
+/// Parsed `.nexus-scanignore`; as in gitignore, the last matching line decides
+#[derive(Debug, Clone, Default)]
+pub struct ScanIgnore {
+    rules: Vec<IgnoreRule>,
+}
+
+impl ScanIgnore {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let dir_only = line.ends_with('/');
+                let pattern = line.trim_end_matches('/');
+                // A pattern with an inner slash is relative to the root; a bare name matches at any depth
+                let anchored = pattern.contains('/');
+                let pattern = pattern.trim_start_matches('/');
+                let mut bases = vec![pattern.to_string()];
+                if !anchored {
+                    bases.push(format!("*/{}", pattern));
+                }
+                let globs = bases
+                    .into_iter()
+                    .flat_map(|base| {
+                        let contents = format!("{}/*", base);
+                        if dir_only { vec![contents] } else { vec![base, contents] }
+                    })
+                    .collect();
+                IgnoreRule { globs, negated }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// The ignore file under `root`, or an empty set when there is none
+    pub fn load(root: &Path) -> Self {
+        match std::fs::read_to_string(root.join(SCAN_IGNORE_FILE)) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn is_ignored(&self, relative_path: &str) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.globs.iter().any(|glob| glob_match(glob, relative_path)))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Identifies a finding across scans for the baseline. The line number is left out so
+/// accepted findings stay accepted when edits above them shift the file.
+fn baseline_key(finding: &VulnerabilityResult) -> String {
+    format!(
+        "{}|{}|{}",
+        finding.rule_id.as_deref().or(finding.cve_id.as_deref()).unwrap_or(&finding.title),
+        finding.affected_files.join(","),
+        finding.matched_text.as_deref().unwrap_or_default()
+    )
+}
+
+/// Findings accepted by `create_baseline`, kept out of later scan results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Baseline {
+    created_at: Option<DateTime<Utc>>,
+    accepted: HashSet<String>,
+}
+
+fn ruleset_hash(rules: &[SecurityRule]) -> Result<String> {
+    Ok(format!("{:x}", Sha256::digest(serde_json::to_vec(rules)?)))
+}
+
+fn file_fingerprint(metadata: &std::fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    format!("{}:{}", modified, metadata.len())
+}
+
+fn sarif_level(severity: &VulnerabilitySeverity) -> &'static str {
+    match severity {
+        VulnerabilitySeverity::Critical | VulnerabilitySeverity::High => "error",
+        VulnerabilitySeverity::Medium => "warning",
+        VulnerabilitySeverity::Low | VulnerabilitySeverity::Info => "note",
+    }
+}
+
+fn sarif_rule(rule: &SecurityRule) -> serde_json::Value {
+    serde_json::json!({
+        "id": rule.id,
+        "name": rule.name,
+        "shortDescription": { "text": rule.name },
+        "fullDescription": { "text": rule.description },
+        "help": { "text": rule.remediation },
+        "defaultConfiguration": { "level": sarif_level(&rule.severity) },
+        "properties": { "tags": rule.tags },
+    })
+}
+
+impl SecurityRule {
+    /// Whether the rule is scoped to `relative_path`. Globs without a `/` match the file name
+    /// alone, so `*.py` covers Python files at any depth.
+    pub fn applies_to(&self, relative_path: &str) -> bool {
+        let relative_path = relative_path.replace('\\', "/");
+        let file_name = relative_path.rsplit('/').next().unwrap_or(&relative_path);
+        self.file_globs.is_empty()
+            || self.file_globs.iter().any(|glob| {
+                let target = if glob.contains('/') { relative_path.as_str() } else { file_name };
+                glob_match(glob, target)
+            })
+    }
+}
+
+/// Top level of a YAML ruleset file
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    rules: Vec<RuleDefinition>,
+}
+
+/// One rule as written in a ruleset file
+#[derive(Debug, Deserialize)]
+struct RuleDefinition {
+    id: String,
+    name: Option<String>,
+    severity: String,
+    pattern: String,
+    #[serde(default)]
+    files: Vec<String>,
+    description: String,
+    #[serde(default)]
+    remediation: String,
+    #[serde(default = "default_rule_enabled")]
+    enabled: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Parse a YAML ruleset, rejecting it with the offending rule's id if any rule is invalid
+pub fn parse_rule_file(yaml: &str) -> Result<Vec<SecurityRule>> {
+    let file: RuleFile = serde_yaml::from_str(yaml).map_err(|e| anyhow!("Invalid ruleset: {}", e))?;
+
+    file.rules
+        .into_iter()
+        .map(|rule| {
+            Regex::new(&rule.pattern).map_err(|e| anyhow!("Rule '{}' has an invalid pattern: {}", rule.id, e))?;
+            let severity = match rule.severity.to_lowercase().as_str() {
+                "critical" => VulnerabilitySeverity::Critical,
+                "high" => VulnerabilitySeverity::High,
+                "medium" => VulnerabilitySeverity::Medium,
+                "low" => VulnerabilitySeverity::Low,
+                "info" => VulnerabilitySeverity::Info,
+                other => return Err(anyhow!("Rule '{}' has an unknown severity: {}", rule.id, other)),
+            };
+            Ok(SecurityRule {
+                name: rule.name.unwrap_or_else(|| rule.id.clone()),
+                id: rule.id,
+                pattern: rule.pattern,
+                severity,
+                description: rule.description,
+                remediation: rule.remediation,
+                file_globs: rule.files,
+                enabled: rule.enabled,
+                tags: rule.tags,
+            })
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,6 +404,8 @@ pub enum ScanType {
     Secrets,
     Dependencies,
     Comprehensive,
+    /// Comprehensive scan that ignores the manifest of the previous scan
+    FullRescan,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,6 +418,18 @@ pub struct ScanResult {
     pub vulnerabilities: Vec<VulnerabilityResult>,
     pub status: String,
     pub summary: String,
+    /// Files read by this scan; unchanged files reuse their previous findings
+    #[serde(default)]
+    pub rescanned_files: Vec<String>,
+    /// Findings left out because their file matches `.nexus-scanignore`
+    #[serde(default)]
+    pub ignored_findings: usize,
+    /// Findings left out because they are in the accepted baseline
+    #[serde(default)]
+    pub baselined_findings: usize,
+    /// Package, version range and fix for each dependency finding
+    #[serde(default)]
+    pub dependency_vulnerabilities: Vec<DependencyVulnerability>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -130,12 +451,263 @@ pub struct RemediationResult {
     pub message: String,
     pub actions_taken: Vec<String>,
     pub timestamp: DateTime<Utc>,
+    /// The result previews the edits without writing them
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Unified diff of the edits made, or that would be made
+    #[serde(default)]
+    pub diff: String,
+    #[serde(default)]
+    pub files_changed: Vec<String>,
+    /// Pass to `rollback_remediation` to undo an applied remediation
+    #[serde(default)]
+    pub remediation_id: Option<String>,
+}
+
+impl From<&VulnerabilityResult> for Vulnerability {
+    fn from(result: &VulnerabilityResult) -> Self {
+        Self {
+            id: result.id.clone(),
+            title: result.title.clone(),
+            description: result.description.clone(),
+            severity: result.severity.clone(),
+            cve_id: result.cve_id.clone(),
+            affected_files: result.affected_files.clone(),
+            remediation: result.remediation.clone(),
+            discovered_at: result.detected_at,
+        }
+    }
+}
+
+/// Accepted findings, kept beside the scan manifests
+const BASELINE_FILE: &str = "baseline.json";
+
+/// Directory scan results kept for lookups by scan id; older ones are dropped
+const MAX_SCAN_RESULTS: usize = 20;
+
+/// Quiet period after the last change to a file before the monitor rescans it
+const MONITOR_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Findings buffered for slow `subscribe_findings` receivers
+const FINDING_BUFFER: usize = 256;
+
+/// State of real-time monitoring, for `security_get_monitoring_status`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MonitoringStatus {
+    pub active: bool,
+    /// Watched directories, with paths nested in another watched path folded into it
+    pub paths: Vec<String>,
+    pub findings_since_start: usize,
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the last finding was reported
+    pub last_event_at: Option<DateTime<Utc>>,
+}
+
+/// Counters shared between the rescan task and `monitoring_status`
+#[derive(Debug, Default)]
+struct MonitorStats {
+    roots: Vec<PathBuf>,
+    findings: usize,
+    last_event_at: Option<DateTime<Utc>>,
+}
+
+/// Watches directories and rescans changed files with the rules in force when monitoring began
+struct RealTimeMonitor {
+    watcher: RecommendedWatcher,
+    stats: Arc<Mutex<MonitorStats>>,
+    started_at: DateTime<Utc>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl std::fmt::Debug for RealTimeMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RealTimeMonitor")
+            .field("stats", &self.stats)
+            .field("started_at", &self.started_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for RealTimeMonitor {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl RealTimeMonitor {
+    fn start(rules: Vec<(SecurityRule, Regex)>, exclude_patterns: Vec<String>, findings: broadcast::Sender<Vulnerability>) -> Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("Security monitor watch failed: {}", e);
+                    return;
+                }
+            };
+            if !is_file_change(&event.kind) {
+                return;
+            }
+            for path in event.paths {
+                if !path.is_dir() {
+                    let _ = tx.send(path);
+                }
+            }
+        })?;
+
+        let stats = Arc::new(Mutex::new(MonitorStats::default()));
+        let scan = MonitorScan { rules, exclude_patterns, stats: Arc::clone(&stats), findings, seen: HashMap::new() };
+        let task = tokio::spawn(scan.run(rx));
+        Ok(Self { watcher, stats, started_at: Utc::now(), task })
+    }
+
+    /// Watch `root` unless a watched directory already contains it; watched directories
+    /// inside `root` are folded into it so no file is watched, and scanned, twice
+    fn add_root(&mut self, root: PathBuf) -> Result<()> {
+        let mut stats = self.stats.lock().map_err(|_| anyhow!("Security monitor state is poisoned"))?;
+        if stats.roots.iter().any(|watched| root.starts_with(watched)) {
+            return Ok(());
+        }
+        // Unwatch first: inotify shares one watch per directory, so dropping a nested
+        // watch after adding `root` would also drop `root`'s watch on it
+        for nested in stats.roots.iter().filter(|watched| watched.starts_with(&root)) {
+            let _ = self.watcher.unwatch(nested);
+        }
+        stats.roots.retain(|watched| !watched.starts_with(&root));
+        self.watcher.watch(&root, RecursiveMode::Recursive)?;
+        tracing::info!("Monitoring {} for security issues", root.display());
+        stats.roots.push(root);
+        Ok(())
+    }
+
+    fn status(&self) -> MonitoringStatus {
+        let stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut paths: Vec<String> = stats.roots.iter().map(|root| root.to_string_lossy().to_string()).collect();
+        paths.sort();
+        MonitoringStatus {
+            active: true,
+            paths,
+            findings_since_start: stats.findings,
+            started_at: Some(self.started_at),
+            last_event_at: stats.last_event_at,
+        }
+    }
+}
+
+/// What the monitor last saw in a file
+struct SeenFile {
+    hash: String,
+    /// (rule, line) of each finding already reported
+    findings: HashSet<(String, u32)>,
+}
+
+struct MonitorScan {
+    rules: Vec<(SecurityRule, Regex)>,
+    exclude_patterns: Vec<String>,
+    stats: Arc<Mutex<MonitorStats>>,
+    findings: broadcast::Sender<Vulnerability>,
+    seen: HashMap<PathBuf, SeenFile>,
+}
+
+impl MonitorScan {
+    /// Rescan each changed file once it has been quiet for `MONITOR_DEBOUNCE`
+    async fn run(mut self, mut changes: mpsc::UnboundedReceiver<PathBuf>) {
+        let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+        loop {
+            let deadline = pending.values().min().map(|seen| *seen + MONITOR_DEBOUNCE);
+            tokio::select! {
+                change = changes.recv() => match change {
+                    Some(path) => {
+                        pending.insert(path, Instant::now());
+                    }
+                    None => return,
+                },
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let now = Instant::now();
+                    let settled: Vec<PathBuf> = pending
+                        .iter()
+                        .filter(|(_, seen)| now.duration_since(**seen) >= MONITOR_DEBOUNCE)
+                        .map(|(path, _)| path.clone())
+                        .collect();
+                    for path in settled {
+                        pending.remove(&path);
+                        self.rescan(&path).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn rescan(&mut self, path: &Path) {
+        let (root, relative) = {
+            let stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let found = stats.roots.iter().find_map(|root| Some((root.clone(), path.strip_prefix(root).ok()?.to_path_buf())));
+            match found {
+                Some((root, relative)) => (root, relative.to_string_lossy().replace('\\', "/")),
+                None => return,
+            }
+        };
+        if self.exclude_patterns.iter().any(|pattern| glob_match(pattern, &relative)) || ScanIgnore::load(&root).is_ignored(&relative) {
+            return;
+        }
+        let Ok(bytes) = tokio::fs::read(path).await else {
+            self.seen.remove(path);
+            return;
+        };
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        if self.seen.get(path).is_some_and(|seen| seen.hash == hash) {
+            return;
+        }
+
+        // Binary files cannot match a text rule
+        let content = String::from_utf8(bytes).unwrap_or_default();
+        let file_path = path.to_string_lossy();
+        let results: Vec<VulnerabilityResult> = self
+            .rules
+            .iter()
+            .filter(|(rule, _)| rule.applies_to(&relative))
+            .flat_map(|(rule, regex)| match_rule(rule, regex, &file_path, &content))
+            .collect();
+
+        let previous = self.seen.remove(path).map(|seen| seen.findings).unwrap_or_default();
+        let mut current = HashSet::new();
+        for result in &results {
+            let key = (result.rule_id.clone().unwrap_or_default(), result.line.unwrap_or_default());
+            if !previous.contains(&key) && !current.contains(&key) {
+                // Nobody listening is fine; the finding still counts
+                let _ = self.findings.send(Vulnerability::from(result));
+                let mut stats = self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                stats.findings += 1;
+                stats.last_event_at = Some(Utc::now());
+            }
+            current.insert(key);
+        }
+        self.seen.insert(path.to_path_buf(), SeenFile { hash, findings: current });
+    }
+}
+
+fn is_file_change(kind: &EventKind) -> bool {
+    use notify::event::ModifyKind;
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+    )
 }
 
 #[derive(Debug)]
 pub struct SecurityScanner {
     config: SecurityConfig,
     scan_cache: HashMap<String, SecurityScanReport>,
+    /// Results of `scan_directory`, by scan id
+    scan_results: HashMap<String, ScanResult>,
+    manifest_dir: Option<PathBuf>,
+    baseline: Baseline,
+    advisory_db_path: Option<PathBuf>,
+    advisories: AdvisoryDatabase,
+    /// Previewed remediations by vulnerability id, applied as previewed
+    remediation_plans: HashMap<String, RemediationPlan>,
+    monitor: Option<RealTimeMonitor>,
+    findings: broadcast::Sender<Vulnerability>,
 }
 
 impl Default for SecurityConfig {
@@ -157,6 +729,7 @@ impl Default for SecurityConfig {
                 "*.min.js".to_string(),
             ],
             custom_rules: vec![],
+            advisory_db_url: None,
         }
     }
 }
@@ -167,15 +740,53 @@ impl SecurityScanner {
         Self {
             config,
             scan_cache: HashMap::new(),
+            scan_results: HashMap::new(),
+            manifest_dir: None,
+            baseline: Baseline::default(),
+            advisory_db_path: None,
+            advisories: AdvisoryDatabase::default(),
+            remediation_plans: HashMap::new(),
+            monitor: None,
+            findings: broadcast::channel(FINDING_BUFFER).0,
+        }
+    }
+
+    /// Findings from real-time monitoring, as they are detected
+    pub fn subscribe_findings(&self) -> broadcast::Receiver<Vulnerability> {
+        self.findings.subscribe()
+    }
+
+    /// Store scan manifests under `dir` so directory scans only re-read changed files
+    pub fn with_manifest_dir(mut self, dir: PathBuf) -> Self {
+        let path = dir.join(BASELINE_FILE);
+        if let Ok(json) = std::fs::read_to_string(&path) {
+            match serde_json::from_str(&json) {
+                Ok(baseline) => self.baseline = baseline,
+                Err(e) => tracing::warn!("Ignoring unreadable security baseline {}: {}", path.display(), e),
+            }
+        }
+        self.manifest_dir = Some(dir);
+        self
+    }
+
+    /// Keep the advisory database used for dependency scans at `path`
+    pub fn with_advisory_db(mut self, path: PathBuf) -> Self {
+        match AdvisoryDatabase::load(&path) {
+            Ok(database) => self.advisories = database,
+            Err(e) => tracing::warn!("Ignoring unreadable advisory database: {:#}", e),
         }
+        self.advisory_db_path = Some(path);
+        self
     }
 
-    pub async fn scan_project(&mut self, project_path: &str) -> Result<SecurityScanReport> {
+    /// Run every scanner over `project_path`. Only reads the scanner, so callers can hold a
+    /// read lock while it runs and store the report afterwards with `store_project_report`.
+    pub async fn scan_project(&self, project_path: &str) -> Result<SecurityScanReport> {
         let scan_id = uuid::Uuid::new_v4().to_string();
         let scan_started = Utc::now();
         
         let mut report = SecurityScanReport {
-            scan_id: scan_id.clone(),
+            scan_id,
             project_path: project_path.to_string(),
             scan_started,
             scan_completed: None,
@@ -199,12 +810,14 @@ impl SecurityScanner {
         
         report.scan_completed = Some(Utc::now());
         
-        // Cache the report
-        self.scan_cache.insert(scan_id, report.clone());
-        
         Ok(report)
     }
 
+    /// Cache a report from `scan_project` so it can be looked up by its scan id
+    pub fn store_project_report(&mut self, report: SecurityScanReport) {
+        self.scan_cache.insert(report.scan_id.clone(), report);
+    }
+
     async fn scan_static_analysis(&self, project_path: &str) -> Result<Vec<VulnerabilityResult>> {
         let mut vulnerabilities = Vec::new();
 
@@ -222,7 +835,7 @@ impl SecurityScanner {
     }
 
     async fn scan_dependencies(&self, project_path: &str) -> Result<Vec<DependencyVulnerability>> {
-        let mut dep_vulns = Vec::new();
+        let mut dep_vulns = self.scan_lockfiles(project_path).await?;
 
         // Check for different package managers
         if Path::new(&format!("{}/package.json", project_path)).exists() {
@@ -245,46 +858,96 @@ impl SecurityScanner {
     }
 
     async fn scan_custom_rules(&self, project_path: &str) -> Result<Vec<VulnerabilityResult>> {
-        let mut vulnerabilities = Vec::new();
+        let rules: Vec<SecurityRule> = self.config.custom_rules.iter().filter(|rule| rule.enabled).cloned().collect();
+        Ok(self.scan_files(project_path, &rules, None, false).await?.findings)
+    }
 
-        for rule in &self.config.custom_rules {
-            let results = self.apply_security_rule(project_path, rule).await?;
-            vulnerabilities.extend(results);
-        }
+    async fn scan_secrets(&self, project_path: &str) -> Result<Vec<VulnerabilityResult>> {
+        Ok(self.scan_files(project_path, &builtin_secret_rules(), None, false).await?.findings)
+    }
 
-        Ok(vulnerabilities)
+    /// Enabled custom rules plus the built-in secret patterns
+    fn file_rules(&self) -> Vec<SecurityRule> {
+        let mut rules: Vec<SecurityRule> = self.config.custom_rules.iter().filter(|rule| rule.enabled).cloned().collect();
+        rules.extend(builtin_secret_rules());
+        rules
     }
 
-    async fn scan_secrets(&self, project_path: &str) -> Result<Vec<VulnerabilityResult>> {
-        let mut vulnerabilities = Vec::new();
+    fn manifest_path(&self, project_path: &str, kind: &str) -> Option<PathBuf> {
+        let root = std::fs::canonicalize(project_path).unwrap_or_else(|_| PathBuf::from(project_path));
+        let key = format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()));
+        self.manifest_dir.as_ref().map(|dir| dir.join(format!("{}-{}.json", &key[..16], kind)))
+    }
 
-        // Common secret patterns
-        let secret_patterns = vec![
-            (r#"(?i)api[_-]?key\s*[:=]\s*['"]?[a-zA-Z0-9]{20,}['"]?"#, "API Key"),
-            (r#"(?i)password\s*[:=]\s*['"]?[^\s'"]{8,}['"]?"#, "Password"),
-            (r#"(?i)secret[_-]?key\s*[:=]\s*['"]?[a-zA-Z0-9]{20,}['"]?"#, "Secret Key"),
-            (r#"(?i)token\s*[:=]\s*['"]?[a-zA-Z0-9]{20,}['"]?"#, "Token"),
-            (r"-----BEGIN [A-Z]+ PRIVATE KEY-----", "Private Key"),
-        ];
-
-        for (pattern, secret_type) in secret_patterns {
-            let matches = self.search_pattern(project_path, pattern).await?;
-            for (file_path, _line_number) in matches {
-                vulnerabilities.push(VulnerabilityResult {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    severity: VulnerabilitySeverity::High,
-                    title: format!("Potential {} Exposure", secret_type),
-                    description: format!("Potential {} found in code", secret_type),
-                    affected_files: vec![file_path],
-                    cve_id: None,
-                    cvss_score: Some(8.0),
-                    remediation: Some("Move sensitive data to environment variables or secure configuration".to_string()),
-                    detected_at: Utc::now(),
-                });
+    /// Apply `rules` to every non-excluded file under `project_path`. When a manifest kind is given
+    /// and a manifest directory is configured, files whose size and mtime match the previous scan
+    /// with the same ruleset keep their earlier findings instead of being read again.
+    async fn scan_files(&self, project_path: &str, rules: &[SecurityRule], manifest_kind: Option<&str>, force_full: bool) -> Result<FileScan> {
+        let compiled = rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (rule, regex))
+                    .map_err(|e| anyhow!("Rule '{}' has an invalid pattern: {}", rule.id, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let manifest_path = manifest_kind.and_then(|kind| self.manifest_path(project_path, kind));
+        let ruleset_hash = ruleset_hash(rules)?;
+        let mut previous = ScanManifest::default();
+        if let (Some(path), false) = (&manifest_path, force_full) {
+            if let Ok(json) = tokio::fs::read_to_string(path).await {
+                match serde_json::from_str::<ScanManifest>(&json) {
+                    // A changed ruleset invalidates every cached finding
+                    Ok(manifest) if manifest.ruleset_hash == ruleset_hash => previous = manifest,
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Ignoring unreadable scan manifest {}: {}", path.display(), e),
+                }
             }
         }
 
-        Ok(vulnerabilities)
+        let root = Path::new(project_path);
+        let ignore = ScanIgnore::load(root);
+        let mut scan = FileScan::default();
+        let mut manifest = ScanManifest { ruleset_hash, files: HashMap::new() };
+
+        for entry in walkdir::WalkDir::new(root).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().replace('\\', "/");
+            let applicable: Vec<_> = compiled.iter().filter(|(rule, _)| rule.applies_to(&relative)).collect();
+            if applicable.is_empty() || self.config.exclude_patterns.iter().any(|pattern| glob_match(pattern, &relative)) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let fingerprint = file_fingerprint(&metadata);
+
+            let findings = match previous.files.remove(&relative) {
+                Some(cached) if cached.fingerprint == fingerprint => cached.findings,
+                _ => {
+                    scan.examined.push(relative.clone());
+                    // Binary and unreadable files cannot match a text rule
+                    let content = tokio::fs::read_to_string(entry.path()).await.unwrap_or_default();
+                    let file_path = entry.path().to_string_lossy();
+                    applicable.iter().flat_map(|(rule, regex)| match_rule(rule, regex, &file_path, &content)).collect()
+                }
+            };
+            if ignore.is_ignored(&relative) {
+                scan.ignored += findings.len();
+            } else {
+                scan.findings.extend(findings.iter().cloned());
+            }
+            manifest.files.insert(relative, ManifestEntry { fingerprint, findings });
+        }
+
+        if let Some(path) = manifest_path {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, serde_json::to_vec(&manifest)?).await?;
+        }
+
+        Ok(scan)
     }
 
     async fn run_semgrep_scan(&self, project_path: &str) -> Result<Vec<VulnerabilityResult>> {
@@ -324,6 +987,64 @@ impl SecurityScanner {
         }
     }
 
+    /// Match the packages pinned by lockfiles in `project_path` against the local advisory database
+    async fn scan_lockfiles(&self, project_path: &str) -> Result<Vec<DependencyVulnerability>> {
+        if self.advisories.advisories.is_empty() {
+            tracing::warn!("Advisory database is empty; update it to check dependencies for known vulnerabilities");
+        }
+        let mut found = Vec::new();
+        for name in ["Cargo.lock", "package-lock.json", "requirements.txt"] {
+            let path = Path::new(project_path).join(name);
+            let Ok(text) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            let packages = match name {
+                "Cargo.lock" => crate::advisory_db::parse_cargo_lock(&text),
+                "package-lock.json" => crate::advisory_db::parse_package_lock(&text),
+                _ => Ok(crate::advisory_db::parse_requirements(&text)),
+            };
+            match packages {
+                Ok(packages) => found.extend(self.advisories.check(&packages, &path.to_string_lossy())),
+                Err(e) => tracing::warn!("Skipping {}: {:#}", path.display(), e),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Replace the local advisory database with the feed at `advisory_db_url`. Callers sharing
+    /// the scanner should fetch `advisory_db_source` themselves and pass the result to
+    /// `install_advisory_db`, so the download doesn't hold their lock.
+    pub async fn update_advisory_db(&mut self) -> Result<AdvisoryDbInfo> {
+        let database = AdvisoryDatabase::fetch(&self.advisory_db_source()?).await?;
+        self.install_advisory_db(database).await
+    }
+
+    /// The configured advisory feed
+    pub fn advisory_db_source(&self) -> Result<String> {
+        self.config
+            .advisory_db_url
+            .clone()
+            .ok_or_else(|| anyhow!("No advisory feed configured; set advisory_db_url in the security config"))
+    }
+
+    /// Save a fetched advisory database and use it for the following scans
+    pub async fn install_advisory_db(&mut self, database: AdvisoryDatabase) -> Result<AdvisoryDbInfo> {
+        if let Some(path) = &self.advisory_db_path {
+            database.save(path).await?;
+        }
+        tracing::info!(
+            "Advisory database updated with {} advisories from {}",
+            database.advisories.len(),
+            database.source.as_deref().unwrap_or("unknown source")
+        );
+        self.advisories = database;
+        Ok(self.advisory_db_info())
+    }
+
+    pub fn advisory_db_info(&self) -> AdvisoryDbInfo {
+        self.advisories.info(self.advisory_db_path.as_ref())
+    }
+
     async fn scan_npm_dependencies(&self, project_path: &str) -> Result<Vec<DependencyVulnerability>> {
         let output = Command::new("npm")
             .args(&["audit", "--json"])
@@ -388,53 +1109,6 @@ impl SecurityScanner {
         }
     }
 
-    async fn apply_security_rule(&self, project_path: &str, rule: &SecurityRule) -> Result<Vec<VulnerabilityResult>> {
-        let matches = self.search_pattern(project_path, &rule.pattern).await?;
-        let mut vulnerabilities = Vec::new();
-
-        for (file_path, _line_number) in matches {
-            vulnerabilities.push(VulnerabilityResult {
-                id: uuid::Uuid::new_v4().to_string(),
-                severity: rule.severity.clone(),
-                title: rule.name.clone(),
-                description: rule.description.clone(),
-                affected_files: vec![file_path],
-                cve_id: None,
-                cvss_score: None,
-                remediation: Some(rule.remediation.clone()),
-                detected_at: Utc::now(),
-            });
-        }
-
-        Ok(vulnerabilities)
-    }
-
-    async fn search_pattern(&self, project_path: &str, pattern: &str) -> Result<Vec<(String, Option<u32>)>> {
-        let output = Command::new("grep")
-            .args(&["-rn", "--include=*.py", "--include=*.js", "--include=*.ts", "--include=*.rs", "--include=*.go", pattern, project_path])
-            .output()
-            .await;
-
-        match output {
-            Ok(result) if result.status.success() => {
-                let output_str = String::from_utf8_lossy(&result.stdout);
-                let mut matches = Vec::new();
-                
-                for line in output_str.lines() {
-                    if let Some((file_line, _)) = line.split_once(':') {
-                        if let Some((file_path, line_num_str)) = file_line.rsplit_once(':') {
-                            let line_number = line_num_str.parse::<u32>().ok();
-                            matches.push((file_path.to_string(), line_number));
-                        }
-                    }
-                }
-                
-                Ok(matches)
-            }
-            _ => Ok(Vec::new()),
-        }
-    }
-
     fn parse_semgrep_output(&self, _json_output: &str) -> Result<Vec<VulnerabilityResult>> {
         // Parse Semgrep JSON output and convert to VulnerabilityResult
         // This is a simplified parser - real implementation would be more robust
@@ -529,24 +1203,10 @@ impl SecurityScanner {
         let mut vulnerabilities = Vec::new();
 
         // Apply custom rules to the file
-        for rule in &self.config.custom_rules {
+        for rule in self.config.custom_rules.iter().filter(|rule| rule.enabled && rule.applies_to(file_path)) {
             let file_content = tokio::fs::read_to_string(file_path).await?;
             if let Ok(regex) = regex::Regex::new(&rule.pattern) {
-                for (_line_num, line) in file_content.lines().enumerate() {
-                    if regex.is_match(line) {
-                        vulnerabilities.push(VulnerabilityResult {
-                            id: uuid::Uuid::new_v4().to_string(),
-                            severity: rule.severity.clone(),
-                            title: rule.name.clone(),
-                            description: rule.description.clone(),
-                            affected_files: vec![file_path.to_string()],
-                            cve_id: None,
-                            cvss_score: None,
-                            remediation: Some(rule.remediation.clone()),
-                            detected_at: Utc::now(),
-                        });
-                    }
-                }
+                vulnerabilities.extend(match_rule(rule, &regex, file_path, &file_content));
             }
         }
 
@@ -559,9 +1219,22 @@ impl SecurityScanner {
                 return Ok(Some(vuln.clone()));
             }
         }
+        for result in self.scan_results.values() {
+            if let Some(vuln) = result.vulnerabilities.iter().find(|v| v.id == vulnerability_id) {
+                return Ok(Some(vuln.clone()));
+            }
+        }
         Ok(None)
     }
 
+    fn dependency_details(&self, vulnerability_id: &str) -> Option<&DependencyVulnerability> {
+        self.scan_cache
+            .values()
+            .flat_map(|report| &report.dependency_vulnerabilities)
+            .chain(self.scan_results.values().flat_map(|result| &result.dependency_vulnerabilities))
+            .find(|dependency| dependency.vulnerability.id == vulnerability_id)
+    }
+
     pub async fn auto_remediate(&self, vulnerability_id: &str) -> Result<String> {
         if !self.config.auto_remediation {
             return Err(anyhow!("Auto-remediation is disabled"));
@@ -610,19 +1283,40 @@ impl SecurityScanner {
     }
 
     // Methods expected by main.rs
-    pub async fn scan_directory(&self, path: &str, scan_type: ScanType) -> Result<ScanResult> {
+    pub async fn scan_directory(&mut self, path: &str, scan_type: ScanType) -> Result<ScanResult> {
+        let result = self.run_scan(path, scan_type).await?;
+        self.store_scan_result(result.clone());
+        Ok(result)
+    }
+
+    /// Scan `path` without recording the result. Callers sharing the scanner can run this
+    /// under a read lock and take the write lock only for `store_scan_result`.
+    pub async fn run_scan(&self, path: &str, scan_type: ScanType) -> Result<ScanResult> {
         let scan_id = uuid::Uuid::new_v4().to_string();
         let started_at = Utc::now();
         
-        // Mock implementation - in real app this would run actual scans
-        let vulnerabilities = match scan_type {
-            ScanType::Secrets => self.scan_secrets(path).await?,
-            ScanType::Vulnerabilities => self.scan_static_analysis(path).await?,
-            _ => Vec::new(),
+        let mut dependency_vulnerabilities = Vec::new();
+        let FileScan { findings, examined: rescanned_files, ignored: ignored_findings } = match scan_type {
+            ScanType::Secrets => self.scan_files(path, &builtin_secret_rules(), Some("secrets"), false).await?,
+            ScanType::Vulnerabilities => FileScan { findings: self.scan_static_analysis(path).await?, ..FileScan::default() },
+            ScanType::Comprehensive => self.scan_files(path, &self.file_rules(), Some("comprehensive"), false).await?,
+            ScanType::FullRescan => self.scan_files(path, &self.file_rules(), Some("comprehensive"), true).await?,
+            ScanType::Dependencies => {
+                dependency_vulnerabilities = self.scan_lockfiles(path).await?;
+                let findings = dependency_vulnerabilities.iter().map(|dependency| dependency.vulnerability.clone()).collect();
+                FileScan { findings, ..FileScan::default() }
+            }
+            ScanType::Malware => FileScan::default(),
         };
 
-        let vuln_count = vulnerabilities.len();
-        Ok(ScanResult {
+        let (baselined, vulnerabilities): (Vec<_>, Vec<_>) =
+            findings.into_iter().partition(|finding| self.baseline.accepted.contains(&baseline_key(finding)));
+        dependency_vulnerabilities.retain(|dependency| !self.baseline.accepted.contains(&baseline_key(&dependency.vulnerability)));
+        let mut summary = format!("Found {} vulnerabilities", vulnerabilities.len());
+        if ignored_findings > 0 || !baselined.is_empty() {
+            summary.push_str(&format!(" ({} ignored, {} in baseline)", ignored_findings, baselined.len()));
+        }
+        let result = ScanResult {
             scan_id,
             scan_type,
             project_path: path.to_string(),
@@ -630,23 +1324,105 @@ impl SecurityScanner {
             completed_at: Some(Utc::now()),
             vulnerabilities,
             status: "completed".to_string(),
-            summary: format!("Found {} vulnerabilities", vuln_count),
-        })
+            summary,
+            rescanned_files,
+            ignored_findings,
+            baselined_findings: baselined.len(),
+            dependency_vulnerabilities,
+        };
+        Ok(result)
+    }
+
+    /// Keep `result` for lookups by scan id, dropping the oldest results beyond `MAX_SCAN_RESULTS`
+    pub fn store_scan_result(&mut self, result: ScanResult) {
+        self.scan_results.insert(result.scan_id.clone(), result);
+        while self.scan_results.len() > MAX_SCAN_RESULTS {
+            let Some(oldest) = self.scan_results.values().min_by_key(|result| result.started_at).map(|result| result.scan_id.clone()) else {
+                break;
+            };
+            self.scan_results.remove(&oldest);
+        }
+    }
+
+    /// Accept every finding reported by `scan_id` so later scans leave them out.
+    /// Adds to any existing baseline and returns how many findings it now holds.
+    pub async fn create_baseline(&mut self, scan_id: &str) -> Result<usize> {
+        let findings = match (self.scan_results.get(scan_id), self.scan_cache.get(scan_id)) {
+            (Some(result), _) => &result.vulnerabilities,
+            (None, Some(report)) => &report.vulnerabilities,
+            (None, None) => return Err(anyhow!("Scan results not found for ID: {}", scan_id)),
+        };
+        let keys: Vec<String> = findings.iter().map(baseline_key).collect();
+        self.baseline.accepted.extend(keys);
+        self.baseline.created_at.get_or_insert_with(Utc::now);
+        self.save_baseline().await?;
+        tracing::info!("Security baseline now accepts {} findings", self.baseline.accepted.len());
+        Ok(self.baseline.accepted.len())
+    }
+
+    pub async fn clear_baseline(&mut self) -> Result<()> {
+        self.baseline = Baseline::default();
+        if let Some(dir) = &self.manifest_dir {
+            match tokio::fs::remove_file(dir.join(BASELINE_FILE)).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
     }
 
+    async fn save_baseline(&self) -> Result<()> {
+        let Some(dir) = &self.manifest_dir else {
+            return Ok(());
+        };
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(dir.join(BASELINE_FILE), serde_json::to_vec(&self.baseline)?).await?;
+        Ok(())
+    }
+
+    /// Watch `paths` and report new findings in changed files through `subscribe_findings`.
+    /// Calling this again adds paths to the running monitor.
     pub async fn start_real_time_monitoring(&mut self, paths: Vec<String>) -> Result<()> {
-        // Mock implementation - would set up file system monitoring
-        tracing::info!("Starting real-time monitoring for paths: {:?}", paths);
+        let roots = paths
+            .iter()
+            .map(|path| std::fs::canonicalize(path).map_err(|e| anyhow!("Cannot monitor {}: {}", path, e)))
+            .collect::<Result<Vec<_>>>()?;
+
+        if self.monitor.is_none() {
+            let rules = self
+                .file_rules()
+                .into_iter()
+                .map(|rule| {
+                    Regex::new(&rule.pattern)
+                        .map(|regex| (rule.clone(), regex))
+                        .map_err(|e| anyhow!("Rule '{}' has an invalid pattern: {}", rule.id, e))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let monitor = RealTimeMonitor::start(rules, self.config.exclude_patterns.clone(), self.findings.clone())?;
+            self.monitor = Some(monitor);
+        }
+        let monitor = self.monitor.as_mut().expect("monitor started above");
+        for root in roots {
+            monitor.add_root(root)?;
+        }
         Ok(())
     }
 
     pub async fn stop_real_time_monitoring(&mut self) -> Result<()> {
-        // Mock implementation - would stop file system monitoring
-        tracing::info!("Stopping real-time monitoring");
+        if self.monitor.take().is_some() {
+            tracing::info!("Stopped real-time security monitoring");
+        }
         Ok(())
     }
 
+    pub fn monitoring_status(&self) -> MonitoringStatus {
+        self.monitor.as_ref().map(RealTimeMonitor::status).unwrap_or_default()
+    }
+
     pub async fn get_scan_results(&self, scan_id: &str) -> Result<ScanResult> {
+        if let Some(result) = self.scan_results.get(scan_id) {
+            return Ok(result.clone());
+        }
         if let Some(report) = self.scan_cache.get(scan_id) {
             Ok(ScanResult {
                 scan_id: scan_id.to_string(),
@@ -657,6 +1433,10 @@ impl SecurityScanner {
                 vulnerabilities: report.vulnerabilities.clone(),
                 status: "completed".to_string(),
                 summary: format!("Found {} vulnerabilities", report.vulnerabilities.len()),
+                rescanned_files: Vec::new(),
+                ignored_findings: 0,
+                baselined_findings: 0,
+                dependency_vulnerabilities: report.dependency_vulnerabilities.clone(),
             })
         } else {
             Err(anyhow!("Scan results not found for ID: {}", scan_id))
@@ -673,6 +1453,117 @@ impl SecurityScanner {
         Ok(())
     }
 
+    /// Load a YAML ruleset and merge it into the active rules, replacing rules with the same id.
+    /// Nothing is merged if any rule in the file is invalid.
+    pub async fn load_rules_from_file(&mut self, path: &Path) -> Result<Vec<SecurityRule>> {
+        let yaml = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| anyhow!("Failed to read ruleset {}: {}", path.display(), e))?;
+        let loaded = parse_rule_file(&yaml)?;
+
+        for rule in &loaded {
+            match self.config.custom_rules.iter_mut().find(|existing| existing.id == rule.id) {
+                Some(existing) => *existing = rule.clone(),
+                None => self.config.custom_rules.push(rule.clone()),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Render a completed scan as a SARIF 2.1.0 log
+    pub fn export_sarif(&self, scan_id: &str) -> Result<String> {
+        let report = self.scan_cache.get(scan_id).ok_or_else(|| anyhow!("Scan results not found for ID: {}", scan_id))?;
+        let completed = report.scan_completed.ok_or_else(|| anyhow!("Scan {} has not completed", scan_id))?;
+
+        let mut rules: Vec<SecurityRule> = self.config.custom_rules.clone();
+        rules.extend(builtin_secret_rules());
+        let rule_index: HashMap<&str, usize> = rules.iter().enumerate().map(|(i, rule)| (rule.id.as_str(), i)).collect();
+
+        let root = Path::new(&report.project_path);
+        let location = |file: &str, line: Option<u32>| {
+            let uri = Path::new(file).strip_prefix(root).unwrap_or(Path::new(file)).to_string_lossy().replace('\\', "/");
+            let mut physical = serde_json::json!({ "artifactLocation": { "uri": uri, "uriBaseId": "%SRCROOT%" } });
+            if let Some(line) = line {
+                physical["region"] = serde_json::json!({ "startLine": line });
+            }
+            serde_json::json!({ "physicalLocation": physical })
+        };
+
+        let mut results = Vec::new();
+        for finding in &report.vulnerabilities {
+            let rule_id = finding.rule_id.clone().unwrap_or_else(|| {
+                format!("finding/{}", finding.title.to_lowercase().split_whitespace().collect::<Vec<_>>().join("-"))
+            });
+            let is_secret = rule_id.starts_with("secret/")
+                || rule_index.get(rule_id.as_str()).is_some_and(|&i| rules[i].tags.iter().any(|tag| tag == SECRET_TAG));
+            let mut message = finding.description.clone();
+            if let Some(matched) = &finding.matched_text {
+                let shown = if is_secret { redact_secret(matched) } else { matched.clone() };
+                message.push_str(&format!(": `{}`", shown));
+            }
+
+            let mut result = serde_json::json!({
+                "ruleId": rule_id,
+                "level": sarif_level(&finding.severity),
+                "message": { "text": message },
+                "locations": finding.affected_files.iter().map(|file| location(file, finding.line)).collect::<Vec<_>>(),
+            });
+            if let Some(&index) = rule_index.get(rule_id.as_str()) {
+                result["ruleIndex"] = serde_json::json!(index);
+            }
+            results.push(result);
+        }
+
+        for dependency in &report.dependency_vulnerabilities {
+            let finding = &dependency.vulnerability;
+            results.push(serde_json::json!({
+                "ruleId": finding.cve_id.clone().unwrap_or_else(|| format!("dependency/{}", dependency.package_name)),
+                "level": sarif_level(&finding.severity),
+                "message": {
+                    "text": format!(
+                        "{} {} is vulnerable ({}): {}",
+                        dependency.package_name, dependency.current_version, dependency.vulnerable_version_range, finding.description
+                    )
+                },
+                "locations": finding.affected_files.iter().map(|file| location(file, None)).collect::<Vec<_>>(),
+            }));
+        }
+
+        let sarif = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "Nexus Terminal Security Scanner",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.iter().map(sarif_rule).collect::<Vec<_>>(),
+                    }
+                },
+                "originalUriBaseIds": {
+                    "%SRCROOT%": { "uri": format!("file://{}/", report.project_path.trim_end_matches('/')) }
+                },
+                "invocations": [{
+                    "executionSuccessful": true,
+                    "startTimeUtc": report.scan_started.to_rfc3339(),
+                    "endTimeUtc": completed.to_rfc3339(),
+                }],
+                "results": results,
+            }]
+        });
+        Ok(serde_json::to_string_pretty(&sarif)?)
+    }
+
+    /// Active rules, optionally only those carrying `tag`
+    pub fn rules(&self, tag: Option<&str>) -> Vec<SecurityRule> {
+        self.config
+            .custom_rules
+            .iter()
+            .filter(|rule| tag.is_none_or(|tag| rule.tags.iter().any(|t| t == tag)))
+            .cloned()
+            .collect()
+    }
+
     pub async fn get_vulnerabilities(&self, severity: Option<String>) -> Result<Vec<Vulnerability>> {
         let mut vulnerabilities = Vec::new();
         
@@ -692,44 +1583,110 @@ impl SecurityScanner {
                     }
                 }
                 
-                vulnerabilities.push(Vulnerability {
-                    id: vuln_result.id.clone(),
-                    title: vuln_result.title.clone(),
-                    description: vuln_result.description.clone(),
-                    severity: vuln_result.severity.clone(),
-                    cve_id: vuln_result.cve_id.clone(),
-                    affected_files: vuln_result.affected_files.clone(),
-                    remediation: vuln_result.remediation.clone(),
-                    discovered_at: vuln_result.detected_at,
-                });
+                vulnerabilities.push(Vulnerability::from(vuln_result));
             }
         }
         
         Ok(vulnerabilities)
     }
 
-    pub async fn remediate_vulnerability(&mut self, vulnerability_id: &str, auto_fix: bool) -> Result<RemediationResult> {
-        if let Some(_vuln) = self.get_vulnerability_details(vulnerability_id).await? {
-            let actions_taken = if auto_fix {
-                vec!["Applied automatic fix".to_string()]
-            } else {
-                vec!["Manual remediation required".to_string()]
+    /// With `dry_run`, compute the fix and return it as a diff without writing anything.
+    /// With `auto_fix`, apply the previewed plan (or a fresh one when there was no preview),
+    /// refusing if the files changed since the plan was made.
+    pub async fn remediate_vulnerability(&mut self, vulnerability_id: &str, auto_fix: bool, dry_run: bool) -> Result<RemediationResult> {
+        let Some(vuln) = self.get_vulnerability_details(vulnerability_id).await? else {
+            return Err(anyhow!("Vulnerability not found: {}", vulnerability_id));
+        };
+        let mut result = RemediationResult {
+            vulnerability_id: vulnerability_id.to_string(),
+            success: false,
+            message: "Manual remediation required".to_string(),
+            actions_taken: vec!["Manual remediation required".to_string()],
+            timestamp: Utc::now(),
+            dry_run,
+            diff: String::new(),
+            files_changed: Vec::new(),
+            remediation_id: None,
+        };
+
+        if dry_run {
+            let plan = self.plan_remediation(&vuln).await?;
+            result.success = true;
+            result.message = format!("{} (preview only; nothing was written)", plan.summary);
+            result.actions_taken = plan.files.iter().map(|file| format!("Would edit {}", file.path)).collect();
+            result.files_changed = plan.files.iter().map(|file| file.path.clone()).collect();
+            result.diff = plan.diff.clone();
+            self.remediation_plans.insert(vulnerability_id.to_string(), plan);
+        } else if auto_fix {
+            let plan = match self.remediation_plans.remove(vulnerability_id) {
+                Some(plan) => plan,
+                None => self.plan_remediation(&vuln).await?,
             };
-            
-            Ok(RemediationResult {
-                vulnerability_id: vulnerability_id.to_string(),
-                success: auto_fix,
-                message: if auto_fix {
-                    "Vulnerability remediated automatically".to_string()
-                } else {
-                    "Manual remediation required".to_string()
-                },
-                actions_taken,
-                timestamp: Utc::now(),
-            })
-        } else {
-            Err(anyhow!("Vulnerability not found: {}", vulnerability_id))
+            let backup = crate::remediation::apply(&plan, &self.remediation_backup_dir()?).await?;
+            tracing::info!("Applied remediation {} for {}", backup.id, vulnerability_id);
+            result.success = true;
+            result.message = plan.summary.clone();
+            result.actions_taken = plan.files.iter().map(|file| format!("Edited {}", file.path)).chain(plan.follow_up.clone()).collect();
+            result.files_changed = plan.files.iter().map(|file| file.path.clone()).collect();
+            result.diff = plan.diff;
+            result.remediation_id = Some(backup.id);
         }
+        Ok(result)
+    }
+
+    /// Restore the files an applied remediation changed from its saved backup
+    pub async fn rollback_remediation(&self, remediation_id: &str) -> Result<RemediationResult> {
+        let backup = crate::remediation::rollback(&self.remediation_backup_dir()?, remediation_id).await?;
+        let files_changed: Vec<String> = backup.files.iter().map(|file| file.path.clone()).collect();
+        Ok(RemediationResult {
+            vulnerability_id: backup.vulnerability_id,
+            success: true,
+            message: format!("Rolled back remediation {}", remediation_id),
+            actions_taken: files_changed.iter().map(|path| format!("Restored {}", path)).collect(),
+            timestamp: Utc::now(),
+            dry_run: false,
+            diff: String::new(),
+            files_changed,
+            remediation_id: Some(remediation_id.to_string()),
+        })
+    }
+
+    /// Edits that fix `vuln`: upgrading a vulnerable dependency or removing a hardcoded secret
+    async fn plan_remediation(&self, vuln: &VulnerabilityResult) -> Result<RemediationPlan> {
+        let file = vuln.affected_files.first().ok_or_else(|| anyhow!("'{}' has no affected file to fix", vuln.title))?;
+        if let Some(dependency) = self.dependency_details(&vuln.id) {
+            let fixed = dependency
+                .fixed_version
+                .as_deref()
+                .ok_or_else(|| anyhow!("No patched version of {} is available", dependency.package_name))?;
+            return crate::remediation::plan_dependency_bump(&vuln.id, file, &dependency.package_name, fixed).await;
+        }
+
+        let rule = vuln
+            .rule_id
+            .as_ref()
+            .and_then(|rule_id| self.file_rules().into_iter().find(|rule| &rule.id == rule_id))
+            .filter(|rule| rule.tags.iter().any(|tag| tag == SECRET_TAG));
+        match (rule, vuln.line) {
+            (Some(rule), Some(line)) => {
+                let regex = Regex::new(&rule.pattern).map_err(|e| anyhow!("Rule '{}' has an invalid pattern: {}", rule.id, e))?;
+                crate::remediation::plan_secret_removal(&vuln.id, file, line as usize, &regex).await
+            }
+            _ => Err(anyhow!("No automatic fix for '{}'", vuln.title)),
+        }
+    }
+
+    /// Keep an applied remediation and delete the backup holding the original contents
+    pub async fn confirm_remediation(&self, remediation_id: &str) -> Result<()> {
+        crate::remediation::confirm(&self.remediation_backup_dir()?, remediation_id).await
+    }
+
+    /// Backups can contain the secrets they replaced, so they only go under the app data directory
+    fn remediation_backup_dir(&self) -> Result<PathBuf> {
+        self.manifest_dir
+            .as_ref()
+            .map(|dir| dir.join("remediation-backups"))
+            .ok_or_else(|| anyhow!("Remediation needs the app data directory to keep its backups"))
     }
 }
 
@@ -765,6 +1722,9 @@ mod tests {
                     cvss_score: None,
                     remediation: None,
                     detected_at: Utc::now(),
+                    rule_id: None,
+                    line: None,
+                    matched_text: None,
                 }
             ],
             dependency_vulnerabilities: vec![],
@@ -776,6 +1736,400 @@ mod tests {
         assert_eq!(score, 80.0); // 100 - 20 for critical
     }
 
+    const RULESET: &str = r#"
+rules:
+  - id: no-eval
+    name: Dynamic code evaluation
+    severity: high
+    pattern: '\beval\('
+    files: ["*.js", "*.py"]
+    description: eval executes arbitrary code
+    remediation: Parse the input instead of evaluating it
+    tags: [injection]
+  - id: todo-marker
+    severity: info
+    pattern: 'TODO\(security\)'
+    description: Unresolved security TODO
+    enabled: false
+    tags: [hygiene]
+"#;
+
+    #[tokio::test]
+    async fn test_load_rules_from_yaml_merges_with_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(&path, RULESET).unwrap();
+
+        let mut config = SecurityConfig::default();
+        config.custom_rules.push(SecurityRule {
+            id: "no-eval".to_string(),
+            name: "Old eval rule".to_string(),
+            pattern: "eval".to_string(),
+            severity: VulnerabilitySeverity::Low,
+            description: String::new(),
+            remediation: String::new(),
+            file_globs: vec![],
+            enabled: true,
+            tags: vec![],
+        });
+        let mut scanner = SecurityScanner::new(config);
+
+        let loaded = scanner.load_rules_from_file(&path).await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        let rules = scanner.rules(None);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].name, "Dynamic code evaluation");
+        assert!(matches!(rules[0].severity, VulnerabilitySeverity::High));
+        assert_eq!(rules[1].name, "todo-marker");
+        assert!(!rules[1].enabled);
+        assert_eq!(scanner.rules(Some("injection")).len(), 1);
+    }
+
+    #[test]
+    fn test_bad_regex_names_the_offending_rule() {
+        let ruleset = RULESET.replace(r"TODO\(security\)", "TODO(security");
+        let error = parse_rule_file(&ruleset).unwrap_err().to_string();
+        assert!(error.contains("Rule 'todo-marker' has an invalid pattern"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_rules_only_apply_to_matching_globs() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(project.path().join("web")).unwrap();
+        std::fs::write(project.path().join("web/app.js"), "const x = eval(input);\n").unwrap();
+        std::fs::write(project.path().join("notes.md"), "never call eval(input)\n// TODO(security)\n").unwrap();
+        std::fs::write(project.path().join("tool.py"), "# TODO(security)\n").unwrap();
+
+        let config = SecurityConfig { custom_rules: parse_rule_file(RULESET).unwrap(), ..SecurityConfig::default() };
+        let scanner = SecurityScanner::new(config);
+
+        let findings = scanner.scan_custom_rules(project.path().to_str().unwrap()).await.unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].affected_files[0].ends_with("web/app.js"));
+        assert_eq!(findings[0].title, "Dynamic code evaluation");
+
+        let rule = &scanner.rules(None)[0];
+        assert!(rule.applies_to("src/deep/module.py"));
+        assert!(!rule.applies_to("README.md"));
+    }
+
+    #[test]
+    fn test_sarif_export_for_mixed_severity_scan() {
+        let config = SecurityConfig { custom_rules: parse_rule_file(RULESET).unwrap(), ..SecurityConfig::default() };
+        let mut scanner = SecurityScanner::new(config);
+        let eval_rule = scanner.rules(None)[0].clone();
+        let secret_rule = builtin_secret_rules().into_iter().find(|r| r.id == "secret/api-key").unwrap();
+        let finding = |severity: VulnerabilitySeverity, title: &str| VulnerabilityResult {
+            id: uuid::Uuid::new_v4().to_string(),
+            severity,
+            title: title.to_string(),
+            description: title.to_string(),
+            affected_files: vec!["/work/app/requirements.txt".to_string()],
+            cve_id: None,
+            cvss_score: None,
+            remediation: None,
+            detected_at: Utc::now(),
+            rule_id: None,
+            line: None,
+            matched_text: None,
+        };
+        let low = finding(VulnerabilitySeverity::Low, "Outdated TLS setting");
+        let dependency = finding(VulnerabilitySeverity::Critical, "Remote code execution");
+        let api_key = "api_key = 'ABCDEFGHIJKLMNOPQRSTUVWX'";
+
+        scanner.scan_cache.insert("scan-1".to_string(), SecurityScanReport {
+            scan_id: "scan-1".to_string(),
+            project_path: "/work/app".to_string(),
+            scan_started: Utc::now(),
+            scan_completed: Some(Utc::now()),
+            vulnerabilities: vec![
+                VulnerabilityResult::from_rule(&eval_rule, "/work/app/web/app.js".to_string(), 12, "eval(".to_string()),
+                VulnerabilityResult::from_rule(&secret_rule, "/work/app/settings.py".to_string(), 3, api_key.to_string()),
+                low,
+            ],
+            dependency_vulnerabilities: vec![DependencyVulnerability {
+                package_name: "pyyaml".to_string(),
+                current_version: "5.3".to_string(),
+                vulnerable_version_range: "<5.4".to_string(),
+                fixed_version: Some("5.4".to_string()),
+                vulnerability: VulnerabilityResult { cve_id: Some("CVE-2020-14343".to_string()), ..dependency },
+            }],
+            security_score: None,
+            recommendations: vec![],
+        });
+
+        let sarif: serde_json::Value = serde_json::from_str(&scanner.export_sarif("scan-1").unwrap()).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        assert!(sarif["$schema"].as_str().unwrap().contains("sarif-2.1.0"));
+        let run = &sarif["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert!(run["tool"]["driver"]["name"].is_string());
+        assert!(rules.iter().all(|r| r["id"].is_string() && r["shortDescription"]["text"].is_string()));
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        for result in results {
+            assert!(result["ruleId"].is_string());
+            assert!(result["message"]["text"].is_string());
+            assert!(["error", "warning", "note"].contains(&result["level"].as_str().unwrap()));
+            if let Some(index) = result["ruleIndex"].as_u64() {
+                assert_eq!(rules[index as usize]["id"], result["ruleId"]);
+            }
+        }
+        let levels: Vec<&str> = results.iter().map(|r| r["level"].as_str().unwrap()).collect();
+        assert_eq!(levels, vec!["error", "error", "note", "error"]);
+
+        let eval = &results[0];
+        assert_eq!(eval["ruleId"], "no-eval");
+        let location = &eval["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "web/app.js");
+        assert_eq!(location["region"]["startLine"], 12);
+
+        let secret_message = results[1]["message"]["text"].as_str().unwrap();
+        assert!(secret_message.contains("[REDACTED]"));
+        assert!(!sarif.to_string().contains("ABCDEFGHIJKLMNOPQRSTUVWX"));
+        assert_eq!(results[3]["ruleId"], "CVE-2020-14343");
+
+        assert!(scanner.export_sarif("missing").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_incremental_scan_only_rereads_changed_files() {
+        let project = tempfile::tempdir().unwrap();
+        let manifests = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join("app.js"), "eval(input)\n").unwrap();
+        std::fs::write(project.path().join("lib.py"), "result = eval(expr)\n").unwrap();
+        std::fs::write(project.path().join("notes.txt"), "nothing to see\n").unwrap();
+
+        let config = SecurityConfig { custom_rules: parse_rule_file(RULESET).unwrap(), ..SecurityConfig::default() };
+        let mut scanner = SecurityScanner::new(config).with_manifest_dir(manifests.path().to_path_buf());
+        let path = project.path().to_str().unwrap();
+
+        let first = scanner.scan_directory(path, ScanType::Comprehensive).await.unwrap();
+        let mut examined = first.rescanned_files.clone();
+        examined.sort();
+        assert_eq!(examined, vec!["app.js", "lib.py", "notes.txt"]);
+        assert_eq!(first.vulnerabilities.len(), 2);
+
+        std::fs::write(project.path().join("app.js"), "const safe = JSON.parse(input)\n").unwrap();
+        let second = scanner.scan_directory(path, ScanType::Comprehensive).await.unwrap();
+        assert_eq!(second.rescanned_files, vec!["app.js"]);
+        // The finding in the untouched file is carried forward unchanged
+        assert_eq!(second.vulnerabilities.len(), 1);
+        let carried = &second.vulnerabilities[0];
+        assert!(carried.affected_files[0].ends_with("lib.py"));
+        assert!(first.vulnerabilities.iter().any(|v| v.id == carried.id));
+
+        let forced = scanner.scan_directory(path, ScanType::FullRescan).await.unwrap();
+        assert_eq!(forced.rescanned_files.len(), 3);
+
+        // Changing the active ruleset invalidates the manifest
+        scanner.update_rules(vec![]).await.unwrap();
+        let after_rules_change = scanner.scan_directory(path, ScanType::Comprehensive).await.unwrap();
+        assert_eq!(after_rules_change.rescanned_files.len(), 3);
+        assert!(after_rules_change.vulnerabilities.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ignored_directory_findings_are_counted_not_reported() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(project.path().join("vendor/lib")).unwrap();
+        std::fs::create_dir_all(project.path().join("src")).unwrap();
+        let secret = "api_key = \"abcdefghijklmnopqrstuvwxyz123456\"\n";
+        std::fs::write(project.path().join("vendor/lib/client.py"), secret).unwrap();
+        std::fs::write(project.path().join("vendor/keep.py"), secret).unwrap();
+        std::fs::write(project.path().join("src/app.py"), secret).unwrap();
+        std::fs::write(project.path().join(SCAN_IGNORE_FILE), "# third-party code\nvendor/\n!vendor/keep.py\n").unwrap();
+
+        let mut scanner = SecurityScanner::new(SecurityConfig::default());
+        let result = scanner.scan_directory(project.path().to_str().unwrap(), ScanType::Secrets).await.unwrap();
+        let mut reported: Vec<_> = result.vulnerabilities.iter().map(|v| v.affected_files[0].clone()).collect();
+        reported.sort();
+        assert_eq!(reported.len(), 2);
+        assert!(reported[0].ends_with("src/app.py"));
+        assert!(reported[1].ends_with("vendor/keep.py"));
+        assert_eq!(result.ignored_findings, 1);
+
+        let ignore = ScanIgnore::parse("*.min.js\n/build\n");
+        assert!(ignore.is_ignored("web/app.min.js"));
+        assert!(ignore.is_ignored("build/out.txt"));
+        assert!(!ignore.is_ignored("src/build/out.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_baseline_hides_accepted_findings_but_not_new_ones() {
+        let project = tempfile::tempdir().unwrap();
+        let manifests = tempfile::tempdir().unwrap();
+        let path = project.path().to_str().unwrap();
+        std::fs::write(project.path().join("settings.py"), "password = \"hunter2hunter2\"\n").unwrap();
+
+        let mut scanner = SecurityScanner::new(SecurityConfig::default()).with_manifest_dir(manifests.path().to_path_buf());
+        let first = scanner.scan_directory(path, ScanType::Secrets).await.unwrap();
+        assert_eq!(first.vulnerabilities.len(), 1);
+        assert_eq!(scanner.create_baseline(&first.scan_id).await.unwrap(), 1);
+
+        std::fs::write(project.path().join("deploy.py"), "token = \"abcdefghijklmnopqrstuvwxyz123456\"\n").unwrap();
+        let second = scanner.scan_directory(path, ScanType::Secrets).await.unwrap();
+        assert_eq!(second.vulnerabilities.len(), 1);
+        assert!(second.vulnerabilities[0].affected_files[0].ends_with("deploy.py"));
+        assert_eq!(second.baselined_findings, 1);
+
+        // Accepted findings stay accepted when lines above them are added
+        std::fs::write(project.path().join("settings.py"), "import os\n\npassword = \"hunter2hunter2\"\n").unwrap();
+        let shifted = scanner.scan_directory(path, ScanType::Secrets).await.unwrap();
+        assert_eq!(shifted.vulnerabilities.len(), 1);
+        assert_eq!(shifted.baselined_findings, 1);
+
+        // The baseline outlives the scanner
+        let mut reopened = SecurityScanner::new(SecurityConfig::default()).with_manifest_dir(manifests.path().to_path_buf());
+        assert_eq!(reopened.scan_directory(path, ScanType::Secrets).await.unwrap().baselined_findings, 1);
+
+        reopened.clear_baseline().await.unwrap();
+        let cleared = reopened.scan_directory(path, ScanType::Secrets).await.unwrap();
+        assert_eq!(cleared.vulnerabilities.len(), 2);
+        assert_eq!(cleared.baselined_findings, 0);
+        assert!(scanner.create_baseline("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_recent_scan_results_are_kept() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join("settings.py"), "password = \"hunter2hunter2\"\n").unwrap();
+        let path = project.path().to_str().unwrap();
+
+        let mut scanner = SecurityScanner::new(SecurityConfig::default());
+        let first = scanner.scan_directory(path, ScanType::Secrets).await.unwrap();
+        let mut last = first.clone();
+        for _ in 0..MAX_SCAN_RESULTS {
+            last = scanner.run_scan(path, ScanType::Secrets).await.unwrap();
+            scanner.store_scan_result(last.clone());
+        }
+        assert_eq!(scanner.scan_results.len(), MAX_SCAN_RESULTS);
+        assert!(scanner.get_scan_results(&first.scan_id).await.is_err());
+        assert_eq!(scanner.get_scan_results(&last.scan_id).await.unwrap().vulnerabilities.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dependency_scan_uses_updated_advisory_db() {
+        let project = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        std::fs::write(
+            project.path().join("package-lock.json"),
+            r#"{"lockfileVersion": 3, "packages": {"node_modules/lodash": {"version": "4.17.20"}, "node_modules/minimist": {"version": "1.2.6"}}}"#,
+        )
+        .unwrap();
+        let feed = data.path().join("feed.json");
+        std::fs::write(
+            &feed,
+            r#"[{"id": "GHSA-35jh-r3h4-6jhm", "package": "lodash", "ecosystem": "npm", "title": "Command injection in lodash",
+                 "aliases": ["CVE-2021-23337"], "severity": "High", "patched": [">= 4.17.21"]},
+                {"id": "GHSA-xvch-5gv4-984h", "package": "minimist", "ecosystem": "npm", "title": "Prototype pollution in minimist",
+                 "patched": [">= 1.2.6"], "unaffected": ["< 1.0.0"]}]"#,
+        )
+        .unwrap();
+
+        let db_path = data.path().join("advisories.json");
+        let mut scanner = SecurityScanner::new(SecurityConfig::default()).with_advisory_db(db_path.clone());
+        assert!(scanner.advisory_db_info().stale);
+        assert!(scanner.update_advisory_db().await.is_err());
+
+        scanner.update_config(SecurityConfig { advisory_db_url: Some(feed.to_string_lossy().to_string()), ..SecurityConfig::default() }).await.unwrap();
+        let info = scanner.update_advisory_db().await.unwrap();
+        assert_eq!(info.advisory_count, 2);
+        assert!(!info.stale);
+
+        let mut reopened = SecurityScanner::new(SecurityConfig::default()).with_advisory_db(db_path);
+        assert_eq!(reopened.advisory_db_info().advisory_count, 2);
+        let result = reopened.scan_directory(project.path().to_str().unwrap(), ScanType::Dependencies).await.unwrap();
+        assert_eq!(result.vulnerabilities.len(), 1);
+        let lodash = &result.dependency_vulnerabilities[0];
+        assert_eq!(lodash.package_name, "lodash");
+        assert_eq!(lodash.vulnerable_version_range, "< 4.17.21");
+        assert_eq!(lodash.fixed_version.as_deref(), Some("4.17.21"));
+        assert_eq!(lodash.vulnerability.cve_id.as_deref(), Some("CVE-2021-23337"));
+    }
+
+    #[tokio::test]
+    async fn test_remediation_preview_matches_apply_and_rolls_back() {
+        let project = tempfile::tempdir().unwrap();
+        let manifests = tempfile::tempdir().unwrap();
+        let settings = project.path().join("compose.yaml");
+        let original = "services:\n  db:\n    environment:\n      password: \"hunter2hunter2\"\n      timeout: 30\n";
+        std::fs::write(&settings, original).unwrap();
+
+        let mut scanner = SecurityScanner::new(SecurityConfig::default()).with_manifest_dir(manifests.path().to_path_buf());
+        let scan = scanner.scan_directory(project.path().to_str().unwrap(), ScanType::Secrets).await.unwrap();
+        let id = scan.vulnerabilities[0].id.clone();
+
+        let preview = scanner.remediate_vulnerability(&id, false, true).await.unwrap();
+        let path = settings.to_string_lossy();
+        assert_eq!(
+            preview.diff,
+            format!(
+                "--- {path}\n+++ {path}\n@@ -1,5 +1,5 @@\n services:\n   db:\n     environment:\n-      password: \"hunter2hunter2\"\n+      password: \"${{PASSWORD}}\"\n       timeout: 30\n"
+            )
+        );
+        assert!(preview.dry_run && preview.remediation_id.is_none());
+        assert_eq!(std::fs::read_to_string(&settings).unwrap(), original);
+
+        let applied = scanner.remediate_vulnerability(&id, true, false).await.unwrap();
+        assert_eq!(applied.diff, preview.diff);
+        assert_eq!(std::fs::read_to_string(&settings).unwrap(), original.replace("hunter2hunter2", "${PASSWORD}"));
+
+        let remediation_id = applied.remediation_id.unwrap();
+        scanner.rollback_remediation(&remediation_id).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&settings).unwrap(), original);
+        assert!(scanner.rollback_remediation(&remediation_id).await.is_err());
+
+        // A preview goes stale once the file changes underneath it
+        scanner.remediate_vulnerability(&id, false, true).await.unwrap();
+        std::fs::write(&settings, format!("# edited\n{}", original)).unwrap();
+        let drift = scanner.remediate_vulnerability(&id, true, false).await.unwrap_err();
+        assert!(drift.to_string().contains("changed after the remediation was previewed"));
+
+        // Without an app data directory there is nowhere private to keep the backup
+        let mut unconfigured = SecurityScanner::new(SecurityConfig::default());
+        let scan = unconfigured.scan_directory(project.path().to_str().unwrap(), ScanType::Secrets).await.unwrap();
+        let error = unconfigured.remediate_vulnerability(&scan.vulnerabilities[0].id, true, false).await.unwrap_err();
+        assert!(error.to_string().contains("app data directory"));
+    }
+
+    #[tokio::test]
+    async fn test_real_time_monitoring_reports_new_secrets_once() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(project.path().join("src")).unwrap();
+        let root = project.path().to_str().unwrap().to_string();
+        let nested = project.path().join("src").to_str().unwrap().to_string();
+
+        let mut scanner = SecurityScanner::new(SecurityConfig::default());
+        let mut findings = scanner.subscribe_findings();
+        scanner.start_real_time_monitoring(vec![nested]).await.unwrap();
+        scanner.start_real_time_monitoring(vec![root.clone(), root]).await.unwrap();
+        assert_eq!(scanner.monitoring_status().paths.len(), 1);
+
+        let config = project.path().join("src/config.rs");
+        let leaked = "let api_key = \"abcdefghijklmnopqrstuvwxyz123456\";\n";
+        std::fs::write(&config, leaked).unwrap();
+
+        let finding = tokio::time::timeout(Duration::from_secs(10), findings.recv()).await.expect("no finding event").unwrap();
+        assert!(finding.affected_files[0].ends_with("src/config.rs"));
+        assert!(matches!(finding.severity, VulnerabilitySeverity::High));
+
+        // Same content, then an unrelated edit: neither is a new finding
+        std::fs::write(&config, leaked).unwrap();
+        std::fs::write(&config, format!("{}// reviewed\n", leaked)).unwrap();
+        tokio::time::sleep(MONITOR_DEBOUNCE * 4).await;
+        assert!(matches!(findings.try_recv(), Err(broadcast::error::TryRecvError::Empty)));
+
+        let status = scanner.monitoring_status();
+        assert!(status.active);
+        assert_eq!(status.findings_since_start, 1);
+        assert!(status.last_event_at.is_some());
+
+        scanner.stop_real_time_monitoring().await.unwrap();
+        assert_eq!(scanner.monitoring_status(), MonitoringStatus::default());
+    }
+
     #[tokio::test]
     async fn test_vulnerability_severity_ordering() {
         use std::mem::discriminant;
@@ -0,0 +1,483 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use crate::ecosystem_awareness::EcosystemState;
+use crate::security_scanner::{SecurityScanner, VulnerabilitySeverity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanCategory {
+    Security,
+    Performance,
+    Packages,
+    Services,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingSeverity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl FindingSeverity {
+    /// Health score points deducted for each finding of this severity
+    fn penalty(self) -> u32 {
+        match self {
+            FindingSeverity::Info => 0,
+            FindingSeverity::Low => 1,
+            FindingSeverity::Medium => 4,
+            FindingSeverity::High => 10,
+            FindingSeverity::Critical => 25,
+        }
+    }
+}
+
+impl From<&VulnerabilitySeverity> for FindingSeverity {
+    fn from(severity: &VulnerabilitySeverity) -> Self {
+        match severity {
+            VulnerabilitySeverity::Critical => FindingSeverity::Critical,
+            VulnerabilitySeverity::High => FindingSeverity::High,
+            VulnerabilitySeverity::Medium => FindingSeverity::Medium,
+            VulnerabilitySeverity::Low => FindingSeverity::Low,
+            VulnerabilitySeverity::Info => FindingSeverity::Info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanFinding {
+    pub severity: FindingSeverity,
+    pub title: String,
+    pub detail: String,
+}
+
+impl ScanFinding {
+    pub fn new(severity: FindingSeverity, title: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            severity,
+            title: title.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "error", rename_all = "snake_case")]
+pub enum CategoryStatus {
+    Completed,
+    TimedOut,
+    Cancelled,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryReport {
+    pub category: ScanCategory,
+    pub status: CategoryStatus,
+    pub findings: Vec<ScanFinding>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthGrade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl HealthGrade {
+    fn from_score(score: u8) -> Self {
+        match score {
+            90..=100 => HealthGrade::A,
+            80..=89 => HealthGrade::B,
+            70..=79 => HealthGrade::C,
+            60..=69 => HealthGrade::D,
+            _ => HealthGrade::F,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemScanOptions {
+    /// Project paths given to the security scanner
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Categories to run; empty runs every category
+    #[serde(default)]
+    pub categories: Vec<ScanCategory>,
+    #[serde(default = "default_category_timeout_secs")]
+    pub category_timeout_secs: u64,
+}
+
+fn default_category_timeout_secs() -> u64 {
+    60
+}
+
+impl Default for SystemScanOptions {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            categories: Vec::new(),
+            category_timeout_secs: default_category_timeout_secs(),
+        }
+    }
+}
+
+/// Unified result of a full system scan; `complete` is false if any category did not finish
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemScanReport {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub categories: Vec<CategoryReport>,
+    pub health_score: u8,
+    pub grade: HealthGrade,
+    pub complete: bool,
+}
+
+/// One category of the system scan
+#[async_trait]
+pub trait SystemCheck: Send + Sync {
+    fn category(&self) -> ScanCategory;
+    async fn run(&self, options: &SystemScanOptions) -> Result<Vec<ScanFinding>>;
+}
+
+/// Runs every check concurrently and folds the results into one report
+pub struct SystemScanner {
+    checks: Vec<Arc<dyn SystemCheck>>,
+}
+
+impl SystemScanner {
+    /// Scanner with the built-in security, performance, package and service checks
+    pub fn new(security_scanner: Arc<RwLock<SecurityScanner>>) -> Self {
+        Self::with_checks(vec![
+            Arc::new(SecurityCheck { scanner: security_scanner }),
+            Arc::new(PerformanceCheck),
+            Arc::new(PackagesCheck),
+            Arc::new(ServicesCheck),
+        ])
+    }
+
+    pub fn with_checks(checks: Vec<Arc<dyn SystemCheck>>) -> Self {
+        Self { checks }
+    }
+
+    /// Run the scan; when `cancel` fires, finished categories are kept and the rest marked cancelled
+    pub async fn full_system_scan(&self, options: &SystemScanOptions, cancel: &CancellationToken) -> SystemScanReport {
+        let started_at = Utc::now();
+        let timeout = Duration::from_secs(options.category_timeout_secs);
+
+        let runs = self
+            .checks
+            .iter()
+            .filter(|check| options.categories.is_empty() || options.categories.contains(&check.category()))
+            .map(|check| run_check(check.as_ref(), options, timeout, cancel));
+        let categories = futures::future::join_all(runs).await;
+
+        let penalty: u32 = categories
+            .iter()
+            .flat_map(|c| &c.findings)
+            .map(|f| f.severity.penalty())
+            .sum();
+        let health_score = 100u32.saturating_sub(penalty) as u8;
+
+        SystemScanReport {
+            id: uuid::Uuid::new_v4().to_string(),
+            started_at,
+            completed_at: Utc::now(),
+            complete: categories.iter().all(|c| c.status == CategoryStatus::Completed),
+            categories,
+            health_score,
+            grade: HealthGrade::from_score(health_score),
+        }
+    }
+}
+
+async fn run_check(
+    check: &dyn SystemCheck,
+    options: &SystemScanOptions,
+    timeout: Duration,
+    cancel: &CancellationToken,
+) -> CategoryReport {
+    let started = Instant::now();
+    let (status, findings) = tokio::select! {
+        _ = cancel.cancelled() => (CategoryStatus::Cancelled, Vec::new()),
+        result = tokio::time::timeout(timeout, check.run(options)) => match result {
+            Ok(Ok(findings)) => (CategoryStatus::Completed, findings),
+            Ok(Err(e)) => (CategoryStatus::Failed(e.to_string()), Vec::new()),
+            Err(_) => (CategoryStatus::TimedOut, Vec::new()),
+        },
+    };
+
+    CategoryReport {
+        category: check.category(),
+        status,
+        findings,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+struct SecurityCheck {
+    scanner: Arc<RwLock<SecurityScanner>>,
+}
+
+#[async_trait]
+impl SystemCheck for SecurityCheck {
+    fn category(&self) -> ScanCategory {
+        ScanCategory::Security
+    }
+
+    async fn run(&self, options: &SystemScanOptions) -> Result<Vec<ScanFinding>> {
+        let mut findings = Vec::new();
+        for path in &options.paths {
+            // The write lock is only taken to store each finished report
+            let report = self.scanner.read().await.scan_project(path).await?;
+            self.scanner.write().await.store_project_report(report.clone());
+            findings.extend(report.vulnerabilities.iter().map(|v| {
+                ScanFinding::new((&v.severity).into(), v.title.clone(), format!("{} ({})", v.description, path))
+            }));
+        }
+        Ok(findings)
+    }
+}
+
+struct PerformanceCheck;
+
+#[async_trait]
+impl SystemCheck for PerformanceCheck {
+    fn category(&self) -> ScanCategory {
+        ScanCategory::Performance
+    }
+
+    async fn run(&self, _options: &SystemScanOptions) -> Result<Vec<ScanFinding>> {
+        let state = EcosystemState::collect_initial_state().await?;
+        let mut findings = Vec::new();
+
+        let cpu = state.performance.cpu_usage.current;
+        if cpu > 75.0 {
+            let severity = if cpu > 90.0 { FindingSeverity::High } else { FindingSeverity::Medium };
+            findings.push(ScanFinding::new(severity, "High CPU usage", format!("CPU usage is {:.1}%", cpu)));
+        }
+
+        let memory = state.performance.memory_usage.current;
+        if memory > 80.0 {
+            let severity = if memory > 90.0 { FindingSeverity::High } else { FindingSeverity::Medium };
+            findings.push(ScanFinding::new(severity, "High memory usage", format!("Memory usage is {:.1}%", memory)));
+        }
+
+        for (mount, usage) in &state.filesystem.disk_usage {
+            if usage.usage_percent > 80.0 {
+                let severity = if usage.usage_percent > 90.0 { FindingSeverity::High } else { FindingSeverity::Medium };
+                findings.push(ScanFinding::new(
+                    severity,
+                    format!("Disk almost full: {}", mount),
+                    format!("{:.1}% used", usage.usage_percent),
+                ));
+            }
+        }
+
+        for bottleneck in &state.performance.system_bottlenecks {
+            findings.push(ScanFinding::new(
+                FindingSeverity::Medium,
+                format!("Bottleneck: {}", bottleneck.component),
+                format!("{} {}", bottleneck.description, bottleneck.recommended_action),
+            ));
+        }
+
+        if state.processes.zombie_processes > 0 {
+            findings.push(ScanFinding::new(
+                FindingSeverity::Low,
+                "Zombie processes",
+                format!("{} zombie processes found", state.processes.zombie_processes),
+            ));
+        }
+
+        Ok(findings)
+    }
+}
+
+struct PackagesCheck;
+
+#[async_trait]
+impl SystemCheck for PackagesCheck {
+    fn category(&self) -> ScanCategory {
+        ScanCategory::Packages
+    }
+
+    async fn run(&self, _options: &SystemScanOptions) -> Result<Vec<ScanFinding>> {
+        // Exit codes are ignored: checkupdates and dnf use them only to say whether updates exist
+        let managers: [(&str, &[&str]); 3] = [
+            ("checkupdates", &[]),
+            ("apt", &["list", "--upgradable"]),
+            ("dnf", &["check-update", "-q"]),
+        ];
+
+        for (program, args) in managers {
+            let output = match Command::new(program).args(args).kill_on_drop(true).output().await {
+                Ok(output) => output,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let outdated: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.starts_with("Listing"))
+                .filter_map(|line| line.split(['/', ' ']).next().map(|name| name.to_string()))
+                .collect();
+            if outdated.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let severity = if outdated.len() >= 20 { FindingSeverity::Medium } else { FindingSeverity::Low };
+            let preview: Vec<&str> = outdated.iter().take(10).map(|s| s.as_str()).collect();
+            return Ok(vec![ScanFinding::new(
+                severity,
+                format!("{} outdated packages", outdated.len()),
+                format!("{} reports updates for: {}", program, preview.join(", ")),
+            )]);
+        }
+
+        Err(anyhow!("No supported package manager found"))
+    }
+}
+
+struct ServicesCheck;
+
+#[async_trait]
+impl SystemCheck for ServicesCheck {
+    fn category(&self) -> ScanCategory {
+        ScanCategory::Services
+    }
+
+    async fn run(&self, _options: &SystemScanOptions) -> Result<Vec<ScanFinding>> {
+        let output = Command::new("systemctl")
+            .args(["--failed", "--no-legend", "--plain", "--no-pager"])
+            .kill_on_drop(true)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(anyhow!("systemctl failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(|unit| {
+                ScanFinding::new(
+                    FindingSeverity::High,
+                    format!("Service failed: {}", unit),
+                    format!("Inspect with: systemctl status {}", unit),
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockCheck {
+        category: ScanCategory,
+        findings: Vec<ScanFinding>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl SystemCheck for MockCheck {
+        fn category(&self) -> ScanCategory {
+            self.category
+        }
+
+        async fn run(&self, _options: &SystemScanOptions) -> Result<Vec<ScanFinding>> {
+            tokio::time::sleep(self.delay).await;
+            Ok(self.findings.clone())
+        }
+    }
+
+    fn mock(category: ScanCategory, severities: &[FindingSeverity], delay_ms: u64) -> Arc<dyn SystemCheck> {
+        Arc::new(MockCheck {
+            category,
+            findings: severities.iter().map(|s| ScanFinding::new(*s, "finding", "detail")).collect(),
+            delay: Duration::from_millis(delay_ms),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_report_aggregates_each_category() {
+        let scanner = SystemScanner::with_checks(vec![
+            mock(ScanCategory::Security, &[FindingSeverity::High], 10),
+            mock(ScanCategory::Performance, &[FindingSeverity::Medium, FindingSeverity::Low], 5),
+            mock(ScanCategory::Packages, &[FindingSeverity::Low], 0),
+            mock(ScanCategory::Services, &[], 0),
+        ]);
+
+        let report = scanner.full_system_scan(&SystemScanOptions::default(), &CancellationToken::new()).await;
+        assert!(report.complete);
+        assert_eq!(report.categories.len(), 4);
+        let counts: Vec<usize> = report.categories.iter().map(|c| c.findings.len()).collect();
+        assert_eq!(counts, vec![1, 2, 1, 0]);
+        assert_eq!(report.health_score, 84);
+        assert_eq!(report.grade, HealthGrade::B);
+
+        let options = SystemScanOptions {
+            categories: vec![ScanCategory::Services],
+            ..Default::default()
+        };
+        let report = scanner.full_system_scan(&options, &CancellationToken::new()).await;
+        assert_eq!(report.categories.len(), 1);
+        assert_eq!(report.grade, HealthGrade::A);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_returns_partial_report() {
+        let scanner = SystemScanner::with_checks(vec![
+            mock(ScanCategory::Services, &[FindingSeverity::High], 0),
+            mock(ScanCategory::Security, &[FindingSeverity::Critical], 5_000),
+        ]);
+
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+
+        let report = scanner.full_system_scan(&SystemScanOptions::default(), &cancel).await;
+        assert!(!report.complete);
+        assert_eq!(report.categories[0].status, CategoryStatus::Completed);
+        assert_eq!(report.categories[0].findings.len(), 1);
+        assert_eq!(report.categories[1].status, CategoryStatus::Cancelled);
+        assert_eq!(report.health_score, 90);
+    }
+
+    #[tokio::test]
+    async fn test_slow_category_times_out() {
+        let scanner = SystemScanner::with_checks(vec![
+            mock(ScanCategory::Packages, &[FindingSeverity::Low], 0),
+            mock(ScanCategory::Security, &[], 3_000),
+        ]);
+        let options = SystemScanOptions {
+            category_timeout_secs: 1,
+            ..Default::default()
+        };
+
+        let report = scanner.full_system_scan(&options, &CancellationToken::new()).await;
+        assert_eq!(report.categories[0].status, CategoryStatus::Completed);
+        assert_eq!(report.categories[1].status, CategoryStatus::TimedOut);
+        assert!(report.categories[1].duration_ms < 2_000);
+    }
+}
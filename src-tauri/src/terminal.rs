@@ -1,18 +1,29 @@
 use anyhow::{Context, Result};
 use portable_pty::{Child, CommandBuilder, MasterPty, PtySize, PtySystem};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
-use std::sync::{Arc, Mutex, OnceLock};
-use std::thread;
-use std::time::Duration;
-use tracing::{debug, error, info};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 use tauri::{AppHandle, Emitter};
 
+use crate::command_history::CommandHistoryStore;
+use crate::recording::{CastRecording, RecordingInfo};
+
 // Global app handle for event emission
 static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 
+/// Default number of scrollback lines kept per terminal
+pub const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+// Matches CSI, OSC and single-character escape sequences
+static ANSI_ESCAPE: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+    regex::Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]")
+        .expect("valid ANSI escape regex")
+});
+
 /// Initialize the global app handle for event emission
 pub fn init_app_handle(app_handle: AppHandle) {
     let _ = APP_HANDLE.set(app_handle);
@@ -24,12 +35,532 @@ pub struct TerminalInfo {
     pub shell: String,
     pub cwd: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub status: TerminalStatus,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+}
+
+/// Whether a terminal's shell is still alive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalStatus {
+    #[default]
+    Running,
+    Exited,
+}
+
+/// How often the reaper checks whether a terminal's shell has exited
+const REAP_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What to do when output arrives faster than the frontend is sent it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputOverflow {
+    /// Discard the oldest unsent output; the shell never waits
+    DropOldest,
+    /// Stop reading the PTY until the backlog is sent, which pauses the writing program
+    Block,
+}
+
+/// The `[terminal.output]` section: how PTY output is batched into `terminal-output` events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputBatchingConfig {
+    /// Minimum time between two frames sent to the frontend
+    pub frame_interval_ms: u64,
+    pub max_frame_bytes: usize,
+    /// Unsent output kept per terminal before `overflow` applies
+    pub buffer_bytes: usize,
+    pub overflow: OutputOverflow,
+}
+
+impl Default for OutputBatchingConfig {
+    fn default() -> Self {
+        Self {
+            frame_interval_ms: 16,
+            max_frame_bytes: 64 * 1024,
+            buffer_bytes: 1024 * 1024,
+            overflow: OutputOverflow::Block,
+        }
+    }
+}
+
+/// Coalesces PTY output into frames sent at a bounded rate. Output arriving after an idle
+/// period goes out at once; during a flood, frames are at least `frame_interval_ms` apart.
+#[derive(Debug)]
+pub struct OutputBatcher {
+    config: OutputBatchingConfig,
+    pending: String,
+    last_frame: Option<Instant>,
+    dropped_bytes: usize,
+    closed: bool,
+}
+
+impl OutputBatcher {
+    pub fn new(config: OutputBatchingConfig) -> Self {
+        Self {
+            config,
+            pending: String::new(),
+            last_frame: None,
+            dropped_bytes: 0,
+            closed: false,
+        }
+    }
+
+    /// Whether more output may be pushed without waiting for a frame to go out
+    pub fn has_room(&self) -> bool {
+        self.config.overflow == OutputOverflow::DropOldest || self.pending.len() < self.config.buffer_bytes
+    }
+
+    pub fn push(&mut self, data: &str) {
+        self.pending.push_str(data);
+        if self.config.overflow == OutputOverflow::DropOldest && self.pending.len() > self.config.buffer_bytes {
+            let mut cut = self.pending.len() - self.config.buffer_bytes;
+            while !self.pending.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.pending.drain(..cut);
+            self.dropped_bytes += cut;
+        }
+    }
+
+    /// How long until the next frame is due, or `None` with nothing to send
+    pub fn wait_time(&self, now: Instant) -> Option<Duration> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let due = self.last_frame.map_or(now, |last| last + Duration::from_millis(self.config.frame_interval_ms));
+        Some(due.saturating_duration_since(now))
+    }
+
+    /// The next frame of at most `max_frame_bytes`, if one is due
+    pub fn take_frame(&mut self, now: Instant) -> Option<String> {
+        if !self.wait_time(now)?.is_zero() {
+            return None;
+        }
+        let mut end = self.pending.len().min(self.config.max_frame_bytes.max(4));
+        while !self.pending.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.last_frame = Some(now);
+        Some(self.pending.drain(..end).collect())
+    }
+
+    /// Bytes discarded by `DropOldest` since the last call
+    pub fn take_dropped_bytes(&mut self) -> usize {
+        std::mem::take(&mut self.dropped_bytes)
+    }
+
+    /// No more output will be pushed; what's pending is still sent
+    pub fn close(&mut self) {
+        self.closed = true;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// Send batched output to the frontend until the batcher is closed and drained
+fn run_output_emitter(terminal_id: &str, pump: &(Mutex<OutputBatcher>, Condvar)) {
+    let (batcher, ready) = pump;
+    loop {
+        let (frame, dropped) = {
+            let Ok(mut guard) = batcher.lock() else {
+                return;
+            };
+            let frame = loop {
+                let now = Instant::now();
+                match guard.wait_time(now) {
+                    Some(wait) if wait.is_zero() => break guard.take_frame(now),
+                    Some(wait) => match ready.wait_timeout(guard, wait) {
+                        Ok((next, _)) => guard = next,
+                        Err(_) => return,
+                    },
+                    None if guard.is_closed() => return,
+                    None => match ready.wait(guard) {
+                        Ok(next) => guard = next,
+                        Err(_) => return,
+                    },
+                }
+            };
+            (frame, guard.take_dropped_bytes())
+        };
+        // Wake the reader if it was waiting for room
+        ready.notify_all();
+
+        if dropped > 0 {
+            warn!("Dropped {} bytes of output from terminal {} the UI couldn't keep up with", dropped, terminal_id);
+        }
+        if let (Some(frame), Some(app_handle)) = (frame, APP_HANDLE.get()) {
+            let event = TerminalOutputEvent {
+                terminal_id: terminal_id.to_string(),
+                data: frame,
+            };
+            if let Err(e) = app_handle.emit("terminal-output", &event) {
+                error!("Failed to emit terminal output: {}", e);
+            }
+        }
+    }
+}
+
+/// Export format for terminal scrollback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollbackFormat {
+    /// Escape sequences stripped
+    Plain,
+    /// Raw output including ANSI escape sequences
+    Ansi,
+}
+
+/// Ring buffer of recent terminal output, stored as raw lines.
+///
+/// Lines are kept exactly as the PTY produced them, so resizing the terminal
+/// never rewraps or corrupts stored content.
+#[derive(Debug)]
+pub struct ScrollbackBuffer {
+    lines: VecDeque<String>,
+    partial: String,
+    max_lines: usize,
+}
+
+impl ScrollbackBuffer {
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            partial: String::new(),
+            max_lines: max_lines.max(1),
+        }
+    }
+
+    /// Append a chunk of PTY output, splitting it into lines
+    pub fn push(&mut self, data: &str) {
+        let mut rest = data;
+        while let Some(pos) = rest.find('\n') {
+            self.partial.push_str(&rest[..pos]);
+            let line = std::mem::take(&mut self.partial);
+            self.lines.push_back(line.strip_suffix('\r').unwrap_or(&line).to_string());
+            rest = &rest[pos + 1..];
+        }
+        self.partial.push_str(rest);
+
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Last `max_lines` lines (including an unterminated trailing line)
+    pub fn lines(&self, max_lines: Option<usize>, format: ScrollbackFormat) -> Vec<String> {
+        let mut all: Vec<&str> = self.lines.iter().map(|l| l.as_str()).collect();
+        if !self.partial.is_empty() {
+            all.push(&self.partial);
+        }
+
+        let skip = max_lines.map_or(0, |n| all.len().saturating_sub(n));
+        all.into_iter()
+            .skip(skip)
+            .map(|line| match format {
+                ScrollbackFormat::Plain => strip_ansi(line),
+                ScrollbackFormat::Ansi => line.to_string(),
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.partial.clear();
+    }
+}
+
+/// Remove ANSI escape sequences and stray carriage returns from a line
+pub fn strip_ansi(line: &str) -> String {
+    ANSI_ESCAPE.replace_all(line, "").replace('\r', "")
+}
+
+/// On-screen placement of a terminal, reported by the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowPosition {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub tab_index: Option<usize>,
+}
+
+/// Launch configuration of a single terminal in a saved layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTerminal {
+    pub shell: String,
+    pub args: Option<Vec<String>>,
+    pub cwd: String,
+    pub env: Option<HashMap<String, String>>,
+    pub position: Option<WindowPosition>,
+    #[serde(default)]
+    pub init_commands: Vec<String>,
+}
+
+/// Snapshot of all live terminals, persisted under the config dir
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionLayout {
+    pub saved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub terminals: Vec<SavedTerminal>,
+}
+
+impl SessionLayout {
+    pub fn path() -> Result<std::path::PathBuf> {
+        Ok(crate::config::AppConfig::config_dir()?.join("session_layout.json"))
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        Self::load_from(&Self::path()?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path()?)
+    }
+
+    fn load_from(path: &std::path::Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(path)
+            .context("Failed to read session layout")?;
+        let layout = serde_json::from_str(&content)
+            .context("Failed to parse session layout")?;
+        Ok(Some(layout))
+    }
+
+    fn save_to(&self, path: &std::path::Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize session layout")?;
+        std::fs::write(path, content)
+            .context("Failed to write session layout")?;
+        Ok(())
+    }
+}
+
+// Matches `NAME=value` pairs the security scanner would report as secrets
+static SECRET_ENV: once_cell::sync::Lazy<Vec<regex::Regex>> = once_cell::sync::Lazy::new(|| {
+    crate::security_scanner::builtin_secret_rules()
+        .iter()
+        .filter_map(|rule| regex::Regex::new(&rule.pattern).ok())
+        .collect()
+});
+
+/// The variables of a terminal's environment that are safe to write to the saved layout
+fn persistable_env(env: &HashMap<String, String>) -> HashMap<String, String> {
+    env.iter()
+        .filter(|(name, value)| {
+            let upper = name.to_ascii_uppercase();
+            let secret_name = ["SECRET", "TOKEN", "PASSWORD", "PASSWD", "CREDENTIAL", "AUTH"]
+                .iter()
+                .any(|word| upper.contains(word))
+                || upper.ends_with("KEY");
+            let pair = format!("{}={}", name, value);
+            !secret_name && !SECRET_ENV.iter().any(|regex| regex.is_match(&pair))
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+#[cfg(windows)]
+const PLATFORM_SHELLS: &[&str] = &["powershell.exe", "cmd.exe"];
+#[cfg(not(windows))]
+const PLATFORM_SHELLS: &[&str] = &["/bin/bash", "/bin/sh"];
+
+/// Pick the shell for a terminal when none was requested: `$SHELL`, then the user's
+/// `/etc/passwd` entry, then the platform default. Candidates that don't exist are skipped.
+pub fn detect_default_shell() -> String {
+    #[cfg(unix)]
+    let (passwd, uid) = (
+        std::fs::read_to_string("/etc/passwd").ok(),
+        Some(unsafe { libc::getuid() }),
+    );
+    #[cfg(not(unix))]
+    let (passwd, uid) = (None, None);
+
+    detect_shell(std::env::var("SHELL").ok(), passwd.as_deref(), uid, shell_exists)
+}
+
+fn detect_shell(
+    env_shell: Option<String>,
+    passwd: Option<&str>,
+    uid: Option<u32>,
+    exists: impl Fn(&str) -> bool,
+) -> String {
+    let from_passwd = passwd.zip(uid).and_then(|(passwd, uid)| {
+        passwd.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            (fields.len() >= 7 && fields[2].parse() == Ok(uid)).then(|| fields[6].to_string())
+        })
+    });
+
+    let candidates = [("$SHELL", env_shell), ("/etc/passwd", from_passwd)]
+        .into_iter()
+        .filter_map(|(source, shell)| Some((source, shell.filter(|shell| !shell.trim().is_empty())?)))
+        .chain(PLATFORM_SHELLS.iter().map(|shell| ("platform default", shell.to_string())));
+
+    for (source, shell) in candidates {
+        if exists(&shell) {
+            info!("Using shell {} from {}", shell, source);
+            return shell;
+        }
+        debug!("Skipping shell {} from {}: not found", shell, source);
+    }
+
+    let fallback = PLATFORM_SHELLS[PLATFORM_SHELLS.len() - 1].to_string();
+    warn!("No usable shell found; falling back to {}", fallback);
+    fallback
+}
+
+/// Whether `shell` is an existing file, searching `PATH` for bare names
+fn shell_exists(shell: &str) -> bool {
+    let path = std::path::Path::new(shell);
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(shell).is_file()))
+        .unwrap_or(false)
+}
+
+/// Signals that can be delivered to a terminal's foreground processes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TerminalSignal {
+    Int,
+    Term,
+    Kill,
+    Hup,
+    Tstp,
+    Cont,
+}
+
+impl TerminalSignal {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerminalSignal::Int => "INT",
+            TerminalSignal::Term => "TERM",
+            TerminalSignal::Kill => "KILL",
+            TerminalSignal::Hup => "HUP",
+            TerminalSignal::Tstp => "TSTP",
+            TerminalSignal::Cont => "CONT",
+        }
+    }
+
+    #[cfg(unix)]
+    fn as_raw(&self) -> libc::c_int {
+        match self {
+            TerminalSignal::Int => libc::SIGINT,
+            TerminalSignal::Term => libc::SIGTERM,
+            TerminalSignal::Kill => libc::SIGKILL,
+            TerminalSignal::Hup => libc::SIGHUP,
+            TerminalSignal::Tstp => libc::SIGTSTP,
+            TerminalSignal::Cont => libc::SIGCONT,
+        }
+    }
+}
+
+impl std::str::FromStr for TerminalSignal {
+    type Err = anyhow::Error;
+
+    /// Accepts `INT`, `SIGINT` or `sigint`
+    fn from_str(name: &str) -> Result<Self> {
+        let upper = name.trim().to_ascii_uppercase();
+        match upper.strip_prefix("SIG").unwrap_or(&upper) {
+            "INT" => Ok(TerminalSignal::Int),
+            "TERM" => Ok(TerminalSignal::Term),
+            "KILL" => Ok(TerminalSignal::Kill),
+            "HUP" => Ok(TerminalSignal::Hup),
+            "TSTP" => Ok(TerminalSignal::Tstp),
+            "CONT" => Ok(TerminalSignal::Cont),
+            _ => Err(anyhow::anyhow!(
+                "Unknown signal '{}'; expected one of INT, TERM, KILL, HUP, TSTP, CONT",
+                name
+            )),
+        }
+    }
 }
 
 struct Terminal {
     _child: Box<dyn Child + Send + Sync>,
     master: Box<dyn MasterPty + Send>,
+    /// Taken once at spawn: the PTY hands out a single writer, and dropping it sends EOF
+    writer: Box<dyn Write + Send>,
     info: TerminalInfo,
+    scrollback: Arc<Mutex<ScrollbackBuffer>>,
+    recording: Arc<Mutex<Option<CastRecording>>>,
+    args: Option<Vec<String>>,
+    env: Option<HashMap<String, String>>,
+    position: Option<WindowPosition>,
+}
+
+impl Terminal {
+    /// Current working directory of the shell, falling back to the launch directory
+    fn current_dir(&self) -> String {
+        #[cfg(target_os = "linux")]
+        if let Some(pid) = self._child.process_id() {
+            if let Ok(cwd) = std::fs::read_link(format!("/proc/{}/cwd", pid)) {
+                return cwd.to_string_lossy().to_string();
+            }
+        }
+        self.info.cwd.clone()
+    }
+
+    /// Whether typed input goes to the shell's own prompt, rather than to a program it is
+    /// running (a REPL, `sudo`, an editor) or to a hidden read such as a password prompt
+    fn at_shell_prompt(&self) -> bool {
+        #[cfg(unix)]
+        {
+            let shell_pid = self._child.process_id().map(|pid| pid as libc::pid_t);
+            if self.master.process_group_leader() != shell_pid {
+                return false;
+            }
+            if let Some(fd) = self.master.as_raw_fd() {
+                let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+                if unsafe { libc::tcgetattr(fd, &mut termios) } == 0 {
+                    // Line editors turn off both; canonical input without echo is a hidden read
+                    let canonical = termios.c_lflag & libc::ICANON != 0;
+                    let echo = termios.c_lflag & libc::ECHO != 0;
+                    return !canonical || echo;
+                }
+            }
+        }
+        true
+    }
+
+    /// Terminal info with the shell's current status
+    fn current_info(&mut self) -> TerminalInfo {
+        let mut info = self.info.clone();
+        if let Ok(Some(status)) = self._child.try_wait() {
+            info.status = TerminalStatus::Exited;
+            info.exit_code = Some(status.exit_code() as i32);
+        }
+        info
+    }
+
+    /// Release what the terminal holds once it's been removed from the manager
+    fn close(self, command_history: Option<&Arc<Mutex<CommandHistoryStore>>>) {
+        let terminal_id = &self.info.id;
+        // The output reader may still hold a handle to the buffer until the PTY closes
+        if let Ok(mut buffer) = self.scrollback.lock() {
+            buffer.clear();
+        }
+        // Keep whatever was recorded rather than losing it with the terminal
+        if let Some(recording) = self.recording.lock().ok().and_then(|mut r| r.take()) {
+            match recording.save() {
+                Ok(info) => info!("Saved recording {} to {}", info.id, info.path),
+                Err(e) => error!("Failed to save recording for terminal {}: {}", terminal_id, e),
+            }
+        }
+        if let Some(history) = command_history {
+            if let Ok(mut history) = history.lock() {
+                history.forget_terminal(terminal_id);
+            }
+        }
+    }
 }
 
 // Manual Debug implementation since Child and MasterPty don't implement Debug
@@ -65,10 +596,19 @@ impl PtySystem for SyncPtySystemWrapper {
 pub struct TerminalManager {
     terminals: Arc<Mutex<HashMap<String, Terminal>>>,
     pty_system: Arc<SyncPtySystemWrapper>,
+    scrollback_lines: usize,
+    command_history: Option<Arc<Mutex<CommandHistoryStore>>>,
+    exit_events: tokio::sync::broadcast::Sender<TerminalExitEvent>,
+    output_batching: OutputBatchingConfig,
 }
 
 impl TerminalManager {
     pub fn new() -> Self {
+        Self::with_scrollback_lines(DEFAULT_SCROLLBACK_LINES)
+    }
+
+    /// Create a manager keeping up to `scrollback_lines` lines of output per terminal
+    pub fn with_scrollback_lines(scrollback_lines: usize) -> Self {
         let pty_system = Arc::new(SyncPtySystemWrapper {
             inner: portable_pty::native_pty_system(),
         });
@@ -76,9 +616,30 @@ impl TerminalManager {
         Self {
             terminals: Arc::new(Mutex::new(HashMap::new())),
             pty_system,
+            scrollback_lines,
+            command_history: None,
+            exit_events: tokio::sync::broadcast::channel(16).0,
+            output_batching: OutputBatchingConfig::default(),
         }
     }
 
+    /// Batch output sent to the frontend according to `config`
+    pub fn with_output_batching(mut self, config: OutputBatchingConfig) -> Self {
+        self.output_batching = config;
+        self
+    }
+
+    /// Be notified when a terminal's shell exits on its own
+    pub fn subscribe_exits(&self) -> tokio::sync::broadcast::Receiver<TerminalExitEvent> {
+        self.exit_events.subscribe()
+    }
+
+    /// Record commands typed into terminals in `history`
+    pub fn with_command_history(mut self, history: Arc<Mutex<CommandHistoryStore>>) -> Self {
+        self.command_history = Some(history);
+        self
+    }
+
     pub async fn create_terminal(&mut self, shell: Option<String>) -> Result<String> {
         self.create_terminal_with_config(shell, None, None, None).await
     }
@@ -93,15 +654,7 @@ impl TerminalManager {
         let terminal_id = Uuid::new_v4().to_string();
         
         // Determine shell
-        let shell_cmd = shell.unwrap_or_else(|| {
-            std::env::var("SHELL").unwrap_or_else(|_| {
-                if cfg!(windows) {
-                    "powershell.exe".to_string()
-                } else {
-                    "/bin/bash".to_string()
-                }
-            })
-        });
+        let shell_cmd = shell.unwrap_or_else(detect_default_shell);
 
         // Determine working directory
         let working_dir = cwd.unwrap_or_else(|| {
@@ -136,9 +689,9 @@ impl TerminalManager {
         let mut cmd = CommandBuilder::new(&shell_cmd);
         
         // Add shell arguments if provided
-        if let Some(shell_args) = args {
+        if let Some(shell_args) = &args {
             for arg in shell_args {
-                cmd.arg(&arg);
+                cmd.arg(arg);
             }
         } else {
             // Default arguments based on shell type
@@ -156,9 +709,9 @@ impl TerminalManager {
             cmd.env("TERM", "xterm-256color");
         }
         
-        if let Some(environment) = env {
+        if let Some(environment) = &env {
             for (key, value) in environment {
-                cmd.env(&key, &value);
+                cmd.env(key, value);
             }
         }
 
@@ -175,12 +728,23 @@ impl TerminalManager {
             shell: shell_cmd.clone(),
             cwd: working_dir.clone(),
             created_at: chrono::Utc::now(),
+            status: TerminalStatus::Running,
+            exit_code: None,
         };
 
+        let writer = pty_pair.master.take_writer()
+            .context("Failed to get terminal writer")?;
+
         let terminal = Terminal {
             _child: child,
             master: pty_pair.master,
+            writer,
             info: terminal_info,
+            scrollback: Arc::new(Mutex::new(ScrollbackBuffer::new(self.scrollback_lines))),
+            recording: Arc::new(Mutex::new(None)),
+            args,
+            env,
+            position: None,
         };
 
         // Store terminal
@@ -197,6 +761,7 @@ impl TerminalManager {
 
         // Start reading output in a separate thread
         self.start_output_reader(&terminal_id).await?;
+        self.start_reaper(&terminal_id);
 
         info!("Created terminal with ID: {} using shell: {} in directory: {}", 
               terminal_id, shell_cmd, working_dir);
@@ -206,9 +771,10 @@ impl TerminalManager {
     async fn start_output_reader(&self, terminal_id: &str) -> Result<()> {
         let terminals = Arc::clone(&self.terminals);
         let terminal_id = terminal_id.to_string();
+        let output_batching = self.output_batching.clone();
 
         tokio::spawn(async move {
-            let mut reader = {
+            let (mut reader, scrollback, recording) = {
                 let terminals_guard = match terminals.lock() {
                     Ok(guard) => guard,
                     Err(e) => {
@@ -218,7 +784,11 @@ impl TerminalManager {
                 };
                 if let Some(terminal) = terminals_guard.get(&terminal_id) {
                     match terminal.master.try_clone_reader() {
-                        Ok(reader) => reader,
+                        Ok(reader) => (
+                            reader,
+                            Arc::clone(&terminal.scrollback),
+                            Arc::clone(&terminal.recording),
+                        ),
                         Err(e) => {
                             error!("Failed to clone reader for terminal {}: {}", terminal_id, e);
                             return;
@@ -230,27 +800,45 @@ impl TerminalManager {
                 }
             };
 
+            // Output is batched on its own thread so a flood can't swamp the event bridge
+            let pump = Arc::new((Mutex::new(OutputBatcher::new(output_batching)), Condvar::new()));
+            {
+                let pump = Arc::clone(&pump);
+                let terminal_id = terminal_id.clone();
+                std::thread::spawn(move || run_output_emitter(&terminal_id, &pump));
+            }
+            let (batcher, ready) = &*pump;
+
             let mut buffer = [0u8; 8192];
             loop {
                 match reader.read(&mut buffer) {
                     Ok(n) if n > 0 => {
                         let output = String::from_utf8_lossy(&buffer[..n]);
                         debug!("Terminal {} output: {}", terminal_id, output);
-                        
-                        // Emit output to frontend via Tauri events
-                        if let Some(app_handle) = APP_HANDLE.get() {
-                            let event = TerminalOutputEvent {
-                                terminal_id: terminal_id.clone(),
-                                data: output.to_string(),
-                            };
-                            if let Err(e) = app_handle.emit("terminal-output", &event) {
-                                error!("Failed to emit terminal output: {}", e);
+
+                        if let Ok(mut buffer) = scrollback.lock() {
+                            buffer.push(&output);
+                        }
+
+                        if let Ok(mut recording) = recording.lock() {
+                            if let Some(recording) = recording.as_mut() {
+                                recording.record_output(&output);
+                            }
+                        }
+
+                        if let Ok(mut guard) = batcher.lock() {
+                            // Under `Block`, wait for the emitter to make room
+                            while !guard.has_room() {
+                                guard = ready.wait(guard).unwrap_or_else(|e| e.into_inner());
                             }
+                            guard.push(&output);
                         }
+                        ready.notify_all();
                     }
                     Ok(_) => {
-                        debug!("No data read from terminal {}", terminal_id);
-                        thread::sleep(Duration::from_millis(10));
+                        // A blocking read only returns nothing once the shell has exited
+                        debug!("Terminal {} reached end of output", terminal_id);
+                        break;
                     }
                     Err(e) => {
                         error!("Error reading from terminal {}: {}", terminal_id, e);
@@ -259,25 +847,98 @@ impl TerminalManager {
                 }
             }
 
+            if let Ok(mut guard) = batcher.lock() {
+                guard.close();
+            }
+            ready.notify_all();
             info!("Output reader for terminal {} terminated", terminal_id);
         });
 
         Ok(())
     }
 
+    /// Watch for the shell exiting on its own, then drop the terminal and announce it.
+    /// Whichever of this and `kill_terminal` removes the terminal first does the cleanup.
+    fn start_reaper(&self, terminal_id: &str) {
+        let terminals = Arc::clone(&self.terminals);
+        let command_history = self.command_history.clone();
+        let exit_events = self.exit_events.clone();
+        let terminal_id = terminal_id.to_string();
+
+        // A plain thread: output readers block tokio workers, which could starve a task
+        std::thread::spawn(move || loop {
+            std::thread::sleep(REAP_INTERVAL);
+
+            let exited = {
+                let Ok(mut terminals) = terminals.lock() else {
+                    error!("Terminal lock poisoned; stopping reaper for {}", terminal_id);
+                    return;
+                };
+                let Some(terminal) = terminals.get_mut(&terminal_id) else {
+                    // Killed explicitly
+                    return;
+                };
+                match terminal._child.try_wait() {
+                    Ok(Some(status)) => {
+                        let exit_code = status.exit_code() as i32;
+                        terminals.remove(&terminal_id).map(|terminal| (terminal, exit_code))
+                    }
+                    Ok(None) => None,
+                    Err(e) => {
+                        error!("Failed to check status of terminal {}: {}", terminal_id, e);
+                        None
+                    }
+                }
+            };
+
+            if let Some((terminal, exit_code)) = exited {
+                terminal.close(command_history.as_ref());
+                info!("Terminal {} exited with code {}", terminal_id, exit_code);
+
+                let event = TerminalExitEvent {
+                    terminal_id: terminal_id.clone(),
+                    exit_code: Some(exit_code),
+                };
+                if let Some(app_handle) = APP_HANDLE.get() {
+                    if let Err(e) = app_handle.emit("terminal-exited", &event) {
+                        error!("Failed to emit terminal exit: {}", e);
+                    }
+                }
+                let _ = exit_events.send(event);
+                return;
+            }
+        });
+    }
+
     pub async fn write_to_terminal(&self, terminal_id: &str, data: &str) -> Result<()> {
-        let terminals = self.terminals.lock()
+        let mut terminals = self.terminals.lock()
             .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
         
-        if let Some(terminal) = terminals.get(terminal_id) {
-            let mut writer = terminal.master.take_writer()
-                .context("Failed to get terminal writer")?;
-            
-            writer.write_all(data.as_bytes())
+        if let Some(terminal) = terminals.get_mut(terminal_id) {
+            // Checked before writing, since the shell may hand the terminal on as soon as it reads the line
+            let at_prompt = terminal.at_shell_prompt();
+            terminal.writer.write_all(data.as_bytes())
                 .context("Failed to write to terminal")?;
             
-            writer.flush()
+            terminal.writer.flush()
                 .context("Failed to flush terminal writer")?;
+
+            if let Some(history) = &self.command_history {
+                if let Err(e) = history.lock()
+                    .map_err(|_| anyhow::anyhow!("Command history lock poisoned"))
+                    .and_then(|mut history| {
+                        if at_prompt {
+                            history.record_input(terminal_id, &terminal.info.shell, data)
+                        } else {
+                            // Input to another program is never a shell command
+                            history.forget_terminal(terminal_id);
+                            Ok(())
+                        }
+                    })
+                {
+                    error!("Failed to record command history for terminal {}: {}", terminal_id, e);
+                }
+            }
             
             debug!("Wrote {} bytes to terminal {}", data.len(), terminal_id);
             Ok(())
@@ -300,6 +961,12 @@ impl TerminalManager {
             
             terminal.master.resize(new_size)
                 .context("Failed to resize terminal")?;
+
+            if let Ok(mut recording) = terminal.recording.lock() {
+                if let Some(recording) = recording.as_mut() {
+                    recording.record_resize(cols, rows);
+                }
+            }
             
             debug!("Resized terminal {} to {}x{}", terminal_id, cols, rows);
             Ok(())
@@ -312,7 +979,8 @@ impl TerminalManager {
         let mut terminals = self.terminals.lock()
             .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
         
-        if let Some(_terminal) = terminals.remove(terminal_id) {
+        if let Some(terminal) = terminals.remove(terminal_id) {
+            terminal.close(self.command_history.as_ref());
             // Terminal will be dropped and cleaned up automatically
             info!("Killed terminal {}", terminal_id);
             Ok(())
@@ -321,14 +989,229 @@ impl TerminalManager {
         }
     }
 
+    /// Send a signal to the terminal's foreground process group, e.g. to interrupt a
+    /// runaway command without killing the shell
+    pub fn send_signal(&self, terminal_id: &str, signal: &str) -> Result<()> {
+        let signal: TerminalSignal = signal.parse()?;
+        let mut terminals = self.terminals.lock()
+            .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
+        let terminal = terminals.get_mut(terminal_id)
+            .ok_or_else(|| anyhow::anyhow!("Terminal {} not found", terminal_id))?;
+
+        #[cfg(unix)]
+        {
+            // The foreground job's group, or the shell's own group when nothing is running
+            let pgrp = terminal.master.process_group_leader()
+                .filter(|pgrp| *pgrp > 0)
+                .or_else(|| terminal._child.process_id().map(|pid| pid as libc::pid_t))
+                .ok_or_else(|| anyhow::anyhow!("Terminal {} has no running process", terminal_id))?;
+            if unsafe { libc::killpg(pgrp, signal.as_raw()) } != 0 {
+                return Err(std::io::Error::last_os_error())
+                    .with_context(|| format!("Failed to send SIG{} to process group {}", signal.name(), pgrp));
+            }
+            debug!("Sent SIG{} to process group {} of terminal {}", signal.name(), pgrp, terminal_id);
+        }
+
+        #[cfg(windows)]
+        {
+            // ConPTY turns a typed Ctrl-C into CTRL_C_EVENT for the attached console processes
+            if signal != TerminalSignal::Int {
+                anyhow::bail!("Signal {} is not supported on Windows; only INT (CTRL_C_EVENT) can be sent", signal.name());
+            }
+            terminal.writer.write_all(b"\x03")
+                .and_then(|_| terminal.writer.flush())
+                .context("Failed to send CTRL_C_EVENT")?;
+            debug!("Sent CTRL_C_EVENT to terminal {}", terminal_id);
+        }
+
+        Ok(())
+    }
+
+    /// Get the most recent lines of output with escape sequences stripped
+    pub fn get_scrollback(&self, terminal_id: &str, max_lines: Option<usize>) -> Result<Vec<String>> {
+        self.scrollback_lines(terminal_id, max_lines, ScrollbackFormat::Plain)
+    }
+
+    /// Write the terminal's scrollback to a file in the requested format
+    pub async fn export_scrollback(&self, terminal_id: &str, file_path: &str, format: ScrollbackFormat) -> Result<()> {
+        let lines = self.scrollback_lines(terminal_id, None, format)?;
+        let mut content = lines.join("\n");
+        content.push('\n');
+
+        tokio::fs::write(file_path, content)
+            .await
+            .with_context(|| format!("Failed to write scrollback to {}", file_path))?;
+
+        info!("Exported {} scrollback lines of terminal {} to {}", lines.len(), terminal_id, file_path);
+        Ok(())
+    }
+
+    fn scrollback_lines(&self, terminal_id: &str, max_lines: Option<usize>, format: ScrollbackFormat) -> Result<Vec<String>> {
+        let terminals = self.terminals.lock()
+            .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
+
+        let terminal = terminals.get(terminal_id)
+            .ok_or_else(|| anyhow::anyhow!("Terminal {} not found", terminal_id))?;
+        let buffer = terminal.scrollback.lock()
+            .map_err(|_| anyhow::anyhow!("Scrollback lock poisoned"))?;
+
+        Ok(buffer.lines(max_lines, format))
+    }
+
+    /// Start recording the terminal's output as an asciinema v2 cast
+    pub fn start_recording(&self, terminal_id: &str, output_path: Option<String>) -> Result<String> {
+        let terminals = self.terminals.lock()
+            .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
+        let terminal = terminals.get(terminal_id)
+            .ok_or_else(|| anyhow::anyhow!("Terminal {} not found", terminal_id))?;
+
+        let mut recording = terminal.recording.lock()
+            .map_err(|_| anyhow::anyhow!("Recording lock poisoned"))?;
+        if let Some(active) = recording.as_ref() {
+            return Err(anyhow::anyhow!("Terminal {} is already being recorded ({})", terminal_id, active.id()));
+        }
+
+        let size = terminal.master.get_size().context("Failed to get terminal size")?;
+        let cast = CastRecording::new(
+            terminal_id,
+            size.cols,
+            size.rows,
+            &terminal.info.shell,
+            output_path.map(std::path::PathBuf::from),
+        )?;
+
+        let recording_id = cast.id().to_string();
+        *recording = Some(cast);
+        info!("Started recording {} of terminal {}", recording_id, terminal_id);
+        Ok(recording_id)
+    }
+
+    /// Stop a recording and finish its cast file
+    pub fn stop_recording(&self, recording_id: &str) -> Result<RecordingInfo> {
+        let cast = self.with_recording(recording_id, |recording| recording.take())?
+            .ok_or_else(|| anyhow::anyhow!("Recording {} not found", recording_id))?;
+        let info = cast.save()?;
+        info!("Saved recording {} to {}", info.id, info.path);
+        Ok(info)
+    }
+
+    pub fn pause_recording(&self, recording_id: &str) -> Result<()> {
+        self.with_recording(recording_id, |recording| {
+            if let Some(recording) = recording.as_mut() {
+                recording.pause();
+            }
+        })
+    }
+
+    pub fn resume_recording(&self, recording_id: &str) -> Result<()> {
+        self.with_recording(recording_id, |recording| {
+            if let Some(recording) = recording.as_mut() {
+                recording.resume();
+            }
+        })
+    }
+
+    fn with_recording<T>(&self, recording_id: &str, f: impl FnOnce(&mut Option<CastRecording>) -> T) -> Result<T> {
+        let terminals = self.terminals.lock()
+            .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
+
+        for terminal in terminals.values() {
+            let mut recording = terminal.recording.lock()
+                .map_err(|_| anyhow::anyhow!("Recording lock poisoned"))?;
+            if recording.as_ref().is_some_and(|r| r.id() == recording_id) {
+                return Ok(f(&mut recording));
+            }
+        }
+        Err(anyhow::anyhow!("Recording {} not found", recording_id))
+    }
+
+    /// Record where the frontend placed a terminal so it can be saved in the layout
+    pub fn set_terminal_position(&self, terminal_id: &str, position: WindowPosition) -> Result<()> {
+        let mut terminals = self.terminals.lock()
+            .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
+
+        let terminal = terminals.get_mut(terminal_id)
+            .ok_or_else(|| anyhow::anyhow!("Terminal {} not found", terminal_id))?;
+        terminal.position = Some(position);
+        Ok(())
+    }
+
+    /// Snapshot every live terminal's launch configuration and placement.
+    ///
+    /// Secret-looking environment variables are left out, since the layout is stored as plain JSON.
+    pub fn snapshot_layout(&self, init_commands: &HashMap<String, Vec<String>>) -> Result<SessionLayout> {
+        let terminals = self.terminals.lock()
+            .map_err(|_| anyhow::anyhow!("Terminal lock poisoned"))?;
+
+        let mut saved: Vec<(chrono::DateTime<chrono::Utc>, SavedTerminal)> = terminals
+            .values()
+            .map(|t| (t.info.created_at, SavedTerminal {
+                shell: t.info.shell.clone(),
+                args: t.args.clone(),
+                cwd: t.current_dir(),
+                env: t.env.as_ref().map(persistable_env),
+                position: t.position.clone(),
+                init_commands: init_commands.get(&t.info.id).cloned().unwrap_or_default(),
+            }))
+            .collect();
+        saved.sort_by_key(|(created_at, _)| *created_at);
+
+        Ok(SessionLayout {
+            saved_at: Some(chrono::Utc::now()),
+            terminals: saved.into_iter().map(|(_, t)| t).collect(),
+        })
+    }
+
+    /// Spawn fresh shells matching a saved layout and return their ids.
+    ///
+    /// Saved directories that no longer exist fall back to the home directory.
+    pub async fn restore_layout(&mut self, layout: &SessionLayout, replay_init_commands: bool) -> Result<Vec<String>> {
+        let mut restored = Vec::new();
+
+        for saved in &layout.terminals {
+            let cwd = if std::path::Path::new(&saved.cwd).is_dir() {
+                saved.cwd.clone()
+            } else {
+                let home = dirs::home_dir()
+                    .unwrap_or_else(|| std::path::PathBuf::from("/"))
+                    .to_string_lossy()
+                    .to_string();
+                info!("Saved directory {} no longer exists, restoring terminal in {}", saved.cwd, home);
+                home
+            };
+
+            let terminal_id = self.create_terminal_with_config(
+                Some(saved.shell.clone()),
+                saved.args.clone(),
+                Some(cwd),
+                saved.env.clone(),
+            ).await?;
+
+            if let Some(position) = &saved.position {
+                self.set_terminal_position(&terminal_id, position.clone())?;
+            }
+
+            if replay_init_commands {
+                for command in &saved.init_commands {
+                    self.write_to_terminal(&terminal_id, &format!("{}\n", command)).await?;
+                }
+            }
+
+            restored.push(terminal_id);
+        }
+
+        info!("Restored {} terminals from saved session layout", restored.len());
+        Ok(restored)
+    }
+
     pub fn get_terminal_info(&self, terminal_id: &str) -> Option<TerminalInfo> {
-        let terminals = self.terminals.lock().ok()?;
-        terminals.get(terminal_id).map(|t| t.info.clone())
+        let mut terminals = self.terminals.lock().ok()?;
+        terminals.get_mut(terminal_id).map(Terminal::current_info)
     }
 
     pub fn list_terminals(&self) -> Vec<TerminalInfo> {
         match self.terminals.lock() {
-            Ok(terminals) => terminals.values().map(|t| t.info.clone()).collect(),
+            Ok(mut terminals) => terminals.values_mut().map(Terminal::current_info).collect(),
             Err(_) => {
                 error!("Failed to acquire terminal lock in list_terminals");
                 Vec::new()
@@ -336,6 +1219,14 @@ impl TerminalManager {
         }
     }
 
+    /// Process ids of the shells running in managed terminals
+    pub fn shell_pids(&self) -> Vec<u32> {
+        self.terminals
+            .lock()
+            .map(|terminals| terminals.values().filter_map(|t| t._child.process_id()).collect())
+            .unwrap_or_default()
+    }
+
     pub fn get_terminal_count(&self) -> usize {
         match self.terminals.lock() {
             Ok(terminals) => terminals.len(),
@@ -365,3 +1256,345 @@ impl Default for TerminalManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrollback_splits_lines_and_evicts_oldest() {
+        let mut buffer = ScrollbackBuffer::new(2);
+        buffer.push("one\r\ntw");
+        buffer.push("o\nthree\nfour");
+
+        assert_eq!(buffer.lines(None, ScrollbackFormat::Ansi), vec!["two", "three", "four"]);
+        assert_eq!(buffer.lines(Some(1), ScrollbackFormat::Ansi), vec!["four"]);
+    }
+
+    #[test]
+    fn test_scrollback_plain_format_strips_ansi() {
+        let mut buffer = ScrollbackBuffer::new(10);
+        buffer.push("\x1b[1;31merror\x1b[0m: failed\n");
+
+        assert_eq!(buffer.lines(None, ScrollbackFormat::Plain), vec!["error: failed"]);
+        assert_eq!(buffer.lines(None, ScrollbackFormat::Ansi), vec!["\x1b[1;31merror\x1b[0m: failed"]);
+    }
+
+    /// Output events of a cast file, replayed through a scrollback buffer
+    fn replay_cast(path: &std::path::Path) -> (serde_json::Value, Vec<(f64, String, String)>, Vec<String>) {
+        let cast = std::fs::read_to_string(path).unwrap();
+        let mut lines = cast.lines();
+        let header = serde_json::from_str(lines.next().unwrap()).unwrap();
+        let events: Vec<(f64, String, String)> = lines.map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        let mut screen = ScrollbackBuffer::new(1000);
+        for (_, kind, data) in &events {
+            if kind == "o" {
+                screen.push(data);
+            }
+        }
+        (header, events, screen.lines(None, ScrollbackFormat::Plain))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_recorded_session_replays_to_final_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.cast");
+        let mut manager = TerminalManager::new();
+        let mut exits = manager.subscribe_exits();
+        let env = HashMap::from([("PS1".to_string(), "$ ".to_string())]);
+        let terminal_id = manager
+            .create_terminal_with_config(Some("/bin/sh".to_string()), Some(vec![]), Some(dir.path().to_string_lossy().to_string()), Some(env))
+            .await
+            .unwrap();
+
+        let recording_id = manager.start_recording(&terminal_id, Some(path.to_string_lossy().to_string())).unwrap();
+        manager.resize_terminal(&terminal_id, 100, 30).await.unwrap();
+        manager.write_to_terminal(&terminal_id, "printf 'recorded %s\\n' session\n").await.unwrap();
+
+        // Events reach the file while recording, before stop_recording is called.
+        // A slow shell may print its first prompt after the echoed command, ahead of the output.
+        let printed = |lines: &[String]| lines.iter().any(|line| line.trim_start_matches("$ ") == "recorded session");
+        let mut replayed = Vec::new();
+        for _ in 0..100 {
+            replayed = replay_cast(&path).2;
+            if printed(&replayed) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert!(printed(&replayed), "{:?}", replayed);
+
+        let info = manager.stop_recording(&recording_id).unwrap();
+        // The shell exits so the PTY reader finishes and the runtime can shut down
+        manager.write_to_terminal(&terminal_id, "exit\n").await.unwrap();
+        wait_for_exit(&mut exits).await;
+
+        let (header, events, _) = replay_cast(&path);
+        assert_eq!(header["version"], 2);
+        assert_eq!((header["width"].as_u64(), header["height"].as_u64()), (Some(80), Some(24)));
+        assert_eq!(info.event_count, events.len());
+        assert!(events.iter().any(|(_, kind, data)| kind == "r" && data == "100x30"));
+        assert!(events.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    async fn wait_for_exit(exits: &mut tokio::sync::broadcast::Receiver<TerminalExitEvent>) -> TerminalExitEvent {
+        tokio::time::timeout(Duration::from_secs(10), exits.recv())
+            .await
+            .expect("terminal never exited")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_flooded_output_is_coalesced_into_bounded_frames() {
+        let config = OutputBatchingConfig {
+            frame_interval_ms: 16,
+            max_frame_bytes: 64 * 1024,
+            buffer_bytes: 256 * 1024,
+            overflow: OutputOverflow::Block,
+        };
+        let mut batcher = OutputBatcher::new(config);
+        let start = Instant::now();
+        let chunk = "y\n".repeat(2048);
+        let (mut sent, mut received, mut frames) = (String::new(), String::new(), 0);
+
+        // A `yes`-like source offering 4KB every 100us for one simulated second
+        for step in 0..10_000u64 {
+            let now = start + Duration::from_micros(step * 100);
+            if batcher.has_room() {
+                batcher.push(&chunk);
+                sent.push_str(&chunk);
+            }
+            if let Some(frame) = batcher.take_frame(now) {
+                assert!(frame.len() <= 64 * 1024);
+                received.push_str(&frame);
+                frames += 1;
+            }
+        }
+        assert!(frames <= 1000 / 16 + 1, "{} frames in one second", frames);
+
+        // Drain the backlog; nothing is lost under `Block`
+        let mut now = start + Duration::from_secs(1);
+        while let Some(wait) = batcher.wait_time(now) {
+            now += wait;
+            received.push_str(&batcher.take_frame(now).unwrap());
+        }
+        assert_eq!(received.len(), sent.len());
+        assert!(received == sent);
+    }
+
+    #[test]
+    fn test_output_after_idle_is_sent_immediately() {
+        let mut batcher = OutputBatcher::new(OutputBatchingConfig::default());
+        let start = Instant::now();
+        assert_eq!(batcher.wait_time(start), None);
+
+        batcher.push("$ ");
+        assert_eq!(batcher.take_frame(start).as_deref(), Some("$ "));
+        // A keystroke echo right after waits out the frame interval...
+        batcher.push("l");
+        assert_eq!(batcher.take_frame(start + Duration::from_millis(5)), None);
+        assert_eq!(batcher.wait_time(start + Duration::from_millis(5)), Some(Duration::from_millis(11)));
+        // ...while one after a pause goes straight out
+        batcher.push("s");
+        assert_eq!(batcher.take_frame(start + Duration::from_millis(40)).as_deref(), Some("ls"));
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_newest_output() {
+        let mut batcher = OutputBatcher::new(OutputBatchingConfig {
+            buffer_bytes: 8,
+            overflow: OutputOverflow::DropOldest,
+            ..OutputBatchingConfig::default()
+        });
+        batcher.push("0123456789");
+        batcher.push("ab");
+        assert!(batcher.has_room());
+        assert_eq!(batcher.take_dropped_bytes(), 4);
+        assert_eq!(batcher.take_frame(Instant::now()).as_deref(), Some("456789ab"));
+    }
+
+    #[test]
+    fn test_default_shell_precedence() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/usr/bin/fish\n";
+        let installed = |found: &'static [&'static str]| move |shell: &str| found.contains(&shell);
+        let env = || Some("/usr/bin/zsh".to_string());
+
+        let all = installed(&["/usr/bin/zsh", "/usr/bin/fish", "/bin/bash", "/bin/sh", "powershell.exe", "cmd.exe"]);
+        assert_eq!(detect_shell(env(), Some(passwd), Some(1000), all), "/usr/bin/zsh");
+        // $SHELL unset or blank falls through to the user's passwd entry
+        assert_eq!(detect_shell(None, Some(passwd), Some(1000), all), "/usr/bin/fish");
+        assert_eq!(detect_shell(Some(" ".to_string()), Some(passwd), Some(0), all), "/bin/bash");
+
+        // Missing binaries are skipped
+        let no_zsh = installed(&["/usr/bin/fish", "/bin/sh", "cmd.exe"]);
+        assert_eq!(detect_shell(env(), Some(passwd), Some(1000), no_zsh), "/usr/bin/fish");
+        assert_eq!(detect_shell(env(), Some(passwd), Some(4242), no_zsh), PLATFORM_SHELLS[1]);
+        assert_eq!(detect_shell(env(), None, None, no_zsh), PLATFORM_SHELLS[1]);
+
+        // Nothing usable at all: the platform's last-resort shell
+        assert_eq!(detect_shell(None, None, None, |_: &str| false), PLATFORM_SHELLS[PLATFORM_SHELLS.len() - 1]);
+    }
+
+    #[test]
+    fn test_signal_names() {
+        assert_eq!("INT".parse::<TerminalSignal>().unwrap(), TerminalSignal::Int);
+        assert_eq!("sigtstp".parse::<TerminalSignal>().unwrap(), TerminalSignal::Tstp);
+        assert_eq!(" SIGCONT".parse::<TerminalSignal>().unwrap(), TerminalSignal::Cont);
+        assert!("USR1".parse::<TerminalSignal>().is_err());
+        assert!("".parse::<TerminalSignal>().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    fn child_pids(parent: u32) -> Vec<u32> {
+        std::fs::read_dir("/proc")
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|pid| {
+                std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                    .ok()
+                    .and_then(|stat| stat.rsplit_once(')').and_then(|(_, rest)| rest.split_whitespace().nth(1)?.parse::<u32>().ok()))
+                    == Some(parent)
+            })
+            .collect()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sigint_interrupts_foreground_sleeper() {
+        let mut manager = TerminalManager::new();
+        let mut exits = manager.subscribe_exits();
+        let terminal_id = manager
+            .create_terminal_with_config(Some("/bin/sh".to_string()), Some(vec![]), None, None)
+            .await
+            .unwrap();
+        let shell_pid = manager.shell_pids()[0];
+
+        assert!(manager.send_signal(&terminal_id, "BOGUS").is_err());
+        assert!(manager.send_signal("missing", "INT").is_err());
+
+        manager.write_to_terminal(&terminal_id, "sleep 30\n").await.unwrap();
+        let mut sleeper = Vec::new();
+        for _ in 0..100 {
+            sleeper = child_pids(shell_pid);
+            if !sleeper.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(sleeper.len(), 1, "sleep never started");
+
+        manager.send_signal(&terminal_id, "INT").unwrap();
+        let mut exited = false;
+        for _ in 0..100 {
+            if !std::path::Path::new(&format!("/proc/{}", sleeper[0])).exists() || child_pids(shell_pid).is_empty() {
+                exited = true;
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert!(exited, "sleep survived SIGINT");
+
+        // The shell itself survives the interrupt; let it exit so the PTY reader finishes
+        assert!(std::path::Path::new(&format!("/proc/{}", shell_pid)).exists());
+        manager.write_to_terminal(&terminal_id, "exit\n").await.unwrap();
+        wait_for_exit(&mut exits).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exited_shell_is_reaped() {
+        let mut manager = TerminalManager::new();
+        let mut exits = manager.subscribe_exits();
+        let terminal_id = manager
+            .create_terminal_with_config(Some("/bin/sh".to_string()), Some(vec![]), None, None)
+            .await
+            .unwrap();
+        assert_eq!(manager.get_terminal_info(&terminal_id).unwrap().status, TerminalStatus::Running);
+
+        manager.write_to_terminal(&terminal_id, "exit 3\n").await.unwrap();
+        let event = wait_for_exit(&mut exits).await;
+
+        assert_eq!(event.terminal_id, terminal_id);
+        assert_eq!(event.exit_code, Some(3));
+        assert!(manager.get_terminal_info(&terminal_id).is_none());
+        assert!(manager.list_terminals().is_empty());
+        // Already removed by the reaper, so an explicit kill finds nothing to clean up
+        assert!(manager.kill_terminal(&terminal_id).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_layout_round_trip_leaves_out_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = TerminalManager::new();
+        let mut exits = manager.subscribe_exits();
+        let env = HashMap::from([
+            ("PS1".to_string(), "$ ".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp_abc".to_string()),
+            ("DEPLOY_OPTS".to_string(), "--api-key=0123456789abcdef0123".to_string()),
+        ]);
+        let terminal_id = manager
+            .create_terminal_with_config(Some("/bin/sh".to_string()), Some(vec![]), Some(dir.path().to_string_lossy().to_string()), Some(env))
+            .await
+            .unwrap();
+        let position = WindowPosition { x: 10, y: 20, width: 800, height: 600, tab_index: Some(1) };
+        manager.set_terminal_position(&terminal_id, position).unwrap();
+
+        let init_commands = HashMap::from([(terminal_id.clone(), vec!["ls".to_string()])]);
+        let layout_path = dir.path().join("session_layout.json");
+        manager.snapshot_layout(&init_commands).unwrap().save_to(&layout_path).unwrap();
+        let saved = std::fs::read_to_string(&layout_path).unwrap();
+        assert!(!saved.contains("ghp_abc") && !saved.contains("0123456789abcdef0123"), "{}", saved);
+        manager.write_to_terminal(&terminal_id, "exit\n").await.unwrap();
+        wait_for_exit(&mut exits).await;
+
+        let layout = SessionLayout::load_from(&layout_path).unwrap().unwrap();
+        let mut restored_manager = TerminalManager::new();
+        let mut restored_exits = restored_manager.subscribe_exits();
+        let restored = restored_manager.restore_layout(&layout, false).await.unwrap();
+        assert_eq!(restored.len(), 1);
+
+        let snapshot = restored_manager.snapshot_layout(&HashMap::new()).unwrap();
+        let terminal = &snapshot.terminals[0];
+        assert_eq!(terminal.shell, "/bin/sh");
+        assert_eq!(std::fs::canonicalize(&terminal.cwd).unwrap(), std::fs::canonicalize(dir.path()).unwrap());
+        assert_eq!(terminal.env, Some(HashMap::from([("PS1".to_string(), "$ ".to_string())])));
+        assert_eq!(terminal.position.as_ref().map(|p| (p.x, p.y, p.width, p.height, p.tab_index)), Some((10, 20, 800, 600, Some(1))));
+        assert_eq!(layout.terminals[0].init_commands, vec!["ls"]);
+
+        restored_manager.write_to_terminal(&restored[0], "exit\n").await.unwrap();
+        wait_for_exit(&mut restored_exits).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_hidden_input_is_not_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = Arc::new(Mutex::new(CommandHistoryStore::new(dir.path())));
+        let mut manager = TerminalManager::new().with_command_history(history.clone());
+        let mut exits = manager.subscribe_exits();
+        let terminal_id = manager
+            .create_terminal_with_config(Some("/bin/sh".to_string()), Some(vec![]), None, None)
+            .await
+            .unwrap();
+        let wait_for_prompt = |expected: bool| {
+            for _ in 0..100 {
+                if manager.terminals.lock().unwrap()[&terminal_id].at_shell_prompt() == expected {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            panic!("at_shell_prompt never became {}", expected);
+        };
+
+        wait_for_prompt(true);
+        manager.write_to_terminal(&terminal_id, "stty -echo; read secret; stty echo\n").await.unwrap();
+        // Canonical input without echo, as while a password is typed
+        wait_for_prompt(false);
+        manager.write_to_terminal(&terminal_id, "hunter2\n").await.unwrap();
+        wait_for_prompt(true);
+        manager.write_to_terminal(&terminal_id, "exit\n").await.unwrap();
+        wait_for_exit(&mut exits).await;
+
+        assert_eq!(history.lock().unwrap().recent_commands(10), vec!["stty -echo; read secret; stty echo", "exit"]);
+    }
+}
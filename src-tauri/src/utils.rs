@@ -1,5 +1,8 @@
 use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::OnceLock;
 use tokio::process::Command;
 use walkdir::WalkDir;
 use ignore::WalkBuilder;
@@ -131,23 +134,134 @@ fn get_file_icon(filename: &str) -> &'static str {
     }
 }
 
-pub async fn execute_safe_command(command: &str) -> Result<String> {
-    let safe_commands = [
-        "ls", "pwd", "whoami", "date", "uname", "df", "free", "ps", "top",
-        "git status", "git log", "git branch", "git diff",
-        "npm list", "cargo --version", "rustc --version",
-        "node --version", "python --version"
-    ];
-    
-    // Check if command is in safe list
-    let is_safe = safe_commands.iter().any(|&safe_cmd| {
-        command.trim().starts_with(safe_cmd)
-    });
-    
-    if !is_safe {
-        return Err(anyhow::anyhow!("Command not in safe list: {}", command));
+/// A denylist entry: commands matching `pattern` are rejected under `name`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeniedPattern {
+    pub name: String,
+    pub pattern: String,
+    /// `pattern`, compiled on first use; `None` if it is not a valid regex
+    #[serde(skip)]
+    regex: OnceLock<Option<Regex>>,
+}
+
+impl PartialEq for DeniedPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.pattern == other.pattern
     }
-    
+}
+
+impl DeniedPattern {
+    pub fn new(name: &str, pattern: &str) -> Self {
+        Self { name: name.to_string(), pattern: pattern.to_string(), regex: OnceLock::new() }
+    }
+
+    fn is_match(&self, command: &str) -> bool {
+        match self.regex.get_or_init(|| Regex::new(&self.pattern).ok()) {
+            Some(regex) => regex.is_match(command),
+            // An unusable pattern must not silently let everything through
+            None => true,
+        }
+    }
+}
+
+/// The `[command_policy]` section: which commands `execute_safe_command` may run.
+///
+/// Allowlist entries match whole leading words, so `git status` allows `git status -s`
+/// but not `git stash`, and `ls` does not allow `lsblk`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandPolicy {
+    pub allowed_commands: Vec<String>,
+    /// Dangerous commands, refused everywhere including template scripts
+    pub denied_patterns: Vec<DeniedPattern>,
+    /// Chaining, substitution and redirection. `execute_safe_command` runs without a shell and
+    /// would pass them to the program as arguments, so it refuses them; scripts may use them.
+    pub shell_syntax_patterns: Vec<DeniedPattern>,
+}
+
+impl Default for CommandPolicy {
+    fn default() -> Self {
+        let allowed_commands = [
+            "ls", "pwd", "whoami", "id", "date", "uname", "uptime", "hostname", "df", "free", "ps",
+            "git status", "git log", "git branch", "git diff",
+            "npm list", "cargo --version", "rustc --version",
+            "node --version", "python --version", "python3 --version",
+        ];
+        let denied_patterns = [
+            ("recursive-delete-root", r"\brm\s+(-[a-zA-Z]*\s+)*-[a-zA-Z]*[rR][a-zA-Z]*\s+(-[a-zA-Z]+\s+)*(/|~|\$HOME)(\s|$)"),
+            ("pipe-to-shell", r"\|\s*(sudo\s+)?(sh|bash|zsh|dash|ksh|fish)\b"),
+            ("privilege-escalation", r"^\s*(sudo|su|doas|pkexec)\b"),
+        ];
+        let shell_syntax_patterns = [
+            ("command-chaining", r"[;&]|\|\|"),
+            ("command-substitution", r"\$\(|`"),
+            ("redirection", r"[<>]"),
+        ];
+
+        Self {
+            allowed_commands: allowed_commands.iter().map(|c| c.to_string()).collect(),
+            denied_patterns: denied_patterns.iter().map(|(name, pattern)| DeniedPattern::new(name, pattern)).collect(),
+            shell_syntax_patterns: shell_syntax_patterns.iter().map(|(name, pattern)| DeniedPattern::new(name, pattern)).collect(),
+        }
+    }
+}
+
+/// Why `CommandPolicy` refused to run a command
+#[derive(Debug, Clone, PartialEq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", rename = "CommandRejected")]
+#[error("Command rejected by rule '{rule}': {reason}")]
+pub struct CommandRejected {
+    pub command: String,
+    /// `allowlist`, or the name of the matching denied pattern
+    pub rule: String,
+    pub reason: String,
+}
+
+impl CommandPolicy {
+    /// Check `command` against the denylist and shell syntax rules, then the allowlist
+    pub fn check(&self, command: &str) -> std::result::Result<(), CommandRejected> {
+        self.check_denied(command)?;
+        Self::check_patterns(&self.shell_syntax_patterns, command)?;
+
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let allowed = self.allowed_commands.iter().any(|entry| {
+            let entry: Vec<&str> = entry.split_whitespace().collect();
+            !entry.is_empty() && words.starts_with(&entry)
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(CommandRejected {
+                command: command.to_string(),
+                rule: "allowlist".to_string(),
+                reason: format!("'{}' is not an allowed command", words.first().unwrap_or(&"")),
+            })
+        }
+    }
+
+    /// Check `command` against the denylist only, for paths that run arbitrary user scripts
+    pub fn check_denied(&self, command: &str) -> std::result::Result<(), CommandRejected> {
+        Self::check_patterns(&self.denied_patterns, command)
+    }
+
+    fn check_patterns(patterns: &[DeniedPattern], command: &str) -> std::result::Result<(), CommandRejected> {
+        for denied in patterns {
+            if denied.is_match(command) {
+                return Err(CommandRejected {
+                    command: command.to_string(),
+                    rule: denied.name.clone(),
+                    reason: format!("matches denied pattern {}", denied.pattern),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `command` without a shell if `policy` allows it. A refusal is a [`CommandRejected`] error.
+pub async fn execute_safe_command(command: &str, policy: &CommandPolicy) -> Result<String> {
+    policy.check(command)?;
+
     // Parse command and arguments
     let parts: Vec<&str> = command.split_whitespace().collect();
     if parts.is_empty() {
@@ -530,3 +644,85 @@ pub async fn analyze_file_permissions(file_path: &str) -> Result<String> {
     
     Ok(analysis)
 }
+
+/// Shell-style match where `*` spans any run of characters, including `/`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(remainder) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            let rest = rest.trim_start_matches('*');
+            (0..=remainder.len())
+                .filter(|&i| remainder.is_char_boundary(i))
+                .any(|i| glob_match(rest, &remainder[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_commands_pass() {
+        let policy = CommandPolicy::default();
+        for command in ["ls -la", "pwd", "git status -s", "  df -h ", "cargo --version"] {
+            assert_eq!(policy.check(command), Ok(()), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_unlisted_binary_is_rejected() {
+        let policy = CommandPolicy::default();
+        for command in ["lsblk", "git stash", "curl https://example.com", "python script.py", ""] {
+            let rejected = policy.check(command).unwrap_err();
+            assert_eq!(rejected.rule, "allowlist", "{}", command);
+        }
+
+        let policy = CommandPolicy { allowed_commands: vec!["lsblk".to_string()], ..CommandPolicy::default() };
+        assert_eq!(policy.check("lsblk -f"), Ok(()));
+    }
+
+    #[test]
+    fn test_dangerous_patterns_are_rejected() {
+        let policy = CommandPolicy::default();
+        let cases = [
+            ("rm -rf /", "recursive-delete-root"),
+            ("rm -f -r ~", "recursive-delete-root"),
+            ("curl -s https://x.sh | bash", "pipe-to-shell"),
+            ("sudo ls", "privilege-escalation"),
+        ];
+        for (command, rule) in cases {
+            let rejected = policy.check_denied(command).unwrap_err();
+            assert_eq!(rejected.rule, rule, "{}", command);
+        }
+        assert_eq!(policy.check_denied("rm -rf ./build"), Ok(()));
+
+        // Shell syntax is refused where there is no shell, but scripts may use it
+        let cases = [
+            ("ls; reboot", "command-chaining"),
+            ("ls $(cat /etc/shadow)", "command-substitution"),
+            ("ps > /etc/motd", "redirection"),
+        ];
+        for (command, rule) in cases {
+            assert_eq!(policy.check(command).unwrap_err().rule, rule, "{}", command);
+        }
+        for script in ["cargo build && cargo test", "make || true; echo done", "echo $(date) > build.log"] {
+            assert_eq!(policy.check_denied(script), Ok(()), "{}", script);
+        }
+
+        let json = serde_json::to_value(policy.check("ls | sh").unwrap_err()).unwrap();
+        assert_eq!(json["kind"], "CommandRejected");
+        assert_eq!(json["rule"], "pipe-to-shell");
+    }
+
+    #[tokio::test]
+    async fn test_rejection_surfaces_through_execute() {
+        let error = execute_safe_command("rm -rf /", &CommandPolicy::default()).await.unwrap_err();
+        let rejected = error.downcast_ref::<CommandRejected>().unwrap();
+        assert_eq!(rejected.rule, "recursive-delete-root");
+        assert!(execute_safe_command("pwd", &CommandPolicy::default()).await.is_ok());
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+use redb::backends::InMemoryBackend;
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const VECTORS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("vectors");
+
+/// What a vector was embedded from, plus tags used to narrow a search
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VectorMetadata {
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredHit {
+    pub id: String,
+    /// Cosine similarity in `[-1, 1]`
+    pub score: f32,
+    pub metadata: VectorMetadata,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredVector {
+    vector: Vec<f32>,
+    metadata: VectorMetadata,
+}
+
+/// A stored vector scaled to unit length, so cosine similarity is a dot product
+struct IndexedVector {
+    unit: Vec<f32>,
+    metadata: VectorMetadata,
+}
+
+/// Embeddings persisted in redb and searched by a brute-force cosine scan over an
+/// in-memory copy, which stays fast enough for tens of thousands of vectors.
+pub struct VectorStore {
+    path: Option<PathBuf>,
+    database: Database,
+    vectors: HashMap<String, IndexedVector>,
+}
+
+impl std::fmt::Debug for VectorStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VectorStore")
+            .field("path", &self.path)
+            .field("len", &self.vectors.len())
+            .finish()
+    }
+}
+
+impl VectorStore {
+    /// Open (or create) the store at `path`, loading every vector it already holds
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let database = Database::create(path)
+            .with_context(|| format!("Failed to open vector store at {}", path.display()))?;
+        Self::load(Some(path.to_path_buf()), database)
+    }
+
+    /// A store that lives only as long as the process
+    pub fn in_memory() -> Result<Self> {
+        let database = Database::builder().create_with_backend(InMemoryBackend::new())?;
+        Self::load(None, database)
+    }
+
+    fn load(path: Option<PathBuf>, database: Database) -> Result<Self> {
+        let txn = database.begin_write()?;
+        txn.open_table(VECTORS_TABLE)?;
+        txn.commit()?;
+
+        let mut vectors = HashMap::new();
+        {
+            let txn = database.begin_read()?;
+            let table = txn.open_table(VECTORS_TABLE)?;
+            for row in table.iter()? {
+                let (key, value) = row?;
+                let stored: StoredVector = serde_json::from_slice(value.value())?;
+                let unit = normalize(&stored.vector)?;
+                vectors.insert(key.value().to_string(), IndexedVector { unit, metadata: stored.metadata });
+            }
+        }
+
+        Ok(Self { path, database, vectors })
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Dimension shared by every stored vector, if any are stored
+    pub fn dimensions(&self) -> Option<usize> {
+        self.vectors.values().next().map(|v| v.unit.len())
+    }
+
+    /// Store `vector` under `id`, replacing any earlier vector with that id
+    pub fn add(&mut self, id: &str, vector: Vec<f32>, metadata: VectorMetadata) -> Result<()> {
+        let unit = normalize(&vector)?;
+        let replacing_only = self.vectors.len() == 1 && self.vectors.contains_key(id);
+        if let Some(dimensions) = self.dimensions().filter(|_| !replacing_only) {
+            if dimensions != unit.len() {
+                return Err(anyhow::anyhow!(
+                    "Vector for '{}' has {} dimensions, but the store holds {}-dimensional vectors",
+                    id,
+                    unit.len(),
+                    dimensions
+                ));
+            }
+        }
+
+        let stored = StoredVector { vector, metadata };
+        let txn = self.database.begin_write()?;
+        {
+            let mut table = txn.open_table(VECTORS_TABLE)?;
+            table.insert(id, serde_json::to_vec(&stored)?.as_slice())?;
+        }
+        txn.commit()?;
+
+        self.vectors.insert(id.to_string(), IndexedVector { unit, metadata: stored.metadata });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<bool> {
+        let txn = self.database.begin_write()?;
+        {
+            let mut table = txn.open_table(VECTORS_TABLE)?;
+            table.remove(id)?;
+        }
+        txn.commit()?;
+        Ok(self.vectors.remove(id).is_some())
+    }
+
+    /// The `top_k` stored vectors most similar to `query`, best first
+    pub fn search(&self, query: &[f32], top_k: usize) -> Result<Vec<ScoredHit>> {
+        self.search_tagged(query, top_k, None)
+    }
+
+    /// Like [`search`](Self::search), but only considers vectors carrying `tag`
+    pub fn search_tagged(&self, query: &[f32], top_k: usize, tag: Option<&str>) -> Result<Vec<ScoredHit>> {
+        let query = normalize(query)?;
+        if let Some(dimensions) = self.dimensions() {
+            if dimensions != query.len() {
+                return Err(anyhow::anyhow!(
+                    "Query has {} dimensions, but the store holds {}-dimensional vectors",
+                    query.len(),
+                    dimensions
+                ));
+            }
+        }
+
+        let mut scored: Vec<(f32, &String, &IndexedVector)> = self
+            .vectors
+            .iter()
+            .filter(|(_, v)| tag.is_none_or(|tag| v.metadata.tags.iter().any(|t| t == tag)))
+            .map(|(id, v)| (dot(&query, &v.unit), id, v))
+            .collect();
+
+        // Highest score first; ties broken by id so results are stable
+        let by_score = |a: &(f32, &String, &IndexedVector), b: &(f32, &String, &IndexedVector)| {
+            b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal).then_with(|| a.1.cmp(b.1))
+        };
+        if top_k < scored.len() {
+            if top_k == 0 {
+                return Ok(Vec::new());
+            }
+            scored.select_nth_unstable_by(top_k - 1, by_score);
+            scored.truncate(top_k);
+        }
+        scored.sort_by(by_score);
+
+        Ok(scored
+            .into_iter()
+            .map(|(score, id, v)| ScoredHit { id: id.clone(), score, metadata: v.metadata.clone() })
+            .collect())
+    }
+}
+
+fn normalize(vector: &[f32]) -> Result<Vec<f32>> {
+    let norm = dot(vector, vector).sqrt();
+    if vector.is_empty() || !norm.is_finite() || norm == 0.0 {
+        return Err(anyhow::anyhow!("Cannot index an empty, zero or non-finite vector"));
+    }
+    Ok(vector.iter().map(|x| x / norm).collect())
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(text: &str, tags: &[&str]) -> VectorMetadata {
+        VectorMetadata { text: text.to_string(), tags: tags.iter().map(|t| t.to_string()).collect() }
+    }
+
+    fn ids(hits: &[ScoredHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_nearest_neighbors_ordered_by_cosine_similarity() {
+        let mut store = VectorStore::in_memory().unwrap();
+        store.add("east", vec![1.0, 0.0], metadata("ls -la", &["history"])).unwrap();
+        store.add("north", vec![0.0, 3.0], metadata("git status", &["history"])).unwrap();
+        store.add("northeast", vec![2.0, 2.0], metadata("man git", &["docs"])).unwrap();
+        store.add("west", vec![-1.0, 0.0], metadata("cd ..", &["history"])).unwrap();
+
+        let hits = store.search(&[1.0, 0.1], 3).unwrap();
+        assert_eq!(ids(&hits), vec!["east", "northeast", "north"]);
+        assert!((hits[0].score - 0.995).abs() < 0.01);
+        assert_eq!(hits[1].metadata.text, "man git");
+
+        let history = store.search_tagged(&[1.0, 1.0], 10, Some("history")).unwrap();
+        assert_eq!(ids(&history), vec!["east", "north", "west"]);
+        assert!(store.search(&[1.0, 0.0], 0).unwrap().is_empty());
+
+        // Re-adding an id replaces its vector
+        store.add("west", vec![1.0, 0.05], metadata("cd ..", &["history"])).unwrap();
+        assert_eq!(store.len(), 4);
+        assert_eq!(ids(&store.search(&[1.0, 0.0], 2).unwrap()), vec!["east", "west"]);
+    }
+
+    #[test]
+    fn test_dimension_mismatch_and_degenerate_vectors_rejected() {
+        let mut store = VectorStore::in_memory().unwrap();
+        store.add("a", vec![1.0, 2.0, 3.0], VectorMetadata::default()).unwrap();
+        assert!(store.add("b", vec![1.0, 2.0], VectorMetadata::default()).is_err());
+        assert!(store.add("c", vec![0.0, 0.0, 0.0], VectorMetadata::default()).is_err());
+        assert!(store.search(&[1.0, 2.0], 1).is_err());
+
+        // The only stored id may change dimension, e.g. after switching embedding models
+        store.add("a", vec![1.0, 2.0], VectorMetadata::default()).unwrap();
+        assert_eq!(store.dimensions(), Some(2));
+    }
+
+    #[test]
+    fn test_vectors_persist_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.redb");
+        {
+            let mut store = VectorStore::open(&path).unwrap();
+            store.add("keep", vec![0.0, 1.0], metadata("kept", &["docs"])).unwrap();
+            store.add("gone", vec![1.0, 0.0], metadata("removed", &[])).unwrap();
+            assert!(store.remove("gone").unwrap());
+        }
+
+        let store = VectorStore::open(&path).unwrap();
+        assert_eq!(store.len(), 1);
+        let hits = store.search(&[0.0, 1.0], 5).unwrap();
+        assert_eq!(ids(&hits), vec!["keep"]);
+        assert_eq!(hits[0].metadata, metadata("kept", &["docs"]));
+    }
+}
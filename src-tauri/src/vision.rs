@@ -5,6 +5,15 @@ use chrono::Utc;
 use std::io::Cursor;
 use base64::Engine;
 use image::{Rgba, GenericImageView};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::cache::{Cache, CacheConfig, CacheMetrics};
+use crate::ocr::{OcrEngine, OcrEngineInfo, TesseractEngine};
+
+const OCR_CACHE_MAX_ENTRIES: usize = 500;
+const OCR_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 60 * 60);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScreenCapture {
@@ -15,9 +24,194 @@ pub struct ScreenCapture {
     pub width: u32,
     pub height: u32,
     pub region: Option<CaptureRegion>,
+    /// Display the capture was taken from
+    #[serde(default)]
+    pub display_index: usize,
+    /// Position of that display's top-left corner on the virtual desktop
+    #[serde(default)]
+    pub offset_x: i32,
+    #[serde(default)]
+    pub offset_y: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A connected monitor and where it sits on the virtual desktop
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayInfo {
+    pub index: usize,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub is_primary: bool,
+}
+
+/// Enumerates and captures displays
+pub trait DisplayProvider: std::fmt::Debug + Send + Sync {
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>>;
+
+    /// Grab one frame from `display`, failing if it is no longer connected
+    fn capture(&self, display: &DisplayInfo) -> Result<image::RgbImage>;
+}
+
+/// Captures through `scrap`, with monitor layout from `xrandr` where available
+#[derive(Debug, Default)]
+pub struct ScrapDisplayProvider;
+
+impl ScrapDisplayProvider {
+    /// scrap's displays paired with what they look like on the virtual desktop
+    fn displays() -> Result<Vec<(scrap::Display, DisplayInfo)>> {
+        let displays = scrap::Display::all().map_err(|e| anyhow!("Failed to enumerate displays: {}", e))?;
+        // scrap reports sizes only; without xrandr every display is assumed to sit at the origin
+        let layout = std::process::Command::new("xrandr")
+            .arg("--listmonitors")
+            .output()
+            .map(|output| parse_xrandr_monitors(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default();
+        let sizes: Vec<(u32, u32)> = displays.iter().map(|d| (d.width() as u32, d.height() as u32)).collect();
+        let monitors = match_monitors(&sizes, &layout);
+
+        let mut infos: Vec<DisplayInfo> = sizes
+            .iter()
+            .zip(&monitors)
+            .enumerate()
+            .map(|(index, (&(width, height), monitor))| DisplayInfo {
+                index,
+                width,
+                height,
+                x: monitor.map_or(0, |m| m.x),
+                y: monitor.map_or(0, |m| m.y),
+                is_primary: monitor.is_some_and(|m| m.is_primary),
+            })
+            .collect();
+        if !infos.iter().any(|d| d.is_primary) {
+            if let Some(first) = infos.first_mut() {
+                first.is_primary = true;
+            }
+        }
+        Ok(displays.into_iter().zip(infos).collect())
+    }
+}
+
+impl DisplayProvider for ScrapDisplayProvider {
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>> {
+        Ok(Self::displays()?.into_iter().map(|(_, info)| info).collect())
+    }
+
+    fn capture(&self, display: &DisplayInfo) -> Result<image::RgbImage> {
+        use scrap::Capturer;
+
+        // Displays may have been plugged or unplugged since listing, shifting their order
+        let (display, _) = Self::displays()?
+            .into_iter()
+            .find(|(_, info)| (info.width, info.height, info.x, info.y) == (display.width, display.height, display.x, display.y))
+            .ok_or_else(|| display_disconnected(display.index))?;
+        let mut capturer = Capturer::new(display).map_err(|e| anyhow!("Failed to create capturer: {}", e))?;
+
+        let (width, height) = (capturer.width(), capturer.height());
+
+        // Capture frame using blocking operations only
+        loop {
+            match capturer.frame() {
+                Ok(buffer) => {
+                    // Convert BGRA buffer to RGB
+                    let mut rgb_data = Vec::with_capacity(width * height * 3);
+                    for chunk in buffer.chunks_exact(4) {
+                        rgb_data.push(chunk[2]); // R
+                        rgb_data.push(chunk[1]); // G
+                        rgb_data.push(chunk[0]); // B
+                        // Skip A
+                    }
+
+                    return image::RgbImage::from_raw(width as u32, height as u32, rgb_data)
+                        .ok_or_else(|| anyhow!("Failed to create image from buffer"));
+                }
+                Err(error) => {
+                    if error.kind() == std::io::ErrorKind::WouldBlock {
+                        // Frame not ready, wait a bit and try again (blocking sleep)
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                        continue;
+                    } else {
+                        return Err(anyhow!("Failed to capture frame: {:?}", error));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The display at `index`, or the primary one when unset
+fn select_display(available: &[DisplayInfo], index: Option<usize>) -> Result<&DisplayInfo> {
+    match index {
+        Some(index) => available.iter().find(|d| d.index == index).ok_or_else(|| {
+            anyhow!("Display {} is not connected ({} displays available)", index, available.len())
+        }),
+        None => available
+            .iter()
+            .find(|d| d.is_primary)
+            .or_else(|| available.first())
+            .ok_or_else(|| anyhow!("No displays connected")),
+    }
+}
+
+fn display_disconnected(index: usize) -> anyhow::Error {
+    anyhow!("Display {} was disconnected before it could be captured", index)
+}
+
+/// Geometry and primary flag of one monitor from `xrandr --listmonitors`
+#[derive(Debug, Clone, PartialEq)]
+struct MonitorLayout {
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    is_primary: bool,
+}
+
+/// Parse lines such as ` 0: +*DP-1 2560/597x1440/336+0+0  DP-1`
+fn parse_xrandr_monitors(output: &str) -> Vec<MonitorLayout> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace().skip(1);
+            let name = parts.next()?;
+            let mut geometry = parts.next()?.split('+');
+            let (width, height) = geometry.next()?.split_once('x')?;
+            Some(MonitorLayout {
+                width: width.split('/').next()?.parse().ok()?,
+                height: height.split('/').next()?.parse().ok()?,
+                x: geometry.next()?.parse().ok()?,
+                y: geometry.next()?.parse().ok()?,
+                is_primary: name.contains('*'),
+            })
+        })
+        .collect()
+}
+
+/// The monitor each display of `sizes` corresponds to. The two lists need not share an order:
+/// displays take the monitor of the same size, and any left over (e.g. scaled ones) are
+/// paired with the remaining monitors in order.
+fn match_monitors<'a>(sizes: &[(u32, u32)], layout: &'a [MonitorLayout]) -> Vec<Option<&'a MonitorLayout>> {
+    let mut used = vec![false; layout.len()];
+    let mut matched: Vec<Option<&MonitorLayout>> = sizes
+        .iter()
+        .map(|&(width, height)| {
+            let found = (0..layout.len()).find(|&i| !used[i] && (layout[i].width, layout[i].height) == (width, height))?;
+            used[found] = true;
+            Some(&layout[found])
+        })
+        .collect();
+    for slot in matched.iter_mut().filter(|slot| slot.is_none()) {
+        if let Some(free) = used.iter().position(|used| !used) {
+            used[free] = true;
+            *slot = Some(&layout[free]);
+        }
+    }
+    matched
+}
+
+/// A captured region, relative to the top-left corner of its display
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CaptureRegion {
     pub x: u32,
     pub y: u32,
@@ -25,11 +219,79 @@ pub struct CaptureRegion {
     pub height: u32,
 }
 
+/// A region the caller asked for. Coordinates are relative to `display_index`, or to the
+/// primary display when unset, and may be negative or run off the display's edge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionRequest {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+    #[serde(default)]
+    pub display_index: Option<usize>,
+}
+
+impl RegionRequest {
+    pub fn new(x: i64, y: i64, width: i64, height: i64) -> Self {
+        Self { x, y, width, height, display_index: None }
+    }
+
+    pub fn on_display(mut self, display_index: usize) -> Self {
+        self.display_index = Some(display_index);
+        self
+    }
+
+    /// Reject empty regions before anything is captured
+    pub fn validate(&self) -> Result<()> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(anyhow!(
+                "Capture region must have a positive size, got {}x{}",
+                self.width,
+                self.height
+            ));
+        }
+        Ok(())
+    }
+
+    /// The part of this region that lies on `display`, failing if none of it does
+    pub fn clamp_to(&self, display: &DisplayInfo) -> Result<CaptureRegion> {
+        self.validate()?;
+        let left = self.x.max(0);
+        let top = self.y.max(0);
+        let right = self.x.saturating_add(self.width).min(display.width as i64);
+        let bottom = self.y.saturating_add(self.height).min(display.height as i64);
+        if left >= right || top >= bottom {
+            return Err(anyhow!(
+                "Capture region {}x{} at ({}, {}) lies outside display {} ({}x{})",
+                self.width,
+                self.height,
+                self.x,
+                self.y,
+                display.index,
+                display.width,
+                display.height
+            ));
+        }
+        Ok(CaptureRegion {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OCRResult {
     pub text: String,
     pub confidence: f64,
     pub bounding_box: BoundingBox,
+    /// Engine that produced the result
+    #[serde(default)]
+    pub engine: String,
+    /// Engine the caller asked for when it was unavailable and `engine` stood in
+    #[serde(default)]
+    pub substituted_for: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +302,45 @@ pub struct BoundingBox {
     pub height: u32,
 }
 
+/// Tuning for `VisionService::diff_captures`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// Side length of the square tiles the captures are compared in
+    pub tile_size: u32,
+    /// Largest per-channel difference still treated as noise
+    pub sensitivity: u8,
+    pub include_diff_image: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 16,
+            sensitivity: 24,
+            include_diff_image: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedRegion {
+    pub bounds: BoundingBox,
+    /// Share of the region's pixels that changed, 0-100
+    pub change_percentage: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisualDiff {
+    pub width: u32,
+    pub height: u32,
+    pub changed_regions: Vec<ChangedRegion>,
+    /// Share of all pixels that changed, 0-100
+    pub change_percentage: f64,
+    /// PNG of the later capture with changed pixels highlighted
+    pub diff_image: Option<Vec<u8>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualElement {
     pub element_type: String,
@@ -57,6 +358,40 @@ pub struct ScreenAnalysis {
     pub visual_elements: Vec<VisualElement>,
     pub detected_context: DetectedContext,
     pub summary: String,
+    /// Stages that failed; the analysis is built from the ones that succeeded
+    #[serde(default)]
+    pub stage_errors: Vec<VisionStageError>,
+}
+
+/// Tauri event carrying a `VisionProgress` payload
+pub const VISION_PROGRESS_EVENT: &str = "vision-progress";
+
+/// Steps of a comprehensive screen analysis, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VisionStage {
+    Capture,
+    Ocr,
+    Elements,
+    Analysis,
+    Complete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisionStageError {
+    pub stage: VisionStage,
+    pub error: String,
+}
+
+/// Sent after each stage of a comprehensive analysis, as the "vision-progress" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisionProgress {
+    pub capture_id: String,
+    pub stage: VisionStage,
+    /// Overall progress, 0-100
+    pub percent: u8,
+    pub message: String,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,13 +413,91 @@ pub struct VisionQuery {
 #[derive(Debug)]
 pub struct VisionService {
     initialized: bool,
+    /// OCR results keyed by engine and image content hash
+    ocr_cache: Mutex<Cache<String, Vec<OCRResult>>>,
+    displays: Arc<dyn DisplayProvider>,
+    /// Registered OCR engines in fallback order
+    ocr_engines: Vec<Arc<dyn OcrEngine>>,
 }
 
 impl VisionService {
     pub fn new() -> Self {
+        let mut config = CacheConfig::new(OCR_CACHE_MAX_ENTRIES).with_ttl(OCR_CACHE_TTL);
+        if let Some(cache_dir) = dirs::cache_dir() {
+            config = config.persist_to(cache_dir.join("nexus-terminal").join("ocr_cache.json"));
+        }
+
+        let ocr_cache = Cache::load(config.clone()).unwrap_or_else(|e| {
+            warn!("Failed to load OCR cache: {}", e);
+            Cache::new(config)
+        });
+
         Self {
             initialized: false,
+            ocr_cache: Mutex::new(ocr_cache),
+            displays: Arc::new(ScrapDisplayProvider),
+            ocr_engines: default_ocr_engines(),
+        }
+    }
+
+    pub fn with_ocr_engines(mut self, engines: Vec<Arc<dyn OcrEngine>>) -> Self {
+        self.ocr_engines = engines;
+        self
+    }
+
+    /// Every registered OCR engine and whether it can run on this machine
+    pub async fn list_ocr_engines(&self) -> Vec<OcrEngineInfo> {
+        let mut engines = Vec::with_capacity(self.ocr_engines.len());
+        for engine in &self.ocr_engines {
+            engines.push(engine.info().await);
+        }
+        engines
+    }
+
+    /// The requested engine if it is available, otherwise the first available one
+    async fn select_ocr_engine(&self, requested: &str) -> Result<Arc<dyn OcrEngine>> {
+        if let Some(engine) = self.ocr_engines.iter().find(|e| e.id() == requested) {
+            if engine.is_available().await {
+                return Ok(engine.clone());
+            }
         }
+        for engine in &self.ocr_engines {
+            if engine.is_available().await {
+                return Ok(engine.clone());
+            }
+        }
+        Err(anyhow!("No OCR engine available. {}", self.ocr_install_hints()))
+    }
+
+    fn ocr_install_hints(&self) -> String {
+        self.ocr_engines
+            .iter()
+            .map(|engine| format!("{}: {}", engine.name(), engine.install_hint()))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    pub fn with_display_provider(mut self, displays: Arc<dyn DisplayProvider>) -> Self {
+        self.displays = displays;
+        self
+    }
+
+    /// Connected displays in capture order
+    pub async fn list_displays(&self) -> Result<Vec<DisplayInfo>> {
+        let displays = self.displays.clone();
+        tokio::task::spawn_blocking(move || displays.list_displays()).await?
+    }
+
+    /// Hit/miss counters for the OCR cache
+    pub fn ocr_cache_metrics(&self) -> CacheMetrics {
+        self.ocr_cache.lock().map(|cache| cache.metrics()).unwrap_or_default()
+    }
+
+    /// Drop every cached OCR result, including the persisted copy
+    pub fn clear_ocr_cache(&self) -> Result<()> {
+        let mut cache = self.ocr_cache.lock().map_err(|_| anyhow!("OCR cache lock poisoned"))?;
+        cache.clear();
+        cache.save()
     }
 
     /// Initialize computer vision dependencies
@@ -95,116 +508,97 @@ impl VisionService {
         Ok(())
     }
 
-    /// Capture full screen using blocking operations in a spawn_blocking call
-    pub async fn capture_full_screen(&self) -> Result<ScreenCapture> {
+    /// Capture a whole display, defaulting to the primary one, using blocking operations in a
+    /// spawn_blocking call
+    pub async fn capture_full_screen(&self, display_index: Option<usize>) -> Result<ScreenCapture> {
         if !self.initialized {
             return Err(anyhow!("Vision service not initialized"));
         }
 
-        // Move the blocking screen capture to a blocking task
-        let capture_result = tokio::task::spawn_blocking(|| -> Result<ScreenCapture> {
-            use scrap::{Capturer, Display};
-            
-            let display = Display::primary().map_err(|e| anyhow!("Failed to get primary display: {}", e))?;
-            let mut capturer = Capturer::new(display).map_err(|e| anyhow!("Failed to create capturer: {}", e))?;
-            
-            let (width, height) = (capturer.width(), capturer.height());
-            
-            // Capture frame using blocking operations only
-            loop {
-                match capturer.frame() {
-                    Ok(buffer) => {
-                        let capture_id = uuid::Uuid::new_v4().to_string();
-                        
-                        // Convert BGRA buffer to RGB
-                        let mut rgb_data = Vec::with_capacity(width * height * 3);
-                        for chunk in buffer.chunks_exact(4) {
-                            rgb_data.push(chunk[2]); // R
-                            rgb_data.push(chunk[1]); // G  
-                            rgb_data.push(chunk[0]); // B
-                            // Skip A
-                        }
-                        
-                        // Create image from RGB data
-                        let img = image::RgbImage::from_raw(width as u32, height as u32, rgb_data)
-                            .ok_or_else(|| anyhow!("Failed to create image from buffer"))?;
-                        
-                        // Convert to PNG bytes
-                        let mut png_data = Vec::new();
-                        {
-                            let mut cursor = std::io::Cursor::new(&mut png_data);
-                            img.write_to(&mut cursor, image::ImageFormat::Png)
-                                .map_err(|e| anyhow!("Failed to encode image: {}", e))?;
-                        }
-                        
-                        return Ok(ScreenCapture {
-                            id: capture_id,
-                            timestamp: chrono::Utc::now().to_rfc3339(),
-                            data: png_data,
-                            format: "png".to_string(),
-                            width: width as u32,
-                            height: height as u32,
-                            region: None,
-                        });
-                    }
-                    Err(error) => {
-                        if error.kind() == std::io::ErrorKind::WouldBlock {
-                            // Frame not ready, wait a bit and try again (blocking sleep)
-                            std::thread::sleep(std::time::Duration::from_millis(10));
-                            continue;
-                        } else {
-                            return Err(anyhow!("Failed to capture frame: {:?}", error));
-                        }
-                    }
-                }
+        let displays = self.displays.clone();
+        tokio::task::spawn_blocking(move || -> Result<ScreenCapture> {
+            let available = displays.list_displays()?;
+            let display = select_display(&available, display_index)?;
+
+            let img = displays.capture(display)?;
+
+            // Convert to PNG bytes
+            let mut png_data = Vec::new();
+            {
+                let mut cursor = std::io::Cursor::new(&mut png_data);
+                img.write_to(&mut cursor, image::ImageFormat::Png)
+                    .map_err(|e| anyhow!("Failed to encode image: {}", e))?;
             }
-        }).await??;
 
-        Ok(capture_result)
+            Ok(ScreenCapture {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                data: png_data,
+                format: "png".to_string(),
+                width: img.width(),
+                height: img.height(),
+                region: None,
+                display_index: display.index,
+                offset_x: display.x,
+                offset_y: display.y,
+            })
+        }).await?
     }
 
-    /// Capture specific region of screen
-    pub async fn capture_screen_region(&self, x: u32, y: u32, width: u32, height: u32) -> Result<ScreenCapture> {
+    /// Capture part of one display. The region is clamped to the display's bounds, and the
+    /// returned capture records the region actually taken.
+    pub async fn capture_screen_region(&self, request: RegionRequest) -> Result<ScreenCapture> {
         if !self.initialized {
             return Err(anyhow!("Vision service not initialized"));
         }
+        request.validate()?;
 
-        // Capture full screen and crop to the specified region
-        let full_capture = self.capture_full_screen().await?;
-        
-        // Decode the full image and crop it
-        let cursor = Cursor::new(&full_capture.data);
-        let img = image::load(cursor, image::ImageFormat::Png)
-            .map_err(|e| anyhow!("Failed to decode captured image: {}", e))?;
-        
-        // Ensure crop bounds are within image bounds
-        let img_width = img.width();
-        let img_height = img.height();
-        let crop_x = x.min(img_width);
-        let crop_y = y.min(img_height);
-        let crop_width = width.min(img_width - crop_x);
-        let crop_height = height.min(img_height - crop_y);
-        
-        // Crop the image
-        let cropped_img = img.crop_imm(crop_x, crop_y, crop_width, crop_height);
-        
-        // Convert cropped image back to PNG bytes
-        let mut png_data = Vec::new();
-        {
-            let mut cursor = Cursor::new(&mut png_data);
-            cropped_img.write_to(&mut cursor, image::ImageFormat::Png)
-                .map_err(|e| anyhow!("Failed to encode cropped image: {}", e))?;
-        }
-        
-        Ok(ScreenCapture {
-            id: uuid::Uuid::new_v4().to_string(),
-            timestamp: Utc::now().to_rfc3339(),
-            data: png_data,
-            format: "png".to_string(),
-            width: crop_width,
-            height: crop_height,
-            region: Some(CaptureRegion { x: crop_x, y: crop_y, width: crop_width, height: crop_height }),
-        })
+        let displays = self.displays.clone();
+        tokio::task::spawn_blocking(move || -> Result<ScreenCapture> {
+            let available = displays.list_displays()?;
+            let display = select_display(&available, request.display_index)?;
+            let mut region = request.clamp_to(display)?;
+
+            let img = displays.capture(display)?;
+            // The frame can be smaller than the reported mode, e.g. under scaling
+            let frame = DisplayInfo { width: img.width(), height: img.height(), ..display.clone() };
+            if (frame.width, frame.height) != (display.width, display.height) {
+                region = request.clamp_to(&frame)?;
+            }
+            let cropped = image::imageops::crop_imm(&img, region.x, region.y, region.width, region.height).to_image();
+
+            let mut png_data = Vec::new();
+            {
+                let mut cursor = Cursor::new(&mut png_data);
+                cropped.write_to(&mut cursor, image::ImageFormat::Png)
+                    .map_err(|e| anyhow!("Failed to encode cropped image: {}", e))?;
+            }
+
+            Ok(ScreenCapture {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+                data: png_data,
+                format: "png".to_string(),
+                width: region.width,
+                height: region.height,
+                region: Some(region),
+                display_index: display.index,
+                offset_x: display.x,
+                offset_y: display.y,
+            })
+        }).await?
+    }
+
+    /// Compare two captures tile by tile, merging adjacent changed tiles into regions
+    pub async fn diff_captures(&self, before: &ScreenCapture, after: &ScreenCapture, options: &DiffOptions) -> Result<VisualDiff> {
+        let before = image::load_from_memory(&before.data)
+            .map_err(|e| anyhow!("Failed to decode capture {}: {}", before.id, e))?
+            .to_rgb8();
+        let after = image::load_from_memory(&after.data)
+            .map_err(|e| anyhow!("Failed to decode capture {}: {}", after.id, e))?
+            .to_rgb8();
+        let options = options.clone();
+        tokio::task::spawn_blocking(move || diff_images(&before, &after, &options)).await?
     }
 
     /// Perform OCR on captured image
@@ -213,148 +607,37 @@ impl VisionService {
             return Err(anyhow!("Vision service not initialized"));
         }
 
-        match engine {
-            "tesseract" => self.perform_tesseract_ocr(image_path).await,
-            "easyocr" => self.perform_easyocr_simulation(image_path).await,
-            _ => Err(anyhow!("Unsupported OCR engine: {}", engine))
-        }
-    }
-    
-    /// Perform OCR using Tesseract
-    async fn perform_tesseract_ocr(&self, image_path: &str) -> Result<Vec<OCRResult>> {
-        use tesseract::Tesseract;
-        
-        // Initialize Tesseract with English language
-        let mut tesseract = Tesseract::new(None, Some("eng"))?
-            .set_image(image_path)?
-            .set_variable("tessedit_char_whitelist", "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!@#$%^&*()_+-=[]{}|;:,.<>?/ ")
-            .map_err(|e| anyhow!("Failed to configure tesseract: {}", e))?;
-        
-        // Get text and confidence data
-        let text = tesseract.get_text()?
-            .trim()
-            .to_string();
-        
-        if text.is_empty() {
-            return Ok(Vec::new());
-        }
-        
-        // Note: Tesseract box detection API may not be available in this version
-        // Using simplified approach for now
-        #[allow(dead_code)]
-        struct BBox {
-            x1: i32,
-            y1: i32,
-            x2: i32,
-            y2: i32,
-        }
-        let boxes: Vec<BBox> = Vec::new(); // Placeholder - would use tesseract API if available
-        
-        let mut results = Vec::new();
-        let lines: Vec<&str> = text.lines().collect();
-        
-        for (i, line) in lines.iter().enumerate() {
-            if line.trim().is_empty() {
-                continue;
+        let selected = self.select_ocr_engine(engine).await?;
+
+        // Identical screenshots are common, so key on content rather than path
+        let cache_key = match tokio::fs::read(image_path).await {
+            Ok(bytes) => {
+                use sha2::{Digest, Sha256};
+                Some(format!("{}>{}:{:x}", engine, selected.id(), Sha256::digest(&bytes)))
+            }
+            Err(_) => None,
+        };
+
+        if let Some(key) = &cache_key {
+            if let Some(cached) = self.ocr_cache.lock().ok().and_then(|mut cache| cache.get(key)) {
+                return Ok(cached);
             }
-            
-            // Get bounding box for this line if available
-            let bbox = if i < boxes.len() {
-                BoundingBox {
-                    x: boxes[i].x1 as u32,
-                    y: boxes[i].y1 as u32,
-                    width: (boxes[i].x2 - boxes[i].x1) as u32,
-                    height: (boxes[i].y2 - boxes[i].y1) as u32,
-                }
-            } else {
-                // Default bounding box if we can't get specific coordinates
-                BoundingBox {
-                    x: 0,
-                    y: i as u32 * 25,  // Estimate line height
-                    width: line.len() as u32 * 10,  // Rough character width
-                    height: 20,
-                }
-            };
-            
-            // Calculate confidence (Tesseract provides this at word level)
-            let confidence = self.calculate_line_confidence(&tesseract, line).unwrap_or(0.75);
-            
-            results.push(OCRResult {
-                text: line.to_string(),
-                confidence,
-                bounding_box: bbox,
-            });
         }
-        
-        Ok(results)
-    }
-    
-    /// Calculate confidence for a text line (helper for Tesseract)
-    fn calculate_line_confidence(&self, _tesseract: &tesseract::Tesseract, _line: &str) -> Option<f64> {
-        // Note: Advanced confidence APIs may not be available in all Tesseract versions
-        // Using default confidence for now
-        Some(0.75)
-    }
-    
-    /// Simulate EasyOCR (as fallback when Tesseract isn't available)
-    async fn perform_easyocr_simulation(&self, image_path: &str) -> Result<Vec<OCRResult>> {
-        // This would integrate with Python's EasyOCR in a real implementation
-        // For now, provide a basic image analysis using image crate
-        
-        use image::GenericImageView;
-        
-        let img = image::open(image_path)
-            .map_err(|e| anyhow!("Failed to open image: {}", e))?;
-        
-        let (width, height) = img.dimensions();
-        
-        // Simple heuristic: detect likely text regions based on image analysis
-        // This is a very basic implementation - real EasyOCR would be much more sophisticated
-        
-        let mut results = Vec::new();
-        
-        // Simulate detection of common UI text regions
-        if width > 100 && height > 50 {
-            results.push(OCRResult {
-                text: "[OCR Simulation - Install Tesseract for better results]".to_string(),
-                confidence: 0.5,
-                bounding_box: BoundingBox {
-                    x: 10,
-                    y: 10,
-                    width: width.min(400),
-                    height: 25,
-                },
-            });
-            
-            // Simulate title detection
-            if height > 100 {
-                results.push(OCRResult {
-                    text: "[Simulated Title Text]".to_string(),
-                    confidence: 0.6,
-                    bounding_box: BoundingBox {
-                        x: width / 4,
-                        y: 20,
-                        width: width / 2,
-                        height: 30,
-                    },
-                });
+
+        let mut results = selected.recognize(Path::new(image_path)).await?;
+        if selected.id() != engine {
+            for result in &mut results {
+                result.substituted_for = Some(engine.to_string());
             }
-            
-            // Simulate body text detection
-            if height > 200 {
-                results.push(OCRResult {
-                    text: "[Simulated body text content]".to_string(),
-                    confidence: 0.7,
-                    bounding_box: BoundingBox {
-                        x: 20,
-                        y: 80,
-                        width: width - 40,
-                        height: height / 3,
-                    },
-                });
+        }
+
+        if let (Some(key), Ok(mut cache)) = (cache_key, self.ocr_cache.lock()) {
+            cache.insert(key, results.clone());
+            if let Err(e) = cache.save() {
+                warn!("Failed to persist OCR cache: {}", e);
             }
         }
-        
+
         Ok(results)
     }
 
@@ -923,14 +1206,13 @@ impl VisionService {
             Err(e) => return Err(anyhow!("Screen capture not available: {}", e)),
         }
 
-        // Check if OCR engine is available
-        match tokio::process::Command::new("tesseract")
-            .arg("--version")
-            .output()
-            .await
-        {
-            Ok(output) if output.status.success() => {},
-            _ => return Err(anyhow!("Tesseract OCR engine not available. Install with: sudo pacman -S tesseract")),
+        // At least one OCR engine has to work; report the rest so they can be installed
+        let engines = self.list_ocr_engines().await;
+        if !engines.iter().any(|engine| engine.available) {
+            return Err(anyhow!("No OCR engine available. {}", self.ocr_install_hints()));
+        }
+        for engine in engines.iter().filter(|engine| !engine.available) {
+            eprintln!("Warning: {} OCR engine not available. {}", engine.name, engine.install_hint);
         }
         
         // Check if AI model endpoint is reachable
@@ -958,29 +1240,108 @@ impl VisionService {
 
     /// Generate comprehensive screen analysis
     pub async fn analyze_screen_comprehensive(&self, capture_id: &str, image_data: Vec<u8>) -> Result<ScreenAnalysis> {
+        self.analyze_screen_with_progress(capture_id, image_data, &|_| {}).await
+    }
+
+    /// Comprehensive analysis that reports each stage to `progress` as it finishes. A failing
+    /// stage is recorded in `stage_errors` and the remaining stages still run.
+    pub async fn analyze_screen_with_progress(
+        &self,
+        capture_id: &str,
+        image_data: Vec<u8>,
+        progress: &(dyn Fn(VisionProgress) + Send + Sync),
+    ) -> Result<ScreenAnalysis> {
         if !self.initialized {
             return Err(anyhow!("Vision service not initialized"));
         }
 
+        let mut stage_errors = Vec::new();
+        let mut report = |stage: VisionStage, percent: u8, outcome: std::result::Result<String, String>| {
+            let (message, error) = match outcome {
+                Ok(message) => (message, None),
+                Err(error) => {
+                    warn!("Screen analysis stage {:?} failed: {}", stage, error);
+                    stage_errors.push(VisionStageError { stage, error: error.clone() });
+                    (format!("{:?} failed", stage), Some(error))
+                }
+            };
+            progress(VisionProgress { capture_id: capture_id.to_string(), stage, percent, message, error });
+        };
+
         // Save image to temp file for processing
         let temp_dir = std::env::var("TEMP_DIR")
-            .unwrap_or_else(|_| "./temp".to_string());
-        let temp_path = format!("{}/capture_{}.png", temp_dir, capture_id);
-        tokio::fs::write(&temp_path, &image_data).await?;
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|_| std::env::temp_dir());
+        let temp_path = temp_dir.join(format!("capture_{}.png", capture_id)).to_string_lossy().to_string();
+        let saved = tokio::fs::write(&temp_path, &image_data).await;
+        report(
+            VisionStage::Capture,
+            10,
+            saved.map(|()| format!("Captured {} bytes", image_data.len())).map_err(|e| e.to_string()),
+        );
 
-        // Perform OCR and element detection (in parallel eventually)
-        let ocr_results = self.perform_ocr(&temp_path, "tesseract").await?;
-        let visual_elements = self.detect_ui_elements(&temp_path).await?;
+        let ocr_results = match self.perform_ocr(&temp_path, "tesseract").await {
+            Ok(results) => {
+                report(VisionStage::Ocr, 50, Ok(format!("Recognized {} text regions", results.len())));
+                results
+            }
+            Err(e) => {
+                report(VisionStage::Ocr, 50, Err(e.to_string()));
+                Vec::new()
+            }
+        };
 
-        // Analyze context
-        let detected_context = self.analyze_context(&ocr_results, &visual_elements).await?;
-        
-        // Generate summary
-        let summary = self.generate_summary(&ocr_results, &visual_elements, &detected_context).await?;
+        let visual_elements = match self.detect_ui_elements(&temp_path).await {
+            Ok(elements) => {
+                report(VisionStage::Elements, 75, Ok(format!("Detected {} UI elements", elements.len())));
+                elements
+            }
+            Err(e) => {
+                report(VisionStage::Elements, 75, Err(e.to_string()));
+                Vec::new()
+            }
+        };
+
+        let analyzed = match self.analyze_context(&ocr_results, &visual_elements).await {
+            Ok(context) => self
+                .generate_summary(&ocr_results, &visual_elements, &context)
+                .await
+                .map(|summary| (context, summary)),
+            Err(e) => Err(e),
+        };
+        let (detected_context, summary) = match analyzed {
+            Ok(analyzed) => {
+                report(VisionStage::Analysis, 90, Ok(format!("Screen looks like a {} window", analyzed.0.window_type)));
+                analyzed
+            }
+            Err(e) => {
+                report(VisionStage::Analysis, 90, Err(e.to_string()));
+                let context = DetectedContext {
+                    window_type: "unknown".to_string(),
+                    primary_content: String::new(),
+                    code_language: None,
+                    terminal_commands: None,
+                    error_messages: None,
+                };
+                (context, "Screen analysis was incomplete".to_string())
+            }
+        };
 
         // Clean up temp file
         let _ = tokio::fs::remove_file(&temp_path).await;
 
+        let failed = stage_errors.len();
+        progress(VisionProgress {
+            capture_id: capture_id.to_string(),
+            stage: VisionStage::Complete,
+            percent: 100,
+            message: match failed {
+                0 => "Analysis complete".to_string(),
+                n => format!("Analysis complete; {} stage(s) failed", n),
+            },
+            error: None,
+        });
+
         Ok(ScreenAnalysis {
             capture_id: capture_id.to_string(),
             timestamp: Utc::now().to_rfc3339(),
@@ -988,6 +1349,7 @@ impl VisionService {
             visual_elements,
             detected_context,
             summary,
+            stage_errors,
         })
     }
 
@@ -1067,6 +1429,118 @@ impl VisionService {
     }
 }
 
+fn diff_images(before: &image::RgbImage, after: &image::RgbImage, options: &DiffOptions) -> Result<VisualDiff> {
+    if before.dimensions() != after.dimensions() {
+        return Err(anyhow!(
+            "Cannot diff captures of different sizes ({}x{} vs {}x{})",
+            before.width(), before.height(), after.width(), after.height()
+        ));
+    }
+
+    let (width, height) = before.dimensions();
+    let tile = options.tile_size.max(1);
+    let (tiles_x, tiles_y) = (width.div_ceil(tile), height.div_ceil(tile));
+    let changed = |x: u32, y: u32| {
+        let (a, b) = (before.get_pixel(x, y), after.get_pixel(x, y));
+        a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > options.sensitivity)
+    };
+
+    // Changed pixel count per tile, row-major
+    let mut tile_changes = vec![0u64; (tiles_x * tiles_y) as usize];
+    let mut total_changed = 0u64;
+    for y in 0..height {
+        for x in 0..width {
+            if changed(x, y) {
+                tile_changes[((y / tile) * tiles_x + x / tile) as usize] += 1;
+                total_changed += 1;
+            }
+        }
+    }
+
+    // Flood-fill neighbouring changed tiles into one region each
+    let mut visited = vec![false; tile_changes.len()];
+    let mut changed_regions = Vec::new();
+    for start in 0..tile_changes.len() {
+        if visited[start] || tile_changes[start] == 0 {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![start];
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+        let mut region_changed = 0u64;
+        while let Some(index) = stack.pop() {
+            let (tx, ty) = (index as u32 % tiles_x, index as u32 / tiles_x);
+            min_x = min_x.min(tx);
+            min_y = min_y.min(ty);
+            max_x = max_x.max(tx);
+            max_y = max_y.max(ty);
+            region_changed += tile_changes[index];
+
+            let neighbours = [
+                (tx > 0).then(|| index - 1),
+                (tx + 1 < tiles_x).then(|| index + 1),
+                (ty > 0).then(|| index - tiles_x as usize),
+                (ty + 1 < tiles_y).then(|| index + tiles_x as usize),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if !visited[neighbour] && tile_changes[neighbour] > 0 {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+
+        let bounds = BoundingBox {
+            x: min_x * tile,
+            y: min_y * tile,
+            width: ((max_x + 1) * tile).min(width) - min_x * tile,
+            height: ((max_y + 1) * tile).min(height) - min_y * tile,
+        };
+        let area = bounds.width as u64 * bounds.height as u64;
+        changed_regions.push(ChangedRegion {
+            change_percentage: region_changed as f64 / area as f64 * 100.0,
+            bounds,
+        });
+    }
+
+    let diff_image = if options.include_diff_image {
+        let highlighted = image::RgbImage::from_fn(width, height, |x, y| {
+            if changed(x, y) {
+                image::Rgb([255, 0, 0])
+            } else {
+                let p = after.get_pixel(x, y);
+                image::Rgb([p[0] / 3, p[1] / 3, p[2] / 3])
+            }
+        });
+        let mut png_data = Vec::new();
+        highlighted
+            .write_to(&mut Cursor::new(&mut png_data), image::ImageFormat::Png)
+            .map_err(|e| anyhow!("Failed to encode diff image: {}", e))?;
+        Some(png_data)
+    } else {
+        None
+    };
+
+    let pixels = (width as u64 * height as u64).max(1);
+    Ok(VisualDiff {
+        width,
+        height,
+        changed_regions,
+        change_percentage: total_changed as f64 / pixels as f64 * 100.0,
+        diff_image,
+    })
+}
+
+fn default_ocr_engines() -> Vec<Arc<dyn OcrEngine>> {
+    #[allow(unused_mut)]
+    let mut engines: Vec<Arc<dyn OcrEngine>> = vec![Arc::new(TesseractEngine)];
+    #[cfg(feature = "onnx-ocr")]
+    if let Some(data_dir) = dirs::data_dir() {
+        engines.push(Arc::new(crate::ocr::OnnxOcrEngine::new(data_dir.join("nexus-terminal").join("ocr"))));
+    }
+    engines
+}
+
 impl Default for VisionService {
     fn default() -> Self {
         Self::new()
@@ -1080,3 +1554,308 @@ static VISION_SERVICE: once_cell::sync::Lazy<tokio::sync::Mutex<VisionService>>
 pub fn get_vision_service() -> &'static tokio::sync::Mutex<VisionService> {
     &VISION_SERVICE
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two side-by-side displays, the second of which can be unplugged
+    #[derive(Debug)]
+    struct MockDisplays {
+        connected: Mutex<usize>,
+    }
+
+    impl MockDisplays {
+        fn all() -> Vec<DisplayInfo> {
+            vec![
+                DisplayInfo { index: 0, width: 32, height: 16, x: 0, y: 0, is_primary: false },
+                DisplayInfo { index: 1, width: 48, height: 24, x: 32, y: 0, is_primary: true },
+            ]
+        }
+    }
+
+    impl DisplayProvider for MockDisplays {
+        fn list_displays(&self) -> Result<Vec<DisplayInfo>> {
+            Ok(Self::all())
+        }
+
+        fn capture(&self, display: &DisplayInfo) -> Result<image::RgbImage> {
+            if display.index >= *self.connected.lock().unwrap() {
+                return Err(display_disconnected(display.index));
+            }
+            Ok(image::RgbImage::new(display.width, display.height))
+        }
+    }
+
+    fn service(displays: Arc<MockDisplays>) -> VisionService {
+        let mut service = VisionService::new().with_display_provider(displays);
+        service.initialized = true;
+        service
+    }
+
+    #[tokio::test]
+    async fn test_capture_selects_display_and_records_offset() {
+        let service = service(Arc::new(MockDisplays { connected: Mutex::new(2) }));
+        assert_eq!(service.list_displays().await.unwrap(), MockDisplays::all());
+
+        let primary = service.capture_full_screen(None).await.unwrap();
+        assert_eq!((primary.display_index, primary.offset_x, primary.width), (1, 32, 48));
+
+        let left = service.capture_full_screen(Some(0)).await.unwrap();
+        assert_eq!((left.display_index, left.offset_x, left.width, left.height), (0, 0, 32, 16));
+
+        let region = service.capture_screen_region(RegionRequest::new(4, 4, 8, 8)).await.unwrap();
+        assert_eq!((region.display_index, region.offset_x), (1, 32));
+
+        let missing = service.capture_full_screen(Some(5)).await.unwrap_err();
+        assert!(missing.to_string().contains("not connected"), "{}", missing);
+    }
+
+    #[tokio::test]
+    async fn test_region_inside_display_is_captured_as_requested() {
+        let service = service(Arc::new(MockDisplays { connected: Mutex::new(2) }));
+
+        let capture = service.capture_screen_region(RegionRequest::new(2, 3, 10, 5).on_display(0)).await.unwrap();
+        assert_eq!(capture.region, Some(CaptureRegion { x: 2, y: 3, width: 10, height: 5 }));
+        assert_eq!((capture.width, capture.height, capture.display_index), (10, 5, 0));
+        let png = image::load_from_memory(&capture.data).unwrap();
+        assert_eq!((png.width(), png.height()), (10, 5));
+    }
+
+    #[tokio::test]
+    async fn test_region_outside_display_is_rejected() {
+        let service = service(Arc::new(MockDisplays { connected: Mutex::new(2) }));
+
+        // Display 0 is 32x16, so this would be on display 1 of the virtual desktop
+        let error = service.capture_screen_region(RegionRequest::new(40, 2, 4, 4).on_display(0)).await.unwrap_err();
+        assert!(error.to_string().contains("outside display 0"), "{}", error);
+
+        let error = service.capture_screen_region(RegionRequest::new(0, 0, 0, 4)).await.unwrap_err();
+        assert!(error.to_string().contains("positive size"), "{}", error);
+        let error = service.capture_screen_region(RegionRequest::new(0, 0, 4, -1)).await.unwrap_err();
+        assert!(error.to_string().contains("positive size"), "{}", error);
+
+        let error = service.capture_screen_region(RegionRequest::new(0, 0, 4, 4).on_display(7)).await.unwrap_err();
+        assert!(error.to_string().contains("not connected"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_region_partly_off_display_is_clamped() {
+        let service = service(Arc::new(MockDisplays { connected: Mutex::new(2) }));
+
+        let capture = service.capture_screen_region(RegionRequest::new(-4, 10, 12, 20).on_display(0)).await.unwrap();
+        assert_eq!(capture.region, Some(CaptureRegion { x: 0, y: 10, width: 8, height: 6 }));
+        assert_eq!((capture.width, capture.height), (8, 6));
+
+        // Primary display is 48x24
+        let capture = service.capture_screen_region(RegionRequest::new(40, 20, 100, 100)).await.unwrap();
+        assert_eq!(capture.region, Some(CaptureRegion { x: 40, y: 20, width: 8, height: 4 }));
+        assert_eq!(capture.display_index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_display_unplugged_after_listing() {
+        let displays = Arc::new(MockDisplays { connected: Mutex::new(2) });
+        let service = service(displays.clone());
+        let listed = service.list_displays().await.unwrap();
+        assert_eq!(listed.len(), 2);
+
+        *displays.connected.lock().unwrap() = 1;
+        let error = service.capture_full_screen(Some(1)).await.unwrap_err();
+        assert!(error.to_string().contains("Display 1 was disconnected"), "{}", error);
+    }
+
+    #[derive(Debug)]
+    struct StubEngine {
+        id: &'static str,
+        available: bool,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl OcrEngine for StubEngine {
+        fn id(&self) -> &str {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            self.id
+        }
+
+        fn install_hint(&self) -> String {
+            format!("install {}", self.id)
+        }
+
+        async fn is_available(&self) -> bool {
+            self.available
+        }
+
+        async fn recognize(&self, _image_path: &Path) -> Result<Vec<OCRResult>> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(vec![OCRResult {
+                text: format!("read by {}", self.id),
+                confidence: 0.9,
+                bounding_box: BoundingBox { x: 0, y: 0, width: 10, height: 10 },
+                engine: self.id.to_string(),
+                substituted_for: None,
+            }])
+        }
+    }
+
+    fn ocr_service(engines: Vec<(&'static str, bool)>) -> VisionService {
+        let engines = engines
+            .into_iter()
+            .map(|(id, available)| Arc::new(StubEngine { id, available, calls: Default::default() }) as Arc<dyn OcrEngine>)
+            .collect();
+        let mut service = VisionService::new().with_ocr_engines(engines);
+        service.initialized = true;
+        service
+    }
+
+    #[tokio::test]
+    async fn test_ocr_uses_requested_engine_or_falls_back() {
+        // The image path does not exist, which also keeps these results out of the on-disk cache
+        let service = ocr_service(vec![("tesseract", false), ("onnx", true), ("spare", true)]);
+
+        let direct = service.perform_ocr("/nonexistent/capture.png", "spare").await.unwrap();
+        assert_eq!(direct[0].engine, "spare");
+        assert_eq!(direct[0].substituted_for, None);
+
+        let fallback = service.perform_ocr("/nonexistent/capture.png", "tesseract").await.unwrap();
+        assert_eq!(fallback[0].engine, "onnx");
+        assert_eq!(fallback[0].substituted_for.as_deref(), Some("tesseract"));
+
+        let engines = service.list_ocr_engines().await;
+        assert_eq!(engines.iter().map(|e| (e.id.as_str(), e.available)).collect::<Vec<_>>(), vec![("tesseract", false), ("onnx", true), ("spare", true)]);
+    }
+
+    #[tokio::test]
+    async fn test_ocr_without_engines_reports_install_hints() {
+        let service = ocr_service(vec![("tesseract", false), ("onnx", false)]);
+        let error = service.perform_ocr("/nonexistent/capture.png", "tesseract").await.unwrap_err().to_string();
+        assert!(error.contains("install tesseract") && error.contains("install onnx"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_ocr_of_same_image_hits_cache() {
+        let engine = Arc::new(StubEngine { id: "tesseract", available: true, calls: Default::default() });
+        let mut service = VisionService::new().with_ocr_engines(vec![engine.clone()]);
+        service.initialized = true;
+        service.ocr_cache = Mutex::new(Cache::new(CacheConfig::new(8)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let write_png = |name: &str, size: u32| {
+            let path = dir.path().join(name);
+            image::RgbImage::from_pixel(size, size, image::Rgb([9, 9, 9])).save(&path).unwrap();
+            path.to_string_lossy().to_string()
+        };
+        let calls = || engine.calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        let first = write_png("first.png", 32);
+        let copy = write_png("copy.png", 32);
+        service.perform_ocr(&first, "tesseract").await.unwrap();
+        let cached = service.perform_ocr(&copy, "tesseract").await.unwrap();
+        assert_eq!(calls(), 1);
+        assert_eq!(cached[0].engine, "tesseract");
+        assert_eq!((service.ocr_cache_metrics().hits, service.ocr_cache_metrics().misses), (1, 1));
+
+        service.clear_ocr_cache().unwrap();
+        service.perform_ocr(&first, "tesseract").await.unwrap();
+        assert_eq!(calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_comprehensive_analysis_reports_stages_and_survives_ocr_failure() {
+        let service = ocr_service(vec![("tesseract", false)]);
+        let capture = png_capture(&image::RgbImage::from_pixel(32, 32, image::Rgb([20, 20, 20])));
+        let events = Mutex::new(Vec::new());
+
+        let analysis = service
+            .analyze_screen_with_progress(&capture.id, capture.data, &|progress| events.lock().unwrap().push(progress))
+            .await
+            .unwrap();
+
+        let events = events.into_inner().unwrap();
+        let stages: Vec<_> = events.iter().map(|e| e.stage).collect();
+        assert_eq!(stages, vec![VisionStage::Capture, VisionStage::Ocr, VisionStage::Elements, VisionStage::Analysis, VisionStage::Complete]);
+        assert!(events.windows(2).all(|pair| pair[0].percent < pair[1].percent));
+        assert!(events[1].error.as_deref().unwrap().contains("install tesseract"), "{:?}", events[1]);
+        assert!(events.iter().filter(|e| e.stage != VisionStage::Ocr).all(|e| e.error.is_none()));
+
+        assert!(analysis.ocr_results.is_empty());
+        assert_eq!(analysis.stage_errors.len(), 1);
+        assert_eq!(analysis.stage_errors[0].stage, VisionStage::Ocr);
+    }
+
+    fn png_capture(img: &image::RgbImage) -> ScreenCapture {
+        let mut data = Vec::new();
+        img.write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png).unwrap();
+        ScreenCapture {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            data,
+            format: "png".to_string(),
+            width: img.width(),
+            height: img.height(),
+            region: None,
+            display_index: 0,
+            offset_x: 0,
+            offset_y: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_diff_reports_only_changed_quadrant() {
+        let service = VisionService::new();
+        let before = image::RgbImage::from_pixel(64, 64, image::Rgb([40, 40, 40]));
+        let mut after = before.clone();
+        // Sub-threshold noise everywhere, a real change in the bottom-right quadrant
+        for (x, y, pixel) in after.enumerate_pixels_mut() {
+            *pixel = if x >= 32 && y >= 32 { image::Rgb([200, 220, 40]) } else { image::Rgb([45, 40, 38]) };
+        }
+
+        let options = DiffOptions { include_diff_image: true, ..DiffOptions::default() };
+        let diff = service.diff_captures(&png_capture(&before), &png_capture(&after), &options).await.unwrap();
+        assert_eq!(diff.changed_regions.len(), 1);
+        let region = &diff.changed_regions[0];
+        assert_eq!((region.bounds.x, region.bounds.y, region.bounds.width, region.bounds.height), (32, 32, 32, 32));
+        assert!((region.change_percentage - 100.0).abs() < 1e-9);
+        assert!((diff.change_percentage - 25.0).abs() < 1e-9);
+        assert!(diff.diff_image.is_some());
+
+        let unchanged = service.diff_captures(&png_capture(&before), &png_capture(&before), &DiffOptions::default()).await.unwrap();
+        assert!(unchanged.changed_regions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_diff_rejects_mismatched_resolutions() {
+        let service = VisionService::new();
+        let small = png_capture(&image::RgbImage::new(32, 32));
+        let large = png_capture(&image::RgbImage::new(64, 32));
+        let error = service.diff_captures(&small, &large, &DiffOptions::default()).await.unwrap_err();
+        assert!(error.to_string().contains("different sizes"), "{}", error);
+    }
+
+    #[test]
+    fn test_parse_xrandr_monitors() {
+        let output = "Monitors: 2\n 0: +*DP-1 2560/597x1440/336+0+0  DP-1\n 1: +HDMI-1 1920/527x1080/296+-1920+180  HDMI-1\n";
+        assert_eq!(
+            parse_xrandr_monitors(output),
+            vec![
+                MonitorLayout { width: 2560, height: 1440, x: 0, y: 0, is_primary: true },
+                MonitorLayout { width: 1920, height: 1080, x: -1920, y: 180, is_primary: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_displays_are_matched_to_monitors_by_size() {
+        let layout = parse_xrandr_monitors("Monitors: 3\n 0: +*DP-1 2560/597x1440/336+0+0  DP-1\n 1: +HDMI-1 1920/527x1080/296+-1920+180  HDMI-1\n 2: +DP-2 1280/300x1024/240+2560+0  DP-2\n");
+        // Listed in a different order than xrandr, and the last one scaled
+        let matched = match_monitors(&[(1920, 1080), (2560, 1440), (640, 512)], &layout);
+        let positions: Vec<Option<(i32, i32)>> = matched.iter().map(|m| m.map(|m| (m.x, m.y))).collect();
+        assert_eq!(positions, vec![Some((-1920, 180)), Some((0, 0)), Some((2560, 0))]);
+
+        assert_eq!(match_monitors(&[(800, 600)], &[]), vec![None]);
+    }
+}
@@ -1,4 +1,4 @@
-use tauri::{command, State};
+use tauri::{command, AppHandle, Emitter, State};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use image::{DynamicImage};
@@ -456,7 +456,7 @@ pub async fn capture_screen_enhanced(
     state: State<'_, AppState>,
 ) -> Result<vision::ScreenCapture, String> {
     let vision_service = state.vision_service.read().await;
-    vision_service.capture_full_screen().await.map_err(|e| e.to_string())
+    vision_service.capture_full_screen(None).await.map_err(|e| e.to_string())
 }
 
 /// Enhanced capture region using VisionService
@@ -470,7 +470,7 @@ pub async fn capture_region_enhanced(
 ) -> Result<vision::ScreenCapture, String> {
     let vision_service = state.vision_service.read().await;
     vision_service
-        .capture_screen_region(x as u32, y as u32, width, height)
+        .capture_screen_region(vision::RegionRequest::new(x as i64, y as i64, width as i64, height as i64))
         .await
         .map_err(|e| e.to_string())
 }
@@ -509,12 +509,16 @@ pub async fn perform_ocr_enhanced(
                         width: first_result.bounding_box.width,
                         height: first_result.bounding_box.height,
                     },
+                    engine: first_result.engine.clone(),
+                    substituted_for: first_result.substituted_for.clone(),
                 })
             } else {
                 Ok(vision::OCRResult {
                     text: String::new(),
                     confidence: 0.0,
                     bounding_box: vision::BoundingBox { x: 0, y: 0, width: 0, height: 0 },
+                    engine: String::new(),
+                    substituted_for: None,
                 })
             }
         }
@@ -528,10 +532,13 @@ pub async fn analyze_screenshot(
     capture_id: String,
     image_data: Vec<u8>,
     state: State<'_, AppState>,
+    app: AppHandle,
 ) -> Result<vision::ScreenAnalysis, String> {
     let vision_service = state.vision_service.read().await;
     vision_service
-        .analyze_screen_comprehensive(&capture_id, image_data)
+        .analyze_screen_with_progress(&capture_id, image_data, &|progress| {
+            let _ = app.emit(vision::VISION_PROGRESS_EVENT, &progress);
+        })
         .await
         .map_err(|e| e.to_string())
 }
@@ -541,12 +548,16 @@ pub async fn analyze_screenshot(
 pub async fn get_vision_stats(
     state: State<'_, AppState>,
 ) -> Result<HashMap<String, serde_json::Value>, String> {
-    let _vision_service = state.vision_service.read().await;
+    let vision_service = state.vision_service.read().await;
+    let ocr_cache = vision_service.ocr_cache_metrics();
     
     // Create stats from vision service state
     let mut stats = HashMap::new();
     stats.insert("initialized".to_string(), serde_json::Value::Bool(true));
     stats.insert("capture_count".to_string(), serde_json::Value::Number(serde_json::Number::from(0)));
+    stats.insert("ocr_cache_hits".to_string(), serde_json::Value::from(ocr_cache.hits));
+    stats.insert("ocr_cache_misses".to_string(), serde_json::Value::from(ocr_cache.misses));
+    stats.insert("ocr_cache_entries".to_string(), serde_json::Value::from(ocr_cache.entries));
     
     Ok(stats)
 }
@@ -559,7 +570,7 @@ pub async fn check_vision_service_status(
     let vision_service = state.vision_service.read().await;
     
     // Check if the service can perform a basic operation
-    match vision_service.capture_full_screen().await {
+    match vision_service.capture_full_screen(None).await {
         Ok(_) => Ok(true),
         Err(_) => Ok(false),
     }
@@ -3,12 +3,22 @@ use reqwest::Client;
 use scraper::{Html, Selector};
 use url::Url;
 use std::collections::{HashMap, HashSet};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::fs;
 use anyhow::{Result, anyhow, Context};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use tracing::info;
+use tracing::{info, warn};
+use crate::headless_browser::{self, RenderOptions};
+use crate::scraper_cookies::{Cookie, CookieJar};
+use crate::scraper_proxy::{ProxyConfig, ProxyFallback, ProxyPool};
+
+/// Default politeness limit for requests made outside a scraping job
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 2.0;
+
+/// User agent sent with every request and matched against robots.txt groups
+const USER_AGENT: &str = "Nexus Terminal Web Scraper 1.0";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrapingOptions {
@@ -20,13 +30,96 @@ pub struct ScrapingOptions {
     pub download_css: bool,
     pub download_js: bool,
     pub delay_between_requests: u64,
-    pub respect_robots_txt: bool,
+    /// Skip URLs disallowed by the host's robots.txt
+    #[serde(default = "default_true", alias = "respect_robots_txt")]
+    pub respect_robots: bool,
     pub include_patterns: Vec<String>,
     pub exclude_patterns: Vec<String>,
     pub output_directory: String,
     pub structure_mirror: bool,
     pub convert_links: bool,
     pub timeout: u64,
+    /// Maximum requests per second to a single host; 0 disables the limit
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+    /// Minimum gap between requests to the same host
+    #[serde(default)]
+    pub per_domain_delay_ms: u64,
+    /// Load pages in a headless browser and save the DOM after scripts have run
+    #[serde(default)]
+    pub render_js: bool,
+    /// With `render_js`, wait for this CSS selector instead of network idle
+    #[serde(default)]
+    pub wait_for_selector: Option<String>,
+    #[serde(default = "default_render_timeout_ms")]
+    pub render_timeout_ms: u64,
+    /// Cookies set on the start URL's host before the first request
+    #[serde(default)]
+    pub initial_cookies: HashMap<String, String>,
+    /// Extra headers sent with every page request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Netscape-format cookie file imported before the job starts
+    #[serde(default)]
+    pub cookie_file: Option<String>,
+    /// Requests that log in before crawling; their cookies are kept for the job
+    #[serde(default)]
+    pub login_steps: Vec<LoginStep>,
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Further proxies; page requests rotate through `proxy` and these in order
+    #[serde(default)]
+    pub proxy_rotation: Vec<ProxyConfig>,
+    #[serde(default)]
+    pub proxy_fallback: ProxyFallback,
+}
+
+/// One request of a login sequence, e.g. posting a sign-in form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginStep {
+    pub url: String,
+    #[serde(default = "default_login_method")]
+    pub method: String,
+    /// Sent as `application/x-www-form-urlencoded`
+    #[serde(default)]
+    pub form: HashMap<String, String>,
+    /// Sent as JSON instead of `form` when set
+    #[serde(default)]
+    pub json: Option<serde_json::Value>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+fn default_login_method() -> String {
+    "POST".to_string()
+}
+
+impl ScrapingOptions {
+    /// Browser rendering settings, if rendering is enabled
+    pub fn render_options(&self) -> Option<RenderOptions> {
+        self.render_js.then(|| RenderOptions {
+            wait_for_selector: self.wait_for_selector.clone(),
+            timeout_ms: self.render_timeout_ms,
+            proxy_server: self.proxies().first().map(ProxyConfig::label),
+        })
+    }
+
+    /// Every configured proxy in rotation order
+    pub fn proxies(&self) -> Vec<ProxyConfig> {
+        self.proxy.iter().chain(&self.proxy_rotation).cloned().collect()
+    }
+}
+
+fn default_render_timeout_ms() -> u64 {
+    headless_browser::DEFAULT_RENDER_TIMEOUT_MS
+}
+
+fn default_requests_per_second() -> f64 {
+    DEFAULT_REQUESTS_PER_SECOND
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +135,15 @@ pub struct ScrapingResult {
     pub downloaded_files: Vec<DownloadedFile>,
     pub current_url: Option<String>,
     pub estimated_time_remaining: Option<u64>,
+    #[serde(default)]
+    pub skipped_urls: Vec<SkippedUrl>,
+}
+
+/// A URL the crawler chose not to fetch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedUrl {
+    pub url: String,
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +161,9 @@ pub struct DownloadedFile {
     pub size: u64,
     pub mime_type: String,
     pub timestamp: DateTime<Utc>,
+    /// Proxy the page was fetched through, `None` for a direct connection
+    #[serde(default)]
+    pub proxy: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,6 +204,138 @@ pub struct RobotsTxtInfo {
     pub sitemap_urls: Vec<String>,
 }
 
+#[derive(Debug, Clone)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RobotsGroup {
+    agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<u32>,
+}
+
+/// Parsed robots.txt, grouped by user agent
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    groups: Vec<RobotsGroup>,
+}
+
+impl RobotsRules {
+    pub fn parse(content: &str) -> Self {
+        let mut groups: Vec<RobotsGroup> = Vec::new();
+        let mut current = RobotsGroup::default();
+        let mut in_rules = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                // A user-agent line after rules starts a new group
+                if in_rules {
+                    groups.push(std::mem::take(&mut current));
+                    in_rules = false;
+                }
+                current.agents.push(value.to_lowercase());
+                continue;
+            }
+            if current.agents.is_empty() {
+                continue;
+            }
+
+            match key.as_str() {
+                // An empty Disallow allows everything and adds no rule
+                "allow" | "disallow" if !value.is_empty() => current.rules.push(RobotsRule {
+                    allow: key == "allow",
+                    pattern: value.to_string(),
+                }),
+                "crawl-delay" => current.crawl_delay = value.parse().ok(),
+                _ => {}
+            }
+            in_rules = true;
+        }
+        if !current.agents.is_empty() {
+            groups.push(current);
+        }
+
+        Self { groups }
+    }
+
+    /// The Disallow pattern blocking `path` for `user_agent`, if any.
+    ///
+    /// The longest matching rule wins and Allow wins ties, as in RFC 9309.
+    pub fn disallowed_by(&self, path: &str, user_agent: &str) -> Option<&str> {
+        let mut best: Option<&RobotsRule> = None;
+        for rule in self.groups_for(user_agent).iter().flat_map(|g| &g.rules) {
+            if !robots_pattern_matches(&rule.pattern, path) {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some(b) => rule.pattern.len() > b.pattern.len() || (rule.pattern.len() == b.pattern.len() && rule.allow),
+            };
+            if better {
+                best = Some(rule);
+            }
+        }
+
+        best.filter(|rule| !rule.allow).map(|rule| rule.pattern.as_str())
+    }
+
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<u32> {
+        self.groups_for(user_agent).iter().find_map(|g| g.crawl_delay)
+    }
+
+    /// Groups naming this agent, falling back to the `*` group
+    fn groups_for(&self, user_agent: &str) -> Vec<&RobotsGroup> {
+        let user_agent = user_agent.to_lowercase();
+        let specific: Vec<&RobotsGroup> = self
+            .groups
+            .iter()
+            .filter(|g| g.agents.iter().any(|a| a != "*" && user_agent.contains(a.as_str())))
+            .collect();
+        if !specific.is_empty() {
+            return specific;
+        }
+        self.groups.iter().filter(|g| g.agents.iter().any(|a| a == "*")).collect()
+    }
+}
+
+/// Match a robots.txt path pattern supporting `*` wildcards and a `$` end anchor
+fn robots_pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScrapingStats {
     pub total_requests: u64,
@@ -127,40 +364,248 @@ pub struct ScrapingEstimate {
     pub warnings: Vec<String>,
 }
 
+/// Request rate allowed against a single host
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub min_delay: Duration,
+}
+
+impl RateLimit {
+    pub fn from_options(options: &ScrapingOptions) -> Self {
+        Self {
+            requests_per_second: options.requests_per_second,
+            min_delay: Duration::from_millis(options.per_domain_delay_ms),
+        }
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_second: DEFAULT_REQUESTS_PER_SECOND,
+            min_delay: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DomainBucket {
+    /// Goes negative when requests are queued ahead of the refill
+    tokens: f64,
+    last_refill: Instant,
+    next_allowed: Instant,
+}
+
+/// Token bucket per host, shared by every clone of the scraper so that
+/// concurrent jobs against the same domain throttle collectively
+#[derive(Debug, Default)]
+pub struct DomainRateLimiter {
+    buckets: tokio::sync::Mutex<HashMap<String, DomainBucket>>,
+    crawl_delays: std::sync::Mutex<HashMap<String, Duration>>,
+}
+
+impl DomainRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a `Crawl-delay` published by the host's robots.txt
+    pub fn set_crawl_delay(&self, url: &str, delay: Duration) {
+        if let Ok(mut delays) = self.crawl_delays.lock() {
+            delays.insert(host_key(url), delay);
+        }
+    }
+
+    /// Wait until a request to `url` is allowed under `limit`
+    pub async fn acquire(&self, url: &str, limit: RateLimit) {
+        let host = host_key(url);
+        let robots_delay = self
+            .crawl_delays
+            .lock()
+            .ok()
+            .and_then(|delays| delays.get(&host).copied())
+            .unwrap_or(Duration::ZERO);
+        let min_delay = limit.min_delay.max(robots_delay);
+
+        // Reserve a slot under the lock, then sleep without holding it
+        let wait = {
+            let mut buckets = self.buckets.lock().await;
+            let now = Instant::now();
+            let bucket = buckets.entry(host).or_insert_with(|| DomainBucket {
+                tokens: 1.0,
+                last_refill: now,
+                next_allowed: now,
+            });
+
+            let mut wait = bucket.next_allowed.saturating_duration_since(now);
+            if limit.requests_per_second > 0.0 {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * limit.requests_per_second).min(1.0);
+                bucket.last_refill = now;
+                bucket.tokens -= 1.0;
+                if bucket.tokens < 0.0 {
+                    wait = wait.max(Duration::from_secs_f64(-bucket.tokens / limit.requests_per_second));
+                }
+            }
+            bucket.next_allowed = now + wait + min_delay;
+            wait
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+fn host_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_else(|| url.to_string())
+}
+
 pub struct WebScraper {
     client: Client,
-    active_jobs: HashMap<String, ScrapingResult>,
+    active_jobs: Arc<std::sync::Mutex<HashMap<String, ScrapingResult>>>,
+    rate_limiter: Arc<DomainRateLimiter>,
+    default_limit: RateLimit,
+    /// Shared by every request the client makes, so logins carry over between jobs
+    cookies: Arc<CookieJar>,
 }
 
 impl WebScraper {
     pub fn new() -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent("Nexus Terminal Web Scraper 1.0")
+        let cookies = match dirs::data_dir() {
+            Some(data_dir) => {
+                let path = data_dir.join("nexus-terminal").join("scraper_cookies.json");
+                CookieJar::load(&path).unwrap_or_else(|e| {
+                    warn!("Failed to load scraper cookies: {}", e);
+                    CookieJar::new()
+                })
+            }
+            None => CookieJar::new(),
+        };
+        Self::with_cookie_jar(Arc::new(cookies))
+    }
+
+    pub fn with_cookie_jar(cookies: Arc<CookieJar>) -> Result<Self> {
+        let client = Self::client_builder(&cookies)
             .build()
             .map_err(|e| anyhow!("Failed to build HTTP client: {}", e))?;
 
         Ok(Self {
             client,
-            active_jobs: HashMap::new(),
+            active_jobs: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            rate_limiter: Arc::new(DomainRateLimiter::new()),
+            default_limit: RateLimit::default(),
+            cookies,
         })
     }
 
+    fn client_builder(cookies: &Arc<CookieJar>) -> reqwest::ClientBuilder {
+        Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(USER_AGENT)
+            .cookie_provider(cookies.clone())
+    }
+
+    /// Clients for the job's proxies, if it has any; fails on an invalid proxy
+    fn proxy_pool(&self, options: &ScrapingOptions) -> Result<Option<Arc<ProxyPool>>> {
+        let proxies = options.proxies();
+        if proxies.is_empty() {
+            return Ok(None);
+        }
+        let pool = ProxyPool::new(
+            &proxies,
+            options.proxy_fallback,
+            || Self::client_builder(&self.cookies),
+            self.client.clone(),
+        )?;
+        Ok(Some(Arc::new(pool)))
+    }
+
+    pub fn cookies(&self) -> &Arc<CookieJar> {
+        &self.cookies
+    }
+
+    /// Import a Netscape-format cookie file into the scraper's jar
+    pub fn import_cookie_file(&self, path: &str) -> Result<usize> {
+        let imported = self.cookies.import_netscape_file(std::path::Path::new(path))?;
+        self.save_cookies();
+        Ok(imported)
+    }
+
+    pub fn clear_cookies(&self) {
+        self.cookies.clear();
+        self.save_cookies();
+    }
+
+    fn save_cookies(&self) {
+        if let Err(e) = self.cookies.save() {
+            warn!("Failed to persist scraper cookies: {}", e);
+        }
+    }
+
+    /// Run `steps` in order, keeping the session cookies they set
+    pub async fn login(&self, steps: &[LoginStep]) -> Result<()> {
+        for step in steps {
+            let method = reqwest::Method::from_bytes(step.method.to_uppercase().as_bytes())
+                .map_err(|_| anyhow!("Invalid login method '{}'", step.method))?;
+            self.rate_limiter.acquire(&step.url, self.default_limit).await;
+
+            let mut request = self.client.request(method, &step.url);
+            for (name, value) in &step.headers {
+                request = request.header(name, value);
+            }
+            request = match &step.json {
+                Some(json) => request.json(json),
+                None if !step.form.is_empty() => request.form(&step.form),
+                None => request,
+            };
+
+            let response = request.send().await.with_context(|| format!("Login request to {} failed", step.url))?;
+            if !response.status().is_success() {
+                return Err(anyhow!("Login step {} returned HTTP {}", step.url, response.status()));
+            }
+        }
+        self.save_cookies();
+        Ok(())
+    }
+
+    /// Log in with `login_steps`, then scrape `url` with the resulting session
+    pub async fn scrape_with_auth(
+        &self,
+        url: &str,
+        login_steps: &[LoginStep],
+        output_path: Option<String>,
+    ) -> Result<DownloadedFile> {
+        self.login(login_steps).await?;
+        self.scrape_single_page(url, output_path, None).await
+    }
+
+    /// Cookies, cookie file and login steps from `options`, applied before the first page
+    async fn prepare_session(&self, options: &ScrapingOptions) -> Result<()> {
+        if let Some(path) = &options.cookie_file {
+            self.cookies.import_netscape_file(std::path::Path::new(path))?;
+        }
+        if !options.initial_cookies.is_empty() {
+            let url = Url::parse(&options.url)?;
+            let host = url.host_str().ok_or_else(|| anyhow!("{} has no host", options.url))?;
+            for (name, value) in &options.initial_cookies {
+                self.cookies.insert(Cookie::new(name, value, host));
+            }
+        }
+        self.login(&options.login_steps).await
+    }
+
     /// Start web scraping job
     pub async fn start_scraping(&mut self, options: ScrapingOptions) -> Result<String> {
         let job_id = Uuid::new_v4().to_string();
         
         // Validate options
         self.validate_options(&options)?;
-        
-        // Check robots.txt if required
-        if options.respect_robots_txt {
-            let robots_info = self.check_robots_txt(&options.url).await?;
-            if !robots_info.allowed_paths.is_empty() {
-                // Check if our URL is allowed
-                // This is a simplified check - proper implementation would be more comprehensive
-            }
-        }
+        let proxies = self.proxy_pool(&options)?;
 
         let result = ScrapingResult {
             id: job_id.clone(),
@@ -174,15 +619,22 @@ impl WebScraper {
             downloaded_files: Vec::new(),
             current_url: Some(options.url.clone()),
             estimated_time_remaining: None,
+            skipped_urls: Vec::new(),
         };
 
-        self.active_jobs.insert(job_id.clone(), result);
+        self.active_jobs
+            .lock()
+            .map_err(|_| anyhow!("Scraping job state poisoned"))?
+            .insert(job_id.clone(), result);
         
-        // Start scraping in background task
-        let scraper = self.clone();
+        // Start scraping in background task; requests that don't rotate use the first proxy
+        let mut scraper = self.clone();
+        if let Some(pool) = &proxies {
+            scraper.client = pool.primary_client().clone();
+        }
         let job_id_clone = job_id.clone();
         tokio::spawn(async move {
-            if let Err(e) = scraper.run_scraping_job(job_id_clone, options).await {
+            if let Err(e) = scraper.run_scraping_job(job_id_clone, options, proxies).await {
                 eprintln!("Scraping job failed: {}", e);
             }
         });
@@ -192,17 +644,27 @@ impl WebScraper {
 
     /// Get scraping job progress
     pub fn get_scraping_progress(&self, job_id: &str) -> Result<ScrapingResult> {
-        self.active_jobs.get(job_id)
+        self.active_jobs
+            .lock()
+            .map_err(|_| anyhow!("Scraping job state poisoned"))?
+            .get(job_id)
             .cloned()
             .ok_or_else(|| anyhow!("Job not found"))
     }
 
-    /// Scrape a single page
-    pub async fn scrape_single_page(&self, url: &str, output_path: Option<String>) -> Result<DownloadedFile> {
+    /// Scrape a single page, rendering it in a headless browser when `render` is given
+    pub async fn scrape_single_page(
+        &self,
+        url: &str,
+        output_path: Option<String>,
+        render: Option<&RenderOptions>,
+    ) -> Result<DownloadedFile> {
         let _parsed_url = Url::parse(url)?;
-        let response = self.client.get(url).send().await?;
-        
-        let content = response.text().await?;
+        self.rate_limiter.acquire(url, self.default_limit).await;
+        let content = match render {
+            Some(render) => headless_browser::render_page(url, render).await?,
+            None => self.client.get(url).send().await?.text().await?,
+        };
         let output_path = output_path.unwrap_or_else(|| {
             let temp_dir = std::env::var("TEMP_DIR")
                 .unwrap_or_else(|_| "./temp".to_string());
@@ -220,14 +682,21 @@ impl WebScraper {
             size: content.len() as u64,
             mime_type: "text/html".to_string(),
             timestamp: Utc::now(),
+            proxy: None,
         })
     }
 
     /// Extract links from a page
     pub async fn extract_links(&self, url: &str) -> Result<Vec<String>> {
+        self.rate_limiter.acquire(url, self.default_limit).await;
         let response = self.client.get(url).send().await?;
         let content = response.text().await?;
-        let document = Html::parse_document(&content);
+        Self::links_in(&content, url)
+    }
+
+    /// Absolute targets of every link in `content`, resolved against `url`
+    fn links_in(content: &str, url: &str) -> Result<Vec<String>> {
+        let document = Html::parse_document(content);
         
         let link_selector = Selector::parse("a[href]").map_err(|e| anyhow!("Failed to parse selector: {}", e))?;
         let base_url = Url::parse(url)?;
@@ -255,11 +724,16 @@ impl WebScraper {
         let base_url = Url::parse(url)?;
         let robots_url = base_url.join("/robots.txt")?;
         
+        self.rate_limiter.acquire(url, self.default_limit).await;
         match self.client.get(robots_url.as_str()).send().await {
             Ok(response) => {
                 if response.status().is_success() {
                     let content = response.text().await?;
-                    Ok(self.parse_robots_txt(&content))
+                    let info = self.parse_robots_txt(&content);
+                    if let Some(delay) = info.crawl_delay {
+                        self.rate_limiter.set_crawl_delay(url, Duration::from_secs(delay as u64));
+                    }
+                    Ok(info)
                 } else {
                     Ok(RobotsTxtInfo {
                         exists: false,
@@ -286,6 +760,7 @@ impl WebScraper {
 
     /// Get website metadata
     pub async fn get_website_metadata(&self, url: &str) -> Result<WebsiteMetadata> {
+        self.rate_limiter.acquire(url, self.default_limit).await;
         let response = self.client.get(url).send().await?;
         let content = response.text().await?;
         let document = Html::parse_document(&content);
@@ -372,25 +847,40 @@ impl WebScraper {
 
     // Private helper methods
 
-    async fn run_scraping_job(&self, job_id: String, options: ScrapingOptions) -> Result<()> {
+    async fn run_scraping_job(&self, job_id: String, options: ScrapingOptions, proxies: Option<Arc<ProxyPool>>) -> Result<()> {
         let mut visited_urls = HashSet::new();
         let mut queue = std::collections::VecDeque::new();
         let mut total_size = 0u64;
         let mut scraped_pages = 0u32;
         let mut errors = Vec::new();
         let mut downloaded_files = Vec::new();
+        let mut skipped_urls = Vec::new();
+        // robots.txt per host, fetched once for the duration of the job
+        let mut robots_cache: HashMap<String, Option<RobotsRules>> = HashMap::new();
         
         // Initialize with starting URL
         queue.push_back((options.url.clone(), 0u32));
         
-        // Create output directory
-        if let Err(e) = fs::create_dir_all(&options.output_directory).await {
+        // Create output directory and set up the session
+        let setup = match fs::create_dir_all(&options.output_directory).await {
+            Ok(()) => self
+                .prepare_session(&options)
+                .await
+                .map_err(|e| format!("Failed to set up session: {}", e)),
+            Err(e) => Err(format!("Failed to create output directory: {}", e)),
+        };
+        if let Err(error) = setup {
             errors.push(ScrapingError {
                 url: options.url.clone(),
-                error: format!("Failed to create output directory: {}", e),
+                error,
                 timestamp: Utc::now(),
                 status_code: None,
             });
+            self.update_job(&job_id, |job| {
+                job.status = "failed".to_string();
+                job.end_time = Some(Utc::now());
+                job.errors = errors;
+            });
             return Ok(());
         }
         
@@ -409,6 +899,14 @@ impl WebScraper {
             }
             
             visited_urls.insert(url.clone());
+
+            if options.respect_robots {
+                if let Some(reason) = self.robots_block_reason(&url, &mut robots_cache).await {
+                    skipped_urls.push(SkippedUrl { url: url.clone(), reason });
+                    self.update_job(&job_id, |job| job.skipped_urls = skipped_urls.clone());
+                    continue;
+                }
+            }
             
             // Apply delay between requests
             if scraped_pages > 0 && options.delay_between_requests > 0 {
@@ -416,7 +914,7 @@ impl WebScraper {
             }
             
             // Download page
-            match self.download_page(&url, &options, depth).await {
+            match self.download_page(&url, &options, depth, proxies.as_deref()).await {
                 Ok((file, links)) => {
                     total_size += file.size;
                     scraped_pages += 1;
@@ -443,32 +941,111 @@ impl WebScraper {
             }
         }
         
-        // Final update (in a real implementation, this would update shared state)
+        self.update_job(&job_id, |job| {
+            job.status = "completed".to_string();
+            job.end_time = Some(Utc::now());
+            job.total_pages = scraped_pages + errors.len() as u32;
+            job.scraped_pages = scraped_pages;
+            job.total_size = total_size;
+            job.errors = errors.clone();
+            job.downloaded_files = downloaded_files.clone();
+            job.current_url = None;
+            job.skipped_urls = skipped_urls.clone();
+        });
+        self.save_cookies();
+
         info!("Scraping job {} completed: {} pages, {} bytes, {} errors", 
               job_id, scraped_pages, total_size, errors.len());
         
         Ok(())
     }
     
+    fn update_job(&self, job_id: &str, update: impl FnOnce(&mut ScrapingResult)) {
+        if let Ok(mut jobs) = self.active_jobs.lock() {
+            if let Some(job) = jobs.get_mut(job_id) {
+                update(job);
+            }
+        }
+    }
+
+    /// Why robots.txt forbids fetching `url`, or `None` if it is allowed
+    async fn robots_block_reason(
+        &self,
+        url: &str,
+        robots_cache: &mut HashMap<String, Option<RobotsRules>>,
+    ) -> Option<String> {
+        let parsed = Url::parse(url).ok()?;
+        let origin = parsed.origin().ascii_serialization();
+
+        if !robots_cache.contains_key(&origin) {
+            let rules = self.fetch_robots_rules(&parsed).await;
+            if let Some(delay) = rules.as_ref().and_then(|r| r.crawl_delay(USER_AGENT)) {
+                self.rate_limiter.set_crawl_delay(url, Duration::from_secs(delay as u64));
+            }
+            robots_cache.insert(origin.clone(), rules);
+        }
+
+        let rules = robots_cache.get(&origin)?.as_ref()?;
+        let path = match parsed.query() {
+            Some(query) => format!("{}?{}", parsed.path(), query),
+            None => parsed.path().to_string(),
+        };
+        rules
+            .disallowed_by(&path, USER_AGENT)
+            .map(|pattern| format!("Disallowed by robots.txt rule 'Disallow: {}'", pattern))
+    }
+
+    async fn fetch_robots_rules(&self, url: &Url) -> Option<RobotsRules> {
+        let robots_url = url.join("/robots.txt").ok()?;
+        self.rate_limiter.acquire(robots_url.as_str(), self.default_limit).await;
+        let response = self.client.get(robots_url.as_str()).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.text().await.ok().map(|content| RobotsRules::parse(&content))
+    }
+
     async fn download_page(
         &self, 
         url: &str, 
         options: &ScrapingOptions, 
-        depth: u32
+        depth: u32,
+        proxies: Option<&ProxyPool>,
     ) -> Result<(DownloadedFile, Vec<String>)> {
-        let response = self.client
-            .get(url)
-            .timeout(Duration::from_secs(options.timeout))
-            .send()
-            .await
-            .context("Failed to fetch page")?;
-        
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("HTTP {} for {}", response.status(), url));
-        }
-        
-        let content = response.text().await
-            .context("Failed to read page content")?;
+        self.rate_limiter.acquire(url, RateLimit::from_options(options)).await;
+        let (content, proxy) = match options.render_options() {
+            Some(render) => {
+                let proxy = render.proxy_server.clone();
+                let content = headless_browser::render_page(url, &render)
+                    .await
+                    .context("Failed to render page")?;
+                (content, proxy)
+            }
+            None => {
+                let build = |client: &Client| {
+                    let mut request = client
+                        .get(url)
+                        .timeout(Duration::from_secs(options.timeout));
+                    for (name, value) in &options.headers {
+                        request = request.header(name, value);
+                    }
+                    request
+                };
+                let (response, proxy) = match proxies {
+                    Some(pool) => pool.send(build).await,
+                    None => build(&self.client).send().await.map(|r| (r, None)).map_err(Into::into),
+                }
+                .context("Failed to fetch page")?;
+
+                if !response.status().is_success() {
+                    return Err(anyhow::anyhow!("HTTP {} for {}", response.status(), url));
+                }
+
+                let content = response.text().await
+                    .context("Failed to read page content")?;
+                (content, proxy)
+            }
+        };
         
         // Generate local file path
         let _parsed_url = Url::parse(url)?;
@@ -490,7 +1067,9 @@ impl WebScraper {
         
         // Extract links for crawling
         let links = if depth < options.depth - 1 {
-            self.extract_links(url).await.unwrap_or_default()
+            // Parse what we already have: rendered links may not exist in the raw HTML, and
+            // refetching would drop the job's custom headers
+            Self::links_in(&content, url).unwrap_or_default()
         } else {
             Vec::new()
         };
@@ -506,6 +1085,7 @@ impl WebScraper {
             size: processed_content.len() as u64,
             mime_type: self.detect_mime_type(url),
             timestamp: Utc::now(),
+            proxy,
         };
         
         Ok((downloaded_file, links))
@@ -615,7 +1195,9 @@ impl WebScraper {
         } // document is dropped here
         
         // Now download all assets without holding any document references
+        let limit = RateLimit::from_options(options);
         for asset_url in all_asset_urls {
+            self.rate_limiter.acquire(&asset_url, limit).await;
             let _ = self.download_asset(&asset_url, output_dir).await;
         }
         
@@ -696,6 +1278,7 @@ impl WebScraper {
         
         visited.insert(url.to_string());
         
+        self.rate_limiter.acquire(url, self.default_limit).await;
         let response = self.client.get(url).send().await?;
         let content = response.text().await?;
         
@@ -777,7 +1360,10 @@ impl Clone for WebScraper {
     fn clone(&self) -> Self {
         Self {
             client: self.client.clone(),
-            active_jobs: HashMap::new(), // Don't clone active jobs
+            active_jobs: Arc::clone(&self.active_jobs),
+            rate_limiter: Arc::clone(&self.rate_limiter),
+            default_limit: self.default_limit,
+            cookies: Arc::clone(&self.cookies),
         }
     }
 }
@@ -791,3 +1377,177 @@ static WEB_SCRAPER: once_cell::sync::Lazy<std::sync::Mutex<WebScraper>> =
 pub fn get_web_scraper() -> &'static std::sync::Mutex<WebScraper> {
     &WEB_SCRAPER
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_to_one_host_respect_rate() {
+        let scraper = WebScraper::new().unwrap();
+        let limit = RateLimit {
+            requests_per_second: 20.0,
+            min_delay: Duration::ZERO,
+        };
+
+        // Clones share the limiter, so concurrent requests throttle together
+        let start = Instant::now();
+        let handles: Vec<_> = (0..10)
+            .map(|i| {
+                let scraper = scraper.clone();
+                tokio::spawn(async move {
+                    let url = format!("https://example.com/page/{}", i);
+                    scraper.rate_limiter.acquire(&url, limit).await;
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // The first request is free, the remaining nine wait 50ms each
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+
+    const SAMPLE_ROBOTS: &str = "\
+User-agent: *
+Disallow: /private/
+Disallow: /*.pdf$
+Disallow: /search*q=
+Allow: /private/public-report
+Crawl-delay: 2
+
+# A stricter group for one crawler
+User-agent: BadBot
+Disallow: /
+";
+
+    #[test]
+    fn test_robots_path_prefix_rules() {
+        let rules = RobotsRules::parse(SAMPLE_ROBOTS);
+
+        assert_eq!(rules.disallowed_by("/private/notes.html", USER_AGENT), Some("/private/"));
+        assert_eq!(rules.disallowed_by("/private/public-report", USER_AGENT), None);
+        assert_eq!(rules.disallowed_by("/privacy", USER_AGENT), None);
+        assert_eq!(rules.disallowed_by("/", USER_AGENT), None);
+        assert_eq!(rules.crawl_delay(USER_AGENT), Some(2));
+
+        // Agent-specific groups replace the wildcard group
+        assert_eq!(rules.disallowed_by("/index.html", "BadBot/2.0"), Some("/"));
+    }
+
+    #[test]
+    fn test_robots_wildcard_rules() {
+        let rules = RobotsRules::parse(SAMPLE_ROBOTS);
+
+        assert_eq!(rules.disallowed_by("/docs/manual.pdf", USER_AGENT), Some("/*.pdf$"));
+        assert_eq!(rules.disallowed_by("/docs/manual.pdf.html", USER_AGENT), None);
+        assert_eq!(rules.disallowed_by("/search?lang=en&q=rust", USER_AGENT), Some("/search*q="));
+        assert_eq!(rules.disallowed_by("/search?lang=en", USER_AGENT), None);
+    }
+
+    /// Login at POST /login (password "hunter2") sets a session cookie that /members requires
+    async fn serve_login_site() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut chunk = [0u8; 1024];
+                // Read the head, then as much body as Content-Length announces
+                let (head, body) = loop {
+                    let n = stream.read(&mut chunk).await.unwrap_or(0);
+                    request.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length = head
+                            .lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap_or(0)))
+                            .unwrap_or(0usize);
+                        if body.len() >= length || n == 0 {
+                            break (head.to_string(), body.to_string());
+                        }
+                    } else if n == 0 {
+                        break (text, String::new());
+                    }
+                };
+
+                let request_line = head.lines().next().unwrap_or("");
+                let has_session = head.lines().any(|l| l.to_lowercase().starts_with("cookie:") && l.contains("session=abc123"));
+                let response = if request_line.starts_with("POST /login ") && body.contains("password=hunter2") {
+                    "HTTP/1.1 303 See Other\r\nSet-Cookie: session=abc123; Path=/; HttpOnly; Max-Age=3600\r\nLocation: /welcome\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else if request_line.starts_with("GET /welcome ") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nwelcome".to_string()
+                } else if request_line.starts_with("GET /members ") && has_session {
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 19\r\n\r\n<p>members only</p>".to_string()
+                } else {
+                    "HTTP/1.1 401 Unauthorized\r\nContent-Length: 6\r\n\r\ndenied".to_string()
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn login_step(base: &str, password: &str) -> LoginStep {
+        LoginStep {
+            url: format!("{}/login", base),
+            method: "POST".to_string(),
+            form: HashMap::from([
+                ("user".to_string(), "ada".to_string()),
+                ("password".to_string(), password.to_string()),
+            ]),
+            json: None,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_session_cookie_reaches_protected_page() {
+        let base = serve_login_site().await;
+        let dir = tempfile::tempdir().unwrap();
+        let output = |name: &str| Some(dir.path().join(name).to_string_lossy().to_string());
+
+        let scraper = WebScraper::with_cookie_jar(Arc::new(CookieJar::new())).unwrap();
+        let denied = scraper.scrape_single_page(&format!("{}/members", base), output("denied.html"), None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&denied.local_path).unwrap(), "denied");
+
+        let wrong = scraper.login(&[login_step(&base, "guess")]).await.unwrap_err();
+        assert!(wrong.to_string().contains("401"), "{}", wrong);
+        assert!(scraper.cookies().is_empty());
+
+        let page = scraper
+            .scrape_with_auth(&format!("{}/members", base), &[login_step(&base, "hunter2")], output("members.html"))
+            .await
+            .unwrap();
+        assert!(std::fs::read_to_string(&page.local_path).unwrap().contains("members only"));
+
+        // The session is scoped to the host that set it
+        let session = scraper.cookies().cookies_for(&Url::parse(&base).unwrap());
+        assert_eq!(session.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["session"]);
+        assert!(session[0].host_only && session[0].http_only && session[0].expires.is_some());
+        assert!(scraper.cookies().cookies_for(&Url::parse("http://localhost/").unwrap()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_crawl_delay_overrides_shorter_configured_delay() {
+        let limiter = DomainRateLimiter::new();
+        limiter.set_crawl_delay("https://example.com/", Duration::from_millis(200));
+        let limit = RateLimit {
+            requests_per_second: 0.0,
+            min_delay: Duration::from_millis(10),
+        };
+
+        let start = Instant::now();
+        limiter.acquire("https://example.com/a", limit).await;
+        limiter.acquire("https://example.com/b", limit).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // Other hosts are unaffected
+        let start = Instant::now();
+        limiter.acquire("https://other.example.org/", limit).await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
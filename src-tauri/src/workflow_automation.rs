@@ -1,9 +1,20 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use chrono::{DateTime, Utc};
 use tokio::process::Command;
 use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Error of a node, and of the execution, stopped by `ExecutionCanceller::cancel`
+pub const CANCELLED_ERROR: &str = "Execution cancelled";
+
+/// Output port of a loop node whose downstream nodes form the loop body
+pub const LOOP_BODY_PORT: &str = "body";
+
+/// Iteration cap for loop nodes that don't set `max_iterations`
+pub const DEFAULT_MAX_LOOP_ITERATIONS: usize = 1000;
 
 // Missing types expected by main.rs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -126,6 +137,49 @@ pub struct WorkflowNode {
     pub execution_time: Option<f64>,
     pub retry_count: u32,
     pub max_retries: u32,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// Shell command that undoes this node's effects when a later node fails
+    #[serde(default)]
+    pub compensation: Option<String>,
+}
+
+/// How a failed node is retried, up to its `max_retries`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub enabled: bool,
+    /// Wait before the first retry; each later retry waits `backoff_multiplier` times longer
+    pub initial_backoff_ms: u64,
+    pub backoff_multiplier: f64,
+    pub max_backoff_ms: u64,
+    /// Failures whose message contains any of these fail the node without retrying
+    pub non_retryable_errors: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_backoff_ms: 1000,
+            backoff_multiplier: 2.0,
+            max_backoff_ms: 60_000,
+            non_retryable_errors: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based)
+    pub fn backoff(&self, retry: u32) -> std::time::Duration {
+        let factor = self.backoff_multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+        let delay_ms = (self.initial_backoff_ms as f64 * factor).min(self.max_backoff_ms as f64);
+        std::time::Duration::from_millis(delay_ms as u64)
+    }
+
+    pub fn should_retry(&self, error: &str) -> bool {
+        self.enabled && !self.non_retryable_errors.iter().any(|pattern| error.contains(pattern.as_str()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,6 +247,7 @@ pub enum NodeStatus {
     Running,
     Completed,
     Failed,
+    Cancelled,
     Skipped,
     Waiting,
 }
@@ -259,10 +314,16 @@ pub enum TriggerType {
     Event,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TriggerConfig {
     pub schedule: Option<String>, // Cron expression
+    /// Globs for file-watch triggers; relative globs are matched against paths under `watch_directory`
     pub file_patterns: Vec<String>,
+    #[serde(default)]
+    pub watch_directory: Option<String>,
+    /// Quiet period after a file changes before the workflow runs, so a burst of writes runs it once
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
     pub git_events: Vec<String>,
     pub webhook_path: Option<String>,
     pub command_pattern: Option<String>,
@@ -274,6 +335,9 @@ pub struct WorkflowSettings {
     pub concurrent_execution: bool,
     pub max_concurrent_runs: u32,
     pub auto_retry: bool,
+    /// Run the compensations of completed nodes, newest first, when the workflow fails
+    #[serde(default)]
+    pub rollback_on_failure: bool,
     pub notification_on_failure: bool,
     pub notification_on_success: bool,
     pub log_level: LogLevel,
@@ -321,6 +385,26 @@ pub struct NodeExecution {
     pub output: Option<serde_json::Value>,
     pub error: Option<String>,
     pub retry_count: u32,
+    /// Every run of the node, including the failed ones that were retried
+    #[serde(default)]
+    pub attempts: Vec<NodeAttempt>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeAttempt {
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub error: Option<String>,
+}
+
+/// Outcome of undoing one completed node during a rollback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompensationResult {
+    pub node_id: String,
+    pub success: bool,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -389,6 +473,55 @@ pub struct RecordedCommand {
     pub duration_ms: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningExecution {
+    pub execution_id: String,
+    pub workflow_id: String,
+}
+
+/// Cancels running executions. Held separately from the engine, which a running
+/// execution keeps borrowed until it finishes.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionCanceller {
+    running: Arc<Mutex<HashMap<String, (String, CancellationToken)>>>,
+}
+
+impl ExecutionCanceller {
+    /// Stop the execution after its current node, killing that node's process.
+    /// Returns false if no such execution is running.
+    pub fn cancel(&self, execution_id: &str) -> bool {
+        match self.running.lock().unwrap().get(execution_id) {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn running(&self) -> Vec<RunningExecution> {
+        self.running
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(execution_id, (workflow_id, _))| RunningExecution {
+                execution_id: execution_id.clone(),
+                workflow_id: workflow_id.clone(),
+            })
+            .collect()
+    }
+
+    fn register(&self, execution_id: &str, workflow_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.running.lock().unwrap().insert(execution_id.to_string(), (workflow_id.to_string(), token.clone()));
+        token
+    }
+
+    fn unregister(&self, execution_id: &str) {
+        self.running.lock().unwrap().remove(execution_id);
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct WorkflowEngine {
@@ -396,6 +529,7 @@ pub struct WorkflowEngine {
     executions: HashMap<String, WorkflowExecution>,
     macros: HashMap<String, Macro>,
     active_recordings: HashMap<String, MacroRecording>,
+    canceller: ExecutionCanceller,
 }
 
 #[allow(dead_code)]
@@ -406,9 +540,46 @@ impl WorkflowEngine {
             executions: HashMap::new(),
             macros: HashMap::new(),
             active_recordings: HashMap::new(),
+            canceller: ExecutionCanceller::default(),
         }
     }
 
+    /// Handle for cancelling this engine's executions while they run
+    pub fn canceller(&self) -> ExecutionCanceller {
+        self.canceller.clone()
+    }
+
+    /// A runner holding a copy of just `workflow_id` and sharing this engine's canceller, so
+    /// a long execution doesn't keep the whole engine locked. Pass it to `absorb` afterwards.
+    pub fn detach(&self, workflow_id: &str) -> Result<WorkflowEngine> {
+        let workflow = self
+            .workflows
+            .get(workflow_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Workflow not found: {}", workflow_id))?;
+        Ok(Self {
+            workflows: HashMap::from([(workflow_id.to_string(), workflow)]),
+            executions: HashMap::new(),
+            macros: HashMap::new(),
+            active_recordings: HashMap::new(),
+            canceller: self.canceller.clone(),
+        })
+    }
+
+    /// Keep the executions a detached runner made and count its completed runs
+    pub fn absorb(&mut self, runner: WorkflowEngine) {
+        for execution in runner.executions.values() {
+            if !matches!(execution.status, ExecutionStatus::Completed) {
+                continue;
+            }
+            if let Some(workflow) = self.workflows.get_mut(&execution.workflow_id) {
+                workflow.execution_count += 1;
+                workflow.last_executed = workflow.last_executed.max(execution.completed_at);
+            }
+        }
+        self.executions.extend(runner.executions);
+    }
+
     pub fn create_workflow(&mut self, name: String, description: String, author: String) -> String {
         let workflow_id = uuid::Uuid::new_v4().to_string();
         
@@ -427,6 +598,7 @@ impl WorkflowEngine {
                 concurrent_execution: false,
                 max_concurrent_runs: 1,
                 auto_retry: false,
+                rollback_on_failure: false,
                 notification_on_failure: true,
                 notification_on_success: false,
                 log_level: LogLevel::Info,
@@ -471,12 +643,23 @@ impl WorkflowEngine {
     }
 
     pub async fn execute_workflow(&mut self, workflow_id: &str) -> Result<String> {
+        self.execute_workflow_with_variables(workflow_id, TriggerType::Manual, HashMap::new()).await
+    }
+
+    /// Run a workflow with initial variables; string variables are also exported to command
+    /// and script nodes as upper-cased environment variables (see `exportable_name`)
+    pub async fn execute_workflow_with_variables(
+        &mut self,
+        workflow_id: &str,
+        triggered_by: TriggerType,
+        variables: HashMap<String, serde_json::Value>,
+    ) -> Result<String> {
         let execution_id = uuid::Uuid::new_v4().to_string();
         
         // Get execution order first before borrowing mutably
         let execution_order = {
             if let Some(workflow) = self.workflows.get(workflow_id) {
-                self.get_execution_order(workflow)?
+                without_loop_bodies(workflow, self.get_execution_order(workflow)?)
             } else {
                 return Err(anyhow!("Workflow not found: {}", workflow_id));
             }
@@ -485,12 +668,12 @@ impl WorkflowEngine {
         let execution = WorkflowExecution {
             id: execution_id.clone(),
             workflow_id: workflow_id.to_string(),
-            triggered_by: TriggerType::Manual,
+            triggered_by,
             status: ExecutionStatus::Running,
             started_at: Utc::now(),
             completed_at: None,
             node_executions: HashMap::new(),
-            variables: HashMap::new(),
+            variables,
             logs: vec![],
             metrics: ExecutionMetrics {
                 total_duration: None,
@@ -503,20 +686,48 @@ impl WorkflowEngine {
         };
 
         self.executions.insert(execution_id.clone(), execution);
-        
+
+        let cancel = self.canceller.register(&execution_id, workflow_id);
+        let result = self.run_execution(workflow_id, &execution_id, execution_order, &cancel).await;
+        self.canceller.unregister(&execution_id);
+        result.map(|()| execution_id)
+    }
+
+    async fn run_execution(
+        &mut self,
+        workflow_id: &str,
+        execution_id: &str,
+        execution_order: Vec<String>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let mut completed = Vec::new();
         for node_id in execution_order {
-            if let Err(e) = self.execute_node(&execution_id, &node_id).await {
-                self.log_execution(&execution_id, LogLevel::Error, Some(&node_id), &format!("Node execution failed: {}", e));
-                if let Some(exec) = self.executions.get_mut(&execution_id) {
+            if cancel.is_cancelled() {
+                self.finish_cancelled(execution_id, &node_id, &format!("Execution cancelled before node {}", node_id));
+                return Err(anyhow!(CANCELLED_ERROR));
+            }
+            if let Err(e) = self.execute_node(execution_id, &node_id, cancel).await {
+                if cancel.is_cancelled() {
+                    let message = format!("Execution cancelled during node {}; its process was killed", node_id);
+                    self.finish_cancelled(execution_id, &node_id, &message);
+                    return Err(anyhow!(CANCELLED_ERROR));
+                }
+                self.log_execution(execution_id, LogLevel::Error, Some(&node_id), &format!("Node execution failed: {}", e));
+                if let Some(workflow) = self.workflows.get(workflow_id).filter(|w| w.settings.rollback_on_failure) {
+                    let results = self.run_compensations(workflow, &completed, execution_id).await;
+                    self.log_compensations(execution_id, results);
+                }
+                if let Some(exec) = self.executions.get_mut(execution_id) {
                     exec.status = ExecutionStatus::Failed;
                     exec.completed_at = Some(Utc::now());
                 }
                 return Err(e);
             }
+            completed.push(node_id);
         }
 
         // Mark execution as completed
-        if let Some(exec) = self.executions.get_mut(&execution_id) {
+        if let Some(exec) = self.executions.get_mut(execution_id) {
             exec.status = ExecutionStatus::Completed;
             exec.completed_at = Some(Utc::now());
             
@@ -531,7 +742,19 @@ impl WorkflowEngine {
             workflow.execution_count += 1;
         }
 
-        Ok(execution_id)
+        Ok(())
+    }
+
+    /// Record where a cancelled execution stopped and mark it, and its interrupted node, cancelled
+    fn finish_cancelled(&mut self, execution_id: &str, node_id: &str, message: &str) {
+        self.log_execution(execution_id, LogLevel::Warning, Some(node_id), message);
+        if let Some(exec) = self.executions.get_mut(execution_id) {
+            if let Some(node_exec) = exec.node_executions.get_mut(node_id) {
+                node_exec.status = NodeStatus::Cancelled;
+            }
+            exec.status = ExecutionStatus::Cancelled;
+            exec.completed_at = Some(Utc::now());
+        }
     }
 
     fn get_execution_order(&self, workflow: &Workflow) -> Result<Vec<String>> {
@@ -546,7 +769,7 @@ impl WorkflowEngine {
             }
         }
 
-        order.reverse();
+        // Dependencies are pushed before their dependents, so the order is already topological
         Ok(order)
     }
 
@@ -582,71 +805,262 @@ impl WorkflowEngine {
         Ok(())
     }
 
-    async fn execute_node(&mut self, execution_id: &str, node_id: &str) -> Result<()> {
-        if let Some(execution) = self.executions.get(execution_id) {
-            if let Some(workflow) = self.workflows.get(&execution.workflow_id) {
-                if let Some(node) = workflow.nodes.iter().find(|n| n.id == node_id) {
-                    let start_time = Utc::now();
-                    
-                    // Create node execution record
-                    let node_exec = NodeExecution {
-                        node_id: node_id.to_string(),
-                        status: NodeStatus::Running,
-                        started_at: Some(start_time),
-                        completed_at: None,
-                        output: None,
-                        error: None,
-                        retry_count: 0,
-                    };
-
-                    if let Some(exec) = self.executions.get_mut(execution_id) {
-                        exec.node_executions.insert(node_id.to_string(), node_exec);
+    async fn execute_node(&mut self, execution_id: &str, node_id: &str, cancel: &CancellationToken) -> Result<()> {
+        let execution = self.executions.get(execution_id).ok_or_else(|| anyhow!("Execution not found"))?;
+        let workflow = self.workflows.get(&execution.workflow_id).ok_or_else(|| anyhow!("Workflow not found"))?;
+        let mut node = workflow
+            .nodes
+            .iter()
+            .find(|n| n.id == node_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Node not found: {}", node_id))?;
+        export_variables(&mut node, &execution.variables);
+
+        let start_time = Utc::now();
+        let node_exec = NodeExecution {
+            node_id: node_id.to_string(),
+            status: NodeStatus::Running,
+            started_at: Some(start_time),
+            completed_at: None,
+            output: None,
+            error: None,
+            retry_count: 0,
+            attempts: Vec::new(),
+        };
+        if let Some(exec) = self.executions.get_mut(execution_id) {
+            exec.node_executions.insert(node_id.to_string(), node_exec);
+        }
+
+        let (result, attempts) = if matches!(node.node_type, NodeType::Loop) {
+            // Body nodes retry on their own, so the loop itself runs once
+            let started_at = Utc::now();
+            let result = self.run_loop_node(&node, execution_id, cancel).await;
+            let error = result.as_ref().err().map(|e| e.to_string());
+            (result, vec![NodeAttempt { attempt: 1, started_at, completed_at: Utc::now(), error }])
+        } else {
+            self.run_node_with_retries(&node, execution_id, cancel).await
+        };
+        // Every attempt but the last failed and was retried
+        for retried in &attempts[..attempts.len() - 1] {
+            self.log_execution(
+                execution_id,
+                LogLevel::Warning,
+                Some(node_id),
+                &format!("Attempt {} failed and was retried: {}", retried.attempt, retried.error.as_deref().unwrap_or_default()),
+            );
+        }
+
+        let end_time = Utc::now();
+        let duration = end_time.signed_duration_since(start_time).num_milliseconds() as f64 / 1000.0;
+
+        // Update node execution record
+        if let Some(exec) = self.executions.get_mut(execution_id) {
+            if let Some(node_exec) = exec.node_executions.get_mut(node_id) {
+                node_exec.completed_at = Some(end_time);
+                node_exec.retry_count = attempts.len().saturating_sub(1) as u32;
+                node_exec.attempts = attempts;
+                match &result {
+                    Ok(output) => {
+                        node_exec.status = NodeStatus::Completed;
+                        node_exec.output = Some(output.clone());
                     }
+                    Err(error) => {
+                        node_exec.status = NodeStatus::Failed;
+                        node_exec.error = Some(error.to_string());
+                    }
+                }
+            }
+            exec.metrics.node_durations.insert(node_id.to_string(), duration);
+        }
 
-                    // Execute based on node type
-                    let result = match node.node_type {
-                        NodeType::Command => self.execute_command_node(node).await,
-                        NodeType::Script => self.execute_script_node(node).await,
-                        NodeType::Condition => self.execute_condition_node(node, execution_id).await,
-                        NodeType::FileOperation => self.execute_file_operation_node(node).await,
-                        NodeType::Delay => self.execute_delay_node(node).await,
-                        _ => Ok(serde_json::Value::Null),
-                    };
-
-                    let end_time = Utc::now();
-                    let duration = end_time.signed_duration_since(start_time).num_milliseconds() as f64 / 1000.0;
-
-                    // Update node execution record
-                    if let Some(exec) = self.executions.get_mut(execution_id) {
-                        if let Some(node_exec) = exec.node_executions.get_mut(node_id) {
-                            node_exec.completed_at = Some(end_time);
-                            match &result {
-                                Ok(output) => {
-                                    node_exec.status = NodeStatus::Completed;
-                                    node_exec.output = Some(output.clone());
-                                }
-                                Err(error) => {
-                                    node_exec.status = NodeStatus::Failed;
-                                    node_exec.error = Some(error.to_string());
-                                }
-                            }
-                        }
-                        exec.metrics.node_durations.insert(node_id.to_string(), duration);
+        result.map(|_| ())
+    }
+
+    /// Run a node, retrying failures with backoff per its retry policy, up to `max_retries` times.
+    /// Returns the final result together with every attempt made.
+    async fn run_node_with_retries(
+        &self,
+        node: &WorkflowNode,
+        execution_id: &str,
+        cancel: &CancellationToken,
+    ) -> (Result<serde_json::Value>, Vec<NodeAttempt>) {
+        let mut attempts = Vec::new();
+        loop {
+            let attempt = attempts.len() as u32 + 1;
+            let started_at = Utc::now();
+            let result = self.run_node(node, execution_id, cancel).await;
+            let error = result.as_ref().err().map(|e| e.to_string());
+            attempts.push(NodeAttempt { attempt, started_at, completed_at: Utc::now(), error: error.clone() });
+
+            match error {
+                Some(error) if attempt <= node.max_retries && node.retry_policy.should_retry(&error) && !cancel.is_cancelled() => {
+                    tokio::select! {
+                        _ = tokio::time::sleep(node.retry_policy.backoff(attempt)) => {}
+                        _ = cancel.cancelled() => return (Err(anyhow!(CANCELLED_ERROR)), attempts),
                     }
+                }
+                _ => return (result, attempts),
+            }
+        }
+    }
+
+    /// Run a loop node of a recorded execution, resolving its items against the execution's
+    /// variables and the outputs of nodes that have finished
+    async fn run_loop_node(&self, node: &WorkflowNode, execution_id: &str, cancel: &CancellationToken) -> Result<serde_json::Value> {
+        let execution = self.executions.get(execution_id).ok_or_else(|| anyhow!("Execution not found"))?;
+        let workflow = self.workflows.get(&execution.workflow_id).ok_or_else(|| anyhow!("Workflow not found"))?;
+        let outputs = execution
+            .node_executions
+            .iter()
+            .filter_map(|(id, node_exec)| Some((id.clone(), node_exec.output.clone()?)))
+            .collect();
+        self.run_loop(workflow, node, &execution.variables, &outputs, execution_id, cancel).await
+    }
+
+    /// Run the loop's body subgraph once per item, binding the item and its index to the loop's
+    /// variables. Without `items` the body repeats until `break_condition` holds. Either way the
+    /// loop fails rather than exceed `max_iterations`. The output collects each pass's body outputs.
+    async fn run_loop(
+        &self,
+        workflow: &Workflow,
+        node: &WorkflowNode,
+        variables: &HashMap<String, serde_json::Value>,
+        outputs: &serde_json::Map<String, serde_json::Value>,
+        execution_id: &str,
+        cancel: &CancellationToken,
+    ) -> Result<serde_json::Value> {
+        let params = &node.config.parameters;
+        let item_variable = params.get("item_variable").and_then(|v| v.as_str()).unwrap_or("item");
+        let index_variable = params.get("index_variable").and_then(|v| v.as_str()).unwrap_or("index");
+        let max_iterations = params
+            .get("max_iterations")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_MAX_LOOP_ITERATIONS, |max| max as usize);
+        let break_condition = params.get("break_condition").and_then(|v| v.as_str());
+        let items = match params.get("items") {
+            Some(serde_json::Value::Array(items)) => Some(items.clone()),
+            Some(serde_json::Value::String(reference)) => Some(resolve_items(reference, variables, outputs)?),
+            Some(other) => return Err(anyhow!("Loop {} items must be an array or a reference, not {}", node.id, other)),
+            None if break_condition.is_some() => None,
+            None => return Err(anyhow!("Loop {} needs items or a break_condition", node.id)),
+        };
+        if let Some(items) = items.as_ref().filter(|items| items.len() > max_iterations) {
+            return Err(anyhow!("Loop {} has {} items, more than max_iterations ({})", node.id, items.len(), max_iterations));
+        }
 
-                    result.map(|_| ())
+        let body = loop_body(workflow, &node.id);
+        let body_order: Vec<String> = self.get_execution_order(workflow)?.into_iter().filter(|id| body.contains(id)).collect();
+        let body_order = without_loop_bodies(workflow, body_order);
+
+        let mut passes = Vec::new();
+        let mut broke = false;
+        for index in 0.. {
+            let item = match &items {
+                Some(items) => match items.get(index) {
+                    Some(item) => item.clone(),
+                    None => break,
+                },
+                None if index == max_iterations => {
+                    return Err(anyhow!("Loop {} reached max_iterations ({}) before its break condition held", node.id, max_iterations));
+                }
+                None => serde_json::Value::Null,
+            };
+            if cancel.is_cancelled() {
+                return Err(anyhow!(CANCELLED_ERROR));
+            }
+
+            let mut pass_variables = variables.clone();
+            pass_variables.insert(item_variable.to_string(), item);
+            pass_variables.insert(index_variable.to_string(), serde_json::json!(index));
+            let mut pass_outputs = outputs.clone();
+            let mut pass = serde_json::Map::new();
+            for body_id in &body_order {
+                let Some(body_node) = workflow.nodes.iter().find(|n| &n.id == body_id) else {
+                    continue;
+                };
+                let result = if matches!(body_node.node_type, NodeType::Loop) {
+                    Box::pin(self.run_loop(workflow, body_node, &pass_variables, &pass_outputs, execution_id, cancel)).await
                 } else {
-                    Err(anyhow!("Node not found: {}", node_id))
+                    let mut body_node = body_node.clone();
+                    export_variables(&mut body_node, &pass_variables);
+                    self.run_node_with_retries(&body_node, execution_id, cancel).await.0
+                };
+                let output = result.map_err(|e| anyhow!("Loop {} failed at {} on pass {}: {}", node.id, body_id, index, e))?;
+                pass_outputs.insert(body_id.clone(), output.clone());
+                pass.insert(body_id.clone(), output);
+            }
+            passes.push(serde_json::Value::Object(pass));
+
+            if let Some(condition) = break_condition {
+                let condition = interpolate(condition, &pass_variables, &pass_outputs);
+                if self.evaluate_condition(&condition, execution_id)? {
+                    broke = true;
+                    break;
                 }
-            } else {
-                Err(anyhow!("Workflow not found"))
             }
-        } else {
-            Err(anyhow!("Execution not found"))
+        }
+
+        Ok(serde_json::json!({ "iterations": passes.len(), "outputs": passes, "broke": broke }))
+    }
+
+    /// Undo `completed` nodes in reverse order. A failing compensation does not stop the rollback.
+    async fn run_compensations(&self, workflow: &Workflow, completed: &[String], execution_id: &str) -> Vec<CompensationResult> {
+        let mut results = Vec::new();
+        for node_id in completed.iter().rev() {
+            let Some(node) = workflow.nodes.iter().find(|n| &n.id == node_id) else {
+                continue;
+            };
+            let Some(compensation) = &node.compensation else {
+                continue;
+            };
+
+            // Run the compensation like a command node, in the node's own environment
+            let mut undo = node.clone();
+            undo.node_type = NodeType::Command;
+            undo.config.command = Some(compensation.clone());
+            // Compensations always run to completion, so they get a token that is never cancelled
+            results.push(match self.run_node(&undo, execution_id, &CancellationToken::new()).await {
+                Ok(output) => CompensationResult { node_id: node_id.clone(), success: true, output: Some(output), error: None },
+                Err(e) => CompensationResult { node_id: node_id.clone(), success: false, output: None, error: Some(e.to_string()) },
+            });
+        }
+        results
+    }
+
+    fn log_compensations(&mut self, execution_id: &str, results: Vec<CompensationResult>) {
+        let Some(execution) = self.executions.get_mut(execution_id) else {
+            return;
+        };
+        for result in results {
+            let (level, message) = match &result.error {
+                None => (LogLevel::Info, "Compensation succeeded".to_string()),
+                Some(error) => (LogLevel::Error, format!("Compensation failed, continuing rollback: {}", error)),
+            };
+            let mut context = HashMap::new();
+            context.insert("phase".to_string(), "rollback".to_string());
+            context.insert("success".to_string(), result.success.to_string());
+            execution.logs.push(ExecutionLog {
+                timestamp: Utc::now(),
+                level,
+                node_id: Some(result.node_id),
+                message,
+                context,
+            });
         }
     }
 
-    async fn execute_command_node(&self, node: &WorkflowNode) -> Result<serde_json::Value> {
+    async fn run_node(&self, node: &WorkflowNode, execution_id: &str, cancel: &CancellationToken) -> Result<serde_json::Value> {
+        match node.node_type {
+            NodeType::Command => self.execute_command_node(node, cancel).await,
+            NodeType::Script => self.execute_script_node(node, cancel).await,
+            NodeType::Condition => self.execute_condition_node(node, execution_id).await,
+            NodeType::FileOperation => self.execute_file_operation_node(node).await,
+            NodeType::Delay => self.execute_delay_node(node, cancel).await,
+            _ => Ok(serde_json::Value::Null),
+        }
+    }
+
+    async fn execute_command_node(&self, node: &WorkflowNode, cancel: &CancellationToken) -> Result<serde_json::Value> {
         if let Some(command) = &node.config.command {
             let mut cmd = if cfg!(target_os = "windows") {
                 let mut c = Command::new("cmd");
@@ -668,10 +1082,15 @@ impl WorkflowEngine {
                 cmd.env(key, value);
             }
 
-            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+            let output = output_or_cancel(cmd, cancel).await?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "Command exited with status {}: {}",
+                    output.status.code().unwrap_or(-1),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
 
-            let output = cmd.output().await?;
-            
             Ok(serde_json::json!({
                 "stdout": String::from_utf8_lossy(&output.stdout),
                 "stderr": String::from_utf8_lossy(&output.stderr),
@@ -682,7 +1101,7 @@ impl WorkflowEngine {
         }
     }
 
-    async fn execute_script_node(&self, node: &WorkflowNode) -> Result<serde_json::Value> {
+    async fn execute_script_node(&self, node: &WorkflowNode, cancel: &CancellationToken) -> Result<serde_json::Value> {
         if let Some(script) = &node.config.script {
             // For simplicity, treat script as a shell command
             // In a real implementation, this could support multiple script languages
@@ -697,7 +1116,7 @@ impl WorkflowEngine {
                 cmd.env(key, value);
             }
 
-            let output = cmd.output().await?;
+            let output = output_or_cancel(cmd, cancel).await?;
             
             Ok(serde_json::json!({
                 "output": String::from_utf8_lossy(&output.stdout),
@@ -743,9 +1162,12 @@ impl WorkflowEngine {
         }
     }
 
-    async fn execute_delay_node(&self, node: &WorkflowNode) -> Result<serde_json::Value> {
+    async fn execute_delay_node(&self, node: &WorkflowNode, cancel: &CancellationToken) -> Result<serde_json::Value> {
         if let Some(delay) = node.config.parameters.get("delay_ms").and_then(|v| v.as_u64()) {
-            tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_millis(delay)) => {}
+                _ = cancel.cancelled() => return Err(anyhow!(CANCELLED_ERROR)),
+            }
             Ok(serde_json::json!({ "delayed_ms": delay }))
         } else {
             Err(anyhow!("No delay specified for delay node"))
@@ -906,6 +1328,35 @@ impl WorkflowEngine {
         self.executions.get(execution_id)
     }
 
+    /// Executions of one workflow, oldest first
+    pub fn list_executions(&self, workflow_id: &str) -> Vec<&WorkflowExecution> {
+        let mut executions: Vec<&WorkflowExecution> =
+            self.executions.values().filter(|execution| execution.workflow_id == workflow_id).collect();
+        executions.sort_by_key(|execution| execution.started_at);
+        executions
+    }
+
+    /// Add a trigger to a workflow, replacing any trigger with the same id
+    pub fn add_trigger(&mut self, workflow_id: &str, trigger: WorkflowTrigger) -> Result<()> {
+        let workflow = self.workflows.get_mut(workflow_id).ok_or_else(|| anyhow!("Workflow not found: {}", workflow_id))?;
+        workflow.triggers.retain(|existing| existing.id != trigger.id);
+        workflow.triggers.push(trigger);
+        workflow.updated_at = Utc::now();
+        Ok(())
+    }
+
+    pub fn set_trigger_enabled(&mut self, workflow_id: &str, trigger_id: &str, enabled: bool) -> Result<()> {
+        let workflow = self.workflows.get_mut(workflow_id).ok_or_else(|| anyhow!("Workflow not found: {}", workflow_id))?;
+        let trigger = workflow
+            .triggers
+            .iter_mut()
+            .find(|trigger| trigger.id == trigger_id)
+            .ok_or_else(|| anyhow!("Trigger not found: {}", trigger_id))?;
+        trigger.enabled = enabled;
+        workflow.updated_at = Utc::now();
+        Ok(())
+    }
+
     pub fn list_workflows(&self) -> Vec<&Workflow> {
         self.workflows.values().collect()
     }
@@ -985,6 +1436,8 @@ impl WorkflowEngine {
                 execution_time: None,
                 retry_count: 0,
                 max_retries: step.retry_count,
+                retry_policy: RetryPolicy::default(),
+                compensation: None,
             };
             nodes.push(node);
         }
@@ -1004,6 +1457,7 @@ impl WorkflowEngine {
                 concurrent_execution: false,
                 max_concurrent_runs: 1,
                 auto_retry: false,
+                rollback_on_failure: false,
                 notification_on_failure: true,
                 notification_on_success: false,
                 log_level: LogLevel::Info,
@@ -1023,6 +1477,7 @@ impl WorkflowEngine {
         if let Some(workflow) = self.workflows.get(workflow_id) {
             let execution_id = uuid::Uuid::new_v4().to_string();
             let start_time = Utc::now();
+            let cancel = self.canceller.register(&execution_id, workflow_id);
             
             // Simple execution for demo purposes
             let mut steps_completed = 0;
@@ -1030,29 +1485,65 @@ impl WorkflowEngine {
             let mut output = serde_json::json!({});
             let mut error = None;
             let mut success = true;
+            let mut cancelled = false;
 
             // Execute each node
-            for node in &workflow.nodes {
-                match self.execute_command_node(node).await {
+            let mut completed = Vec::new();
+            let execution_order = self.get_execution_order(workflow).inspect_err(|_| self.canceller.unregister(&execution_id))?;
+            let execution_order = without_loop_bodies(workflow, execution_order);
+            for node_id in execution_order {
+                let Some(node) = workflow.nodes.iter().find(|n| n.id == node_id) else {
+                    continue;
+                };
+                if cancel.is_cancelled() {
+                    cancelled = true;
+                    break;
+                }
+                let result = if matches!(node.node_type, NodeType::Loop) {
+                    let outputs = output.as_object().cloned().unwrap_or_default();
+                    self.run_loop(workflow, node, &HashMap::new(), &outputs, &execution_id, &cancel).await
+                } else {
+                    self.run_node_with_retries(node, &execution_id, &cancel).await.0
+                };
+                match result {
                     Ok(node_output) => {
                         output[&node.id] = node_output;
                         steps_completed += 1;
+                        completed.push(node_id);
+                    }
+                    Err(_) if cancel.is_cancelled() => {
+                        cancelled = true;
+                        break;
                     }
                     Err(e) => {
                         error = Some(e.to_string());
                         success = false;
+                        if workflow.settings.rollback_on_failure {
+                            let rollback = self.run_compensations(workflow, &completed, &execution_id).await;
+                            output["rollback"] = serde_json::to_value(rollback)?;
+                        }
                         break;
                     }
                 }
             }
 
+            self.canceller.unregister(&execution_id);
+            let status = if cancelled {
+                success = false;
+                error = Some(CANCELLED_ERROR.to_string());
+                ExecutionStatus::Cancelled
+            } else if success {
+                ExecutionStatus::Completed
+            } else {
+                ExecutionStatus::Failed
+            };
             let end_time = Utc::now();
             let duration = end_time.signed_duration_since(start_time).num_milliseconds() as f64 / 1000.0;
             
             Ok(ExecutionResult {
                 execution_id,
                 workflow_id: workflow_id.to_string(),
-                status: if success { ExecutionStatus::Completed } else { ExecutionStatus::Failed },
+                status,
                 started_at: start_time,
                 completed_at: Some(end_time),
                 duration_seconds: Some(duration),
@@ -1143,6 +1634,8 @@ impl WorkflowEngine {
                     execution_time: Some(cmd.duration_ms as f64 / 1000.0),
                     retry_count: 0,
                     max_retries: 3,
+                    retry_policy: RetryPolicy::default(),
+                    compensation: None,
                 };
                 nodes.push(node);
             }
@@ -1162,6 +1655,7 @@ impl WorkflowEngine {
                     concurrent_execution: false,
                     max_concurrent_runs: 1,
                     auto_retry: false,
+                    rollback_on_failure: false,
                     notification_on_failure: true,
                     notification_on_success: false,
                     log_level: LogLevel::Info,
@@ -1218,6 +1712,155 @@ impl WorkflowEngine {
     }
 }
 
+/// Nodes downstream of `loop_id`'s body port; they run once per iteration instead of in the main order
+fn loop_body(workflow: &Workflow, loop_id: &str) -> HashSet<String> {
+    let mut body = HashSet::new();
+    let mut pending: Vec<&str> = workflow
+        .connections
+        .iter()
+        .filter(|c| c.from_node == loop_id && c.from_port == LOOP_BODY_PORT)
+        .map(|c| c.to_node.as_str())
+        .collect();
+    while let Some(node_id) = pending.pop() {
+        if node_id == loop_id || !body.insert(node_id.to_string()) {
+            continue;
+        }
+        pending.extend(workflow.connections.iter().filter(|c| c.from_node == node_id).map(|c| c.to_node.as_str()));
+    }
+    body
+}
+
+/// `order` minus the bodies of the loop nodes in it, which those loops run themselves
+fn without_loop_bodies(workflow: &Workflow, order: Vec<String>) -> Vec<String> {
+    let nested: HashSet<String> = workflow
+        .nodes
+        .iter()
+        .filter(|node| matches!(node.node_type, NodeType::Loop) && order.contains(&node.id))
+        .flat_map(|node| loop_body(workflow, &node.id))
+        .collect();
+    order.into_iter().filter(|id| !nested.contains(id)).collect()
+}
+
+/// Environment variables that change how the shell, dynamic loader or an interpreter starts,
+/// which workflow variables (e.g. a webhook body) must not be able to set
+const PROTECTED_ENVIRONMENT: &[&str] = &[
+    "PATH", "IFS", "ENV", "BASH_ENV", "SHELL", "SHELLOPTS", "BASHOPTS", "CDPATH", "GLOBIGNORE", "HOME",
+    "PS4", "PROMPT_COMMAND", "PYTHONPATH", "PYTHONSTARTUP", "PYTHONHOME", "PERL5LIB", "PERL5OPT",
+    "RUBYLIB", "RUBYOPT", "NODE_OPTIONS", "NODE_PATH",
+];
+
+/// Whether an upper-cased variable name is safe to export: `[A-Z_][A-Z0-9_]*` and not one
+/// of the loader (`LD_*`, `DYLD_*`), exported-function or `PROTECTED_ENVIRONMENT` names
+fn exportable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let well_formed = chars.next().is_some_and(|c| c.is_ascii_uppercase() || c == '_')
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    well_formed
+        && !name.starts_with("LD_")
+        && !name.starts_with("DYLD_")
+        && !name.starts_with("BASH_FUNC_")
+        && !PROTECTED_ENVIRONMENT.contains(&name)
+}
+
+/// Export scalar variables to a command or script node as upper-cased environment variables,
+/// without overriding the node's own environment. Names that aren't valid identifiers or
+/// that would change how the node's process starts are skipped.
+fn export_variables(node: &mut WorkflowNode, variables: &HashMap<String, serde_json::Value>) {
+    for (name, value) in variables {
+        let name = name.to_uppercase();
+        if !exportable_name(&name) {
+            continue;
+        }
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+            _ => continue,
+        };
+        node.config.environment.entry(name).or_insert(value);
+    }
+}
+
+/// Look up `name.field.0` style references in the variables, then in node outputs
+fn lookup<'a>(
+    reference: &str,
+    variables: &'a HashMap<String, serde_json::Value>,
+    outputs: &'a serde_json::Map<String, serde_json::Value>,
+) -> Option<&'a serde_json::Value> {
+    let mut path = reference.trim().split('.');
+    let root = path.next()?;
+    let mut value = variables.get(root).or_else(|| outputs.get(root))?;
+    for key in path {
+        value = match value {
+            serde_json::Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => value.get(key)?,
+        };
+    }
+    Some(value)
+}
+
+/// Items for a loop from a reference: an array as is, or a string split into its non-empty lines
+fn resolve_items(
+    reference: &str,
+    variables: &HashMap<String, serde_json::Value>,
+    outputs: &serde_json::Map<String, serde_json::Value>,
+) -> Result<Vec<serde_json::Value>> {
+    match lookup(reference, variables, outputs) {
+        Some(serde_json::Value::Array(items)) => Ok(items.clone()),
+        Some(serde_json::Value::String(text)) => {
+            Ok(text.lines().filter(|line| !line.trim().is_empty()).map(|line| serde_json::json!(line)).collect())
+        }
+        Some(other) => Err(anyhow!("Loop items '{}' is not an array or text: {}", reference, other)),
+        None => Err(anyhow!("Loop items '{}' not found", reference)),
+    }
+}
+
+/// Replace `{{reference}}` placeholders; unknown references become empty
+fn interpolate(
+    template: &str,
+    variables: &HashMap<String, serde_json::Value>,
+    outputs: &serde_json::Map<String, serde_json::Value>,
+) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        match lookup(&rest[start + 2..start + len], variables, outputs) {
+            Some(serde_json::Value::String(text)) => result.push_str(text),
+            Some(value) => result.push_str(&value.to_string()),
+            None => {}
+        }
+        rest = &rest[start + len + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Run `cmd` to completion, or kill it and every process it started once `cancel` fires
+async fn output_or_cancel(mut cmd: Command, cancel: &CancellationToken) -> Result<std::process::Output> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+    // Own process group, so a cancel also reaches commands the shell has spawned
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let child = cmd.spawn()?;
+    let pid = child.id();
+    tokio::select! {
+        output = child.wait_with_output() => Ok(output?),
+        _ = cancel.cancelled() => {
+            #[cfg(unix)]
+            if let Some(pid) = pid {
+                unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+            }
+            #[cfg(not(unix))]
+            let _ = pid;
+            Err(anyhow!(CANCELLED_ERROR))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1297,6 +1940,8 @@ mod tests {
             execution_time: None,
             retry_count: 0,
             max_retries: 3,
+            retry_policy: RetryPolicy::default(),
+            compensation: None,
         };
 
         let node2 = WorkflowNode {
@@ -1320,6 +1965,8 @@ mod tests {
             execution_time: None,
             retry_count: 0,
             max_retries: 3,
+            retry_policy: RetryPolicy::default(),
+            compensation: None,
         };
 
         engine.add_node(&workflow_id, node1).unwrap();
@@ -1343,4 +1990,326 @@ mod tests {
         let order = engine.get_execution_order(workflow).unwrap();
         assert_eq!(order, vec!["node1", "node2"]);
     }
+
+    fn command_node(id: &str, command: &str, max_retries: u32) -> WorkflowNode {
+        WorkflowNode {
+            id: id.to_string(),
+            node_type: NodeType::Command,
+            name: id.to_string(),
+            description: String::new(),
+            position: NodePosition { x: 0.0, y: 0.0 },
+            config: NodeConfig {
+                command: Some(command.to_string()),
+                script: None,
+                condition: None,
+                parameters: HashMap::new(),
+                environment: HashMap::new(),
+                working_directory: None,
+                timeout_seconds: None,
+            },
+            input_ports: vec![],
+            output_ports: vec![],
+            status: NodeStatus::Pending,
+            execution_time: None,
+            retry_count: 0,
+            max_retries,
+            retry_policy: RetryPolicy { initial_backoff_ms: 5, ..RetryPolicy::default() },
+            compensation: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_flaky_node_succeeds_after_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let counter = dir.path().join("attempts");
+        // Fails on the first two runs, succeeds on the third
+        let flaky = format!(
+            "n=$(cat '{0}' 2>/dev/null || echo 0); n=$((n+1)); echo $n > '{0}'; [ $n -ge 3 ] || {{ echo \"attempt $n\" >&2; exit 1; }}",
+            counter.display()
+        );
+
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Flaky".to_string(), String::new(), "Test".to_string());
+        engine.add_node(&workflow_id, command_node("flaky", &flaky, 3)).unwrap();
+
+        let execution_id = engine.execute_workflow(&workflow_id).await.unwrap();
+        let execution = engine.get_execution(&execution_id).unwrap();
+        assert!(matches!(execution.status, ExecutionStatus::Completed));
+        let node = &execution.node_executions["flaky"];
+        assert!(matches!(node.status, NodeStatus::Completed));
+        assert_eq!(node.retry_count, 2);
+        let errors: Vec<Option<&str>> = node.attempts.iter().map(|a| a.error.as_deref()).collect();
+        assert_eq!(errors, vec![
+            Some("Command exited with status 1: attempt 1"),
+            Some("Command exited with status 1: attempt 2"),
+            None,
+        ]);
+        assert_eq!(execution.logs.iter().filter(|log| matches!(log.level, LogLevel::Warning)).count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_always_failing_node_fails_workflow_after_max_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Broken".to_string(), String::new(), "Test".to_string());
+        engine.add_node(&workflow_id, command_node("broken", "exit 7", 2)).unwrap();
+        engine.add_node(&workflow_id, command_node("after", &format!("touch '{}'", marker.display()), 0)).unwrap();
+        engine.add_connection(&workflow_id, WorkflowConnection {
+            id: "conn".to_string(),
+            from_node: "broken".to_string(),
+            from_port: "output".to_string(),
+            to_node: "after".to_string(),
+            to_port: "input".to_string(),
+            condition: None,
+            transform: None,
+        }).unwrap();
+
+        let error = engine.execute_workflow(&workflow_id).await.unwrap_err();
+        assert!(error.to_string().contains("status 7"));
+        let execution = engine.executions.values().next().unwrap();
+        assert!(matches!(execution.status, ExecutionStatus::Failed));
+        let node = &execution.node_executions["broken"];
+        assert!(matches!(node.status, NodeStatus::Failed));
+        assert_eq!((node.attempts.len(), node.retry_count), (3, 2));
+        assert!(!execution.node_executions.contains_key("after"));
+        assert!(!marker.exists());
+
+        // Non-retryable failures stop at the first attempt
+        let mut node = command_node("fatal", "echo 'permission denied' >&2; exit 1", 5);
+        node.retry_policy.non_retryable_errors = vec!["permission denied".to_string()];
+        let (result, attempts) = engine.run_node_with_retries(&node, "none", &CancellationToken::new()).await;
+        assert!(result.is_err());
+        assert_eq!(attempts.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_workflow_rolls_back_completed_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let resource = dir.path().join("resource");
+
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Provision".to_string(), String::new(), "Test".to_string());
+        engine.workflows.get_mut(&workflow_id).unwrap().settings.rollback_on_failure = true;
+
+        let mut create = command_node("create", &format!("touch '{}'", resource.display()), 0);
+        create.compensation = Some(format!("rm '{}'", resource.display()));
+        let mut register = command_node("register", "true", 0);
+        // This compensation fails; the rollback must still reach `create`
+        register.compensation = Some("echo 'registry unreachable' >&2; exit 4".to_string());
+        let deploy = command_node("deploy", "exit 1", 0);
+        for node in [create, register, deploy] {
+            engine.add_node(&workflow_id, node).unwrap();
+        }
+        for (from, to) in [("create", "register"), ("register", "deploy")] {
+            engine.add_connection(&workflow_id, WorkflowConnection {
+                id: format!("{}-{}", from, to),
+                from_node: from.to_string(),
+                from_port: "output".to_string(),
+                to_node: to.to_string(),
+                to_port: "input".to_string(),
+                condition: None,
+                transform: None,
+            }).unwrap();
+        }
+
+        assert!(engine.execute_workflow(&workflow_id).await.is_err());
+        assert!(!resource.exists(), "create's compensation should have removed the resource");
+
+        let execution = engine.executions.values().next().unwrap();
+        let rollback: Vec<(&str, &str)> = execution
+            .logs
+            .iter()
+            .filter(|log| log.context.get("phase").map(String::as_str) == Some("rollback"))
+            .map(|log| (log.node_id.as_deref().unwrap(), log.context["success"].as_str()))
+            .collect();
+        assert_eq!(rollback, vec![("register", "false"), ("create", "true")]);
+        assert!(execution.logs.iter().any(|log| log.message.contains("registry unreachable")));
+
+        // Without opting in, completed steps are left alone
+        engine.workflows.get_mut(&workflow_id).unwrap().settings.rollback_on_failure = false;
+        assert!(engine.execute_workflow(&workflow_id).await.is_err());
+        assert!(resource.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_stops_execution_and_kills_running_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("finished");
+        let after = dir.path().join("after");
+
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Slow".to_string(), String::new(), "Test".to_string());
+        // The sleep runs in a child of the shell, so only a process-group kill stops it touching the marker
+        engine.add_node(&workflow_id, command_node("slow", &format!("sleep 1 && touch '{}'", marker.display()), 0)).unwrap();
+        engine.add_node(&workflow_id, command_node("after", &format!("touch '{}'", after.display()), 0)).unwrap();
+        engine
+            .add_connection(&workflow_id, WorkflowConnection {
+                id: "slow-after".to_string(),
+                from_node: "slow".to_string(),
+                from_port: "output".to_string(),
+                to_node: "after".to_string(),
+                to_port: "input".to_string(),
+                condition: None,
+                transform: None,
+            })
+            .unwrap();
+
+        let canceller = engine.canceller();
+        let engine = Arc::new(tokio::sync::RwLock::new(engine));
+        let run = tokio::spawn({
+            let engine = engine.clone();
+            let workflow_id = workflow_id.clone();
+            async move { engine.write().await.execute_workflow(&workflow_id).await }
+        });
+
+        let running = loop {
+            if let Some(running) = canceller.running().pop() {
+                break running;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        };
+        assert_eq!(running.workflow_id, workflow_id);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(canceller.cancel(&running.execution_id));
+
+        let error = run.await.unwrap().unwrap_err();
+        assert_eq!(error.to_string(), CANCELLED_ERROR);
+        assert!(canceller.running().is_empty());
+        assert!(!canceller.cancel(&running.execution_id));
+
+        let engine = engine.read().await;
+        let execution = engine.get_execution(&running.execution_id).unwrap();
+        assert!(matches!(execution.status, ExecutionStatus::Cancelled));
+        assert!(matches!(execution.node_executions["slow"].status, NodeStatus::Cancelled));
+        assert!(!execution.node_executions.contains_key("after"));
+        let last = execution.logs.last().unwrap();
+        assert_eq!(last.node_id.as_deref(), Some("slow"));
+        assert!(last.message.contains("cancelled during node slow"));
+
+        // Past the point where the sleep would have finished, the killed command must not have carried on
+        tokio::time::sleep(std::time::Duration::from_millis(1200)).await;
+        assert!(!marker.exists());
+        assert!(!after.exists());
+    }
+
+    fn loop_node(id: &str, parameters: serde_json::Value) -> WorkflowNode {
+        let mut node = command_node(id, "", 0);
+        node.node_type = NodeType::Loop;
+        node.config.command = None;
+        node.config.parameters = serde_json::from_value(parameters).unwrap();
+        node
+    }
+
+    fn connect(engine: &mut WorkflowEngine, workflow_id: &str, from: &str, port: &str, to: &str) {
+        let connection = WorkflowConnection {
+            id: format!("{}-{}", from, to),
+            from_node: from.to_string(),
+            from_port: port.to_string(),
+            to_node: to.to_string(),
+            to_port: "input".to_string(),
+            condition: None,
+            transform: None,
+        };
+        engine.add_connection(workflow_id, connection).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_loop_runs_body_per_item_and_aggregates_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let seen = dir.path().join("seen");
+        let after = dir.path().join("after");
+
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Each".to_string(), String::new(), "Test".to_string());
+        engine.add_node(&workflow_id, loop_node("each", serde_json::json!({ "items": ["a", "b", "c"] }))).unwrap();
+        let body = format!("echo \"$ITEM $INDEX\" >> '{}'; printf 'got %s' \"$ITEM\"", seen.display());
+        engine.add_node(&workflow_id, command_node("greet", &body, 0)).unwrap();
+        engine.add_node(&workflow_id, command_node("done", &format!("touch '{}'", after.display()), 0)).unwrap();
+        connect(&mut engine, &workflow_id, "each", LOOP_BODY_PORT, "greet");
+        connect(&mut engine, &workflow_id, "each", "output", "done");
+
+        let execution_id = engine.execute_workflow(&workflow_id).await.unwrap();
+
+        // The body ran once per item and not again in the main order; the downstream node ran once
+        assert_eq!(std::fs::read_to_string(&seen).unwrap(), "a 0\nb 1\nc 2\n");
+        assert!(after.exists());
+
+        let execution = engine.get_execution(&execution_id).unwrap();
+        let output = execution.node_executions["each"].output.as_ref().unwrap();
+        assert_eq!(output["iterations"], 3);
+        assert_eq!(output["broke"], false);
+        let stdout: Vec<&serde_json::Value> = output["outputs"].as_array().unwrap().iter().map(|pass| &pass["greet"]["stdout"]).collect();
+        assert_eq!(stdout, ["got a", "got b", "got c"]);
+        assert!(!execution.node_executions.contains_key("greet"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_loop_break_condition_and_iteration_cap() {
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Until".to_string(), String::new(), "Test".to_string());
+        engine.add_node(&workflow_id, command_node("list", "printf '1\\n2\\n3\\n4\\n'", 0)).unwrap();
+        let each = loop_node("each", serde_json::json!({
+            "items": "list.stdout",
+            "item_variable": "n",
+            "break_condition": "{{n}} == 2",
+        }));
+        engine.add_node(&workflow_id, each).unwrap();
+        engine.add_node(&workflow_id, command_node("show", "printf '%s' \"$N\"", 0)).unwrap();
+        connect(&mut engine, &workflow_id, "list", "output", "each");
+        connect(&mut engine, &workflow_id, "each", LOOP_BODY_PORT, "show");
+
+        let execution_id = engine.execute_workflow(&workflow_id).await.unwrap();
+        let output = engine.get_execution(&execution_id).unwrap().node_executions["each"].output.clone().unwrap();
+        assert_eq!(output["iterations"], 2);
+        assert_eq!(output["broke"], true);
+        assert_eq!(output["outputs"][1]["show"]["stdout"], "2");
+
+        // A loop without items stops at max_iterations when its break condition never holds
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Forever".to_string(), String::new(), "Test".to_string());
+        let forever = loop_node("forever", serde_json::json!({ "break_condition": "{{index}} == never", "max_iterations": 5 }));
+        engine.add_node(&workflow_id, forever).unwrap();
+        engine.add_node(&workflow_id, command_node("tick", "true", 0)).unwrap();
+        connect(&mut engine, &workflow_id, "forever", LOOP_BODY_PORT, "tick");
+
+        let error = engine.execute_workflow(&workflow_id).await.unwrap_err();
+        assert!(error.to_string().contains("max_iterations (5)"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_variables_cannot_override_process_environment() {
+        let mut engine = WorkflowEngine::new();
+        let workflow_id = engine.create_workflow("Env".to_string(), String::new(), "Test".to_string());
+        engine.add_node(&workflow_id, command_node("show", "printf '%s:%s' \"$BRANCH\" \"$PATH\"", 0)).unwrap();
+        let variables = HashMap::from([
+            ("branch".to_string(), serde_json::json!("main")),
+            ("path".to_string(), serde_json::json!("/tmp/evil")),
+            ("ld_preload".to_string(), serde_json::json!("/tmp/evil.so")),
+            ("bad-name".to_string(), serde_json::json!("x")),
+        ]);
+
+        let mut runner = engine.detach(&workflow_id).unwrap();
+        let execution_id = runner.execute_workflow_with_variables(&workflow_id, TriggerType::Manual, variables).await.unwrap();
+        let stdout = runner.get_execution(&execution_id).unwrap().node_executions["show"].output.clone().unwrap()["stdout"].clone();
+        assert!(stdout.as_str().unwrap().starts_with("main:"));
+        assert_ne!(stdout, "main:/tmp/evil");
+
+        engine.absorb(runner);
+        assert!(engine.get_execution(&execution_id).is_some());
+        assert_eq!(engine.get_workflow(&workflow_id).unwrap().execution_count, 1);
+
+        assert!(exportable_name("BRANCH_2"));
+        for name in ["PATH", "LD_PRELOAD", "DYLD_INSERT_LIBRARIES", "BASH_ENV", "IFS", "BASH_FUNC_LS%%", "BAD-NAME", "2X", ""] {
+            assert!(!exportable_name(name), "{}", name);
+        }
+    }
 }
@@ -0,0 +1,318 @@
+use anyhow::{anyhow, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::utils::glob_match;
+use crate::workflow_automation::{TriggerConfig, TriggerType, WorkflowEngine};
+
+/// Workflow variable holding the file whose change fired a file-watch trigger
+pub const TRIGGER_PATH_VARIABLE: &str = "trigger_path";
+
+/// Quiet period used when a trigger does not set `debounce_ms`
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A running watcher for one enabled file-watch trigger
+struct ActiveTrigger {
+    config: TriggerConfig,
+    _watcher: RecommendedWatcher,
+    runner: JoinHandle<()>,
+}
+
+impl Drop for ActiveTrigger {
+    fn drop(&mut self) {
+        self.runner.abort();
+    }
+}
+
+/// Keeps one file watcher per enabled file-watch trigger and runs the workflow when a matching file changes
+pub struct FileTriggerManager {
+    engine: Arc<RwLock<WorkflowEngine>>,
+    /// (workflow id, trigger id) -> watcher
+    active: HashMap<(String, String), ActiveTrigger>,
+}
+
+impl std::fmt::Debug for FileTriggerManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileTriggerManager")
+            .field("active", &self.active.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FileTriggerManager {
+    pub fn new(engine: Arc<RwLock<WorkflowEngine>>) -> Self {
+        Self { engine, active: HashMap::new() }
+    }
+
+    /// Start watchers for newly enabled or changed file-watch triggers and drop those of
+    /// disabled triggers and deleted workflows. Call after any change to workflows or triggers.
+    pub fn sync(&mut self, engine: &WorkflowEngine) -> Result<()> {
+        let mut wanted: HashMap<(String, String), TriggerConfig> = HashMap::new();
+        for workflow in engine.list_workflows() {
+            for trigger in &workflow.triggers {
+                if trigger.enabled && matches!(trigger.trigger_type, TriggerType::FileWatch) {
+                    wanted.insert((workflow.id.clone(), trigger.id.clone()), trigger.config.clone());
+                }
+            }
+        }
+
+        self.active.retain(|key, active| {
+            let keep = wanted.get(key) == Some(&active.config);
+            if !keep {
+                info!("Stopped file trigger {} of workflow {}", key.1, key.0);
+            }
+            keep
+        });
+
+        let mut errors = Vec::new();
+        for (key, config) in wanted {
+            if self.active.contains_key(&key) {
+                continue;
+            }
+            match self.start(&key.0, config) {
+                Ok(active) => {
+                    info!("Watching files for trigger {} of workflow {}", key.1, key.0);
+                    self.active.insert(key, active);
+                }
+                Err(e) => errors.push(format!("trigger {} of workflow {}: {}", key.1, key.0, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Could not start file triggers: {}", errors.join("; ")))
+        }
+    }
+
+    /// Number of file-watch triggers currently being watched
+    pub fn active_count(&self) -> usize {
+        self.active.len()
+    }
+
+    fn start(&self, workflow_id: &str, config: TriggerConfig) -> Result<ActiveTrigger> {
+        if config.file_patterns.is_empty() {
+            return Err(anyhow!("no file patterns configured"));
+        }
+        let base = config
+            .watch_directory
+            .as_deref()
+            .map(|dir| Path::new(dir).canonicalize().map_err(|e| anyhow!("cannot watch {}: {}", dir, e)))
+            .transpose()?;
+        let roots = watch_roots(&config.file_patterns, base.as_deref())?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let patterns = config.file_patterns.clone();
+        let matcher_base = base.clone();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("File trigger watch failed: {}", e);
+                    return;
+                }
+            };
+            if !is_change(&event.kind) {
+                return;
+            }
+            for path in event.paths {
+                if !path.is_dir() && path_matches(&patterns, matcher_base.as_deref(), &path) {
+                    let _ = tx.send(path);
+                }
+            }
+        })?;
+        for root in &roots {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+        }
+
+        let debounce = config.debounce_ms.map(Duration::from_millis).unwrap_or(DEFAULT_DEBOUNCE);
+        let runner = tokio::spawn(run_on_change(Arc::clone(&self.engine), workflow_id.to_string(), debounce, rx));
+        Ok(ActiveTrigger { config, _watcher: watcher, runner })
+    }
+}
+
+/// Run the workflow once per changed path, after that path has been quiet for `debounce`
+async fn run_on_change(
+    engine: Arc<RwLock<WorkflowEngine>>,
+    workflow_id: String,
+    debounce: Duration,
+    mut changes: mpsc::UnboundedReceiver<PathBuf>,
+) {
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    loop {
+        let deadline = pending.values().min().map(|seen| *seen + debounce);
+        tokio::select! {
+            change = changes.recv() => match change {
+                Some(path) => {
+                    pending.insert(path, Instant::now());
+                }
+                None => return,
+            },
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                let now = Instant::now();
+                let settled: Vec<PathBuf> = pending
+                    .iter()
+                    .filter(|(_, seen)| now.duration_since(**seen) >= debounce)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+                for path in settled {
+                    pending.remove(&path);
+                    let mut variables = HashMap::new();
+                    variables.insert(TRIGGER_PATH_VARIABLE.to_string(), serde_json::json!(path.to_string_lossy()));
+                    let result = engine
+                        .write()
+                        .await
+                        .execute_workflow_with_variables(&workflow_id, TriggerType::FileWatch, variables)
+                        .await;
+                    if let Err(e) = result {
+                        warn!("Workflow {} triggered by {} failed: {}", workflow_id, path.display(), e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_change(kind: &EventKind) -> bool {
+    use notify::event::ModifyKind;
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+    )
+}
+
+/// Directories to watch: `base` if given, otherwise the literal directory prefix of each absolute pattern
+fn watch_roots(patterns: &[String], base: Option<&Path>) -> Result<Vec<PathBuf>> {
+    if let Some(base) = base {
+        return Ok(vec![base.to_path_buf()]);
+    }
+
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for pattern in patterns {
+        if !Path::new(pattern).is_absolute() {
+            return Err(anyhow!("relative pattern '{}' needs a watch directory", pattern));
+        }
+        let literal = pattern.split('*').next().unwrap_or_default();
+        let dir = match literal.rfind('/') {
+            Some(0) | None => PathBuf::from("/"),
+            Some(end) => PathBuf::from(&literal[..end]),
+        };
+        let dir = dir.canonicalize().map_err(|e| anyhow!("cannot watch {}: {}", dir.display(), e))?;
+        // Nested roots are already covered by the recursive watch on their ancestor
+        if !roots.iter().any(|root| dir.starts_with(root)) {
+            roots.retain(|root| !root.starts_with(&dir));
+            roots.push(dir);
+        }
+    }
+    Ok(roots)
+}
+
+/// Whether `path` matches any pattern, either as an absolute path or relative to `base`
+fn path_matches(patterns: &[String], base: Option<&Path>, path: &Path) -> bool {
+    let absolute = path.to_string_lossy();
+    let relative = base.and_then(|base| path.strip_prefix(base).ok()).map(|relative| relative.to_string_lossy());
+    patterns.iter().any(|pattern| {
+        glob_match(pattern, &absolute) || relative.as_deref().is_some_and(|relative| glob_match(pattern, relative))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow_automation::WorkflowTrigger;
+
+    async fn wait_for_runs(engine: &RwLock<WorkflowEngine>, workflow_id: &str, runs: usize) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while engine.read().await.list_executions(workflow_id).len() < runs && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_matching_file_changes_run_the_workflow() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+
+        let engine = Arc::new(RwLock::new(WorkflowEngine::new()));
+        let workflow_id = {
+            let mut engine = engine.write().await;
+            let workflow_id = engine.create_workflow("On save".to_string(), String::new(), "Test".to_string());
+            let trigger = WorkflowTrigger {
+                id: "on-save".to_string(),
+                trigger_type: TriggerType::FileWatch,
+                config: TriggerConfig {
+                    file_patterns: vec!["src/*.rs".to_string()],
+                    watch_directory: Some(root.to_string_lossy().to_string()),
+                    debounce_ms: Some(100),
+                    ..TriggerConfig::default()
+                },
+                enabled: true,
+            };
+            engine.add_trigger(&workflow_id, trigger).unwrap();
+            workflow_id
+        };
+
+        let mut manager = FileTriggerManager::new(Arc::clone(&engine));
+        manager.sync(&*engine.read().await).unwrap();
+        assert_eq!(manager.active_count(), 1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        std::fs::write(root.join("notes.txt"), "not a match").unwrap();
+        std::fs::write(root.join("src").join("notes.md"), "not a match either").unwrap();
+        // An editor saving in several writes should still run the workflow once
+        for contents in ["fn", "fn main", "fn main() {}"] {
+            std::fs::write(root.join("src").join("main.rs"), contents).unwrap();
+        }
+
+        wait_for_runs(&engine, &workflow_id, 1).await;
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        {
+            let engine = engine.read().await;
+            let runs = engine.list_executions(&workflow_id);
+            assert_eq!(runs.len(), 1);
+            assert!(matches!(runs[0].triggered_by, TriggerType::FileWatch));
+            assert_eq!(runs[0].variables[TRIGGER_PATH_VARIABLE], serde_json::json!(root.join("src").join("main.rs").to_string_lossy()));
+        }
+
+        // Disabling the trigger removes its watcher
+        engine.write().await.set_trigger_enabled(&workflow_id, "on-save", false).unwrap();
+        manager.sync(&*engine.read().await).unwrap();
+        assert_eq!(manager.active_count(), 0);
+        std::fs::write(root.join("src").join("lib.rs"), "pub fn lib() {}").unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(engine.read().await.list_executions(&workflow_id).len(), 1);
+
+        // So does deleting the workflow
+        engine.write().await.set_trigger_enabled(&workflow_id, "on-save", true).unwrap();
+        manager.sync(&*engine.read().await).unwrap();
+        assert_eq!(manager.active_count(), 1);
+        engine.write().await.delete_workflow(&workflow_id).unwrap();
+        manager.sync(&*engine.read().await).unwrap();
+        assert_eq!(manager.active_count(), 0);
+    }
+
+    #[test]
+    fn test_watch_roots_and_matching() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("a").join("b")).unwrap();
+        let patterns = vec![
+            format!("{}/a/b/*.rs", root.display()),
+            format!("{}/a/*.toml", root.display()),
+        ];
+
+        assert_eq!(watch_roots(&patterns, None).unwrap(), vec![root.join("a")]);
+        assert!(watch_roots(&["*.rs".to_string()], None).is_err());
+        assert!(path_matches(&patterns, None, &root.join("a").join("b").join("x.rs")));
+        assert!(!path_matches(&patterns, None, &root.join("a").join("x.rs")));
+        assert!(path_matches(&["*.rs".to_string()], Some(&root), &root.join("a").join("x.rs")));
+    }
+}
@@ -0,0 +1,387 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::workflow_automation::{TriggerType, WorkflowEngine};
+
+/// Address the webhook server binds to when none is given; loopback only
+pub const DEFAULT_WEBHOOK_ADDR: &str = "127.0.0.1:8787";
+
+/// Header that must carry the server's shared secret
+pub const WEBHOOK_SECRET_HEADER: &str = "x-nexus-webhook-secret";
+
+/// Variable holding the request body when it isn't a JSON object
+pub const WEBHOOK_BODY_VARIABLE: &str = "body";
+
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a webhook trigger can be reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub workflow_id: String,
+    pub trigger_id: String,
+    pub url: String,
+}
+
+/// What a client needs to call the webhook server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookServerInfo {
+    pub url: String,
+    /// Must be sent in the `X-Nexus-Webhook-Secret` header
+    pub secret: String,
+    pub endpoints: Vec<WebhookEndpoint>,
+}
+
+/// HTTP server that runs a workflow when its webhook trigger's path receives a POST
+/// carrying the shared secret. A JSON object body becomes the execution's variables.
+#[derive(Debug)]
+pub struct WebhookServer {
+    local_addr: SocketAddr,
+    secret: String,
+    shutdown: CancellationToken,
+}
+
+impl WebhookServer {
+    /// Bind to `bind_addr` and start serving. Without a `secret` a random one is generated.
+    pub async fn start(engine: Arc<RwLock<WorkflowEngine>>, bind_addr: &str, secret: Option<String>) -> Result<Self> {
+        let secret = secret.unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+        if secret.is_empty() {
+            return Err(anyhow!("Webhook secret must not be empty"));
+        }
+        let listener = TcpListener::bind(bind_addr)
+            .await
+            .with_context(|| format!("Failed to bind webhook server to {}", bind_addr))?;
+        let local_addr = listener.local_addr()?;
+        // The server is plain HTTP and runs commands, so it must not be reachable off this machine
+        if !local_addr.ip().is_loopback() {
+            return Err(anyhow!("Webhook server must bind to a loopback address, not {}", local_addr));
+        }
+        let shutdown = CancellationToken::new();
+
+        let accept_shutdown = shutdown.clone();
+        let accept_secret = secret.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = accept_shutdown.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            let engine = engine.clone();
+                            let secret = accept_secret.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = serve_connection(stream, engine, &secret).await {
+                                    debug!("Webhook connection from {} ended: {}", peer, e);
+                                }
+                            });
+                        }
+                        Err(e) => warn!("Failed to accept webhook connection: {}", e),
+                    },
+                }
+            }
+        });
+
+        info!("Workflow webhook server listening on {}", local_addr);
+        Ok(Self { local_addr, secret, shutdown })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn url_for(&self, webhook_path: &str) -> String {
+        format!("http://{}/{}", self.local_addr, webhook_path.trim_start_matches('/'))
+    }
+
+    /// URLs of every enabled webhook trigger
+    pub fn endpoints(&self, engine: &WorkflowEngine) -> Vec<WebhookEndpoint> {
+        let mut endpoints: Vec<WebhookEndpoint> = engine
+            .list_workflows()
+            .into_iter()
+            .flat_map(|workflow| {
+                workflow.triggers.iter().filter_map(move |trigger| {
+                    let path = webhook_path(trigger)?;
+                    Some(WebhookEndpoint {
+                        workflow_id: workflow.id.clone(),
+                        trigger_id: trigger.id.clone(),
+                        url: self.url_for(path),
+                    })
+                })
+            })
+            .collect();
+        endpoints.sort_by(|a, b| a.url.cmp(&b.url));
+        endpoints
+    }
+
+    pub fn info(&self, engine: &WorkflowEngine) -> WebhookServerInfo {
+        WebhookServerInfo { url: self.url_for(""), secret: self.secret.clone(), endpoints: self.endpoints(engine) }
+    }
+
+    /// Stop accepting requests
+    pub fn stop(&self) {
+        self.shutdown.cancel();
+    }
+}
+
+impl Drop for WebhookServer {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+fn webhook_path(trigger: &crate::workflow_automation::WorkflowTrigger) -> Option<&str> {
+    if !trigger.enabled || !matches!(trigger.trigger_type, TriggerType::WebHook) {
+        return None;
+    }
+    trigger.config.webhook_path.as_deref().filter(|path| !path.trim_start_matches('/').is_empty())
+}
+
+/// Workflow whose enabled webhook trigger is registered on `path`
+fn find_workflow(engine: &WorkflowEngine, path: &str) -> Option<String> {
+    let path = path.trim_start_matches('/');
+    engine
+        .list_workflows()
+        .into_iter()
+        .find(|workflow| {
+            workflow
+                .triggers
+                .iter()
+                .any(|trigger| webhook_path(trigger).is_some_and(|registered| registered.trim_start_matches('/') == path))
+        })
+        .map(|workflow| workflow.id.clone())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+async fn serve_connection(mut stream: TcpStream, engine: Arc<RwLock<WorkflowEngine>>, secret: &str) -> Result<()> {
+    let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => return respond(&mut stream, 400, &serde_json::json!({ "error": e.to_string() })).await,
+        Err(_) => return respond(&mut stream, 408, &serde_json::json!({ "error": "Request timed out" })).await,
+    };
+    let (status, body) = handle_request(request, &engine, secret).await;
+    respond(&mut stream, status, &body).await
+}
+
+async fn handle_request(request: Request, engine: &RwLock<WorkflowEngine>, secret: &str) -> (u16, serde_json::Value) {
+    // Check the secret before anything else so unauthenticated callers can't probe for paths
+    let provided = request.headers.get(WEBHOOK_SECRET_HEADER).map(String::as_str).unwrap_or_default();
+    if !secrets_match(secret, provided) {
+        return (401, serde_json::json!({ "error": "Missing or invalid webhook secret" }));
+    }
+    if request.method != "POST" {
+        return (405, serde_json::json!({ "error": "Webhooks only accept POST" }));
+    }
+    let path = request.path.split('?').next().unwrap_or_default();
+    let Some(workflow_id) = find_workflow(&*engine.read().await, path) else {
+        return (404, serde_json::json!({ "error": format!("No webhook registered on {}", path) }));
+    };
+    let variables = match body_variables(&request.body) {
+        Ok(variables) => variables,
+        Err(e) => return (400, serde_json::json!({ "error": e.to_string() })),
+    };
+
+    // Run on a detached copy so the engine isn't locked for the whole execution
+    let mut runner = match engine.read().await.detach(&workflow_id) {
+        Ok(runner) => runner,
+        Err(e) => return (404, serde_json::json!({ "error": e.to_string() })),
+    };
+    let result = runner.execute_workflow_with_variables(&workflow_id, TriggerType::WebHook, variables).await;
+    engine.write().await.absorb(runner);
+    match result {
+        Ok(execution_id) => (200, serde_json::json!({ "workflow_id": workflow_id, "execution_id": execution_id })),
+        Err(e) => {
+            warn!("Workflow {} triggered by webhook {} failed: {}", workflow_id, path, e);
+            (500, serde_json::json!({ "workflow_id": workflow_id, "error": e.to_string() }))
+        }
+    }
+}
+
+/// Compare every byte so the time taken doesn't reveal how much of the secret matched
+fn secrets_match(expected: &str, provided: &str) -> bool {
+    let expected = expected.as_bytes();
+    expected.len() == provided.len()
+        && expected.iter().zip(provided.as_bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// A JSON object's fields become variables; any other JSON value is stored under `body`
+fn body_variables(body: &[u8]) -> Result<HashMap<String, serde_json::Value>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(HashMap::new());
+    }
+    let value: serde_json::Value = serde_json::from_slice(body).context("Request body is not valid JSON")?;
+    Ok(match value {
+        serde_json::Value::Object(fields) => fields.into_iter().collect(),
+        other => HashMap::from([(WEBHOOK_BODY_VARIABLE.to_string(), other)]),
+    })
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err(anyhow!("Request headers too large"));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(anyhow!("Connection closed before the request was complete"));
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = std::str::from_utf8(&buffer[..header_end]).context("Request headers are not UTF-8")?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        return Err(anyhow!("Malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length = match headers.get("content-length") {
+        Some(length) => length.parse::<usize>().context("Invalid Content-Length")?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_BYTES {
+        return Err(anyhow!("Request body larger than {} bytes", MAX_BODY_BYTES));
+    }
+    let mut body = buffer.split_off(header_end + 4);
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(anyhow!("Connection closed before the body was complete"));
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Ok(Request { method, path, headers, body })
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: &serde_json::Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow_automation::{TriggerConfig, WorkflowTrigger};
+
+    async fn engine_with_webhook(path: &str) -> (Arc<RwLock<WorkflowEngine>>, String) {
+        let engine = Arc::new(RwLock::new(WorkflowEngine::new()));
+        let workflow_id = {
+            let mut engine = engine.write().await;
+            let workflow_id = engine.create_workflow("Deploy".to_string(), String::new(), "Test".to_string());
+            let trigger = WorkflowTrigger {
+                id: "deploy-hook".to_string(),
+                trigger_type: TriggerType::WebHook,
+                config: TriggerConfig { webhook_path: Some(path.to_string()), ..TriggerConfig::default() },
+                enabled: true,
+            };
+            engine.add_trigger(&workflow_id, trigger).unwrap();
+            workflow_id
+        };
+        (engine, workflow_id)
+    }
+
+    #[tokio::test]
+    async fn test_post_runs_workflow_with_body_as_variables() {
+        let (engine, workflow_id) = engine_with_webhook("/hooks/deploy").await;
+        let server = WebhookServer::start(engine.clone(), "127.0.0.1:0", Some("s3cret".to_string())).await.unwrap();
+
+        let endpoints = server.endpoints(&*engine.read().await);
+        assert_eq!(endpoints.len(), 1);
+        assert_eq!(endpoints[0].url, format!("http://{}/hooks/deploy", server.local_addr()));
+
+        let response = reqwest::Client::new()
+            .post(&endpoints[0].url)
+            .header(WEBHOOK_SECRET_HEADER, "s3cret")
+            .json(&serde_json::json!({ "branch": "main", "commit": "abc123" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let reply: serde_json::Value = response.json().await.unwrap();
+
+        let engine = engine.read().await;
+        let runs = engine.list_executions(&workflow_id);
+        assert_eq!(runs.len(), 1);
+        assert_eq!(reply["execution_id"], serde_json::json!(runs[0].id));
+        assert!(matches!(runs[0].triggered_by, TriggerType::WebHook));
+        assert_eq!(runs[0].variables["branch"], serde_json::json!("main"));
+        assert_eq!(runs[0].variables["commit"], serde_json::json!("abc123"));
+    }
+
+    #[tokio::test]
+    async fn test_request_without_secret_is_rejected() {
+        let (engine, workflow_id) = engine_with_webhook("hooks/deploy").await;
+        let server = WebhookServer::start(engine.clone(), "127.0.0.1:0", None).await.unwrap();
+        let client = reqwest::Client::new();
+        let url = server.url_for("hooks/deploy");
+
+        let missing = client.post(&url).body("{}").send().await.unwrap();
+        assert_eq!(missing.status(), 401);
+        let wrong = client.post(&url).header(WEBHOOK_SECRET_HEADER, "guess").body("{}").send().await.unwrap();
+        assert_eq!(wrong.status(), 401);
+        let unknown = client
+            .post(server.url_for("hooks/other"))
+            .header(WEBHOOK_SECRET_HEADER, server.secret())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), 404);
+
+        assert!(engine.read().await.list_executions(&workflow_id).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_non_loopback_bind_is_refused() {
+        let (engine, _) = engine_with_webhook("hooks/deploy").await;
+        let error = WebhookServer::start(engine, "0.0.0.0:0", None).await.unwrap_err();
+        assert!(error.to_string().contains("loopback"), "{}", error);
+    }
+}